@@ -0,0 +1,110 @@
+use crate::Settings;
+use digilogic_routing::RoutingConfig;
+use serde::{Deserialize, Serialize};
+
+const ROUTING_CONFIG_KEY: &str = "routing";
+
+#[derive(Serialize)]
+struct SettingsFileRef<'a> {
+    settings: &'a Settings,
+    routing: &'a RoutingConfig,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct SettingsFile {
+    settings: Settings,
+    routing: RoutingConfig,
+}
+
+/// The platform specific directory digilogic stores its configuration in.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn config_dir() -> Option<std::path::PathBuf> {
+    use std::env::var_os;
+    use std::path::PathBuf;
+
+    let base = if cfg!(target_os = "windows") {
+        var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .filter(|path| path.is_absolute())
+            .or_else(|| var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    };
+
+    base.map(|base| base.join("digilogic"))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn settings_path() -> Option<std::path::PathBuf> {
+    config_dir().map(|dir| dir.join("settings.ron"))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn load_settings_file() -> Option<SettingsFile> {
+    let path = settings_path()?;
+    let ron = std::fs::read_to_string(&path).ok()?;
+    match ron::from_str(&ron) {
+        Ok(file) => Some(file),
+        Err(err) => {
+            bevy_log::warn!("ignoring invalid settings file {}: {err}", path.display());
+            None
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn load_settings_file() -> Option<SettingsFile> {
+    None
+}
+
+/// Loads the settings from the config directory.
+/// Falls back to the eframe storage, which is the only option on the web.
+pub fn load(storage: Option<&dyn eframe::Storage>) -> (Settings, RoutingConfig) {
+    if let Some(file) = load_settings_file() {
+        return (file.settings, file.routing);
+    }
+
+    let settings = storage
+        .and_then(|storage| eframe::get_value(storage, eframe::APP_KEY))
+        .unwrap_or_default();
+    let routing = storage
+        .and_then(|storage| eframe::get_value(storage, ROUTING_CONFIG_KEY))
+        .unwrap_or_default();
+    (settings, routing)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn save_settings_file(settings: &Settings, routing: &RoutingConfig) -> std::io::Result<()> {
+    let Some(path) = settings_path() else {
+        return Err(std::io::ErrorKind::NotFound.into());
+    };
+
+    let file = SettingsFileRef { settings, routing };
+    let ron = ron::ser::to_string_pretty(&file, ron::ser::PrettyConfig::default())
+        .map_err(std::io::Error::other)?;
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, ron)
+}
+
+/// Saves the settings to the config directory, or the eframe storage on the web.
+pub fn save(storage: &mut dyn eframe::Storage, settings: &Settings, routing: &RoutingConfig) {
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Err(err) = save_settings_file(settings, routing) {
+        bevy_log::error!("error saving settings: {err}");
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    {
+        eframe::set_value(storage, eframe::APP_KEY, settings);
+        eframe::set_value(storage, ROUTING_CONFIG_KEY, routing);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    let _ = storage;
+}
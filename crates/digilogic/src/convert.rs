@@ -0,0 +1,186 @@
+//! The `convert` subcommand, which loads circuits in one format and writes them in another
+//! without opening a window.
+
+use digilogic_headless::{HeadlessApp, HeadlessBuilder};
+use std::path::{Path, PathBuf};
+
+/// Whether a file name matches a pattern, where `*` matches any run of characters
+/// and `?` matches a single character.
+fn matches_wildcard(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let name = name.chars().collect::<Vec<_>>();
+
+    // the position after the last `*` and what it matched up to, to backtrack to
+    let mut star = None;
+    let (mut p, mut n) = (0, 0);
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p + 1, n));
+                p += 1;
+            }
+            Some(&c) if (c == '?') || (c == name[n]) => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    p = star_p;
+                    n = star_n + 1;
+                    star = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Expands wildcards in the file name of `pattern` to the matching files, sorted by name.
+/// Shells on Windows don't do this themselves.
+fn expand_pattern(pattern: &str) -> std::io::Result<Vec<PathBuf>> {
+    let path = Path::new(pattern);
+    let Some(file_pattern) = path.file_name().and_then(|name| name.to_str()) else {
+        return Ok(vec![path.to_owned()]);
+    };
+    if !file_pattern.contains(['*', '?']) {
+        return Ok(vec![path.to_owned()]);
+    }
+
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let is_match = name
+            .to_str()
+            .is_some_and(|name| matches_wildcard(file_pattern, name));
+        if is_match && entry.file_type()?.is_file() {
+            files.push(dir.join(name));
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Where a circuit is written when converting into a directory, named after its file or,
+/// for projects, after the circuit.
+fn output_path(
+    app: &HeadlessApp,
+    input: &Path,
+    circuit: digilogic_core::components::CircuitID,
+    dir: &Path,
+    format: &str,
+) -> Option<PathBuf> {
+    let is_project = input.extension().is_some_and(|ext| ext == "dlp");
+    let stem = if is_project {
+        app.summary(circuit)?.name
+    } else {
+        input.file_stem()?.to_string_lossy().into_owned()
+    };
+    Some(dir.join(stem).with_extension(format))
+}
+
+/// Returns whether all files were converted.
+pub(crate) fn run_convert(
+    inputs: &[String],
+    output: &Path,
+    format: Option<&str>,
+    routing: bool,
+) -> bool {
+    let mut files = Vec::new();
+    for pattern in inputs {
+        match expand_pattern(pattern) {
+            Ok(matches) if matches.is_empty() => eprintln!("no files match {pattern}"),
+            Ok(matches) => files.extend(matches),
+            Err(err) => eprintln!("{pattern}: {err}"),
+        }
+    }
+    if files.is_empty() {
+        return false;
+    }
+
+    let mut app = HeadlessBuilder::default()
+        .routing(routing)
+        .log_level(bevy_log::Level::WARN)
+        .build();
+
+    let mut loaded = Vec::new();
+    let mut ok = true;
+    for filename in &files {
+        let result = app.load(filename);
+        match result {
+            Ok(circuits) => loaded.extend(circuits.into_iter().map(|circuit| (filename, circuit))),
+            Err(err) => {
+                eprintln!("{err:#}");
+                ok = false;
+            }
+        }
+    }
+    if routing && !app.settle() {
+        eprintln!("routing did not settle");
+    }
+
+    // a single circuit goes to the output file, several ones into the output directory
+    let into_dir = (loaded.len() > 1) || format.is_some() || output.is_dir();
+    let format = match format {
+        Some(format) => format,
+        None if into_dir => {
+            eprintln!("converting into a directory needs a format to convert to (--to)");
+            return false;
+        }
+        None => "",
+    };
+    if into_dir {
+        if let Err(err) = std::fs::create_dir_all(output) {
+            eprintln!("{}: {err}", output.display());
+            return false;
+        }
+    }
+
+    for (input, circuit) in loaded {
+        let path = if into_dir {
+            match output_path(&app, input, circuit, output, format) {
+                Some(path) => path,
+                None => {
+                    eprintln!("no output file for {}", input.display());
+                    ok = false;
+                    continue;
+                }
+            }
+        } else {
+            output.to_owned()
+        };
+
+        let result = app.save(circuit, &path);
+        match result {
+            Ok(()) => println!("{} -> {}", input.display(), path.display()),
+            Err(err) => {
+                eprintln!("error writing {}: {err:#}", path.display());
+                ok = false;
+            }
+        }
+    }
+    ok
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wildcards() {
+        assert!(matches_wildcard("*.dig", "adder.dig"));
+        assert!(matches_wildcard("*.dig", ".dig"));
+        assert!(!matches_wildcard("*.dig", "adder.dlc"));
+        assert!(matches_wildcard("add?r.*", "adder.dlc"));
+        assert!(!matches_wildcard("add?r", "addr"));
+        assert!(matches_wildcard("a*b*c", "aXbYbZc"));
+        assert!(!matches_wildcard("a*b*c", "aXbYbZ"));
+        assert!(matches_wildcard("*", ""));
+        assert!(matches_wildcard("adder.dig", "adder.dig"));
+    }
+}
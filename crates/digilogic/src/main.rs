@@ -3,6 +3,9 @@
     windows_subsystem = "windows"
 )]
 
+mod config;
+#[cfg(not(target_arch = "wasm32"))]
+mod convert;
 mod ui;
 
 use bevy_ecs::prelude::*;
@@ -10,11 +13,13 @@ use bevy_reflect::Reflect;
 use bevy_state::prelude::*;
 use bevy_time::{Time, Virtual};
 use digilogic_core::states::SimulationConnected;
-use digilogic_core::SharedStr;
+use digilogic_core::{Fixed, SharedStr};
 use digilogic_routing::RoutingConfig;
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 
-const ROUTING_CONFIG_KEY: &str = "routing";
+// only needed to enable features of the renderer used by egui and vello
+use wgpu as _;
 
 #[cfg(debug_assertions)]
 const LOG_LEVEL: bevy_log::Level = bevy_log::Level::DEBUG;
@@ -37,6 +42,7 @@ impl Backend {
 }
 
 #[cfg(not(target_arch = "wasm32"))]
+#[allow(clippy::derivable_impls)]
 impl Default for Backend {
     fn default() -> Self {
         Self::Builtin
@@ -52,14 +58,28 @@ impl Default for Backend {
 
 #[derive(Serialize, Deserialize, Resource, Reflect)]
 #[reflect(Resource)]
-struct AppSettings {
+#[serde(default)]
+struct Settings {
     dark_mode: bool,
     show_bounding_boxes: bool,
     show_routing_graph: bool,
     show_root_wires: bool,
+    show_diagnostics: bool,
+    show_console: bool,
+    show_profiler: bool,
+    grid_size: u32,
+    /// The pattern unnamed nets are named after, see [`digilogic_ux::NetNamePattern`]
+    net_name_pattern: SharedStr,
+    /// Autosave interval in minutes for modified circuits, 0 disables autosaving
+    autosave_interval: u32,
+    /// Most recently opened files, most recent first
+    recent_files: Vec<PathBuf>,
     backend: Backend,
     builtin_backend_engine: native_main::SimulationEngine,
     external_backend_addr: (SharedStr, u16),
+    /// Port of the remote control server, 0 disables it
+    remote_control_port: u16,
+    gate_style: ui::GateStyle,
 }
 
 const DEFAULT_LOCAL_SERVER_ADDR: (SharedStr, u16) = (
@@ -67,20 +87,39 @@ const DEFAULT_LOCAL_SERVER_ADDR: (SharedStr, u16) = (
     digilogic_netcode::DEFAULT_PORT,
 );
 
-impl Default for AppSettings {
+impl Default for Settings {
     fn default() -> Self {
         Self {
             dark_mode: true,
             show_bounding_boxes: false,
             show_routing_graph: false,
             show_root_wires: false,
+            show_diagnostics: false,
+            show_console: false,
+            show_profiler: false,
+            grid_size: 10,
+            net_name_pattern: SharedStr::new_static(digilogic_ux::NetNamePattern::DEFAULT),
+            autosave_interval: 5,
+            recent_files: Vec::new(),
             backend: Backend::default(),
             builtin_backend_engine: native_main::SimulationEngine::default(),
             external_backend_addr: DEFAULT_LOCAL_SERVER_ADDR,
+            remote_control_port: 0,
+            gate_style: ui::GateStyle::default(),
         }
     }
 }
 
+const MAX_RECENT_FILES: usize = 10;
+
+impl Settings {
+    fn add_recent_file(&mut self, path: PathBuf) {
+        self.recent_files.retain(|recent| *recent != path);
+        self.recent_files.insert(0, path);
+        self.recent_files.truncate(MAX_RECENT_FILES);
+    }
+}
+
 #[derive(Event)]
 enum FileDialogEvent {
     OpenProject,
@@ -88,7 +127,22 @@ enum FileDialogEvent {
 
     AddCircuit,
     ImportCircuit,
-    SaveCircuit,
+    /// Saves the circuit in the Digilogic circuit format, or as a Digital circuit.
+    SaveCircuit(digilogic_core::components::CircuitID),
+    /// Saves every modified circuit, asking for a file for those not saved as Digilogic circuits.
+    SaveModified,
+    /// Runs a script file on the circuit.
+    RunScript(digilogic_core::components::CircuitID),
+    /// Exports the circuit with an exporter added by an extension.
+    ExportCircuit {
+        circuit: digilogic_core::components::CircuitID,
+        exporter: usize,
+    },
+    /// Exports the values of traced nets during a range of simulation steps.
+    ExportTimingDiagram {
+        signals: Vec<Entity>,
+        window: std::ops::Range<u64>,
+    },
 }
 
 #[repr(transparent)]
@@ -102,17 +156,44 @@ fn resume_time(mut time: ResMut<Time<Virtual>>) {
     time.unpause();
 }
 
+fn sync_grid_size(settings: Res<Settings>, mut grid_size: ResMut<digilogic_ux::GridSize>) {
+    let size = Fixed::try_from_u32(settings.grid_size).unwrap_or_default();
+
+    // Don't trigger change detection if nothing changed.
+    if grid_size.0 != size {
+        grid_size.0 = size;
+    }
+}
+
+fn sync_net_name_pattern(
+    settings: Res<Settings>,
+    mut pattern: ResMut<digilogic_ux::NetNamePattern>,
+) {
+    // Don't trigger change detection if nothing changed.
+    if pattern.0 != settings.net_name_pattern {
+        pattern.0 = settings.net_name_pattern.clone();
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn sync_remote_control(
+    settings: Res<Settings>,
+    mut remote_control: ResMut<digilogic_remote::RemoteControl>,
+) {
+    // Don't restart the server if nothing changed.
+    if remote_control.port != settings.remote_control_port {
+        remote_control.port = settings.remote_control_port;
+    }
+}
+
 impl App {
     fn new(cc: &eframe::CreationContext<'_>) -> Self {
         let context = &cc.egui_ctx;
         let render_state = cc.wgpu_render_state.as_ref().unwrap();
 
-        let app_state: AppSettings = cc
-            .storage
-            .and_then(|storage| eframe::get_value(storage, eframe::APP_KEY))
-            .unwrap_or_default();
+        let (settings, routing_config) = config::load(cc.storage);
 
-        let visuals = if app_state.dark_mode {
+        let visuals = if settings.dark_mode {
             egui::Visuals::dark()
         } else {
             egui::Visuals::light()
@@ -130,14 +211,16 @@ impl App {
             bevy_state::app::StatesPlugin,
             bevy_log::LogPlugin {
                 level: LOG_LEVEL,
+                custom_layer: ui::profiler_layer,
                 ..Default::default()
             },
         ));
 
-        app.register_type::<std::path::PathBuf>()
+        app.register_type::<PathBuf>()
             .register_type::<std::time::Instant>()
-            .register_type::<AppSettings>();
-        app.insert_resource(app_state);
+            .register_type::<Settings>();
+        app.insert_resource(settings);
+        app.insert_resource(routing_config);
         app.add_event::<FileDialogEvent>();
 
         // Setup virtual time to only advance while simulating.
@@ -147,14 +230,6 @@ impl App {
         app.add_systems(OnExit(SimulationConnected), pause_time);
         app.add_systems(OnEnter(SimulationConnected), resume_time);
 
-        // TODO: find a way to have plugins register what they want to save and restore.
-        if let Some(routing_config) = cc
-            .storage
-            .and_then(|storage| eframe::get_value::<RoutingConfig>(storage, ROUTING_CONFIG_KEY))
-        {
-            app.insert_resource(routing_config);
-        }
-
         // Digilogic plugins
         app.add_plugins((
             digilogic_core::CorePlugin,
@@ -162,9 +237,43 @@ impl App {
             digilogic_routing::RoutingPlugin,
             digilogic_netcode::ClientPlugin,
             digilogic_ux::UxPlugin,
+            digilogic_script::ScriptPlugin,
+            digilogic_extension::ExtensionPlugin,
+            digilogic_report::ReportPlugin,
             ui::UiPlugin::new(context, render_state),
         ));
 
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            app.add_plugins((
+                digilogic_remote::RemotePlugin,
+                digilogic_collab::CollabPlugin,
+            ));
+            app.add_systems(
+                bevy_app::PreUpdate,
+                sync_remote_control.run_if(resource_changed::<Settings>),
+            );
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(dir) = config::config_dir() {
+            let mut symbol_registry = app
+                .world_mut()
+                .resource_mut::<digilogic_core::symbol::SymbolRegistry>();
+            digilogic_serde::load_symbol_library(&dir.join("symbols"), &mut symbol_registry);
+
+            let world = app.world_mut();
+            world.resource_scope(|world, mut symbol_shapes: Mut<ui::SymbolShapes>| {
+                let symbol_registry = world.resource::<digilogic_core::symbol::SymbolRegistry>();
+                ui::load_symbol_artwork(&dir.join("symbols"), symbol_registry, &mut symbol_shapes);
+            });
+        }
+
+        app.add_systems(
+            bevy_app::PreUpdate,
+            (sync_grid_size, sync_net_name_pattern).run_if(resource_changed::<Settings>),
+        );
+
         Self(app)
     }
 }
@@ -172,9 +281,13 @@ impl App {
 fn handle_exit_events(world: &mut World, context: &egui::Context) {
     type AppExitEvents = Events<bevy_app::AppExit>;
 
-    let mut exit_events = world.get_resource_mut::<AppExitEvents>().unwrap();
     let close_requested = context.input(|i| i.viewport().close_requested());
+    if close_requested && ui::intercept_quit(world) {
+        context.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+        return;
+    }
 
+    let mut exit_events = world.get_resource_mut::<AppExitEvents>().unwrap();
     if !exit_events.is_empty() && !close_requested {
         context.send_viewport_cmd(egui::ViewportCommand::Close);
     } else if close_requested {
@@ -186,6 +299,11 @@ trait FileDialogExt {
     fn add_project_filters(self) -> Self;
     fn add_circuit_filters(self) -> Self;
     fn add_import_filters(self) -> Self;
+    fn add_script_filters(self) -> Self;
+    fn add_format_filters<'a>(
+        self,
+        formats: impl IntoIterator<Item = (&'a str, &'a [&'static str])>,
+    ) -> Self;
 }
 
 impl FileDialogExt for rfd::FileDialog {
@@ -199,8 +317,98 @@ impl FileDialogExt for rfd::FileDialog {
 
     fn add_import_filters(self) -> Self {
         self.add_filter("Digital Circuit", &["dig"])
+            .add_filter("Logisim Circuit", &["circ"])
             .add_filter("Yosys JSON", &["yosys", "json"])
     }
+
+    fn add_script_filters(self) -> Self {
+        self.add_filter("Rhai Script", &["rhai"])
+    }
+
+    fn add_format_filters<'a>(
+        self,
+        formats: impl IntoIterator<Item = (&'a str, &'a [&'static str])>,
+    ) -> Self {
+        formats
+            .into_iter()
+            .fold(self, |dialog, (name, extensions)| {
+                dialog.add_filter(name, extensions)
+            })
+    }
+}
+
+fn add_recent_file(world: &mut World, filename: &std::path::Path) {
+    if let Some(mut settings) = world.get_resource_mut::<Settings>() {
+        settings.add_recent_file(filename.to_owned());
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn run_script_file(
+    world: &mut World,
+    circuit: digilogic_core::components::CircuitID,
+    filename: &std::path::Path,
+) {
+    use digilogic_script::{ConsoleLineKind, RunScript, ScriptConsole};
+
+    let mut console = world.resource_mut::<ScriptConsole>();
+    console.push(
+        ConsoleLineKind::Input,
+        format!("> run {}", filename.display()),
+    );
+    match std::fs::read_to_string(filename) {
+        Ok(source) => world.trigger(RunScript { circuit, source }),
+        Err(err) => console.push(ConsoleLineKind::Error, err.to_string()),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn export_timing_diagram(
+    world: &mut World,
+    signals: &[Entity],
+    window: std::ops::Range<u64>,
+    filename: &std::path::Path,
+) -> std::io::Result<()> {
+    use digilogic_core::components::Name;
+
+    let signals = signals
+        .iter()
+        .filter_map(|&net| Some((net, world.get::<Name>(net)?.0.as_str())))
+        .collect::<Vec<_>>();
+    let traces = world.resource::<digilogic_netcode::SignalTraces>();
+    std::fs::write(filename, traces.to_wavedrom(&signals, window))
+}
+
+/// Asks for the file to save a circuit to, starting from its current file.
+#[cfg(not(target_arch = "wasm32"))]
+fn save_circuit_dialog(
+    world: &World,
+    dialog: rfd::FileDialog,
+    circuit: digilogic_core::components::CircuitID,
+) -> Option<PathBuf> {
+    use digilogic_core::components::{FilePath, Name};
+
+    let mut dialog = dialog
+        .add_circuit_filters()
+        .add_filter("Digital Circuit", &["dig"]);
+    let file_path = world.get::<FilePath>(circuit.0);
+    match file_path.map(|FilePath(path)| path) {
+        Some(path) if path.extension().is_some_and(|ext| ext == "dlc") => {
+            if let Some(directory) = path.parent() {
+                dialog = dialog.set_directory(directory);
+            }
+            if let Some(file_name) = path.file_name() {
+                dialog = dialog.set_file_name(file_name.to_string_lossy());
+            }
+        }
+        _ => {
+            if let Some(Name(name)) = world.get::<Name>(circuit.0) {
+                dialog = dialog.set_file_name(format!("{name}.dlc"));
+            }
+        }
+    }
+
+    dialog.save_file()
 }
 
 fn handle_file_dialog(world: &mut World, frame: &mut eframe::Frame) {
@@ -219,33 +427,106 @@ fn handle_file_dialog(world: &mut World, frame: &mut eframe::Frame) {
             match file_dialog_event {
                 FileDialogEvent::OpenProject => {
                     if let Some(filename) = dialog.add_project_filters().pick_file() {
+                        add_recent_file(world, &filename);
                         let mut load_events =
                             world.get_resource_mut::<ProjectLoadEvents>().unwrap();
                         load_events.send(digilogic_core::events::ProjectLoadEvent { filename });
                     }
                 }
                 FileDialogEvent::SaveProject => {
-                    if let Some(filename) = dialog.add_project_filters().save_file() {
+                    if let Some(_filename) = dialog.add_project_filters().save_file() {
                         // TODO: save project file
                     }
                 }
                 FileDialogEvent::AddCircuit => {
                     if let Some(filename) = dialog.add_circuit_filters().pick_file() {
+                        add_recent_file(world, &filename);
                         let mut load_events =
                             world.get_resource_mut::<CircuitLoadEvents>().unwrap();
                         load_events.send(digilogic_core::events::CircuitLoadEvent { filename });
                     }
                 }
                 FileDialogEvent::ImportCircuit => {
-                    if let Some(filename) = dialog.add_import_filters().pick_file() {
+                    let formats = world.resource::<digilogic_extension::FileFormats>();
+                    let importers = formats
+                        .importers()
+                        .iter()
+                        .map(|importer| (importer.name.as_str(), importer.extensions));
+                    let dialog = dialog.add_import_filters().add_format_filters(importers);
+                    if let Some(filename) = dialog.pick_file() {
+                        add_recent_file(world, &filename);
                         let mut load_events =
                             world.get_resource_mut::<CircuitLoadEvents>().unwrap();
                         load_events.send(digilogic_core::events::CircuitLoadEvent { filename });
                     }
                 }
-                FileDialogEvent::SaveCircuit => {
-                    if let Some(filename) = dialog.add_project_filters().save_file() {
-                        // TODO: save circuit file
+                FileDialogEvent::SaveCircuit(circuit) => {
+                    if let Some(filename) = save_circuit_dialog(world, dialog, circuit) {
+                        add_recent_file(world, &filename);
+                        world.send_event(digilogic_core::events::SaveEvent { circuit, filename });
+                    }
+                }
+                FileDialogEvent::SaveModified => {
+                    use digilogic_core::components::{Circuit, FilePath, Modified};
+
+                    let modified: Vec<_> = world
+                        .query_filtered::<Entity, (With<Circuit>, With<Modified>)>()
+                        .iter(world)
+                        .collect();
+                    for circuit in modified {
+                        let circuit = digilogic_core::components::CircuitID(circuit);
+                        let file_path = world
+                            .get::<FilePath>(circuit.0)
+                            .map(|FilePath(path)| path)
+                            .filter(|path| path.extension().is_some_and(|ext| ext == "dlc"));
+                        let filename = match file_path {
+                            Some(path) => path.clone(),
+                            None => {
+                                let dialog = rfd::FileDialog::new().set_parent(frame);
+                                // the circuits left unsaved keep the pending action from going on
+                                let Some(filename) = save_circuit_dialog(world, dialog, circuit)
+                                else {
+                                    break;
+                                };
+                                add_recent_file(world, &filename);
+                                filename
+                            }
+                        };
+                        world.send_event(digilogic_core::events::SaveEvent { circuit, filename });
+                    }
+                }
+                FileDialogEvent::ExportCircuit { circuit, exporter } => {
+                    let formats = world.resource::<digilogic_extension::FileFormats>();
+                    let Some(exporter) = formats.exporters().get(exporter).cloned() else {
+                        continue;
+                    };
+                    let dialog =
+                        dialog.add_format_filters([(exporter.name.as_str(), exporter.extensions)]);
+                    if let Some(filename) = dialog.save_file() {
+                        let result = (exporter.export)(world, circuit, &filename);
+                        if let Err(err) = result {
+                            world.send_event(digilogic_core::events::ErrorEvent::error(
+                                "exporter",
+                                format!("error exporting to {}: {err:#}", filename.display()),
+                            ));
+                        }
+                    }
+                }
+                FileDialogEvent::ExportTimingDiagram { signals, window } => {
+                    let dialog = dialog.add_filter("WaveDrom JSON", &["json"]);
+                    if let Some(filename) = dialog.save_file() {
+                        let result = export_timing_diagram(world, &signals, window, &filename);
+                        if let Err(err) = result {
+                            world.send_event(digilogic_core::events::ErrorEvent::error(
+                                "timing diagram",
+                                format!("error exporting to {}: {err}", filename.display()),
+                            ));
+                        }
+                    }
+                }
+                FileDialogEvent::RunScript(circuit) => {
+                    if let Some(filename) = dialog.add_script_filters().pick_file() {
+                        run_script_file(world, circuit, &filename);
                     }
                 }
             }
@@ -260,16 +541,22 @@ fn handle_file_dialog(world: &mut World, frame: &mut eframe::Frame) {
 
 impl eframe::App for App {
     fn save(&mut self, storage: &mut dyn eframe::Storage) {
-        if let Some(app_state) = self.0.world().get_resource::<AppSettings>() {
-            eframe::set_value(storage, eframe::APP_KEY, app_state);
-        }
+        let world = self.0.world();
 
         // TODO: find a way to have plugins register what they want to save and restore.
-        if let Some(routing_config) = self.0.world().get_resource::<RoutingConfig>() {
-            eframe::set_value(storage, ROUTING_CONFIG_KEY, routing_config);
+        if let (Some(settings), Some(routing_config)) = (
+            world.get_resource::<Settings>(),
+            world.get_resource::<RoutingConfig>(),
+        ) {
+            config::save(storage, settings, routing_config);
         }
     }
 
+    fn on_exit(&mut self) {
+        #[cfg(not(target_arch = "wasm32"))]
+        ui::end_session();
+    }
+
     fn update(&mut self, context: &egui::Context, frame: &mut eframe::Frame) {
         match self.0.plugins_state() {
             bevy_app::PluginsState::Adding => {
@@ -332,6 +619,30 @@ mod native_main {
             #[arg(short, long)]
             port: Option<u16>,
         },
+        /// Loads circuits without opening a window and reports what the electrical rule check finds
+        Check {
+            /// The project or circuit files to load
+            #[arg(required = true)]
+            files: Vec<std::path::PathBuf>,
+            /// Skip routing the wires of the circuits
+            #[arg(long)]
+            no_routing: bool,
+        },
+        /// Converts circuits from one file format to another without opening a window
+        Convert {
+            /// The project or circuit files to convert, `*` and `?` match any files
+            #[arg(required = true)]
+            inputs: Vec<String>,
+            /// The file to write, or the directory to write into when converting several circuits
+            #[arg(short, long)]
+            output: std::path::PathBuf,
+            /// The extension of the format to convert to when writing into a directory
+            #[arg(long)]
+            to: Option<String>,
+            /// Skip routing the wires of the circuits
+            #[arg(long)]
+            no_routing: bool,
+        },
     }
 
     #[derive(Parser)]
@@ -361,6 +672,54 @@ mod native_main {
         .unwrap();
     }
 
+    /// Returns whether all files loaded without any errors found in them.
+    fn run_check(files: &[std::path::PathBuf], routing: bool) -> bool {
+        let mut app = digilogic_headless::HeadlessBuilder::default()
+            .routing(routing)
+            .log_level(bevy_log::Level::WARN)
+            .build();
+
+        let mut ok = true;
+        for filename in files {
+            let circuits = match app.load(filename) {
+                Ok(circuits) => circuits,
+                Err(err) => {
+                    eprintln!("{err:#}");
+                    ok = false;
+                    continue;
+                }
+            };
+            if !app.settle() {
+                eprintln!("{} did not settle", filename.display());
+            }
+
+            for summary in circuits
+                .into_iter()
+                .filter_map(|circuit| app.summary(circuit))
+            {
+                println!(
+                    "{}: {} symbols, {} nets, {} endpoints",
+                    summary.name,
+                    summary.stats.symbols,
+                    summary.stats.nets,
+                    summary.stats.endpoints,
+                );
+            }
+        }
+
+        for finding in app.findings() {
+            let severity = match finding.severity() {
+                digilogic_ux::Severity::Warning => "warning",
+                digilogic_ux::Severity::Error => {
+                    ok = false;
+                    "error"
+                }
+            };
+            println!("{severity}: {}", finding.message);
+        }
+        ok
+    }
+
     pub fn run() {
         let args = Args::parse();
         match args.command {
@@ -372,6 +731,21 @@ mod native_main {
                 }
                 SimulationEngine::GsimCompute => todo!(),
             },
+            Some(Commands::Check { files, no_routing }) => {
+                if !run_check(&files, !no_routing) {
+                    std::process::exit(1);
+                }
+            }
+            Some(Commands::Convert {
+                inputs,
+                output,
+                to,
+                no_routing,
+            }) => {
+                if !crate::convert::run_convert(&inputs, &output, to.as_deref(), !no_routing) {
+                    std::process::exit(1);
+                }
+            }
         }
     }
 }
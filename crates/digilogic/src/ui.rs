@@ -1,29 +1,118 @@
 mod canvas;
 use canvas::*;
 
+mod clipboard;
+use clipboard::*;
+
 mod draw;
 use digilogic_ux::DragType;
 use draw::*;
+pub(crate) use draw::{GateStyle, SymbolShapes};
 
 mod settings;
 use settings::*;
 
+mod properties;
+use properties::*;
+
+mod net_labels;
+use net_labels::*;
+
+mod annotations;
+use annotations::*;
+
+mod symbol_labels;
+use symbol_labels::*;
+
+mod io_stub;
+use io_stub::*;
+
+mod diagnostics;
+use diagnostics::*;
+
+mod console;
+use console::*;
+
+mod extensions;
+use extensions::*;
+
+mod bit_assignment;
+use bit_assignment::*;
+
+mod context_menu;
+use context_menu::*;
+
+mod go_to;
+use go_to::*;
+
+mod timing_diagram;
+use timing_diagram::*;
+
+mod hierarchy;
+use hierarchy::*;
+
 mod explorer;
 use explorer::*;
 
 mod palette;
 use palette::*;
 
-use crate::{AppSettings, Backend, FileDialogEvent, DEFAULT_LOCAL_SERVER_ADDR};
+mod symbol_palette;
+use symbol_palette::*;
+
+mod thumbnails;
+use thumbnails::*;
+
+mod welcome;
+use welcome::*;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod session;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) use session::end_session;
+#[cfg(not(target_arch = "wasm32"))]
+use session::*;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod file_watch;
+#[cfg(not(target_arch = "wasm32"))]
+use file_watch::*;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod svg_shapes;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) use svg_shapes::load_symbol_artwork;
+
+mod errors;
+use errors::*;
+
+mod profiler;
+pub(crate) use profiler::profiler_layer;
+use profiler::*;
+
+mod unsaved;
+pub(crate) use unsaved::intercept_quit;
+use unsaved::*;
+
+use crate::{Backend, FileDialogEvent, Settings, DEFAULT_LOCAL_SERVER_ADDR};
 use bevy_ecs::prelude::*;
 use bevy_ecs::system::lifetimeless::{Read, Write};
 use bevy_ecs::system::SystemParam;
 use bevy_reflect::Reflect;
 use bevy_state::prelude::*;
-use digilogic_core::components::{Circuit, CircuitID, Name, Viewport};
+use digilogic_core::components::{
+    Circuit, CircuitID, Endpoint, GraphicKind, Modified, Name, Net, Port, Selected, Symbol,
+    SymbolKind, Viewport,
+};
+use digilogic_core::events::CircuitLoadedEvent;
 use digilogic_core::resources::Project;
 use digilogic_core::states::{SimulationConnected, SimulationState};
-use digilogic_core::{fixed, Fixed, SharedStr};
+use digilogic_core::symbol::SymbolRegistry;
+use digilogic_core::transform::{BoundingBox, Direction, Rotation};
+use digilogic_core::visibility::{ViewVisibilityBundle, VisibleLayers};
+use digilogic_core::Fixed;
+use digilogic_extension::Overlay;
+use digilogic_ux::{ActiveTool, CircuitStats, GraphicPlacementKind, PlacementKind};
 use egui::*;
 use egui_dock::*;
 use egui_wgpu::RenderState;
@@ -86,22 +175,59 @@ impl Default for PanZoom {
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Component, Reflect)]
 #[repr(u8)]
 enum Layer {
+    Graphic,
     Symbol,
     RoutingGraph,
     Wire,
     Port,
+    ErcMarker,
+    Waypoint,
     BoundingBox,
+    AlignmentGuide,
+    SelectionBox,
+    PortStub,
+}
+
+impl Layer {
+    const ALL: [Self; 11] = [
+        Self::Graphic,
+        Self::Symbol,
+        Self::RoutingGraph,
+        Self::Wire,
+        Self::Port,
+        Self::ErcMarker,
+        Self::Waypoint,
+        Self::BoundingBox,
+        Self::AlignmentGuide,
+        Self::SelectionBox,
+        Self::PortStub,
+    ];
+
+    /// The layer users can hide per viewport this part of the scene belongs to, if any.
+    fn visible_layer(self) -> Option<VisibleLayers> {
+        match self {
+            Self::Graphic => Some(VisibleLayers::ANNOTATIONS),
+            Self::Symbol | Self::Port => Some(VisibleLayers::SYMBOLS),
+            Self::Wire | Self::Waypoint => Some(VisibleLayers::WIRES),
+            Self::ErcMarker => Some(VisibleLayers::ERC_MARKERS),
+            Self::RoutingGraph
+            | Self::BoundingBox
+            | Self::AlignmentGuide
+            | Self::SelectionBox
+            | Self::PortStub => None,
+        }
+    }
 }
 
 #[derive(Default, Component)]
 struct Scene {
-    layers: [Mutex<vello::Scene>; 5],
+    layers: [Mutex<vello::Scene>; Layer::ALL.len()],
     combined: vello::Scene,
 }
 
 impl Scene {
     #[inline]
-    fn for_layer(&self, layer: Layer) -> MutexGuard<vello::Scene> {
+    fn for_layer(&self, layer: Layer) -> MutexGuard<'_, vello::Scene> {
         self.layers[layer as usize].lock().unwrap()
     }
 }
@@ -112,14 +238,25 @@ struct ViewportBundle {
     circuit: CircuitID,
     pan_zoom: PanZoom,
     scene: Scene,
+    overlay: Overlay,
     canvas: Canvas,
+    visible_layers: VisibleLayers,
+    view_visibility: ViewVisibilityBundle,
+}
+
+/// Marks a viewport that has been detached from the dock into its own window.
+#[derive(Debug, Default, Clone, Copy, Component, Reflect)]
+struct DetachedViewport;
+
+fn detached_viewport_id(viewport: Entity) -> ViewportId {
+    ViewportId::from_hash_of(("detached_viewport", viewport))
 }
 
 fn combine_scenes(
-    app_state: Res<AppSettings>,
-    mut viewports: Query<(&PanZoom, &mut Scene), With<Viewport>>,
+    settings: Res<Settings>,
+    mut viewports: Query<(&PanZoom, &VisibleLayers, &mut Scene, &Overlay), With<Viewport>>,
 ) {
-    for (pan_zoom, mut scene) in viewports.iter_mut() {
+    for (pan_zoom, &visible_layers, mut scene, overlay) in viewports.iter_mut() {
         let transform =
             vello::kurbo::Affine::translate((pan_zoom.pan.x as f64, pan_zoom.pan.y as f64))
                 .then_scale(pan_zoom.zoom as f64);
@@ -127,18 +264,28 @@ fn combine_scenes(
         let scene = &mut *scene;
         scene.combined.reset();
 
-        for (i, layer) in scene.layers.iter_mut().enumerate() {
-            if i == (Layer::BoundingBox as usize) && !app_state.show_bounding_boxes {
+        for (kind, layer) in Layer::ALL.into_iter().zip(scene.layers.iter_mut()) {
+            if kind == Layer::BoundingBox && !settings.show_bounding_boxes {
+                continue;
+            }
+
+            if kind == Layer::RoutingGraph && !settings.show_routing_graph {
                 continue;
             }
 
-            if i == (Layer::RoutingGraph as usize) && !app_state.show_routing_graph {
+            if kind
+                .visible_layer()
+                .is_some_and(|layer| !visible_layers.contains(layer))
+            {
                 continue;
             }
 
             let layer = layer.get_mut().unwrap();
             scene.combined.append(layer, Some(transform));
         }
+
+        // overlays of extensions go on top of everything
+        scene.combined.append(&overlay.0, Some(transform));
     }
 }
 
@@ -146,44 +293,153 @@ fn combine_scenes(
 #[reflect(Resource)]
 struct OpenWindows {
     settings: bool,
+    symbol_properties: bool,
+    bit_assignment: bool,
+    go_to: bool,
+    timing_diagram: bool,
+    unsaved_changes: Option<CloseAction>,
 }
 
 impl OpenWindows {
     fn any(&self) -> bool {
         self.settings
+            || self.symbol_properties
+            || self.bit_assignment
+            || self.go_to
+            || self.timing_diagram
+            || self.unsaved_changes.is_some()
+    }
+}
+
+const SELECT_ALL_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::A);
+const INVERT_SELECTION_SHORTCUT: KeyboardShortcut =
+    KeyboardShortcut::new(Modifiers::COMMAND, Key::I);
+const CLEAR_SELECTION_SHORTCUT: KeyboardShortcut =
+    KeyboardShortcut::new(Modifiers::NONE, Key::Escape);
+const ROTATE_CW_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::NONE, Key::R);
+const ROTATE_CCW_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::SHIFT, Key::R);
+const FLIP_HORIZONTAL_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::NONE, Key::X);
+const FLIP_VERTICAL_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::NONE, Key::Y);
+const GO_TO_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::G);
+
+fn nudge_direction(key: Key) -> Option<Direction> {
+    match key {
+        Key::ArrowLeft => Some(Direction::NegX),
+        Key::ArrowRight => Some(Direction::PosX),
+        Key::ArrowUp => Some(Direction::NegY),
+        Key::ArrowDown => Some(Direction::PosY),
+        _ => None,
     }
 }
 
+/// Arrow keys nudge the selection, releasing the key finishes the move.
+fn handle_nudging(
+    egui: Res<Egui>,
+    open_windows: Res<OpenWindows>,
+    mut nudge_events: EventWriter<digilogic_ux::NudgeSelection>,
+    mut end_events: EventWriter<digilogic_ux::EndNudge>,
+) {
+    let accept_input = !open_windows.any() && !egui.context.wants_keyboard_input();
+
+    egui.context.input(|state| {
+        for event in &state.events {
+            let &egui::Event::Key {
+                key,
+                pressed,
+                modifiers,
+                ..
+            } = event
+            else {
+                continue;
+            };
+            let Some(direction) = nudge_direction(key) else {
+                continue;
+            };
+
+            if !pressed {
+                // always end the move, even if input got blocked while the key was held
+                end_events.send(digilogic_ux::EndNudge);
+            } else if accept_input {
+                // key repeats arrive as further presses
+                nudge_events.send(digilogic_ux::NudgeSelection {
+                    direction,
+                    large: modifiers.shift,
+                });
+            }
+        }
+    });
+}
+
 // TODO: separate responsibilities
 #[allow(clippy::too_many_arguments)]
 fn update_menu(
     mut commands: Commands,
     egui: Res<Egui>,
-    mut settings: ResMut<AppSettings>,
+    mut settings: ResMut<Settings>,
     mut routing_config: ResMut<digilogic_routing::RoutingConfig>,
     mut file_dialog_events: EventWriter<FileDialogEvent>,
+    mut circuit_loaded_events: EventWriter<CircuitLoadedEvent>,
     mut open_windows: ResMut<OpenWindows>,
     project: Option<Res<Project>>,
-    circuits: Query<Entity, With<Circuit>>,
+    mut dock_state: NonSendMut<DockState<Entity>>,
+    symbol_palettes: Query<Entity, With<SymbolPaletteTab>>,
+    viewports: Query<&CircuitID, With<Viewport>>,
+    mut selection: digilogic_ux::Selection,
+    (mut rotate_events, mut mirror_events): (
+        EventWriter<digilogic_ux::RotateSelection>,
+        EventWriter<digilogic_ux::MirrorSelection>,
+    ),
+    symbol_registry: Res<SymbolRegistry>,
+    mut thumbnails: Thumbnails,
+    mut extension_menus: ExtensionMenus,
 ) {
+    let focused_circuit = dock_state
+        .find_active_focused()
+        .and_then(|(_, viewport)| viewports.get(*viewport).ok())
+        .copied();
+
+    if !open_windows.any() && !egui.context.wants_keyboard_input() {
+        egui.context.input_mut(|state| {
+            if let Some(circuit) = focused_circuit {
+                if state.consume_shortcut(&SELECT_ALL_SHORTCUT) {
+                    selection.select_all(circuit);
+                }
+                if state.consume_shortcut(&INVERT_SELECTION_SHORTCUT) {
+                    selection.invert(circuit);
+                }
+            }
+            if state.consume_shortcut(&CLEAR_SELECTION_SHORTCUT) {
+                selection.clear();
+            }
+            // the more specific shortcut has to be consumed first
+            if state.consume_shortcut(&ROTATE_CCW_SHORTCUT) {
+                rotate_events.send(digilogic_ux::RotateSelection {
+                    rotation: Rotation::Rot270,
+                });
+            }
+            if state.consume_shortcut(&ROTATE_CW_SHORTCUT) {
+                rotate_events.send(digilogic_ux::RotateSelection {
+                    rotation: Rotation::Rot90,
+                });
+            }
+            if state.consume_shortcut(&FLIP_HORIZONTAL_SHORTCUT) {
+                mirror_events.send(digilogic_ux::MirrorSelection { vertical: false });
+            }
+            if state.consume_shortcut(&FLIP_VERTICAL_SHORTCUT) {
+                mirror_events.send(digilogic_ux::MirrorSelection { vertical: true });
+            }
+            if state.consume_shortcut(&GO_TO_SHORTCUT) {
+                open_windows.go_to = true;
+            }
+        });
+    }
+
     TopBottomPanel::top("menu_panel").show(&egui.context, |ui| {
         ui.add_enabled_ui(!open_windows.any(), |ui| {
             menu::bar(ui, |ui| {
                 ui.menu_button("File", |ui| {
                     if ui.button("New Project").clicked() {
-                        if project.is_some() {
-                            // TODO: check for unsaved changes
-
-                            for circuit in circuits.iter() {
-                                commands.entity(circuit).despawn();
-                            }
-                        }
-
-                        commands.insert_resource(Project {
-                            name: SharedStr::new_static("Unnamed Project"),
-                            file_path: None,
-                            root_circuit: None,
-                        });
+                        commands.trigger(NewProject);
                         ui.close_menu();
                     }
 
@@ -201,7 +457,7 @@ fn update_menu(
 
                     ui.add_enabled_ui(project.is_some(), |ui| {
                         if ui.button("New Circuit").clicked() {
-                            // TODO
+                            spawn_blank_circuit(&mut commands, &mut circuit_loaded_events);
                             ui.close_menu();
                         }
 
@@ -215,12 +471,41 @@ fn update_menu(
                             ui.close_menu();
                         }
 
-                        if ui.button("Save Circuit").clicked() {
-                            file_dialog_events.send(FileDialogEvent::SaveCircuit);
+                        let save_button =
+                            ui.add_enabled(focused_circuit.is_some(), Button::new("Save Circuit"));
+                        if save_button.clicked() {
+                            if let Some(circuit) = focused_circuit {
+                                file_dialog_events.send(FileDialogEvent::SaveCircuit(circuit));
+                            }
+                            ui.close_menu();
+                        }
+
+                        extension_menus.show_export_menu(
+                            ui,
+                            focused_circuit,
+                            &mut file_dialog_events,
+                        );
+
+                        if ui.button("Export Timing Diagram…").clicked() {
+                            open_windows.timing_diagram = true;
                             ui.close_menu();
                         }
                     });
 
+                    #[cfg(not(target_arch = "wasm32"))]
+                    {
+                        let script_circuit = focused_circuit
+                            .or(project.as_deref().and_then(|project| project.root_circuit));
+                        ui.add_enabled_ui(script_circuit.is_some(), |ui| {
+                            if ui.button("Run Script File…").clicked() {
+                                if let Some(circuit) = script_circuit {
+                                    file_dialog_events.send(FileDialogEvent::RunScript(circuit));
+                                }
+                                ui.close_menu();
+                            }
+                        });
+                    }
+
                     ui.separator();
 
                     #[cfg(not(target_arch = "wasm32"))]
@@ -230,22 +515,160 @@ fn update_menu(
                 });
                 ui.add_space(8.0);
 
-                ui.menu_button("View", |ui| {
-                    ui.menu_button("Debug", |ui| {
-                        ui.checkbox(&mut settings.show_bounding_boxes, "Bounding boxes");
-                        ui.checkbox(&mut settings.show_routing_graph, "Routing graph");
-                        ui.checkbox(&mut settings.show_root_wires, "Root wires");
+                ui.menu_button("Edit", |ui| {
+                    ui.add_enabled_ui(focused_circuit.is_some(), |ui| {
+                        let select_all_button = Button::new("Select All")
+                            .shortcut_text(ui.ctx().format_shortcut(&SELECT_ALL_SHORTCUT));
+                        if ui.add(select_all_button).clicked() {
+                            if let Some(circuit) = focused_circuit {
+                                selection.select_all(circuit);
+                            }
+                            ui.close_menu();
+                        }
+
+                        let invert_button = Button::new("Invert Selection")
+                            .shortcut_text(ui.ctx().format_shortcut(&INVERT_SELECTION_SHORTCUT));
+                        if ui.add(invert_button).clicked() {
+                            if let Some(circuit) = focused_circuit {
+                                selection.invert(circuit);
+                            }
+                            ui.close_menu();
+                        }
+                    });
+
+                    let clear_button = Button::new("Clear Selection")
+                        .shortcut_text(ui.ctx().format_shortcut(&CLEAR_SELECTION_SHORTCUT));
+                    if ui.add(clear_button).clicked() {
+                        selection.clear();
+                        ui.close_menu();
+                    }
+
+                    ui.separator();
+
+                    ui.add_enabled_ui(!selection.set.is_empty(), |ui| {
+                        let rotate_cw_button = Button::new("Rotate Clockwise")
+                            .shortcut_text(ui.ctx().format_shortcut(&ROTATE_CW_SHORTCUT));
+                        if ui.add(rotate_cw_button).clicked() {
+                            rotate_events.send(digilogic_ux::RotateSelection {
+                                rotation: Rotation::Rot90,
+                            });
+                            ui.close_menu();
+                        }
+
+                        let rotate_ccw_button = Button::new("Rotate Counterclockwise")
+                            .shortcut_text(ui.ctx().format_shortcut(&ROTATE_CCW_SHORTCUT));
+                        if ui.add(rotate_ccw_button).clicked() {
+                            rotate_events.send(digilogic_ux::RotateSelection {
+                                rotation: Rotation::Rot270,
+                            });
+                            ui.close_menu();
+                        }
+
+                        let flip_horizontal_button = Button::new("Flip Horizontally")
+                            .shortcut_text(ui.ctx().format_shortcut(&FLIP_HORIZONTAL_SHORTCUT));
+                        if ui.add(flip_horizontal_button).clicked() {
+                            mirror_events.send(digilogic_ux::MirrorSelection { vertical: false });
+                            ui.close_menu();
+                        }
+
+                        let flip_vertical_button = Button::new("Flip Vertically")
+                            .shortcut_text(ui.ctx().format_shortcut(&FLIP_VERTICAL_SHORTCUT));
+                        if ui.add(flip_vertical_button).clicked() {
+                            mirror_events.send(digilogic_ux::MirrorSelection { vertical: true });
+                            ui.close_menu();
+                        }
+
+                        if ui.button("Bit Assignment…").clicked() {
+                            open_windows.bit_assignment = true;
+                            ui.close_menu();
+                        }
+
+                        if let Some(circuit) = focused_circuit {
+                            if ui.button("Create Sub-Circuit").clicked() {
+                                commands.trigger(digilogic_ux::CreateSubCircuit { circuit });
+                                ui.close_menu();
+                            }
+
+                            ui.menu_button("Replace Kind", |ui| {
+                                let size = Vec2::splat(ui.spacing().interact_size.y);
+                                let color = ui.visuals().text_color();
+                                for def in symbol_registry.iter() {
+                                    let button = match thumbnails.image(def, size, color) {
+                                        Some(image) => {
+                                            Button::image_and_text(image, def.name().as_str())
+                                        }
+                                        None => Button::new(def.name().as_str()),
+                                    };
+                                    if ui.add(button).clicked() {
+                                        for symbol in selection.set.iter() {
+                                            commands.trigger(digilogic_ux::ReplaceSymbolKind {
+                                                circuit,
+                                                symbol,
+                                                kind: def.kind(),
+                                            });
+                                        }
+                                        ui.close_menu();
+                                    }
+                                }
+                            });
+                        }
+                    });
+
+                    ui.separator();
+
+                    ui.add_enabled_ui(focused_circuit.is_some(), |ui| {
+                        if ui.button("Renumber Designators").clicked() {
+                            if let Some(circuit) = focused_circuit {
+                                commands.trigger(digilogic_ux::RenumberDesignators { circuit });
+                            }
+                            ui.close_menu();
+                        }
+
+                        if ui.button("Create Chip From Circuit").clicked() {
+                            if let Some(circuit) = focused_circuit {
+                                commands.trigger(digilogic_ux::CreateChip { circuit });
+                            }
+                            ui.close_menu();
+                        }
                     });
 
                     ui.separator();
 
-                    if ui.button("Settings").clicked() {
+                    if ui.button("Preferences").clicked() {
                         open_windows.settings = true;
                         ui.close_menu();
                     }
                 });
                 ui.add_space(8.0);
 
+                ui.menu_button("View", |ui| {
+                    if ui.button("Symbol Palette").clicked() {
+                        open_symbol_palette(&mut commands, &mut dock_state, &symbol_palettes);
+                        ui.close_menu();
+                    }
+
+                    let go_to_button = Button::new("Go to Designator…")
+                        .shortcut_text(ui.ctx().format_shortcut(&GO_TO_SHORTCUT));
+                    if ui.add(go_to_button).clicked() {
+                        open_windows.go_to = true;
+                        ui.close_menu();
+                    }
+
+                    ui.checkbox(&mut settings.show_diagnostics, "Diagnostics");
+                    ui.checkbox(&mut settings.show_console, "Console");
+                    ui.checkbox(&mut settings.show_profiler, "Profiler");
+                    extension_menus.show_panel_buttons(ui);
+
+                    ui.menu_button("Debug", |ui| {
+                        ui.checkbox(&mut settings.show_bounding_boxes, "Bounding boxes");
+                        ui.checkbox(&mut settings.show_routing_graph, "Routing graph");
+                        ui.checkbox(&mut settings.show_root_wires, "Root wires");
+                    });
+                });
+                ui.add_space(8.0);
+
+                extension_menus.show_commands_menu(ui, &mut commands);
+
                 ui.menu_button("Routing", |ui| {
                     let mut prune_graph = routing_config.prune_graph;
                     ui.checkbox(&mut prune_graph, "Prune graph");
@@ -266,17 +689,98 @@ fn update_menu(
     });
 }
 
+fn graphic_kind_name(kind: GraphicKind) -> &'static str {
+    match kind {
+        GraphicKind::Rectangle => "Rectangle",
+        GraphicKind::Line => "Line",
+        GraphicKind::Ellipse => "Ellipse",
+    }
+}
+
+/// Connects to the backend chosen in the settings.
+fn start_simulation(commands: &mut Commands, settings: &Settings) {
+    match settings.backend {
+        #[cfg(not(target_arch = "wasm32"))]
+        Backend::Builtin => {
+            //let executable = std::env::current_exe().unwrap();
+            //std::process::Command::new(executable)
+            //    .arg("server")
+            //    .spawn()
+            //    .unwrap();
+
+            commands.trigger(digilogic_netcode::Connect {
+                server_addr: DEFAULT_LOCAL_SERVER_ADDR,
+            });
+        }
+        Backend::External => {
+            commands.trigger(digilogic_netcode::Connect {
+                server_addr: settings.external_backend_addr.clone(),
+            });
+        }
+    }
+}
+
+fn start_simulation_from_script(
+    _trigger: Trigger<digilogic_script::StartSimulation>,
+    mut commands: Commands,
+    settings: Res<Settings>,
+    simulation_state: Res<State<SimulationState>>,
+) {
+    if !simulation_state.is_connected() {
+        start_simulation(&mut commands, &settings);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn update_tool_bar(
     mut commands: Commands,
     egui: Res<Egui>,
-    settings: Res<AppSettings>,
+    settings: Res<Settings>,
     open_windows: Res<OpenWindows>,
     mut project: Option<ResMut<Project>>,
     simulation_state: Res<State<SimulationState>>,
+    mut active_tool: ResMut<ActiveTool>,
+    mut graphic_kind: ResMut<GraphicPlacementKind>,
     circuits: Query<(Entity, &Name), With<Circuit>>,
 ) {
     TopBottomPanel::top("tool_bar_panel").show(&egui.context, |ui| {
         menu::bar(ui, |ui| {
+            ui.add_enabled_ui(!open_windows.any(), |ui| {
+                let mut tool = *active_tool;
+                for &candidate in ActiveTool::ALL {
+                    ui.selectable_value(&mut tool, candidate, candidate.name());
+                }
+
+                // Don't trigger change detection if nothing changed.
+                if tool != *active_tool {
+                    *active_tool = tool;
+                }
+
+                if tool == ActiveTool::DrawGraphic {
+                    let mut kind = graphic_kind.0;
+                    ComboBox::from_id_salt("graphic_kind")
+                        .selected_text(graphic_kind_name(kind))
+                        .show_ui(ui, |ui| {
+                            for candidate in [
+                                GraphicKind::Rectangle,
+                                GraphicKind::Line,
+                                GraphicKind::Ellipse,
+                            ] {
+                                ui.selectable_value(
+                                    &mut kind,
+                                    candidate,
+                                    graphic_kind_name(candidate),
+                                );
+                            }
+                        });
+                    if kind != graphic_kind.0 {
+                        graphic_kind.0 = kind;
+                    }
+                }
+            });
+
+            ui.separator();
+
             let mut root_circuit = project.as_deref().and_then(|project| project.root_circuit);
             let root_name = root_circuit
                 .and_then(|root_circuit| circuits.get(root_circuit.0).ok())
@@ -305,25 +809,7 @@ fn update_tool_bar(
                 match simulation_state.is_connected() {
                     false => {
                         if ui.button("Start").clicked() {
-                            match settings.backend {
-                                #[cfg(not(target_arch = "wasm32"))]
-                                Backend::Builtin => {
-                                    //let executable = std::env::current_exe().unwrap();
-                                    //std::process::Command::new(executable)
-                                    //    .arg("server")
-                                    //    .spawn()
-                                    //    .unwrap();
-
-                                    commands.trigger(digilogic_netcode::Connect {
-                                        server_addr: DEFAULT_LOCAL_SERVER_ADDR,
-                                    });
-                                }
-                                Backend::External => {
-                                    commands.trigger(digilogic_netcode::Connect {
-                                        server_addr: settings.external_backend_addr.clone(),
-                                    });
-                                }
-                            }
+                            start_simulation(&mut commands, &settings);
                         }
                     }
                     true => {
@@ -359,28 +845,245 @@ fn update_tool_bar(
     });
 }
 
-fn update_status_bar(egui: Res<Egui>, open_windows: Res<OpenWindows>) {
+fn simulation_state_text(state: SimulationState) -> &'static str {
+    match state {
+        SimulationState::Disconnected => "Disconnected",
+        SimulationState::WaitingOnServer => "Waiting on server",
+        SimulationState::Building => "Building",
+        SimulationState::ActiveIdle => "Idle",
+        SimulationState::ActiveRunning => "Running",
+    }
+}
+
+/// Summarizes the contents of a circuit, like `12 symbols, 8 nets, 20 endpoints`.
+fn circuit_stats_text(stats: &CircuitStats) -> String {
+    let count = |count: usize, singular: &str, plural: &str| match count {
+        1 => format!("1 {singular}"),
+        _ => format!("{count} {plural}"),
+    };
+
+    format!(
+        "{}, {}, {}",
+        count(stats.symbols, "symbol", "symbols"),
+        count(stats.nets, "net", "nets"),
+        count(stats.endpoints, "endpoint", "endpoints"),
+    )
+}
+
+type SelectionQuery<'w, 's> =
+    Query<'w, 's, (Has<Symbol>, Has<Net>, Has<Endpoint>, Has<Port>), With<Selected>>;
+
+#[derive(Debug, Default)]
+struct SelectionSummary {
+    symbols: usize,
+    nets: usize,
+    endpoints: usize,
+    ports: usize,
+    other: usize,
+}
+
+impl SelectionSummary {
+    fn from_query(selection: &SelectionQuery) -> Self {
+        let mut summary = Self::default();
+        for kind in selection.iter() {
+            match kind {
+                (true, _, _, _) => summary.symbols += 1,
+                (_, true, _, _) => summary.nets += 1,
+                (_, _, true, _) => summary.endpoints += 1,
+                (_, _, _, true) => summary.ports += 1,
+                _ => summary.other += 1,
+            }
+        }
+        summary
+    }
+
+    fn text(&self) -> String {
+        let mut parts = Vec::new();
+        for (count, singular, plural) in [
+            (self.symbols, "symbol", "symbols"),
+            (self.nets, "net", "nets"),
+            (self.endpoints, "endpoint", "endpoints"),
+            (self.ports, "port", "ports"),
+            (self.other, "other", "others"),
+        ] {
+            match count {
+                0 => (),
+                1 => parts.push(format!("1 {singular}")),
+                _ => parts.push(format!("{count} {plural}")),
+            }
+        }
+
+        if parts.is_empty() {
+            "Nothing selected".to_owned()
+        } else {
+            format!("Selected: {}", parts.join(", "))
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_status_bar(
+    egui: Res<Egui>,
+    settings: Res<Settings>,
+    open_windows: Res<OpenWindows>,
+    active_tool: Res<ActiveTool>,
+    routing_status: Res<digilogic_routing::RoutingStatus>,
+    simulation_state: Res<State<SimulationState>>,
+    selection: SelectionQuery,
+    mut dock_state: NonSendMut<DockState<Entity>>,
+    viewports: Query<&CircuitID, With<Viewport>>,
+    circuit_stats: Query<&CircuitStats>,
+) {
+    let focused_stats = dock_state
+        .find_active_focused()
+        .and_then(|(_, viewport)| viewports.get(*viewport).ok())
+        .and_then(|circuit| circuit_stats.get(circuit.0).ok());
+
     TopBottomPanel::bottom("status_bar_panel").show(&egui.context, |ui| {
         ui.add_enabled_ui(!open_windows.any(), |ui| {
-            ui.with_layout(Layout::bottom_up(Align::RIGHT), |ui| {
-                warn_if_debug_build(ui);
+            ui.horizontal(|ui| {
+                if let Some(stats) = focused_stats {
+                    let label = ui.label(format!("Circuit: {}", circuit_stats_text(stats)));
+                    if let Some(extent) = stats.extent {
+                        label.on_hover_text(format!(
+                            "Symbols cover {} × {}",
+                            extent.width(),
+                            extent.height(),
+                        ));
+                    }
+                    ui.separator();
+                }
+                ui.label(SelectionSummary::from_query(&selection).text());
+                ui.separator();
+                ui.label(format!("Tool: {}", active_tool.name()));
+                ui.separator();
+                ui.label(format!("Grid: {}", settings.grid_size));
+                ui.separator();
+                ui.label(format!(
+                    "Routing: {} nets in {} circuits",
+                    routing_status.routed_nets, routing_status.routed_circuits,
+                ));
+                ui.separator();
+                ui.label(format!(
+                    "Simulation: {}",
+                    simulation_state_text(**simulation_state),
+                ));
+
+                ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                    warn_if_debug_build(ui);
+                });
             });
         });
     });
 }
 
+/// Regions smaller than this in either dimension are ignored, they are most likely accidental.
+const MIN_ZOOM_REGION_SIZE: f32 = 4.0;
+
+/// Sets the pan and zoom so that `region` fills the viewport.
+/// Both rectangles are in screen coordinates.
+fn zoom_to_region(pan_zoom: &mut PanZoom, viewport_rect: Rect, region: Rect) {
+    if (region.width() < MIN_ZOOM_REGION_SIZE) || (region.height() < MIN_ZOOM_REGION_SIZE) {
+        return;
+    }
+
+    let to_world = |pos: Pos2| (pos - viewport_rect.left_top()) / pan_zoom.zoom - pan_zoom.pan;
+    zoom_to_world_region(
+        pan_zoom,
+        viewport_rect,
+        to_world(region.min),
+        to_world(region.max),
+    );
+}
+
+/// Space left around the contents of a circuit when zooming to fit them, in screen pixels.
+const ZOOM_TO_FIT_MARGIN: f32 = 32.0;
+
+/// Sets the pan and zoom so that `extent` fills the viewport, leaving a small margin.
+fn zoom_to_extent(pan_zoom: &mut PanZoom, viewport_rect: Rect, extent: BoundingBox) {
+    let viewport_rect = viewport_rect.shrink(ZOOM_TO_FIT_MARGIN);
+    if !viewport_rect.is_positive() {
+        return;
+    }
+
+    let to_vec = |pos: digilogic_core::transform::Vec2| vec2(pos.x.to_f32(), pos.y.to_f32());
+    zoom_to_world_region(
+        pan_zoom,
+        viewport_rect,
+        to_vec(extent.min()),
+        to_vec(extent.max()),
+    );
+}
+
+/// Sets the pan and zoom so that the region between `world_min` and `world_max`,
+/// in circuit coordinates, fills `viewport_rect`.
+fn zoom_to_world_region(
+    pan_zoom: &mut PanZoom,
+    viewport_rect: Rect,
+    world_min: Vec2,
+    world_max: Vec2,
+) {
+    let world_size = (world_max - world_min).max(Vec2::splat(1.0));
+    let world_center = (world_min + world_max) / 2.0;
+
+    let zoom = (viewport_rect.width() / world_size.x)
+        .min(viewport_rect.height() / world_size.y)
+        .clamp(
+            linear_to_zoom(MIN_LINEAR_ZOOM),
+            linear_to_zoom(MAX_LINEAR_ZOOM),
+        );
+
+    pan_zoom.zoom = zoom;
+    pan_zoom.pan = (viewport_rect.size() / 2.0) / zoom - world_center;
+}
+
+#[allow(clippy::too_many_arguments)]
 fn update_viewport(
     egui: &Egui,
     ui: &mut Ui,
     renderer: &mut CanvasRenderer,
-    (&circuit, mut pan_zoom, scene, mut canvas): (&CircuitID, Mut<PanZoom>, &Scene, Mut<Canvas>),
+    (&circuit, mut pan_zoom, scene, mut canvas, mut visible_layers): (
+        &CircuitID,
+        Mut<PanZoom>,
+        &Scene,
+        Mut<Canvas>,
+        Mut<VisibleLayers>,
+    ),
     commands: &mut Commands,
     viewport: Entity,
+    active_tool: ActiveTool,
+    ghost: Option<&SymbolShape>,
+    net_labels: &mut NetLabels,
+    symbol_labels: &SymbolLabels,
+    annotations: &mut Annotations,
+    io_symbol_offer: &mut IoSymbolOffer,
+    breadcrumbs: &mut Breadcrumbs,
+    context_menu: &mut ViewportContextMenu,
+    extent: Option<BoundingBox>,
 ) {
+    breadcrumbs.show(ui, viewport);
+
+    let mut zoom_to_fit = false;
+
     TopBottomPanel::bottom("status_bar")
         .show_separator_line(false)
         .show_inside(ui, |ui| {
-            ui.label(format!("{:.0}%", pan_zoom.zoom * pan_zoom.zoom * 100.0));
+            ui.horizontal(|ui| {
+                ui.label(format!("{:.0}%", pan_zoom.zoom * pan_zoom.zoom * 100.0));
+                ui.separator();
+                ui.menu_button("Layers", |ui| {
+                    for (layer, name) in VisibleLayers::NAMED {
+                        let mut visible = visible_layers.contains(layer);
+                        if ui.checkbox(&mut visible, name).changed() {
+                            visible_layers.set(layer, visible);
+                        }
+                    }
+                });
+                ui.separator();
+                zoom_to_fit = ui
+                    .add_enabled(extent.is_some(), Button::new("Zoom to Fit"))
+                    .clicked();
+            });
         });
 
     CentralPanel::default().show_inside(ui, |ui| {
@@ -400,11 +1103,98 @@ fn update_viewport(
             .ui(ui)
             .interact(Sense::click_and_drag());
 
-        if response.dragged_by(PointerButton::Middle) {
+        if active_tool == ActiveTool::Select {
+            context_menu.show(&response, viewport, circuit);
+        }
+
+        if let Some(extent) = extent.filter(|_| zoom_to_fit) {
+            zoom_to_extent(&mut pan_zoom, response.rect, extent);
+        }
+
+        if response.dragged_by(PointerButton::Middle)
+            || (active_tool.pans() && response.dragged_by(PointerButton::Primary))
+        {
             let zoom = pan_zoom.zoom;
             pan_zoom.pan += response.drag_delta() / zoom;
         }
 
+        // middle clicking without dragging stamps the last copied selection
+        if response.clicked_by(PointerButton::Middle) {
+            commands.trigger(StampLastCopied { viewport });
+        }
+
+        // the zoom tool, or Ctrl with the secondary button, zooms into a dragged region
+        let command = ui.input(|state| state.modifiers.command);
+        let zoom_buttons = [
+            (active_tool.zooms(), PointerButton::Primary),
+            (command, PointerButton::Secondary),
+        ];
+        let zoom_dragging = zoom_buttons
+            .iter()
+            .any(|&(enabled, button)| enabled && response.dragged_by(button));
+        let zoom_region_done = zoom_buttons
+            .iter()
+            .any(|&(enabled, button)| enabled && response.drag_stopped_by(button));
+
+        // the press origin is already gone when the drag stops, so it is remembered
+        let zoom_origin_id = response.id.with("zoom_region_origin");
+        if zoom_dragging {
+            if let Some(origin) = ui.input(|state| state.pointer.press_origin()) {
+                ui.data_mut(|data| data.insert_temp(zoom_origin_id, origin));
+            }
+        }
+        let zoom_origin = if zoom_region_done {
+            ui.data_mut(|data| data.remove_temp::<Pos2>(zoom_origin_id))
+        } else if zoom_dragging {
+            ui.data(|data| data.get_temp::<Pos2>(zoom_origin_id))
+        } else {
+            None
+        };
+        let zoom_region = zoom_origin
+            .zip(ui.input(|state| state.pointer.interact_pos()))
+            .map(|(origin, pos)| Rect::from_two_pos(origin, pos));
+
+        if let Some(region) = zoom_region {
+            if zoom_region_done {
+                zoom_to_region(&mut pan_zoom, response.rect, region);
+            } else {
+                ui.painter_at(response.rect).rect(
+                    region,
+                    0.0,
+                    Color32::from_white_alpha(16),
+                    Stroke::new(1.0, Color32::from_white_alpha(128)),
+                );
+            }
+        }
+
+        if visible_layers.contains(VisibleLayers::SYMBOLS) {
+            symbol_labels.show(ui, &response, circuit, &pan_zoom);
+        }
+        if visible_layers.contains(VisibleLayers::ANNOTATIONS) {
+            annotations.show(ui, &response, viewport, circuit, &pan_zoom);
+        }
+        io_symbol_offer.show(ui, &response, viewport, &pan_zoom);
+
+        // symbols dragged from the palette are placed where they are dropped
+        if let Some(kind) = response.dnd_release_payload::<SymbolKind>() {
+            if let Some(mouse_pos) = ui.input(|state| state.pointer.interact_pos()) {
+                let world_pos =
+                    (mouse_pos - response.rect.left_top()) / pan_zoom.zoom - pan_zoom.pan;
+                commands.trigger(digilogic_ux::PlaceSymbol {
+                    circuit,
+                    kind: *kind,
+                    pos: digilogic_core::transform::Vec2 {
+                        x: Fixed::try_from_f32(world_pos.x).unwrap(),
+                        y: Fixed::try_from_f32(world_pos.y).unwrap(),
+                    },
+                });
+            }
+        }
+
+        // a double-click on a net label renames the net instead of being forwarded
+        let label_double_clicked = visible_layers.contains(VisibleLayers::LABELS)
+            && net_labels.show(ui, &response, viewport, circuit, &pan_zoom);
+
         if let Some(mouse_pos) = response.hover_pos() {
             let old_mouse_world_pos =
                 (mouse_pos - response.rect.left_top()) / pan_zoom.zoom - pan_zoom.pan;
@@ -419,6 +1209,21 @@ fn update_viewport(
 
             pan_zoom.pan += new_mouse_world_pos - old_mouse_world_pos;
 
+            if let Some(ghost) = ghost {
+                let transform = vello::kurbo::Affine::translate((
+                    (new_mouse_world_pos.x + pan_zoom.pan.x) as f64,
+                    (new_mouse_world_pos.y + pan_zoom.pan.y) as f64,
+                ))
+                .then_scale(pan_zoom.zoom as f64)
+                .then_translate((response.rect.left() as f64, response.rect.top() as f64).into());
+
+                ghost.paint_outline(
+                    &ui.painter_at(response.rect),
+                    transform,
+                    Stroke::new(2.0, Color32::from_white_alpha(128)),
+                );
+            }
+
             // note: this will only happen if the mouse is hovering the viewport
             forward_hover_events(
                 ui,
@@ -427,6 +1232,7 @@ fn update_viewport(
                 viewport,
                 circuit,
                 new_mouse_world_pos,
+                !label_double_clicked,
             );
         }
     });
@@ -439,6 +1245,7 @@ fn forward_hover_events(
     viewport: Entity,
     circuit: CircuitID,
     world_mouse_pos: Vec2,
+    forward_double_clicks: bool,
 ) {
     let pos = digilogic_core::transform::Vec2 {
         x: Fixed::try_from_f32(world_mouse_pos.x).unwrap(),
@@ -484,6 +1291,19 @@ fn forward_hover_events(
             );
         }
 
+        if forward_double_clicks && response.double_clicked_by(egui_button) {
+            commands.trigger_targets(
+                digilogic_ux::DoubleClickEvent {
+                    viewport,
+                    circuit,
+                    pos,
+                    button: ux_button,
+                    modifiers,
+                },
+                viewport,
+            );
+        }
+
         let drag_type = match (
             response.drag_started_by(egui_button),
             response.dragged_by(egui_button),
@@ -516,8 +1336,21 @@ fn forward_hover_events(
     }
 }
 
-type ViewportQuery<'w, 's> =
-    Query<'w, 's, (Read<CircuitID>, Write<PanZoom>, Read<Scene>, Write<Canvas>), With<Viewport>>;
+type ViewportQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Read<CircuitID>,
+        Write<PanZoom>,
+        Read<Scene>,
+        Write<Canvas>,
+        Write<VisibleLayers>,
+    ),
+    With<Viewport>,
+>;
+
+type TabCircuitQuery<'w, 's> =
+    Query<'w, 's, (Read<Name>, Has<Modified>, Option<Read<CircuitStats>>), With<Circuit>>;
 
 //#[allow(clippy::type_complexity)]
 #[derive(SystemParam)]
@@ -526,22 +1359,116 @@ struct TabViewer<'w, 's> {
     egui: Res<'w, Egui>,
     renderer: NonSendMut<'w, CanvasRenderer>,
     viewports: ViewportQuery<'w, 's>,
-    circuits: Query<'w, 's, Read<Name>, With<Circuit>>,
-    open_windows: Res<'w, OpenWindows>,
+    circuits: TabCircuitQuery<'w, 's>,
+    open_windows: ResMut<'w, OpenWindows>,
+    active_tool: ResMut<'w, ActiveTool>,
+    placement_kind: ResMut<'w, PlacementKind>,
+    symbol_registry: Res<'w, SymbolRegistry>,
+    symbol_shapes: Res<'w, SymbolShapes>,
+    thumbnails: ResMut<'w, SymbolThumbnails>,
+    symbol_palettes: Query<'w, 's, (), With<SymbolPaletteTab>>,
+    diagnostics: Diagnostics<'w, 's>,
+    console: Console<'w, 's>,
+    extension_panels: ExtensionPanels<'w, 's>,
+    profiler: Profiler<'w, 's>,
+    net_labels: NetLabels<'w, 's>,
+    symbol_labels: SymbolLabels<'w, 's>,
+    annotations: Annotations<'w, 's>,
+    io_symbol_offer: IoSymbolOffer<'w, 's>,
+    breadcrumbs: Breadcrumbs<'w, 's>,
+    context_menu: ViewportContextMenu<'w, 's>,
+    detach_requests: Local<'s, Vec<Entity>>,
+}
+
+/// The shape following the cursor while placing a symbol.
+fn placement_ghost<'a>(
+    active_tool: ActiveTool,
+    placement_kind: PlacementKind,
+    symbol_registry: &SymbolRegistry,
+    symbol_shapes: &'a SymbolShapes,
+) -> Option<&'a SymbolShape> {
+    if active_tool != ActiveTool::PlaceSymbol {
+        return None;
+    }
+
+    let kind = placement_kind.0?;
+    let def = symbol_registry.iter().find(|def| def.kind() == kind)?;
+    symbol_shapes.get(def.kind(), def.shape())
 }
 
 impl egui_dock::TabViewer for TabViewer<'_, '_> {
     type Tab = Entity;
 
     fn title(&mut self, tab: &mut Self::Tab) -> WidgetText {
-        let (&circuit, _, _, _) = self.viewports.get(*tab).expect("invalid viewport ID");
-        let name = self.circuits.get(circuit.0).expect("invalid circuit ID");
-        name.0.as_str().into()
+        if self.symbol_palettes.contains(*tab) {
+            return "Symbols".into();
+        }
+        if self.diagnostics.is_tab(*tab) {
+            return self.diagnostics.title().into();
+        }
+        if self.console.is_tab(*tab) {
+            return "Console".into();
+        }
+        if self.extension_panels.is_tab(*tab) {
+            return self.extension_panels.title(*tab).into();
+        }
+        if self.profiler.is_tab(*tab) {
+            return "Profiler".into();
+        }
+
+        let (&circuit, _, _, _, _) = self.viewports.get(*tab).expect("invalid viewport ID");
+        let (name, modified, _) = self.circuits.get(circuit.0).expect("invalid circuit ID");
+        if modified {
+            format!("{} •", name.0).into()
+        } else {
+            name.0.as_str().into()
+        }
     }
 
     fn ui(&mut self, ui: &mut Ui, tab: &mut Self::Tab) {
         ui.add_enabled_ui(!self.open_windows.any(), |ui| {
+            if self.symbol_palettes.contains(*tab) {
+                show_symbol_palette(
+                    ui,
+                    &self.egui,
+                    &mut self.renderer,
+                    &self.symbol_registry,
+                    &self.symbol_shapes,
+                    &mut self.thumbnails,
+                    &mut self.active_tool,
+                    &mut self.placement_kind,
+                );
+                return;
+            }
+            if self.diagnostics.is_tab(*tab) {
+                self.diagnostics.show(ui);
+                return;
+            }
+            if self.console.is_tab(*tab) {
+                self.console.show(ui);
+                return;
+            }
+            if self.extension_panels.is_tab(*tab) {
+                self.extension_panels.show(ui, *tab);
+                return;
+            }
+            if self.profiler.is_tab(*tab) {
+                self.profiler.show(ui);
+                return;
+            }
+
             let viewport_item = self.viewports.get_mut(*tab).expect("invalid viewport ID");
+            let extent = self
+                .circuits
+                .get(viewport_item.0 .0)
+                .ok()
+                .and_then(|(_, _, stats)| stats?.extent);
+            let ghost = placement_ghost(
+                *self.active_tool,
+                *self.placement_kind,
+                &self.symbol_registry,
+                &self.symbol_shapes,
+            );
 
             update_viewport(
                 &self.egui,
@@ -550,6 +1477,15 @@ impl egui_dock::TabViewer for TabViewer<'_, '_> {
                 viewport_item,
                 &mut self.commands,
                 *tab,
+                *self.active_tool,
+                ghost,
+                &mut self.net_labels,
+                &self.symbol_labels,
+                &mut self.annotations,
+                &mut self.io_symbol_offer,
+                &mut self.breadcrumbs,
+                &mut self.context_menu,
+                extent,
             );
         });
     }
@@ -558,7 +1494,34 @@ impl egui_dock::TabViewer for TabViewer<'_, '_> {
         Id::new(*tab)
     }
 
+    fn context_menu(
+        &mut self,
+        ui: &mut Ui,
+        tab: &mut Self::Tab,
+        _surface: SurfaceIndex,
+        _node: NodeIndex,
+    ) {
+        if ui.button("Open in New Window").clicked() {
+            self.detach_requests.push(*tab);
+            ui.close_menu();
+        }
+    }
+
     fn on_close(&mut self, tab: &mut Self::Tab) -> bool {
+        if let Ok((&circuit, _, _, _, _)) = self.viewports.get(*tab) {
+            // Other tabs showing the same circuit keep its changes in view.
+            let last_tab = self
+                .viewports
+                .iter()
+                .filter(|&(&other, _, _, _, _)| other == circuit)
+                .count()
+                == 1;
+            if last_tab && matches!(self.circuits.get(circuit.0), Ok((_, true, _))) {
+                self.open_windows.unsaved_changes = Some(CloseAction::CloseTab(*tab));
+                return false;
+            }
+        }
+
         self.commands.entity(*tab).despawn();
         true
     }
@@ -577,6 +1540,60 @@ fn update_tabs(mut dock_state: NonSendMut<DockState<Entity>>, mut tab_viewer: Ta
             .style(egui_dock::Style::from_egui(context.style().as_ref()))
             .show_inside(ui, &mut tab_viewer);
     });
+
+    // Tabs dragged out of the dock area end up in a floating dock window,
+    // move those into their own OS window instead.
+    let floating_tabs = dock_state
+        .iter_all_tabs()
+        .filter(|((surface, _), _)| !surface.is_main())
+        .map(|(_, &tab)| tab)
+        .collect::<Vec<_>>();
+    tab_viewer.detach_requests.extend(floating_tabs);
+
+    for viewport in tab_viewer.detach_requests.drain(..) {
+        if let Some(index) = dock_state.find_tab(&viewport) {
+            dock_state.remove_tab(index);
+            tab_viewer
+                .commands
+                .entity(viewport)
+                .insert(DetachedViewport);
+        }
+    }
+}
+
+fn update_detached_viewports(
+    mut dock_state: NonSendMut<DockState<Entity>>,
+    detached_viewports: Query<Entity, With<DetachedViewport>>,
+    mut tab_viewer: TabViewer,
+) {
+    let context = tab_viewer.egui.context.clone();
+
+    for mut viewport in detached_viewports.iter() {
+        let title = egui_dock::TabViewer::title(&mut tab_viewer, &mut viewport);
+        let builder = ViewportBuilder::default()
+            .with_title(title.text())
+            .with_inner_size([800.0, 600.0]);
+
+        let close_requested =
+            context.show_viewport_immediate(detached_viewport_id(viewport), builder, |ctx, _| {
+                CentralPanel::default()
+                    .frame(Frame::none())
+                    .show(ctx, |ui| {
+                        egui_dock::TabViewer::ui(&mut tab_viewer, ui, &mut viewport);
+                    });
+
+                ctx.input(|state| state.viewport().close_requested())
+            });
+
+        // Closing the window docks the viewport again instead of closing it.
+        if close_requested {
+            tab_viewer
+                .commands
+                .entity(viewport)
+                .remove::<DetachedViewport>();
+            dock_state.main_surface_mut().push_to_first_leaf(viewport);
+        }
+    }
 }
 
 #[cfg(feature = "inspector")]
@@ -632,31 +1649,54 @@ impl bevy_app::Plugin for UiPlugin {
         app.insert_non_send_resource(DockState::<Entity>::new(Vec::new()));
         app.insert_non_send_resource(CanvasRenderer::new(&self.render_state));
         app.insert_resource(Egui::new(&self.context, &self.render_state));
-        app.insert_resource(SymbolShapes(Vec::new()));
+        app.init_resource::<SymbolShapes>();
+        app.init_resource::<SymbolThumbnails>();
         app.insert_resource(VelloFont(Font::new(
             vello::peniko::Blob::new(Arc::new(FONT_BYTES)),
             0,
         )));
         app.init_resource::<OpenWindows>();
-        app.register_type::<Viewport>();
+        app.init_resource::<NetRenaming>();
+        app.init_resource::<LastCopied>();
+        app.register_type::<Viewport>()
+            .register_type::<DetachedViewport>()
+            .register_type::<SymbolPaletteTab>();
 
         app.add_systems(bevy_app::Startup, init_symbol_shapes);
+        app.add_systems(
+            bevy_app::PreUpdate,
+            (
+                sync_gate_style.run_if(resource_changed::<Settings>),
+                invalidate_thumbnails.run_if(resource_changed::<SymbolShapes>),
+            )
+                .chain(),
+        );
 
         app.add_systems(
             bevy_app::Update,
-            (draw_symbols, draw_ports, draw_wires).in_set(DrawSet),
+            (
+                draw_graphics,
+                draw_symbols,
+                draw_ports,
+                draw_wires,
+                draw_waypoints,
+                draw_alignment_guides,
+                draw_selection_boxes,
+                draw_port_stubs,
+            )
+                .in_set(DrawSet),
         );
         app.add_systems(
             bevy_app::Update,
             draw_bounding_boxes
                 .in_set(DrawSet)
-                .run_if(|app_state: Res<AppSettings>| app_state.show_bounding_boxes),
+                .run_if(|settings: Res<Settings>| settings.show_bounding_boxes),
         );
         app.add_systems(
             bevy_app::Update,
             draw_routing_graph
                 .in_set(DrawSet)
-                .run_if(|app_state: Res<AppSettings>| app_state.show_routing_graph),
+                .run_if(|settings: Res<Settings>| settings.show_routing_graph),
         );
         app.add_systems(bevy_app::Update, combine_scenes.after(DrawSet));
 
@@ -672,7 +1712,32 @@ impl bevy_app::Plugin for UiPlugin {
             update_tabs
                 .after(combine_scenes)
                 .after(MenuSet)
-                .after(ExplorerSet),
+                .after(ExplorerSet)
+                .run_if(not(no_viewports_open)),
+        );
+
+        app.add_systems(bevy_app::Update, handle_clipboard.after(MenuSet));
+        app.observe(stamp_last_copied);
+        app.observe(start_simulation_from_script);
+        app.add_systems(bevy_app::Update, handle_nudging.after(MenuSet));
+        app.add_systems(
+            bevy_app::Update,
+            handle_rename_shortcut.after(MenuSet).before(update_tabs),
+        );
+
+        app.add_systems(
+            bevy_app::Update,
+            update_detached_viewports
+                .after(update_tabs)
+                .run_if(|viewports: Query<(), With<DetachedViewport>>| !viewports.is_empty()),
+        );
+
+        app.add_systems(
+            bevy_app::Update,
+            update_welcome_page
+                .after(MenuSet)
+                .after(ExplorerSet)
+                .run_if(no_viewports_open),
         );
 
         app.add_systems(
@@ -681,9 +1746,25 @@ impl bevy_app::Plugin for UiPlugin {
         );
 
         app.add_plugins(SettingsPlugin)
+            .add_plugins(PropertiesPlugin)
+            .add_plugins(BitAssignmentPlugin)
             .add_plugins(ExplorerPlugin)
+            .add_plugins(HierarchyPlugin)
+            .add_plugins(GoToPlugin)
+            .add_plugins(TimingDiagramPlugin)
+            .add_plugins(AnnotationsPlugin)
+            .add_plugins(IoStubPlugin)
+            .add_plugins(DiagnosticsPlugin)
+            .add_plugins(ConsolePlugin)
+            .add_plugins(ExtensionUiPlugin)
+            .add_plugins(ProfilerPlugin)
+            .add_plugins(UnsavedChangesPlugin)
+            .add_plugins(ErrorsPlugin)
             .add_plugins(PalettePlugin);
 
+        #[cfg(not(target_arch = "wasm32"))]
+        app.add_plugins(SessionPlugin).add_plugins(FileWatchPlugin);
+
         #[cfg(feature = "inspector")]
         {
             app.add_plugins(bevy_inspector_egui::DefaultInspectorConfigPlugin);
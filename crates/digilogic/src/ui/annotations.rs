@@ -0,0 +1,229 @@
+use super::{update_tabs, PanZoom};
+use aery::prelude::*;
+use bevy_ecs::prelude::*;
+use bevy_ecs::system::lifetimeless::Read;
+use bevy_ecs::system::SystemParam;
+use digilogic_core::components::*;
+use digilogic_core::transform::GlobalTransform;
+use digilogic_core::Fixed;
+use digilogic_ux::{EditAnnotation, SetAnnotation};
+use egui::*;
+
+const MIN_FONT_SIZE: f32 = 4.0;
+const MAX_FONT_SIZE: f32 = 96.0;
+
+/// The annotation being edited, the viewport it is edited in, and the edited values.
+#[derive(Default, Resource)]
+struct AnnotationEditing {
+    target: Option<(Entity, Entity)>,
+    original: Annotation,
+    text: String,
+    font_size: f32,
+    color: Color32,
+    request_focus: bool,
+}
+
+impl AnnotationEditing {
+    fn value(&self) -> Annotation {
+        Annotation {
+            text: self.text.as_str().into(),
+            font_size: Fixed::try_from_f32(self.font_size).unwrap_or(Annotation::DEFAULT_FONT_SIZE),
+            color: self.color.to_srgba_unmultiplied(),
+        }
+    }
+}
+
+fn to_color32(color: [u8; 4]) -> Color32 {
+    let [r, g, b, a] = color;
+    Color32::from_rgba_unmultiplied(r, g, b, a)
+}
+
+/// Opens the editor for annotations that were placed or double-clicked.
+/// An annotation that is still being edited keeps its changes.
+fn start_annotation_editing(
+    mut events: EventReader<EditAnnotation>,
+    annotations: Query<&Annotation>,
+    mut editing: ResMut<AnnotationEditing>,
+    mut set_events: EventWriter<SetAnnotation>,
+) {
+    for event in events.read() {
+        if let Some((_, previous)) = editing.target {
+            if previous != event.annotation {
+                set_events.send(SetAnnotation {
+                    annotation: previous,
+                    value: editing.value(),
+                });
+            }
+        }
+
+        let Ok(annotation) = annotations.get(event.annotation).cloned() else {
+            continue;
+        };
+
+        *editing = AnnotationEditing {
+            target: Some((event.viewport, event.annotation)),
+            text: annotation.text.to_string(),
+            font_size: annotation.font_size.to_f32(),
+            color: to_color32(annotation.color),
+            original: annotation,
+            request_focus: true,
+        };
+    }
+}
+
+type AnnotationQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        Read<Annotation>,
+        Read<GlobalTransform>,
+        Has<Selected>,
+        Has<Hovered>,
+    ),
+>;
+
+#[derive(SystemParam)]
+pub(super) struct Annotations<'w, 's> {
+    circuits: Query<'w, 's, ((), Relations<Child>), With<Circuit>>,
+    annotations: AnnotationQuery<'w, 's>,
+    editing: ResMut<'w, AnnotationEditing>,
+    set_events: EventWriter<'w, SetAnnotation>,
+}
+
+impl Annotations<'_, '_> {
+    /// Draws the annotations of `circuit` and the editor if one of them is edited.
+    pub(super) fn show(
+        &mut self,
+        ui: &Ui,
+        response: &Response,
+        viewport: Entity,
+        circuit: CircuitID,
+        pan_zoom: &PanZoom,
+    ) {
+        let Ok((_, edges)) = self.circuits.get(circuit.0) else {
+            return;
+        };
+
+        let editing = self
+            .editing
+            .target
+            .filter(|&(target_viewport, _)| target_viewport == viewport)
+            .map(|(_, annotation)| annotation);
+
+        let painter = ui.painter_at(response.rect);
+        let highlight = ui.visuals().selection.stroke;
+        let mut editor_anchor = None;
+
+        edges.join::<Child>(&self.annotations).for_each(
+            |(entity, annotation, transform, selected, hovered)| {
+                let position = transform.translation;
+                let position = Vec2::new(position.x.to_f32(), position.y.to_f32());
+                let anchor = response.rect.left_top() + (position + pan_zoom.pan) * pan_zoom.zoom;
+
+                // the annotation being edited shows the edited values
+                let (text, font_size, color) = if editing == Some(entity) {
+                    editor_anchor = Some(anchor);
+                    (
+                        self.editing.text.clone(),
+                        self.editing.font_size,
+                        self.editing.color,
+                    )
+                } else {
+                    (
+                        annotation.text.to_string(),
+                        annotation.font_size.to_f32(),
+                        to_color32(annotation.color),
+                    )
+                };
+
+                let font = FontId::proportional((font_size * pan_zoom.zoom).max(1.0));
+                let galley = painter.layout_no_wrap(text, font, color);
+                let rect = Rect::from_min_size(anchor, galley.size());
+                painter.galley(anchor, galley, color);
+
+                if selected {
+                    painter.rect_stroke(rect.expand(2.0), 2.0, highlight);
+                } else if hovered {
+                    painter.rect_stroke(rect.expand(2.0), 2.0, Stroke::new(1.0, color));
+                }
+            },
+        );
+
+        match (editing, editor_anchor) {
+            (Some(annotation), Some(anchor)) => self.show_editor(ui, viewport, annotation, anchor),
+            (Some(annotation), None) if !self.annotations.contains(annotation) => {
+                // the annotation was deleted in the meantime
+                self.editing.target = None;
+            }
+            _ => (),
+        }
+    }
+
+    fn show_editor(&mut self, ui: &Ui, viewport: Entity, annotation: Entity, anchor: Pos2) {
+        let editing = &mut *self.editing;
+        let mut done = false;
+        let mut cancel = false;
+
+        Area::new(Id::new(("annotation_editor", viewport)))
+            .fixed_pos(anchor - Vec2::new(0.0, 4.0))
+            .pivot(Align2::LEFT_BOTTOM)
+            .order(Order::Foreground)
+            .show(ui.ctx(), |ui| {
+                Frame::popup(ui.style()).show(ui, |ui| {
+                    let edit = ui.add(
+                        TextEdit::multiline(&mut editing.text)
+                            .hint_text("Comment")
+                            .desired_rows(3)
+                            .desired_width(200.0),
+                    );
+                    if editing.request_focus {
+                        edit.request_focus();
+                        editing.request_focus = false;
+                    }
+
+                    ui.horizontal(|ui| {
+                        ui.label("Size");
+                        ui.add(
+                            DragValue::new(&mut editing.font_size)
+                                .range(MIN_FONT_SIZE..=MAX_FONT_SIZE)
+                                .speed(0.5),
+                        );
+                        ui.label("Color");
+                        ui.color_edit_button_srgba(&mut editing.color);
+
+                        done |= ui.button("Done").clicked();
+                    });
+
+                    // Ctrl+Enter keeps the changes, Escape discards them
+                    ui.input(|state| {
+                        done |= state.modifiers.command && state.key_pressed(Key::Enter);
+                        cancel |= state.key_pressed(Key::Escape);
+                    });
+                });
+            });
+
+        if done || cancel {
+            let value = if cancel {
+                editing.original.clone()
+            } else {
+                editing.value()
+            };
+            self.set_events.send(SetAnnotation { annotation, value });
+            editing.target = None;
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct AnnotationsPlugin;
+
+impl bevy_app::Plugin for AnnotationsPlugin {
+    fn build(&self, app: &mut bevy_app::App) {
+        app.init_resource::<AnnotationEditing>();
+        app.add_systems(
+            bevy_app::Update,
+            start_annotation_editing.before(update_tabs),
+        );
+    }
+}
@@ -780,7 +780,6 @@ pub(crate) use {
 
 #[cfg(test)]
 mod bez_path_test {
-    use super::*;
     use vello::kurbo::PathEl::*;
 
     #[test]
@@ -0,0 +1,155 @@
+use super::{Egui, OpenWindows};
+use aery::prelude::*;
+use bevy_ecs::prelude::*;
+use bevy_ecs::system::lifetimeless::Read;
+use digilogic_core::components::*;
+use digilogic_ux::{SelectionSet, SetEndpointBits};
+use egui::*;
+
+/// The net whose bit assignment is edited, with one row of text per endpoint.
+#[derive(Default, Resource)]
+struct BitAssignmentDialog {
+    net: Option<Entity>,
+    rows: Vec<(Entity, String, String)>,
+}
+
+type BusQuery<'w, 's> = Query<'w, 's, ((Read<Name>, Read<BitWidth>), Relations<Child>), With<Net>>;
+
+type TapQuery<'w, 's> =
+    Query<'w, 's, (Entity, Option<Read<Bits>>, Option<Read<PortID>>), With<Endpoint>>;
+
+/// An empty text assigns all bits of the net.
+fn parse_row(text: &str, width: BitWidth) -> Result<Option<Bits>, BitsError> {
+    if text.trim().is_empty() {
+        return Ok(None);
+    }
+
+    let bits = Bits::parse(text)?;
+    bits.validate(width)?;
+    Ok(Some(bits))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_bit_assignment_dialog(
+    egui: Res<Egui>,
+    mut open_windows: ResMut<OpenWindows>,
+    mut dialog: ResMut<BitAssignmentDialog>,
+    selection: Res<SelectionSet>,
+    nets: BusQuery,
+    endpoints: TapQuery,
+    ports: Query<&Name, With<Port>>,
+    mut bits_events: EventWriter<SetEndpointBits>,
+) {
+    if !open_windows.bit_assignment {
+        dialog.net = None;
+        return;
+    }
+
+    if dialog.net.is_none() {
+        let Some(net) = selection.iter().find(|&entity| nets.contains(entity)) else {
+            open_windows.bit_assignment = false;
+            return;
+        };
+
+        let (_, edges) = nets.get(net).unwrap();
+        let mut rows = Vec::new();
+        edges
+            .join::<Child>(&endpoints)
+            .for_each(|(endpoint, bits, port)| {
+                let label = port
+                    .and_then(|port| ports.get(port.0).ok())
+                    .map(|name| name.0.to_string())
+                    .unwrap_or_else(|| "Unconnected".to_owned());
+                let text = bits.map(Bits::to_string).unwrap_or_default();
+                rows.push((endpoint, label, text));
+            });
+
+        dialog.net = Some(net);
+        dialog.rows = rows;
+    }
+
+    // the net may have been deleted while the dialog was open
+    let Some(((name, &width), _)) = dialog.net.and_then(|net| nets.get(net).ok()) else {
+        dialog.net = None;
+        open_windows.bit_assignment = false;
+        return;
+    };
+
+    let mut open = true;
+    let mut apply = false;
+    let mut close = false;
+    let mut all_valid = true;
+
+    Window::new("Bit Assignment")
+        .open(&mut open)
+        .collapsible(false)
+        .resizable(false)
+        .show(&egui.context, |ui| {
+            ui.label(format!("{} is {} bits wide.", name.0, width.0));
+            ui.label("Leave a field empty to carry all bits. Ranges like 3:0 are allowed.");
+
+            ui.separator();
+
+            Grid::new("bit_assignment_grid")
+                .num_columns(3)
+                .show(ui, |ui| {
+                    for (_, label, text) in &mut dialog.rows {
+                        ui.label(label.as_str());
+                        ui.add(TextEdit::singleline(text).desired_width(96.0));
+                        match parse_row(text, width) {
+                            Ok(_) => {
+                                ui.label("");
+                            }
+                            Err(err) => {
+                                all_valid = false;
+                                ui.colored_label(ui.visuals().error_fg_color, err.to_string());
+                            }
+                        }
+                        ui.end_row();
+                    }
+                });
+
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                ui.add_enabled_ui(all_valid, |ui| {
+                    if ui.button("OK").clicked() {
+                        apply = true;
+                        close = true;
+                    }
+                    if ui.button("Apply").clicked() {
+                        apply = true;
+                    }
+                });
+                if ui.button("Cancel").clicked() {
+                    close = true;
+                }
+            });
+        });
+
+    if apply {
+        for (endpoint, _, text) in &dialog.rows {
+            if let Ok(bits) = parse_row(text, width) {
+                bits_events.send(SetEndpointBits {
+                    endpoint: *endpoint,
+                    bits,
+                });
+            }
+        }
+    }
+
+    if close || !open {
+        dialog.net = None;
+        open_windows.bit_assignment = false;
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct BitAssignmentPlugin;
+
+impl bevy_app::Plugin for BitAssignmentPlugin {
+    fn build(&self, app: &mut bevy_app::App) {
+        app.init_resource::<BitAssignmentDialog>();
+        app.add_systems(bevy_app::Update, update_bit_assignment_dialog);
+    }
+}
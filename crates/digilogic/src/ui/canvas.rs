@@ -30,6 +30,7 @@ pub struct Canvas {
     texture: Texture,
     texture_view: TextureView,
     texture_id: egui::TextureId,
+    filter: FilterMode,
 }
 
 const TEXTURE_FILTER: FilterMode = FilterMode::Nearest;
@@ -62,18 +63,24 @@ fn create_texture(
 
 impl Canvas {
     pub fn create(render_state: &egui_wgpu::RenderState) -> Self {
+        Self::create_with_filter(render_state, TEXTURE_FILTER)
+    }
+
+    /// Creates a canvas sampled with `filter`, for canvases not shown at their pixel size.
+    pub fn create_with_filter(render_state: &egui_wgpu::RenderState, filter: FilterMode) -> Self {
         let (texture, texture_view) = create_texture(render_state, 1, 1);
 
         let texture_id = render_state.renderer.write().register_native_texture(
             &render_state.device,
             &texture_view,
-            TEXTURE_FILTER,
+            filter,
         );
 
         Self {
             texture,
             texture_view,
             texture_id,
+            filter,
         }
     }
 
@@ -92,6 +99,11 @@ impl Canvas {
         self.texture_id
     }
 
+    /// Unregisters the texture from egui, its ID must not be used anymore.
+    pub fn free(self, render_state: &egui_wgpu::RenderState) {
+        render_state.renderer.write().free_texture(&self.texture_id);
+    }
+
     pub fn resize(&mut self, render_state: &egui_wgpu::RenderState, width: u32, height: u32) {
         if (self.width() == width) && (self.height() == height) {
             return;
@@ -105,7 +117,7 @@ impl Canvas {
             .update_egui_texture_from_wgpu_texture(
                 &render_state.device,
                 &self.texture_view,
-                TEXTURE_FILTER,
+                self.filter,
                 self.texture_id,
             );
     }
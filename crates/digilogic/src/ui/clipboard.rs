@@ -0,0 +1,136 @@
+use super::{Egui, OpenWindows};
+use bevy_ecs::prelude::*;
+use digilogic_core::components::{CircuitID, Viewport};
+use digilogic_core::fixed;
+use digilogic_core::transform::Vec2;
+use digilogic_serde::CircuitFragments;
+use digilogic_ux::{CursorPosition, GridSize, SelectionSet};
+use egui::{Event, Key};
+use egui_dock::DockState;
+
+/// The selection copied most recently, stamped by [`StampLastCopied`].
+#[derive(Debug, Default, Resource)]
+pub(super) struct LastCopied(Option<String>);
+
+/// Pastes the most recently copied selection at the cursor of a viewport again,
+/// so a block can be placed repeatedly without going through the clipboard.
+#[derive(Event, Debug)]
+pub(super) struct StampLastCopied {
+    pub(super) viewport: Entity,
+}
+
+/// Pastes a fragment with its top left corner at the grid point closest to `position`,
+/// and selects the pasted entities.
+/// Pasted symbols are numbered after the existing ones, so repeated pastes count up.
+fn paste_at(
+    fragments: &mut CircuitFragments,
+    selection: &mut SelectionSet,
+    circuit: CircuitID,
+    contents: &str,
+    mut position: Vec2,
+    grid_size: GridSize,
+) {
+    if grid_size.0 > fixed!(0) {
+        position = position.round_to_multiple(grid_size.0);
+    }
+
+    match fragments.paste(circuit, contents, position) {
+        Ok(entities) => {
+            selection.clear();
+            selection.select_all(entities);
+        }
+        Err(err) => bevy_log::warn!("ignoring clipboard contents: {err}"),
+    }
+}
+
+/// Copies the selection of the focused viewport and pastes fragments at the cursor.
+/// V stamps the most recently copied selection at the cursor.
+#[allow(clippy::too_many_arguments)]
+pub(super) fn handle_clipboard(
+    egui: Res<Egui>,
+    open_windows: Res<OpenWindows>,
+    mut dock_state: NonSendMut<DockState<Entity>>,
+    viewports: Query<(&CircuitID, &CursorPosition), With<Viewport>>,
+    grid_size: Res<GridSize>,
+    mut selection: ResMut<SelectionSet>,
+    mut last_copied: ResMut<LastCopied>,
+    mut fragments: CircuitFragments,
+) {
+    if open_windows.any() || egui.context.wants_keyboard_input() {
+        return;
+    }
+
+    let Some((_, &mut viewport)) = dock_state.find_active_focused() else {
+        return;
+    };
+    let Ok((&circuit, cursor_position)) = viewports.get(viewport) else {
+        return;
+    };
+
+    let events = egui.context.input(|state| state.events.clone());
+    for event in events {
+        match event {
+            Event::Copy => {
+                if let Some(contents) = fragments.copy(circuit, selection.iter()) {
+                    egui.context.copy_text(contents.clone());
+                    last_copied.0 = Some(contents);
+                }
+            }
+            Event::Paste(contents) => {
+                paste_at(
+                    &mut fragments,
+                    &mut selection,
+                    circuit,
+                    &contents,
+                    cursor_position.0,
+                    *grid_size,
+                );
+            }
+            // held keys would stamp on top of the previous block
+            Event::Key {
+                key: Key::V,
+                pressed: true,
+                repeat: false,
+                modifiers,
+                ..
+            } if modifiers.is_none() => {
+                if let Some(contents) = &last_copied.0 {
+                    paste_at(
+                        &mut fragments,
+                        &mut selection,
+                        circuit,
+                        contents,
+                        cursor_position.0,
+                        *grid_size,
+                    );
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+pub(super) fn stamp_last_copied(
+    trigger: Trigger<StampLastCopied>,
+    last_copied: Res<LastCopied>,
+    viewports: Query<(&CircuitID, &CursorPosition), With<Viewport>>,
+    grid_size: Res<GridSize>,
+    mut selection: ResMut<SelectionSet>,
+    mut fragments: CircuitFragments,
+) {
+    let Some(contents) = &last_copied.0 else {
+        return;
+    };
+    let Ok((&circuit, cursor_position)) = viewports.get(trigger.event().viewport) else {
+        return;
+    };
+
+    paste_at(
+        &mut fragments,
+        &mut selection,
+        circuit,
+        contents,
+        cursor_position.0,
+        *grid_size,
+    );
+}
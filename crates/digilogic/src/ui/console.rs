@@ -0,0 +1,194 @@
+use super::{update_tabs, MenuSet};
+use crate::Settings;
+use bevy_ecs::prelude::*;
+use bevy_ecs::system::SystemParam;
+use bevy_reflect::Reflect;
+use digilogic_core::components::{CircuitID, Viewport};
+use digilogic_core::resources::Project;
+use digilogic_script::{ConsoleLineKind, RunScript, ScriptConsole};
+use egui::*;
+use egui_dock::{DockState, NodeIndex};
+
+const ERROR_COLOR: Color32 = Color32::from_rgb(240, 13, 13);
+
+/// Marks the dock tab that runs scripts and shows what they printed.
+#[derive(Debug, Default, Clone, Copy, Component, Reflect)]
+pub struct ConsoleTab;
+
+/// The circuit of the viewport that was focused last, which scripts are run on.
+/// Typing into the console takes the focus away from the viewport, so it is remembered.
+#[derive(Debug, Default, Resource)]
+struct ScriptCircuit(Option<CircuitID>);
+
+#[derive(SystemParam)]
+pub(super) struct Console<'w, 's> {
+    commands: Commands<'w, 's>,
+    tabs: Query<'w, 's, (), With<ConsoleTab>>,
+    console: ResMut<'w, ScriptConsole>,
+    circuit: Res<'w, ScriptCircuit>,
+    input: Local<'s, String>,
+}
+
+impl Console<'_, '_> {
+    #[inline]
+    pub(super) fn is_tab(&self, tab: Entity) -> bool {
+        self.tabs.contains(tab)
+    }
+
+    /// Lists the console lines above a line to type scripts into.
+    pub(super) fn show(&mut self, ui: &mut Ui) {
+        TopBottomPanel::bottom("console_input")
+            .frame(Frame::none().inner_margin(Margin::symmetric(0.0, 4.0)))
+            .show_inside(ui, |ui| {
+                ui.horizontal(|ui| {
+                    let enabled = self.circuit.0.is_some();
+                    if ui.button("Clear").clicked() {
+                        self.console.clear();
+                    }
+
+                    let hint = if enabled {
+                        "Type a script and press Enter"
+                    } else {
+                        "Open a circuit to run scripts on"
+                    };
+                    let input = ui.add_enabled(
+                        enabled,
+                        TextEdit::singleline(&mut *self.input)
+                            .hint_text(hint)
+                            .font(TextStyle::Monospace)
+                            .desired_width(f32::INFINITY),
+                    );
+                    if input.lost_focus() && ui.input(|state| state.key_pressed(Key::Enter)) {
+                        self.run_input();
+                        input.request_focus();
+                    }
+                });
+            });
+
+        ScrollArea::vertical()
+            .auto_shrink(false)
+            .stick_to_bottom(true)
+            .show(ui, |ui| {
+                for line in &self.console.lines {
+                    let text = RichText::new(&line.text).monospace();
+                    let text = match line.kind {
+                        ConsoleLineKind::Input => text.weak(),
+                        ConsoleLineKind::Output => text,
+                        ConsoleLineKind::Error => text.color(ERROR_COLOR),
+                    };
+                    ui.label(text);
+                }
+            });
+    }
+
+    fn run_input(&mut self) {
+        let Some(circuit) = self.circuit.0 else {
+            return;
+        };
+        let source = std::mem::take(&mut *self.input);
+        if source.trim().is_empty() {
+            return;
+        }
+
+        self.console
+            .push(ConsoleLineKind::Input, format!("> {source}"));
+        self.commands.trigger(RunScript { circuit, source });
+    }
+}
+
+/// Remembers the circuit of the focused viewport, falling back to the root circuit.
+fn track_script_circuit(
+    mut dock_state: NonSendMut<DockState<Entity>>,
+    viewports: Query<&CircuitID, With<Viewport>>,
+    project: Option<Res<Project>>,
+    mut script_circuit: ResMut<ScriptCircuit>,
+) {
+    let focused = dock_state
+        .find_active_focused()
+        .and_then(|(_, tab)| viewports.get(*tab).ok())
+        .copied();
+    let still_open = script_circuit
+        .0
+        .is_some_and(|circuit| viewports.iter().any(|&viewport| viewport == circuit));
+
+    let circuit = focused
+        .or(script_circuit.0.filter(|_| still_open))
+        .or(project.as_deref().and_then(|project| project.root_circuit));
+    if script_circuit.0 != circuit {
+        script_circuit.0 = circuit;
+    }
+}
+
+/// Keeps the console tab open while it is enabled in the settings,
+/// and disables it when the tab is closed.
+fn sync_console_tab(
+    mut commands: Commands,
+    mut settings: ResMut<Settings>,
+    mut dock_state: NonSendMut<DockState<Entity>>,
+    tabs: Query<Entity, With<ConsoleTab>>,
+    mut shown: Local<bool>,
+) {
+    // The settings are marked as changed whenever the preferences are open,
+    // so toggling is detected by comparing against the last state instead.
+    let tab = tabs.iter().next();
+
+    if settings.show_console == *shown {
+        if tab.is_none() && *shown {
+            settings.show_console = false;
+            *shown = false;
+        }
+        return;
+    }
+    *shown = settings.show_console;
+
+    match (settings.show_console, tab) {
+        (true, None) => {
+            let tab = commands.spawn(ConsoleTab).id();
+            let surface = dock_state.main_surface_mut();
+            if surface.is_empty() {
+                surface.push_to_first_leaf(tab);
+            } else {
+                surface.split_below(NodeIndex::root(), 0.75, vec![tab]);
+            }
+        }
+        (false, Some(tab)) => {
+            if let Some(index) = dock_state.find_tab(&tab) {
+                dock_state.remove_tab(index);
+            }
+            commands.entity(tab).despawn();
+        }
+        _ => (),
+    }
+}
+
+/// Shows the console when a script file is run, so its output isn't missed.
+fn show_console_on_output(
+    console: Res<ScriptConsole>,
+    mut settings: ResMut<Settings>,
+    mut line_count: Local<usize>,
+) {
+    if console.lines.len() > *line_count && !settings.show_console {
+        settings.show_console = true;
+    }
+    *line_count = console.lines.len();
+}
+
+#[derive(Debug, Default)]
+pub struct ConsolePlugin;
+
+impl bevy_app::Plugin for ConsolePlugin {
+    fn build(&self, app: &mut bevy_app::App) {
+        app.register_type::<ConsoleTab>();
+        app.init_resource::<ScriptCircuit>();
+        app.add_systems(
+            bevy_app::Update,
+            (
+                track_script_circuit.before(MenuSet),
+                (show_console_on_output, sync_console_tab)
+                    .chain()
+                    .after(MenuSet)
+                    .before(update_tabs),
+            ),
+        );
+    }
+}
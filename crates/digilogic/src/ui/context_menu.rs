@@ -0,0 +1,149 @@
+use aery::prelude::*;
+use bevy_ecs::prelude::*;
+use bevy_ecs::system::SystemParam;
+use digilogic_core::components::{CircuitID, DeMorgan, Grouped, Locked, Net, SymbolKind, Traced};
+use digilogic_core::visibility::{Visibility, VisibilityOverrides};
+use digilogic_ux::{GroupSelection, SelectionSet, SetDeMorgan, SetLocked, UngroupSelection};
+use egui::*;
+
+/// The menu opened by clicking into a viewport with the secondary button.
+#[derive(SystemParam)]
+pub(super) struct ViewportContextMenu<'w, 's> {
+    commands: Commands<'w, 's>,
+    selection: Res<'w, SelectionSet>,
+    locked: Query<'w, 's, (), With<Locked>>,
+    grouped: Query<'w, 's, (), Participates<Grouped>>,
+    gates: Query<'w, 's, (&'static SymbolKind, Has<DeMorgan>)>,
+    nets: Query<'w, 's, Has<Traced>, With<Net>>,
+    lock_events: EventWriter<'w, SetLocked>,
+    de_morgan_events: EventWriter<'w, SetDeMorgan>,
+    group_events: EventWriter<'w, GroupSelection>,
+    ungroup_events: EventWriter<'w, UngroupSelection>,
+    visibility_overrides: Query<'w, 's, &'static mut VisibilityOverrides>,
+}
+
+impl ViewportContextMenu<'_, '_> {
+    pub(super) fn show(&mut self, response: &Response, viewport: Entity, circuit: CircuitID) {
+        response.context_menu(|ui| {
+            if let Ok(mut overrides) = self.visibility_overrides.get_mut(viewport) {
+                if !overrides.is_empty() && ui.button("Show All in This View").clicked() {
+                    overrides.clear();
+                    ui.close_menu();
+                }
+            }
+
+            if self.selection.is_empty() {
+                ui.label("Nothing selected");
+                return;
+            }
+
+            let any_locked = self
+                .selection
+                .iter()
+                .any(|entity| self.locked.contains(entity));
+            let any_unlocked = self
+                .selection
+                .iter()
+                .any(|entity| !self.locked.contains(entity));
+
+            if any_unlocked && ui.button("Lock").clicked() {
+                self.lock_events.send(SetLocked {
+                    entities: self.selection.iter().collect(),
+                    locked: true,
+                });
+                ui.close_menu();
+            }
+            if any_locked && ui.button("Unlock").clicked() {
+                self.lock_events.send(SetLocked {
+                    entities: self.selection.iter().collect(),
+                    locked: false,
+                });
+                ui.close_menu();
+            }
+
+            // only gates with an equivalent can be drawn as one
+            let (any_original, any_de_morgan) = self
+                .selection
+                .iter()
+                .filter_map(|entity| self.gates.get(entity).ok())
+                .filter(|(kind, _)| kind.has_de_morgan_equivalent())
+                .fold(
+                    (false, false),
+                    |(original, de_morgan), (_, is_de_morgan)| {
+                        (original || !is_de_morgan, de_morgan || is_de_morgan)
+                    },
+                );
+
+            if any_original && ui.button("Draw as De Morgan Equivalent").clicked() {
+                self.de_morgan_events.send(SetDeMorgan {
+                    entities: self.selection.iter().collect(),
+                    de_morgan: true,
+                });
+                ui.close_menu();
+            }
+            if any_de_morgan && ui.button("Draw as Original Gate").clicked() {
+                self.de_morgan_events.send(SetDeMorgan {
+                    entities: self.selection.iter().collect(),
+                    de_morgan: false,
+                });
+                ui.close_menu();
+            }
+            if any_original || any_de_morgan {
+                ui.separator();
+            }
+
+            // traced nets are recorded while simulating, for timing diagrams
+            let (any_untraced, any_traced) = self
+                .selection
+                .iter()
+                .filter_map(|entity| self.nets.get(entity).ok())
+                .fold((false, false), |(untraced, traced), is_traced| {
+                    (untraced || !is_traced, traced || is_traced)
+                });
+
+            if any_untraced && ui.button("Trace").clicked() {
+                for entity in self.selection.iter() {
+                    if self.nets.contains(entity) {
+                        self.commands.entity(entity).insert(Traced);
+                    }
+                }
+                ui.close_menu();
+            }
+            if any_traced && ui.button("Stop Tracing").clicked() {
+                for entity in self.selection.iter() {
+                    if self.nets.contains(entity) {
+                        self.commands.entity(entity).remove::<Traced>();
+                    }
+                }
+                ui.close_menu();
+            }
+            if any_untraced || any_traced {
+                ui.separator();
+            }
+
+            if (self.selection.len() > 1) && ui.button("Group").clicked() {
+                self.group_events.send(GroupSelection { circuit });
+                ui.close_menu();
+            }
+            let any_grouped = self
+                .selection
+                .iter()
+                .any(|entity| self.grouped.contains(entity));
+            if any_grouped && ui.button("Ungroup").clicked() {
+                self.ungroup_events.send(UngroupSelection);
+                ui.close_menu();
+            }
+
+            ui.separator();
+
+            if ui.button("Hide in This View").clicked() {
+                if let Ok(mut overrides) = self.visibility_overrides.get_mut(viewport) {
+                    for entity in self.selection.iter() {
+                        overrides.insert(entity, Visibility::Hidden);
+                    }
+                }
+                ui.close_menu();
+            }
+        });
+    }
+}
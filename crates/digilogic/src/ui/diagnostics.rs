@@ -0,0 +1,248 @@
+use super::{
+    linear_to_zoom, update_tabs, Canvas, Egui, MenuSet, PanZoom, ViewportSpawner, MAX_LINEAR_ZOOM,
+    MIN_LINEAR_ZOOM,
+};
+use crate::Settings;
+use aery::prelude::*;
+use bevy_ecs::prelude::*;
+use bevy_ecs::system::lifetimeless::Read;
+use bevy_ecs::system::SystemParam;
+use bevy_reflect::Reflect;
+use digilogic_core::components::{Child, Circuit, Name, Port, Symbol, Viewport};
+use digilogic_core::transform::{AbsoluteBoundingBox, BoundingBox};
+use digilogic_ux::{ApplyQuickFix, ErcReport, Finding, SelectionSet, Severity};
+use egui::*;
+use egui_dock::{DockState, NodeIndex};
+
+const WARNING_COLOR: Color32 = Color32::from_rgb(240, 170, 20);
+const ERROR_COLOR: Color32 = Color32::from_rgb(240, 13, 13);
+
+/// The space left around the offending entities when zooming to a finding.
+const FOCUS_MARGIN: f32 = 40.0;
+
+/// Marks the dock tab that shows the findings of the electrical rule check.
+#[derive(Debug, Default, Clone, Copy, Component, Reflect)]
+pub struct DiagnosticsTab;
+
+/// Requests the viewport of a finding to be shown, zoomed to the offending entities.
+#[derive(Event, Debug)]
+struct NavigateToFinding(Finding);
+
+/// The area a viewport is to be zoomed to once its canvas is available.
+#[derive(Debug, Default, Resource)]
+struct PendingFocus(Option<(Entity, BoundingBox)>);
+
+fn severity_icon(severity: Severity) -> RichText {
+    match severity {
+        Severity::Warning => RichText::new("⚠").color(WARNING_COLOR),
+        Severity::Error => RichText::new("⛔").color(ERROR_COLOR),
+    }
+}
+
+fn severity_title(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Warning => "Warnings",
+        Severity::Error => "Errors",
+    }
+}
+
+#[derive(SystemParam)]
+pub(super) struct Diagnostics<'w, 's> {
+    tabs: Query<'w, 's, (), With<DiagnosticsTab>>,
+    report: Res<'w, ErcReport>,
+    circuits: Query<'w, 's, Read<Name>, With<Circuit>>,
+    fix_events: EventWriter<'w, ApplyQuickFix>,
+    navigate_events: EventWriter<'w, NavigateToFinding>,
+}
+
+impl Diagnostics<'_, '_> {
+    #[inline]
+    pub(super) fn is_tab(&self, tab: Entity) -> bool {
+        self.tabs.contains(tab)
+    }
+
+    pub(super) fn title(&self) -> String {
+        format!("Diagnostics ({})", self.report.findings().len())
+    }
+
+    /// Lists the findings grouped by severity, errors first.
+    pub(super) fn show(&mut self, ui: &mut Ui) {
+        ScrollArea::vertical().auto_shrink(false).show(ui, |ui| {
+            if self.report.findings().is_empty() {
+                ui.weak("No problems found");
+                return;
+            }
+
+            for severity in [Severity::Error, Severity::Warning] {
+                let findings = self
+                    .report
+                    .findings()
+                    .iter()
+                    .filter(|finding| finding.severity() == severity)
+                    .cloned()
+                    .collect::<Vec<_>>();
+                if findings.is_empty() {
+                    continue;
+                }
+
+                let title = format!("{} ({})", severity_title(severity), findings.len());
+                CollapsingHeader::new(title)
+                    .id_salt(severity_title(severity))
+                    .default_open(true)
+                    .show(ui, |ui| {
+                        for finding in &findings {
+                            self.show_finding(ui, finding);
+                        }
+                    });
+            }
+        });
+    }
+
+    fn show_finding(&mut self, ui: &mut Ui, finding: &Finding) {
+        ui.horizontal(|ui| {
+            ui.label(severity_icon(finding.severity()));
+            if ui
+                .selectable_label(false, finding.message.as_str())
+                .on_hover_text("Show in circuit")
+                .clicked()
+            {
+                self.navigate_events
+                    .send(NavigateToFinding(finding.clone()));
+            }
+            if let Ok(name) = self.circuits.get(finding.circuit.0) {
+                ui.weak(name.0.as_str());
+            }
+            for fix in &finding.fixes {
+                if ui.small_button(fix.label()).clicked() {
+                    self.fix_events.send(ApplyQuickFix(fix.clone()));
+                }
+            }
+        });
+    }
+}
+
+/// Keeps the diagnostics tab open while it is enabled in the settings,
+/// and disables it when the tab is closed.
+fn sync_diagnostics_tab(
+    mut commands: Commands,
+    mut settings: ResMut<Settings>,
+    mut dock_state: NonSendMut<DockState<Entity>>,
+    tabs: Query<Entity, With<DiagnosticsTab>>,
+    mut shown: Local<bool>,
+) {
+    // The settings are marked as changed whenever the preferences are open,
+    // so toggling is detected by comparing against the last state instead.
+    let tab = tabs.iter().next();
+
+    if settings.show_diagnostics == *shown {
+        if tab.is_none() && *shown {
+            settings.show_diagnostics = false;
+            *shown = false;
+        }
+        return;
+    }
+    *shown = settings.show_diagnostics;
+
+    match (settings.show_diagnostics, tab) {
+        (true, None) => {
+            let tab = commands.spawn(DiagnosticsTab).id();
+            let surface = dock_state.main_surface_mut();
+            if surface.is_empty() {
+                surface.push_to_first_leaf(tab);
+            } else {
+                surface.split_below(NodeIndex::root(), 0.75, vec![tab]);
+            }
+        }
+        (false, Some(tab)) => {
+            if let Some(index) = dock_state.find_tab(&tab) {
+                dock_state.remove_tab(index);
+            }
+            commands.entity(tab).despawn();
+        }
+        _ => (),
+    }
+}
+
+/// Selects the entity of a finding and zooms its circuit to it.
+/// Ports are selected through their symbol.
+#[allow(clippy::too_many_arguments)]
+fn navigate_to_findings(
+    egui: Res<Egui>,
+    mut navigate_events: EventReader<NavigateToFinding>,
+    ports: Query<Relations<Child>, With<Port>>,
+    symbols: Query<Entity, With<Symbol>>,
+    bounds: Query<&AbsoluteBoundingBox>,
+    mut selection: ResMut<SelectionSet>,
+    mut pending: ResMut<PendingFocus>,
+    mut viewport_spawner: ViewportSpawner,
+) {
+    let Some(NavigateToFinding(finding)) = navigate_events.read().last() else {
+        return;
+    };
+
+    let mut selected = finding.entity;
+    if let Ok(edges) = ports.get(finding.entity) {
+        edges
+            .join::<Up<Child>>(&symbols)
+            .for_each(|symbol| selected = symbol);
+    }
+    selection.select_only(selected);
+
+    let region = std::iter::once(selected)
+        .chain(finding.related.iter().copied())
+        .filter_map(|entity| bounds.get(entity).ok())
+        .map(|&bounds| *bounds)
+        .reduce(|a, b| BoundingBox::from_points(a.min().min(b.min()), a.max().max(b.max())));
+
+    let viewport = viewport_spawner.focus_or_spawn_viewport(finding.circuit, &egui);
+    pending.0 = region.map(|region| (viewport, region));
+}
+
+/// Zooms a viewport to a finding, waiting for viewports that have just been opened.
+fn apply_pending_focus(
+    mut pending: ResMut<PendingFocus>,
+    mut viewports: Query<(&mut PanZoom, &Canvas), With<Viewport>>,
+) {
+    let Some((viewport, region)) = pending.0 else {
+        return;
+    };
+    let Ok((mut pan_zoom, canvas)) = viewports.get_mut(viewport) else {
+        return;
+    };
+    if (canvas.width() == 0) || (canvas.height() == 0) {
+        return;
+    }
+    pending.0 = None;
+
+    let min = Vec2::new(region.min().x.to_f32(), region.min().y.to_f32());
+    let max = Vec2::new(region.max().x.to_f32(), region.max().y.to_f32());
+    let size = (max - min) + Vec2::splat(2.0 * FOCUS_MARGIN);
+    let screen_size = Vec2::new(canvas.width() as f32, canvas.height() as f32);
+
+    pan_zoom.zoom = (screen_size.x / size.x).min(screen_size.y / size.y).clamp(
+        linear_to_zoom(MIN_LINEAR_ZOOM),
+        linear_to_zoom(MAX_LINEAR_ZOOM),
+    );
+    pan_zoom.pan = (screen_size / 2.0) / pan_zoom.zoom - (min + max) / 2.0;
+}
+
+#[derive(Debug, Default)]
+pub struct DiagnosticsPlugin;
+
+impl bevy_app::Plugin for DiagnosticsPlugin {
+    fn build(&self, app: &mut bevy_app::App) {
+        app.register_type::<DiagnosticsTab>();
+        app.init_resource::<PendingFocus>();
+        app.add_event::<NavigateToFinding>();
+        app.add_systems(
+            bevy_app::Update,
+            sync_diagnostics_tab.after(MenuSet).before(update_tabs),
+        );
+        app.add_systems(
+            bevy_app::Update,
+            (navigate_to_findings, apply_pending_focus)
+                .chain()
+                .after(update_tabs),
+        );
+    }
+}
@@ -1,81 +1,195 @@
 use super::{Layer, PaletteBrushes, Scene, Viewport};
+use aery::operations::Join as _;
 use aery::prelude::*;
 use bevy_ecs::prelude::*;
 use bevy_ecs::system::lifetimeless::Read;
+use bevy_reflect::Reflect;
 use bitflags::bitflags;
 use digilogic_core::components::*;
+use digilogic_core::symbol::SymbolRegistry;
 use digilogic_core::transform::*;
-use digilogic_core::visibility::ComputedVisibility;
+use digilogic_core::visibility::{ComputedVisibility, ViewVisibility};
+use digilogic_core::HashMap;
 use digilogic_routing::{VertexKind, Vertices};
-use vello::kurbo::{Affine, BezPath, Cap, Circle, Join, Line, Rect, Stroke, Vec2};
+use digilogic_ux::{ErcError, ErcWarning};
+use serde::{Deserialize, Serialize};
+use std::ops::Index;
+use vello::kurbo::{
+    Affine, BezPath, Cap, Circle, Ellipse, Join, Line, PathEl, Point, Rect, Shape as _, Stroke,
+    Vec2,
+};
 use vello::peniko::{Color, Fill, Font};
 
 include!("bez_path.rs");
 
 bitflags! {
+    #[derive(Clone, Copy)]
     pub struct PathKind: u8 {
         const FILL = 0x1;
         const STROKE = 0x2;
     }
 }
 
-struct PathInfo {
-    kind: PathKind,
-    path: BezPath,
+pub(super) struct PathInfo {
+    pub(super) kind: PathKind,
+    pub(super) path: BezPath,
 }
 
 #[derive(Default)]
 pub struct SymbolShape {
-    paths: Vec<PathInfo>,
+    pub(super) paths: Vec<PathInfo>,
+}
+
+impl SymbolShape {
+    /// Paints the outline of the shape with egui, for previews outside of a canvas.
+    pub fn paint_outline(&self, painter: &egui::Painter, transform: Affine, stroke: egui::Stroke) {
+        for path in self.paths.iter() {
+            let mut points = Vec::new();
+            let flush = |points: &mut Vec<egui::Pos2>, closed: bool| {
+                let points = std::mem::take(points);
+                if points.len() < 2 {
+                    return;
+                }
+
+                if closed {
+                    painter.add(egui::Shape::closed_line(points, stroke));
+                } else {
+                    painter.add(egui::Shape::line(points, stroke));
+                }
+            };
+
+            vello::kurbo::flatten(&(transform * &path.path), 0.25, |el| match el {
+                PathEl::MoveTo(p) => {
+                    flush(&mut points, false);
+                    points.push(egui::pos2(p.x as f32, p.y as f32));
+                }
+                PathEl::LineTo(p) => points.push(egui::pos2(p.x as f32, p.y as f32)),
+                PathEl::ClosePath => flush(&mut points, true),
+                _ => unreachable!("flattened paths only contain lines"),
+            });
+            flush(&mut points, false);
+        }
+    }
+}
+
+/// How gates are drawn.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+pub enum GateStyle {
+    /// The distinctive shapes of ANSI/IEEE Std 91.
+    #[default]
+    Distinctive,
+    /// Rectangles labelled with the qualifier of the gate, as in IEC 60617.
+    Rectangular,
+}
+
+impl GateStyle {
+    pub(crate) const ALL: &[Self] = &[Self::Distinctive, Self::Rectangular];
 }
 
 #[derive(Default, Resource)]
-pub struct SymbolShapes(pub Vec<SymbolShape>);
+pub struct SymbolShapes {
+    /// The built-in shapes, in the order of [`Shape`].
+    shapes: Vec<SymbolShape>,
+    /// The rectangular outlines of gates, drawn instead of the distinctive ones
+    /// in [`GateStyle::Rectangular`].
+    rectangular: HashMap<Shape, SymbolShape>,
+    pub(super) gate_style: GateStyle,
+    /// Artwork loaded for kinds of symbols, drawn instead of their shape.
+    pub(super) kinds: HashMap<SymbolKind, SymbolShape>,
+}
+
+impl SymbolShapes {
+    /// The built-in `shape` in the current gate style.
+    fn builtin(&self, shape: Shape) -> Option<&SymbolShape> {
+        let rectangular = match self.gate_style {
+            GateStyle::Distinctive => None,
+            GateStyle::Rectangular => self.rectangular.get(&shape),
+        };
+        rectangular.or_else(|| self.shapes.get(shape as usize))
+    }
+
+    /// The shape symbols of `kind` are drawn with, their artwork if it was loaded.
+    pub fn get(&self, kind: SymbolKind, shape: Shape) -> Option<&SymbolShape> {
+        self.kinds.get(&kind).or_else(|| self.builtin(shape))
+    }
+}
+
+impl Index<Shape> for SymbolShapes {
+    type Output = SymbolShape;
+
+    fn index(&self, shape: Shape) -> &SymbolShape {
+        self.builtin(shape).expect("shapes are not initialized")
+    }
+}
 
 type SymbolQuery<'w, 's> = Query<
     'w,
     's,
     (
         Read<Shape>,
+        Read<SymbolKind>,
+        Read<BoundingBox>,
         Read<GlobalTransform>,
         Read<ComputedVisibility>,
         Option<Read<digilogic_netcode::StateOffset>>,
         Option<Read<BitWidth>>,
         Has<Hovered>,
+        Has<Selected>,
+        Has<Probed>,
+        Has<DeMorgan>,
     ),
     With<Symbol>,
 >;
 
+#[allow(dead_code)]
 #[derive(Resource)]
 pub struct VelloFont(pub Font);
 
+#[allow(clippy::too_many_arguments)]
 pub fn draw_symbols(
     symbol_shapes: Res<SymbolShapes>,
+    symbol_registry: Res<SymbolRegistry>,
     palette: Res<PaletteBrushes>,
-    font: Res<VelloFont>,
+    _font: Res<VelloFont>,
     sim_state: Option<Res<digilogic_netcode::SimState>>,
-    viewports: Query<(&Scene, &CircuitID), With<Viewport>>,
+    viewports: Query<(&Scene, &CircuitID, &ViewVisibility), With<Viewport>>,
     children: Query<(Entity, Relations<Child>)>,
     symbols: SymbolQuery,
+    ports: Query<(&Transform, &PortDirection), With<Port>>,
 ) {
-    for (scene, circuit) in viewports.iter() {
+    digilogic_core::profile_scope!("draw");
+
+    for (scene, circuit, view_visibility) in viewports.iter() {
         let mut scene = scene.for_layer(Layer::Symbol);
         scene.reset();
 
         children
             .traverse::<Child>(std::iter::once(circuit.0))
             .for_each(|&mut entity, _| {
-                let Ok((shape, transform, &visibility, state_offset, bit_width, hovered)) =
-                    symbols.get(entity)
+                let Ok((
+                    shape,
+                    &kind,
+                    bounding_box,
+                    transform,
+                    &visibility,
+                    state_offset,
+                    bit_width,
+                    hovered,
+                    selected,
+                    probed,
+                    de_morgan,
+                )) = symbols.get(entity)
                 else {
                     return;
                 };
 
-                if !*visibility {
+                if !view_visibility.is_visible(entity, visibility) {
                     return;
                 }
 
-                let transform = Affine::scale(transform.scale.to_f64())
+                let scale = transform.scale.to_f64();
+                let mirror_scale = if transform.mirrored { -scale } else { scale };
+                let transform = Affine::scale_non_uniform(mirror_scale, scale)
                     .then_rotate(transform.rotation.radians())
                     .then_translate(Vec2::new(
                         transform.translation.x.to_f64(),
@@ -85,7 +199,88 @@ pub fn draw_symbols(
                 // TODO: figure out how to layout text, as draw requires a Glyph iterator
                 //scene.draw_glyphs(&font.0).hint(true).font_size(12.0).draw();
 
-                let symbol_shape = &symbol_shapes.0[*shape as usize];
+                // chips have no fixed outline, they are drawn as their bounding box
+                let owned_shape;
+                let nominal_bounds = symbol_registry
+                    .get_def(kind)
+                    .map(|def| def.bounding_box())
+                    .filter(|nominal| nominal.height() != bounding_box.height());
+                // outlines stretch downwards along with the ports, like gates with many inputs
+                let stretch = nominal_bounds.map(|nominal| {
+                    let top = nominal.min().y.to_f64();
+                    Affine::translate((0.0, -top))
+                        .then_scale_non_uniform(
+                            1.0,
+                            bounding_box.height().to_f64() / nominal.height().to_f64(),
+                        )
+                        .then_translate(Vec2::new(0.0, top))
+                });
+                let symbol_shape = if let Some(artwork) = symbol_shapes.kinds.get(&kind) {
+                    artwork
+                } else if let Some((dual, inverted)) = de_morgan_dual(kind).filter(|_| de_morgan) {
+                    let mut inputs = Vec::new();
+                    let mut output = None;
+                    if let Ok((_, edges)) = children.get(entity) {
+                        edges
+                            .join::<Child>(&ports)
+                            .for_each(|(port_transform, direction)| {
+                                let position = Point::new(
+                                    port_transform.translation.x.to_f64(),
+                                    port_transform.translation.y.to_f64(),
+                                );
+                                match direction {
+                                    PortDirection::Input => inputs.push(position),
+                                    PortDirection::Output => output = Some(position),
+                                    _ => (),
+                                }
+                            });
+                    }
+                    owned_shape = de_morgan_gate(
+                        &symbol_shapes[*shape],
+                        &symbol_shapes[dual],
+                        stretch.unwrap_or_default(),
+                        &inputs,
+                        output.filter(|_| inverted),
+                    );
+                    &owned_shape
+                } else if matches!(shape, Shape::Chip) {
+                    owned_shape = SymbolShape {
+                        paths: vec![PathInfo {
+                            kind: PathKind::FILL | PathKind::STROKE,
+                            path: Rect::new(
+                                bounding_box.min().x.to_f64(),
+                                bounding_box.min().y.to_f64(),
+                                bounding_box.max().x.to_f64(),
+                                bounding_box.max().y.to_f64(),
+                            )
+                            .to_path(0.1),
+                        }],
+                    };
+                    &owned_shape
+                } else if let Shape::Mux | Shape::Demux = shape {
+                    // the slope of multiplexers stays the same as they grow in both directions
+                    owned_shape = SymbolShape {
+                        paths: vec![PathInfo {
+                            kind: PathKind::FILL | PathKind::STROKE,
+                            path: mux_path(bounding_box, matches!(shape, Shape::Demux)),
+                        }],
+                    };
+                    &owned_shape
+                } else if let Some(stretch) = stretch {
+                    owned_shape = SymbolShape {
+                        paths: symbol_shapes[*shape]
+                            .paths
+                            .iter()
+                            .map(|path| PathInfo {
+                                kind: path.kind,
+                                path: stretch * path.path.clone(),
+                            })
+                            .collect(),
+                    };
+                    &owned_shape
+                } else {
+                    &symbol_shapes[*shape]
+                };
                 for path in symbol_shape.paths.iter() {
                     let color = palette
                         .get_color_for_state(
@@ -102,6 +297,10 @@ pub fn draw_symbols(
                     if path.kind.contains(PathKind::STROKE) {
                         let (width, color) = if hovered {
                             (3.5, Color::WHITE)
+                        } else if selected {
+                            (3.5, Color::rgb8(90, 160, 255))
+                        } else if probed {
+                            (3.5, Color::rgb8(255, 160, 50))
                         } else {
                             (3.0, Color::rgb8(150, 150, 150))
                         };
@@ -122,53 +321,162 @@ pub fn draw_symbols(
     }
 }
 
+type GraphicQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Read<Graphic>,
+        Read<GlobalTransform>,
+        Read<ComputedVisibility>,
+        Has<Hovered>,
+        Has<Selected>,
+    ),
+>;
+
+/// Graphics are drawn below everything else, they only frame the circuit.
+pub fn draw_graphics(
+    viewports: Query<(&Scene, &CircuitID, &ViewVisibility), With<Viewport>>,
+    children: Query<(Entity, Relations<Child>)>,
+    graphics: GraphicQuery,
+) {
+    digilogic_core::profile_scope!("draw");
+
+    for (scene, circuit, view_visibility) in viewports.iter() {
+        let mut scene = scene.for_layer(Layer::Graphic);
+        scene.reset();
+
+        children
+            .traverse::<Child>(std::iter::once(circuit.0))
+            .for_each(|&mut entity, _| {
+                let Ok((graphic, transform, &visibility, hovered, selected)) = graphics.get(entity)
+                else {
+                    return;
+                };
+
+                if !view_visibility.is_visible(entity, visibility) {
+                    return;
+                }
+
+                let (width, color) = if hovered {
+                    (2.5, Color::WHITE)
+                } else if selected {
+                    (2.5, Color::rgb8(90, 160, 255))
+                } else {
+                    let [r, g, b, a] = graphic.color;
+                    (1.5, Color::rgba8(r, g, b, a))
+                };
+
+                let start = (
+                    transform.translation.x.to_f64(),
+                    transform.translation.y.to_f64(),
+                );
+                let end = (
+                    start.0 + graphic.extent.x.to_f64(),
+                    start.1 + graphic.extent.y.to_f64(),
+                );
+                let rect = Rect::from_points(start, end);
+                let path = match graphic.kind {
+                    GraphicKind::Rectangle => rect.to_path(0.1),
+                    GraphicKind::Line => Line::new(start, end).to_path(0.1),
+                    GraphicKind::Ellipse => Ellipse::from_rect(rect).to_path(0.1),
+                };
+
+                scene.stroke(&Stroke::new(width), Affine::IDENTITY, color, None, &path);
+            });
+    }
+}
+
 type PortQuery<'w, 's> = Query<
     'w,
     's,
     (
         Read<GlobalTransform>,
         Read<ComputedVisibility>,
-        Has<Input>,
-        Has<Output>,
+        Read<PortDirection>,
         Has<Hovered>,
+        Option<Read<NetID>>,
+        Has<ErcWarning>,
+        Has<ErcError>,
     ),
     With<Port>,
 >;
 
+/// A small triangle next to a port that violates an electrical rule.
+fn erc_badge(port: &GlobalTransform) -> BezPath {
+    let (x, y) = (
+        port.translation.x.to_f64() + 6.0,
+        port.translation.y.to_f64() - 6.0,
+    );
+
+    let mut path = BezPath::new();
+    path.move_to((x, y - 5.0));
+    path.line_to((x + 5.0, y + 4.0));
+    path.line_to((x - 5.0, y + 4.0));
+    path.close_path();
+    path
+}
+
 pub fn draw_ports(
-    viewports: Query<(&Scene, &CircuitID), With<Viewport>>,
+    viewports: Query<(&Scene, &CircuitID, &ViewVisibility), With<Viewport>>,
     children: Query<(Entity, Relations<Child>)>,
     ports: PortQuery,
+    hovered_nets: Query<(), (With<Net>, With<Hovered>)>,
+    disconnected: Query<&GlobalTransform, (With<Endpoint>, With<Disconnected>)>,
 ) {
-    for (scene, circuit) in viewports.iter() {
+    digilogic_core::profile_scope!("draw");
+
+    for (scene, circuit, view_visibility) in viewports.iter() {
+        let mut markers = scene.for_layer(Layer::ErcMarker);
+        markers.reset();
         let mut scene = scene.for_layer(Layer::Port);
         scene.reset();
 
         children
             .traverse::<Child>(std::iter::once(circuit.0))
             .for_each(|&mut entity, _| {
-                let Ok(entity) = ports.get(entity) else {
+                // endpoints that lost their port are marked where the port used to be
+                if let Ok(transform) = disconnected.get(entity) {
+                    let center = (
+                        transform.translation.x.to_f64(),
+                        transform.translation.y.to_f64(),
+                    );
+                    markers.stroke(
+                        &Stroke::new(2.0),
+                        Affine::IDENTITY,
+                        Color::rgb8(240, 13, 13),
+                        None,
+                        &Circle::new(center, 6.0),
+                    );
+                    return;
+                }
+
+                let Ok(port) = ports.get(entity) else {
                     return;
                 };
 
-                let (transform, &visibility, is_input, is_output, hovered) = entity;
+                let (transform, &visibility, &direction, hovered, net, warning, error) = port;
+                // ports light up together with the net connected to them
+                let hovered = hovered || net.is_some_and(|net| hovered_nets.contains(net.0));
 
-                if !*visibility {
+                if !view_visibility.is_visible(entity, visibility) {
                     return;
                 }
 
-                let transform = Affine::scale(transform.scale.to_f64())
+                let badge = (warning || error).then(|| erc_badge(transform));
+
+                let scale = transform.scale.to_f64();
+                let mirror_scale = if transform.mirrored { -scale } else { scale };
+                let transform = Affine::scale_non_uniform(mirror_scale, scale)
                     .then_rotate(transform.rotation.radians())
                     .then_translate(Vec2::new(
                         transform.translation.x.to_f64(),
                         transform.translation.y.to_f64(),
                     ));
 
-                let color = match (is_input, is_output) {
-                    (true, true) => Color::rgb8(232, 225, 40),
-                    (true, false) => Color::rgb8(40, 110, 228),
-                    (false, true) => Color::rgb8(240, 13, 13),
-                    (false, false) => Color::rgb8(140, 140, 140),
+                let color = match direction {
+                    PortDirection::Bidirectional => Color::rgb8(232, 225, 40),
+                    PortDirection::Input => Color::rgb8(40, 110, 228),
+                    PortDirection::Output => Color::rgb8(240, 13, 13),
                 };
 
                 let radius = if hovered { 6.0 } else { 4.0 };
@@ -180,6 +488,15 @@ pub fn draw_ports(
                     None,
                     &Circle::new((0.0, 0.0), radius),
                 );
+
+                if let Some(badge) = badge {
+                    let color = if error {
+                        Color::rgb8(240, 13, 13)
+                    } else {
+                        Color::rgb8(240, 170, 20)
+                    };
+                    markers.fill(Fill::NonZero, Affine::IDENTITY, color, None, &badge);
+                }
             });
     }
 }
@@ -189,38 +506,54 @@ type VertexQuery<'w, 's> = Query<
     's,
     (
         (
+            Entity,
             Option<Read<Vertices>>,
             Option<Read<ComputedVisibility>>,
             Option<Read<digilogic_netcode::StateOffset>>,
             Option<Read<BitWidth>>,
             Has<Hovered>,
+            Has<Probed>,
+            Has<ErcError>,
         ),
         Relations<Child>,
     ),
 >;
 
 pub fn draw_wires(
-    app_state: Res<crate::AppSettings>,
+    settings: Res<crate::Settings>,
     palette: Res<PaletteBrushes>,
     sim_state: Option<Res<digilogic_netcode::SimState>>,
-    viewports: Query<(&Scene, &CircuitID), With<Viewport>>,
+    viewports: Query<(&Scene, &CircuitID, &ViewVisibility), With<Viewport>>,
     vertices: VertexQuery,
 ) {
+    digilogic_core::profile_scope!("draw");
+
     let brush_transform = palette.get_brush_transform();
 
-    for (scene, circuit) in viewports.iter() {
+    for (scene, circuit, view_visibility) in viewports.iter() {
         let mut scene = scene.for_layer(Layer::Wire);
         scene.reset();
 
         vertices
             .traverse::<Child>(std::iter::once(circuit.0))
             .for_each(
-                |&mut (vertices, visibility, state_offset, bit_width, hovered), _| {
+                |&mut (
+                    entity,
+                    vertices,
+                    visibility,
+                    state_offset,
+                    bit_width,
+                    hovered,
+                    probed,
+                    error,
+                ),
+                 _| {
                     let Some(vertices) = vertices else {
                         return;
                     };
 
-                    if !*visibility.copied().unwrap_or_default() {
+                    if !view_visibility.is_visible(entity, visibility.copied().unwrap_or_default())
+                    {
                         return;
                     }
 
@@ -232,7 +565,7 @@ pub fn draw_wires(
 
                     let brush_transform = brush.is_some().then_some(brush_transform);
 
-                    let (width, radius) = if hovered && brush.is_none() {
+                    let (width, radius) = if (hovered || probed) && brush.is_none() {
                         (3.0, 4.5)
                     } else {
                         (2.5, 4.0)
@@ -252,8 +585,20 @@ pub fn draw_wires(
                                 is_root_path = is_root;
                             }
                             VertexKind::WireEnd { junction_kind } => {
-                                let brush = brush.clone().unwrap_or_else(|| {
-                                    let is_root = is_root_path && app_state.show_root_wires;
+                                let brush = brush.unwrap_or_else(|| {
+                                    if probed && !hovered {
+                                        return Color::rgb8(255, 160, 50).into();
+                                    }
+                                    // nets violating an electrical rule stand out until fixed
+                                    if error {
+                                        return if hovered {
+                                            Color::rgb8(250, 110, 110).into()
+                                        } else {
+                                            Color::rgb8(230, 40, 40).into()
+                                        };
+                                    }
+
+                                    let is_root = is_root_path && settings.show_root_wires;
 
                                     match (is_root, hovered) {
                                         (true, true) => Color::rgb8(245, 220, 116).into(),
@@ -268,7 +613,7 @@ pub fn draw_wires(
                                 scene.stroke(
                                     &Stroke::new(width),
                                     Affine::IDENTITY,
-                                    brush.clone(),
+                                    brush,
                                     brush_transform,
                                     &path,
                                 );
@@ -290,10 +635,78 @@ pub fn draw_wires(
     }
 }
 
+type WaypointNetQuery<'w, 's> = Query<'w, 's, (Has<Selected>, Relations<Child>), With<Net>>;
+type WaypointEndpointQuery<'w, 's> = Query<'w, 's, ((), Relations<Child>), With<Endpoint>>;
+type WaypointQuery<'w, 's> =
+    Query<'w, 's, (Read<GlobalTransform>, Has<Hovered>, Has<Selected>), With<Waypoint>>;
+
+/// Draws handles at the waypoints of selected nets, and at waypoints under the cursor.
+pub fn draw_waypoints(
+    viewports: Query<(&Scene, &CircuitID), With<Viewport>>,
+    circuits: Query<((), Relations<Child>), With<Circuit>>,
+    nets: WaypointNetQuery,
+    endpoints: WaypointEndpointQuery,
+    waypoints: WaypointQuery,
+) {
+    digilogic_core::profile_scope!("draw");
+
+    for (scene, circuit) in viewports.iter() {
+        let mut scene = scene.for_layer(Layer::Waypoint);
+        scene.reset();
+
+        let Ok((_, circuit_children)) = circuits.get(circuit.0) else {
+            continue;
+        };
+
+        circuit_children
+            .join::<Child>(&nets)
+            .for_each(|(net_selected, net_children)| {
+                net_children
+                    .join::<Child>(&endpoints)
+                    .for_each(|(_, endpoint_children)| {
+                        endpoint_children.join::<Child>(&waypoints).for_each(
+                            |(transform, hovered, selected)| {
+                                if !(net_selected || hovered || selected) {
+                                    return;
+                                }
+
+                                let half_size = if hovered { 4.0 } else { 3.0 };
+                                let center = (
+                                    transform.translation.x.to_f64(),
+                                    transform.translation.y.to_f64(),
+                                );
+                                let handle = Rect::from_center_size(
+                                    center,
+                                    (half_size * 2.0, half_size * 2.0),
+                                );
+
+                                scene.fill(
+                                    Fill::NonZero,
+                                    Affine::IDENTITY,
+                                    Color::WHITE,
+                                    None,
+                                    &handle,
+                                );
+                                scene.stroke(
+                                    &Stroke::new(1.0),
+                                    Affine::IDENTITY,
+                                    Color::rgb8(8, 190, 42),
+                                    None,
+                                    &handle,
+                                );
+                            },
+                        );
+                    });
+            });
+    }
+}
+
 pub fn draw_bounding_boxes(
     viewports: Query<(&Scene, &CircuitID), With<Viewport>>,
     boxes: Query<(Option<&AbsoluteBoundingBox>, Relations<Child>)>,
 ) {
+    digilogic_core::profile_scope!("draw");
+
     for (scene, circuit) in viewports.iter() {
         let mut scene = scene.for_layer(Layer::BoundingBox);
         scene.reset();
@@ -321,10 +734,110 @@ pub fn draw_bounding_boxes(
     }
 }
 
+pub fn draw_alignment_guides(
+    viewports: Query<(&Scene, Ref<digilogic_ux::AlignmentGuides>), With<Viewport>>,
+) {
+    digilogic_core::profile_scope!("draw");
+
+    for (scene, guides) in viewports.iter() {
+        if !guides.is_changed() {
+            continue;
+        }
+
+        let mut scene = scene.for_layer(Layer::AlignmentGuide);
+        scene.reset();
+
+        for guide in guides.iter() {
+            scene.stroke(
+                &Stroke::new(1.0).with_dashes(0.0, [4.0, 4.0]),
+                Affine::IDENTITY,
+                Color::rgb8(255, 64, 200),
+                None,
+                &Line::new(
+                    (guide.from.x.to_f64(), guide.from.y.to_f64()),
+                    (guide.to.x.to_f64(), guide.to.y.to_f64()),
+                ),
+            );
+        }
+    }
+}
+
+pub fn draw_selection_boxes(
+    viewports: Query<(&Scene, Ref<digilogic_ux::SelectionBox>), With<Viewport>>,
+) {
+    digilogic_core::profile_scope!("draw");
+
+    for (scene, selection_box) in viewports.iter() {
+        if !selection_box.is_changed() {
+            continue;
+        }
+
+        let mut scene = scene.for_layer(Layer::SelectionBox);
+        scene.reset();
+
+        if let Some(bounds) = selection_box.bounds() {
+            let rect = Rect::new(
+                bounds.min().x.to_f64(),
+                bounds.min().y.to_f64(),
+                bounds.max().x.to_f64(),
+                bounds.max().y.to_f64(),
+            );
+            scene.fill(
+                Fill::NonZero,
+                Affine::IDENTITY,
+                Color::rgba8(90, 160, 255, 32),
+                None,
+                &rect,
+            );
+            scene.stroke(
+                &Stroke::new(1.0),
+                Affine::IDENTITY,
+                Color::rgb8(90, 160, 255),
+                None,
+                &rect,
+            );
+        }
+    }
+}
+
+pub fn draw_port_stubs(viewports: Query<(&Scene, Ref<digilogic_ux::PortStub>), With<Viewport>>) {
+    digilogic_core::profile_scope!("draw");
+
+    for (scene, port_stub) in viewports.iter() {
+        if !port_stub.is_changed() {
+            continue;
+        }
+
+        let mut scene = scene.for_layer(Layer::PortStub);
+        scene.reset();
+
+        if let Some((_, from, to, crosses_symbol)) = port_stub.0 {
+            let color = if crosses_symbol {
+                Color::rgb8(240, 160, 60)
+            } else {
+                Color::rgb8(125, 240, 147)
+            };
+
+            scene.stroke(
+                &Stroke::new(2.5).with_dashes(0.0, [6.0, 4.0]),
+                Affine::IDENTITY,
+                color,
+                None,
+                &Line::new(
+                    (from.x.to_f64(), from.y.to_f64()),
+                    (to.x.to_f64(), to.y.to_f64()),
+                ),
+            );
+        }
+    }
+}
+
 pub fn draw_routing_graph(
     viewports: Query<(&Scene, &CircuitID), With<Viewport>>,
     graphs: Query<Ref<digilogic_routing::graph::Graph>>,
 ) {
+    digilogic_core::profile_scope!("draw");
+
     for (scene, circuit) in viewports.iter() {
         let mut scene = scene.for_layer(Layer::RoutingGraph);
         scene.reset();
@@ -386,8 +899,171 @@ const INOUT_SCALE: f64 = 2.5;
 const INPUT_TRANSLATE: (f64, f64) = (-46.5, -17.75);
 const OUTPUT_TRANSLATE: (f64, f64) = (-12.0, -17.75);
 
-pub fn init_symbol_shapes(mut symbol_svgs: ResMut<SymbolShapes>) {
-    symbol_svgs.0 = vec![
+const FLIP_FLOP_TRANSLATE: (f64, f64) = (0.0, -20.0);
+
+const ARITHMETIC_TRANSLATE: (f64, f64) = (0.0, -10.0);
+
+/// The outline of multiplexers with a single select input.
+const MUX_BOUNDING_BOX: BoundingBox = BoundingBox::from_top_left_size(
+    digilogic_core::transform::Vec2 {
+        x: digilogic_core::fixed!(0),
+        y: digilogic_core::fixed!(-10),
+    },
+    digilogic_core::fixed!(40),
+    digilogic_core::fixed!(60),
+);
+
+/// A trapezoid filling `bounding_box`, whose narrow side is inset by half of its width at both ends,
+/// on the right for multiplexers and on the left for demultiplexers.
+fn mux_path(bounding_box: &BoundingBox, demux: bool) -> BezPath {
+    let (left, top) = (bounding_box.min().x.to_f64(), bounding_box.min().y.to_f64());
+    let (right, bottom) = (bounding_box.max().x.to_f64(), bounding_box.max().y.to_f64());
+    let inset = (right - left) / 2.0;
+    let (left_inset, right_inset) = if demux { (inset, 0.0) } else { (0.0, inset) };
+
+    let mut path = BezPath::new();
+    path.move_to((left, top + left_inset));
+    path.line_to((right, top + right_inset));
+    path.line_to((right, bottom - right_inset));
+    path.line_to((left, bottom - left_inset));
+    path.close_path();
+    path
+}
+
+/// The gate drawn for the De Morgan equivalent of `kind`,
+/// and whether its output is negated along with its inputs.
+fn de_morgan_dual(kind: SymbolKind) -> Option<(Shape, bool)> {
+    match kind {
+        SymbolKind::And => Some((Shape::Or, true)),
+        SymbolKind::Or => Some((Shape::And, true)),
+        SymbolKind::Not => Some((Shape::Buffer, false)),
+        _ => None,
+    }
+}
+
+/// The De Morgan equivalent of a gate drawn as `original`, the outline of its `dual` squeezed
+/// between negation circles on the `inputs` and, if there is one to negate, the `output`.
+/// The outline is stretched like the gate by `stretch`, the circles are placed at the ports.
+fn de_morgan_gate(
+    original: &SymbolShape,
+    dual: &SymbolShape,
+    stretch: Affine,
+    inputs: &[Point],
+    output: Option<Point>,
+) -> SymbolShape {
+    const RADIUS: f64 = 5.0;
+
+    let Some(extent) = original
+        .paths
+        .iter()
+        .map(|path| path.path.bounding_box())
+        .reduce(|a, b| a.union(b))
+    else {
+        return SymbolShape::default();
+    };
+    let Some(body) = dual.paths.first() else {
+        return SymbolShape::default();
+    };
+
+    let body_extent = body.path.bounding_box();
+    let left = extent.x0 + 2.0 * RADIUS;
+    let right = match output {
+        Some(_) => extent.x1 - 2.0 * RADIUS,
+        None => extent.x1,
+    };
+    let squeeze = Affine::translate((-body_extent.x0, 0.0))
+        .then_scale_non_uniform((right - left) / body_extent.width(), 1.0)
+        .then_translate(Vec2::new(left, 0.0));
+
+    let bubbles = inputs
+        .iter()
+        .map(|input| Point::new(extent.x0 + RADIUS, input.y))
+        .chain(output.map(|output| Point::new(extent.x1 - RADIUS, output.y)))
+        .map(|center| PathInfo {
+            kind: PathKind::FILL | PathKind::STROKE,
+            path: Circle::new(center, RADIUS).to_path(0.1),
+        });
+
+    SymbolShape {
+        paths: std::iter::once(PathInfo {
+            kind: body.kind,
+            path: stretch * squeeze * body.path.clone(),
+        })
+        .chain(bubbles)
+        .collect(),
+    }
+}
+
+/// A rectangular gate filling the outline of `bounding_box`,
+/// with a negation circle on its output if it is `inverted`.
+fn rectangular_gate(bounding_box: BoundingBox, inverted: bool) -> SymbolShape {
+    let (left, top) = (bounding_box.min().x.to_f64(), bounding_box.min().y.to_f64());
+    let (right, bottom) = (bounding_box.max().x.to_f64(), bounding_box.max().y.to_f64());
+    if !inverted {
+        return SymbolShape {
+            paths: vec![PathInfo {
+                kind: PathKind::FILL | PathKind::STROKE,
+                path: Rect::new(left, top, right, bottom).to_path(0.1),
+            }],
+        };
+    }
+
+    let radius = 4.0;
+    let middle = (top + bottom) / 2.0;
+    SymbolShape {
+        paths: vec![
+            PathInfo {
+                kind: PathKind::FILL | PathKind::STROKE,
+                path: Rect::new(left, top, right - 2.0 * radius, bottom).to_path(0.1),
+            },
+            PathInfo {
+                kind: PathKind::STROKE,
+                path: Circle::new((right - radius, middle), radius).to_path(0.1),
+            },
+        ],
+    }
+}
+
+/// Draws gates in the style set in the settings.
+pub fn sync_gate_style(settings: Res<crate::Settings>, mut symbol_shapes: ResMut<SymbolShapes>) {
+    // Don't trigger change detection if nothing changed.
+    if symbol_shapes.gate_style != settings.gate_style {
+        symbol_shapes.gate_style = settings.gate_style;
+    }
+}
+
+pub fn init_symbol_shapes(
+    symbol_registry: Res<SymbolRegistry>,
+    mut symbol_svgs: ResMut<SymbolShapes>,
+) {
+    let gate_bounds = |kind: SymbolKind| {
+        symbol_registry
+            .get_def(kind)
+            .map(|def| def.bounding_box())
+            .unwrap_or_default()
+    };
+    symbol_svgs.rectangular = [
+        (
+            Shape::And,
+            rectangular_gate(gate_bounds(SymbolKind::And), false),
+        ),
+        (
+            Shape::Or,
+            rectangular_gate(gate_bounds(SymbolKind::Or), false),
+        ),
+        (
+            Shape::Xor,
+            rectangular_gate(gate_bounds(SymbolKind::Xor), false),
+        ),
+        (
+            Shape::Not,
+            rectangular_gate(gate_bounds(SymbolKind::Not), true),
+        ),
+    ]
+    .into_iter()
+    .collect();
+
+    symbol_svgs.shapes = vec![
         // Chip
         SymbolShape {
             paths: vec![PathInfo {
@@ -491,5 +1167,176 @@ pub fn init_symbol_shapes(mut symbol_svgs: ResMut<SymbolShapes>) {
                 ),
             }],
         },
+        // FlipFlop -- a box with the clock input marked by a wedge
+        SymbolShape {
+            paths: vec![
+                PathInfo {
+                    kind: PathKind::FILL | PathKind::STROKE,
+                    path: scale_path(bez_path!(M 0,0 H 80 V 80 H 0 Z), 1.0, FLIP_FLOP_TRANSLATE),
+                },
+                PathInfo {
+                    kind: PathKind::STROKE,
+                    path: scale_path(bez_path!(M 0,52 L 10,60 L 0,68), 1.0, FLIP_FLOP_TRANSLATE),
+                },
+            ],
+        },
+        // Mux
+        SymbolShape {
+            paths: vec![PathInfo {
+                kind: PathKind::FILL | PathKind::STROKE,
+                path: mux_path(&MUX_BOUNDING_BOX, false),
+            }],
+        },
+        // Demux
+        SymbolShape {
+            paths: vec![PathInfo {
+                kind: PathKind::FILL | PathKind::STROKE,
+                path: mux_path(&MUX_BOUNDING_BOX, true),
+            }],
+        },
+        // Adder -- a box marked with a plus sign
+        SymbolShape {
+            paths: vec![
+                PathInfo {
+                    kind: PathKind::FILL | PathKind::STROKE,
+                    path: scale_path(bez_path!(M 0,0 H 60 V 60 H 0 Z), 1.0, ARITHMETIC_TRANSLATE),
+                },
+                PathInfo {
+                    kind: PathKind::STROKE,
+                    path: scale_path(
+                        bez_path!(M 22,30 H 38 M 30,22 V 38),
+                        1.0,
+                        ARITHMETIC_TRANSLATE,
+                    ),
+                },
+            ],
+        },
+        // Subtractor -- a box marked with a minus sign
+        SymbolShape {
+            paths: vec![
+                PathInfo {
+                    kind: PathKind::FILL | PathKind::STROKE,
+                    path: scale_path(bez_path!(M 0,0 H 60 V 60 H 0 Z), 1.0, ARITHMETIC_TRANSLATE),
+                },
+                PathInfo {
+                    kind: PathKind::STROKE,
+                    path: scale_path(bez_path!(M 22,30 H 38), 1.0, ARITHMETIC_TRANSLATE),
+                },
+            ],
+        },
+        // Comparator -- a box marked with an equals sign
+        SymbolShape {
+            paths: vec![
+                PathInfo {
+                    kind: PathKind::FILL | PathKind::STROKE,
+                    path: scale_path(bez_path!(M 0,0 H 60 V 60 H 0 Z), 1.0, ARITHMETIC_TRANSLATE),
+                },
+                PathInfo {
+                    kind: PathKind::STROKE,
+                    path: scale_path(
+                        bez_path!(M 22,26 H 38 M 22,34 H 38),
+                        1.0,
+                        ARITHMETIC_TRANSLATE,
+                    ),
+                },
+            ],
+        },
+        // Counter -- the flip-flop outline stretched for the extra inputs
+        SymbolShape {
+            paths: vec![
+                PathInfo {
+                    kind: PathKind::FILL | PathKind::STROKE,
+                    path: scale_path(bez_path!(M 0,0 H 80 V 120 H 0 Z), 1.0, FLIP_FLOP_TRANSLATE),
+                },
+                PathInfo {
+                    kind: PathKind::STROKE,
+                    path: scale_path(bez_path!(M 0,92 L 10,100 L 0,108), 1.0, FLIP_FLOP_TRANSLATE),
+                },
+            ],
+        },
+        // ShiftRegister -- the flip-flop outline stretched for the extra inputs
+        SymbolShape {
+            paths: vec![
+                PathInfo {
+                    kind: PathKind::FILL | PathKind::STROKE,
+                    path: scale_path(bez_path!(M 0,0 H 80 V 140 H 0 Z), 1.0, FLIP_FLOP_TRANSLATE),
+                },
+                PathInfo {
+                    kind: PathKind::STROKE,
+                    path: scale_path(
+                        bez_path!(M 0,112 L 10,120 L 0,128),
+                        1.0,
+                        FLIP_FLOP_TRANSLATE,
+                    ),
+                },
+            ],
+        },
+        // Constant -- a box the value is written into
+        SymbolShape {
+            paths: vec![PathInfo {
+                kind: PathKind::FILL | PathKind::STROKE,
+                path: bez_path!(M -40,-10 H 0 V 10 H -40 Z),
+            }],
+        },
+        // Tunnel -- a flag pointing at its port, the label is written into it
+        SymbolShape {
+            paths: vec![PathInfo {
+                kind: PathKind::FILL | PathKind::STROKE,
+                path: bez_path!(M 0,0 L 10,-10 H 60 V 10 H 10 Z),
+            }],
+        },
+        // Splitter -- a bar the taps leave from, stretched for more than two taps
+        SymbolShape {
+            paths: vec![PathInfo {
+                kind: PathKind::FILL | PathKind::STROKE,
+                path: bez_path!(M 0,-10 H 10 V 30 H 0 Z),
+            }],
+        },
+        // PullUp -- a resistor tied to a supply bar above its port
+        SymbolShape {
+            paths: vec![PathInfo {
+                kind: PathKind::STROKE,
+                path: bez_path!(
+                    M 0,0 V -8 L 6,-11 L -6,-17 L 6,-23 L -6,-29 L 0,-32 V -40 M -10,-40 H 10
+                ),
+            }],
+        },
+        // PullDown -- a resistor tied to a ground symbol below its port
+        SymbolShape {
+            paths: vec![PathInfo {
+                kind: PathKind::STROKE,
+                path: bez_path!(
+                    M 0,0 V 8 L 6,11 L -6,17 L 6,23 L -6,29 L 0,32 V 40 M -10,40 H 10 M -6,44 H 6 M -2,48 H 2
+                ),
+            }],
+        },
+        // Buffer -- a triangle with the enable entering its upper edge
+        SymbolShape {
+            paths: vec![
+                PathInfo {
+                    kind: PathKind::FILL | PathKind::STROKE,
+                    path: bez_path!(M 5,-12 L 35,0 L 5,12 Z),
+                },
+                PathInfo {
+                    kind: PathKind::STROKE,
+                    path: bez_path!(M 0,0 H 5 M 35,0 H 40 M 20,-20 V -6),
+                },
+            ],
+        },
+        // OpenDrain -- a triangle marked with the open-drain diamond
+        SymbolShape {
+            paths: vec![
+                PathInfo {
+                    kind: PathKind::FILL | PathKind::STROKE,
+                    path: bez_path!(M 5,-12 L 35,0 L 5,12 Z),
+                },
+                PathInfo {
+                    kind: PathKind::STROKE,
+                    path: bez_path!(
+                        M 0,0 H 5 M 35,0 H 40 M 12,-4 L 16,0 L 12,4 L 8,0 Z M 8,6 H 16
+                    ),
+                },
+            ],
+        },
     ];
 }
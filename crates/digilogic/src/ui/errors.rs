@@ -0,0 +1,66 @@
+use super::{Egui, MenuSet};
+use bevy_ecs::prelude::*;
+use digilogic_core::events::{ErrorEvent, Severity};
+use egui::*;
+
+const WARNING_COLOR: Color32 = Color32::from_rgb(240, 170, 20);
+const ERROR_COLOR: Color32 = Color32::from_rgb(240, 13, 13);
+
+/// The number of errors kept until the user dismisses them.
+const MAX_ERRORS: usize = 20;
+
+/// The errors reported since the user last dismissed them, oldest first.
+#[derive(Debug, Default, Resource)]
+struct ErrorLog(Vec<ErrorEvent>);
+
+fn collect_errors(mut events: EventReader<ErrorEvent>, mut log: ResMut<ErrorLog>) {
+    for event in events.read() {
+        if log.0.len() == MAX_ERRORS {
+            log.0.remove(0);
+        }
+        log.0.push(event.clone());
+    }
+}
+
+fn update_error_window(egui: Res<Egui>, mut log: ResMut<ErrorLog>) {
+    if log.0.is_empty() {
+        return;
+    }
+
+    let mut dismiss = false;
+    Window::new("Errors")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(Align2::RIGHT_BOTTOM, [-8.0, -8.0])
+        .show(&egui.context, |ui| {
+            for error in log.0.iter() {
+                ui.horizontal(|ui| {
+                    match error.severity {
+                        Severity::Warning => ui.label(RichText::new("⚠").color(WARNING_COLOR)),
+                        Severity::Error => ui.label(RichText::new("⛔").color(ERROR_COLOR)),
+                    };
+                    ui.label(&error.message).on_hover_text(error.source);
+                });
+            }
+
+            ui.add_space(8.0);
+            dismiss = ui.button("Dismiss").clicked();
+        });
+
+    if dismiss {
+        log.0.clear();
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ErrorsPlugin;
+
+impl bevy_app::Plugin for ErrorsPlugin {
+    fn build(&self, app: &mut bevy_app::App) {
+        app.init_resource::<ErrorLog>();
+        app.add_systems(
+            bevy_app::Update,
+            (collect_errors, update_error_window).chain().after(MenuSet),
+        );
+    }
+}
@@ -1,12 +1,16 @@
-use super::{Canvas, Egui, MenuSet, OpenWindows, ViewportBundle};
+use super::{
+    circuit_stats_text, detached_viewport_id, Canvas, Egui, MenuSet, OpenWindows, ViewportBundle,
+};
 use bevy_derive::{Deref, DerefMut};
 use bevy_ecs::prelude::*;
-use bevy_ecs::system::lifetimeless::Read;
+use bevy_ecs::system::lifetimeless::{Read, Write};
 use bevy_ecs::system::SystemParam;
 use bevy_reflect::Reflect;
 use digilogic_core::components::{Circuit, CircuitID, Name, Viewport};
+use digilogic_core::events::CircuitLoadedEvent;
 use digilogic_core::resources::Project;
 use digilogic_core::SharedStr;
+use digilogic_ux::CircuitStats;
 use egui::*;
 use egui_dock::*;
 use egui_wgpu::RenderState;
@@ -67,14 +71,14 @@ fn inject_name_edit_state(trigger: Trigger<OnAdd, Circuit>, mut commands: Comman
 }
 
 #[derive(SystemParam)]
-struct ViewportSpawner<'w, 's> {
+pub(super) struct ViewportSpawner<'w, 's> {
     commands: Commands<'w, 's>,
     dock_state: NonSendMut<'w, DockState<Entity>>,
     viewports: Query<'w, 's, (Entity, Read<CircuitID>), With<Viewport>>,
 }
 
 impl ViewportSpawner<'_, '_> {
-    fn spawn_viewport(&mut self, circuit: CircuitID, render_state: &RenderState) {
+    fn spawn_viewport(&mut self, circuit: CircuitID, render_state: &RenderState) -> Entity {
         let viewport = self
             .commands
             .spawn(ViewportBundle {
@@ -82,37 +86,59 @@ impl ViewportSpawner<'_, '_> {
                 circuit,
                 pan_zoom: Default::default(),
                 scene: Default::default(),
+                overlay: Default::default(),
                 canvas: Canvas::create(render_state),
+                visible_layers: Default::default(),
+                view_visibility: Default::default(),
             })
             .id();
 
         self.dock_state
             .main_surface_mut()
             .push_to_first_leaf(viewport);
+
+        viewport
     }
 
-    fn focus_or_spawn_viewport(&mut self, circuit: CircuitID, render_state: &RenderState) {
+    /// Returns the viewport showing `circuit`.
+    pub(super) fn focus_or_spawn_viewport(&mut self, circuit: CircuitID, egui: &Egui) -> Entity {
         for (viewport, &viewport_circuit) in self.viewports.iter() {
             if viewport_circuit == circuit {
-                let index = self
-                    .dock_state
-                    .find_tab(&viewport)
-                    .expect("viewport without tab");
-                self.dock_state.set_active_tab(index);
-                return;
+                if let Some(index) = self.dock_state.find_tab(&viewport) {
+                    self.dock_state.set_active_tab(index);
+                } else {
+                    // The viewport has been detached into its own window.
+                    egui.context.send_viewport_cmd_to(
+                        detached_viewport_id(viewport),
+                        ViewportCommand::Focus,
+                    );
+                }
+                return viewport;
             }
         }
 
-        self.spawn_viewport(circuit, render_state);
+        self.spawn_viewport(circuit, &egui.render_state)
     }
 }
 
+type CircuitQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        Write<Name>,
+        Write<NameEditState>,
+        Option<Read<CircuitStats>>,
+    ),
+    With<Circuit>,
+>;
+
 fn update_explorer(
     egui: Res<Egui>,
     open_windows: Res<OpenWindows>,
     mut project: Option<ResMut<Project>>,
     mut project_name_edit_state: Local<EditState>,
-    mut circuits: Query<(Entity, &mut Name, &mut NameEditState), With<Circuit>>,
+    mut circuits: CircuitQuery,
     mut edit_buffer: Local<String>,
     mut viewport_spawner: ViewportSpawner,
 ) {
@@ -135,7 +161,7 @@ fn update_explorer(
                         );
                     })
                     .body(|ui| {
-                        for (circuit_id, mut circuit_name, mut circuit_name_edit_state) in
+                        for (circuit_id, mut circuit_name, mut circuit_name_edit_state, stats) in
                             circuits.iter_mut()
                         {
                             if project
@@ -145,18 +171,29 @@ fn update_explorer(
                                 // TODO: visually mark root circuit
                             }
 
-                            let clicked = show_editable_name(
-                                ui,
-                                &mut circuit_name_edit_state,
-                                &mut edit_buffer,
-                                &mut circuit_name.0,
-                            );
+                            // Renaming marks the circuit as modified, so only write actual changes.
+                            let mut name = circuit_name.0.clone();
+                            let clicked = ui
+                                .horizontal(|ui| {
+                                    let clicked = show_editable_name(
+                                        ui,
+                                        &mut circuit_name_edit_state,
+                                        &mut edit_buffer,
+                                        &mut name,
+                                    );
+                                    if let Some(stats) = stats {
+                                        ui.weak(circuit_stats_text(stats));
+                                    }
+                                    clicked
+                                })
+                                .inner;
+                            if name != circuit_name.0 {
+                                circuit_name.0 = name;
+                            }
 
                             if clicked {
-                                viewport_spawner.focus_or_spawn_viewport(
-                                    CircuitID(circuit_id),
-                                    &egui.render_state,
-                                );
+                                viewport_spawner
+                                    .focus_or_spawn_viewport(CircuitID(circuit_id), &egui);
                             }
                         }
                     });
@@ -169,6 +206,24 @@ fn update_explorer(
         });
 }
 
+/// Opens a viewport for the first circuit loaded while no viewport is open,
+/// so leaving the welcome page always shows a circuit.
+fn open_loaded_circuit(
+    egui: Res<Egui>,
+    mut circuit_loaded_events: EventReader<CircuitLoadedEvent>,
+    mut viewport_spawner: ViewportSpawner,
+) {
+    if !viewport_spawner.viewports.is_empty() {
+        circuit_loaded_events.clear();
+        return;
+    }
+
+    if let Some(ev) = circuit_loaded_events.read().next() {
+        viewport_spawner.spawn_viewport(ev.circuit, &egui.render_state);
+    }
+    circuit_loaded_events.clear();
+}
+
 #[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct ExplorerSet;
 
@@ -181,6 +236,9 @@ impl bevy_app::Plugin for ExplorerPlugin {
             .register_type::<NameEditState>();
         app.observe(inject_name_edit_state);
         app.configure_sets(bevy_app::Update, ExplorerSet.after(MenuSet));
-        app.add_systems(bevy_app::Update, update_explorer.in_set(ExplorerSet));
+        app.add_systems(
+            bevy_app::Update,
+            (update_explorer, open_loaded_circuit).in_set(ExplorerSet),
+        );
     }
 }
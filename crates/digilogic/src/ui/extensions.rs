@@ -0,0 +1,216 @@
+use super::{update_tabs, DrawSet, Egui, MenuSet};
+use crate::FileDialogEvent;
+use bevy_ecs::prelude::*;
+use bevy_ecs::system::SystemParam;
+use bevy_reflect::Reflect;
+use digilogic_core::components::{CircuitID, Viewport};
+use digilogic_extension::{CommandRegistry, FileFormats, Overlay, PanelId, PanelRegistry};
+use egui::*;
+use egui_dock::{DockState, NodeIndex};
+
+/// Marks the dock tab showing a panel added by an extension.
+#[derive(Debug, Clone, Copy, Component, Reflect)]
+pub struct ExtensionPanelTab(PanelId);
+
+#[derive(Event, Debug)]
+struct OpenExtensionPanel(PanelId);
+
+/// Where the tabs of extension panels were laid out this frame.
+/// Panels need the whole world, so they are shown once the dock is done.
+#[derive(Debug, Default, Resource)]
+struct PendingPanels(Vec<PendingPanel>);
+
+#[derive(Debug)]
+struct PendingPanel {
+    tab: Entity,
+    panel: PanelId,
+    layer: LayerId,
+    rect: Rect,
+    clip_rect: Rect,
+    enabled: bool,
+}
+
+/// The extension panel tabs of the dock.
+#[derive(SystemParam)]
+pub(super) struct ExtensionPanels<'w, 's> {
+    tabs: Query<'w, 's, &'static ExtensionPanelTab>,
+    panels: Res<'w, PanelRegistry>,
+    pending: ResMut<'w, PendingPanels>,
+}
+
+impl ExtensionPanels<'_, '_> {
+    #[inline]
+    pub(super) fn is_tab(&self, tab: Entity) -> bool {
+        self.tabs.contains(tab)
+    }
+
+    pub(super) fn title(&self, tab: Entity) -> String {
+        self.tabs
+            .get(tab)
+            .ok()
+            .and_then(|&ExtensionPanelTab(panel)| self.panels.get(panel))
+            .map(|panel| panel.title.to_string())
+            .unwrap_or_default()
+    }
+
+    /// Reserves the space of the tab for the panel, which is shown later.
+    pub(super) fn show(&mut self, ui: &mut Ui, tab: Entity) {
+        let Ok(&ExtensionPanelTab(panel)) = self.tabs.get(tab) else {
+            return;
+        };
+
+        let rect = ui.available_rect_before_wrap();
+        self.pending.0.push(PendingPanel {
+            tab,
+            panel,
+            layer: ui.layer_id(),
+            rect,
+            clip_rect: ui.clip_rect(),
+            enabled: ui.is_enabled(),
+        });
+        ui.allocate_rect(rect, Sense::hover());
+    }
+}
+
+/// The menu entries added by extensions.
+#[derive(SystemParam)]
+pub(super) struct ExtensionMenus<'w> {
+    commands: Res<'w, CommandRegistry>,
+    panels: Res<'w, PanelRegistry>,
+    formats: Res<'w, FileFormats>,
+    open_events: EventWriter<'w, OpenExtensionPanel>,
+}
+
+impl ExtensionMenus<'_> {
+    /// Lists the commands added by extensions, if there are any.
+    pub(super) fn show_commands_menu(&self, ui: &mut Ui, commands: &mut Commands) {
+        if self.commands.iter().next().is_none() {
+            return;
+        }
+
+        ui.menu_button("Extensions", |ui| {
+            for command in self.commands.iter() {
+                if ui.button(command.name.as_str()).clicked() {
+                    commands.run_system(command.system);
+                    ui.close_menu();
+                }
+            }
+        });
+        ui.add_space(8.0);
+    }
+
+    pub(super) fn show_panel_buttons(&mut self, ui: &mut Ui) {
+        for (id, panel) in self.panels.iter() {
+            if ui.button(panel.title.as_str()).clicked() {
+                self.open_events.send(OpenExtensionPanel(id));
+                ui.close_menu();
+            }
+        }
+    }
+
+    /// Lists the formats circuits can be exported to, if there are any.
+    pub(super) fn show_export_menu(
+        &self,
+        ui: &mut Ui,
+        circuit: Option<CircuitID>,
+        file_dialog_events: &mut EventWriter<FileDialogEvent>,
+    ) {
+        if self.formats.exporters().is_empty() {
+            return;
+        }
+
+        ui.add_enabled_ui(circuit.is_some(), |ui| {
+            ui.menu_button("Export Circuit", |ui| {
+                for (index, exporter) in self.formats.exporters().iter().enumerate() {
+                    if ui.button(exporter.name.as_str()).clicked() {
+                        if let Some(circuit) = circuit {
+                            file_dialog_events.send(FileDialogEvent::ExportCircuit {
+                                circuit,
+                                exporter: index,
+                            });
+                        }
+                        ui.close_menu();
+                    }
+                }
+            });
+        });
+    }
+}
+
+fn open_extension_panels(
+    mut commands: Commands,
+    mut open_events: EventReader<OpenExtensionPanel>,
+    mut dock_state: NonSendMut<DockState<Entity>>,
+    tabs: Query<(Entity, &ExtensionPanelTab)>,
+) {
+    for &OpenExtensionPanel(panel) in open_events.read() {
+        if let Some((tab, _)) = tabs.iter().find(|(_, tab)| tab.0 == panel) {
+            if let Some(index) = dock_state.find_tab(&tab) {
+                dock_state.set_active_tab(index);
+            }
+            continue;
+        }
+
+        let tab = commands.spawn(ExtensionPanelTab(panel)).id();
+        let surface = dock_state.main_surface_mut();
+        if surface.is_empty() {
+            surface.push_to_first_leaf(tab);
+        } else {
+            surface.split_right(NodeIndex::root(), 0.75, vec![tab]);
+        }
+    }
+}
+
+/// Shows the extension panels in the space their tabs reserved.
+fn show_extension_panels(world: &mut World) {
+    let pending = std::mem::take(&mut world.resource_mut::<PendingPanels>().0);
+    if pending.is_empty() {
+        return;
+    }
+
+    let context = world.resource::<Egui>().context.clone();
+    world.resource_scope(|world, mut panels: Mut<PanelRegistry>| {
+        for pending in pending {
+            let mut ui = Ui::new(
+                context.clone(),
+                pending.layer,
+                Id::new(("extension_panel", pending.tab)),
+                UiBuilder::new().max_rect(pending.rect),
+            );
+            ui.set_clip_rect(pending.clip_rect);
+            if !pending.enabled {
+                ui.disable();
+            }
+            panels.show(pending.panel, &mut ui, world);
+        }
+    });
+}
+
+fn clear_overlays(mut overlays: Query<&mut Overlay, With<Viewport>>) {
+    for mut overlay in overlays.iter_mut() {
+        overlay.0.reset();
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ExtensionUiPlugin;
+
+impl bevy_app::Plugin for ExtensionUiPlugin {
+    fn build(&self, app: &mut bevy_app::App) {
+        app.register_type::<ExtensionPanelTab>();
+        app.init_resource::<PendingPanels>();
+        app.add_event::<OpenExtensionPanel>();
+        app.configure_sets(
+            bevy_app::Update,
+            digilogic_extension::DrawOverlaySet.in_set(DrawSet),
+        );
+        app.add_systems(
+            bevy_app::Update,
+            (
+                clear_overlays.before(digilogic_extension::DrawOverlaySet),
+                open_extension_panels.after(MenuSet).before(update_tabs),
+                show_extension_panels.after(update_tabs),
+            ),
+        );
+    }
+}
@@ -0,0 +1,161 @@
+use super::{Egui, MenuSet};
+use bevy_ecs::prelude::*;
+use bevy_ecs::system::lifetimeless::Read;
+use digilogic_core::components::{Circuit, CircuitID, FilePath, Modified, Name, Viewport};
+use digilogic_core::events::{CircuitReloadEvent, CircuitReloadedEvent, SavedEvent};
+use digilogic_core::visibility::VisibilityOverrides;
+use digilogic_core::HashMap;
+use egui::*;
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime};
+
+/// How often the files of open circuits are checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The files of open circuits, checked for changes made by other programs,
+/// like scripts generating circuits.
+#[derive(Debug, Default, Resource)]
+struct WatchedFiles {
+    /// When each circuit's file was last modified, as far as digilogic knows.
+    modified: HashMap<Entity, SystemTime>,
+    /// Circuits whose files changed, waiting for the user to decide whether to reload them.
+    changed: Vec<Entity>,
+    last_poll: Option<Instant>,
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+fn poll_watched_files(
+    mut watched: ResMut<WatchedFiles>,
+    mut saved_events: EventReader<SavedEvent>,
+    circuits: Query<(Entity, Read<FilePath>), With<Circuit>>,
+) {
+    let watched = &mut *watched;
+
+    // files written by digilogic itself are not changes to prompt about
+    for event in saved_events.read() {
+        if let Some(modified) = modified_time(&event.filename) {
+            watched.modified.insert(event.circuit.0, modified);
+        }
+    }
+
+    let now = Instant::now();
+    if watched
+        .last_poll
+        .is_some_and(|last_poll| (now - last_poll) < POLL_INTERVAL)
+    {
+        return;
+    }
+    watched.last_poll = Some(now);
+
+    watched
+        .modified
+        .retain(|&circuit, _| circuits.contains(circuit));
+    watched
+        .changed
+        .retain(|&circuit| circuits.contains(circuit));
+
+    for (circuit, path) in circuits.iter() {
+        let Some(modified) = modified_time(&path.0) else {
+            continue;
+        };
+        let previous = watched.modified.insert(circuit, modified);
+        if previous.is_some_and(|previous| previous != modified)
+            && !watched.changed.contains(&circuit)
+        {
+            watched.changed.push(circuit);
+        }
+    }
+}
+
+type ChangedCircuitQuery<'w, 's> =
+    Query<'w, 's, (Read<Name>, Read<FilePath>, Has<Modified>), With<Circuit>>;
+
+fn update_reload_prompt(
+    egui: Res<Egui>,
+    mut watched: ResMut<WatchedFiles>,
+    circuits: ChangedCircuitQuery,
+    mut reload_events: EventWriter<CircuitReloadEvent>,
+) {
+    let Some(&circuit) = watched.changed.first() else {
+        return;
+    };
+    let Ok((name, path, modified)) = circuits.get(circuit) else {
+        watched.changed.remove(0);
+        return;
+    };
+
+    let mut reload = false;
+    let mut ignore = false;
+
+    Window::new("File Changed")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(&egui.context, |ui| {
+            ui.label(format!(
+                "{} was changed by another program.",
+                path.0.display()
+            ));
+            ui.label(format!("Do you want to reload {}?", name.0));
+            if modified {
+                ui.colored_label(
+                    ui.visuals().warn_fg_color,
+                    "Your unsaved changes to it will be lost.",
+                );
+            }
+
+            ui.add_space(8.0);
+            ui.horizontal(|ui| {
+                reload = ui.button("Reload").clicked();
+                ignore = ui.button("Ignore").clicked();
+            });
+        });
+
+    if reload {
+        reload_events.send(CircuitReloadEvent {
+            circuit: CircuitID(circuit),
+        });
+    }
+    if reload || ignore {
+        watched.changed.remove(0);
+    }
+}
+
+/// Shows reloaded circuits in the tabs of their previous versions,
+/// so the tabs keep their pan and zoom.
+fn show_reloaded_circuits(
+    mut reloaded_events: EventReader<CircuitReloadedEvent>,
+    mut viewports: Query<(&mut CircuitID, &mut VisibilityOverrides), With<Viewport>>,
+) {
+    for event in reloaded_events.read() {
+        for (mut circuit, mut overrides) in viewports.iter_mut() {
+            if *circuit == event.previous {
+                *circuit = event.circuit;
+                // the overridden entities were despawned with the previous version
+                overrides.0.clear();
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct FileWatchPlugin;
+
+impl bevy_app::Plugin for FileWatchPlugin {
+    fn build(&self, app: &mut bevy_app::App) {
+        app.init_resource::<WatchedFiles>();
+        app.add_systems(
+            bevy_app::Update,
+            (
+                poll_watched_files,
+                update_reload_prompt.after(MenuSet),
+                show_reloaded_circuits,
+            ),
+        );
+    }
+}
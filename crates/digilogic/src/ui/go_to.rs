@@ -0,0 +1,197 @@
+use super::{Canvas, Egui, MenuSet, OpenWindows, PanZoom};
+use aery::prelude::*;
+use bevy_ecs::prelude::*;
+use bevy_ecs::system::lifetimeless::Read;
+use digilogic_core::components::*;
+use digilogic_core::transform::GlobalTransform;
+use digilogic_ux::SelectionSet;
+use egui::*;
+use egui_dock::DockState;
+
+/// A reference designator like `U42` or `U42A`, split into its parts.
+#[derive(Debug, PartialEq, Eq)]
+struct Designator<'a> {
+    prefix: &'a str,
+    number: u32,
+    suffix: &'a str,
+}
+
+fn parse_designator(text: &str) -> Option<Designator<'_>> {
+    let text = text.trim();
+    let number_start = text.find(|c: char| c.is_ascii_digit())?;
+    let (prefix, rest) = text.split_at(number_start);
+    let number_end = rest
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(rest.len());
+    let (number, suffix) = rest.split_at(number_end);
+
+    Some(Designator {
+        prefix,
+        number: number.parse().ok()?,
+        suffix,
+    })
+}
+
+#[derive(Default, Resource)]
+struct GoToDialog {
+    text: String,
+    error: Option<String>,
+    request_focus: bool,
+}
+
+type DesignatorQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        Read<DesignatorPrefix>,
+        Read<DesignatorNumber>,
+        Option<Read<DesignatorSuffix>>,
+        Read<GlobalTransform>,
+    ),
+    With<Symbol>,
+>;
+
+type GoToViewportQuery<'w, 's> =
+    Query<'w, 's, (Read<CircuitID>, &'static mut PanZoom, Read<Canvas>), With<Viewport>>;
+
+#[allow(clippy::too_many_arguments)]
+fn update_go_to_dialog(
+    egui: Res<Egui>,
+    mut open_windows: ResMut<OpenWindows>,
+    mut dialog: ResMut<GoToDialog>,
+    mut dock_state: NonSendMut<DockState<Entity>>,
+    mut viewports: GoToViewportQuery,
+    circuits: Query<Relations<Child>, With<Circuit>>,
+    symbols: DesignatorQuery,
+    mut selection: ResMut<SelectionSet>,
+) {
+    if !open_windows.go_to {
+        dialog.request_focus = true;
+        return;
+    }
+
+    let mut open = true;
+    let mut submit = false;
+    let mut close = false;
+
+    Window::new("Go to Designator")
+        .open(&mut open)
+        .collapsible(false)
+        .resizable(false)
+        .anchor(Align2::CENTER_TOP, [0.0, 64.0])
+        .show(&egui.context, |ui| {
+            let edit = ui.add(
+                TextEdit::singleline(&mut dialog.text)
+                    .hint_text("U42")
+                    .desired_width(160.0),
+            );
+            if dialog.request_focus {
+                edit.request_focus();
+                dialog.request_focus = false;
+            }
+
+            if edit.lost_focus() {
+                if ui.input(|state| state.key_pressed(Key::Enter)) {
+                    submit = true;
+                } else if ui.input(|state| state.key_pressed(Key::Escape)) {
+                    close = true;
+                }
+            }
+
+            if let Some(error) = &dialog.error {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+            }
+        });
+
+    if submit {
+        let focused = dock_state
+            .find_active_focused()
+            .map(|(_, &mut viewport)| viewport)
+            .filter(|&viewport| viewports.contains(viewport));
+
+        match (parse_designator(&dialog.text), focused) {
+            (None, _) => {
+                dialog.error = Some(format!("\"{}\" is not a designator", dialog.text.trim()));
+            }
+            (Some(_), None) => {
+                dialog.error = Some("No circuit is open".to_owned());
+            }
+            (Some(designator), Some(viewport)) => {
+                let (&circuit, mut pan_zoom, canvas) = viewports.get_mut(viewport).unwrap();
+
+                let mut found = None;
+                if let Ok(edges) = circuits.get(circuit.0) {
+                    edges.join::<Child>(&symbols).for_each(
+                        |(symbol, prefix, number, suffix, transform)| {
+                            let suffix = suffix.map(|suffix| suffix.0.as_str()).unwrap_or("");
+                            if prefix.0.eq_ignore_ascii_case(designator.prefix)
+                                && (number.0 == designator.number)
+                                && suffix.eq_ignore_ascii_case(designator.suffix)
+                            {
+                                found = Some((symbol, transform.translation));
+                            }
+                        },
+                    );
+                }
+
+                if let Some((symbol, position)) = found {
+                    let position = Vec2::new(position.x.to_f32(), position.y.to_f32());
+                    let center = Vec2::new(canvas.width() as f32, canvas.height() as f32) / 2.0;
+                    pan_zoom.pan = center / pan_zoom.zoom - position;
+
+                    selection.select_only(symbol);
+                    close = true;
+                } else {
+                    dialog.error =
+                        Some(format!("No symbol {} in this circuit", dialog.text.trim()));
+                }
+            }
+        }
+
+        // keep typing after a failed attempt
+        dialog.request_focus = !close;
+    }
+
+    if close || !open {
+        dialog.text.clear();
+        dialog.error = None;
+        open_windows.go_to = false;
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct GoToPlugin;
+
+impl bevy_app::Plugin for GoToPlugin {
+    fn build(&self, app: &mut bevy_app::App) {
+        app.init_resource::<GoToDialog>();
+        app.add_systems(bevy_app::Update, update_go_to_dialog.after(MenuSet));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_designators() {
+        assert_eq!(
+            parse_designator(" U42 "),
+            Some(Designator {
+                prefix: "U",
+                number: 42,
+                suffix: "",
+            })
+        );
+        assert_eq!(
+            parse_designator("U3B"),
+            Some(Designator {
+                prefix: "U",
+                number: 3,
+                suffix: "B",
+            })
+        );
+        assert_eq!(parse_designator("U"), None);
+    }
+}
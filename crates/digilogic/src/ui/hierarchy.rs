@@ -0,0 +1,183 @@
+use super::{Egui, ViewportSpawner};
+use aery::prelude::*;
+use bevy_ecs::prelude::*;
+use bevy_ecs::system::lifetimeless::Read;
+use bevy_ecs::system::SystemParam;
+use digilogic_core::components::*;
+use digilogic_ux::{EnterSubCircuit, SelectionSet};
+use egui::*;
+
+/// The sub-circuit symbols a viewport was entered through, outermost first.
+/// Viewports opened from the explorer don't have a path.
+#[derive(Debug, Default, Clone, Component)]
+pub(super) struct InstancePath(Vec<Entity>);
+
+/// Requests the circuit `depth` levels down the instance path of `viewport` to be shown,
+/// with the instance that leads further down selected.
+#[derive(Event, Debug)]
+struct NavigateHierarchy {
+    viewport: Entity,
+    depth: usize,
+}
+
+type InstanceQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        (
+            Read<DesignatorPrefix>,
+            Read<DesignatorNumber>,
+            Read<SubCircuit>,
+        ),
+        Relations<Child>,
+    ),
+    With<Symbol>,
+>;
+
+type CircuitQuery<'w, 's> = Query<'w, 's, (Entity, Read<Name>), With<Circuit>>;
+
+/// The circuit an instance is placed in.
+fn parent_circuit(
+    instances: &InstanceQuery,
+    circuits: &CircuitQuery,
+    instance: Entity,
+) -> Option<(Entity, Name)> {
+    let (_, edges) = instances.get(instance).ok()?;
+    let mut parent = None;
+    edges
+        .join::<Up<Child>>(circuits)
+        .for_each(|(circuit, name)| parent = Some((circuit, name.clone())));
+    parent
+}
+
+#[derive(SystemParam)]
+pub(super) struct Breadcrumbs<'w, 's> {
+    paths: Query<'w, 's, Read<InstancePath>>,
+    instances: InstanceQuery<'w, 's>,
+    circuits: CircuitQuery<'w, 's>,
+    navigate_events: EventWriter<'w, NavigateHierarchy>,
+}
+
+impl Breadcrumbs<'_, '_> {
+    /// Shows the instance path of a viewport above it, if it was entered through a sub-circuit symbol.
+    pub(super) fn show(&mut self, ui: &mut Ui, viewport: Entity) {
+        let Ok(path) = self.paths.get(viewport) else {
+            return;
+        };
+        let Some((_, root_name)) = path
+            .0
+            .first()
+            .and_then(|&instance| parent_circuit(&self.instances, &self.circuits, instance))
+        else {
+            return;
+        };
+
+        let mut labels = vec![root_name.0.to_string()];
+        for &instance in &path.0 {
+            // the path is stale if one of its instances has been deleted
+            let Ok(((prefix, number, sub_circuit), _)) = self.instances.get(instance) else {
+                return;
+            };
+            let circuit_name = self
+                .circuits
+                .get(sub_circuit.0)
+                .map(|(_, name)| name.0.as_str())
+                .unwrap_or_default();
+            labels.push(format!("{}{} ({})", prefix.0, number.0, circuit_name));
+        }
+
+        let mut navigate = None;
+        TopBottomPanel::top("breadcrumbs").show_inside(ui, |ui| {
+            ui.horizontal(|ui| {
+                if ui
+                    .button("⬆ Up")
+                    .on_hover_text("Return to the parent circuit")
+                    .clicked()
+                {
+                    navigate = Some(path.0.len() - 1);
+                }
+
+                ui.separator();
+
+                let current = labels.len() - 1;
+                for (depth, label) in labels.iter().enumerate() {
+                    if depth > 0 {
+                        ui.label("›");
+                    }
+                    if ui.selectable_label(depth == current, label).clicked() && (depth != current)
+                    {
+                        navigate = Some(depth);
+                    }
+                }
+            });
+        });
+
+        if let Some(depth) = navigate {
+            self.navigate_events
+                .send(NavigateHierarchy { viewport, depth });
+        }
+    }
+}
+
+fn enter_sub_circuits(
+    egui: Res<Egui>,
+    mut commands: Commands,
+    mut enter_events: EventReader<EnterSubCircuit>,
+    paths: Query<&InstancePath>,
+    mut viewport_spawner: ViewportSpawner,
+) {
+    for event in enter_events.read() {
+        let mut path = paths.get(event.viewport).cloned().unwrap_or_default();
+        path.0.push(event.instance);
+
+        let viewport = viewport_spawner.focus_or_spawn_viewport(event.circuit, &egui);
+        commands.entity(viewport).insert(path);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn navigate_hierarchy(
+    egui: Res<Egui>,
+    mut commands: Commands,
+    mut navigate_events: EventReader<NavigateHierarchy>,
+    paths: Query<&InstancePath>,
+    instances: InstanceQuery,
+    circuits: CircuitQuery,
+    mut selection: ResMut<SelectionSet>,
+    mut viewport_spawner: ViewportSpawner,
+) {
+    for event in navigate_events.read() {
+        let Ok(path) = paths.get(event.viewport) else {
+            continue;
+        };
+        let Some(&instance) = path.0.get(event.depth) else {
+            continue;
+        };
+        let Some((circuit, _)) = parent_circuit(&instances, &circuits, instance) else {
+            continue;
+        };
+
+        let viewport = viewport_spawner.focus_or_spawn_viewport(CircuitID(circuit), &egui);
+        let parent_path = InstancePath(path.0[..event.depth].to_vec());
+        if parent_path.0.is_empty() {
+            commands.entity(viewport).remove::<InstancePath>();
+        } else {
+            commands.entity(viewport).insert(parent_path);
+        }
+
+        selection.select_only(instance);
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct HierarchyPlugin;
+
+impl bevy_app::Plugin for HierarchyPlugin {
+    fn build(&self, app: &mut bevy_app::App) {
+        app.add_event::<NavigateHierarchy>();
+        app.add_systems(
+            bevy_app::Update,
+            (enter_sub_circuits, navigate_hierarchy).after(super::update_tabs),
+        );
+    }
+}
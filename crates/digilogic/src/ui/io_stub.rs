@@ -0,0 +1,102 @@
+use super::{update_tabs, PanZoom};
+use bevy_ecs::prelude::*;
+use bevy_ecs::system::SystemParam;
+use digilogic_core::components::SymbolKind;
+use digilogic_ux::{CreateIoSymbol, OfferIoSymbol};
+use egui::*;
+
+/// The offer to create an input or output symbol at the end of a drag from a port.
+#[derive(Default, Resource)]
+struct PendingIoSymbol(Option<OfferIoSymbol>);
+
+fn receive_io_symbol_offers(
+    mut events: EventReader<OfferIoSymbol>,
+    mut pending: ResMut<PendingIoSymbol>,
+) {
+    if let Some(event) = events.read().last() {
+        pending.0 = Some(event.clone());
+    }
+}
+
+#[derive(SystemParam)]
+pub(super) struct IoSymbolOffer<'w, 's> {
+    commands: Commands<'w, 's>,
+    pending: ResMut<'w, PendingIoSymbol>,
+}
+
+impl IoSymbolOffer<'_, '_> {
+    /// Shows the offer where the drag ended, if it ended in `viewport`.
+    pub(super) fn show(
+        &mut self,
+        ui: &Ui,
+        response: &Response,
+        viewport: Entity,
+        pan_zoom: &PanZoom,
+    ) {
+        let Some(offer) = self
+            .pending
+            .0
+            .as_ref()
+            .filter(|offer| offer.viewport == viewport)
+        else {
+            return;
+        };
+
+        let position = Vec2::new(offer.position.x.to_f32(), offer.position.y.to_f32());
+        let anchor = response.rect.left_top() + (position + pan_zoom.pan) * pan_zoom.zoom;
+
+        // the kind fitting the port comes first
+        let kinds = match offer.suggested {
+            SymbolKind::Out => [
+                (SymbolKind::Out, "Create Output"),
+                (SymbolKind::In, "Create Input"),
+            ],
+            _ => [
+                (SymbolKind::In, "Create Input"),
+                (SymbolKind::Out, "Create Output"),
+            ],
+        };
+
+        let mut chosen = None;
+        let area = Area::new(Id::new(("io_symbol_offer", viewport)))
+            .fixed_pos(anchor)
+            .order(Order::Foreground)
+            .show(ui.ctx(), |ui| {
+                Frame::popup(ui.style()).show(ui, |ui| {
+                    for (kind, label) in kinds {
+                        if ui.button(label).clicked() {
+                            chosen = Some(kind);
+                        }
+                    }
+                });
+            });
+
+        let cancel =
+            area.response.clicked_elsewhere() || ui.input(|state| state.key_pressed(Key::Escape));
+
+        if let Some(kind) = chosen {
+            self.commands.trigger(CreateIoSymbol {
+                circuit: offer.circuit,
+                port: offer.port,
+                kind,
+                position: offer.position,
+            });
+            self.pending.0 = None;
+        } else if cancel {
+            self.pending.0 = None;
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct IoStubPlugin;
+
+impl bevy_app::Plugin for IoStubPlugin {
+    fn build(&self, app: &mut bevy_app::App) {
+        app.init_resource::<PendingIoSymbol>();
+        app.add_systems(
+            bevy_app::Update,
+            receive_io_symbol_offers.before(update_tabs),
+        );
+    }
+}
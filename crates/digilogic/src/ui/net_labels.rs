@@ -0,0 +1,233 @@
+use super::{Egui, OpenWindows, PanZoom};
+use aery::prelude::*;
+use bevy_ecs::prelude::*;
+use bevy_ecs::system::lifetimeless::Read;
+use bevy_ecs::system::SystemParam;
+use digilogic_core::components::*;
+use digilogic_core::transform::GlobalTransform;
+use digilogic_routing::{Vertex, VertexKind, Vertices};
+use digilogic_ux::{RenameNet, SelectionSet};
+use egui::*;
+use egui_dock::DockState;
+
+const RENAME_NET_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::NONE, Key::F2);
+const LABEL_FONT_SIZE: f32 = 12.0;
+
+/// The net whose label is being edited inline, and the viewport it is edited in.
+#[derive(Default, Resource)]
+pub(super) struct NetRenaming {
+    target: Option<(Entity, Entity)>,
+    text: String,
+    request_focus: bool,
+}
+
+impl NetRenaming {
+    fn start(&mut self, viewport: Entity, net: Entity, name: &str) {
+        self.target = Some((viewport, net));
+        self.text = name.to_owned();
+        self.request_focus = true;
+    }
+}
+
+/// Labels are placed at the middle of the longest segment of a net's wires.
+fn label_position(vertices: &[Vertex]) -> Option<digilogic_core::transform::Vec2> {
+    vertices
+        .windows(2)
+        .filter(|pair| !matches!(pair[0].kind, VertexKind::WireEnd { .. }))
+        .max_by_key(|pair| pair[0].position.manhatten_distance_to(pair[1].position))
+        .map(|pair| (pair[0].position + pair[1].position) * digilogic_core::fixed!(0.5))
+}
+
+type NetLabelQuery<'w, 's> = Query<'w, 's, (Entity, Read<Name>, Read<Vertices>), With<Net>>;
+
+type BitLabelQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        (Option<Read<GlobalTransform>>, Option<Read<Bits>>),
+        Relations<Child>,
+    ),
+>;
+
+#[derive(SystemParam)]
+pub(super) struct NetLabels<'w, 's> {
+    circuits: Query<'w, 's, ((), Relations<Child>), With<Circuit>>,
+    nets: NetLabelQuery<'w, 's>,
+    bit_labels: BitLabelQuery<'w, 's>,
+    renaming: ResMut<'w, NetRenaming>,
+    rename_events: EventWriter<'w, RenameNet>,
+}
+
+impl NetLabels<'_, '_> {
+    /// Draws the labels of the nets in `circuit` and the inline editor if one of them is renamed.
+    /// Returns whether a double-click was used to start renaming.
+    pub(super) fn show(
+        &mut self,
+        ui: &Ui,
+        response: &Response,
+        viewport: Entity,
+        circuit: CircuitID,
+        pan_zoom: &PanZoom,
+    ) -> bool {
+        let Ok((_, edges)) = self.circuits.get(circuit.0) else {
+            return false;
+        };
+
+        let mut labels = Vec::new();
+        edges
+            .join::<Child>(&self.nets)
+            .for_each(|(net, name, vertices)| {
+                if let Some(position) = label_position(vertices) {
+                    labels.push((net, name.0.clone(), position));
+                }
+            });
+
+        let to_screen = |position: digilogic_core::transform::Vec2| {
+            let position = Vec2::new(position.x.to_f32(), position.y.to_f32());
+            response.rect.left_top() + (position + pan_zoom.pan) * pan_zoom.zoom
+        };
+
+        let painter = ui.painter_at(response.rect);
+        let font = FontId::proportional(LABEL_FONT_SIZE);
+        let color = ui.visuals().text_color();
+        let pointer = ui.input(|state| state.pointer.interact_pos());
+        let mut consumed_double_click = false;
+
+        let editing = self
+            .renaming
+            .target
+            .filter(|&(target_viewport, _)| target_viewport == viewport)
+            .map(|(_, net)| net);
+        if editing.is_some_and(|net| labels.iter().all(|&(label_net, _, _)| label_net != net)) {
+            // the net was deleted or moved to another circuit
+            self.renaming.target = None;
+        }
+
+        for (net, name, position) in &labels {
+            let anchor = to_screen(*position) - Vec2::new(0.0, 2.0);
+
+            if editing == Some(*net) {
+                let duplicate = labels.iter().any(|(other, other_name, _)| {
+                    (other != net) && (**other_name == *self.renaming.text)
+                });
+                self.show_editor(ui, viewport, *net, anchor, duplicate);
+                continue;
+            }
+
+            if name.is_empty() {
+                continue;
+            }
+
+            let galley = painter.layout_no_wrap(name.to_string(), font.clone(), color);
+            let rect = Align2::CENTER_BOTTOM.anchor_size(anchor, galley.size());
+            painter.galley(rect.min, galley, color);
+
+            if response.double_clicked() && pointer.is_some_and(|pointer| rect.contains(pointer)) {
+                self.renaming.start(viewport, *net, name);
+                consumed_double_click = true;
+            }
+        }
+
+        // endpoints that only carry some bits of their net show which ones
+        let bits_color = ui.visuals().weak_text_color();
+        self.bit_labels
+            .traverse::<Child>(std::iter::once(circuit.0))
+            .for_each(|&mut (transform, bits), _| {
+                let (Some(transform), Some(bits)) = (transform, bits) else {
+                    return;
+                };
+
+                let anchor = to_screen(transform.translation) + Vec2::new(4.0, -4.0);
+                painter.text(
+                    anchor,
+                    Align2::LEFT_BOTTOM,
+                    format!("[{bits}]"),
+                    font.clone(),
+                    bits_color,
+                );
+            });
+
+        consumed_double_click
+    }
+
+    fn show_editor(
+        &mut self,
+        ui: &Ui,
+        viewport: Entity,
+        net: Entity,
+        anchor: Pos2,
+        duplicate: bool,
+    ) {
+        let renaming = &mut *self.renaming;
+
+        Area::new(Id::new(("net_label_editor", viewport)))
+            .fixed_pos(anchor)
+            .pivot(Align2::CENTER_BOTTOM)
+            .order(Order::Foreground)
+            .show(ui.ctx(), |ui| {
+                Frame::popup(ui.style()).show(ui, |ui| {
+                    let edit = ui.add(
+                        TextEdit::singleline(&mut renaming.text)
+                            .font(FontId::proportional(LABEL_FONT_SIZE))
+                            .desired_width(120.0),
+                    );
+                    if renaming.request_focus {
+                        edit.request_focus();
+                        renaming.request_focus = false;
+                    }
+
+                    if duplicate {
+                        ui.colored_label(
+                            ui.visuals().warn_fg_color,
+                            "Another net already has this name",
+                        );
+                    }
+
+                    if edit.lost_focus() {
+                        // Escape cancels, Enter or clicking elsewhere keeps the new name
+                        if !ui.input(|state| state.key_pressed(Key::Escape)) {
+                            self.rename_events.send(RenameNet {
+                                net,
+                                name: renaming.text.as_str().into(),
+                            });
+                        }
+                        renaming.target = None;
+                    }
+                });
+            });
+    }
+}
+
+/// Starts renaming the selected net in the focused viewport.
+pub(super) fn handle_rename_shortcut(
+    egui: Res<Egui>,
+    open_windows: Res<OpenWindows>,
+    mut dock_state: NonSendMut<DockState<Entity>>,
+    viewports: Query<(), With<Viewport>>,
+    selection: Res<SelectionSet>,
+    nets: Query<&Name, With<Net>>,
+    mut renaming: ResMut<NetRenaming>,
+) {
+    if open_windows.any() || egui.context.wants_keyboard_input() {
+        return;
+    }
+
+    let Some((_, &mut viewport)) = dock_state.find_active_focused() else {
+        return;
+    };
+    if !viewports.contains(viewport) {
+        return;
+    }
+
+    if egui
+        .context
+        .input_mut(|state| state.consume_shortcut(&RENAME_NET_SHORTCUT))
+    {
+        let selected_net = selection
+            .iter()
+            .find_map(|entity| nets.get(entity).ok().map(|name| (entity, name)));
+        if let Some((net, name)) = selected_net {
+            renaming.start(viewport, net, name);
+        }
+    }
+}
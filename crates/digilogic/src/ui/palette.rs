@@ -372,7 +372,7 @@ impl PaletteBrushes {
         sim_state: Option<&digilogic_netcode::SimState>,
         offset: Option<digilogic_netcode::StateOffset>,
         width: Option<digilogic_core::components::BitWidth>,
-    ) -> Option<BrushRef> {
+    ) -> Option<BrushRef<'_>> {
         const MAX_BIT_PLANE_SIZE: usize = 32;
         let mut bit_plane_0 = [0u8; MAX_BIT_PLANE_SIZE];
         let mut bit_plane_1 = [0u8; MAX_BIT_PLANE_SIZE];
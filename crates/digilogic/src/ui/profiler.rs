@@ -0,0 +1,265 @@
+use super::{update_tabs, MenuSet};
+use crate::Settings;
+use bevy_ecs::prelude::*;
+use bevy_reflect::Reflect;
+use bevy_time::{Real, Time};
+use digilogic_core::HashMap;
+use egui::*;
+use egui_dock::{DockState, NodeIndex};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// The number of frames the profiler keeps timings of.
+const HISTORY_LEN: usize = 240;
+
+const GRAPH_HEIGHT: f32 = 32.0;
+
+/// The name the total time of each frame is shown under.
+const FRAME_SERIES: &str = "frame";
+
+/// Marks the dock tab that shows the profiler.
+#[derive(Debug, Default, Clone, Copy, Component, Reflect)]
+pub struct ProfilerTab;
+
+/// The time spent in each kind of profiled span since the last frame,
+/// filled in by the tracing layer from any thread.
+#[derive(Debug, Default, Clone, Resource)]
+struct SpanTimings(Arc<Mutex<HashMap<&'static str, Duration>>>);
+
+#[cfg(not(target_arch = "wasm32"))]
+mod layer {
+    use super::SpanTimings;
+    use bevy_log::tracing_subscriber::layer::{Context, Layer};
+    use bevy_log::tracing_subscriber::registry::LookupSpan;
+    use std::time::Instant;
+    use tracing::span::Id;
+    use tracing::Subscriber;
+
+    struct Entered(Instant);
+
+    /// Adds up the time spent in spans with [`digilogic_core::PROFILE_TARGET`] by name.
+    pub(super) struct SpanTimingLayer(pub(super) SpanTimings);
+
+    impl<S> Layer<S> for SpanTimingLayer
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+            let Some(span) = ctx.span(id) else {
+                return;
+            };
+            if span.metadata().target() == digilogic_core::PROFILE_TARGET {
+                span.extensions_mut().replace(Entered(Instant::now()));
+            }
+        }
+
+        fn on_exit(&self, id: &Id, ctx: Context<'_, S>) {
+            let Some(span) = ctx.span(id) else {
+                return;
+            };
+            let Some(Entered(start)) = span.extensions_mut().remove::<Entered>() else {
+                return;
+            };
+
+            let elapsed = start.elapsed();
+            let timings = (self.0).0.lock();
+            if let Ok(mut timings) = timings {
+                *timings.entry(span.name()).or_default() += elapsed;
+            }
+        }
+    }
+}
+
+/// Installs the layer collecting the timings of profiled spans, passed to the `LogPlugin`.
+pub(crate) fn profiler_layer(app: &mut bevy_app::App) -> Option<bevy_log::BoxedLayer> {
+    let timings = SpanTimings::default();
+    app.insert_resource(timings.clone());
+
+    #[cfg(not(target_arch = "wasm32"))]
+    return Some(Box::new(layer::SpanTimingLayer(timings)));
+
+    #[cfg(target_arch = "wasm32")]
+    return None;
+}
+
+/// The time spent per frame in each kind of profiled span, in milliseconds, oldest first.
+#[derive(Debug, Default, Resource)]
+struct ProfilerHistory(BTreeMap<&'static str, VecDeque<f32>>);
+
+impl ProfilerHistory {
+    fn push(&mut self, name: &'static str, time: Duration) {
+        let history = self.0.entry(name).or_default();
+        if history.len() == HISTORY_LEN {
+            history.pop_front();
+        }
+        history.push_back(time.as_secs_f32() * 1000.0);
+    }
+}
+
+fn collect_span_timings(
+    timings: Option<Res<SpanTimings>>,
+    time: Res<Time<Real>>,
+    mut history: ResMut<ProfilerHistory>,
+    tabs: Query<(), With<ProfilerTab>>,
+) {
+    let Some(timings) = timings else {
+        return;
+    };
+    let Ok(mut timings) = timings.0.lock() else {
+        return;
+    };
+
+    // Spans keep being recorded while the profiler is closed, they just aren't kept.
+    if tabs.is_empty() {
+        timings.clear();
+        return;
+    }
+
+    history.push(FRAME_SERIES, time.delta());
+    for name in history.0.keys().copied().collect::<Vec<_>>() {
+        if name != FRAME_SERIES {
+            let time = timings.remove(name).unwrap_or_default();
+            history.push(name, time);
+        }
+    }
+    for (name, time) in timings.drain() {
+        history.push(name, time);
+    }
+}
+
+fn show_graph(ui: &mut Ui, values: &VecDeque<f32>, max: f32) {
+    let (rect, _) = ui.allocate_exact_size(
+        Vec2::new(ui.available_width(), GRAPH_HEIGHT),
+        Sense::hover(),
+    );
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+    if values.len() < 2 {
+        return;
+    }
+
+    let step = rect.width() / ((HISTORY_LEN - 1) as f32);
+    let offset = (HISTORY_LEN - values.len()) as f32 * step;
+    let points = values
+        .iter()
+        .enumerate()
+        .map(|(i, &value)| {
+            Pos2::new(
+                rect.left() + offset + (i as f32) * step,
+                rect.bottom() - (value / max).min(1.0) * rect.height(),
+            )
+        })
+        .collect();
+    painter.add(Shape::line(
+        points,
+        Stroke::new(1.0, ui.visuals().selection.stroke.color),
+    ));
+}
+
+/// Lists the profiled spans with a graph of their recent timings.
+fn show_profiler(ui: &mut Ui, history: &ProfilerHistory) {
+    ScrollArea::vertical().auto_shrink(false).show(ui, |ui| {
+        if history.0.is_empty() {
+            ui.weak("No timings recorded yet");
+            return;
+        }
+
+        // Spans share the scale of the frame time, so they can be compared at a glance.
+        let max = history.0.values().flatten().copied().fold(1.0f32, f32::max);
+
+        let frame = history.0.get(FRAME_SERIES).into_iter();
+        let spans = history.0.iter().filter(|(name, _)| **name != FRAME_SERIES);
+        for (name, values) in frame.map(|values| (&FRAME_SERIES, values)).chain(spans) {
+            let latest = values.back().copied().unwrap_or_default();
+            let peak = values.iter().copied().fold(0.0f32, f32::max);
+            let average = values.iter().sum::<f32>() / (values.len().max(1) as f32);
+
+            ui.horizontal(|ui| {
+                ui.strong(*name);
+                ui.weak(format!(
+                    "{latest:.2} ms, average {average:.2} ms, peak {peak:.2} ms"
+                ));
+            });
+            show_graph(ui, values, max);
+            ui.add_space(4.0);
+        }
+    });
+}
+
+/// Shows the profiler in its tab.
+#[derive(bevy_ecs::system::SystemParam)]
+pub(super) struct Profiler<'w, 's> {
+    tabs: Query<'w, 's, (), With<ProfilerTab>>,
+    history: Res<'w, ProfilerHistory>,
+}
+
+impl Profiler<'_, '_> {
+    #[inline]
+    pub(super) fn is_tab(&self, tab: Entity) -> bool {
+        self.tabs.contains(tab)
+    }
+
+    pub(super) fn show(&self, ui: &mut Ui) {
+        show_profiler(ui, &self.history);
+    }
+}
+
+/// Keeps the profiler tab open while it is enabled in the settings,
+/// and disables it when the tab is closed.
+fn sync_profiler_tab(
+    mut commands: Commands,
+    mut settings: ResMut<Settings>,
+    mut dock_state: NonSendMut<DockState<Entity>>,
+    tabs: Query<Entity, With<ProfilerTab>>,
+    mut history: ResMut<ProfilerHistory>,
+    mut shown: Local<bool>,
+) {
+    // The settings are marked as changed whenever the preferences are open,
+    // so toggling is detected by comparing against the last state instead.
+    let tab = tabs.iter().next();
+
+    if settings.show_profiler == *shown {
+        if tab.is_none() && *shown {
+            settings.show_profiler = false;
+            *shown = false;
+        }
+        return;
+    }
+    *shown = settings.show_profiler;
+
+    match (settings.show_profiler, tab) {
+        (true, None) => {
+            let tab = commands.spawn(ProfilerTab).id();
+            let surface = dock_state.main_surface_mut();
+            if surface.is_empty() {
+                surface.push_to_first_leaf(tab);
+            } else {
+                surface.split_right(NodeIndex::root(), 0.75, vec![tab]);
+            }
+        }
+        (false, Some(tab)) => {
+            if let Some(index) = dock_state.find_tab(&tab) {
+                dock_state.remove_tab(index);
+            }
+            commands.entity(tab).despawn();
+            history.0.clear();
+        }
+        _ => (),
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ProfilerPlugin;
+
+impl bevy_app::Plugin for ProfilerPlugin {
+    fn build(&self, app: &mut bevy_app::App) {
+        app.register_type::<ProfilerTab>();
+        app.init_resource::<ProfilerHistory>();
+        app.add_systems(
+            bevy_app::Update,
+            sync_profiler_tab.after(MenuSet).before(update_tabs),
+        );
+        app.add_systems(bevy_app::Last, collect_span_timings);
+    }
+}
@@ -0,0 +1,230 @@
+use super::{Egui, OpenWindows};
+use bevy_ecs::prelude::*;
+use digilogic_core::components::{BitWidth, LogicState, ParamValue, Parameters, SymbolKind};
+use digilogic_core::symbol::{ParamType, SymbolRegistry, VALUE_PARAM, WIDTH_PARAM};
+use digilogic_core::transform::Rotation;
+use digilogic_ux::{
+    EditSymbolProperties, SetSymbolProperties, SymbolProperties, SymbolPropertiesQuery,
+};
+use egui::*;
+use std::num::NonZeroU8;
+
+/// The symbol shown in the properties dialog, together with the edited values.
+#[derive(Default, Resource)]
+struct PropertiesDialog {
+    symbol: Option<Entity>,
+    name: String,
+    designator_prefix: String,
+    designator_number: u32,
+    designator_suffix: String,
+    bit_width: u8,
+    rotation: Rotation,
+    parameters: Parameters,
+}
+
+impl PropertiesDialog {
+    fn open(&mut self, symbol: Entity, properties: SymbolProperties) {
+        *self = Self {
+            symbol: Some(symbol),
+            name: properties.name.to_string(),
+            designator_prefix: properties.designator_prefix.to_string(),
+            designator_number: properties.designator_number,
+            designator_suffix: properties.designator_suffix.to_string(),
+            bit_width: properties.bit_width.get(),
+            rotation: properties.rotation,
+            parameters: properties.parameters,
+        };
+    }
+
+    fn properties(&self) -> SymbolProperties {
+        SymbolProperties {
+            name: self.name.as_str().into(),
+            designator_prefix: self.designator_prefix.as_str().into(),
+            designator_number: self.designator_number,
+            designator_suffix: self.designator_suffix.as_str().into(),
+            bit_width: NonZeroU8::new(self.bit_width).unwrap_or(NonZeroU8::MIN),
+            rotation: self.rotation,
+            parameters: self.parameters.clone(),
+        }
+    }
+}
+
+const fn rotation_text(rotation: Rotation) -> &'static str {
+    match rotation {
+        Rotation::Rot0 => "0°",
+        Rotation::Rot90 => "90°",
+        Rotation::Rot180 => "180°",
+        Rotation::Rot270 => "270°",
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_properties_dialog(
+    egui: Res<Egui>,
+    mut open_windows: ResMut<OpenWindows>,
+    mut dialog: ResMut<PropertiesDialog>,
+    mut edit_events: EventReader<EditSymbolProperties>,
+    mut set_events: EventWriter<SetSymbolProperties>,
+    symbols: SymbolPropertiesQuery,
+    kinds: Query<&SymbolKind>,
+    symbol_registry: Res<SymbolRegistry>,
+) {
+    if let Some(event) = edit_events.read().last() {
+        if let Some(properties) = SymbolProperties::read(&symbols, event.symbol) {
+            dialog.open(event.symbol, properties);
+            open_windows.symbol_properties = true;
+        }
+    }
+
+    let Some(symbol) = dialog.symbol else {
+        open_windows.symbol_properties = false;
+        return;
+    };
+
+    // the symbol may have been deleted while the dialog was open
+    let Some(current) = SymbolProperties::read(&symbols, symbol) else {
+        dialog.symbol = None;
+        open_windows.symbol_properties = false;
+        return;
+    };
+
+    let params = kinds
+        .get(symbol)
+        .ok()
+        .and_then(|&kind| symbol_registry.get_def(kind))
+        .map(|def| def.params())
+        .unwrap_or_default();
+
+    let mut open = open_windows.symbol_properties;
+    let mut apply = false;
+    let mut close = false;
+
+    Window::new("Symbol Properties")
+        .open(&mut open)
+        .collapsible(false)
+        .resizable(false)
+        .show(&egui.context, |ui| {
+            let dialog = &mut *dialog;
+
+            Grid::new("symbol_properties_grid")
+                .num_columns(2)
+                .show(ui, |ui| {
+                    ui.label("Name");
+                    ui.text_edit_singleline(&mut dialog.name);
+                    ui.end_row();
+
+                    ui.label("Designator");
+                    ui.horizontal(|ui| {
+                        ui.add(
+                            TextEdit::singleline(&mut dialog.designator_prefix).desired_width(32.0),
+                        );
+                        ui.add(DragValue::new(&mut dialog.designator_number));
+                        ui.add(
+                            TextEdit::singleline(&mut dialog.designator_suffix).desired_width(32.0),
+                        );
+                    });
+                    ui.end_row();
+
+                    ui.label("Bit width");
+                    ui.add(DragValue::new(&mut dialog.bit_width).range(1..=255));
+                    ui.end_row();
+
+                    ui.label("Rotation");
+                    ComboBox::from_id_salt("symbol_rotation_selector")
+                        .selected_text(rotation_text(dialog.rotation))
+                        .show_ui(ui, |ui| {
+                            for rotation in [
+                                Rotation::Rot0,
+                                Rotation::Rot90,
+                                Rotation::Rot180,
+                                Rotation::Rot270,
+                            ] {
+                                ui.selectable_value(
+                                    &mut dialog.rotation,
+                                    rotation,
+                                    rotation_text(rotation),
+                                );
+                            }
+                        });
+                    ui.end_row();
+
+                    // literals are checked against the width set in the same dialog
+                    let literal_width = dialog
+                        .parameters
+                        .integer(WIDTH_PARAM)
+                        .and_then(|width| NonZeroU8::new(width.min(u8::MAX as u32) as u8))
+                        .map(BitWidth);
+
+                    for param in params {
+                        let value = dialog
+                            .parameters
+                            .entry(param.name().clone())
+                            .or_insert_with(|| param.default_value().clone());
+
+                        ui.label(param.label());
+                        match (param.ty(), value) {
+                            (ParamType::Integer { min, max }, ParamValue::Integer(value)) => {
+                                ui.add(DragValue::new(value).range(min..=max));
+                            }
+                            (_, value) => {
+                                let mut text = value.to_string();
+                                ui.vertical(|ui| {
+                                    if ui.text_edit_singleline(&mut text).changed() {
+                                        *value = ParamValue::Text(text.as_str().into());
+                                    }
+
+                                    let literal_error = literal_width
+                                        .filter(|_| param.name().as_str() == VALUE_PARAM)
+                                        .and_then(|width| LogicState::parse(&text, width).err());
+                                    if let Some(error) = literal_error {
+                                        ui.colored_label(
+                                            ui.visuals().warn_fg_color,
+                                            format!("Invalid value: {error}"),
+                                        );
+                                    }
+                                });
+                            }
+                        }
+                        ui.end_row();
+                    }
+                });
+
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                if ui.button("OK").clicked() {
+                    apply = true;
+                    close = true;
+                }
+                if ui.button("Apply").clicked() {
+                    apply = true;
+                }
+                if ui.button("Cancel").clicked() {
+                    close = true;
+                }
+            });
+        });
+
+    if apply {
+        // unchanged properties would only mark the symbol as edited
+        let properties = dialog.properties();
+        if properties != current {
+            set_events.send(SetSymbolProperties { symbol, properties });
+        }
+    }
+
+    if close || !open {
+        dialog.symbol = None;
+        open_windows.symbol_properties = false;
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct PropertiesPlugin;
+
+impl bevy_app::Plugin for PropertiesPlugin {
+    fn build(&self, app: &mut bevy_app::App) {
+        app.init_resource::<PropertiesDialog>();
+        app.add_systems(bevy_app::Update, update_properties_dialog);
+    }
+}
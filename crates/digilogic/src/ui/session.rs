@@ -0,0 +1,461 @@
+use super::{ensure_project, Egui, ExplorerSet, MenuSet, ViewportSpawner};
+use crate::config::config_dir;
+use crate::Settings;
+use bevy_ecs::prelude::*;
+use bevy_ecs::system::lifetimeless::Read;
+use digilogic_core::components::{Circuit, CircuitID, FilePath, Modified, Name, Viewport};
+use digilogic_core::events::{
+    CircuitLoadEvent, CircuitLoadedEvent, CircuitRecoverEvent, CircuitTemplateLoadEvent,
+    ErrorEvent, ProjectLoadEvent,
+};
+use digilogic_core::resources::Project;
+use digilogic_core::{HashMap, SharedStr};
+use egui::*;
+use egui_dock::DockState;
+use serde::{Deserialize, Serialize};
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+type CircuitFileQuery<'w, 's> = Query<'w, 's, (Read<Name>, Option<Read<FilePath>>), With<Circuit>>;
+
+/// A modified circuit, restored from its autosave.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct UnsavedCircuit {
+    name: SharedStr,
+    autosave: PathBuf,
+    /// The file of the circuit, if it has one. The autosave is restored in its place.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    file: Option<PathBuf>,
+}
+
+/// The files open in digilogic, written whenever they change
+/// so they can be reopened if digilogic doesn't exit cleanly.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct Session {
+    /// The project file, if the project was loaded from one.
+    project: Option<PathBuf>,
+    /// The circuit files added to the project, if it wasn't loaded from a file.
+    circuits: Vec<PathBuf>,
+    /// The modified circuits, as they were last autosaved.
+    unsaved: Vec<UnsavedCircuit>,
+    /// The circuit files shown in tabs, in the order of the tabs.
+    tabs: Vec<PathBuf>,
+    /// Set once digilogic exits normally.
+    clean_exit: bool,
+}
+
+impl Session {
+    fn is_empty(&self) -> bool {
+        self.project.is_none() && self.circuits.is_empty() && self.unsaved.is_empty()
+    }
+}
+
+fn session_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("session.ron"))
+}
+
+fn read_session() -> Option<Session> {
+    let path = session_path()?;
+    let ron = std::fs::read_to_string(&path).ok()?;
+    match ron::from_str(&ron) {
+        Ok(session) => Some(session),
+        Err(err) => {
+            bevy_log::warn!("ignoring invalid session file {}: {err}", path.display());
+            None
+        }
+    }
+}
+
+fn write_session(session: &Session) -> std::io::Result<()> {
+    let Some(path) = session_path() else {
+        return Err(std::io::ErrorKind::NotFound.into());
+    };
+
+    let ron = ron::ser::to_string_pretty(session, ron::ser::PrettyConfig::default())
+        .map_err(std::io::Error::other)?;
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, ron)
+}
+
+/// The directory modified circuits are autosaved to.
+fn autosave_dir() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("autosave"))
+}
+
+fn remove_autosave(path: &Path) {
+    if let Err(err) = std::fs::remove_file(path) {
+        if err.kind() != std::io::ErrorKind::NotFound {
+            bevy_log::warn!("error removing autosave {}: {err}", path.display());
+        }
+    }
+}
+
+fn remove_autosaves() {
+    if let Some(dir) = autosave_dir() {
+        if let Err(err) = std::fs::remove_dir_all(&dir) {
+            if err.kind() != std::io::ErrorKind::NotFound {
+                bevy_log::warn!("error removing autosaves {}: {err}", dir.display());
+            }
+        }
+    }
+}
+
+/// Records that digilogic is exiting normally, so the session is not offered for restoring.
+pub(crate) fn end_session() {
+    if let Some(mut session) = read_session() {
+        session.clean_exit = true;
+        if let Err(err) = write_session(&session) {
+            bevy_log::error!("error saving session: {err}");
+        }
+    }
+
+    // Exiting normally means the user decided about all unsaved changes.
+    remove_autosaves();
+}
+
+#[derive(Debug, Default, Resource)]
+struct SessionState {
+    /// The session as it was last written.
+    written: Session,
+    /// The previous session, if it ended without exiting cleanly and the user hasn't decided yet.
+    restorable: Option<Session>,
+    /// Circuit files whose tabs are reopened once they are loaded.
+    pending_tabs: Vec<PathBuf>,
+    /// Names of the restored circuits without a file along with their autosaves,
+    /// marked as modified once they are loaded.
+    pending_unsaved: Vec<(SharedStr, PathBuf)>,
+    /// Files of the circuits restored from their autosaves along with the autosaves,
+    /// marked as modified once they are loaded.
+    pending_recovered: Vec<(PathBuf, PathBuf)>,
+    /// The autosave file last written for each modified circuit. Restored circuits keep
+    /// the autosave they were restored from until they are autosaved again.
+    autosaves: HashMap<Entity, PathBuf>,
+    last_autosave: Option<Instant>,
+}
+
+fn start_session(mut commands: Commands) {
+    let restorable = read_session().filter(|session| !session.clean_exit && !session.is_empty());
+    if restorable.is_none() {
+        remove_autosaves();
+    }
+
+    commands.insert_resource(SessionState {
+        restorable,
+        ..Default::default()
+    });
+}
+
+fn persist_session(
+    mut state: ResMut<SessionState>,
+    dock_state: NonSend<DockState<Entity>>,
+    project: Option<Res<Project>>,
+    circuits: Query<Read<FilePath>, With<Circuit>>,
+    names: CircuitFileQuery,
+    viewports: Query<Read<CircuitID>, With<Viewport>>,
+) {
+    // Don't overwrite the previous session before the user decided whether to restore it.
+    if state.restorable.is_some() {
+        return;
+    }
+
+    let project_path = project
+        .as_ref()
+        .and_then(|project| project.file_path.clone());
+    let mut unsaved: Vec<_> = state
+        .autosaves
+        .iter()
+        .filter_map(|(&circuit, autosave)| {
+            let (name, file) = names.get(circuit).ok()?;
+            Some(UnsavedCircuit {
+                name: name.0.clone(),
+                autosave: autosave.clone(),
+                file: file.map(|file| file.0.clone()),
+            })
+        })
+        .collect();
+    unsaved.sort_by(|a, b| a.autosave.cmp(&b.autosave));
+    let session = Session {
+        circuits: if project_path.is_none() {
+            circuits.iter().map(|path| path.0.clone()).collect()
+        } else {
+            Vec::new()
+        },
+        project: project_path,
+        unsaved,
+        tabs: dock_state
+            .iter_all_tabs()
+            .filter_map(|(_, viewport)| viewports.get(*viewport).ok())
+            .filter_map(|circuit| circuits.get(circuit.0).ok())
+            .map(|path| path.0.clone())
+            .collect(),
+        clean_exit: false,
+    };
+
+    if session != state.written {
+        if let Err(err) = write_session(&session) {
+            bevy_log::error!("error saving session: {err}");
+        }
+        state.written = session;
+    }
+}
+
+/// The autosave file of a circuit. Circuits with a file are keyed by its path,
+/// circuits without one by their entity in this process.
+fn autosave_path(dir: &Path, circuit: Entity, file: Option<&Path>) -> PathBuf {
+    match file {
+        Some(file) => {
+            let mut hasher = std::hash::DefaultHasher::new();
+            file.hash(&mut hasher);
+            let stem = file.file_stem().unwrap_or_default().to_string_lossy();
+            dir.join(format!("{stem}-{:016x}.dlc", hasher.finish()))
+        }
+        None => dir.join(format!("{}-{}.dlc", std::process::id(), circuit.to_bits())),
+    }
+}
+
+/// Writes modified circuits to the autosave directory every autosave interval.
+/// Circuits with a file are autosaved as well, their files are only written by saving.
+fn autosave_circuits(world: &mut World) {
+    let interval = world
+        .get_resource::<Settings>()
+        .map_or(0, |settings| settings.autosave_interval);
+    let mut state = world.resource_mut::<SessionState>();
+    // The autosaves of the previous session are kept until the user decided whether to restore it.
+    if (interval == 0) || state.restorable.is_some() {
+        state.last_autosave = None;
+        return;
+    }
+
+    let now = Instant::now();
+    let last_autosave = *state.last_autosave.get_or_insert(now);
+    if (now - last_autosave) < Duration::from_secs(u64::from(interval) * 60) {
+        return;
+    }
+    state.last_autosave = Some(now);
+
+    let Some(dir) = autosave_dir() else {
+        return;
+    };
+    if let Err(err) = std::fs::create_dir_all(&dir) {
+        bevy_log::error!("error creating autosave directory {}: {err}", dir.display());
+        return;
+    }
+
+    let modified: Vec<_> = world
+        .query_filtered::<(Entity, Option<&FilePath>), (With<Circuit>, With<Modified>)>()
+        .iter(world)
+        .map(|(circuit, file)| {
+            (
+                circuit,
+                autosave_path(&dir, circuit, file.map(|file| &*file.0)),
+            )
+        })
+        .collect();
+
+    // Circuits saved, unmodified or removed since don't need their autosaves anymore.
+    let mut autosaves = std::mem::take(&mut world.resource_mut::<SessionState>().autosaves);
+    autosaves.retain(|circuit, path| {
+        let keep = modified.iter().any(|(modified, _)| modified == circuit);
+        if !keep {
+            remove_autosave(path);
+        }
+        keep
+    });
+
+    for (circuit, path) in modified {
+        let result = digilogic_serde::save_circuit_file(world, CircuitID(circuit), &path);
+        match result {
+            Ok(()) => {
+                // The previous autosave is only removed once the new one was written, restored
+                // circuits and circuits saved to another file are autosaved to a new path.
+                if let Some(previous) = autosaves.insert(circuit, path.clone()) {
+                    if previous != path {
+                        remove_autosave(&previous);
+                    }
+                }
+            }
+            Err(err) => {
+                world.send_event(ErrorEvent::error(
+                    "autosave",
+                    format!("error autosaving circuit: {err:#}"),
+                ));
+            }
+        }
+    }
+
+    world.resource_mut::<SessionState>().autosaves = autosaves;
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_restore_prompt(
+    mut commands: Commands,
+    egui: Res<Egui>,
+    mut state: ResMut<SessionState>,
+    project: Option<Res<Project>>,
+    mut project_load_events: EventWriter<ProjectLoadEvent>,
+    mut circuit_load_events: EventWriter<CircuitLoadEvent>,
+    mut template_load_events: EventWriter<CircuitTemplateLoadEvent>,
+    mut circuit_recover_events: EventWriter<CircuitRecoverEvent>,
+    mut error_events: EventWriter<ErrorEvent>,
+) {
+    let Some(session) = &state.restorable else {
+        return;
+    };
+
+    let mut restore = false;
+    let mut dismiss = false;
+
+    Window::new("Restore Session")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(&egui.context, |ui| {
+            ui.label("digilogic did not exit cleanly last time.");
+            ui.label("Do you want to reopen the files of the previous session?");
+            ui.add_space(4.0);
+            for path in session.project.iter().chain(&session.circuits) {
+                ui.weak(path.display().to_string());
+            }
+            for unsaved in &session.unsaved {
+                ui.weak(format!("{} (unsaved)", unsaved.name));
+            }
+
+            ui.add_space(8.0);
+            ui.horizontal(|ui| {
+                restore = ui.button("Restore").clicked();
+                dismiss = ui.button("Dismiss").clicked();
+            });
+        });
+
+    if restore {
+        let session = state.restorable.take().unwrap();
+        // Circuits with a file are recovered before their files are loaded, so loading them
+        // returns the recovered circuits.
+        for unsaved in &session.unsaved {
+            if let Some(filename) = &unsaved.file {
+                circuit_recover_events.send(CircuitRecoverEvent {
+                    filename: filename.clone(),
+                    copy: unsaved.autosave.clone(),
+                });
+                state
+                    .pending_recovered
+                    .push((filename.clone(), unsaved.autosave.clone()));
+            }
+        }
+
+        if let Some(filename) = session.project {
+            project_load_events.send(ProjectLoadEvent { filename });
+        } else {
+            ensure_project(&mut commands, project.as_deref());
+            for filename in session.circuits {
+                circuit_load_events.send(CircuitLoadEvent { filename });
+            }
+        }
+
+        for unsaved in session
+            .unsaved
+            .into_iter()
+            .filter(|unsaved| unsaved.file.is_none())
+        {
+            match std::fs::read_to_string(&unsaved.autosave) {
+                Ok(contents) => {
+                    template_load_events.send(CircuitTemplateLoadEvent {
+                        name: unsaved.name.clone(),
+                        contents: contents.into(),
+                    });
+                    state.pending_unsaved.push((unsaved.name, unsaved.autosave));
+                }
+                Err(err) => {
+                    error_events.send(ErrorEvent::error(
+                        "session",
+                        format!("error restoring circuit {}: {err}", unsaved.name),
+                    ));
+                }
+            }
+        }
+        state.pending_tabs = session.tabs;
+    } else if dismiss {
+        state.restorable = None;
+        remove_autosaves();
+    }
+}
+
+/// Marks circuits restored from their autosaves as modified, as they still have to be saved.
+/// This runs the frame after they were loaded, once loading cleared [`Modified`].
+/// Until they are autosaved again, the session refers to the autosaves they were restored from.
+fn mark_restored_unsaved(
+    mut commands: Commands,
+    mut state: ResMut<SessionState>,
+    mut circuit_loaded_events: EventReader<CircuitLoadedEvent>,
+    circuits: CircuitFileQuery,
+) {
+    for event in circuit_loaded_events.read() {
+        let Ok((name, file)) = circuits.get(event.circuit.0) else {
+            continue;
+        };
+        let state = &mut *state;
+        let autosave = match file {
+            Some(file) => take_pending(&mut state.pending_recovered, &file.0),
+            None => take_pending(&mut state.pending_unsaved, &name.0),
+        };
+        if let Some(autosave) = autosave {
+            commands.entity(event.circuit.0).insert(Modified);
+            state.autosaves.insert(event.circuit.0, autosave);
+        }
+    }
+}
+
+/// Removes a pending restored circuit, returning its autosave.
+fn take_pending<K: PartialEq>(pending: &mut Vec<(K, PathBuf)>, key: &K) -> Option<PathBuf> {
+    let index = pending.iter().position(|(pending, _)| pending == key)?;
+    Some(pending.swap_remove(index).1)
+}
+
+/// Reopens the tabs of a restored session once their circuits are loaded.
+fn restore_tabs(
+    egui: Res<Egui>,
+    mut state: ResMut<SessionState>,
+    circuits: Query<(Entity, Ref<FilePath>), With<Circuit>>,
+    mut viewport_spawner: ViewportSpawner,
+) {
+    if state.pending_tabs.is_empty() {
+        return;
+    }
+
+    state.pending_tabs.retain(|tab| {
+        // Circuits loaded this frame may still get a viewport from the explorer.
+        let loaded = circuits
+            .iter()
+            .find(|(_, path)| !path.is_added() && (path.0 == *tab));
+        match loaded {
+            Some((circuit, _)) => {
+                viewport_spawner.focus_or_spawn_viewport(CircuitID(circuit), &egui);
+                false
+            }
+            None => true,
+        }
+    });
+}
+
+#[derive(Debug, Default)]
+pub struct SessionPlugin;
+
+impl bevy_app::Plugin for SessionPlugin {
+    fn build(&self, app: &mut bevy_app::App) {
+        app.add_systems(bevy_app::Startup, start_session);
+        app.add_systems(bevy_app::PreUpdate, mark_restored_unsaved);
+        app.add_systems(
+            bevy_app::Update,
+            (
+                update_restore_prompt.after(MenuSet),
+                restore_tabs.after(ExplorerSet),
+            ),
+        );
+        app.add_systems(bevy_app::Last, (autosave_circuits, persist_session).chain());
+    }
+}
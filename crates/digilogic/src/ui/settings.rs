@@ -1,6 +1,7 @@
-use super::{Egui, OpenWindows};
-use crate::{AppSettings, Backend};
+use super::{Egui, GateStyle, OpenWindows};
+use crate::{Backend, Settings};
 use bevy_ecs::prelude::*;
+use digilogic_routing::RoutingConfig;
 use egui::*;
 use egui_dock::*;
 
@@ -21,6 +22,9 @@ macro_rules! def_pages {
 
 def_pages! {
     Appearance,
+    Editor,
+    Routing,
+    Rendering,
     Simulator,
 }
 
@@ -34,6 +38,15 @@ impl Backend {
     }
 }
 
+impl GateStyle {
+    const fn text(self) -> &'static str {
+        match self {
+            Self::Distinctive => "Distinctive (ANSI)",
+            Self::Rectangular => "Rectangular (IEC)",
+        }
+    }
+}
+
 #[cfg(not(target_arch = "wasm32"))]
 impl crate::native_main::SimulationEngine {
     const fn text(self) -> &'static str {
@@ -44,7 +57,87 @@ impl crate::native_main::SimulationEngine {
     }
 }
 
-fn update_simulator_settings(ui: &mut Ui, settings: &mut AppSettings) {
+fn update_appearance_settings(ui: &mut Ui, context: &Context, settings: &mut Settings) {
+    ui.horizontal(|ui| {
+        ui.label("Theme");
+        let mut dark_mode = settings.dark_mode;
+        ui.selectable_value(&mut dark_mode, true, "Dark");
+        ui.selectable_value(&mut dark_mode, false, "Light");
+
+        if dark_mode != settings.dark_mode {
+            settings.dark_mode = dark_mode;
+            context.set_visuals(if dark_mode {
+                Visuals::dark()
+            } else {
+                Visuals::light()
+            });
+        }
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Gate style");
+        ComboBox::from_id_salt("gate_style_selector")
+            .selected_text(settings.gate_style.text())
+            .show_ui(ui, |ui| {
+                for &gate_style in GateStyle::ALL {
+                    ui.selectable_value(&mut settings.gate_style, gate_style, gate_style.text());
+                }
+            });
+    });
+
+    ui.separator();
+
+    let theme = if settings.dark_mode {
+        Theme::Dark
+    } else {
+        Theme::Light
+    };
+    context.style_ui(ui, theme);
+}
+
+fn update_editor_settings(ui: &mut Ui, settings: &mut Settings) {
+    ui.horizontal(|ui| {
+        ui.label("Grid size");
+        ui.add(DragValue::new(&mut settings.grid_size).range(1..=100));
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Net names");
+        let mut pattern = settings.net_name_pattern.to_string();
+        let edit = ui.add(
+            TextEdit::singleline(&mut pattern)
+                .hint_text(digilogic_ux::NetNamePattern::DEFAULT)
+                .desired_width(120.0),
+        );
+        // Don't trigger change detection if nothing changed.
+        if edit.changed() {
+            settings.net_name_pattern = pattern.as_str().into();
+        }
+    });
+    ui.label("Unnamed nets are named after this pattern. {n} is replaced by a number, {driver} by the output driving the net, like U3.Q.");
+
+    ui.horizontal(|ui| {
+        ui.label("Autosave interval");
+        ui.add(
+            DragValue::new(&mut settings.autosave_interval)
+                .range(0..=60)
+                .suffix(" min"),
+        );
+    });
+    ui.label("Modified circuits are autosaved, without overwriting their files. An autosave interval of 0 disables autosaving.");
+}
+
+fn update_routing_settings(ui: &mut Ui, routing_config: &mut RoutingConfig) {
+    ui.checkbox(&mut routing_config.prune_graph, "Prune graph");
+}
+
+fn update_rendering_settings(ui: &mut Ui, settings: &mut Settings) {
+    ui.checkbox(&mut settings.show_bounding_boxes, "Show bounding boxes");
+    ui.checkbox(&mut settings.show_routing_graph, "Show routing graph");
+    ui.checkbox(&mut settings.show_root_wires, "Highlight root wires");
+}
+
+fn update_simulator_settings(ui: &mut Ui, settings: &mut Settings) {
     ui.horizontal(|ui| {
         ui.label("Backend");
         ComboBox::from_id_salt("backend_selector")
@@ -96,11 +189,23 @@ fn update_simulator_settings(ui: &mut Ui, settings: &mut AppSettings) {
             });
         }
     }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            ui.label("Remote control port");
+            ui.add(DragValue::new(&mut settings.remote_control_port));
+        });
+        ui.label("External tools can drive the simulation through a WebSocket server on this port. A port of 0 disables the server.");
+    }
 }
 
 struct TabViewer<'a> {
     context: &'a Context,
-    settings: &'a mut AppSettings,
+    settings: &'a mut Settings,
+    routing_config: &'a mut RoutingConfig,
 }
 
 impl egui_dock::TabViewer for TabViewer<'_> {
@@ -109,20 +214,19 @@ impl egui_dock::TabViewer for TabViewer<'_> {
     fn title(&mut self, tab: &mut Self::Tab) -> WidgetText {
         match *tab {
             Page::Appearance => "Appearance".into(),
+            Page::Editor => "Editor".into(),
+            Page::Routing => "Routing".into(),
+            Page::Rendering => "Rendering".into(),
             Page::Simulator => "Simulator".into(),
         }
     }
 
     fn ui(&mut self, ui: &mut Ui, tab: &mut Self::Tab) {
         match *tab {
-            Page::Appearance => {
-                let theme = if self.settings.dark_mode {
-                    Theme::Dark
-                } else {
-                    Theme::Light
-                };
-                self.context.style_ui(ui, theme)
-            }
+            Page::Appearance => update_appearance_settings(ui, self.context, self.settings),
+            Page::Editor => update_editor_settings(ui, self.settings),
+            Page::Routing => update_routing_settings(ui, self.routing_config),
+            Page::Rendering => update_rendering_settings(ui, self.settings),
             Page::Simulator => update_simulator_settings(ui, self.settings),
         }
     }
@@ -131,15 +235,19 @@ impl egui_dock::TabViewer for TabViewer<'_> {
 fn update_settings_window(
     egui: Res<Egui>,
     mut dock_state: NonSendMut<DockState<Page>>,
-    mut settings: ResMut<AppSettings>,
+    mut settings: ResMut<Settings>,
+    mut routing_config: ResMut<RoutingConfig>,
     mut open_windows: ResMut<OpenWindows>,
 ) {
+    let mut new_routing_config = routing_config.clone();
+
     let mut tab_viewer = TabViewer {
         context: &egui.context,
         settings: &mut settings,
+        routing_config: &mut new_routing_config,
     };
 
-    Window::new("Settings")
+    Window::new("Preferences")
         .open(&mut open_windows.settings)
         .collapsible(false)
         .show(&egui.context, |ui| {
@@ -153,6 +261,11 @@ fn update_settings_window(
                 .style(egui_dock::Style::from_egui(egui.context.style().as_ref()))
                 .show_inside(ui, &mut tab_viewer);
         });
+
+    // Don't trigger change detection if nothing changed, it would cause rerouting.
+    if new_routing_config != *routing_config {
+        *routing_config = new_routing_config;
+    }
 }
 
 #[derive(Debug, Default)]
@@ -0,0 +1,251 @@
+//! Symbol artwork loaded from SVG files, drawn instead of the built-in shape of a kind.
+//!
+//! Every `.svg` file in the symbol library directory that is named like a kind of symbol,
+//! for example `LATCH.svg`, replaces the shape symbols of that kind are drawn with.
+//! Coordinates are used as they are, with the origin at the position of the symbol.
+//! Paths, lines, rectangles, circles, polylines and polygons are drawn,
+//! filled and stroked as set by their `fill` and `stroke` attributes or styles.
+//! Transforms are not supported.
+
+use super::{PathInfo, PathKind, SymbolShape, SymbolShapes};
+use bevy_log::{info, warn};
+use digilogic_core::symbol::SymbolRegistry;
+use std::fmt;
+use std::path::Path;
+use vello::kurbo::{BezPath, Circle, Line, Rect, Shape as _, SvgParseError};
+use xml::attribute::OwnedAttribute;
+use xml::reader::{EventReader, XmlEvent};
+
+#[derive(Debug)]
+pub(crate) enum SvgShapeError {
+    Xml(xml::reader::Error),
+    Path(SvgParseError),
+    InvalidNumber(String),
+    /// The file has no elements that are drawn.
+    Empty,
+}
+
+impl fmt::Display for SvgShapeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Xml(error) => write!(f, "invalid SVG: {error}"),
+            Self::Path(error) => write!(f, "invalid path data: {error}"),
+            Self::InvalidNumber(value) => write!(f, "invalid number `{value}`"),
+            Self::Empty => f.write_str("no drawable elements"),
+        }
+    }
+}
+
+impl std::error::Error for SvgShapeError {}
+
+fn attribute<'a>(attributes: &'a [OwnedAttribute], name: &str) -> Option<&'a str> {
+    attributes
+        .iter()
+        .find(|attribute| attribute.name.local_name == name)
+        .map(|attribute| attribute.value.as_str())
+}
+
+/// A presentation property of an element, where the `style` attribute takes precedence.
+fn property<'a>(attributes: &'a [OwnedAttribute], name: &str) -> Option<&'a str> {
+    let style = attribute(attributes, "style").and_then(|style| {
+        style.split(';').find_map(|declaration| {
+            let (property, value) = declaration.split_once(':')?;
+            (property.trim() == name).then_some(value.trim())
+        })
+    });
+    style.or_else(|| attribute(attributes, name))
+}
+
+fn number(attributes: &[OwnedAttribute], name: &str) -> Result<f64, SvgShapeError> {
+    let value = attribute(attributes, name).unwrap_or("0").trim();
+    value
+        .trim_end_matches("px")
+        .parse()
+        .map_err(|_| SvgShapeError::InvalidNumber(value.to_owned()))
+}
+
+fn points(attributes: &[OwnedAttribute], closed: bool) -> Result<BezPath, SvgShapeError> {
+    let value = attribute(attributes, "points").unwrap_or_default();
+    let coordinates = value
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|coordinate| !coordinate.is_empty())
+        .map(|coordinate| {
+            coordinate
+                .parse::<f64>()
+                .map_err(|_| SvgShapeError::InvalidNumber(coordinate.to_owned()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut path = BezPath::new();
+    for (index, point) in coordinates.chunks_exact(2).enumerate() {
+        if index == 0 {
+            path.move_to((point[0], point[1]));
+        } else {
+            path.line_to((point[0], point[1]));
+        }
+    }
+    if closed {
+        path.close_path();
+    }
+    Ok(path)
+}
+
+/// The outline of a drawn element, `None` for elements that are not drawn.
+fn element_path(
+    name: &str,
+    attributes: &[OwnedAttribute],
+) -> Result<Option<BezPath>, SvgShapeError> {
+    let path = match name {
+        "path" => BezPath::from_svg(attribute(attributes, "d").unwrap_or_default())
+            .map_err(SvgShapeError::Path)?,
+        "line" => Line::new(
+            (number(attributes, "x1")?, number(attributes, "y1")?),
+            (number(attributes, "x2")?, number(attributes, "y2")?),
+        )
+        .to_path(0.1),
+        "rect" => {
+            let (x, y) = (number(attributes, "x")?, number(attributes, "y")?);
+            let (width, height) = (number(attributes, "width")?, number(attributes, "height")?);
+            Rect::new(x, y, x + width, y + height).to_path(0.1)
+        }
+        "circle" => Circle::new(
+            (number(attributes, "cx")?, number(attributes, "cy")?),
+            number(attributes, "r")?,
+        )
+        .to_path(0.1),
+        "polyline" => points(attributes, false)?,
+        "polygon" => points(attributes, true)?,
+        _ => return Ok(None),
+    };
+    Ok(Some(path))
+}
+
+/// Parses the drawn elements of an SVG document into a shape.
+pub(crate) fn parse_svg_shape(contents: &str) -> Result<SymbolShape, SvgShapeError> {
+    let mut paths = Vec::new();
+    for event in EventReader::from_str(contents) {
+        let XmlEvent::StartElement {
+            name, attributes, ..
+        } = event.map_err(SvgShapeError::Xml)?
+        else {
+            continue;
+        };
+        let Some(path) = element_path(&name.local_name, &attributes)? else {
+            continue;
+        };
+
+        // like in SVG, shapes are filled and not stroked unless set otherwise
+        let mut kind = PathKind::empty();
+        if property(&attributes, "fill") != Some("none") {
+            kind |= PathKind::FILL;
+        }
+        if property(&attributes, "stroke").is_some_and(|stroke| stroke != "none") {
+            kind |= PathKind::STROKE;
+        }
+        if !kind.is_empty() {
+            paths.push(PathInfo { kind, path });
+        }
+    }
+
+    if paths.is_empty() {
+        return Err(SvgShapeError::Empty);
+    }
+    Ok(SymbolShape { paths })
+}
+
+/// Loads the artwork of the kinds of symbols that have an SVG file in `dir`.
+/// Invalid files and files not named like a kind are skipped with a warning.
+/// Returns the number of kinds artwork was loaded for.
+pub(crate) fn load_symbol_artwork(
+    dir: &Path,
+    symbol_registry: &SymbolRegistry,
+    symbol_shapes: &mut SymbolShapes,
+) -> usize {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return 0,
+        Err(err) => {
+            warn!("error reading symbol library {}: {err}", dir.display());
+            return 0;
+        }
+    };
+
+    let mut count = 0;
+    for path in entries.filter_map(|entry| entry.ok().map(|entry| entry.path())) {
+        if path.extension().and_then(|ext| ext.to_str()) != Some("svg") {
+            continue;
+        }
+        let Some(name) = path.file_stem().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        let Some(def) = symbol_registry
+            .iter()
+            .find(|def| def.name().as_str() == name)
+        else {
+            warn!("no symbol kind named `{name}` for {}", path.display());
+            continue;
+        };
+
+        let shape = std::fs::read_to_string(&path)
+            .map_err(|err| err.to_string())
+            .and_then(|contents| parse_svg_shape(&contents).map_err(|err| err.to_string()));
+        match shape {
+            Ok(shape) => {
+                symbol_shapes.kinds.insert(def.kind(), shape);
+                count += 1;
+            }
+            Err(err) => warn!("loading symbol artwork {}: {err}", path.display()),
+        }
+    }
+
+    if count > 0 {
+        info!(
+            "loaded artwork for {count} symbol kind(s) from {}",
+            dir.display()
+        );
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_elements() {
+        let shape = parse_svg_shape(
+            r#"<svg xmlns="http://www.w3.org/2000/svg">
+                <g>
+                    <path d="M 0,-20 H 40 V 20 H 0 Z" fill="none" stroke="black"/>
+                    <circle cx="45" cy="0" r="5" style="stroke: black"/>
+                    <line x1="50" y1="0" x2="60" y2="0" style="fill: none; stroke: black"/>
+                    <polyline points="0,0 10,10 20,0" fill="none"/>
+                </g>
+            </svg>"#,
+        )
+        .unwrap();
+
+        let kinds: Vec<_> = shape.paths.iter().map(|path| path.kind.bits()).collect();
+        assert_eq!(
+            kinds,
+            [
+                PathKind::STROKE.bits(),
+                (PathKind::FILL | PathKind::STROKE).bits(),
+                PathKind::STROKE.bits(),
+            ]
+        );
+        assert_eq!(
+            shape.paths[0].path.bounding_box(),
+            Rect::new(0.0, -20.0, 40.0, 20.0)
+        );
+
+        assert!(matches!(
+            parse_svg_shape("<svg/>"),
+            Err(SvgShapeError::Empty)
+        ));
+        assert!(matches!(
+            parse_svg_shape(r#"<svg><rect width="x"/></svg>"#),
+            Err(SvgShapeError::InvalidNumber(_))
+        ));
+    }
+}
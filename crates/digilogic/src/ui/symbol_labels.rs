@@ -0,0 +1,131 @@
+use super::{GateStyle, PanZoom, SymbolShapes};
+use aery::prelude::*;
+use bevy_ecs::prelude::*;
+use bevy_ecs::system::lifetimeless::Read;
+use bevy_ecs::system::SystemParam;
+use digilogic_core::components::*;
+use digilogic_core::symbol::{BUS_PORT, LABEL_PARAM, VALUE_PARAM};
+use digilogic_core::transform::{AbsoluteBoundingBox, GlobalTransform};
+use egui::*;
+
+/// The size of text on symbol faces at a zoom of 1, it scales with the symbol.
+const FACE_FONT_SIZE: f32 = 10.0;
+
+/// Text smaller than this is not readable and left out.
+const MIN_FONT_SIZE: f32 = 4.0;
+
+type SymbolFaceQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        (
+            Read<SymbolKind>,
+            Read<AbsoluteBoundingBox>,
+            Option<Read<Parameters>>,
+            Has<DeMorgan>,
+        ),
+        Relations<Child>,
+    ),
+    With<Symbol>,
+>;
+
+type TapQuery<'w, 's> = Query<'w, 's, (Read<Name>, Read<GlobalTransform>), With<Port>>;
+
+#[derive(SystemParam)]
+pub(super) struct SymbolLabels<'w, 's> {
+    circuits: Query<'w, 's, ((), Relations<Child>), With<Circuit>>,
+    symbols: SymbolFaceQuery<'w, 's>,
+    taps: TapQuery<'w, 's>,
+    symbol_shapes: Res<'w, SymbolShapes>,
+}
+
+impl SymbolLabels<'_, '_> {
+    /// Draws the text shown on the faces of the symbols in `circuit`, the values of constants,
+    /// the labels of tunnels, the bits of splitter taps and the qualifiers of rectangular gates.
+    pub(super) fn show(
+        &self,
+        ui: &Ui,
+        response: &Response,
+        circuit: CircuitID,
+        pan_zoom: &PanZoom,
+    ) {
+        let Ok((_, edges)) = self.circuits.get(circuit.0) else {
+            return;
+        };
+
+        let font_size = FACE_FONT_SIZE * pan_zoom.zoom;
+        if font_size < MIN_FONT_SIZE {
+            return;
+        }
+
+        let painter = ui.painter_at(response.rect);
+        let font = FontId::monospace(font_size);
+        let color = ui.visuals().text_color();
+        let to_screen = |position: digilogic_core::transform::Vec2| {
+            let position = Vec2::new(position.x.to_f32(), position.y.to_f32());
+            response.rect.left_top() + (position + pan_zoom.pan) * pan_zoom.zoom
+        };
+
+        edges.join::<Child>(&self.symbols).for_each(
+            |((kind, bounding_box, parameters, de_morgan), symbol_edges)| {
+                if *kind == SymbolKind::Splitter {
+                    // taps are named by the bits of the bus they carry
+                    let tap_color = ui.visuals().weak_text_color();
+                    symbol_edges
+                        .join::<Child>(&self.taps)
+                        .for_each(|(name, transform)| {
+                            if name.0.as_str() == BUS_PORT {
+                                return;
+                            }
+                            let anchor = to_screen(transform.translation)
+                                + Vec2::new(2.0, -2.0) * pan_zoom.zoom;
+                            painter.text(
+                                anchor,
+                                Align2::LEFT_BOTTOM,
+                                &name.0,
+                                font.clone(),
+                                tap_color,
+                            );
+                        });
+                    return;
+                }
+
+                if self.symbol_shapes.gate_style == GateStyle::Rectangular {
+                    // De Morgan equivalents are drawn as their dual gate
+                    let qualifier = match kind {
+                        SymbolKind::And if de_morgan => Some("≥1"),
+                        SymbolKind::Or if de_morgan => Some("&"),
+                        SymbolKind::And => Some("&"),
+                        SymbolKind::Or => Some("≥1"),
+                        SymbolKind::Xor => Some("=1"),
+                        SymbolKind::Not => Some("1"),
+                        _ => None,
+                    };
+                    if let Some(qualifier) = qualifier {
+                        let anchor = to_screen(bounding_box.center());
+                        painter.text(
+                            anchor,
+                            Align2::CENTER_CENTER,
+                            qualifier,
+                            font.clone(),
+                            color,
+                        );
+                        return;
+                    }
+                }
+
+                let param = match kind {
+                    SymbolKind::Constant => VALUE_PARAM,
+                    SymbolKind::Tunnel => LABEL_PARAM,
+                    _ => return,
+                };
+                let Some(value) = parameters.and_then(|parameters| parameters.text(param)) else {
+                    return;
+                };
+
+                let anchor = to_screen(bounding_box.center());
+                painter.text(anchor, Align2::CENTER_CENTER, value, font.clone(), color);
+            },
+        );
+    }
+}
@@ -0,0 +1,136 @@
+use super::{CanvasRenderer, Egui, SymbolShapes, SymbolThumbnails};
+use bevy_ecs::prelude::*;
+use bevy_reflect::Reflect;
+use digilogic_core::symbol::{SymbolDef, SymbolRegistry};
+use digilogic_ux::{ActiveTool, PlacementKind};
+use egui::*;
+use egui_dock::{DockState, NodeIndex};
+
+/// Marks the dock tab that shows the symbol palette.
+#[derive(Debug, Default, Clone, Copy, Component, Reflect)]
+pub struct SymbolPaletteTab;
+
+const ITEM_SIZE: Vec2 = vec2(72.0, 72.0);
+const THUMBNAIL_MARGIN: f32 = 8.0;
+const DRAG_PREVIEW_SIZE: f32 = 48.0;
+const FULL_UV: Rect = Rect::from_min_max(Pos2::ZERO, pos2(1.0, 1.0));
+
+/// Dragging an item onto a viewport places a symbol of its kind where it is dropped.
+fn symbol_item(
+    ui: &mut Ui,
+    def: &SymbolDef,
+    thumbnail: Option<TextureId>,
+    selected: bool,
+) -> Response {
+    let (rect, response) = ui.allocate_exact_size(ITEM_SIZE, Sense::click_and_drag());
+    response.dnd_set_drag_payload(def.kind());
+
+    if response.dragged() {
+        if let (Some(thumbnail), Some(pos)) = (thumbnail, ui.ctx().pointer_interact_pos()) {
+            let painter = ui
+                .ctx()
+                .layer_painter(LayerId::new(Order::Tooltip, response.id));
+            painter.image(
+                thumbnail,
+                Rect::from_center_size(pos, Vec2::splat(DRAG_PREVIEW_SIZE)),
+                FULL_UV,
+                ui.visuals().strong_text_color(),
+            );
+        }
+    }
+
+    if !ui.is_rect_visible(rect) {
+        return response;
+    }
+
+    let visuals = ui.style().interact_selectable(&response, selected);
+    if selected || response.hovered() {
+        ui.painter().rect(
+            rect,
+            visuals.rounding,
+            visuals.weak_bg_fill,
+            visuals.bg_stroke,
+        );
+    }
+
+    let font_id = TextStyle::Small.resolve(ui.style());
+    let label_height = ui.fonts(|fonts| fonts.row_height(&font_id));
+    let thumbnail_rect = Rect::from_min_max(
+        rect.min + vec2(THUMBNAIL_MARGIN, THUMBNAIL_MARGIN),
+        rect.max - vec2(THUMBNAIL_MARGIN, THUMBNAIL_MARGIN + label_height),
+    );
+
+    if let Some(thumbnail) = thumbnail {
+        let side = thumbnail_rect.size().min_elem();
+        ui.painter().image(
+            thumbnail,
+            Rect::from_center_size(thumbnail_rect.center(), Vec2::splat(side)),
+            FULL_UV,
+            visuals.fg_stroke.color,
+        );
+    }
+
+    ui.painter().text(
+        pos2(rect.center().x, rect.max.y - THUMBNAIL_MARGIN / 2.0),
+        Align2::CENTER_BOTTOM,
+        def.name().as_str(),
+        font_id,
+        visuals.text_color(),
+    );
+
+    response.on_hover_text(def.name().as_str())
+}
+
+/// Lists every kind of symbol, selecting one enters placement mode.
+#[allow(clippy::too_many_arguments)]
+pub(super) fn show_symbol_palette(
+    ui: &mut Ui,
+    egui: &Egui,
+    renderer: &mut CanvasRenderer,
+    symbol_registry: &SymbolRegistry,
+    symbol_shapes: &SymbolShapes,
+    thumbnails: &mut SymbolThumbnails,
+    active_tool: &mut ResMut<ActiveTool>,
+    placement_kind: &mut ResMut<PlacementKind>,
+) {
+    ScrollArea::vertical().show(ui, |ui| {
+        ui.horizontal_wrapped(|ui| {
+            for def in symbol_registry.iter() {
+                let selected = (**active_tool == ActiveTool::PlaceSymbol)
+                    && (placement_kind.0 == Some(def.kind()));
+
+                let thumbnail = thumbnails.texture(egui, renderer, symbol_shapes, def);
+                if symbol_item(ui, def, thumbnail, selected).clicked() {
+                    if selected {
+                        **active_tool = ActiveTool::Select;
+                    } else {
+                        **active_tool = ActiveTool::PlaceSymbol;
+                        placement_kind.0 = Some(def.kind());
+                    }
+                }
+            }
+        });
+    });
+}
+
+/// Focuses the symbol palette tab, adding it to the right of the dock if it isn't open yet.
+pub(super) fn open_symbol_palette(
+    commands: &mut Commands,
+    dock_state: &mut DockState<Entity>,
+    symbol_palettes: &Query<Entity, With<SymbolPaletteTab>>,
+) {
+    if let Some(palette) = symbol_palettes.iter().next() {
+        if let Some(index) = dock_state.find_tab(&palette) {
+            dock_state.set_active_tab(index);
+        }
+        return;
+    }
+
+    let palette = commands.spawn(SymbolPaletteTab).id();
+    let surface = dock_state.main_surface_mut();
+    if surface.is_empty() {
+        surface.push_to_first_leaf(palette);
+    } else {
+        surface.split_right(NodeIndex::root(), 0.8, vec![palette]);
+    }
+}
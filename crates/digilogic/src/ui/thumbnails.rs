@@ -0,0 +1,172 @@
+use super::{Canvas, CanvasRenderer, Egui, SymbolShapes};
+use bevy_ecs::prelude::*;
+use bevy_ecs::system::SystemParam;
+use digilogic_core::components::SymbolKind;
+use digilogic_core::symbol::SymbolDef;
+use digilogic_core::transform::BoundingBox;
+use digilogic_core::HashMap;
+use egui::{Color32, Image, Rect, TextureId, Vec2};
+use vello::kurbo::{Affine, Stroke};
+use vello::peniko::Color;
+
+/// The size of the rendered thumbnails in pixels, large enough to be scaled down everywhere.
+const THUMBNAIL_SIZE: u32 = 96;
+const THUMBNAIL_MARGIN: f64 = 4.0;
+const THUMBNAIL_STROKE_WIDTH: f64 = 2.5;
+
+/// Maps the bounding box of a symbol onto `rect`, keeping its aspect ratio.
+/// A bounding box without width or height is fit by its other side, one without either
+/// is only centered.
+fn fit_bounds_to_rect(bounds: BoundingBox, rect: Rect) -> Affine {
+    let center = bounds.center();
+    let fit = |available: f32, size: f64| (size > 0.0).then(|| available as f64 / size);
+    let scale = match (
+        fit(rect.width(), bounds.width().to_f64()),
+        fit(rect.height(), bounds.height().to_f64()),
+    ) {
+        (Some(x), Some(y)) => x.min(y),
+        (Some(scale), None) | (None, Some(scale)) => scale,
+        (None, None) => 1.0,
+    };
+
+    Affine::translate((-center.x.to_f64(), -center.y.to_f64()))
+        .then_scale(scale)
+        .then_translate((rect.center().x as f64, rect.center().y as f64).into())
+}
+
+/// Small images of the shape of each kind of symbol, rendered offscreen the first time
+/// they are shown and kept until the shapes change.
+#[derive(Default, Resource)]
+pub(super) struct SymbolThumbnails {
+    canvases: HashMap<SymbolKind, Canvas>,
+}
+
+impl SymbolThumbnails {
+    /// The thumbnail of `def`, drawn in white so it can be tinted.
+    /// `None` if the kind has no shape to draw.
+    pub(super) fn texture(
+        &mut self,
+        egui: &Egui,
+        renderer: &mut CanvasRenderer,
+        symbol_shapes: &SymbolShapes,
+        def: &SymbolDef,
+    ) -> Option<TextureId> {
+        if let Some(canvas) = self.canvases.get(&def.kind()) {
+            return Some(canvas.texture_id());
+        }
+
+        let shape = symbol_shapes.get(def.kind(), def.shape())?;
+
+        let size = THUMBNAIL_SIZE as f32;
+        let rect = Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(size, size))
+            .shrink(THUMBNAIL_MARGIN as f32);
+        let transform = fit_bounds_to_rect(def.bounding_box(), rect);
+        // the stroke is scaled together with the shape, so it is widened by the inverse
+        let stroke = Stroke::new(THUMBNAIL_STROKE_WIDTH / transform.as_coeffs()[0]);
+
+        let mut scene = vello::Scene::new();
+        for path in shape.paths.iter() {
+            scene.stroke(&stroke, transform, Color::WHITE, None, &path.path);
+        }
+
+        let mut canvas = Canvas::create_with_filter(&egui.render_state, wgpu::FilterMode::Linear);
+        canvas.resize(&egui.render_state, THUMBNAIL_SIZE, THUMBNAIL_SIZE);
+        canvas.render(renderer, &egui.render_state, &scene, Color::TRANSPARENT);
+
+        let texture_id = canvas.texture_id();
+        self.canvases.insert(def.kind(), canvas);
+        Some(texture_id)
+    }
+
+    /// The thumbnail of `def` as a square image fitting into `size`, tinted with `color`.
+    pub(super) fn image(
+        &mut self,
+        egui: &Egui,
+        renderer: &mut CanvasRenderer,
+        symbol_shapes: &SymbolShapes,
+        def: &SymbolDef,
+        size: Vec2,
+        color: Color32,
+    ) -> Option<Image<'static>> {
+        let texture_id = self.texture(egui, renderer, symbol_shapes, def)?;
+        let side = size.min_elem();
+        Some(Image::new((texture_id, Vec2::splat(side))).tint(color))
+    }
+}
+
+/// The thumbnails together with everything needed to render them,
+/// for systems that don't render to a canvas themselves.
+#[derive(SystemParam)]
+pub(super) struct Thumbnails<'w> {
+    egui: Res<'w, Egui>,
+    renderer: NonSendMut<'w, CanvasRenderer>,
+    symbol_shapes: Res<'w, SymbolShapes>,
+    thumbnails: ResMut<'w, SymbolThumbnails>,
+}
+
+impl Thumbnails<'_> {
+    /// The thumbnail of `def` as a square image fitting into `size`, tinted with `color`.
+    pub(super) fn image(
+        &mut self,
+        def: &SymbolDef,
+        size: Vec2,
+        color: Color32,
+    ) -> Option<Image<'static>> {
+        self.thumbnails.image(
+            &self.egui,
+            &mut self.renderer,
+            &self.symbol_shapes,
+            def,
+            size,
+            color,
+        )
+    }
+}
+
+/// Drops all thumbnails after the shapes changed, e.g. because of the gate style
+/// or loaded artwork, they are rendered again when shown next.
+pub(super) fn invalidate_thumbnails(egui: Res<Egui>, mut thumbnails: ResMut<SymbolThumbnails>) {
+    for (_, canvas) in thumbnails.canvases.drain() {
+        canvas.free(&egui.render_state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use digilogic_core::transform::Vec2 as FixedVec2;
+    use vello::kurbo::Point;
+
+    fn bounds(width: i16, height: i16) -> BoundingBox {
+        BoundingBox::from_points(
+            FixedVec2::default(),
+            FixedVec2 {
+                x: width.into(),
+                y: height.into(),
+            },
+        )
+    }
+
+    #[test]
+    fn fits_degenerate_bounds() {
+        let rect = Rect::from_min_size(egui::pos2(0.0, 0.0), egui::vec2(100.0, 50.0));
+        let center = Point::new(50.0, 25.0);
+
+        let transform = fit_bounds_to_rect(bounds(20, 20), rect);
+        assert_eq!(transform * Point::new(10.0, 10.0), center);
+        assert_eq!(transform * Point::new(20.0, 20.0), Point::new(75.0, 50.0));
+
+        // a horizontal line is fit by its width alone
+        let transform = fit_bounds_to_rect(bounds(20, 0), rect);
+        assert_eq!(transform * Point::new(20.0, 0.0), Point::new(100.0, 25.0));
+
+        // a vertical line is fit by its height alone
+        let transform = fit_bounds_to_rect(bounds(0, 10), rect);
+        assert_eq!(transform * Point::new(0.0, 10.0), Point::new(50.0, 50.0));
+
+        // a single point is only centered
+        let transform = fit_bounds_to_rect(bounds(0, 0), rect);
+        assert_eq!(transform * Point::ORIGIN, center);
+        assert!(transform.as_coeffs().iter().all(|coeff| coeff.is_finite()));
+    }
+}
@@ -0,0 +1,117 @@
+use super::{Egui, MenuSet, OpenWindows};
+use crate::FileDialogEvent;
+use bevy_ecs::prelude::*;
+use digilogic_core::components::{Name, Net, Traced};
+use digilogic_core::HashSet;
+use digilogic_netcode::SignalTraces;
+use egui::*;
+
+#[derive(Default, Resource)]
+struct TimingDiagramDialog {
+    /// Traced nets left out of the diagram.
+    excluded: HashSet<Entity>,
+    start: u64,
+    end: u64,
+    /// Whether the window starts out covering all recorded steps.
+    reset: bool,
+}
+
+type TracedNetQuery<'w, 's> = Query<'w, 's, (Entity, &'static Name), (With<Net>, With<Traced>)>;
+
+fn update_timing_diagram_dialog(
+    egui: Res<Egui>,
+    mut open_windows: ResMut<OpenWindows>,
+    mut dialog: ResMut<TimingDiagramDialog>,
+    traces: Res<SignalTraces>,
+    nets: TracedNetQuery,
+    mut file_dialog_events: EventWriter<FileDialogEvent>,
+) {
+    if !open_windows.timing_diagram {
+        dialog.reset = true;
+        return;
+    }
+
+    let steps = traces.steps();
+    if dialog.reset {
+        dialog.start = 0;
+        dialog.end = steps;
+        dialog.reset = false;
+    }
+
+    let mut nets = nets.iter().collect::<Vec<_>>();
+    nets.sort_by(|(_, a), (_, b)| a.0.cmp(&b.0));
+
+    let mut open = true;
+    let mut export = false;
+
+    Window::new("Export Timing Diagram")
+        .open(&mut open)
+        .collapsible(false)
+        .resizable(false)
+        .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(&egui.context, |ui| {
+            if nets.is_empty() {
+                ui.label("No nets are traced.");
+                ui.label("Choose Trace in the context menu of nets, then run the simulation.");
+                return;
+            }
+
+            ui.label("Signals");
+            for &(net, name) in &nets {
+                let mut included = !dialog.excluded.contains(&net);
+                if ui.checkbox(&mut included, name.0.as_str()).changed() {
+                    if included {
+                        dialog.excluded.remove(&net);
+                    } else {
+                        dialog.excluded.insert(net);
+                    }
+                }
+            }
+
+            ui.add_space(8.0);
+            ui.label(format!("{steps} steps recorded"));
+            let dialog = &mut *dialog;
+            ui.horizontal(|ui| {
+                ui.label("From step");
+                ui.add(DragValue::new(&mut dialog.start).range(0..=steps.saturating_sub(1)));
+                ui.label("to");
+                ui.add(DragValue::new(&mut dialog.end).range(dialog.start + 1..=steps));
+            });
+
+            ui.add_space(8.0);
+            let any_included = nets.iter().any(|(net, _)| !dialog.excluded.contains(net));
+            let valid = any_included && (dialog.start < dialog.end) && (dialog.end <= steps);
+            export = ui
+                .add_enabled(valid, Button::new("Export WaveDrom…"))
+                .clicked();
+        });
+
+    if export {
+        let signals = nets
+            .iter()
+            .map(|&(net, _)| net)
+            .filter(|net| !dialog.excluded.contains(net))
+            .collect();
+        file_dialog_events.send(FileDialogEvent::ExportTimingDiagram {
+            signals,
+            window: dialog.start..dialog.end,
+        });
+    }
+
+    if export || !open {
+        open_windows.timing_diagram = false;
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct TimingDiagramPlugin;
+
+impl bevy_app::Plugin for TimingDiagramPlugin {
+    fn build(&self, app: &mut bevy_app::App) {
+        app.init_resource::<TimingDiagramDialog>();
+        app.add_systems(
+            bevy_app::Update,
+            update_timing_diagram_dialog.after(MenuSet),
+        );
+    }
+}
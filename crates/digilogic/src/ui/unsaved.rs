@@ -0,0 +1,251 @@
+use super::{Egui, MenuSet, OpenWindows};
+use crate::FileDialogEvent;
+use bevy_ecs::prelude::*;
+use bevy_ecs::system::lifetimeless::Read;
+use bevy_reflect::Reflect;
+use digilogic_core::components::{Circuit, CircuitID, Modified, Name, Viewport};
+use digilogic_core::events::SavedEvent;
+use digilogic_core::resources::Project;
+use digilogic_core::SharedStr;
+use egui::*;
+use egui_dock::DockState;
+
+/// What is waiting for the user to decide about unsaved changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub(super) enum CloseAction {
+    /// Closing the last tab showing a modified circuit.
+    CloseTab(Entity),
+    NewProject,
+    Quit,
+}
+
+/// Set once the user chose to quit without saving, so the next close request goes through.
+#[derive(Debug, Default, Resource)]
+struct QuitConfirmed(bool);
+
+/// An action that goes on once all modified circuits were saved.
+#[derive(Debug, Default, Resource)]
+struct SaveBeforeClose {
+    action: Option<CloseAction>,
+    /// Set once the file dialogs asking where to save have been shown.
+    asked: bool,
+}
+
+/// Replaces the project with an empty one, asking about unsaved changes first.
+#[derive(Event, Debug)]
+pub(super) struct NewProject;
+
+fn replace_project(
+    commands: &mut Commands,
+    project: Option<&Project>,
+    circuits: impl IntoIterator<Item = Entity>,
+) {
+    if project.is_some() {
+        for circuit in circuits {
+            commands.entity(circuit).despawn();
+        }
+    }
+
+    commands.insert_resource(Project {
+        name: SharedStr::new_static("Unnamed Project"),
+        file_path: None,
+        root_circuit: None,
+    });
+}
+
+fn new_project(
+    _trigger: Trigger<NewProject>,
+    mut commands: Commands,
+    project: Option<Res<Project>>,
+    circuits: Query<(Entity, Has<Modified>), With<Circuit>>,
+    mut open_windows: ResMut<OpenWindows>,
+) {
+    if circuits.iter().any(|(_, modified)| modified) {
+        open_windows.unsaved_changes = Some(CloseAction::NewProject);
+        return;
+    }
+
+    replace_project(
+        &mut commands,
+        project.as_deref(),
+        circuits.iter().map(|(circuit, _)| circuit),
+    );
+}
+
+/// Whether quitting has to wait for the user to decide about unsaved changes,
+/// in which case they are asked to.
+pub(crate) fn intercept_quit(world: &mut World) -> bool {
+    if world.resource::<QuitConfirmed>().0 {
+        return false;
+    }
+
+    let modified = world
+        .query_filtered::<(), (With<Circuit>, With<Modified>)>()
+        .iter(world)
+        .next()
+        .is_some();
+    if modified {
+        world.resource_mut::<OpenWindows>().unsaved_changes = Some(CloseAction::Quit);
+    }
+    modified
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_unsaved_changes_prompt(
+    mut commands: Commands,
+    egui: Res<Egui>,
+    mut open_windows: ResMut<OpenWindows>,
+    mut quit_confirmed: ResMut<QuitConfirmed>,
+    mut save_before_close: ResMut<SaveBeforeClose>,
+    mut dock_state: NonSendMut<DockState<Entity>>,
+    mut file_dialog_events: EventWriter<FileDialogEvent>,
+    project: Option<Res<Project>>,
+    circuits: Query<(Entity, Read<Name>, Has<Modified>), With<Circuit>>,
+    viewports: Query<Read<CircuitID>, With<Viewport>>,
+) {
+    let Some(action) = open_windows.unsaved_changes else {
+        return;
+    };
+
+    let affected = match action {
+        CloseAction::CloseTab(viewport) => viewports
+            .get(viewport)
+            .ok()
+            .and_then(|circuit| circuits.get(circuit.0).ok())
+            .map(|(_, name, _)| name.0.clone())
+            .into_iter()
+            .collect::<Vec<_>>(),
+        CloseAction::NewProject | CloseAction::Quit => circuits
+            .iter()
+            .filter(|(_, _, modified)| *modified)
+            .map(|(_, name, _)| name.0.clone())
+            .collect(),
+    };
+
+    let mut save = false;
+    let mut discard = false;
+    let mut cancel = egui.context.input(|state| state.key_pressed(Key::Escape));
+
+    Window::new("Unsaved Changes")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(&egui.context, |ui| {
+            ui.label("The following circuits have unsaved changes:");
+            for name in &affected {
+                ui.label(format!("• {name}"));
+            }
+
+            ui.add_space(8.0);
+            ui.horizontal(|ui| {
+                save = ui.button("Save").clicked();
+                discard = ui.button("Discard").clicked();
+                cancel |= ui.button("Cancel").clicked();
+            });
+        });
+
+    if save {
+        match action {
+            // Saving happens through a file dialog, closing the tab has to be repeated once it is done.
+            CloseAction::CloseTab(viewport) => {
+                if let Ok(&circuit) = viewports.get(viewport) {
+                    file_dialog_events.send(FileDialogEvent::SaveCircuit(circuit));
+                }
+            }
+            CloseAction::NewProject | CloseAction::Quit => {
+                file_dialog_events.send(FileDialogEvent::SaveModified);
+                *save_before_close = SaveBeforeClose {
+                    action: Some(action),
+                    asked: false,
+                };
+            }
+        }
+    } else if discard {
+        match action {
+            CloseAction::CloseTab(viewport) => {
+                if let Some(index) = dock_state.find_tab(&viewport) {
+                    dock_state.remove_tab(index);
+                }
+                commands.entity(viewport).despawn();
+            }
+            CloseAction::NewProject => {
+                replace_project(
+                    &mut commands,
+                    project.as_deref(),
+                    circuits.iter().map(|(circuit, _, _)| circuit),
+                );
+            }
+            CloseAction::Quit => {
+                quit_confirmed.0 = true;
+                egui.context.send_viewport_cmd(ViewportCommand::Close);
+            }
+        }
+    }
+
+    if save || discard || cancel {
+        open_windows.unsaved_changes = None;
+    }
+}
+
+/// Goes on with replacing the project or quitting once the circuits were saved,
+/// which happens the frame after the file dialogs were shown.
+/// If any circuit is left unsaved, because a dialog was cancelled or saving failed, the action is dropped.
+fn continue_after_save(
+    mut commands: Commands,
+    egui: Res<Egui>,
+    mut save_before_close: ResMut<SaveBeforeClose>,
+    mut quit_confirmed: ResMut<QuitConfirmed>,
+    mut saved_events: EventReader<SavedEvent>,
+    project: Option<Res<Project>>,
+    circuits: Query<(Entity, Has<Modified>), With<Circuit>>,
+) {
+    let Some(action) = save_before_close.action else {
+        saved_events.clear();
+        return;
+    };
+    if !save_before_close.asked {
+        save_before_close.asked = true;
+        return;
+    }
+    *save_before_close = SaveBeforeClose::default();
+
+    // Saved circuits are only marked as unmodified at the end of the frame.
+    let saved: Vec<_> = saved_events.read().map(|event| event.circuit.0).collect();
+    let unsaved = circuits
+        .iter()
+        .any(|(circuit, modified)| modified && !saved.contains(&circuit));
+    if unsaved {
+        return;
+    }
+
+    match action {
+        CloseAction::CloseTab(_) => (),
+        CloseAction::NewProject => {
+            replace_project(
+                &mut commands,
+                project.as_deref(),
+                circuits.iter().map(|(circuit, _)| circuit),
+            );
+        }
+        CloseAction::Quit => {
+            quit_confirmed.0 = true;
+            egui.context.send_viewport_cmd(ViewportCommand::Close);
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct UnsavedChangesPlugin;
+
+impl bevy_app::Plugin for UnsavedChangesPlugin {
+    fn build(&self, app: &mut bevy_app::App) {
+        app.init_resource::<QuitConfirmed>();
+        app.init_resource::<SaveBeforeClose>();
+        app.observe(new_project);
+        app.add_systems(
+            bevy_app::Update,
+            update_unsaved_changes_prompt.after(MenuSet),
+        );
+        app.add_systems(bevy_app::PostUpdate, continue_after_save);
+    }
+}
@@ -0,0 +1,147 @@
+use super::Egui;
+use crate::{FileDialogEvent, Settings};
+use bevy_ecs::prelude::*;
+use digilogic_core::bundles::CircuitBundle;
+use digilogic_core::components::{Circuit, CircuitID, Name, Viewport};
+use digilogic_core::events::*;
+use digilogic_core::resources::Project;
+use digilogic_core::SharedStr;
+use egui::*;
+use std::path::Path;
+
+struct Template {
+    name: &'static str,
+    contents: &'static str,
+}
+
+const TEMPLATES: &[Template] = &[
+    Template {
+        name: "Half Adder",
+        contents: include_str!("../../assets/templates/half_adder.dlc"),
+    },
+    Template {
+        name: "Logic Gates",
+        contents: include_str!("../../assets/templates/logic_gates.dlc"),
+    },
+];
+
+/// Makes sure there is a project to add circuits to.
+pub(super) fn ensure_project(commands: &mut Commands, project: Option<&Project>) {
+    if project.is_none() {
+        commands.insert_resource(Project {
+            name: SharedStr::new_static("Unnamed Project"),
+            file_path: None,
+            root_circuit: None,
+        });
+    }
+}
+
+/// Spawns an empty circuit.
+pub(super) fn spawn_blank_circuit(
+    commands: &mut Commands,
+    circuit_loaded_events: &mut EventWriter<CircuitLoadedEvent>,
+) {
+    let circuit = commands
+        .spawn(CircuitBundle {
+            circuit: Circuit,
+            name: Name(SharedStr::new_static("Unnamed Circuit")),
+        })
+        .id();
+
+    circuit_loaded_events.send(CircuitLoadedEvent {
+        circuit: CircuitID(circuit),
+    });
+}
+
+pub(super) fn no_viewports_open(viewports: Query<(), With<Viewport>>) -> bool {
+    viewports.is_empty()
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(super) fn update_welcome_page(
+    mut commands: Commands,
+    egui: Res<Egui>,
+    mut settings: ResMut<Settings>,
+    project: Option<Res<Project>>,
+    mut file_dialog_events: EventWriter<FileDialogEvent>,
+    mut project_load_events: EventWriter<ProjectLoadEvent>,
+    mut circuit_load_events: EventWriter<CircuitLoadEvent>,
+    mut template_load_events: EventWriter<CircuitTemplateLoadEvent>,
+    mut circuit_loaded_events: EventWriter<CircuitLoadedEvent>,
+) {
+    let mut open_recent = None;
+
+    CentralPanel::default().show(&egui.context, |ui| {
+        ui.vertical_centered(|ui| {
+            ui.add_space(32.0);
+            ui.heading("Welcome to digilogic");
+            ui.add_space(16.0);
+
+            ui.horizontal(|ui| {
+                if ui.button("New Blank Circuit").clicked() {
+                    ensure_project(&mut commands, project.as_deref());
+                    spawn_blank_circuit(&mut commands, &mut circuit_loaded_events);
+                }
+
+                if ui.button("Open Project...").clicked() {
+                    file_dialog_events.send(FileDialogEvent::OpenProject);
+                }
+
+                if ui.button("Import Circuit...").clicked() {
+                    ensure_project(&mut commands, project.as_deref());
+                    file_dialog_events.send(FileDialogEvent::ImportCircuit);
+                }
+            });
+        });
+
+        ui.add_space(16.0);
+
+        ui.columns(2, |columns| {
+            columns[0].heading("Recent Files");
+            if settings.recent_files.is_empty() {
+                columns[0].label("No recent files");
+            }
+            for path in settings.recent_files.iter() {
+                let text = path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| path.display().to_string());
+                let response = columns[0]
+                    .link(text)
+                    .on_hover_text(path.display().to_string());
+                if response.clicked() {
+                    open_recent = Some(path.clone());
+                }
+            }
+
+            columns[1].heading("Templates");
+            for template in TEMPLATES {
+                if columns[1].link(template.name).clicked() {
+                    ensure_project(&mut commands, project.as_deref());
+                    template_load_events.send(CircuitTemplateLoadEvent {
+                        name: SharedStr::new_static(template.name),
+                        contents: SharedStr::new_static(template.contents),
+                    });
+                }
+            }
+        });
+    });
+
+    if let Some(path) = open_recent {
+        if is_project_file(&path) {
+            project_load_events.send(ProjectLoadEvent {
+                filename: path.clone(),
+            });
+        } else {
+            ensure_project(&mut commands, project.as_deref());
+            circuit_load_events.send(CircuitLoadEvent {
+                filename: path.clone(),
+            });
+        }
+        settings.add_recent_file(path);
+    }
+}
+
+fn is_project_file(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "dlp")
+}
@@ -0,0 +1,431 @@
+//! A circuit shared as an automerge document.
+//!
+//! The document maps the ID of every entity of the circuit to its components, each one a JSON
+//! string written through reflection, and the targets of its relations:
+//!
+//! ```text
+//! {
+//!   "circuit": "<id>",
+//!   "entities": {
+//!     "<id>": {
+//!       "components": { "<type path>": "<json>", ... },
+//!       "relations": { "<kind>:<id>": true, ... }
+//!     },
+//!     ...
+//!   }
+//! }
+//! ```
+//!
+//! Concurrent changes to different components of an entity merge, changes to the same component
+//! are resolved the same way by all peers. Entity IDs differ between peers, so the document uses
+//! IDs of its own, which are random entity IDs that are unlikely to collide. References to
+//! entities outside of the circuit, like the circuits instantiated by sub-circuit symbols, are
+//! kept as they are and can only be followed by the peer that made them.
+
+use crate::network::PeerId;
+use anyhow::{anyhow, Result};
+use automerge::sync::{self, SyncDoc};
+use automerge::transaction::Transactable;
+use automerge::{AutoCommit, ObjId, ObjType, ReadDoc, ScalarValue, Value, ROOT};
+use bevy_ecs::entity::EntityHashMap;
+use bevy_ecs::prelude::*;
+use bevy_reflect::serde::{ReflectDeserializer, ReflectSerializer};
+use bevy_reflect::{Reflect, ReflectFromReflect, TypeRegistry};
+use digilogic_core::snapshot::{map_entities, RelationKind, Snapshot};
+use digilogic_core::HashMap;
+use serde::de::DeserializeSeed;
+
+const CIRCUIT_KEY: &str = "circuit";
+const ENTITIES_KEY: &str = "entities";
+const COMPONENTS_KEY: &str = "components";
+const RELATIONS_KEY: &str = "relations";
+
+fn entity_key(entity: Entity) -> String {
+    format!("{:016x}", entity.to_bits())
+}
+
+fn parse_entity_key(key: &str) -> Option<Entity> {
+    let bits = u64::from_str_radix(key, 16).ok()?;
+    Entity::try_from_bits(bits).ok()
+}
+
+/// A random ID for an entity in the document.
+fn new_document_entity() -> Entity {
+    let (bits, _) = uuid::Uuid::new_v4().as_u64_pair();
+    // the highest bit marks other kinds of identifiers, the generation can't be 0
+    Entity::from_bits((bits & !(1 << 63)) | (1 << 32))
+}
+
+fn relation_key(kind: RelationKind, target: Entity) -> String {
+    format!("{kind:?}:{}", entity_key(target))
+}
+
+fn parse_relation_key(key: &str) -> Option<(RelationKind, Entity)> {
+    let (kind, target) = key.split_once(':')?;
+    let kind = RelationKind::ALL
+        .into_iter()
+        .find(|candidate| format!("{candidate:?}") == kind)?;
+    Some((kind, parse_entity_key(target)?))
+}
+
+fn serialize_component(
+    component: &dyn Reflect,
+    entity_map: &EntityHashMap<Entity>,
+    registry: &TypeRegistry,
+) -> Result<String> {
+    let mut component = component.clone_value();
+    map_entities(component.as_mut(), entity_map);
+    let json = serde_json::to_string(&ReflectSerializer::new(component.as_ref(), registry))?;
+    Ok(json)
+}
+
+fn deserialize_component(
+    json: &str,
+    entity_map: &EntityHashMap<Entity>,
+    registry: &TypeRegistry,
+) -> Result<(&'static str, Box<dyn Reflect>)> {
+    let mut deserializer = serde_json::Deserializer::from_str(json);
+    let mut component = ReflectDeserializer::new(registry).deserialize(&mut deserializer)?;
+    map_entities(component.as_mut(), entity_map);
+
+    let registration = component
+        .get_represented_type_info()
+        .and_then(|info| registry.get(info.type_id()))
+        .ok_or_else(|| anyhow!("unregistered component in {json}"))?;
+    let component = registration
+        .data::<ReflectFromReflect>()
+        .and_then(|from_reflect| from_reflect.from_reflect(component.as_ref()))
+        .unwrap_or(component);
+    Ok((registration.type_info().type_path(), component))
+}
+
+/// The map at `key` in `parent`, if there is one.
+fn child_map(doc: &AutoCommit, parent: &ObjId, key: &str) -> Result<Option<ObjId>> {
+    match doc.get(parent, key)? {
+        Some((Value::Object(ObjType::Map), id)) => Ok(Some(id)),
+        _ => Ok(None),
+    }
+}
+
+/// Gets the map at `key` in `parent`, creating it if it doesn't exist.
+fn map_object(doc: &mut AutoCommit, parent: &ObjId, key: &str) -> Result<ObjId> {
+    let existing = child_map(doc, parent, key)?;
+    match existing {
+        Some(id) => Ok(id),
+        None => Ok(doc.put_object(parent, key, ObjType::Map)?),
+    }
+}
+
+/// Makes the map `object` hold exactly `entries`, only writing the entries that changed.
+fn write_entries(
+    doc: &mut AutoCommit,
+    object: &ObjId,
+    entries: &HashMap<String, ScalarValue>,
+) -> Result<()> {
+    let stale = doc
+        .keys(object)
+        .filter(|key| !entries.contains_key(key))
+        .collect::<Vec<_>>();
+    for key in stale {
+        doc.delete(object, key.as_str())?;
+    }
+
+    for (key, value) in entries {
+        let current = doc.get(object, key.as_str())?;
+        let unchanged =
+            matches!(&current, Some((Value::Scalar(current), _)) if **current == *value);
+        if !unchanged {
+            doc.put(object, key.as_str(), value.clone())?;
+        }
+    }
+    Ok(())
+}
+
+/// A circuit shared with the peers of a session, or the one about to be received from the host.
+pub(crate) struct SharedCircuit {
+    doc: AutoCommit,
+    /// The local circuit, `None` until the host's document arrived.
+    circuit: Option<Entity>,
+    /// The IDs of local entities in the document, and the other way around.
+    to_document: EntityHashMap<Entity>,
+    from_document: EntityHashMap<Entity>,
+    /// The state of the circuit last written to or read from the document.
+    published: Option<Snapshot>,
+    sync_states: HashMap<PeerId, sync::State>,
+}
+
+impl std::fmt::Debug for SharedCircuit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SharedCircuit")
+            .field("circuit", &self.circuit)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SharedCircuit {
+    /// Shares `circuit` as a new document.
+    pub(crate) fn host(world: &mut World, circuit: Entity) -> Result<Self> {
+        let mut shared = Self::join();
+        shared.circuit = Some(circuit);
+        shared.published = Some(Snapshot::empty(circuit));
+
+        let id = shared.document_entity(circuit);
+        shared.doc.put(ROOT, CIRCUIT_KEY, entity_key(id))?;
+        shared.doc.put_object(ROOT, ENTITIES_KEY, ObjType::Map)?;
+        shared.publish(world)?;
+        Ok(shared)
+    }
+
+    /// Waits for the document of the host.
+    pub(crate) fn join() -> Self {
+        Self {
+            doc: AutoCommit::new(),
+            circuit: None,
+            to_document: EntityHashMap::default(),
+            from_document: EntityHashMap::default(),
+            published: None,
+            sync_states: HashMap::default(),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn circuit(&self) -> Option<Entity> {
+        self.circuit
+    }
+
+    /// The ID of a local entity in the document, which is made up if it has none yet.
+    fn document_entity(&mut self, entity: Entity) -> Entity {
+        if let Some(&id) = self.to_document.get(&entity) {
+            return id;
+        }
+        let id = new_document_entity();
+        self.to_document.insert(entity, id);
+        self.from_document.insert(id, entity);
+        id
+    }
+
+    /// The local entity with the ID `id` in the document.
+    pub(crate) fn local_entity(&self, id: Entity) -> Option<Entity> {
+        self.from_document.get(&id).copied()
+    }
+
+    /// The ID of a local entity in the document, if it has been shared.
+    pub(crate) fn shared_entity(&self, entity: Entity) -> Option<Entity> {
+        self.to_document.get(&entity).copied()
+    }
+
+    fn forget(&mut self, entity: Entity) {
+        if let Some(id) = self.to_document.remove(&entity) {
+            self.from_document.remove(&id);
+        }
+    }
+
+    fn entities_object(&self) -> Result<Option<ObjId>> {
+        child_map(&self.doc, &ROOT, ENTITIES_KEY)
+    }
+
+    /// Writes the edits made to the circuit since it was last published to the document.
+    /// Returns whether there were any.
+    pub(crate) fn publish(&mut self, world: &mut World) -> Result<bool> {
+        let (Some(circuit), Some(published)) = (self.circuit, self.published.as_ref()) else {
+            return Ok(false);
+        };
+        let current = Snapshot::capture(world, circuit);
+        let diff = published.diff(&current);
+        if diff.is_empty() {
+            return Ok(false);
+        }
+
+        // every entity needs an ID before references to it can be written
+        for entity in current.entities() {
+            self.document_entity(entity);
+        }
+        let entities = self
+            .entities_object()?
+            .ok_or_else(|| anyhow!("the document has no entities"))?;
+
+        for &entity in diff.despawned() {
+            if let Some(id) = self.shared_entity(entity) {
+                self.doc.delete(&entities, entity_key(id))?;
+            }
+            self.forget(entity);
+        }
+
+        let registry = world.resource::<AppTypeRegistry>().clone();
+        let registry = registry.read();
+        for entity in diff.changed() {
+            let mut components = HashMap::default();
+            for (type_path, component) in current.named_components(entity) {
+                let json = serialize_component(component, &self.to_document, &registry)?;
+                components.insert(type_path.to_owned(), ScalarValue::Str(json.into()));
+            }
+            let relations = current
+                .relations(entity)
+                .iter()
+                .map(|&(kind, target)| {
+                    let target = self.to_document.get(&target).copied().unwrap_or(target);
+                    (relation_key(kind, target), ScalarValue::Boolean(true))
+                })
+                .collect();
+
+            let id = self.to_document[&entity];
+            let object = map_object(&mut self.doc, &entities, &entity_key(id))?;
+            let components_object = map_object(&mut self.doc, &object, COMPONENTS_KEY)?;
+            write_entries(&mut self.doc, &components_object, &components)?;
+            let relations_object = map_object(&mut self.doc, &object, RELATIONS_KEY)?;
+            write_entries(&mut self.doc, &relations_object, &relations)?;
+        }
+
+        self.published = Some(current);
+        Ok(true)
+    }
+
+    /// The state of the circuit in the document, with local entities reserved for the entities
+    /// that don't exist locally yet.
+    fn document_snapshot(&mut self, world: &mut World, circuit: Entity) -> Result<Snapshot> {
+        let mut snapshot = Snapshot::empty(circuit);
+        let Some(entities) = self.entities_object()? else {
+            return Ok(snapshot);
+        };
+
+        let ids = self
+            .doc
+            .keys(&entities)
+            .filter_map(|key| Some((parse_entity_key(&key)?, key)))
+            .collect::<Vec<_>>();
+        for &(id, _) in ids.iter() {
+            if !self.from_document.contains_key(&id) {
+                let entity = world.spawn_empty().id();
+                self.to_document.insert(entity, id);
+                self.from_document.insert(id, entity);
+            }
+        }
+
+        let registry = world.resource::<AppTypeRegistry>().clone();
+        let registry = registry.read();
+        for (id, key) in ids {
+            let object = child_map(&self.doc, &entities, &key)?;
+            let Some(object) = object else {
+                continue;
+            };
+
+            let mut components = Vec::new();
+            let components_object = child_map(&self.doc, &object, COMPONENTS_KEY)?;
+            if let Some(components_object) = components_object {
+                for item in self.doc.map_range(&components_object, ..) {
+                    let Some(json) = item.value.to_str() else {
+                        continue;
+                    };
+                    let component = deserialize_component(json, &self.from_document, &registry);
+                    match component {
+                        Ok(component) => components.push(component),
+                        Err(err) => bevy_log::warn!("skipping shared component: {err:#}"),
+                    }
+                }
+            }
+
+            let mut relations = Vec::new();
+            let relations_object = child_map(&self.doc, &object, RELATIONS_KEY)?;
+            if let Some(relations_object) = relations_object {
+                for key in self.doc.keys(&relations_object) {
+                    let Some((kind, target)) = parse_relation_key(&key) else {
+                        continue;
+                    };
+                    if let Some(&target) = self.from_document.get(&target) {
+                        relations.push((kind, target));
+                    }
+                }
+            }
+
+            snapshot.insert(self.from_document[&id], components, relations);
+        }
+        Ok(snapshot)
+    }
+
+    /// Makes the circuit match the document, after changes from peers were received.
+    /// Returns the circuit if it was just received from the host.
+    pub(crate) fn apply(&mut self, world: &mut World) -> Result<Option<Entity>> {
+        let mut received = None;
+        let circuit = match self.circuit {
+            Some(circuit) => circuit,
+            None => {
+                let Some(id) = self
+                    .doc
+                    .get(ROOT, CIRCUIT_KEY)?
+                    .and_then(|(value, _)| parse_entity_key(value.to_str()?))
+                else {
+                    // the document hasn't arrived yet
+                    return Ok(None);
+                };
+                let circuit = world.spawn_empty().id();
+                self.to_document.insert(circuit, id);
+                self.from_document.insert(id, circuit);
+                self.circuit = Some(circuit);
+                self.published = Some(Snapshot::empty(circuit));
+                received = Some(circuit);
+                circuit
+            }
+        };
+
+        let target = self.document_snapshot(world, circuit)?;
+        let current = match received {
+            Some(_) => Snapshot::empty(circuit),
+            None => Snapshot::capture(world, circuit),
+        };
+        let diff = current.diff(&target);
+        let respawned = diff.apply(world);
+
+        for &entity in diff.despawned() {
+            self.forget(entity);
+        }
+        for (entity, respawned) in respawned {
+            if let Some(id) = self.to_document.remove(&entity) {
+                self.to_document.insert(respawned, id);
+                self.from_document.insert(id, respawned);
+            }
+        }
+        // entities reserved for the document that didn't end up in the circuit
+        let captured = Snapshot::capture(world, circuit);
+        self.published = Some(captured);
+        Ok(received)
+    }
+
+    /// The next sync message for `peer`, if it is missing anything.
+    pub(crate) fn sync_message(&mut self, peer: PeerId) -> Option<Vec<u8>> {
+        let state = self.sync_states.entry(peer).or_default();
+        let message = self.doc.sync().generate_sync_message(state)?;
+        Some(message.encode())
+    }
+
+    /// Merges the changes in a sync message from `peer`. Returns whether the document changed.
+    pub(crate) fn receive(&mut self, peer: PeerId, message: &[u8]) -> Result<bool> {
+        let heads = self.doc.get_heads();
+        let message = sync::Message::decode(message)?;
+        let state = self.sync_states.entry(peer).or_default();
+        self.doc.sync().receive_sync_message(state, message)?;
+        Ok(self.doc.get_heads() != heads)
+    }
+
+    /// Forgets what was synchronized with a peer that left.
+    pub(crate) fn remove_peer(&mut self, peer: PeerId) {
+        self.sync_states.remove(&peer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys() {
+        for _ in 0..16 {
+            let entity = new_document_entity();
+            assert_eq!(parse_entity_key(&entity_key(entity)), Some(entity));
+            assert_eq!(
+                parse_relation_key(&relation_key(RelationKind::Grouped, entity)),
+                Some((RelationKind::Grouped, entity))
+            );
+        }
+        assert_eq!(parse_entity_key("circuit"), None);
+    }
+}
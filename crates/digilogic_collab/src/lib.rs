@@ -0,0 +1,563 @@
+//! Collaborative editing of a circuit by several people at once.
+//!
+//! One participant hosts a session for one of their circuits, the others join it by address and
+//! get a copy of the circuit. The edits everyone makes are written to an
+//! [automerge](https://automerge.org) document, which the host synchronizes with everyone over
+//! WebSocket connections. Concurrent edits merge, so the copies end up the same.
+//! Sessions are not authenticated, so hosts only accept peers from the same machine unless
+//! they opt in to accepting other machines as well.
+//!
+//! Participants also share their presence, which is their name, their color, where their cursor
+//! is and what they have selected. The cursors and selections of the others are drawn on top of
+//! the circuit.
+
+mod document;
+mod network;
+
+use anyhow::{anyhow, Result};
+use bevy_ecs::prelude::*;
+use digilogic_core::components::{Circuit, CircuitID, Name, Selected, Viewport};
+use digilogic_core::events::{CircuitLoadedEvent, ErrorEvent};
+use digilogic_core::transform::AbsoluteBoundingBox;
+use digilogic_core::HashMap;
+use digilogic_extension::{DrawOverlaySet, ExtensionAppExt, Overlay};
+use digilogic_ux::{Changelog, CursorPosition};
+use document::SharedCircuit;
+use egui::*;
+use network::{Network, NetworkEvent, Packet, PeerId};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use vello::kurbo::{Affine, BezPath, Rect, Stroke};
+use vello::peniko::{Color as VelloColor, Fill};
+
+/// The port sessions are hosted on unless chosen otherwise.
+pub const DEFAULT_PORT: u16 = 9735;
+
+/// How often the own presence is sent while it doesn't change.
+const PRESENCE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long the presence of a participant is shown after it was last received.
+const PRESENCE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How others see the local participant, and where to host or join sessions.
+#[derive(Debug, Clone, Resource)]
+pub struct CollabSettings {
+    pub name: String,
+    pub color: [u8; 3],
+    pub port: u16,
+    /// Whether hosted sessions accept peers from other machines, not just from this one.
+    /// There is no authentication, anyone who can reach the port can join.
+    pub all_interfaces: bool,
+    /// The address of the host to join, with its port.
+    pub address: String,
+    /// The circuit to host a session for.
+    circuit: Option<Entity>,
+}
+
+impl Default for CollabSettings {
+    fn default() -> Self {
+        let [r, g, b, ..] = uuid::Uuid::new_v4().into_bytes();
+        Self {
+            name: "Anonymous".to_owned(),
+            // keep colors dark enough to stand out from the background
+            color: [r / 2 + 64, g / 2 + 64, b / 2 + 64],
+            port: DEFAULT_PORT,
+            all_interfaces: false,
+            address: format!("localhost:{DEFAULT_PORT}"),
+            circuit: None,
+        }
+    }
+}
+
+/// What a participant shares about themselves, sent as JSON.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Presence {
+    /// Tells the participants apart, since the host relays the presence of everyone else.
+    id: u64,
+    name: String,
+    color: [u8; 3],
+    /// In circuit coordinates.
+    cursor: Option<[f32; 2]>,
+    /// The IDs of the selected entities in the document.
+    selection: Vec<u64>,
+}
+
+#[derive(Debug)]
+struct RemotePresence {
+    presence: Presence,
+    /// The connection the presence arrived over.
+    peer: PeerId,
+    received: Instant,
+}
+
+/// A running session, either hosted or joined.
+#[derive(Debug, Resource)]
+pub struct CollabSession {
+    network: Network,
+    shared: SharedCircuit,
+    /// Where the host can be reached, for joined sessions.
+    address: Option<String>,
+    presence_id: u64,
+    /// The own presence as it was last sent.
+    sent: Option<(Presence, Instant)>,
+    presences: HashMap<u64, RemotePresence>,
+    /// The changelog sequence number the last publish happened at.
+    published_sequence: Option<u64>,
+}
+
+impl CollabSession {
+    /// Shares `circuit` with everyone who joins on `port`, from this machine only
+    /// unless `all_interfaces` is set.
+    pub fn host(
+        world: &mut World,
+        circuit: Entity,
+        port: u16,
+        all_interfaces: bool,
+    ) -> Result<Self> {
+        let network = Network::host(port, all_interfaces)
+            .map_err(|err| anyhow!("cannot host a session on port {port}: {err}"))?;
+        let shared = SharedCircuit::host(world, circuit)?;
+        Ok(Self::new(network, shared, None))
+    }
+
+    /// Joins the session hosted at `address`. The circuit shows up once the host sent it.
+    pub fn join(address: impl Into<String>) -> Self {
+        let address = address.into();
+        let network = Network::join(address.clone());
+        Self::new(network, SharedCircuit::join(), Some(address))
+    }
+
+    fn new(network: Network, shared: SharedCircuit, address: Option<String>) -> Self {
+        Self {
+            network,
+            shared,
+            address,
+            presence_id: uuid::Uuid::new_v4().as_u64_pair().0,
+            sent: None,
+            presences: HashMap::default(),
+            published_sequence: None,
+        }
+    }
+
+    /// The shared circuit, `None` while waiting for the host.
+    #[inline]
+    pub fn circuit(&self) -> Option<Entity> {
+        self.shared.circuit()
+    }
+
+    /// Handles what arrived from peers, then sends them the local changes.
+    fn update(&mut self, world: &mut World) -> Result<()> {
+        let mut received = false;
+        for event in self.network.poll() {
+            match event {
+                // the sync messages for new peers are generated below
+                NetworkEvent::Connected(peer) => {
+                    bevy_log::info!("collaboration peer {peer} connected");
+                }
+                NetworkEvent::Received(peer, Packet::Sync(message)) => {
+                    received |= self.shared.receive(peer, &message)?;
+                }
+                NetworkEvent::Received(peer, Packet::Presence(json)) => {
+                    self.receive_presence(peer, json);
+                }
+                NetworkEvent::Disconnected(peer) => {
+                    self.shared.remove_peer(peer);
+                    self.presences.retain(|_, presence| presence.peer != peer);
+                    if !self.network.is_host() {
+                        return Err(anyhow!("the host ended the session"));
+                    }
+                }
+                NetworkEvent::Failed(message) => return Err(anyhow!(message)),
+            }
+        }
+
+        // Local edits are published before remote ones are applied,
+        // so that applying them doesn't undo the local ones.
+        let sequence = world
+            .get_resource::<Changelog>()
+            .map(Changelog::next_sequence);
+        if sequence.is_none() || (sequence != self.published_sequence) {
+            self.shared.publish(world)?;
+            self.published_sequence = sequence;
+        }
+        if received {
+            if let Some(circuit) = self.shared.apply(world)? {
+                world.send_event(CircuitLoadedEvent {
+                    circuit: CircuitID(circuit),
+                });
+            }
+        }
+
+        for peer in self.network.peers() {
+            if let Some(message) = self.shared.sync_message(peer) {
+                self.network.send(peer, Packet::Sync(message));
+            }
+        }
+
+        self.send_presence(world);
+        self.presences
+            .retain(|_, presence| presence.received.elapsed() < PRESENCE_TIMEOUT);
+        Ok(())
+    }
+
+    fn receive_presence(&mut self, peer: PeerId, json: String) {
+        let presence = match serde_json::from_str::<Presence>(&json) {
+            Ok(presence) => presence,
+            Err(err) => {
+                bevy_log::warn!("invalid presence from collaboration peer {peer}: {err}");
+                return;
+            }
+        };
+
+        if self.network.is_host() {
+            for other in self.network.peers() {
+                if other != peer {
+                    self.network.send(other, Packet::Presence(json.clone()));
+                }
+            }
+        }
+
+        self.presences.insert(
+            presence.id,
+            RemotePresence {
+                presence,
+                peer,
+                received: Instant::now(),
+            },
+        );
+    }
+
+    fn send_presence(&mut self, world: &mut World) {
+        let Some(circuit) = self.shared.circuit() else {
+            return;
+        };
+        let settings = world.get_resource_or_insert_with(CollabSettings::default);
+        let (name, color) = (settings.name.clone(), settings.color);
+
+        let cursor = world
+            .query_filtered::<(&CircuitID, &CursorPosition), With<Viewport>>()
+            .iter(world)
+            .find(|(viewport_circuit, _)| viewport_circuit.0 == circuit)
+            .map(|(_, cursor)| [cursor.x.to_f32(), cursor.y.to_f32()]);
+        let mut selection = world
+            .query_filtered::<Entity, With<Selected>>()
+            .iter(world)
+            .filter_map(|entity| self.shared.shared_entity(entity))
+            .map(Entity::to_bits)
+            .collect::<Vec<_>>();
+        selection.sort_unstable();
+
+        let presence = Presence {
+            id: self.presence_id,
+            name,
+            color,
+            cursor,
+            selection,
+        };
+        let due = match &self.sent {
+            Some((sent, at)) => (*sent != presence) || (at.elapsed() >= PRESENCE_INTERVAL),
+            None => true,
+        };
+        if !due {
+            return;
+        }
+
+        let json = serde_json::to_string(&presence).unwrap();
+        for peer in self.network.peers() {
+            self.network.send(peer, Packet::Presence(json.clone()));
+        }
+        self.sent = Some((presence, Instant::now()));
+    }
+}
+
+fn sync_session(world: &mut World) {
+    let Some(mut session) = world.remove_resource::<CollabSession>() else {
+        return;
+    };
+
+    let result = session.update(world);
+    match result {
+        Ok(()) => world.insert_resource(session),
+        Err(err) => {
+            world.send_event(ErrorEvent::error("collaboration", format!("{err:#}")));
+        }
+    }
+}
+
+fn vello_color([r, g, b]: [u8; 3]) -> VelloColor {
+    VelloColor::rgb8(r, g, b)
+}
+
+fn draw_presences(
+    session: Option<Res<CollabSession>>,
+    bounding_boxes: Query<&AbsoluteBoundingBox>,
+    mut viewports: Query<(&CircuitID, &mut Overlay), With<Viewport>>,
+) {
+    let Some(session) = session else {
+        return;
+    };
+    let Some(circuit) = session.circuit() else {
+        return;
+    };
+
+    for (viewport_circuit, mut overlay) in viewports.iter_mut() {
+        if viewport_circuit.0 != circuit {
+            continue;
+        }
+
+        for RemotePresence { presence, .. } in session.presences.values() {
+            let color = vello_color(presence.color);
+
+            for &id in presence.selection.iter() {
+                let Some(bounding_box) = Entity::try_from_bits(id)
+                    .ok()
+                    .and_then(|id| session.shared.local_entity(id))
+                    .and_then(|entity| bounding_boxes.get(entity).ok())
+                else {
+                    continue;
+                };
+
+                let min = bounding_box.min();
+                let rect = Rect::from_origin_size(
+                    (min.x.to_f32() as f64, min.y.to_f32() as f64),
+                    (
+                        bounding_box.width().to_f32() as f64,
+                        bounding_box.height().to_f32() as f64,
+                    ),
+                )
+                .inflate(3.0, 3.0);
+                overlay
+                    .0
+                    .stroke(&Stroke::new(2.0), Affine::IDENTITY, color, None, &rect);
+            }
+
+            if let Some([x, y]) = presence.cursor {
+                let (x, y) = (x as f64, y as f64);
+                let mut pointer = BezPath::new();
+                pointer.move_to((x, y));
+                pointer.line_to((x, y + 16.0));
+                pointer.line_to((x + 4.5, y + 12.0));
+                pointer.line_to((x + 11.5, y + 12.0));
+                pointer.close_path();
+                overlay
+                    .0
+                    .fill(Fill::NonZero, Affine::IDENTITY, color, None, &pointer);
+            }
+        }
+    }
+}
+
+fn color_swatch(ui: &mut Ui, [r, g, b]: [u8; 3]) {
+    let (rect, _) = ui.allocate_exact_size(vec2(12.0, 12.0), Sense::hover());
+    ui.painter()
+        .rect_filled(rect, 2.0, Color32::from_rgb(r, g, b));
+}
+
+fn show_collab_panel(ui: &mut Ui, world: &mut World) {
+    let mut settings = world
+        .get_resource_or_insert_with(CollabSettings::default)
+        .clone();
+
+    Grid::new("collab_settings").num_columns(2).show(ui, |ui| {
+        ui.label("Name");
+        ui.text_edit_singleline(&mut settings.name);
+        ui.end_row();
+
+        ui.label("Color");
+        ui.color_edit_button_srgb(&mut settings.color);
+        ui.end_row();
+    });
+    ui.separator();
+
+    if let Some(session) = world.get_resource::<CollabSession>() {
+        match (&session.address, session.circuit()) {
+            (None, _) => ui.label(format!("Hosting on port {}", settings.port)),
+            (Some(address), None) => ui.label(format!("Joining {address}…")),
+            (Some(address), Some(_)) => ui.label(format!("Joined {address}")),
+        };
+
+        ui.label("Participants");
+        ui.horizontal(|ui| {
+            color_swatch(ui, settings.color);
+            ui.label(format!("{} (you)", settings.name));
+        });
+        let mut presences = session
+            .presences
+            .values()
+            .map(|remote| &remote.presence)
+            .collect::<Vec<_>>();
+        presences.sort_by(|a, b| a.name.cmp(&b.name));
+        for presence in presences {
+            ui.horizontal(|ui| {
+                color_swatch(ui, presence.color);
+                ui.label(&presence.name);
+            });
+        }
+
+        ui.add_space(8.0);
+        if ui.button("Leave").clicked() {
+            world.remove_resource::<CollabSession>();
+        }
+    } else {
+        let mut circuits = world
+            .query_filtered::<(Entity, &Name), With<Circuit>>()
+            .iter(world)
+            .map(|(circuit, name)| (circuit, name.0.clone()))
+            .collect::<Vec<_>>();
+        circuits.sort_by(|(_, a), (_, b)| a.cmp(b));
+        if !circuits
+            .iter()
+            .any(|&(circuit, _)| Some(circuit) == settings.circuit)
+        {
+            settings.circuit = circuits.first().map(|&(circuit, _)| circuit);
+        }
+
+        let mut host = false;
+        let mut join = false;
+        Grid::new("collab_session").num_columns(2).show(ui, |ui| {
+            ui.label("Circuit");
+            let selected = circuits
+                .iter()
+                .find(|&&(circuit, _)| Some(circuit) == settings.circuit)
+                .map(|(_, name)| name.as_str())
+                .unwrap_or_default();
+            ComboBox::from_id_salt("collab_circuit")
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    for (circuit, name) in circuits.iter() {
+                        ui.selectable_value(&mut settings.circuit, Some(*circuit), name.as_str());
+                    }
+                });
+            ui.end_row();
+
+            ui.label("Port");
+            ui.add(DragValue::new(&mut settings.port).range(1..=u16::MAX));
+            ui.end_row();
+
+            ui.label("");
+            ui.checkbox(&mut settings.all_interfaces, "Accept other machines")
+                .on_hover_text("Anyone who can reach the port can join and edit the circuit");
+            ui.end_row();
+
+            ui.label("");
+            host = ui
+                .add_enabled(settings.circuit.is_some(), Button::new("Host"))
+                .clicked();
+            ui.end_row();
+
+            ui.label("Address");
+            ui.text_edit_singleline(&mut settings.address);
+            ui.end_row();
+
+            ui.label("");
+            join = ui
+                .add_enabled(!settings.address.is_empty(), Button::new("Join"))
+                .clicked();
+            ui.end_row();
+        });
+
+        if let (true, Some(circuit)) = (host, settings.circuit) {
+            let result =
+                CollabSession::host(world, circuit, settings.port, settings.all_interfaces);
+            match result {
+                Ok(session) => world.insert_resource(session),
+                Err(err) => {
+                    world.send_event(ErrorEvent::error("collaboration", format!("{err:#}")));
+                }
+            }
+        } else if join {
+            world.insert_resource(CollabSession::join(settings.address.trim()));
+        }
+    }
+
+    world.insert_resource(settings);
+}
+
+#[derive(Debug, Default)]
+pub struct CollabPlugin;
+
+impl bevy_app::Plugin for CollabPlugin {
+    fn build(&self, app: &mut bevy_app::App) {
+        app.init_resource::<CollabSettings>();
+        app.add_panel("Collaboration", show_collab_panel);
+        app.add_systems(bevy_app::PreUpdate, sync_session);
+        app.add_systems(bevy_app::Update, draw_presences.in_set(DrawOverlaySet));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use digilogic_core::transform::Transform;
+    use digilogic_headless::{HeadlessApp, HeadlessBuilder};
+
+    /// Exchanges sync messages between two documents until neither has anything left to send.
+    fn synchronize(host: &mut SharedCircuit, guest: &mut SharedCircuit) -> bool {
+        let mut received = false;
+        for _ in 0..16 {
+            let to_guest = host.sync_message(1);
+            let to_host = guest.sync_message(network::HOST);
+            if to_guest.is_none() && to_host.is_none() {
+                return received;
+            }
+            if let Some(message) = to_guest {
+                received |= guest.receive(network::HOST, &message).unwrap();
+            }
+            if let Some(message) = to_host {
+                host.receive(1, &message).unwrap();
+            }
+        }
+        panic!("synchronizing doesn't finish");
+    }
+
+    /// The number of symbols, nets and endpoints in a circuit.
+    fn counts(app: &mut HeadlessApp, circuit: Entity) -> (usize, usize, usize) {
+        app.settle();
+        let stats = app.summary(CircuitID(circuit)).unwrap().stats;
+        (stats.symbols, stats.nets, stats.endpoints)
+    }
+
+    #[test]
+    fn synchronize_edits() {
+        let mut host_app = HeadlessBuilder::default().build();
+        let circuit = host_app
+            .load_str(
+                "half adder",
+                include_str!("../../digilogic/assets/templates/half_adder.dlc"),
+            )
+            .unwrap();
+        assert!(host_app.settle());
+        let mut host = SharedCircuit::host(host_app.app_mut().world_mut(), circuit.0).unwrap();
+
+        let mut guest_app = HeadlessBuilder::default().build();
+        let mut guest = SharedCircuit::join();
+        assert!(synchronize(&mut host, &mut guest));
+        let guest_circuit = guest
+            .apply(guest_app.app_mut().world_mut())
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            counts(&mut guest_app, guest_circuit),
+            counts(&mut host_app, circuit.0)
+        );
+
+        // move a symbol on the guest's side
+        let world = guest_app.app_mut().world_mut();
+        let (symbol, mut transform) = world
+            .query_filtered::<(Entity, &mut Transform), With<digilogic_core::components::Symbol>>()
+            .iter_mut(world)
+            .next()
+            .unwrap();
+        transform.translation.x += digilogic_core::fixed!(40);
+        let moved = *transform;
+        assert!(guest.publish(world).unwrap());
+        assert!(!guest.publish(world).unwrap());
+
+        synchronize(&mut host, &mut guest);
+        let host_world = host_app.app_mut().world_mut();
+        assert!(host.apply(host_world).unwrap().is_none());
+        let id = guest.shared_entity(symbol).unwrap();
+        let host_symbol = host.local_entity(id).unwrap();
+        assert_eq!(host_world.get::<Transform>(host_symbol), Some(&moved));
+    }
+}
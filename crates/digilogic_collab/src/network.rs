@@ -0,0 +1,270 @@
+//! The connections of a session. The host accepts peers, which only connect to the host,
+//! and relays what they send to each other.
+
+use digilogic_core::HashMap;
+use digilogic_remote::websocket::{self, Message, Role};
+use std::io::{self, BufReader};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How long joining waits for the host to answer.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Identifies a connection within a [`Network`]. The host is always [`HOST`] to its peers.
+pub(crate) type PeerId = u64;
+
+pub(crate) const HOST: PeerId = 0;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Packet {
+    /// An automerge sync message.
+    Sync(Vec<u8>),
+    /// The presence of a participant, as JSON.
+    Presence(String),
+}
+
+#[derive(Debug)]
+pub(crate) enum NetworkEvent {
+    Connected(PeerId),
+    Received(PeerId, Packet),
+    Disconnected(PeerId),
+    /// Joining the host failed.
+    Failed(String),
+}
+
+enum Outgoing {
+    Packet(Packet),
+    Pong(Vec<u8>),
+}
+
+struct Peer {
+    outgoing: Sender<Outgoing>,
+    /// Shut down to end the threads of the connection.
+    stream: TcpStream,
+}
+
+type Peers = Arc<Mutex<HashMap<PeerId, Peer>>>;
+
+/// The connections of a session, served by threads that hand what they receive to the app.
+pub(crate) struct Network {
+    role: Role,
+    peers: Peers,
+    events: Mutex<Receiver<NetworkEvent>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl std::fmt::Debug for Network {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Network")
+            .field("role", &self.role)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Runs a connection after its handshake, until either side closes it.
+fn serve_peer(
+    peer: PeerId,
+    stream: TcpStream,
+    reader: BufReader<TcpStream>,
+    role: Role,
+    peers: &Peers,
+    events: &Sender<NetworkEvent>,
+) -> io::Result<()> {
+    let (outgoing, outgoing_receiver) = channel();
+    peers.lock().unwrap().insert(
+        peer,
+        Peer {
+            outgoing: outgoing.clone(),
+            stream: stream.try_clone()?,
+        },
+    );
+    let _ = events.send(NetworkEvent::Connected(peer));
+
+    let mut writer = stream;
+    std::thread::spawn(move || {
+        for outgoing in outgoing_receiver {
+            let result = match outgoing {
+                Outgoing::Packet(Packet::Sync(message)) => {
+                    websocket::write_binary(&mut writer, role, &message)
+                }
+                Outgoing::Packet(Packet::Presence(presence)) => {
+                    websocket::write_text(&mut writer, role, &presence)
+                }
+                Outgoing::Pong(payload) => websocket::write_pong(&mut writer, role, &payload),
+            };
+            if result.is_err() {
+                break;
+            }
+        }
+        let _ = websocket::write_close(&mut writer, role);
+    });
+
+    let mut reader = reader;
+    let result = loop {
+        let packet = match websocket::read_message(&mut reader) {
+            Ok(Message::Binary(message)) => Packet::Sync(message),
+            Ok(Message::Text(presence)) => Packet::Presence(presence),
+            Ok(Message::Ping(payload)) => {
+                let _ = outgoing.send(Outgoing::Pong(payload));
+                continue;
+            }
+            Ok(Message::Close) => break Ok(()),
+            Err(err) => break Err(err),
+        };
+        if events.send(NetworkEvent::Received(peer, packet)).is_err() {
+            break Ok(());
+        }
+    };
+
+    // dropping the sender ends the writer thread
+    peers.lock().unwrap().remove(&peer);
+    let _ = events.send(NetworkEvent::Disconnected(peer));
+    result
+}
+
+impl Network {
+    /// Accepts peers on `port`. Peers are accepted from the same machine only,
+    /// unless `all_interfaces` is set, as anyone who can connect may edit the circuit.
+    pub(crate) fn host(port: u16, all_interfaces: bool) -> io::Result<Self> {
+        let ip = if all_interfaces {
+            Ipv4Addr::UNSPECIFIED
+        } else {
+            Ipv4Addr::LOCALHOST
+        };
+        let listener = TcpListener::bind(SocketAddr::from((ip, port)))?;
+        listener.set_nonblocking(true)?;
+
+        let peers = Peers::default();
+        let (events, events_receiver) = channel();
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let next_peer = Arc::new(AtomicU64::new(HOST + 1));
+        let accept_peers = peers.clone();
+        let accept_stop = stop.clone();
+        std::thread::spawn(move || {
+            while !accept_stop.load(Ordering::Relaxed) {
+                let result = listener.accept();
+                let stream = match result {
+                    Ok((stream, _)) => stream,
+                    Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                        std::thread::sleep(Duration::from_millis(50));
+                        continue;
+                    }
+                    Err(err) => {
+                        bevy_log::error!("collaboration host stopped accepting peers: {err}");
+                        break;
+                    }
+                };
+
+                let peer = next_peer.fetch_add(1, Ordering::Relaxed);
+                let peers = accept_peers.clone();
+                let events = events.clone();
+                std::thread::spawn(move || {
+                    let result = stream
+                        .set_nonblocking(false)
+                        .and_then(|()| stream.try_clone())
+                        .and_then(|reader| {
+                            let mut reader = BufReader::new(reader);
+                            let mut writer = stream.try_clone()?;
+                            websocket::handshake(&mut reader, &mut writer)?;
+                            serve_peer(peer, stream, reader, Role::Server, &peers, &events)
+                        });
+                    if let Err(err) = result {
+                        bevy_log::warn!("collaboration peer {peer} disconnected: {err}");
+                    }
+                });
+            }
+        });
+
+        Ok(Self {
+            role: Role::Server,
+            peers,
+            events: Mutex::new(events_receiver),
+            stop,
+        })
+    }
+
+    /// Connects to the host at `addr` in the background,
+    /// reporting the outcome with a [`NetworkEvent`].
+    pub(crate) fn join(addr: String) -> Self {
+        let peers = Peers::default();
+        let (events, events_receiver) = channel();
+
+        let join_peers = peers.clone();
+        std::thread::spawn(move || {
+            let connect = || -> io::Result<(TcpStream, BufReader<TcpStream>)> {
+                let socket_addr = addr
+                    .to_socket_addrs()?
+                    .next()
+                    .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "unknown host"))?;
+                let stream = TcpStream::connect_timeout(&socket_addr, CONNECT_TIMEOUT)?;
+                let mut reader = BufReader::new(stream.try_clone()?);
+                let mut writer = stream.try_clone()?;
+                websocket::client_handshake(&mut reader, &mut writer, &addr)?;
+                Ok((stream, reader))
+            };
+
+            match connect() {
+                Ok((stream, reader)) => {
+                    let result =
+                        serve_peer(HOST, stream, reader, Role::Client, &join_peers, &events);
+                    if let Err(err) = result {
+                        bevy_log::warn!("connection to the collaboration host lost: {err}");
+                    }
+                }
+                Err(err) => {
+                    let _ = events.send(NetworkEvent::Failed(format!(
+                        "cannot join the session at {addr}: {err}"
+                    )));
+                }
+            }
+        });
+
+        Self {
+            role: Role::Client,
+            peers,
+            events: Mutex::new(events_receiver),
+            stop: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn is_host(&self) -> bool {
+        self.role == Role::Server
+    }
+
+    /// The events since the last call.
+    pub(crate) fn poll(&self) -> Vec<NetworkEvent> {
+        self.events.lock().unwrap().try_iter().collect()
+    }
+
+    pub(crate) fn peers(&self) -> Vec<PeerId> {
+        let mut peers = self
+            .peers
+            .lock()
+            .unwrap()
+            .keys()
+            .copied()
+            .collect::<Vec<_>>();
+        peers.sort_unstable();
+        peers
+    }
+
+    pub(crate) fn send(&self, peer: PeerId, packet: Packet) {
+        if let Some(connection) = self.peers.lock().unwrap().get(&peer) {
+            let _ = connection.outgoing.send(Outgoing::Packet(packet));
+        }
+    }
+}
+
+impl Drop for Network {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        for peer in self.peers.lock().unwrap().values() {
+            let _ = peer.stream.shutdown(std::net::Shutdown::Both);
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use crate::components::*;
 use crate::transform::*;
 use crate::visibility::*;
+use crate::{fixed, Fixed};
 use bevy_ecs::prelude::*;
 
 /// A Port is a connection point for an Endpoint. For sub-Circuits,
@@ -25,9 +26,13 @@ pub struct PortBundle {
     /// connected subnet.
     pub bit_width: BitWidth,
 
+    pub direction: PortDirection,
+    pub side: PortSide,
+
     pub transform: TransformBundle,
     pub visibility: VisibilityBundle,
     pub bounds: BoundingBoxBundle,
+    /// The directions wires leave the Port in, following from its side.
     pub directions: DirectionsBundle,
 }
 
@@ -81,6 +86,26 @@ pub struct EndpointBundle {
     pub bounds: BoundingBoxBundle,
 }
 
+/// Half the size of the bounding box Waypoints are grabbed by.
+pub const WAYPOINT_HALF_SIZE: Fixed = fixed!(3);
+
+/// A Waypoint is a point the Wire of an Endpoint has to pass through.
+///
+/// Waypoints have an Endpoint as a Parent. Their Transform is not
+/// relative to the Endpoint, so they stay in place when it moves.
+#[derive(Debug, Bundle)]
+pub struct WaypointBundle {
+    /// The marker that this is a Waypoint
+    pub waypoint: Waypoint,
+
+    /// The position of the Waypoint along the Wire
+    pub number: Number,
+
+    pub transform: TransformBundle,
+    pub visibility: VisibilityBundle,
+    pub bounds: BoundingBoxBundle,
+}
+
 /// A Net is a set of Endpoints that are connected together.
 ///
 /// Nets have a Circuit as a Parent, and Endpoints as Children
@@ -101,6 +126,32 @@ pub struct NetBundle {
     pub visibility: VisibilityBundle,
 }
 
+/// An Annotation is a free text comment in a Circuit.
+///
+/// Annotations have a Circuit as a Parent
+#[derive(Debug, Bundle, Default)]
+pub struct AnnotationBundle {
+    /// The text and its appearance
+    pub annotation: Annotation,
+
+    pub transform: TransformBundle,
+    pub visibility: VisibilityBundle,
+    pub bounds: BoundingBoxBundle,
+}
+
+/// A Graphic is a rectangle, line or ellipse drawn into a Circuit.
+///
+/// Graphics have a Circuit as a Parent
+#[derive(Debug, Bundle, Default)]
+pub struct GraphicBundle {
+    /// The primitive and its appearance
+    pub graphic: Graphic,
+
+    pub transform: TransformBundle,
+    pub visibility: VisibilityBundle,
+    pub bounds: BoundingBoxBundle,
+}
+
 /// A Circuit is a set of Symbols and Nets forming an Electronic Circuit.
 /// It has Symbol and Net Children, and a SymbolKind
 ///
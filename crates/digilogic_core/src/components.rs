@@ -1,9 +1,13 @@
-use crate::SharedStr;
+use crate::transform::{Directions, Vec2};
+use crate::{fixed, Fixed, SharedStr};
 use aery::prelude::*;
 use bevy_derive::{Deref, DerefMut};
 use bevy_ecs::prelude::*;
 use bevy_reflect::Reflect;
+use serde::{Deserialize, Serialize};
 use smallvec::{smallvec, SmallVec};
+use std::collections::BTreeMap;
+use std::fmt;
 use std::num::NonZeroU8;
 use std::path::PathBuf;
 
@@ -15,14 +19,20 @@ use std::path::PathBuf;
 #[aery(Recursive)]
 pub struct Child;
 
+/// Points from a Symbol, or a nested Group, to the Group it is part of.
+#[derive(Debug, Relation)]
+pub struct Grouped;
+
 /////
 // Entity ID components
 /////
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Component, Reflect)]
+#[reflect(Component)]
 pub struct PortID(pub Entity);
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Component, Reflect)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Component, Reflect)]
+#[reflect(Component)]
 pub enum SymbolKind {
     And,
     Or,
@@ -30,29 +40,121 @@ pub enum SymbolKind {
     Not,
     In,
     Out,
+    /// Stores its `D` input on the rising edge of its clock.
+    DFlipFlop,
+    /// Sets, resets or toggles its output on the rising edge of its clock,
+    /// depending on its `J` and `K` inputs.
+    JkFlipFlop,
+    /// Toggles its output on the rising edge of its clock while its `T` input is high.
+    TFlipFlop,
+    /// Passes the data input picked by its select inputs to its output.
+    Mux,
+    /// Passes its data input to the output picked by its select inputs,
+    /// the other outputs are low.
+    Demux,
+    /// Adds its operands and carry input.
+    Adder,
+    /// Subtracts its second operand and borrow input from its first operand.
+    Subtractor,
+    /// Compares its operands as unsigned numbers.
+    Comparator,
+    /// Loads its data input on the rising clock edge while enabled.
+    Register,
+    /// Counts up or down on the rising clock edge while enabled, or loads its data input.
+    Counter,
+    /// Shifts its bits by one in either direction on the rising clock edge while enabled,
+    /// or loads its data input.
+    ShiftRegister,
+    /// Drives its output with the literal set by its `value` parameter.
+    Constant,
+    /// Joins its net with the nets of all tunnels in the same circuit that have the same
+    /// `label` parameter, without a wire between them.
+    Tunnel,
+    /// Splits a bus into taps of some of its bits, or merges the taps into the bus.
+    Splitter,
+    /// Weakly drives the bits of its net high that no other port drives.
+    PullUp,
+    /// Weakly drives the bits of its net low that no other port drives.
+    PullDown,
+    /// Passes its input through while enabled, and leaves its output high impedance otherwise.
+    Buffer,
+    /// Drives its output low where its input is low, and leaves it high impedance where it is high.
+    OpenDrain,
+    /// A kind registered with [`SymbolRegistry::register`](crate::symbol::SymbolRegistry::register),
+    /// by its index in the registry.
+    Custom(u16),
+}
+
+impl SymbolKind {
+    /// The index of the kind in the [`SymbolRegistry`](crate::symbol::SymbolRegistry).
+    pub fn index(self) -> usize {
+        match self {
+            Self::And => 0,
+            Self::Or => 1,
+            Self::Xor => 2,
+            Self::Not => 3,
+            Self::In => 4,
+            Self::Out => 5,
+            Self::DFlipFlop => 6,
+            Self::JkFlipFlop => 7,
+            Self::TFlipFlop => 8,
+            Self::Mux => 9,
+            Self::Demux => 10,
+            Self::Adder => 11,
+            Self::Subtractor => 12,
+            Self::Comparator => 13,
+            Self::Register => 14,
+            Self::Counter => 15,
+            Self::ShiftRegister => 16,
+            Self::Constant => 17,
+            Self::Tunnel => 18,
+            Self::Splitter => 19,
+            Self::PullUp => 20,
+            Self::PullDown => 21,
+            Self::Buffer => 22,
+            Self::OpenDrain => 23,
+            Self::Custom(index) => index as usize,
+        }
+    }
+
+    /// Whether symbols of this kind can be drawn as their [`DeMorgan`] equivalent.
+    pub fn has_de_morgan_equivalent(self) -> bool {
+        matches!(self, Self::And | Self::Or | Self::Not)
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Component, Reflect)]
+#[reflect(Component)]
 pub struct SymbolID(pub Entity);
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Component, Reflect)]
+#[reflect(Component)]
 pub struct WaypointID(pub Entity);
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Component, Reflect)]
+#[reflect(Component)]
 pub struct EndpointID(pub Entity);
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Component, Reflect)]
+#[reflect(Component)]
 pub struct NetID(pub Entity);
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Component, Reflect)]
+#[reflect(Component)]
 pub struct CircuitID(pub Entity);
 
+/// The Circuit a sub-circuit Symbol is an instance of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Component, Reflect)]
+#[reflect(Component)]
+pub struct SubCircuit(pub Entity);
+
 /////
 // Entity part components
 /////
 
 /// The Shape of the Entity as an index into the Shapes Vello can draw
-#[derive(Default, Debug, Component, Clone, Copy, Reflect)]
+#[derive(Default, Debug, Component, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Component)]
 pub enum Shape {
     #[default]
     Chip,
@@ -62,10 +164,26 @@ pub enum Shape {
     Not,
     Input,
     Output,
+    FlipFlop,
+    Mux,
+    Demux,
+    Adder,
+    Subtractor,
+    Comparator,
+    Counter,
+    ShiftRegister,
+    Constant,
+    Tunnel,
+    Splitter,
+    PullUp,
+    PullDown,
+    Buffer,
+    OpenDrain,
 }
 
 /// A Name for the entity.
 #[derive(Default, Debug, Clone, Deref, Component, Reflect)]
+#[reflect(Component)]
 pub struct Name(pub SharedStr);
 
 // The file path of the entity.
@@ -74,23 +192,28 @@ pub struct FilePath(pub PathBuf);
 
 /// The Reference Designator prefix (like U for ICs, R for resistors, etc.)
 #[derive(Default, Debug, Clone, Deref, Component, Reflect)]
+#[reflect(Component)]
 pub struct DesignatorPrefix(pub SharedStr);
 
 /// The Reference Designator number (like 1, 2, 3, etc.)
 #[derive(Default, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Component, Reflect)]
+#[reflect(Component)]
 pub struct DesignatorNumber(pub u32);
 
 /// The Reference Designator suffix (like A, B, C, etc.) if it has one
 #[derive(Default, Debug, Clone, Deref, Component, Reflect)]
+#[reflect(Component)]
 pub struct DesignatorSuffix(pub SharedStr);
 
 /// The Number of the entity (pin number, etc.)
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Component, Reflect)]
+#[reflect(Component)]
 pub struct Number(pub i32);
 
 // The bitwidth of a Port / Symbol / Net.
 // Can be up to 255 bits wide.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Component, Reflect)]
+#[reflect(Component)]
 pub struct BitWidth(pub NonZeroU8);
 
 /// The logic state of the entity
@@ -107,64 +230,607 @@ impl LogicState {
             bit_plane_1: smallvec![1],
         }
     }
+
+    /// The state of `width` bits that are all undefined.
+    pub fn undefined(width: BitWidth) -> Self {
+        let byte_count = width.0.get().div_ceil(8) as usize;
+        Self {
+            bit_plane_0: smallvec![0xFF; byte_count],
+            bit_plane_1: smallvec![0; byte_count],
+        }
+    }
+
+    /// Parses a literal of `width` bits, either decimal or with a `0x`, `0o` or `0b` prefix
+    /// for hexadecimal, octal or binary. Digits may be separated by underscores,
+    /// and binary literals may contain `x` for undefined and `z` for high impedance bits.
+    pub fn parse(text: &str, width: BitWidth) -> Result<Self, LiteralError> {
+        let text = text.trim();
+        let (digits, bits_per_digit) = match text.get(..2) {
+            Some("0x" | "0X") => (&text[2..], 4),
+            Some("0o" | "0O") => (&text[2..], 3),
+            Some("0b" | "0B") => (&text[2..], 1),
+            _ => (text, 0),
+        };
+        let digits = digits.chars().filter(|&c| c != '_');
+        if digits.clone().next().is_none() {
+            return Err(LiteralError::Empty);
+        }
+
+        let width_bits = width.0.get() as usize;
+        let byte_count = width_bits.div_ceil(8);
+        let mut state = Self {
+            bit_plane_0: smallvec![0; byte_count],
+            bit_plane_1: smallvec![0xFF; byte_count],
+        };
+        if width_bits % 8 != 0 {
+            state.bit_plane_1[byte_count - 1] = (1 << (width_bits % 8)) - 1;
+        }
+        let mut set_bit = |bit: usize, plane_0: bool, plane_1: bool| {
+            if bit >= width_bits {
+                return if plane_0 || !plane_1 {
+                    Err(LiteralError::TooWide)
+                } else {
+                    Ok(())
+                };
+            }
+            let mask = 1 << (bit % 8);
+            if plane_0 {
+                state.bit_plane_0[bit / 8] |= mask;
+            }
+            if !plane_1 {
+                state.bit_plane_1[bit / 8] &= !mask;
+            }
+            Ok(())
+        };
+
+        if bits_per_digit == 0 {
+            // decimal literals are limited to 128 bits, which covers all practical constants
+            let value = digits.clone().try_fold(0u128, |value, c| {
+                let digit = c.to_digit(10).ok_or(LiteralError::InvalidDigit(c))?;
+                value
+                    .checked_mul(10)
+                    .and_then(|value| value.checked_add(digit.into()))
+                    .ok_or(LiteralError::TooWide)
+            })?;
+            for bit in (0..128).filter(|&bit| (value >> bit) & 1 != 0) {
+                set_bit(bit, true, true)?;
+            }
+        } else {
+            for (index, c) in digits.rev().enumerate() {
+                let (value, valid) = match c {
+                    'x' | 'X' if bits_per_digit == 1 => (1, false),
+                    'z' | 'Z' if bits_per_digit == 1 => (0, false),
+                    _ => (
+                        c.to_digit(1 << bits_per_digit)
+                            .ok_or(LiteralError::InvalidDigit(c))?,
+                        true,
+                    ),
+                };
+                for offset in 0..bits_per_digit {
+                    let bit = index * bits_per_digit + offset;
+                    set_bit(bit, (value >> offset) & 1 != 0, valid)?;
+                }
+            }
+        }
+
+        Ok(state)
+    }
+}
+
+/// An error parsing a literal with [`LogicState::parse`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LiteralError {
+    /// No digits were given.
+    Empty,
+    /// A character is not a digit of the literal's base.
+    InvalidDigit(char),
+    /// The value does not fit into the given width.
+    TooWide,
+}
+
+impl fmt::Display for LiteralError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => f.write_str("no digits given"),
+            Self::InvalidDigit(c) => write!(f, "`{c}` is not a valid digit"),
+            Self::TooWide => f.write_str("the value does not fit into the width"),
+        }
+    }
 }
 
+impl std::error::Error for LiteralError {}
+
 /// The list of bits that the entity uses in a Net. The order of the bits becomes
 /// the order they are presented to the input of the entity. So, for example, if
 /// a Net is 4 bits wide, and an entity uses bits 1, 3, and 0, then the entity
 /// will be presented with 3 bits, bit 0 being the Net's bit 1, bit 1 being the
 /// Net's bit 3, and bit 2 being the Net's bit 0.
-#[derive(Debug, Component, Reflect)]
+#[derive(Debug, Clone, PartialEq, Eq, Component, Reflect)]
+#[reflect(Component)]
 pub struct Bits(pub SmallVec<[u8; 8]>);
 
-/// The entity is an input
-#[derive(Default, Debug, Component, Reflect)]
-pub struct Input;
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BitsError {
+    /// No bits were given.
+    Empty,
+    /// A part of the list is neither a bit index nor a range of them.
+    Invalid(String),
+    /// A bit is not part of a Net of the given width.
+    OutOfRange { bit: u8, width: NonZeroU8 },
+}
 
-/// The entity is an output
-#[derive(Default, Debug, Component, Reflect)]
-pub struct Output;
+impl fmt::Display for BitsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => f.write_str("no bits given"),
+            Self::Invalid(part) => write!(f, "`{part}` is not a bit or a range of bits"),
+            Self::OutOfRange { bit, width } => {
+                write!(f, "bit {bit} is out of range for a width of {width}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for BitsError {}
+
+impl Bits {
+    /// Parses a comma separated list of bit indices and ranges.
+    /// A range `a:b` includes both ends and runs downwards if `a` is larger than `b`,
+    /// so `3:0,7` is the list 3, 2, 1, 0, 7.
+    pub fn parse(text: &str) -> Result<Self, BitsError> {
+        let parse_bit = |part: &str| {
+            part.trim()
+                .parse::<u8>()
+                .map_err(|_| BitsError::Invalid(part.trim().to_owned()))
+        };
+
+        let mut bits = SmallVec::new();
+        for part in text.split(',').filter(|part| !part.trim().is_empty()) {
+            if let Some((first, last)) = part.split_once(':') {
+                let (first, last) = (parse_bit(first)?, parse_bit(last)?);
+                if first <= last {
+                    bits.extend(first..=last);
+                } else {
+                    bits.extend((last..=first).rev());
+                }
+            } else {
+                bits.push(parse_bit(part)?);
+            }
+        }
+
+        if bits.is_empty() {
+            return Err(BitsError::Empty);
+        }
+        Ok(Self(bits))
+    }
+
+    /// Checks that every bit is part of a Net of the given width.
+    pub fn validate(&self, width: BitWidth) -> Result<(), BitsError> {
+        if self.0.is_empty() {
+            return Err(BitsError::Empty);
+        }
+
+        match self.0.iter().find(|&&bit| bit >= width.0.get()) {
+            Some(&bit) => Err(BitsError::OutOfRange {
+                bit,
+                width: width.0,
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Formats the bits in the form accepted by [`Bits::parse`], with consecutive bits as ranges.
+impl fmt::Display for Bits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut rest = self.0.as_slice();
+        let mut first_part = true;
+
+        while let [first, ..] = rest {
+            let step = match rest.get(1) {
+                Some(&next) if next == first.wrapping_add(1) => 1,
+                Some(&next) if next == first.wrapping_sub(1) => -1,
+                _ => 0,
+            };
+
+            let mut len = 1;
+            if step != 0 {
+                while rest
+                    .get(len)
+                    .is_some_and(|&bit| i16::from(bit) == i16::from(*first) + step * (len as i16))
+                {
+                    len += 1;
+                }
+            }
+
+            if !first_part {
+                f.write_str(",")?;
+            }
+            first_part = false;
+
+            if len > 1 {
+                write!(f, "{}:{}", first, rest[len - 1])?;
+            } else {
+                write!(f, "{first}")?;
+            }
+            rest = &rest[len..];
+        }
+
+        Ok(())
+    }
+}
+
+/// The value of an [`Attributes`] entry.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Reflect)]
+#[serde(untagged)]
+pub enum AttributeValue {
+    String(SharedStr),
+    Number(f64),
+    Bool(bool),
+}
+
+impl fmt::Display for AttributeValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::String(value) => f.write_str(value),
+            Self::Number(value) => write!(f, "{value}"),
+            Self::Bool(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+impl From<&str> for AttributeValue {
+    fn from(value: &str) -> Self {
+        Self::String(value.into())
+    }
+}
+
+impl From<SharedStr> for AttributeValue {
+    fn from(value: SharedStr) -> Self {
+        Self::String(value)
+    }
+}
+
+impl From<f64> for AttributeValue {
+    fn from(value: f64) -> Self {
+        Self::Number(value)
+    }
+}
+
+impl From<bool> for AttributeValue {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+/// Free form data attached to a Symbol, Net or Circuit, like a part number or a note.
+/// Attributes have no meaning to the editor, they are only kept and saved along with the entity.
+#[derive(Default, Debug, Clone, PartialEq, Deref, DerefMut, Component, Reflect)]
+#[reflect(Component)]
+pub struct Attributes(pub BTreeMap<SharedStr, AttributeValue>);
+
+impl Attributes {
+    /// Sets the attribute `key`, returning its previous value.
+    pub fn insert(
+        &mut self,
+        key: impl Into<SharedStr>,
+        value: impl Into<AttributeValue>,
+    ) -> Option<AttributeValue> {
+        self.0.insert(key.into(), value.into())
+    }
+}
+
+/// The value of a symbol parameter, see [`ParamDef`](crate::symbol::ParamDef).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Reflect)]
+#[serde(untagged)]
+pub enum ParamValue {
+    Integer(u32),
+    Text(SharedStr),
+}
+
+impl fmt::Display for ParamValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Integer(value) => write!(f, "{value}"),
+            Self::Text(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+/// The values of the parameters the kind of a Symbol declares, like the number of inputs of a
+/// gate. Unlike [`Attributes`], parameters change how the symbol is built and simulated.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Deref, DerefMut, Component, Reflect)]
+#[reflect(Component)]
+pub struct Parameters(pub BTreeMap<SharedStr, ParamValue>);
+
+impl Parameters {
+    /// The value of the integer parameter `name`, if it is set.
+    pub fn integer(&self, name: &str) -> Option<u32> {
+        match self.0.get(name)? {
+            ParamValue::Integer(value) => Some(*value),
+            ParamValue::Text(_) => None,
+        }
+    }
+
+    /// The value of the text parameter `name`, if it is set.
+    pub fn text(&self, name: &str) -> Option<&SharedStr> {
+        match self.0.get(name)? {
+            ParamValue::Text(value) => Some(value),
+            ParamValue::Integer(_) => None,
+        }
+    }
+}
+
+/// A free text comment in a Circuit. Annotations are Children of their Circuit and
+/// placed by their Transform, which is the top left corner of the text.
+#[derive(Debug, Clone, PartialEq, Component, Reflect)]
+#[reflect(Component)]
+pub struct Annotation {
+    pub text: SharedStr,
+    pub font_size: Fixed,
+    /// RGBA
+    pub color: [u8; 4],
+}
+
+impl Annotation {
+    pub const DEFAULT_FONT_SIZE: Fixed = fixed!(12);
+    pub const DEFAULT_COLOR: [u8; 4] = [255, 255, 255, 255];
+}
+
+impl Default for Annotation {
+    fn default() -> Self {
+        Self {
+            text: SharedStr::default(),
+            font_size: Self::DEFAULT_FONT_SIZE,
+            color: Self::DEFAULT_COLOR,
+        }
+    }
+}
+
+/// The kind of primitive a [`Graphic`] draws.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
+pub enum GraphicKind {
+    #[default]
+    Rectangle,
+    Line,
+    Ellipse,
+}
+
+/// A drawing primitive without electrical meaning, like a frame around a functional block.
+/// Graphics are Children of their Circuit and span from their Transform to `extent` relative
+/// to it. They are not Symbols, so routing and connectivity never see them.
+#[derive(Debug, Clone, PartialEq, Component, Reflect)]
+#[reflect(Component)]
+pub struct Graphic {
+    pub kind: GraphicKind,
+    pub extent: Vec2,
+    /// RGBA
+    pub color: [u8; 4],
+}
+
+impl Graphic {
+    pub const DEFAULT_COLOR: [u8; 4] = [150, 150, 150, 255];
+}
+
+impl Default for Graphic {
+    fn default() -> Self {
+        Self {
+            kind: GraphicKind::default(),
+            extent: Vec2::ZERO,
+            color: Self::DEFAULT_COLOR,
+        }
+    }
+}
+
+/// Which way signals pass through a Port.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Component, Reflect)]
+#[reflect(Component)]
+pub enum PortDirection {
+    Input,
+    /// Drives the Net connected to the Port.
+    Output,
+    /// Drives the Net or reads from it, like the pin of a bus transceiver.
+    Bidirectional,
+}
+
+impl PortDirection {
+    #[inline]
+    pub fn is_input(self) -> bool {
+        self != Self::Output
+    }
+
+    #[inline]
+    pub fn is_output(self) -> bool {
+        self != Self::Input
+    }
+}
+
+/// The side of its Symbol a Port is attached to, before the Symbol is rotated or mirrored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Component, Reflect)]
+#[reflect(Component)]
+pub enum PortSide {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+impl PortSide {
+    /// The direction wires leave a Port on this side in.
+    pub fn directions(self) -> Directions {
+        match self {
+            Self::Left => Directions::NEG_X,
+            Self::Right => Directions::POS_X,
+            Self::Top => Directions::NEG_Y,
+            Self::Bottom => Directions::POS_Y,
+        }
+    }
+}
 
 /// Whether the entity is selected
 #[derive(Default, Debug, Component, Reflect)]
 pub struct Selected;
 
-/// Whether the entity is hovered
+/// Whether the entity is hovered.
+/// Nets are also hovered while one of their endpoints or waypoints is.
 #[derive(Default, Debug, Component, Reflect)]
 #[component(storage = "SparseSet")]
 pub struct Hovered;
 
+/// Whether the entity is highlighted because a related entity in another circuit is selected
+#[derive(Default, Debug, Component, Reflect)]
+#[component(storage = "SparseSet")]
+pub struct Probed;
+
+/// Whether the values of the net are recorded while simulating, for timing diagrams
+#[derive(Default, Debug, Component, Reflect)]
+pub struct Traced;
+
+/// A set of Symbols and other Groups that are selected and moved as a unit.
+/// Groups are Children of their Circuit, their members are related to them with [`Grouped`].
+#[derive(Default, Debug, Component, Reflect)]
+#[reflect(Component)]
+pub struct Group;
+
+/// The entity can't be moved or deleted, and is only box selected when explicitly asked for
+#[derive(Default, Debug, Component, Reflect)]
+#[reflect(Component)]
+pub struct Locked;
+
+/// A gate drawn as its De Morgan equivalent, like an AND gate as an OR gate with negated
+/// inputs and output. It only changes how the symbol looks, not how it is simulated.
+#[derive(Default, Debug, Component, Reflect)]
+#[reflect(Component)]
+pub struct DeMorgan;
+
+/// An endpoint that lost the port it was connected to, for example because its symbol changed
+/// its kind and has no matching port anymore. Cleared once the endpoint is connected again.
+#[derive(Default, Debug, Component, Reflect)]
+#[reflect(Component)]
+pub struct Disconnected;
+
+/// A circuit that has been edited since it was loaded or last saved.
+#[derive(Default, Debug, Component, Reflect)]
+pub struct Modified;
+
 // Entity type tags
 
 /// A Port is a connection point for an Endpoint. For sub-Circuits,
 /// it also connects to an Input or Output Symbol in the child Circuit.
 #[derive(Default, Debug, Component, Reflect)]
+#[reflect(Component)]
 pub struct Port;
 
 /// A Symbol is an instance of a SymbolKind. It has Port Children which
 /// are its input and output Ports. It represents an all or part of an
 /// electronic component.
 #[derive(Default, Debug, Component, Reflect)]
+#[reflect(Component)]
 pub struct Symbol;
 
 /// An Endpoint is a connection point for a Wire. It connects to a Port
 /// in a Symbol. Its Parent is the Subnet that the Endpoint is part of.
 /// It has Waypoint Children.
 #[derive(Default, Debug, Component, Reflect)]
+#[reflect(Component)]
 pub struct Endpoint;
 
+/// A Waypoint is a point the Wire of an Endpoint has to pass through.
+/// Its Parent is the Endpoint, and its Number orders it among the other
+/// Waypoints of the Endpoint, starting from the Endpoint.
+#[derive(Default, Debug, Component, Reflect)]
+#[reflect(Component)]
+pub struct Waypoint;
+
 /// A Net is a set of Subnets that are connected together. It has
 /// Subnet Children, and a Netlist Parent. Often a Net will have
 /// only one Subnet, unless there's a bus split.
 #[derive(Default, Debug, Component, Reflect)]
+#[reflect(Component)]
 pub struct Net;
 
 /// A Circuit is a set of Symbols and Nets forming an Electronic Circuit.
 /// It has Symbol and Net Children, and a SymbolKind
 #[derive(Default, Debug, Component, Reflect)]
+#[reflect(Component)]
 pub struct Circuit;
 
 /// A Viewport is a view into the Circuit. Mostly handled by the UI layer
 /// but defined here for other systems to use.
 #[derive(Default, Debug, Component, Reflect)]
 pub struct Viewport;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_bits() {
+        assert_eq!(Bits::parse("3:0,7").unwrap().0.as_slice(), &[3, 2, 1, 0, 7]);
+        assert_eq!(Bits::parse(" 1, 3 ,0").unwrap().0.as_slice(), &[1, 3, 0]);
+        assert_eq!(Bits::parse(""), Err(BitsError::Empty));
+        assert_eq!(Bits::parse("1:x"), Err(BitsError::Invalid("x".to_owned())));
+    }
+
+    #[test]
+    fn format_bits() {
+        for text in ["3:0,7", "1,3,0", "0:7", "5"] {
+            assert_eq!(Bits::parse(text).unwrap().to_string(), text);
+        }
+    }
+
+    #[test]
+    fn attributes() {
+        let mut attributes = Attributes::default();
+        attributes.insert("part", "SN74LS00N");
+        attributes.insert("tolerance", 0.05);
+        attributes.insert("populated", false);
+        assert_eq!(
+            attributes.insert("part", "SN74HC00N"),
+            Some("SN74LS00N".into())
+        );
+
+        let formatted: Vec<_> = attributes
+            .iter()
+            .map(|(key, value)| format!("{key}={value}"))
+            .collect();
+        assert_eq!(
+            formatted,
+            ["part=SN74HC00N", "populated=false", "tolerance=0.05"]
+        );
+    }
+
+    #[test]
+    fn parse_literals() {
+        let width = BitWidth(NonZeroU8::new(12).unwrap());
+        let planes = |text: &str| {
+            LogicState::parse(text, width)
+                .map(|state| (state.bit_plane_0.to_vec(), state.bit_plane_1.to_vec()))
+        };
+
+        for text in ["1234", "0x4D2", "0o2322", "0b0100_1101_0010"] {
+            assert_eq!(planes(text), Ok((vec![0xD2, 0x04], vec![0xFF, 0x0F])));
+        }
+        assert_eq!(planes("0b1xz"), Ok((vec![0b110, 0], vec![0xFC, 0x0F])));
+        assert_eq!(planes("0x1000"), Err(LiteralError::TooWide));
+        assert_eq!(
+            planes("0x0FFF").map(|(plane_0, _)| plane_0),
+            Ok(vec![0xFF, 0x0F])
+        );
+        assert_eq!(planes("12a"), Err(LiteralError::InvalidDigit('a')));
+        assert_eq!(planes("0x_"), Err(LiteralError::Empty));
+    }
+
+    #[test]
+    fn validate_bits() {
+        let width = BitWidth(NonZeroU8::new(4).unwrap());
+        assert_eq!(Bits::parse("3:0").unwrap().validate(width), Ok(()));
+        assert_eq!(
+            Bits::parse("4").unwrap().validate(width),
+            Err(BitsError::OutOfRange {
+                bit: 4,
+                width: width.0
+            })
+        );
+    }
+}
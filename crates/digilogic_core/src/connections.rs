@@ -0,0 +1,338 @@
+//! Helpers keeping the references between ports, endpoints and nets consistent.
+//!
+//! The hierarchy of a circuit is made of [`Child`] relations: symbols and nets are children of
+//! their circuit, ports of their symbol, endpoints of their net and waypoints of their endpoint.
+//! On top of that an endpoint connected to a port refers to it with a [`PortID`] and follows it
+//! through [`InheritTransform`], and the port refers back to the net with a [`NetID`].
+
+use crate::bundles::EndpointBundle;
+use crate::components::*;
+use crate::transform::{InheritTransform, Transform, Vec2};
+use crate::HashSet;
+use aery::prelude::*;
+use bevy_ecs::prelude::*;
+use bevy_ecs::system::lifetimeless::Read;
+use bevy_ecs::system::SystemParam;
+use std::fmt;
+
+/// Connects `endpoint` of `net` to `port`, so it follows the port around.
+pub fn connect_endpoint(commands: &mut Commands, endpoint: Entity, port: Entity, net: Entity) {
+    commands
+        .entity(endpoint)
+        .insert((PortID(port), Transform::default()))
+        .set::<InheritTransform>(port);
+    commands.entity(port).insert(NetID(net));
+}
+
+/// Spawns an endpoint of `net` connected to `port`.
+pub fn spawn_port_endpoint(commands: &mut Commands, net: Entity, port: Entity) -> Entity {
+    let endpoint = commands
+        .spawn(EndpointBundle::default())
+        .set::<Child>(net)
+        .id();
+    connect_endpoint(commands, endpoint, port, net);
+    endpoint
+}
+
+/// Disconnects `endpoint` from `port`, leaving it at `translation`.
+pub fn disconnect_endpoint(
+    commands: &mut Commands,
+    endpoint: Entity,
+    port: Entity,
+    translation: Vec2,
+) {
+    commands
+        .entity(endpoint)
+        .remove::<PortID>()
+        .unset::<InheritTransform>(port)
+        .insert(Transform {
+            translation,
+            ..Default::default()
+        });
+    commands.entity(port).remove::<NetID>();
+}
+
+/// Moves `endpoint` into `net`, along with the port it is connected to, if any.
+pub fn move_endpoint(commands: &mut Commands, endpoint: Entity, port: Option<Entity>, net: Entity) {
+    commands.entity(endpoint).set::<Child>(net);
+    if let Some(port) = port {
+        commands.entity(port).insert(NetID(net));
+    }
+}
+
+/// A reference between entities of a circuit that doesn't match the others.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectionError {
+    /// An entity is not the child of the kind of entity it belongs to.
+    MissingParent {
+        entity: Entity,
+        kind: &'static str,
+        parent_kind: &'static str,
+    },
+    /// An endpoint refers to an entity that is not a port.
+    UnknownPort { endpoint: Entity, port: Entity },
+    /// An endpoint is connected to a port that doesn't refer to the endpoint's net.
+    PortNetMismatch {
+        endpoint: Entity,
+        port: Entity,
+        net: Option<Entity>,
+    },
+    /// A port refers to a net none of whose endpoints are connected to it.
+    StalePortNet { port: Entity, net: Entity },
+}
+
+impl fmt::Display for ConnectionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingParent {
+                entity,
+                kind,
+                parent_kind,
+            } => write!(f, "{kind} {entity} is not part of a {parent_kind}"),
+            Self::UnknownPort { endpoint, port } => {
+                write!(f, "endpoint {endpoint} is connected to {port}, which is not a port")
+            }
+            Self::PortNetMismatch {
+                endpoint,
+                port,
+                net: Some(net),
+            } => write!(
+                f,
+                "endpoint {endpoint} is connected to port {port}, which belongs to net {net} instead"
+            ),
+            Self::PortNetMismatch {
+                endpoint,
+                port,
+                net: None,
+            } => write!(
+                f,
+                "endpoint {endpoint} is connected to port {port}, which doesn't belong to a net"
+            ),
+            Self::StalePortNet { port, net } => {
+                write!(f, "port {port} belongs to net {net}, but no endpoint of it connects to the port")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConnectionError {}
+
+type PartsQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        Has<Symbol>,
+        Has<Net>,
+        Has<Port>,
+        Has<Endpoint>,
+        Has<Waypoint>,
+    ),
+    Or<(
+        With<Symbol>,
+        With<Net>,
+        With<Port>,
+        With<Endpoint>,
+        With<Waypoint>,
+    )>,
+>;
+
+/// Queries checking the references between the entities of all circuits.
+#[allow(missing_debug_implementations)]
+#[derive(SystemParam)]
+pub struct ConnectionQueries<'w, 's> {
+    parts: PartsQuery<'w, 's>,
+    relations: Query<'w, 's, (Entity, Relations<Child>)>,
+    entities: Query<'w, 's, Entity>,
+    circuits: Query<'w, 's, (), With<Circuit>>,
+    symbols: Query<'w, 's, (), With<Symbol>>,
+    nets: Query<'w, 's, (), With<Net>>,
+    ports: Query<'w, 's, (Entity, Option<Read<NetID>>), With<Port>>,
+    endpoints: Query<'w, 's, (Entity, Option<Read<PortID>>), With<Endpoint>>,
+}
+
+impl ConnectionQueries<'_, '_> {
+    fn parent(&self, entity: Entity) -> Option<Entity> {
+        let (_, edges) = self.relations.get(entity).ok()?;
+        let mut parent = None;
+        edges
+            .join::<Up<Child>>(&self.entities)
+            .for_each(|entity| parent = Some(entity));
+        parent
+    }
+
+    fn net_of(&self, entity: Entity) -> Option<Entity> {
+        self.parent(entity)
+            .filter(|&parent| self.nets.contains(parent))
+    }
+
+    /// Finds all inconsistent references in the world.
+    pub fn errors(&self) -> Vec<ConnectionError> {
+        let mut errors = Vec::new();
+
+        for (entity, is_symbol, is_net, is_port, is_endpoint, is_waypoint) in self.parts.iter() {
+            let (kind, parent_kind, parents): (_, _, &dyn Fn(Entity) -> bool) = if is_symbol {
+                ("symbol", "circuit", &|parent| {
+                    self.circuits.contains(parent)
+                })
+            } else if is_net {
+                ("net", "circuit", &|parent| self.circuits.contains(parent))
+            } else if is_port {
+                ("port", "symbol", &|parent| self.symbols.contains(parent))
+            } else if is_endpoint {
+                ("endpoint", "net", &|parent| self.nets.contains(parent))
+            } else if is_waypoint {
+                ("waypoint", "endpoint", &|parent| {
+                    self.endpoints.contains(parent)
+                })
+            } else {
+                continue;
+            };
+
+            if !self.parent(entity).is_some_and(parents) {
+                errors.push(ConnectionError::MissingParent {
+                    entity,
+                    kind,
+                    parent_kind,
+                });
+            }
+        }
+
+        let mut connected_ports = HashSet::default();
+        for (endpoint, port_id) in self.endpoints.iter() {
+            let Some(&PortID(port)) = port_id else {
+                continue;
+            };
+            let Ok((_, port_net)) = self.ports.get(port) else {
+                errors.push(ConnectionError::UnknownPort { endpoint, port });
+                continue;
+            };
+
+            let Some(net) = self.net_of(endpoint) else {
+                // already reported as missing its parent
+                continue;
+            };
+            connected_ports.insert((port, net));
+
+            let port_net = port_net.map(|port_net| port_net.0);
+            if port_net != Some(net) {
+                errors.push(ConnectionError::PortNetMismatch {
+                    endpoint,
+                    port,
+                    net: port_net,
+                });
+            }
+        }
+
+        for (port, port_net) in self.ports.iter() {
+            let Some(&NetID(net)) = port_net else {
+                continue;
+            };
+            if !connected_ports.contains(&(port, net)) {
+                errors.push(ConnectionError::StalePortNet { port, net });
+            }
+        }
+
+        errors
+    }
+}
+
+type ChangedConnectionsQuery<'w, 's> = Query<'w, 's, (), Or<(Changed<PortID>, Changed<NetID>)>>;
+
+pub(crate) fn connections_changed(
+    changed: ChangedConnectionsQuery,
+    edges_changed: Query<(), aery::edges::EdgeChanged<Child>>,
+    removed_port_ids: RemovedComponents<PortID>,
+    removed_net_ids: RemovedComponents<NetID>,
+    removed_endpoints: RemovedComponents<Endpoint>,
+) -> bool {
+    !changed.is_empty()
+        || !edges_changed.is_empty()
+        || !removed_port_ids.is_empty()
+        || !removed_net_ids.is_empty()
+        || !removed_endpoints.is_empty()
+}
+
+/// Reports inconsistent references between ports, endpoints and nets
+/// that were left behind by loaders or edits.
+pub(crate) fn validate_connections(queries: ConnectionQueries) {
+    for error in queries.errors() {
+        bevy_log::warn!("inconsistent circuit: {error}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy_ecs::system::RunSystemOnce;
+
+    fn errors(world: &mut World) -> Vec<ConnectionError> {
+        world.run_system_once(|queries: ConnectionQueries| queries.errors())
+    }
+
+    #[test]
+    fn validate() {
+        let mut app = bevy_app::App::new();
+        app.register_relation::<Child>()
+            .register_relation::<InheritTransform>();
+
+        let world = app.world_mut();
+        let circuit = world.spawn(Circuit).id();
+        let symbol = world.spawn(Symbol).set::<Child>(circuit).id();
+        let [port_a, port_b] = [(); 2].map(|_| world.spawn(Port).set::<Child>(symbol).id());
+        let [net_a, net_b] = [(); 2].map(|_| world.spawn(Net).set::<Child>(circuit).id());
+
+        // keep both nets populated, removing the last child of an entity also detaches it
+        // from its own parent
+        for net in [net_a, net_b] {
+            world.spawn(EndpointBundle::default()).set::<Child>(net);
+        }
+
+        let endpoint = world.run_system_once(move |mut commands: Commands| {
+            spawn_port_endpoint(&mut commands, net_a, port_a)
+        });
+        world.run_system_once(move |mut commands: Commands| {
+            spawn_port_endpoint(&mut commands, net_a, port_b);
+        });
+        assert_eq!(errors(world), vec![]);
+
+        world.run_system_once(move |mut commands: Commands| {
+            move_endpoint(&mut commands, endpoint, Some(port_a), net_b);
+        });
+        assert_eq!(errors(world), vec![]);
+
+        // moving the endpoint without its port leaves the port behind
+        world.run_system_once(move |mut commands: Commands| {
+            commands.entity(endpoint).set::<Child>(net_a);
+        });
+        assert_eq!(
+            errors(world),
+            vec![
+                ConnectionError::PortNetMismatch {
+                    endpoint,
+                    port: port_a,
+                    net: Some(net_b),
+                },
+                ConnectionError::StalePortNet {
+                    port: port_a,
+                    net: net_b,
+                },
+            ]
+        );
+
+        world.run_system_once(move |mut commands: Commands| {
+            disconnect_endpoint(&mut commands, endpoint, port_a, Vec2::ZERO);
+        });
+        assert_eq!(errors(world), vec![]);
+
+        let waypoint = world.spawn(Waypoint).id();
+        assert_eq!(
+            errors(world),
+            vec![ConnectionError::MissingParent {
+                entity: waypoint,
+                kind: "waypoint",
+                parent_kind: "endpoint",
+            }]
+        );
+    }
+}
@@ -0,0 +1,429 @@
+//! Which ports each net connects, kept up to date as endpoints change.
+//!
+//! Walking from a net through its endpoints to the ports they refer to takes a few joins over
+//! [`Child`] relations. [`Connectivity`] keeps the result of that walk for every circuit, and
+//! only revisits the endpoints and nets that changed since the last frame.
+//!
+//! Tunnels join nets without a wire, so the ports of tunnels are tracked by their label as well.
+
+use crate::components::*;
+use crate::symbol::LABEL_PARAM;
+use crate::{HashMap, SharedStr};
+use aery::edges::EdgeChanged;
+use aery::prelude::*;
+use bevy_ecs::prelude::*;
+use bevy_ecs::system::lifetimeless::Read;
+use std::collections::{BTreeMap, BTreeSet};
+
+#[derive(Debug, Default)]
+struct NetConnections {
+    circuit: Option<Entity>,
+    /// The connected ports, with the number of endpoints connecting each of them.
+    ports: BTreeMap<Entity, u32>,
+}
+
+/// The ports connected to each net of all circuits.
+///
+/// Only nets with at least one endpoint connected to a port are known.
+/// Systems reading the graph should run after [`ConnectivitySet`].
+#[derive(Debug, Default, Resource)]
+pub struct Connectivity {
+    /// The net and port of every endpoint connected to a port.
+    endpoints: HashMap<Entity, (Entity, Entity)>,
+    nets: HashMap<Entity, NetConnections>,
+    /// The net each port is connected to.
+    ports: HashMap<Entity, Entity>,
+    circuits: HashMap<Entity, BTreeSet<Entity>>,
+    /// The ports of the tunnels in each circuit, by label.
+    tunnels: HashMap<(Entity, SharedStr), BTreeSet<Entity>>,
+    /// The circuit and label of the tunnel each tunnel port belongs to.
+    tunnel_ports: HashMap<Entity, (Entity, SharedStr)>,
+}
+
+impl Connectivity {
+    /// The net `port` is connected to.
+    #[inline]
+    pub fn net_of(&self, port: Entity) -> Option<Entity> {
+        self.ports.get(&port).copied()
+    }
+
+    /// The circuit `net` is placed in.
+    #[inline]
+    pub fn circuit_of(&self, net: Entity) -> Option<Entity> {
+        self.nets
+            .get(&net)
+            .and_then(|connections| connections.circuit)
+    }
+
+    /// The ports connected to `net`, in a stable order.
+    pub fn ports_of(&self, net: Entity) -> impl Iterator<Item = Entity> + '_ {
+        self.nets
+            .get(&net)
+            .into_iter()
+            .flat_map(|connections| connections.ports.keys().copied())
+    }
+
+    /// The ports connected to the same net as `port`, including `port` itself.
+    pub fn connected_ports(&self, port: Entity) -> impl Iterator<Item = Entity> + '_ {
+        self.net_of(port)
+            .into_iter()
+            .flat_map(|net| self.ports_of(net))
+    }
+
+    /// The nets of `circuit` that connect any ports, in a stable order.
+    pub fn nets_in(&self, circuit: Entity) -> impl Iterator<Item = Entity> + '_ {
+        self.circuits.get(&circuit).into_iter().flatten().copied()
+    }
+
+    /// The nets joined with `net` by tunnels, including `net` itself, in a stable order.
+    /// Nets joined by tunnels form a single net for all purposes but drawing wires.
+    pub fn joined_nets(&self, net: Entity) -> BTreeSet<Entity> {
+        let mut joined = BTreeSet::from([net]);
+        let mut pending = vec![net];
+        while let Some(net) = pending.pop() {
+            for port in self.ports_of(net) {
+                let Some(tunnel) = self.tunnel_ports.get(&port) else {
+                    continue;
+                };
+
+                let other_nets = self.tunnels[tunnel]
+                    .iter()
+                    .filter_map(|&other| self.net_of(other));
+                for other_net in other_nets {
+                    if joined.insert(other_net) {
+                        pending.push(other_net);
+                    }
+                }
+            }
+        }
+        joined
+    }
+
+    /// The net representing all nets joined with `net` by tunnels.
+    #[inline]
+    pub fn joined_root(&self, net: Entity) -> Entity {
+        self.joined_nets(net).first().copied().unwrap_or(net)
+    }
+
+    fn connect(&mut self, endpoint: Entity, net: Entity, circuit: Option<Entity>, port: Entity) {
+        if self.endpoints.get(&endpoint) == Some(&(net, port)) {
+            return;
+        }
+        self.disconnect(endpoint);
+
+        self.endpoints.insert(endpoint, (net, port));
+        *self
+            .nets
+            .entry(net)
+            .or_default()
+            .ports
+            .entry(port)
+            .or_default() += 1;
+        self.ports.insert(port, net);
+        self.place_net(net, circuit);
+    }
+
+    fn disconnect(&mut self, endpoint: Entity) {
+        let Some((net, port)) = self.endpoints.remove(&endpoint) else {
+            return;
+        };
+        let Some(connections) = self.nets.get_mut(&net) else {
+            return;
+        };
+
+        if let Some(count) = connections.ports.get_mut(&port) {
+            *count -= 1;
+            if *count == 0 {
+                connections.ports.remove(&port);
+                if self.ports.get(&port) == Some(&net) {
+                    self.ports.remove(&port);
+                }
+            }
+        }
+
+        if connections.ports.is_empty() {
+            self.place_net(net, None);
+            self.nets.remove(&net);
+        }
+    }
+
+    fn place_net(&mut self, net: Entity, circuit: Option<Entity>) {
+        let Some(connections) = self.nets.get_mut(&net) else {
+            return;
+        };
+        if connections.circuit == circuit {
+            return;
+        }
+
+        if let Some(previous) = connections.circuit {
+            if let Some(nets) = self.circuits.get_mut(&previous) {
+                nets.remove(&net);
+                if nets.is_empty() {
+                    self.circuits.remove(&previous);
+                }
+            }
+        }
+        if let Some(circuit) = circuit {
+            self.circuits.entry(circuit).or_default().insert(net);
+        }
+        connections.circuit = circuit;
+    }
+}
+
+/// The system updating [`Connectivity`].
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ConnectivitySet;
+
+type ChangedEndpointQuery<'w, 's> =
+    Query<'w, 's, Entity, (With<Endpoint>, Or<(Changed<PortID>, EdgeChanged<Child>)>)>;
+
+type ChangedNetQuery<'w, 's> = Query<'w, 's, Entity, (With<Net>, EdgeChanged<Child>)>;
+
+type ConnectivityEndpointQuery<'w, 's> =
+    Query<'w, 's, (Option<Read<PortID>>, Relations<Child>), With<Endpoint>>;
+
+type ConnectivityNetQuery<'w, 's> = Query<'w, 's, (Entity, Relations<Child>), With<Net>>;
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn update_connectivity(
+    mut connectivity: ResMut<Connectivity>,
+    changed_endpoints: ChangedEndpointQuery,
+    changed_nets: ChangedNetQuery,
+    mut removed_port_ids: RemovedComponents<PortID>,
+    mut removed_endpoints: RemovedComponents<Endpoint>,
+    endpoints: ConnectivityEndpointQuery,
+    nets: ConnectivityNetQuery,
+    circuits: Query<Entity, With<Circuit>>,
+) {
+    let dirty_endpoints = changed_endpoints
+        .iter()
+        .chain(removed_port_ids.read())
+        .chain(removed_endpoints.read())
+        .collect::<Vec<_>>();
+    if dirty_endpoints.is_empty() && changed_nets.is_empty() {
+        return;
+    }
+
+    let circuit_of = |net: Entity| {
+        let mut circuit = None;
+        if let Ok((_, edges)) = nets.get(net) {
+            edges
+                .join::<Up<Child>>(&circuits)
+                .for_each(|entity| circuit = Some(entity));
+        }
+        circuit
+    };
+
+    for endpoint in dirty_endpoints {
+        let Ok((Some(&PortID(port)), edges)) = endpoints.get(endpoint) else {
+            connectivity.disconnect(endpoint);
+            continue;
+        };
+
+        let mut net = None;
+        edges
+            .join::<Up<Child>>(&nets)
+            .for_each(|(entity, _)| net = Some(entity));
+        match net {
+            Some(net) => connectivity.connect(endpoint, net, circuit_of(net), port),
+            None => connectivity.disconnect(endpoint),
+        }
+    }
+
+    for net in changed_nets.iter() {
+        connectivity.place_net(net, circuit_of(net));
+    }
+}
+
+type ChangedTunnelQuery<'w, 's> = Query<
+    'w,
+    's,
+    (),
+    (
+        With<Symbol>,
+        Or<(Changed<SymbolKind>, Changed<Parameters>, EdgeChanged<Child>)>,
+    ),
+>;
+
+type TunnelQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        (Read<SymbolKind>, Option<Read<Parameters>>),
+        Relations<Child>,
+    ),
+    With<Symbol>,
+>;
+
+/// Collects the ports of all tunnels again when any symbol changed its kind, label or circuit.
+pub(crate) fn update_tunnels(
+    mut connectivity: ResMut<Connectivity>,
+    changed_symbols: ChangedTunnelQuery,
+    mut removed_symbols: RemovedComponents<Symbol>,
+    circuits: Query<(Entity, Relations<Child>), With<Circuit>>,
+    symbols: TunnelQuery,
+    ports: Query<Entity, With<Port>>,
+) {
+    if changed_symbols.is_empty() && (removed_symbols.read().count() == 0) {
+        return;
+    }
+
+    let connectivity = &mut *connectivity;
+    connectivity.tunnels.clear();
+    connectivity.tunnel_ports.clear();
+    for (circuit, edges) in circuits.iter() {
+        edges
+            .join::<Child>(&symbols)
+            .for_each(|((&kind, parameters), symbol_edges)| {
+                if kind != SymbolKind::Tunnel {
+                    return;
+                }
+                let Some(label) = parameters
+                    .and_then(|parameters| parameters.text(LABEL_PARAM))
+                    .filter(|label| !label.is_empty())
+                else {
+                    return;
+                };
+
+                symbol_edges.join::<Child>(&ports).for_each(|port| {
+                    let tunnel = (circuit, label.clone());
+                    connectivity
+                        .tunnels
+                        .entry(tunnel.clone())
+                        .or_default()
+                        .insert(port);
+                    connectivity.tunnel_ports.insert(port, tunnel);
+                });
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bundles::EndpointBundle;
+    use crate::connections::{disconnect_endpoint, move_endpoint, spawn_port_endpoint};
+    use crate::transform::{InheritTransform, Vec2};
+
+    #[test]
+    fn follows_endpoints() {
+        let mut app = bevy_app::App::new();
+        app.register_relation::<Child>()
+            .register_relation::<InheritTransform>()
+            .init_resource::<Connectivity>()
+            .add_systems(bevy_app::Update, update_connectivity);
+
+        let world = app.world_mut();
+        let circuit = world.spawn(Circuit).id();
+        let symbol = world.spawn(Symbol).set::<Child>(circuit).id();
+        let [port_a, port_b] = [(); 2].map(|_| world.spawn(Port).set::<Child>(symbol).id());
+        let [net_a, net_b] = [(); 2].map(|_| world.spawn(Net).set::<Child>(circuit).id());
+        for net in [net_a, net_b] {
+            world.spawn(EndpointBundle::default()).set::<Child>(net);
+        }
+
+        let mut commands = world.commands();
+        let endpoint = spawn_port_endpoint(&mut commands, net_a, port_a);
+        spawn_port_endpoint(&mut commands, net_a, port_b);
+        world.flush();
+        app.update();
+
+        let connectivity = app.world().resource::<Connectivity>();
+        assert_eq!(connectivity.net_of(port_a), Some(net_a));
+        assert_eq!(
+            connectivity.connected_ports(port_b).collect::<Vec<_>>(),
+            [port_a, port_b]
+        );
+        assert_eq!(connectivity.nets_in(circuit).collect::<Vec<_>>(), [net_a]);
+
+        let world = app.world_mut();
+        move_endpoint(&mut world.commands(), endpoint, Some(port_a), net_b);
+        world.flush();
+        app.update();
+
+        let connectivity = app.world().resource::<Connectivity>();
+        assert_eq!(connectivity.net_of(port_a), Some(net_b));
+        assert_eq!(connectivity.ports_of(net_a).collect::<Vec<_>>(), [port_b]);
+        assert_eq!(
+            connectivity.nets_in(circuit).collect::<Vec<_>>(),
+            [net_a, net_b]
+        );
+
+        let world = app.world_mut();
+        disconnect_endpoint(&mut world.commands(), endpoint, port_a, Vec2::ZERO);
+        world.flush();
+        app.update();
+
+        let connectivity = app.world().resource::<Connectivity>();
+        assert_eq!(connectivity.net_of(port_a), None);
+        assert_eq!(connectivity.circuit_of(net_b), None);
+        assert_eq!(connectivity.nets_in(circuit).collect::<Vec<_>>(), [net_a]);
+    }
+
+    #[test]
+    fn joins_tunnels() {
+        let mut app = bevy_app::App::new();
+        app.register_relation::<Child>()
+            .register_relation::<InheritTransform>()
+            .init_resource::<Connectivity>()
+            .add_systems(
+                bevy_app::Update,
+                (update_connectivity, update_tunnels).in_set(ConnectivitySet),
+            );
+
+        let world = app.world_mut();
+        let circuit = world.spawn(Circuit).id();
+        let tunnel = |world: &mut World, label: &str| {
+            let parameters = Parameters(
+                [(
+                    SharedStr::new_static(LABEL_PARAM),
+                    ParamValue::Text(label.into()),
+                )]
+                .into(),
+            );
+            let symbol = world
+                .spawn((Symbol, SymbolKind::Tunnel, parameters))
+                .set::<Child>(circuit)
+                .id();
+            world.spawn(Port).set::<Child>(symbol).id()
+        };
+        let ports = [
+            tunnel(world, "clk"),
+            tunnel(world, "clk"),
+            tunnel(world, "rst"),
+            tunnel(world, ""),
+        ];
+        let nets = ports.map(|_| world.spawn(Net).set::<Child>(circuit).id());
+
+        let mut commands = world.commands();
+        for (net, port) in nets.into_iter().zip(ports) {
+            spawn_port_endpoint(&mut commands, net, port);
+        }
+        world.flush();
+        app.update();
+
+        let connectivity = app.world().resource::<Connectivity>();
+        assert_eq!(
+            connectivity
+                .joined_nets(nets[1])
+                .into_iter()
+                .collect::<Vec<_>>(),
+            [nets[0], nets[1]]
+        );
+        assert_eq!(connectivity.joined_root(nets[1]), nets[0]);
+        assert_eq!(
+            connectivity
+                .joined_nets(nets[2])
+                .into_iter()
+                .collect::<Vec<_>>(),
+            [nets[2]]
+        );
+        assert_eq!(
+            connectivity
+                .joined_nets(nets[3])
+                .into_iter()
+                .collect::<Vec<_>>(),
+            [nets[3]]
+        );
+    }
+}
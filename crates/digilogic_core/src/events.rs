@@ -1,4 +1,5 @@
 use crate::components::CircuitID;
+use crate::SharedStr;
 use bevy_ecs::prelude::*;
 use std::path::PathBuf;
 
@@ -15,15 +16,99 @@ pub struct CircuitLoadEvent {
     pub filename: PathBuf,
 }
 
+/// Load a circuit from the contents of a Digilogic circuit file that is not
+/// stored on disk, like the templates bundled with the application.
+#[derive(Debug, Event)]
+pub struct CircuitTemplateLoadEvent {
+    pub name: SharedStr,
+    pub contents: SharedStr,
+}
+
+/// Load a circuit file from a copy of it in the Digilogic format stored elsewhere, like an
+/// autosave. The circuit is treated as loaded from `filename`, loading that file afterwards
+/// returns it.
+#[derive(Debug, Event)]
+pub struct CircuitRecoverEvent {
+    pub filename: PathBuf,
+    pub copy: PathBuf,
+}
+
 #[derive(Debug, Event)]
 pub struct CircuitLoadedEvent {
     pub circuit: CircuitID,
 }
 
-// TODO: fixme
-// #[derive(Event)]
-// pub struct ErrorEvent {
-//     // TODO: Add context to this for logging
-//     // TODO: Add a way to localize this error for display to the user
-//     error: Arc<Box<dyn Error>>,
-// }
+/// Load a circuit again from its file, replacing the loaded version.
+#[derive(Debug, Event)]
+pub struct CircuitReloadEvent {
+    pub circuit: CircuitID,
+}
+
+/// A circuit was loaded again from its file. The `previous` version is despawned,
+/// and the circuit and project resources referencing it refer to `circuit` instead.
+#[derive(Debug, Event)]
+pub struct CircuitReloadedEvent {
+    pub previous: CircuitID,
+    pub circuit: CircuitID,
+}
+
+/// Save a circuit to a file.
+#[derive(Debug, Event)]
+pub struct SaveEvent {
+    pub circuit: CircuitID,
+    pub filename: PathBuf,
+}
+
+/// A circuit was saved to a file.
+#[derive(Debug, Event)]
+pub struct SavedEvent {
+    pub circuit: CircuitID,
+    pub filename: PathBuf,
+}
+
+/// How much an error affects the user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Something didn't work out entirely, but the action went through.
+    Warning,
+    /// The action failed.
+    Error,
+}
+
+/// An error to report to the user, sent by the part of the application it occurred in
+/// instead of just logging it.
+#[derive(Debug, Clone, Event)]
+pub struct ErrorEvent {
+    pub severity: Severity,
+    pub message: String,
+    /// The part of the application reporting the error, like "loader" or "router".
+    pub source: &'static str,
+}
+
+impl ErrorEvent {
+    pub fn error(source: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            message: message.into(),
+            source,
+        }
+    }
+
+    pub fn warning(source: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            message: message.into(),
+            source,
+        }
+    }
+}
+
+/// Logs every reported error, so they also show up when running without a UI.
+pub(crate) fn log_error_events(mut events: EventReader<ErrorEvent>) {
+    for event in events.read() {
+        match event.severity {
+            Severity::Warning => bevy_log::warn!("{}: {}", event.source, event.message),
+            Severity::Error => bevy_log::error!("{}: {}", event.source, event.message),
+        }
+    }
+}
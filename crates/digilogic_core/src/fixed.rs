@@ -1,4 +1,4 @@
-use bevy_reflect::Reflect;
+use bevy_reflect::{Reflect, ReflectDeserialize, ReflectSerialize};
 use std::fmt;
 use std::ops::*;
 
@@ -7,7 +7,7 @@ const FRACT_MASK: i32 = (1 << FRACT_BITS) - 1;
 
 #[derive(Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Reflect)]
 #[repr(transparent)]
-#[reflect_value]
+#[reflect_value(PartialEq, Hash, Serialize, Deserialize)]
 pub struct Fixed(i32);
 
 impl Fixed {
@@ -102,7 +102,7 @@ macro_rules! try_from_float_fn {
             #[inline]
             pub fn $name(value: $t) -> Option<Self> {
                 let scaled = value * ((1 << FRACT_BITS) as $t);
-                if (scaled < (i32::MIN as $t)) || (scaled > (i32::MAX as $t)) {
+                if scaled.is_nan() || (scaled < (i32::MIN as $t)) || (scaled > (i32::MAX as $t)) {
                     None
                 } else {
                     Some(Self(scaled as i32))
@@ -125,7 +125,7 @@ macro_rules! try_to_u_fn {
     ($name:ident : $t:ty) => {
         impl Fixed {
             #[inline]
-            pub fn $name(self) -> Option<$t> {
+            pub const fn $name(self) -> Option<$t> {
                 if self.0 < 0 {
                     None
                 } else {
@@ -154,9 +154,9 @@ macro_rules! try_to_s_fn {
     ($name:ident : $t:ty) => {
         impl Fixed {
             #[inline]
-            pub fn $name(self) -> Option<$t> {
+            pub const fn $name(self) -> Option<$t> {
                 let v = self.0 >> FRACT_BITS;
-                if v > (<$t>::MAX as i32) {
+                if (v < (<$t>::MIN as i32)) || (v > (<$t>::MAX as i32)) {
                     None
                 } else {
                     Some(v as $t)
@@ -179,7 +179,7 @@ macro_rules! to_u_fn {
     ($name:ident : $t:ty) => {
         impl Fixed {
             #[inline]
-            pub fn $name(self) -> Option<$t> {
+            pub const fn $name(self) -> Option<$t> {
                 if self.0 < 0 {
                     None
                 } else {
@@ -203,7 +203,7 @@ macro_rules! to_s_fn {
     ($name:ident : $t:ty) => {
         impl Fixed {
             #[inline]
-            pub fn $name(self) -> $t {
+            pub const fn $name(self) -> $t {
                 (self.0 >> FRACT_BITS) as $t
             }
         }
@@ -302,6 +302,32 @@ macro_rules! fixed {
     }};
 }
 
+/// The integer square root of `n`, rounded to the nearest integer.
+const fn isqrt(n: u64) -> u64 {
+    let mut remainder = n;
+    let mut root = 0;
+    let mut bit = 1 << 62;
+    while bit > n {
+        bit >>= 2;
+    }
+    while bit != 0 {
+        if remainder >= root + bit {
+            remainder -= root + bit;
+            root = (root >> 1) + bit;
+        } else {
+            root >>= 1;
+        }
+        bit >>= 2;
+    }
+
+    // `remainder` is now `n - root²`, and `n` is closer to `(root + 1)²` past `root² + root`
+    if remainder > root {
+        root + 1
+    } else {
+        root
+    }
+}
+
 impl fmt::Debug for Fixed {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         fmt::Debug::fmt(&self.to_f64(), f)
@@ -419,6 +445,125 @@ impl Fixed {
         }
     }
 
+    #[inline]
+    pub const fn checked_add(self, rhs: Self) -> Option<Self> {
+        match self.0.checked_add(rhs.0) {
+            Some(v) => Some(Self(v)),
+            None => None,
+        }
+    }
+
+    #[inline]
+    pub const fn checked_sub(self, rhs: Self) -> Option<Self> {
+        match self.0.checked_sub(rhs.0) {
+            Some(v) => Some(Self(v)),
+            None => None,
+        }
+    }
+
+    #[inline]
+    pub const fn checked_neg(self) -> Option<Self> {
+        match self.0.checked_neg() {
+            Some(v) => Some(Self(v)),
+            None => None,
+        }
+    }
+
+    #[inline]
+    pub const fn checked_mul(self, rhs: Self) -> Option<Self> {
+        let lhs = self.0 as i64;
+        let rhs = rhs.0 as i64;
+        let result64 = (lhs * rhs) >> FRACT_BITS;
+        let result32 = result64 as i32;
+        if (result32 as i64) != result64 {
+            None
+        } else {
+            Some(Self(result32))
+        }
+    }
+
+    #[inline]
+    pub const fn checked_div(self, rhs: Self) -> Option<Self> {
+        if rhs.0 == 0 {
+            return None;
+        }
+
+        let lhs = self.0 as i64;
+        let rhs = rhs.0 as i64;
+        let result64 = (lhs << FRACT_BITS) / rhs;
+        let result32 = result64 as i32;
+        if (result32 as i64) != result64 {
+            None
+        } else {
+            Some(Self(result32))
+        }
+    }
+
+    #[inline]
+    pub const fn checked_rem(self, rhs: Self) -> Option<Self> {
+        match self.0.checked_rem(rhs.0) {
+            Some(v) => Some(Self(v)),
+            None => None,
+        }
+    }
+
+    #[inline]
+    pub const fn checked_abs(self) -> Option<Self> {
+        match self.0.checked_abs() {
+            Some(v) => Some(Self(v)),
+            None => None,
+        }
+    }
+
+    #[inline]
+    pub const fn saturating_add(self, rhs: Self) -> Self {
+        Self(self.0.saturating_add(rhs.0))
+    }
+
+    #[inline]
+    pub const fn saturating_sub(self, rhs: Self) -> Self {
+        Self(self.0.saturating_sub(rhs.0))
+    }
+
+    #[inline]
+    pub const fn saturating_neg(self) -> Self {
+        Self(self.0.saturating_neg())
+    }
+
+    #[inline]
+    pub const fn saturating_mul(self, rhs: Self) -> Self {
+        let lhs = self.0 as i64;
+        let rhs = rhs.0 as i64;
+        let result = (lhs * rhs) >> FRACT_BITS;
+        if result > (i32::MAX as i64) {
+            Self::MAX
+        } else if result < (i32::MIN as i64) {
+            Self::MIN
+        } else {
+            Self(result as i32)
+        }
+    }
+
+    /// Saturating division, panics if `rhs` is zero.
+    #[inline]
+    pub const fn saturating_div(self, rhs: Self) -> Self {
+        let lhs = self.0 as i64;
+        let rhs = rhs.0 as i64;
+        let result = (lhs << FRACT_BITS) / rhs;
+        if result > (i32::MAX as i64) {
+            Self::MAX
+        } else if result < (i32::MIN as i64) {
+            Self::MIN
+        } else {
+            Self(result as i32)
+        }
+    }
+
+    #[inline]
+    pub const fn saturating_abs(self) -> Self {
+        Self(self.0.saturating_abs())
+    }
+
     #[inline]
     pub const fn min(self, rhs: Self) -> Self {
         if self.0 <= rhs.0 {
@@ -437,6 +582,13 @@ impl Fixed {
         }
     }
 
+    /// Rounds to the nearest multiple of `step`, which must be positive.
+    #[inline]
+    pub const fn round_to_multiple(self, step: Self) -> Self {
+        let half = step.0 / 2;
+        Self((self.0 + half).div_euclid(step.0) * step.0)
+    }
+
     #[inline]
     pub const fn clamp(self, min: Self, max: Self) -> Self {
         assert!(min.0 <= max.0);
@@ -473,6 +625,40 @@ impl Fixed {
     pub const fn sqr(self) -> Self {
         self.wrapping_mul(self)
     }
+
+    /// The square root, rounded to the nearest representable value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `self` is negative.
+    #[inline]
+    pub const fn sqrt(self) -> Self {
+        assert!(
+            self.0 >= 0,
+            "attempt to take the square root of a negative number"
+        );
+        Self(isqrt((self.0 as u64) << FRACT_BITS) as i32)
+    }
+
+    /// The length of the vector `(x, y)`, rounded to the nearest representable value.
+    /// The squares of the coordinates can't overflow, only a result above [`Fixed::MAX`] saturates.
+    #[inline]
+    pub const fn hypot(x: Self, y: Self) -> Self {
+        let x = x.0 as i64;
+        let y = y.0 as i64;
+        let length = isqrt((x * x) as u64 + (y * y) as u64);
+        if length > (i32::MAX as u64) {
+            Self::MAX
+        } else {
+            Self(length as i32)
+        }
+    }
+
+    /// Interpolates linearly from `self` at `t = 0` to `rhs` at `t = 1`.
+    #[inline]
+    pub const fn lerp(self, rhs: Self, t: Self) -> Self {
+        self.const_add(rhs.const_sub(self).const_mul(t))
+    }
 }
 
 // FIXME: move all of this into the corresponding traits once const trait impls become stable
@@ -737,4 +923,76 @@ mod inspector {
     }
 }
 
-// TODO: tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn integer_conversions() {
+        assert_eq!(Fixed::from_i16(-3).to_i32(), -3);
+        assert_eq!(Fixed::try_from_i32(1 << 23), None);
+        assert_eq!(Fixed::try_from_i32(-(1 << 23)), Some(Fixed::MIN_INT));
+        assert_eq!(Fixed::from_i16(200).try_to_i8(), None);
+        assert_eq!(Fixed::from_i16(-200).try_to_i8(), None);
+        assert_eq!(Fixed::from_i16(-100).try_to_i8(), Some(-100));
+        assert_eq!(Fixed::from_i8(-1).try_to_u32(), None);
+        assert_eq!(i64::from(fixed!(2.5)), 2);
+    }
+
+    #[test]
+    fn float_conversions() {
+        assert_eq!(Fixed::try_from_f32(1.5), Some(fixed!(1.5)));
+        assert_eq!(Fixed::try_from_f64(f64::NAN), None);
+        assert_eq!(Fixed::try_from_f64(1e10), None);
+        assert_eq!(fixed!(-0.25).to_f64(), -0.25);
+    }
+
+    #[test]
+    fn checked_arithmetic() {
+        assert_eq!(fixed!(1.5).checked_add(fixed!(2)), Some(fixed!(3.5)));
+        assert_eq!(Fixed::MAX.checked_add(Fixed::EPSILON), None);
+        assert_eq!(Fixed::MIN.checked_sub(Fixed::EPSILON), None);
+        assert_eq!(Fixed::MIN.checked_neg(), None);
+        assert_eq!(fixed!(1.5).checked_mul(fixed!(-2)), Some(fixed!(-3)));
+        assert_eq!(Fixed::MAX_INT.checked_mul(fixed!(2)), None);
+        assert_eq!(fixed!(3).checked_div(fixed!(2)), Some(fixed!(1.5)));
+        assert_eq!(fixed!(3).checked_div(fixed!(0)), None);
+        assert_eq!(fixed!(3).checked_rem(fixed!(0)), None);
+        assert_eq!(Fixed::MIN.checked_abs(), None);
+    }
+
+    #[test]
+    fn saturating_arithmetic() {
+        assert_eq!(Fixed::MAX.saturating_add(fixed!(1)), Fixed::MAX);
+        assert_eq!(Fixed::MIN.saturating_sub(fixed!(1)), Fixed::MIN);
+        assert_eq!(Fixed::MIN.saturating_neg(), Fixed::MAX);
+        assert_eq!(Fixed::MAX_INT.saturating_mul(fixed!(-2)), Fixed::MIN);
+        assert_eq!(Fixed::MAX_INT.saturating_div(fixed!(0.5)), Fixed::MAX);
+        assert_eq!(fixed!(-2).saturating_abs(), fixed!(2));
+    }
+
+    #[test]
+    fn square_roots() {
+        for bits in (0..(1 << 20)).chain([i32::MAX - 1, i32::MAX]) {
+            let value = Fixed::from_bits(bits);
+            let error = value.sqrt().to_f64() - value.to_f64().sqrt();
+            assert!(
+                error.abs() <= 0.5 / 256.0,
+                "sqrt({value}) is off by {error}"
+            );
+        }
+        assert_eq!(Fixed::hypot(fixed!(3), fixed!(-4)), fixed!(5));
+        assert_eq!(Fixed::hypot(Fixed::MIN, Fixed::MIN), Fixed::MAX);
+        assert_eq!(fixed!(2).lerp(fixed!(4), fixed!(0.25)), fixed!(2.5));
+        assert_eq!(fixed!(2).lerp(fixed!(-4), fixed!(1)), fixed!(-4));
+    }
+
+    #[test]
+    fn serde_round_trip() {
+        let value = fixed!(-12.75);
+        let ron = ron::to_string(&value).unwrap();
+        assert_eq!(ron::from_str::<Fixed>(&ron).unwrap(), value);
+        assert_eq!(ron::from_str::<Fixed>("7").unwrap(), fixed!(7));
+        assert!(ron::from_str::<Fixed>("10000000").is_err());
+    }
+}
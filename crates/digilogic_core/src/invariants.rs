@@ -0,0 +1,323 @@
+//! Checks that the parts of a circuit carry the components and relations the rest of the code
+//! relies on.
+//!
+//! Systems query for the components of a part and skip or `unwrap()` when one is missing, so a
+//! part spawned without its bundle either silently disappears or panics far away from where it
+//! was spawned. The same goes for parts that are not the [`Child`] of the part they belong to.
+//! In debug builds every new combination of components is checked once, as is every part that
+//! was just spawned or moved to another parent, and any problem is reported as an [`ErrorEvent`]
+//! where it shows up.
+
+use crate::components::*;
+use crate::events::ErrorEvent;
+use crate::transform::{BoundingBox, Directions, Transform};
+use crate::HashSet;
+use aery::edges::{EdgeChanged, EdgeInfo, Edges};
+use bevy_ecs::archetype::ArchetypeId;
+use bevy_ecs::component::ComponentId;
+use bevy_ecs::prelude::*;
+use bevy_ecs::query::QueryFilter;
+use std::fmt;
+
+/// Parts of a circuit that are missing a component every part of their kind has.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingComponent {
+    /// One of the affected entities.
+    pub entity: Entity,
+    /// The number of affected entities.
+    pub count: usize,
+    pub kind: &'static str,
+    pub component: &'static str,
+}
+
+impl fmt::Display for MissingComponent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self {
+            entity,
+            count,
+            kind,
+            component,
+        } = self;
+        write!(f, "{kind} {entity} has no {component}")?;
+        if *count > 1 {
+            write!(f, ", nor do {} other {kind}s like it", count - 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for MissingComponent {}
+
+/// Parts of a circuit that are not the [`Child`] of the kind of part they belong to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingParent {
+    /// One of the affected entities.
+    pub entity: Entity,
+    /// The number of affected entities.
+    pub count: usize,
+    pub kind: &'static str,
+    pub parent: &'static str,
+}
+
+impl fmt::Display for MissingParent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self {
+            entity,
+            count,
+            kind,
+            parent,
+        } = self;
+        write!(f, "{kind} {entity} is not part of a {parent}")?;
+        if *count > 1 {
+            write!(f, ", nor are {} other {kind}s", count - 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for MissingParent {}
+
+struct Rule {
+    kind: &'static str,
+    marker: ComponentId,
+    required: Vec<(&'static str, ComponentId)>,
+}
+
+macro_rules! rule {
+    ($world:ident, $kind:literal, $marker:ty => $($required:ty),+ $(,)?) => {
+        Rule {
+            kind: $kind,
+            marker: $world.init_component::<$marker>(),
+            required: vec![$((stringify!($required), $world.init_component::<$required>())),+],
+        }
+    };
+}
+
+/// The components of the bundle of each kind of part that systems can't do without.
+fn rules(world: &mut World) -> Vec<Rule> {
+    vec![
+        rule!(world, "circuit", Circuit => Name),
+        rule!(
+            world,
+            "symbol",
+            Symbol => SymbolKind,
+            Name,
+            DesignatorPrefix,
+            DesignatorNumber,
+            Shape,
+            Transform,
+            BoundingBox,
+        ),
+        rule!(
+            world,
+            "port",
+            Port => Name,
+            BitWidth,
+            PortDirection,
+            PortSide,
+            Directions,
+            Transform,
+            BoundingBox,
+        ),
+        rule!(world, "net", Net => Name, BitWidth),
+        rule!(world, "endpoint", Endpoint => Transform, BoundingBox),
+        rule!(world, "waypoint", Waypoint => Number, Transform, BoundingBox),
+    ]
+}
+
+/// The parts of `M` matching `F` that are not the child of a `P`.
+fn orphans<M: Component, P: Component, F: QueryFilter>(world: &mut World) -> Vec<Entity> {
+    let mut parts = world.query_filtered::<(Entity, Option<Edges<Child>>), (With<M>, F)>();
+    parts
+        .iter(world)
+        .filter(|(_, edges)| {
+            !edges
+                .targets()
+                .iter()
+                .any(|&parent| world.get::<P>(parent).is_some())
+        })
+        .map(|(entity, _)| entity)
+        .collect()
+}
+
+struct ParentRule {
+    kind: &'static str,
+    parent: &'static str,
+    /// Finds all parts of the kind without their parent.
+    all: fn(&mut World) -> Vec<Entity>,
+    /// Finds the parts of the kind without their parent that were spawned or moved since the
+    /// last check.
+    changed: fn(&mut World) -> Vec<Entity>,
+}
+
+macro_rules! parent_rule {
+    ($kind:literal, $marker:ty => $parent_kind:literal, $parent:ty) => {
+        ParentRule {
+            kind: $kind,
+            parent: $parent_kind,
+            all: orphans::<$marker, $parent, ()>,
+            changed: orphans::<$marker, $parent, Or<(Added<$marker>, EdgeChanged<Child>)>>,
+        }
+    };
+}
+
+/// The part each kind of part has to be the child of.
+fn parent_rules() -> [ParentRule; 3] {
+    [
+        parent_rule!("net", Net => "circuit", Circuit),
+        parent_rule!("endpoint", Endpoint => "net", Net),
+        parent_rule!("port", Port => "symbol", Symbol),
+    ]
+}
+
+fn missing_parents_of(world: &mut World, changed_only: bool) -> Vec<MissingParent> {
+    parent_rules()
+        .into_iter()
+        .filter_map(|rule| {
+            let find = if changed_only { rule.changed } else { rule.all };
+            let orphans = find(world);
+            Some(MissingParent {
+                entity: *orphans.iter().min()?,
+                count: orphans.len(),
+                kind: rule.kind,
+                parent: rule.parent,
+            })
+        })
+        .collect()
+}
+
+/// Remembers which combinations of components were reported already.
+#[derive(Default)]
+pub(crate) struct ArchetypeValidation {
+    rules: Vec<Rule>,
+    reported: HashSet<(ArchetypeId, ComponentId)>,
+}
+
+impl ArchetypeValidation {
+    fn errors(&mut self, world: &mut World) -> Vec<MissingComponent> {
+        if self.rules.is_empty() {
+            self.rules = rules(world);
+        }
+
+        let mut errors = Vec::new();
+        for archetype in world.archetypes().iter() {
+            let Some(first) = archetype.entities().first() else {
+                continue;
+            };
+
+            for rule in self.rules.iter() {
+                if !archetype.contains(rule.marker) {
+                    continue;
+                }
+
+                for &(component, id) in rule.required.iter() {
+                    if archetype.contains(id) || !self.reported.insert((archetype.id(), id)) {
+                        continue;
+                    }
+
+                    errors.push(MissingComponent {
+                        entity: first.id(),
+                        count: archetype.len(),
+                        kind: rule.kind,
+                        component,
+                    });
+                }
+            }
+        }
+        errors
+    }
+}
+
+/// Finds the parts of all circuits that are missing a component of their bundle.
+pub fn missing_components(world: &mut World) -> Vec<MissingComponent> {
+    ArchetypeValidation::default().errors(world)
+}
+
+/// Finds the parts of all circuits that are not the child of the part they belong to.
+pub fn missing_parents(world: &mut World) -> Vec<MissingParent> {
+    missing_parents_of(world, false)
+}
+
+/// Reports parts missing a component as soon as the first one is spawned,
+/// and parts missing their parent as soon as they are spawned or moved, in debug builds.
+pub(crate) fn validate_circuit_parts(
+    world: &mut World,
+    mut validation: Local<ArchetypeValidation>,
+) {
+    let errors: Vec<_> = validation
+        .errors(world)
+        .into_iter()
+        .map(|error| format!("incomplete circuit part: {error}"))
+        .chain(
+            missing_parents_of(world, true)
+                .into_iter()
+                .map(|error| format!("misplaced circuit part: {error}")),
+        )
+        .collect();
+
+    for message in errors {
+        world.send_event(ErrorEvent::warning("invariants", message));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bundles::NetBundle;
+    use aery::prelude::*;
+    use std::num::NonZeroU8;
+
+    #[test]
+    fn reports_missing_components() {
+        let mut world = World::new();
+        world.spawn(NetBundle {
+            net: Net,
+            name: Name::default(),
+            bit_width: BitWidth(NonZeroU8::MIN),
+            visibility: Default::default(),
+        });
+        assert!(missing_components(&mut world).is_empty());
+
+        let net = world.spawn((Net, Name::default())).id();
+        world.spawn((Net, Name::default()));
+        assert_eq!(
+            missing_components(&mut world),
+            [MissingComponent {
+                entity: net,
+                count: 2,
+                kind: "net",
+                component: "BitWidth",
+            }]
+        );
+    }
+
+    #[test]
+    fn reports_missing_parents() {
+        let mut world = World::new();
+        let circuit = world.spawn(Circuit).id();
+        let net = world.spawn(Net).set::<Child>(circuit).id();
+        world.spawn(Endpoint).set::<Child>(net);
+        assert!(missing_parents(&mut world).is_empty());
+
+        let endpoint = world.spawn(Endpoint).set::<Child>(circuit).id();
+        world.spawn(Endpoint);
+        let port = world.spawn(Port).set::<Child>(net).id();
+        assert_eq!(
+            missing_parents(&mut world),
+            [
+                MissingParent {
+                    entity: endpoint,
+                    count: 2,
+                    kind: "endpoint",
+                    parent: "net",
+                },
+                MissingParent {
+                    entity: port,
+                    count: 1,
+                    kind: "port",
+                    parent: "symbol",
+                },
+            ]
+        );
+    }
+}
@@ -1,7 +1,12 @@
 pub mod bundles;
 pub mod components;
+pub mod connections;
+pub mod connectivity;
 pub mod events;
+pub mod invariants;
 pub mod resources;
+pub mod snapshot;
+pub mod stable_id;
 pub mod states;
 pub mod symbol;
 pub mod transform;
@@ -11,7 +16,7 @@ pub mod visibility;
 extern crate static_assertions;
 
 mod shared_str;
-pub use shared_str::SharedStr;
+pub use shared_str::{collect_unused_strings, InternStats, SharedStr, SharedStrPool};
 
 mod fixed;
 pub use fixed::FRACT_BITS as FIXED_FRACT_BITS;
@@ -20,6 +25,22 @@ pub use fixed::{Fixed, FromFixedError, ToFixedError};
 pub type HashSet<T> = ahash::AHashSet<T>;
 pub type HashMap<K, V> = ahash::AHashMap<K, V>;
 
+/// The tracing target of the spans the profiler shows the time spent in.
+pub const PROFILE_TARGET: &str = "digilogic::profile";
+
+#[doc(hidden)]
+pub use bevy_log as __bevy_log;
+
+/// Counts the time until the end of the enclosing scope towards `name` in the profiler.
+/// All scopes with the same name are added up per frame.
+#[macro_export]
+macro_rules! profile_scope {
+    ($name:literal) => {
+        let _profile_scope =
+            $crate::__bevy_log::info_span!(target: $crate::PROFILE_TARGET, $name).entered();
+    };
+}
+
 use bevy_ecs::prelude::*;
 use bevy_ecs::system::SystemParam;
 use bevy_state::prelude::*;
@@ -71,9 +92,11 @@ impl bevy_app::Plugin for CorePlugin {
         }
 
         app.register_relation::<components::Child>();
+        app.register_relation::<components::Grouped>();
 
         app.register_type::<components::PortID>()
             .register_type::<components::SymbolKind>()
+            .register_type::<components::SubCircuit>()
             .register_type::<components::SymbolID>()
             .register_type::<components::WaypointID>()
             .register_type::<components::EndpointID>()
@@ -88,13 +111,28 @@ impl bevy_app::Plugin for CorePlugin {
             .register_type::<components::BitWidth>()
             .register_type::<components::LogicState>()
             .register_type::<components::Bits>()
-            .register_type::<components::Input>()
-            .register_type::<components::Output>()
+            .register_type::<components::AttributeValue>()
+            .register_type::<components::Attributes>()
+            .register_type::<components::ParamValue>()
+            .register_type::<components::Parameters>()
+            .register_type::<components::Annotation>()
+            .register_type::<components::GraphicKind>()
+            .register_type::<components::Graphic>()
+            .register_type::<components::PortDirection>()
+            .register_type::<components::PortSide>()
             .register_type::<components::Selected>()
             .register_type::<components::Hovered>()
+            .register_type::<components::Probed>()
+            .register_type::<components::Traced>()
+            .register_type::<components::Disconnected>()
+            .register_type::<components::Modified>()
+            .register_type::<components::Locked>()
+            .register_type::<components::DeMorgan>()
+            .register_type::<components::Group>()
             .register_type::<components::Port>()
             .register_type::<components::Symbol>()
             .register_type::<components::Endpoint>()
+            .register_type::<components::Waypoint>()
             .register_type::<components::Net>()
             .register_type::<components::Circuit>()
             .register_type::<resources::Project>()
@@ -107,11 +145,40 @@ impl bevy_app::Plugin for CorePlugin {
             .add_computed_state::<states::SimulationActive>();
 
         app.init_resource::<symbol::SymbolRegistry>();
+        app.init_resource::<SharedStrPool>()
+            .add_systems(bevy_app::Last, shared_str::update_shared_str_pool);
+        stable_id::register(app);
 
         app.add_event::<events::ProjectLoadEvent>()
             .add_event::<events::ProjectLoadedEvent>()
             .add_event::<events::CircuitLoadEvent>()
-            .add_event::<events::CircuitLoadedEvent>();
+            .add_event::<events::CircuitTemplateLoadEvent>()
+            .add_event::<events::CircuitRecoverEvent>()
+            .add_event::<events::CircuitLoadedEvent>()
+            .add_event::<events::CircuitReloadEvent>()
+            .add_event::<events::CircuitReloadedEvent>()
+            .add_event::<events::SaveEvent>()
+            .add_event::<events::SavedEvent>()
+            .add_event::<events::ErrorEvent>()
+            .add_systems(bevy_app::Last, events::log_error_events);
+
+        app.add_systems(
+            bevy_app::Last,
+            connections::validate_connections.run_if(connections::connections_changed),
+        );
+
+        app.init_resource::<connectivity::Connectivity>()
+            .add_systems(
+                bevy_app::PostUpdate,
+                (
+                    connectivity::update_connectivity,
+                    connectivity::update_tunnels,
+                )
+                    .in_set(connectivity::ConnectivitySet),
+            );
+
+        #[cfg(debug_assertions)]
+        app.add_systems(bevy_app::Last, invariants::validate_circuit_parts);
 
         app.add_plugins((transform::TransformPlugin, visibility::VisibilityPlugin));
     }
@@ -1,13 +1,119 @@
+use crate::HashSet;
+use bevy_ecs::prelude::*;
 use bevy_reflect::prelude::*;
 use std::borrow::Borrow;
 use std::fmt;
 use std::hash::{Hash, Hasher};
 use std::ops::Deref;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
 
 const SHARED_STR_INLINE_CAP: usize = (size_of::<usize>() * 3) - (size_of::<u8>() * 2);
 const_assert!(SHARED_STR_INLINE_CAP <= (u8::MAX as usize));
 
+/// Strings too long to be stored inline, shared by all `SharedStr`s with the same contents.
+#[derive(Default)]
+struct InternPool {
+    strings: HashSet<Arc<str>>,
+    /// The combined length of `strings`, kept up to date so taking stats doesn't have to add it up.
+    bytes: usize,
+    lookups: u64,
+    hits: u64,
+}
+
+fn intern_pool() -> MutexGuard<'static, InternPool> {
+    static POOL: OnceLock<Mutex<InternPool>> = OnceLock::new();
+
+    POOL.get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+}
+
+/// Returns the pooled copy of `s`, adding the one `to_arc` creates if there is none yet.
+fn intern<S: AsRef<str>>(s: S, to_arc: impl FnOnce(S) -> Arc<str>) -> Arc<str> {
+    let mut pool = intern_pool();
+    pool.lookups += 1;
+    if let Some(interned) = pool.strings.get(s.as_ref()) {
+        let interned = Arc::clone(interned);
+        pool.hits += 1;
+        return interned;
+    }
+
+    let interned = to_arc(s);
+    pool.bytes += interned.len();
+    pool.strings.insert(Arc::clone(&interned));
+    interned
+}
+
+/// How much the strings shared between `SharedStr`s save.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct InternStats {
+    /// The number of distinct strings in the pool.
+    pub strings: usize,
+    /// The combined length of all strings in the pool.
+    pub bytes: usize,
+    /// How often a string was looked up in the pool.
+    pub lookups: u64,
+    /// How often a looked up string was already in the pool.
+    pub hits: u64,
+}
+
+impl InternStats {
+    /// Takes a snapshot of the pool.
+    pub fn current() -> Self {
+        let pool = intern_pool();
+        Self {
+            strings: pool.strings.len(),
+            bytes: pool.bytes,
+            lookups: pool.lookups,
+            hits: pool.hits,
+        }
+    }
+}
+
+/// Removes the strings no `SharedStr` refers to anymore from the pool,
+/// returning how many were removed.
+pub fn collect_unused_strings() -> usize {
+    let mut pool = intern_pool();
+    let InternPool { strings, bytes, .. } = &mut *pool;
+    let before = strings.len();
+    strings.retain(|s| {
+        let used = Arc::strong_count(s) > 1;
+        if !used {
+            *bytes -= s.len();
+        }
+        used
+    });
+    before - strings.len()
+}
+
+/// The state of the pool `SharedStr`s share their contents through.
+#[derive(Debug, Default, Resource)]
+pub struct SharedStrPool {
+    /// Updated once per frame.
+    pub stats: InternStats,
+    /// Whether strings nothing refers to anymore are periodically removed from the pool.
+    pub collect_unused: bool,
+}
+
+/// The number of frames between two collections of unused strings.
+const COLLECT_INTERVAL_FRAMES: u32 = 600;
+
+pub(crate) fn update_shared_str_pool(mut pool: ResMut<SharedStrPool>, mut frames: Local<u32>) {
+    *frames += 1;
+    if pool.collect_unused && (*frames >= COLLECT_INTERVAL_FRAMES) {
+        *frames = 0;
+        let removed = collect_unused_strings();
+        if removed > 0 {
+            bevy_log::debug!("removed {removed} unused strings from the pool");
+        }
+    }
+
+    let stats = InternStats::current();
+    if pool.stats != stats {
+        pool.stats = stats;
+    }
+}
+
 enum SharedStrRepr {
     Static(&'static str),
     Arc(Arc<str>),
@@ -17,8 +123,11 @@ enum SharedStrRepr {
     },
 }
 
+/// An immutable string that is cheap to clone.
+/// Short strings are stored inline, longer ones are shared through a global pool,
+/// so every copy of a repeated name refers to the same allocation.
 #[derive(Reflect)]
-#[reflect_value]
+#[reflect_value(PartialEq, Hash, Serialize, Deserialize)]
 #[repr(transparent)]
 pub struct SharedStr(SharedStrRepr);
 
@@ -145,7 +254,7 @@ impl From<&str> for SharedStr {
         if s.len() <= SHARED_STR_INLINE_CAP {
             Self::new_small(s)
         } else {
-            Self(SharedStrRepr::Arc(s.into()))
+            Self(SharedStrRepr::Arc(intern(s, Into::into)))
         }
     }
 }
@@ -155,7 +264,7 @@ impl From<String> for SharedStr {
         if s.len() <= SHARED_STR_INLINE_CAP {
             Self::new_small(&s)
         } else {
-            Self(SharedStrRepr::Arc(s.into()))
+            Self(SharedStrRepr::Arc(intern(s, Into::into)))
         }
     }
 }
@@ -165,7 +274,7 @@ impl From<Box<str>> for SharedStr {
         if s.len() <= SHARED_STR_INLINE_CAP {
             Self::new_small(&s)
         } else {
-            Self(SharedStrRepr::Arc(s.into()))
+            Self(SharedStrRepr::Arc(intern(s, Into::into)))
         }
     }
 }
@@ -173,7 +282,7 @@ impl From<Box<str>> for SharedStr {
 impl From<Arc<str>> for SharedStr {
     #[inline]
     fn from(s: Arc<str>) -> Self {
-        Self(SharedStrRepr::Arc(s))
+        Self(SharedStrRepr::Arc(intern(s, |s| s)))
     }
 }
 
@@ -293,3 +402,29 @@ mod inspector {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn long_strings_are_shared() {
+        let text = "a name too long to be stored inline";
+        let a = SharedStr::from(text);
+        let b = SharedStr::from(text.to_owned());
+        assert_eq!(a, b);
+        assert_eq!(a.as_ptr(), b.as_ptr());
+
+        let short = SharedStr::from("AND");
+        assert!(matches!(short.0, SharedStrRepr::Small { .. }));
+
+        collect_unused_strings();
+        assert!(intern_pool().strings.contains(text));
+        drop((a, b));
+        collect_unused_strings();
+        let pool = intern_pool();
+        assert!(!pool.strings.contains(text));
+        let bytes = pool.strings.iter().map(|s| s.len()).sum::<usize>();
+        assert_eq!(pool.bytes, bytes);
+    }
+}
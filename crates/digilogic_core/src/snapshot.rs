@@ -0,0 +1,979 @@
+//! Copies of the state of a circuit, and the differences between two of them.
+//!
+//! A [`Snapshot`] holds every component of a circuit and its parts that is registered with
+//! [`ReflectComponent`], along with the relations between them. Components derived from others,
+//! like [`GlobalTransform`](crate::transform::GlobalTransform), and state that doesn't belong to
+//! the circuit, like selection, are not registered that way and are left alone. The
+//! [`VisibleLayers`] of a viewport is registered as a component too, but is skipped for the same
+//! reason.
+//!
+//! Applying the [`SnapshotDiff`] from one snapshot to another turns the circuit of the first into
+//! the second. Collaborative editing publishes local edits and applies remote ones this way.
+//! [`SnapshotDiff::serializable`] and [`SnapshotDiffDeserializer`] convert diffs from and to any
+//! serde format through reflection.
+
+use crate::components::{Child, Grouped};
+use crate::transform::*;
+use crate::visibility::{ComputedVisibility, InheritVisibility, Visibility, VisibleLayers};
+use aery::edges::{EdgeInfo, Edges};
+use aery::prelude::*;
+use bevy_ecs::entity::EntityHashMap;
+use bevy_ecs::prelude::*;
+use bevy_reflect::serde::{ReflectDeserializer, ReflectSerializer};
+use bevy_reflect::{Reflect, ReflectMut, TypeRegistry};
+use serde::de::{DeserializeSeed, Error as _, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// The relations between the parts of a circuit that snapshots keep.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum RelationKind {
+    Child,
+    Grouped,
+    InheritTransform,
+    InheritVisibility,
+}
+
+impl RelationKind {
+    pub const ALL: [Self; 4] = [
+        Self::Child,
+        Self::Grouped,
+        Self::InheritTransform,
+        Self::InheritVisibility,
+    ];
+
+    fn set(self, entity: &mut EntityWorldMut, target: Entity) {
+        match self {
+            Self::Child => entity.set::<Child>(target),
+            Self::Grouped => entity.set::<Grouped>(target),
+            Self::InheritTransform => entity.set::<InheritTransform>(target),
+            Self::InheritVisibility => entity.set::<InheritVisibility>(target),
+        };
+    }
+
+    fn unset(self, entity: &mut EntityWorldMut, target: Entity) {
+        match self {
+            Self::Child => entity.unset::<Child>(target),
+            Self::Grouped => entity.unset::<Grouped>(target),
+            Self::InheritTransform => entity.unset::<InheritTransform>(target),
+            Self::InheritVisibility => entity.unset::<InheritVisibility>(target),
+        };
+    }
+
+    fn unset_all(self, entity: &mut EntityWorldMut) {
+        match self {
+            Self::Child => entity.unset_all::<Child>(),
+            Self::Grouped => entity.unset_all::<Grouped>(),
+            Self::InheritTransform => entity.unset_all::<InheritTransform>(),
+            Self::InheritVisibility => entity.unset_all::<InheritVisibility>(),
+        };
+    }
+}
+
+type RelationTarget = (RelationKind, Entity);
+
+/// Adds the components derived from the ones of a spawned entity, which snapshots don't keep.
+/// Their values are computed by the systems keeping them up to date.
+fn insert_derived_components(entity: &mut EntityWorldMut) {
+    fn insert_for<C: Component, D: Component + Default>(entity: &mut EntityWorldMut) {
+        if entity.contains::<C>() && !entity.contains::<D>() {
+            entity.insert(D::default());
+        }
+    }
+
+    insert_for::<Transform, GlobalTransform>(entity);
+    insert_for::<BoundingBox, AbsoluteBoundingBox>(entity);
+    insert_for::<Direction, AbsoluteDirection>(entity);
+    insert_for::<Directions, AbsoluteDirections>(entity);
+    insert_for::<Visibility, ComputedVisibility>(entity);
+}
+
+/// The components of a single entity, by type path.
+type Components = Vec<(&'static str, Box<dyn Reflect>)>;
+
+#[derive(Debug, Default)]
+struct EntitySnapshot {
+    /// Sorted by type path.
+    components: Components,
+    /// The targets of the relations of the entity, sorted.
+    relations: Vec<RelationTarget>,
+}
+
+/// The state of a circuit and all of its parts at one point in time.
+#[derive(Debug)]
+pub struct Snapshot {
+    circuit: Entity,
+    entities: BTreeMap<Entity, EntitySnapshot>,
+}
+
+/// The type paths and reflection data of all components snapshots capture, sorted by type path.
+fn snapshot_components(registry: &TypeRegistry) -> Vec<(&'static str, &ReflectComponent)> {
+    let mut components = registry
+        .iter()
+        .filter(|registration| {
+            // Relation edges are captured by their targets instead, so that restoring
+            // them keeps both sides of every edge in sync.
+            registration.type_info().type_path_table().crate_name() != Some("aery")
+        })
+        .filter(|registration| registration.type_id() != std::any::TypeId::of::<VisibleLayers>())
+        .filter_map(|registration| {
+            let component = registration.data::<ReflectComponent>()?;
+            Some((registration.type_info().type_path(), component))
+        })
+        .collect::<Vec<_>>();
+    components.sort_unstable_by_key(|&(type_path, _)| type_path);
+    components
+}
+
+fn capture_relations<R: Relation>(
+    world: &mut World,
+    kind: RelationKind,
+    entities: &mut BTreeMap<Entity, EntitySnapshot>,
+) {
+    let mut edges = world.query::<Edges<R>>();
+    for (&entity, snapshot) in entities.iter_mut() {
+        if let Ok(edges) = edges.get(world, entity) {
+            let targets = edges.targets().iter().map(|&target| (kind, target));
+            snapshot.relations.extend(targets);
+        }
+    }
+}
+
+impl Snapshot {
+    /// Captures `circuit` and all entities below it.
+    pub fn capture(world: &mut World, circuit: Entity) -> Self {
+        let mut entities = BTreeMap::new();
+        let mut children = world.query::<Edges<Child>>();
+        let mut pending = vec![circuit];
+        while let Some(entity) = pending.pop() {
+            if world.get_entity(entity).is_none() {
+                continue;
+            }
+            if let Ok(edges) = children.get(world, entity) {
+                pending.extend_from_slice(edges.hosts());
+            }
+            entities.insert(entity, EntitySnapshot::default());
+        }
+
+        let registry = world.resource::<AppTypeRegistry>().clone();
+        let registry = registry.read();
+        let components = snapshot_components(&registry);
+        for (&entity, snapshot) in entities.iter_mut() {
+            let entity = world.entity(entity);
+            snapshot.components = components
+                .iter()
+                .filter_map(|&(type_path, component)| {
+                    Some((type_path, component.reflect(entity)?.clone_value()))
+                })
+                .collect();
+        }
+
+        capture_relations::<Child>(world, RelationKind::Child, &mut entities);
+        capture_relations::<Grouped>(world, RelationKind::Grouped, &mut entities);
+        capture_relations::<InheritTransform>(world, RelationKind::InheritTransform, &mut entities);
+        capture_relations::<InheritVisibility>(
+            world,
+            RelationKind::InheritVisibility,
+            &mut entities,
+        );
+        for snapshot in entities.values_mut() {
+            snapshot.relations.sort_unstable();
+        }
+
+        Self { circuit, entities }
+    }
+
+    /// A snapshot of nothing, to be filled with [`Snapshot::insert`].
+    pub fn empty(circuit: Entity) -> Self {
+        Self {
+            circuit,
+            entities: BTreeMap::new(),
+        }
+    }
+
+    /// Adds an entity with its components, by type path, and the targets of its relations.
+    /// Replaces whatever was captured for the entity before.
+    pub fn insert(
+        &mut self,
+        entity: Entity,
+        mut components: Vec<(&'static str, Box<dyn Reflect>)>,
+        mut relations: Vec<(RelationKind, Entity)>,
+    ) {
+        components.sort_unstable_by_key(|&(type_path, _)| type_path);
+        relations.sort_unstable();
+        self.entities.insert(
+            entity,
+            EntitySnapshot {
+                components,
+                relations,
+            },
+        );
+    }
+
+    #[inline]
+    pub fn circuit(&self) -> Entity {
+        self.circuit
+    }
+
+    /// The circuit and all entities below it, in a stable order.
+    pub fn entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.entities.keys().copied()
+    }
+
+    /// The captured components of `entity`, in a stable order.
+    pub fn components(&self, entity: Entity) -> impl Iterator<Item = &dyn Reflect> + '_ {
+        self.entities
+            .get(&entity)
+            .into_iter()
+            .flat_map(|snapshot| snapshot.components.iter())
+            .map(|(_, component)| component.as_ref())
+    }
+
+    /// The captured components of `entity` along with their type paths, sorted by type path.
+    pub fn named_components(
+        &self,
+        entity: Entity,
+    ) -> impl Iterator<Item = (&'static str, &dyn Reflect)> + '_ {
+        self.entities
+            .get(&entity)
+            .into_iter()
+            .flat_map(|snapshot| snapshot.components.iter())
+            .map(|(type_path, component)| (*type_path, component.as_ref()))
+    }
+
+    /// The targets of the relations of `entity`, sorted.
+    pub fn relations(&self, entity: Entity) -> &[(RelationKind, Entity)] {
+        self.entities
+            .get(&entity)
+            .map(|snapshot| snapshot.relations.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// The changes that turn the state captured in `self` into the one captured in `to`.
+    pub fn diff(&self, to: &Snapshot) -> SnapshotDiff {
+        let despawned = self
+            .entities
+            .keys()
+            .filter(|entity| !to.entities.contains_key(entity))
+            .copied()
+            .collect::<Vec<_>>();
+
+        let mut changed = BTreeMap::new();
+        for (&entity, new) in to.entities.iter() {
+            let old = self.entities.get(&entity);
+            let old_components = old.map(|old| old.components.as_slice()).unwrap_or(&[]);
+            let old_relations = old.map(|old| old.relations.as_slice()).unwrap_or(&[]);
+
+            let mut diff = EntityDiff {
+                spawned: old.is_none(),
+                ..Default::default()
+            };
+            diff_components(old_components, &new.components, &mut diff);
+            diff.set = new
+                .relations
+                .iter()
+                .filter(|relation| !old_relations.contains(relation))
+                .copied()
+                .collect();
+            diff.unset = old_relations
+                .iter()
+                .filter(|relation| !new.relations.contains(relation))
+                .copied()
+                .collect();
+
+            if !diff.is_empty() {
+                changed.insert(entity, diff);
+            }
+        }
+
+        // When the last host of an entity goes away, aery unsets the relations of the entity
+        // itself too, so those are set again afterwards.
+        let mut left = despawned
+            .iter()
+            .flat_map(|entity| self.entities[entity].relations.iter())
+            .chain(changed.values().flat_map(|diff| diff.unset.iter()))
+            .map(|&(_, target)| target)
+            .collect::<Vec<_>>();
+        left.sort_unstable();
+        left.dedup();
+        for target in left {
+            let Some(new) = to.entities.get(&target) else {
+                continue;
+            };
+            let diff = changed.entry(target).or_default();
+            for &relation in new.relations.iter() {
+                if !diff.set.contains(&relation) {
+                    diff.set.push(relation);
+                }
+            }
+            if diff.is_empty() {
+                changed.remove(&target);
+            }
+        }
+
+        SnapshotDiff { despawned, changed }
+    }
+}
+
+fn diff_components(
+    old: &[(&'static str, Box<dyn Reflect>)],
+    new: &[(&'static str, Box<dyn Reflect>)],
+    diff: &mut EntityDiff,
+) {
+    let mut old = old.iter().peekable();
+    let mut new = new.iter().peekable();
+    loop {
+        let order = match (old.peek(), new.peek()) {
+            (None, None) => break,
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (Some((old_path, _)), Some((new_path, _))) => old_path.cmp(new_path),
+        };
+
+        match order {
+            Ordering::Less => {
+                let (type_path, _) = old.next().unwrap();
+                diff.removed.push(type_path);
+            }
+            Ordering::Greater => {
+                let (type_path, component) = new.next().unwrap();
+                diff.inserted.push((type_path, component.clone_value()));
+            }
+            Ordering::Equal => {
+                let (_, old_component) = old.next().unwrap();
+                let (type_path, component) = new.next().unwrap();
+                if !old_component
+                    .reflect_partial_eq(component.as_ref())
+                    .unwrap_or(false)
+                {
+                    diff.inserted.push((type_path, component.clone_value()));
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct EntityDiff {
+    spawned: bool,
+    /// Components that were added or changed, with their new value.
+    inserted: Components,
+    /// The type paths of removed components.
+    removed: Vec<&'static str>,
+    set: Vec<RelationTarget>,
+    unset: Vec<RelationTarget>,
+}
+
+impl EntityDiff {
+    fn is_empty(&self) -> bool {
+        !self.spawned
+            && self.inserted.is_empty()
+            && self.removed.is_empty()
+            && self.set.is_empty()
+            && self.unset.is_empty()
+    }
+}
+
+/// The changes between two [`Snapshot`]s of a circuit.
+#[derive(Debug, Default)]
+pub struct SnapshotDiff {
+    despawned: Vec<Entity>,
+    changed: BTreeMap<Entity, EntityDiff>,
+}
+
+/// Replaces the entities in `value` that are keys of `entity_map`.
+pub fn map_entities(value: &mut dyn Reflect, entity_map: &EntityHashMap<Entity>) {
+    match value.reflect_mut() {
+        ReflectMut::Struct(value) => {
+            for i in 0..value.field_len() {
+                map_entities(value.field_at_mut(i).unwrap(), entity_map);
+            }
+        }
+        ReflectMut::TupleStruct(value) => {
+            for i in 0..value.field_len() {
+                map_entities(value.field_mut(i).unwrap(), entity_map);
+            }
+        }
+        ReflectMut::Tuple(value) => {
+            for i in 0..value.field_len() {
+                map_entities(value.field_mut(i).unwrap(), entity_map);
+            }
+        }
+        ReflectMut::List(value) => {
+            for i in 0..value.len() {
+                map_entities(value.get_mut(i).unwrap(), entity_map);
+            }
+        }
+        ReflectMut::Array(value) => {
+            for i in 0..value.len() {
+                map_entities(value.get_mut(i).unwrap(), entity_map);
+            }
+        }
+        ReflectMut::Map(value) => {
+            for i in 0..value.len() {
+                map_entities(value.get_at_mut(i).unwrap().1, entity_map);
+            }
+        }
+        ReflectMut::Enum(value) => {
+            for i in 0..value.field_len() {
+                map_entities(value.field_at_mut(i).unwrap(), entity_map);
+            }
+        }
+        ReflectMut::Value(value) => {
+            if let Some(entity) = value.downcast_mut::<Entity>() {
+                if let Some(&mapped) = entity_map.get(entity) {
+                    *entity = mapped;
+                }
+            }
+        }
+    }
+}
+
+impl SnapshotDiff {
+    /// Whether both snapshots captured the same state.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.despawned.is_empty() && self.changed.is_empty()
+    }
+
+    /// The entities that were despawned, in a stable order.
+    #[inline]
+    pub fn despawned(&self) -> &[Entity] {
+        &self.despawned
+    }
+
+    /// The entities that were spawned or changed, in a stable order.
+    pub fn changed(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.changed.keys().copied()
+    }
+
+    /// Makes the changes to `world`.
+    ///
+    /// Spawned entities keep their ID where possible. The entities that had to be spawned under
+    /// a new ID, because theirs was taken in the meantime, are returned along with the new ID,
+    /// and references to them are updated in all components and relations set by the diff.
+    /// Spawned entities also get the derived components snapshots leave out.
+    pub fn apply(&self, world: &mut World) -> EntityHashMap<Entity> {
+        // Relations are unset before any entity is despawned, so that entities moved away from
+        // a despawned parent aren't despawned along with it.
+        for (&entity, diff) in self.changed.iter() {
+            if let Some(mut entity) = world.get_entity_mut(entity) {
+                for &(kind, target) in diff.unset.iter() {
+                    kind.unset(&mut entity, target);
+                }
+            }
+        }
+        for &entity in self.despawned.iter() {
+            if let Some(mut entity) = world.get_entity_mut(entity) {
+                for kind in RelationKind::ALL {
+                    kind.unset_all(&mut entity);
+                }
+            }
+        }
+        for &entity in self.despawned.iter() {
+            if world.get_entity(entity).is_some() {
+                world.despawn(entity);
+            }
+        }
+        world.flush();
+
+        let mut entity_map = EntityHashMap::default();
+        for (&entity, diff) in self.changed.iter() {
+            if diff.spawned && world.get_or_spawn(entity).is_none() {
+                entity_map.insert(entity, world.spawn_empty().id());
+            }
+        }
+        let mapped = |entity: Entity| entity_map.get(&entity).copied().unwrap_or(entity);
+
+        let registry = world.resource::<AppTypeRegistry>().clone();
+        let registry = registry.read();
+        for (&entity, diff) in self.changed.iter() {
+            let Some(mut entity) = world.get_entity_mut(mapped(entity)) else {
+                continue;
+            };
+
+            for (type_path, component) in diff.inserted.iter() {
+                let Some(reflect) = registry
+                    .get_with_type_path(type_path)
+                    .and_then(|registration| registration.data::<ReflectComponent>())
+                else {
+                    bevy_log::warn!("cannot restore unregistered component {type_path}");
+                    continue;
+                };
+
+                if entity_map.is_empty() {
+                    reflect.insert(&mut entity, component.as_ref(), &registry);
+                } else {
+                    let mut component = component.clone_value();
+                    map_entities(component.as_mut(), &entity_map);
+                    reflect.insert(&mut entity, component.as_ref(), &registry);
+                }
+            }
+
+            for type_path in diff.removed.iter() {
+                let reflect = registry
+                    .get_with_type_path(type_path)
+                    .and_then(|registration| registration.data::<ReflectComponent>());
+                if let Some(reflect) = reflect {
+                    reflect.remove(&mut entity);
+                }
+            }
+
+            if diff.spawned {
+                insert_derived_components(&mut entity);
+            }
+        }
+        // Relations are set once all components are in place, so that the observers of
+        // relations see what the observers of components added.
+        world.flush();
+
+        // Parents are set before their children, like when the circuit was built.
+        let mut pending = self.changed.iter().collect::<Vec<_>>();
+        while !pending.is_empty() {
+            let waiting = pending
+                .iter()
+                .map(|&(&entity, _)| entity)
+                .collect::<Vec<_>>();
+            let (ready, blocked) = pending.into_iter().partition::<Vec<_>, _>(|(_, diff)| {
+                !diff.set.iter().any(|&(kind, target)| {
+                    (kind == RelationKind::Child) && waiting.binary_search(&target).is_ok()
+                })
+            });
+            // cycles are set in any order
+            let (ready, blocked) = if ready.is_empty() {
+                (blocked, Vec::new())
+            } else {
+                (ready, blocked)
+            };
+
+            for (&entity, diff) in ready {
+                if let Some(mut entity) = world.get_entity_mut(mapped(entity)) {
+                    for &(kind, target) in diff.set.iter() {
+                        kind.set(&mut entity, mapped(target));
+                    }
+                }
+            }
+            world.flush();
+            pending = blocked;
+        }
+
+        entity_map
+    }
+
+    /// Serializes the diff through the reflection data in `registry`.
+    pub fn serializable<'a>(&'a self, registry: &'a TypeRegistry) -> impl Serialize + 'a {
+        let changed = self
+            .changed
+            .iter()
+            .map(|(&entity, diff)| {
+                let inserted = diff
+                    .inserted
+                    .iter()
+                    .map(|(_, component)| ReflectSerializer::new(component.as_ref(), registry))
+                    .collect::<Vec<_>>();
+                (
+                    entity,
+                    diff.spawned,
+                    inserted,
+                    &diff.removed,
+                    &diff.set,
+                    &diff.unset,
+                )
+            })
+            .collect::<Vec<_>>();
+        (&self.despawned, changed)
+    }
+}
+
+/// Deserializes a [`SnapshotDiff`] written by [`SnapshotDiff::serializable`].
+pub struct SnapshotDiffDeserializer<'a> {
+    registry: &'a TypeRegistry,
+}
+
+impl fmt::Debug for SnapshotDiffDeserializer<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SnapshotDiffDeserializer")
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'a> SnapshotDiffDeserializer<'a> {
+    #[inline]
+    pub fn new(registry: &'a TypeRegistry) -> Self {
+        Self { registry }
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for SnapshotDiffDeserializer<'_> {
+    type Value = SnapshotDiff;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_tuple(2, self)
+    }
+}
+
+impl<'de> Visitor<'de> for SnapshotDiffDeserializer<'_> {
+    type Value = SnapshotDiff;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a snapshot diff")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let despawned = seq
+            .next_element()?
+            .ok_or_else(|| A::Error::invalid_length(0, &self))?;
+        let changed = seq
+            .next_element_seed(ChangedDeserializer(self.registry))?
+            .ok_or_else(|| A::Error::invalid_length(1, &self))?;
+        Ok(SnapshotDiff { despawned, changed })
+    }
+}
+
+struct ChangedDeserializer<'a>(&'a TypeRegistry);
+
+impl<'de> DeserializeSeed<'de> for ChangedDeserializer<'_> {
+    type Value = BTreeMap<Entity, EntityDiff>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for ChangedDeserializer<'_> {
+    type Value = BTreeMap<Entity, EntityDiff>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a list of changed entities")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut changed = BTreeMap::new();
+        loop {
+            let element = seq.next_element_seed(EntityDiffDeserializer(self.0))?;
+            let Some((entity, diff)) = element else {
+                return Ok(changed);
+            };
+            changed.insert(entity, diff);
+        }
+    }
+}
+
+struct EntityDiffDeserializer<'a>(&'a TypeRegistry);
+
+impl<'de> DeserializeSeed<'de> for EntityDiffDeserializer<'_> {
+    type Value = (Entity, EntityDiff);
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_tuple(6, self)
+    }
+}
+
+impl<'de> Visitor<'de> for EntityDiffDeserializer<'_> {
+    type Value = (Entity, EntityDiff);
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a changed entity")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let registry = self.0;
+        let entity = seq
+            .next_element()?
+            .ok_or_else(|| A::Error::invalid_length(0, &self))?;
+        let spawned = seq
+            .next_element()?
+            .ok_or_else(|| A::Error::invalid_length(1, &self))?;
+        let inserted = seq
+            .next_element_seed(ComponentsDeserializer(registry))?
+            .ok_or_else(|| A::Error::invalid_length(2, &self))?;
+        let removed: Vec<String> = seq
+            .next_element()?
+            .ok_or_else(|| A::Error::invalid_length(3, &self))?;
+        let set = seq
+            .next_element()?
+            .ok_or_else(|| A::Error::invalid_length(4, &self))?;
+        let unset = seq
+            .next_element()?
+            .ok_or_else(|| A::Error::invalid_length(5, &self))?;
+
+        let removed = removed
+            .iter()
+            .map(|type_path| {
+                registry
+                    .get_with_type_path(type_path)
+                    .map(|registration| registration.type_info().type_path())
+                    .ok_or_else(|| A::Error::custom(format!("unknown component {type_path}")))
+            })
+            .collect::<Result<_, _>>()?;
+
+        let diff = EntityDiff {
+            spawned,
+            inserted,
+            removed,
+            set,
+            unset,
+        };
+        Ok((entity, diff))
+    }
+}
+
+struct ComponentsDeserializer<'a>(&'a TypeRegistry);
+
+impl<'de> DeserializeSeed<'de> for ComponentsDeserializer<'_> {
+    type Value = Components;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for ComponentsDeserializer<'_> {
+    type Value = Components;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a list of components")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut components = Vec::new();
+        loop {
+            let element = seq.next_element_seed(ReflectDeserializer::new(self.0))?;
+            let Some(component) = element else {
+                return Ok(components);
+            };
+            let type_path = component
+                .get_represented_type_info()
+                .ok_or_else(|| A::Error::custom("component without type information"))?
+                .type_path();
+            components.push((type_path, component));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::*;
+    use crate::stable_id::StableId;
+    use crate::transform::{BoundingBox, Direction, Directions, Transform};
+    use crate::visibility::Visibility;
+    use smallvec::smallvec;
+    use std::collections::BTreeSet;
+    use std::num::NonZeroU8;
+
+    fn app() -> bevy_app::App {
+        let mut app = bevy_app::App::new();
+        app.add_plugins((bevy_state::app::StatesPlugin, crate::CorePlugin));
+        app
+    }
+
+    /// Spawns a circuit with every component snapshots capture on at least one of its parts.
+    fn spawn_circuit(world: &mut World) -> Entity {
+        let bit_width = BitWidth(NonZeroU8::MIN);
+        let placed = (Transform::default(), BoundingBox::default());
+
+        let circuit = world.spawn((Circuit, Name("top".into()))).id();
+        let group = world.spawn((Group, Locked)).set::<Child>(circuit).id();
+        let symbol = world
+            .spawn((
+                Symbol,
+                SymbolKind::And,
+                DeMorgan,
+                Name("and".into()),
+                DesignatorPrefix("U".into()),
+                DesignatorNumber(1),
+                DesignatorSuffix("A".into()),
+                Shape::default(),
+                placed,
+                Attributes([("part".into(), "74HC08".into())].into()),
+                Parameters([("inputs".into(), ParamValue::Integer(2))].into()),
+                SubCircuit(circuit),
+            ))
+            .set::<Child>(circuit)
+            .set::<Grouped>(group)
+            .id();
+        let port = world
+            .spawn((
+                Port,
+                Name("a".into()),
+                bit_width,
+                PortDirection::Input,
+                PortSide::Left,
+                Directions::NEG_X,
+                Number(1),
+                placed,
+            ))
+            .set::<Child>(symbol)
+            .set::<InheritTransform>(symbol)
+            .id();
+        let net = world
+            .spawn((
+                Net,
+                Name("n".into()),
+                bit_width,
+                Visibility::Hidden,
+                Bits(smallvec![0]),
+            ))
+            .set::<Child>(circuit)
+            .id();
+        let endpoint = world
+            .spawn((
+                Endpoint,
+                PortID(port),
+                Direction::PosX,
+                Disconnected,
+                placed,
+            ))
+            .set::<Child>(net)
+            .set::<InheritVisibility>(net)
+            .id();
+        world
+            .spawn((
+                Waypoint,
+                Number(0),
+                EndpointID(endpoint),
+                NetID(net),
+                SymbolID(symbol),
+                CircuitID(circuit),
+                WaypointID(endpoint),
+                placed,
+            ))
+            .set::<Child>(endpoint);
+        world
+            .spawn((Annotation::default(), Transform::default()))
+            .set::<Child>(circuit);
+        world
+            .spawn((Graphic::default(), Transform::default()))
+            .set::<Child>(circuit);
+        world.flush();
+        circuit
+    }
+
+    fn find<T: Component>(world: &mut World) -> Entity {
+        world.query_filtered::<Entity, With<T>>().single(world)
+    }
+
+    /// Renames, removes, despawns, spawns and moves parts of the circuit.
+    fn edit_circuit(world: &mut World, circuit: Entity) {
+        let symbol = find::<Symbol>(world);
+        world.entity_mut(symbol).insert(Name("or".into()));
+        let group = find::<Group>(world);
+        world.entity_mut(symbol).unset::<Grouped>(group);
+        world.entity_mut(group).remove::<Locked>();
+        let graphic = find::<Graphic>(world);
+        world.despawn(graphic);
+        world.flush();
+        let annotation = find::<Annotation>(world);
+        world.entity_mut(annotation).set::<Child>(group);
+        world
+            .spawn((Annotation::default(), Transform::default()))
+            .set::<Child>(circuit);
+        world.flush();
+    }
+
+    #[test]
+    fn captures_every_component() {
+        let mut app = app();
+        let circuit = spawn_circuit(app.world_mut());
+        let snapshot = Snapshot::capture(app.world_mut(), circuit);
+
+        let registry = app.world().resource::<AppTypeRegistry>().read();
+        let registered = snapshot_components(&registry)
+            .into_iter()
+            .map(|(type_path, _)| type_path)
+            .filter(|type_path| type_path.starts_with("digilogic_core::"))
+            .collect::<BTreeSet<_>>();
+        let captured = snapshot
+            .entities
+            .values()
+            .flat_map(|snapshot| snapshot.components.iter())
+            .map(|&(type_path, _)| type_path)
+            .collect::<BTreeSet<_>>();
+        assert_eq!(captured, registered);
+
+        // every component compares equal to itself, or diffs would never be empty
+        assert!(snapshot.diff(&snapshot).is_empty());
+    }
+
+    #[test]
+    fn undo_and_redo() {
+        let mut app = app();
+        let world = app.world_mut();
+        let circuit = spawn_circuit(world);
+        let before = Snapshot::capture(world, circuit);
+        edit_circuit(world, circuit);
+        let after = Snapshot::capture(world, circuit);
+
+        let undo = after.diff(&before);
+        let redo = before.diff(&after);
+        assert_eq!(undo.despawned().len(), 1);
+        assert_eq!(redo.despawned().len(), 1);
+
+        assert!(undo.apply(world).is_empty());
+        assert!(before.diff(&Snapshot::capture(world, circuit)).is_empty());
+        assert_eq!(
+            Some(find::<Graphic>(world)),
+            redo.despawned().first().copied()
+        );
+
+        assert!(redo.apply(world).is_empty());
+        assert!(after.diff(&Snapshot::capture(world, circuit)).is_empty());
+    }
+
+    #[test]
+    fn respawns_under_new_id() {
+        let mut app = app();
+        let world = app.world_mut();
+        let circuit = spawn_circuit(world);
+        let before = Snapshot::capture(world, circuit);
+        let endpoint = find::<Endpoint>(world);
+        let waypoint = find::<Waypoint>(world);
+        world.despawn(endpoint);
+        world.flush();
+        let after = Snapshot::capture(world, circuit);
+
+        // takes the IDs of the despawned endpoint and waypoint
+        world.spawn_empty();
+        world.spawn_empty();
+
+        let entity_map = after.diff(&before).apply(world);
+        assert_eq!(entity_map.len(), 2);
+        let endpoint = entity_map[&endpoint];
+        assert_eq!(
+            world.get::<EndpointID>(entity_map[&waypoint]),
+            Some(&EndpointID(endpoint))
+        );
+        assert_eq!(find::<Endpoint>(world), endpoint,);
+        let restored = Snapshot::capture(world, circuit);
+        assert_eq!(restored.entities().count(), before.entities().count());
+        assert!(restored.entities().any(|entity| entity == endpoint));
+    }
+
+    #[test]
+    fn serializes_diffs() {
+        let mut app = app();
+        let world = app.world_mut();
+        let circuit = spawn_circuit(world);
+        let before = Snapshot::capture(world, circuit);
+        let stable_id = *world.get::<StableId>(circuit).unwrap();
+        edit_circuit(world, circuit);
+        world.entity_mut(circuit).insert(StableId::new());
+        let after = Snapshot::capture(world, circuit);
+
+        let registry = world.resource::<AppTypeRegistry>().clone();
+        let registry = registry.read();
+        let undo = ron::to_string(&after.diff(&before).serializable(&registry)).unwrap();
+        let mut deserializer = ron::Deserializer::from_str(&undo).unwrap();
+        let undo = SnapshotDiffDeserializer::new(&registry)
+            .deserialize(&mut deserializer)
+            .unwrap();
+        drop(registry);
+
+        undo.apply(world);
+        assert!(before.diff(&Snapshot::capture(world, circuit)).is_empty());
+        assert_eq!(world.get::<StableId>(circuit), Some(&stable_id));
+    }
+}
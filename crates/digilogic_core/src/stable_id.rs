@@ -0,0 +1,144 @@
+//! Identities of entities that outlive a session.
+//!
+//! Entity IDs are reused and change whenever a circuit is loaded again, so anything referring to
+//! parts of a circuit from outside of it, like waveform configurations or scripts, uses the
+//! [`StableId`] of the entity instead. Circuits, symbols, nets, annotations and graphics get one
+//! when they are spawned, unless they are spawned with the ID they were saved with.
+
+use crate::components::*;
+use crate::HashMap;
+use bevy_ecs::prelude::*;
+use bevy_reflect::Reflect;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use uuid::Uuid;
+
+/// A random ID assigned to an entity once, and saved and loaded along with it.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+    Component,
+    Reflect,
+)]
+#[reflect(Component)]
+#[serde(transparent)]
+pub struct StableId(pub Uuid);
+
+impl StableId {
+    /// Creates a new random ID.
+    pub fn new() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+impl Default for StableId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for StableId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// The entity currently holding each [`StableId`].
+#[derive(Debug, Default, Resource)]
+pub struct StableIds {
+    entities: HashMap<StableId, Entity>,
+}
+
+impl StableIds {
+    /// The entity holding `id`, if it is loaded.
+    pub fn get(&self, id: StableId) -> Option<Entity> {
+        self.entities.get(&id).copied()
+    }
+}
+
+fn assign_stable_id<T: Component>(trigger: Trigger<OnAdd, T>, mut commands: Commands) {
+    let entity = trigger.entity();
+    // Checked once the command runs, so an ID inserted right after the marker, like when
+    // components are restored one by one, is kept.
+    commands.add(move |world: &mut World| {
+        if let Some(mut entity) = world.get_entity_mut(entity) {
+            if !entity.contains::<StableId>() {
+                entity.insert(StableId::new());
+            }
+        }
+    });
+}
+
+fn index_stable_id(
+    trigger: Trigger<OnInsert, StableId>,
+    ids: Query<&StableId>,
+    mut stable_ids: ResMut<StableIds>,
+) {
+    let entity = trigger.entity();
+    if let Ok(&id) = ids.get(entity) {
+        stable_ids.entities.insert(id, entity);
+    }
+}
+
+fn unindex_stable_id(
+    trigger: Trigger<OnRemove, StableId>,
+    ids: Query<&StableId>,
+    mut stable_ids: ResMut<StableIds>,
+) {
+    let entity = trigger.entity();
+    if let Ok(id) = ids.get(entity) {
+        if stable_ids.entities.get(id) == Some(&entity) {
+            stable_ids.entities.remove(id);
+        }
+    }
+}
+
+pub(crate) fn register(app: &mut bevy_app::App) {
+    app.register_type::<StableId>()
+        .init_resource::<StableIds>()
+        .observe(assign_stable_id::<Circuit>)
+        .observe(assign_stable_id::<Symbol>)
+        .observe(assign_stable_id::<Net>)
+        .observe(assign_stable_id::<Annotation>)
+        .observe(assign_stable_id::<Graphic>)
+        .observe(index_stable_id)
+        .observe(unindex_stable_id);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn assign_and_look_up() {
+        let mut app = bevy_app::App::new();
+        register(&mut app);
+
+        let saved = StableId::new();
+        let symbol = app.world_mut().spawn(Symbol).id();
+        let loaded = app.world_mut().spawn((Symbol, saved)).id();
+        let port = app.world_mut().spawn(Port).id();
+        app.update();
+
+        let world = app.world();
+        let assigned = *world.get::<StableId>(symbol).unwrap();
+        assert_ne!(assigned, saved);
+        assert_eq!(world.get::<StableId>(loaded), Some(&saved));
+        assert_eq!(world.get::<StableId>(port), None);
+
+        let stable_ids = world.resource::<StableIds>();
+        assert_eq!(stable_ids.get(assigned), Some(symbol));
+        assert_eq!(stable_ids.get(saved), Some(loaded));
+
+        app.world_mut().despawn(loaded);
+        assert_eq!(app.world().resource::<StableIds>().get(saved), None);
+    }
+}
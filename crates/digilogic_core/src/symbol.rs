@@ -6,15 +6,122 @@ use crate::{fixed, Fixed, SharedStr};
 use aery::prelude::*;
 use bevy_ecs::prelude::*;
 use smallvec::SmallVec;
+use std::borrow::Cow;
+use std::fmt;
 use std::num::NonZeroU8;
+use std::sync::Arc;
 
-#[derive(Debug, Clone)]
-struct PortDef {
+/// A port of a kind of symbol, relative to the position of the symbol.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PortDef {
     name: SharedStr,
     position: Vec2,
-    input: bool,
-    output: bool,
-    directions: Directions,
+    direction: PortDirection,
+    side: PortSide,
+    /// The width of the port if it differs from the one the symbol is built with.
+    bit_width: Option<BitWidth>,
+}
+
+impl PortDef {
+    pub fn new(
+        name: impl Into<SharedStr>,
+        position: Vec2,
+        direction: PortDirection,
+        side: PortSide,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            position,
+            direction,
+            side,
+            bit_width: None,
+        }
+    }
+
+    pub fn input(name: impl Into<SharedStr>, position: Vec2, side: PortSide) -> Self {
+        Self::new(name, position, PortDirection::Input, side)
+    }
+
+    pub fn output(name: impl Into<SharedStr>, position: Vec2, side: PortSide) -> Self {
+        Self::new(name, position, PortDirection::Output, side)
+    }
+
+    /// Fixes the width of the port instead of using the one the symbol is built with.
+    pub fn with_bit_width(mut self, bit_width: BitWidth) -> Self {
+        self.bit_width = Some(bit_width);
+        self
+    }
+}
+
+/// The ports of a kind of symbol.
+#[derive(Debug, Clone)]
+enum PortLayout {
+    Fixed(&'static [PortDef]),
+    /// The inputs and output of a gate, generated for the number of inputs
+    /// set by the `inputs` parameter.
+    Gate,
+    /// Ports whose width is set by the `width` parameter, unless it is fixed like for carries.
+    Arithmetic(&'static [PortDef]),
+    /// The data, select and output ports of a multiplexer or demultiplexer,
+    /// generated for the number of select inputs set by the `select_bits` parameter.
+    Mux {
+        demux: bool,
+    },
+    /// The bus and tap ports of a splitter, generated for the taps set by the `taps` parameter.
+    Splitter,
+    /// The ports of a kind registered at runtime.
+    Registered(Arc<[PortDef]>),
+}
+
+/// The type and range of a [`ParamDef`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamType {
+    Integer { min: u32, max: u32 },
+    Text,
+}
+
+/// A parameter declared by a kind of symbol.
+#[derive(Debug, Clone)]
+pub struct ParamDef {
+    name: SharedStr,
+    label: &'static str,
+    ty: ParamType,
+    default: ParamValue,
+}
+
+impl ParamDef {
+    /// The key the value is stored under in [`Parameters`].
+    #[inline]
+    pub fn name(&self) -> &SharedStr {
+        &self.name
+    }
+
+    #[inline]
+    pub fn label(&self) -> &'static str {
+        self.label
+    }
+
+    #[inline]
+    pub fn ty(&self) -> ParamType {
+        self.ty
+    }
+
+    #[inline]
+    pub fn default_value(&self) -> &ParamValue {
+        &self.default
+    }
+
+    /// Fits `value` to the type of the parameter, or `None` if it has the wrong type.
+    /// Integers outside of the range are clamped.
+    pub fn validate(&self, value: &ParamValue) -> Option<ParamValue> {
+        match (self.ty, value) {
+            (ParamType::Integer { min, max }, &ParamValue::Integer(value)) => {
+                Some(ParamValue::Integer(value.clamp(min, max)))
+            }
+            (ParamType::Text, ParamValue::Text(value)) => Some(ParamValue::Text(value.clone())),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -22,44 +129,432 @@ pub struct SymbolDef {
     kind: SymbolKind,
     name: SharedStr,
     designator_prefix: SharedStr,
-    ports: &'static [PortDef],
+    ports: PortLayout,
+    params: &'static [ParamDef],
     bounding_box: BoundingBox,
     shape: Shape,
 }
 
+impl SymbolDef {
+    #[inline]
+    pub fn kind(&self) -> SymbolKind {
+        self.kind
+    }
+
+    #[inline]
+    pub fn name(&self) -> &SharedStr {
+        &self.name
+    }
+
+    #[inline]
+    pub fn designator_prefix(&self) -> &SharedStr {
+        &self.designator_prefix
+    }
+
+    #[inline]
+    pub fn shape(&self) -> Shape {
+        self.shape
+    }
+
+    /// The bounding box of symbols of this kind with the default parameters.
+    #[inline]
+    pub fn bounding_box(&self) -> BoundingBox {
+        self.bounding_box
+    }
+
+    #[inline]
+    pub fn params(&self) -> &[ParamDef] {
+        self.params
+    }
+
+    /// The parameters of the kind, taken from `values` where they are valid
+    /// and set to their defaults otherwise.
+    pub fn resolve_parameters(&self, values: &Parameters) -> Parameters {
+        Parameters(
+            self.params
+                .iter()
+                .map(|param| {
+                    let value = values
+                        .get(&param.name)
+                        .and_then(|value| param.validate(value))
+                        .unwrap_or_else(|| param.default.clone());
+                    (param.name.clone(), value)
+                })
+                .collect(),
+        )
+    }
+
+    /// The bounding box of symbols of this kind with `parameters`,
+    /// which differs from [`bounding_box`](Self::bounding_box) if they stretch the outline.
+    pub fn bounding_box_for(&self, parameters: &Parameters) -> BoundingBox {
+        match self.ports {
+            PortLayout::Gate => gate_bounding_box(gate_input_count(parameters)),
+            PortLayout::Mux { .. } => mux_bounding_box(mux_select_bits(parameters)),
+            PortLayout::Splitter => splitter_bounding_box(splitter_taps(parameters).len()),
+            PortLayout::Fixed(_) | PortLayout::Arithmetic(_) | PortLayout::Registered(_) => {
+                self.bounding_box
+            }
+        }
+    }
+
+    /// Whether symbols of this kind with parameters `a` and `b` have different ports.
+    pub fn ports_differ(&self, a: &Parameters, b: &Parameters) -> bool {
+        self.port_defs(a) != self.port_defs(b)
+    }
+
+    fn port_defs(&self, parameters: &Parameters) -> Cow<'_, [PortDef]> {
+        match self.ports {
+            PortLayout::Fixed(ports) => Cow::Borrowed(ports),
+            PortLayout::Registered(ref ports) => Cow::Borrowed(ports),
+            PortLayout::Arithmetic(ports) => {
+                let width = arithmetic_width(parameters);
+                Cow::Owned(
+                    ports
+                        .iter()
+                        .map(|port| PortDef {
+                            bit_width: port.bit_width.or(Some(width)),
+                            ..port.clone()
+                        })
+                        .collect(),
+                )
+            }
+            PortLayout::Gate => Cow::Owned(gate_ports(gate_input_count(parameters))),
+            PortLayout::Mux { demux } => Cow::Owned(mux_ports(mux_select_bits(parameters), demux)),
+            PortLayout::Splitter => Cow::Owned(splitter_ports(
+                arithmetic_width(parameters),
+                &splitter_taps(parameters),
+            )),
+        }
+    }
+}
+
 const PORT_HALF_WIDTH: Fixed = fixed!(4);
 
-const GATE_PORTS_2_INPUT: &[PortDef] = &[
-    PortDef {
-        name: SharedStr::new_static("A"),
-        position: Vec2 {
+/// The name of the parameter setting the number of inputs of a gate.
+pub const INPUTS_PARAM: &str = "inputs";
+
+const MIN_GATE_INPUTS: u32 = 2;
+const MAX_GATE_INPUTS: u32 = 64;
+
+/// Gates with up to this many inputs keep the outline of the 2 input gate.
+const MAX_COMPACT_GATE_INPUTS: u32 = 5;
+
+/// The spacing of the inputs of gates that are stretched beyond the compact outline.
+const GATE_INPUT_SPACING: Fixed = fixed!(20);
+
+const GATE_PARAMS: &[ParamDef] = &[ParamDef {
+    name: SharedStr::new_static(INPUTS_PARAM),
+    label: "Inputs",
+    ty: ParamType::Integer {
+        min: MIN_GATE_INPUTS,
+        max: MAX_GATE_INPUTS,
+    },
+    default: ParamValue::Integer(MIN_GATE_INPUTS),
+}];
+
+// the inputs of compact gates stay within the outline of the 2 input gate, on the grid
+const COMPACT_GATE_INPUTS: [&[Fixed]; 4] = [
+    &[fixed!(0), fixed!(40)],
+    &[fixed!(0), fixed!(20), fixed!(40)],
+    &[fixed!(0), fixed!(10), fixed!(30), fixed!(40)],
+    &[fixed!(0), fixed!(10), fixed!(20), fixed!(30), fixed!(40)],
+];
+const_assert_eq!(
+    (MAX_COMPACT_GATE_INPUTS - MIN_GATE_INPUTS + 1) as usize,
+    COMPACT_GATE_INPUTS.len()
+);
+
+/// The width of a gate outline, and its height when it is compact.
+const GATE_WIDTH: Fixed = fixed!(80);
+const COMPACT_GATE_HEIGHT: Fixed = fixed!(60);
+
+/// The number of inputs set by `parameters`, within the supported range.
+fn gate_input_count(parameters: &Parameters) -> u32 {
+    parameters
+        .integer(INPUTS_PARAM)
+        .unwrap_or(MIN_GATE_INPUTS)
+        .clamp(MIN_GATE_INPUTS, MAX_GATE_INPUTS)
+}
+
+/// The distance between the first and last input of a gate.
+fn gate_input_span(inputs: u32) -> Fixed {
+    if inputs <= MAX_COMPACT_GATE_INPUTS {
+        fixed!(40)
+    } else {
+        GATE_INPUT_SPACING * Fixed::from_u16((inputs - 1) as u16)
+    }
+}
+
+/// Inputs are named like spreadsheet columns: A to Z, then AA, AB and so on.
+fn gate_input_name(index: u32) -> SharedStr {
+    let mut name = Vec::new();
+    let mut index = index + 1;
+    while index > 0 {
+        index -= 1;
+        name.push(b'A' + (index % 26) as u8);
+        index /= 26;
+    }
+    name.reverse();
+    String::from_utf8(name).unwrap().into()
+}
+
+fn gate_ports(inputs: u32) -> Vec<PortDef> {
+    let input_y = |index: u32| match COMPACT_GATE_INPUTS.get((inputs - MIN_GATE_INPUTS) as usize) {
+        Some(compact) => compact[index as usize],
+        None => GATE_INPUT_SPACING * Fixed::from_u16(index as u16),
+    };
+
+    (0..inputs)
+        .map(|index| {
+            PortDef::input(
+                gate_input_name(index),
+                Vec2 {
+                    x: fixed!(0),
+                    y: input_y(index),
+                },
+                PortSide::Left,
+            )
+        })
+        .chain(std::iter::once(PortDef::output(
+            SharedStr::new_static("Y"),
+            Vec2 {
+                x: GATE_WIDTH,
+                y: gate_input_span(inputs) / fixed!(2),
+            },
+            PortSide::Right,
+        )))
+        .collect()
+}
+
+/// The outline of a gate grows downwards with its inputs, starting from the compact one.
+fn gate_bounding_box(inputs: u32) -> BoundingBox {
+    let height = COMPACT_GATE_HEIGHT + gate_input_span(inputs) - fixed!(40);
+    BoundingBox::from_top_left_size(
+        Vec2 {
             x: fixed!(0),
-            y: fixed!(0),
+            y: fixed!(-10),
         },
-        input: true,
-        output: false,
-        directions: Directions::NEG_X,
+        GATE_WIDTH,
+        height,
+    )
+}
+
+/// The name of the parameter setting the number of select inputs of a multiplexer,
+/// which have twice as many data ports.
+pub const SELECT_BITS_PARAM: &str = "select_bits";
+
+/// The select inputs are named by this prefix followed by their index, starting from the lowest bit.
+pub const SELECT_PORT_PREFIX: &str = "S";
+
+const MIN_SELECT_BITS: u32 = 1;
+const MAX_SELECT_BITS: u32 = 6;
+
+const MUX_PARAMS: &[ParamDef] = &[ParamDef {
+    name: SharedStr::new_static(SELECT_BITS_PARAM),
+    label: "Select bits",
+    ty: ParamType::Integer {
+        min: MIN_SELECT_BITS,
+        max: MAX_SELECT_BITS,
     },
-    PortDef {
-        name: SharedStr::new_static("B"),
-        position: Vec2 {
+    default: ParamValue::Integer(MIN_SELECT_BITS),
+}];
+
+/// The number of select inputs set by `parameters`, within the supported range.
+fn mux_select_bits(parameters: &Parameters) -> u32 {
+    parameters
+        .integer(SELECT_BITS_PARAM)
+        .unwrap_or(MIN_SELECT_BITS)
+        .clamp(MIN_SELECT_BITS, MAX_SELECT_BITS)
+}
+
+/// The position along the wide edge of a multiplexer of data port `index`.
+/// Like a gate, two ports are spread further apart than more ports.
+fn mux_data_y(select_bits: u32, index: u32) -> Fixed {
+    let spacing = if select_bits == 1 {
+        fixed!(40)
+    } else {
+        GATE_INPUT_SPACING
+    };
+    spacing * Fixed::from_u16(index as u16)
+}
+
+/// The outline of a multiplexer grows downwards with its data ports,
+/// and to the right to fit its select inputs on the bottom edge.
+/// Its edges are sloped so the narrow side is half the width shorter than the wide one.
+fn mux_bounding_box(select_bits: u32) -> BoundingBox {
+    let span = mux_data_y(select_bits, (1 << select_bits) - 1);
+    BoundingBox::from_top_left_size(
+        Vec2 {
             x: fixed!(0),
-            y: fixed!(40),
+            y: fixed!(-10),
         },
-        input: true,
-        output: false,
-        directions: Directions::NEG_X,
+        GATE_INPUT_SPACING * Fixed::from_u16(select_bits as u16 + 1),
+        span + fixed!(20),
+    )
+}
+
+/// A multiplexer has its data inputs on the left, a demultiplexer its data outputs on the right.
+/// The select inputs sit on the sloped bottom edge, spaced like the data ports.
+fn mux_ports(select_bits: u32, demux: bool) -> Vec<PortDef> {
+    let count = 1 << select_bits;
+    let bounding_box = mux_bounding_box(select_bits);
+    let width = bounding_box.width();
+    let span = mux_data_y(select_bits, count - 1);
+
+    let data = (0..count).map(|index| {
+        let y = mux_data_y(select_bits, index);
+        if demux {
+            PortDef::output(format!("Y{index}"), Vec2 { x: width, y }, PortSide::Right)
+        } else {
+            PortDef::input(
+                format!("D{index}"),
+                Vec2 { x: fixed!(0), y },
+                PortSide::Left,
+            )
+        }
+    });
+
+    let select = (0..select_bits).map(|index| {
+        let x = GATE_INPUT_SPACING * Fixed::from_u16(index as u16 + 1);
+        // the bottom edge rises by half of the distance towards the narrow side
+        let y = if demux {
+            bounding_box.max().y - width / fixed!(2) + x / fixed!(2)
+        } else {
+            bounding_box.max().y - x / fixed!(2)
+        };
+        PortDef::input(
+            format!("{SELECT_PORT_PREFIX}{index}"),
+            Vec2 { x, y },
+            PortSide::Bottom,
+        )
+    });
+
+    let middle = span / fixed!(2);
+    let other = if demux {
+        PortDef::input(
+            "D",
+            Vec2 {
+                x: fixed!(0),
+                y: middle,
+            },
+            PortSide::Left,
+        )
+    } else {
+        PortDef::output(
+            "Y",
+            Vec2 {
+                x: width,
+                y: middle,
+            },
+            PortSide::Right,
+        )
+    };
+
+    if demux {
+        std::iter::once(other).chain(select).chain(data).collect()
+    } else {
+        data.chain(select).chain(std::iter::once(other)).collect()
+    }
+}
+
+/// The name of the parameter setting the width of the operands of arithmetic symbols,
+/// and of the data of registers, counters and shift registers.
+pub const WIDTH_PARAM: &str = "width";
+
+const MAX_ARITHMETIC_WIDTH: u32 = 64;
+
+const WIDTH_PARAM_DEF: ParamDef = ParamDef {
+    name: SharedStr::new_static(WIDTH_PARAM),
+    label: "Width",
+    ty: ParamType::Integer {
+        min: 1,
+        max: MAX_ARITHMETIC_WIDTH,
     },
+    default: ParamValue::Integer(1),
+};
+
+const ARITHMETIC_PARAMS: &[ParamDef] = &[WIDTH_PARAM_DEF];
+
+/// The width of the operands set by `parameters`, within the supported range.
+fn arithmetic_width(parameters: &Parameters) -> BitWidth {
+    let width = parameters
+        .integer(WIDTH_PARAM)
+        .unwrap_or(1)
+        .clamp(1, MAX_ARITHMETIC_WIDTH);
+    BitWidth(NonZeroU8::new(width as u8).unwrap())
+}
+
+/// A port of an arithmetic symbol, inputs on the left and outputs on the right of its outline.
+/// Operands have the width set by the `width` parameter, flags like carries are a single bit.
+const fn arithmetic_port(name: &'static str, y: Fixed, output: bool, flag: bool) -> PortDef {
     PortDef {
-        name: SharedStr::new_static("Y"),
+        name: SharedStr::new_static(name),
         position: Vec2 {
-            x: fixed!(80),
-            y: fixed!(20),
+            x: if output { fixed!(60) } else { fixed!(0) },
+            y,
         },
-        input: false,
-        output: true,
-        directions: Directions::POS_X,
+        direction: if output {
+            PortDirection::Output
+        } else {
+            PortDirection::Input
+        },
+        side: if output {
+            PortSide::Right
+        } else {
+            PortSide::Left
+        },
+        bit_width: if flag {
+            Some(BitWidth(NonZeroU8::MIN))
+        } else {
+            None
+        },
+    }
+}
+
+const ARITHMETIC_BOUNDING_BOX: BoundingBox = BoundingBox::from_top_left_size(
+    Vec2 {
+        x: fixed!(0),
+        y: fixed!(-10),
     },
+    fixed!(60),
+    fixed!(60),
+);
+
+// the carry ports of adders, which are optional in simulation
+pub const CARRY_IN_PORT: &str = "CI";
+pub const CARRY_OUT_PORT: &str = "CO";
+
+// the borrow ports of subtractors, which are optional in simulation
+pub const BORROW_IN_PORT: &str = "BI";
+pub const BORROW_OUT_PORT: &str = "BO";
+
+// the outputs of comparators, of which any may be left unconnected
+pub const LESS_PORT: &str = "LT";
+pub const EQUAL_PORT: &str = "EQ";
+pub const GREATER_PORT: &str = "GT";
+
+const ADDER_PORTS: &[PortDef] = &[
+    arithmetic_port("A", fixed!(0), false, false),
+    arithmetic_port("B", fixed!(20), false, false),
+    arithmetic_port(CARRY_IN_PORT, fixed!(40), false, true),
+    arithmetic_port("S", fixed!(0), true, false),
+    arithmetic_port(CARRY_OUT_PORT, fixed!(40), true, true),
+];
+
+const SUBTRACTOR_PORTS: &[PortDef] = &[
+    arithmetic_port("A", fixed!(0), false, false),
+    arithmetic_port("B", fixed!(20), false, false),
+    arithmetic_port(BORROW_IN_PORT, fixed!(40), false, true),
+    arithmetic_port("D", fixed!(0), true, false),
+    arithmetic_port(BORROW_OUT_PORT, fixed!(40), true, true),
+];
+
+const COMPARATOR_PORTS: &[PortDef] = &[
+    arithmetic_port("A", fixed!(0), false, false),
+    arithmetic_port("B", fixed!(20), false, false),
+    arithmetic_port(LESS_PORT, fixed!(0), true, true),
+    arithmetic_port(EQUAL_PORT, fixed!(20), true, true),
+    arithmetic_port(GREATER_PORT, fixed!(40), true, true),
 ];
 
 const GATE_PORTS_1_INPUT: &[PortDef] = &[
@@ -69,9 +564,9 @@ const GATE_PORTS_1_INPUT: &[PortDef] = &[
             x: fixed!(0),
             y: fixed!(0),
         },
-        input: true,
-        output: false,
-        directions: Directions::NEG_X,
+        direction: PortDirection::Input,
+        side: PortSide::Left,
+        bit_width: None,
     },
     PortDef {
         name: SharedStr::new_static("Y"),
@@ -79,10 +574,206 @@ const GATE_PORTS_1_INPUT: &[PortDef] = &[
             x: fixed!(40),
             y: fixed!(0),
         },
-        input: false,
-        output: true,
-        directions: Directions::POS_X,
+        direction: PortDirection::Output,
+        side: PortSide::Right,
+        bit_width: None,
+    },
+];
+
+/// The name of the clock input of a flip-flop, whose rising edge updates the output.
+pub const CLOCK_PORT: &str = "CLK";
+/// The name of the reset input of a flip-flop, which clears the output on the next clock edge.
+pub const RESET_PORT: &str = "R";
+/// The name of the output of a flip-flop.
+pub const Q_PORT: &str = "Q";
+/// The name of the inverted output of a flip-flop.
+pub const Q_INVERTED_PORT: &str = "QN";
+
+/// The outline shared by all flip-flops, with the data inputs at the top left,
+/// the clock at the bottom left and the reset in the middle of the bottom edge.
+const FLIP_FLOP_BOUNDING_BOX: BoundingBox = BoundingBox::from_top_left_size(
+    Vec2 {
+        x: fixed!(0),
+        y: fixed!(-20),
+    },
+    fixed!(80),
+    fixed!(80),
+);
+
+const fn flip_flop_input(name: &'static str, y: Fixed) -> PortDef {
+    PortDef {
+        name: SharedStr::new_static(name),
+        position: Vec2 { x: fixed!(0), y },
+        direction: PortDirection::Input,
+        side: PortSide::Left,
+        bit_width: None,
+    }
+}
+
+// the clock, reset and outputs every flip-flop has besides its data inputs
+const FLIP_FLOP_CLOCK: PortDef = flip_flop_input(CLOCK_PORT, fixed!(40));
+const FLIP_FLOP_RESET: PortDef = PortDef {
+    name: SharedStr::new_static(RESET_PORT),
+    position: Vec2 {
+        x: fixed!(40),
+        y: fixed!(60),
+    },
+    direction: PortDirection::Input,
+    side: PortSide::Bottom,
+    bit_width: None,
+};
+const FLIP_FLOP_Q: PortDef = PortDef {
+    name: SharedStr::new_static(Q_PORT),
+    position: Vec2 {
+        x: fixed!(80),
+        y: fixed!(0),
+    },
+    direction: PortDirection::Output,
+    side: PortSide::Right,
+    bit_width: None,
+};
+const FLIP_FLOP_Q_INVERTED: PortDef = PortDef {
+    name: SharedStr::new_static(Q_INVERTED_PORT),
+    position: Vec2 {
+        x: fixed!(80),
+        y: fixed!(40),
+    },
+    direction: PortDirection::Output,
+    side: PortSide::Right,
+    bit_width: None,
+};
+
+const D_FLIP_FLOP_PORTS: &[PortDef] = &[
+    flip_flop_input("D", fixed!(0)),
+    FLIP_FLOP_CLOCK,
+    FLIP_FLOP_RESET,
+    FLIP_FLOP_Q,
+    FLIP_FLOP_Q_INVERTED,
+];
+
+const JK_FLIP_FLOP_PORTS: &[PortDef] = &[
+    flip_flop_input("J", fixed!(0)),
+    flip_flop_input("K", fixed!(20)),
+    FLIP_FLOP_CLOCK,
+    FLIP_FLOP_RESET,
+    FLIP_FLOP_Q,
+    FLIP_FLOP_Q_INVERTED,
+];
+
+const T_FLIP_FLOP_PORTS: &[PortDef] = &[
+    flip_flop_input("T", fixed!(0)),
+    FLIP_FLOP_CLOCK,
+    FLIP_FLOP_RESET,
+    FLIP_FLOP_Q,
+    FLIP_FLOP_Q_INVERTED,
+];
+
+/// The name of the enable input of registers and counters, which only change while it is high.
+pub const ENABLE_PORT: &str = "EN";
+/// The name of the input that makes counters load their data input instead of counting.
+pub const LOAD_PORT: &str = "LD";
+/// The name of the input that makes counters count up while it is high and down while it is low.
+pub const UP_PORT: &str = "UD";
+
+/// The name of the serial input of shift registers.
+pub const SERIAL_IN_PORT: &str = "SI";
+/// The name of the serial output of shift registers.
+pub const SERIAL_OUT_PORT: &str = "SO";
+/// The name of the input that makes shift registers shift towards the higher bits while it is high.
+pub const LEFT_PORT: &str = "L";
+
+/// Makes a port of a symbol with the [`PortLayout::Arithmetic`] layout a single bit wide.
+const fn single_bit(mut port: PortDef) -> PortDef {
+    port.bit_width = Some(BitWidth(NonZeroU8::MIN));
+    port
+}
+
+/// Registers have the outline of flip-flops, with the enable in place of a second data input.
+const REGISTER_PORTS: &[PortDef] = &[
+    flip_flop_input("D", fixed!(0)),
+    single_bit(flip_flop_input(ENABLE_PORT, fixed!(20))),
+    single_bit(FLIP_FLOP_CLOCK),
+    single_bit(FLIP_FLOP_RESET),
+    FLIP_FLOP_Q,
+];
+
+/// Counters have the outline of flip-flops stretched by two more inputs on the left.
+const COUNTER_BOUNDING_BOX: BoundingBox = BoundingBox::from_top_left_size(
+    Vec2 {
+        x: fixed!(0),
+        y: fixed!(-20),
+    },
+    fixed!(80),
+    fixed!(120),
+);
+
+const COUNTER_PORTS: &[PortDef] = &[
+    flip_flop_input("D", fixed!(0)),
+    single_bit(flip_flop_input(LOAD_PORT, fixed!(20))),
+    single_bit(flip_flop_input(ENABLE_PORT, fixed!(40))),
+    single_bit(flip_flop_input(UP_PORT, fixed!(60))),
+    single_bit(flip_flop_input(CLOCK_PORT, fixed!(80))),
+    single_bit(PortDef {
+        name: SharedStr::new_static(RESET_PORT),
+        position: Vec2 {
+            x: fixed!(40),
+            y: fixed!(100),
+        },
+        direction: PortDirection::Input,
+        side: PortSide::Bottom,
+        bit_width: None,
+    }),
+    FLIP_FLOP_Q,
+    single_bit(PortDef {
+        name: SharedStr::new_static(CARRY_OUT_PORT),
+        position: Vec2 {
+            x: fixed!(80),
+            y: fixed!(20),
+        },
+        direction: PortDirection::Output,
+        side: PortSide::Right,
+        bit_width: None,
+    }),
+];
+
+/// Shift registers have the outline of counters stretched by one more input.
+const SHIFT_REGISTER_BOUNDING_BOX: BoundingBox = BoundingBox::from_top_left_size(
+    Vec2 {
+        x: fixed!(0),
+        y: fixed!(-20),
     },
+    fixed!(80),
+    fixed!(140),
+);
+
+const SHIFT_REGISTER_PORTS: &[PortDef] = &[
+    single_bit(flip_flop_input(SERIAL_IN_PORT, fixed!(0))),
+    flip_flop_input("D", fixed!(20)),
+    single_bit(flip_flop_input(LOAD_PORT, fixed!(40))),
+    single_bit(flip_flop_input(ENABLE_PORT, fixed!(60))),
+    single_bit(flip_flop_input(LEFT_PORT, fixed!(80))),
+    single_bit(flip_flop_input(CLOCK_PORT, fixed!(100))),
+    single_bit(PortDef {
+        name: SharedStr::new_static(RESET_PORT),
+        position: Vec2 {
+            x: fixed!(40),
+            y: fixed!(120),
+        },
+        direction: PortDirection::Input,
+        side: PortSide::Bottom,
+        bit_width: None,
+    }),
+    FLIP_FLOP_Q,
+    single_bit(PortDef {
+        name: SharedStr::new_static(SERIAL_OUT_PORT),
+        position: Vec2 {
+            x: fixed!(80),
+            y: fixed!(20),
+        },
+        direction: PortDirection::Output,
+        side: PortSide::Right,
+        bit_width: None,
+    }),
 ];
 
 const KINDS: &[SymbolDef] = &[
@@ -99,7 +790,8 @@ const KINDS: &[SymbolDef] = &[
             fixed!(60),
         ),
         shape: Shape::And,
-        ports: GATE_PORTS_2_INPUT,
+        ports: PortLayout::Gate,
+        params: GATE_PARAMS,
     },
     SymbolDef {
         kind: SymbolKind::Or,
@@ -114,7 +806,8 @@ const KINDS: &[SymbolDef] = &[
             fixed!(60),
         ),
         shape: Shape::Or,
-        ports: GATE_PORTS_2_INPUT,
+        ports: PortLayout::Gate,
+        params: GATE_PARAMS,
     },
     SymbolDef {
         kind: SymbolKind::Xor,
@@ -129,7 +822,8 @@ const KINDS: &[SymbolDef] = &[
             fixed!(60),
         ),
         shape: Shape::Xor,
-        ports: GATE_PORTS_2_INPUT,
+        ports: PortLayout::Gate,
+        params: GATE_PARAMS,
     },
     SymbolDef {
         kind: SymbolKind::Not,
@@ -144,7 +838,8 @@ const KINDS: &[SymbolDef] = &[
             fixed!(20),
         ),
         shape: Shape::Not,
-        ports: GATE_PORTS_1_INPUT,
+        ports: PortLayout::Fixed(GATE_PORTS_1_INPUT),
+        params: &[],
     },
     SymbolDef {
         kind: SymbolKind::In,
@@ -159,16 +854,17 @@ const KINDS: &[SymbolDef] = &[
             fixed!(40),
         ),
         shape: Shape::Input,
-        ports: &[PortDef {
+        params: &[],
+        ports: PortLayout::Fixed(&[PortDef {
             name: SharedStr::new_static("Y"),
             position: Vec2 {
                 x: fixed!(0),
                 y: fixed!(0),
             },
-            input: false,
-            output: true,
-            directions: Directions::POS_X,
-        }],
+            direction: PortDirection::Output,
+            side: PortSide::Right,
+            bit_width: None,
+        }]),
     },
     SymbolDef {
         kind: SymbolKind::Out,
@@ -183,19 +879,440 @@ const KINDS: &[SymbolDef] = &[
             fixed!(40),
         ),
         shape: Shape::Output,
-        ports: &[PortDef {
+        params: &[],
+        ports: PortLayout::Fixed(&[PortDef {
+            name: SharedStr::new_static("A"),
+            position: Vec2 {
+                x: fixed!(0),
+                y: fixed!(0),
+            },
+            direction: PortDirection::Input,
+            side: PortSide::Left,
+            bit_width: None,
+        }]),
+    },
+    SymbolDef {
+        kind: SymbolKind::DFlipFlop,
+        name: SharedStr::new_static("DFF"),
+        designator_prefix: SharedStr::new_static("U"),
+        bounding_box: FLIP_FLOP_BOUNDING_BOX,
+        shape: Shape::FlipFlop,
+        ports: PortLayout::Fixed(D_FLIP_FLOP_PORTS),
+        params: &[],
+    },
+    SymbolDef {
+        kind: SymbolKind::JkFlipFlop,
+        name: SharedStr::new_static("JKFF"),
+        designator_prefix: SharedStr::new_static("U"),
+        bounding_box: FLIP_FLOP_BOUNDING_BOX,
+        shape: Shape::FlipFlop,
+        ports: PortLayout::Fixed(JK_FLIP_FLOP_PORTS),
+        params: &[],
+    },
+    SymbolDef {
+        kind: SymbolKind::TFlipFlop,
+        name: SharedStr::new_static("TFF"),
+        designator_prefix: SharedStr::new_static("U"),
+        bounding_box: FLIP_FLOP_BOUNDING_BOX,
+        shape: Shape::FlipFlop,
+        ports: PortLayout::Fixed(T_FLIP_FLOP_PORTS),
+        params: &[],
+    },
+    SymbolDef {
+        kind: SymbolKind::Mux,
+        name: SharedStr::new_static("MUX"),
+        designator_prefix: SharedStr::new_static("U"),
+        bounding_box: BoundingBox::from_top_left_size(
+            Vec2 {
+                x: fixed!(0),
+                y: fixed!(-10),
+            },
+            fixed!(40),
+            fixed!(60),
+        ),
+        shape: Shape::Mux,
+        ports: PortLayout::Mux { demux: false },
+        params: MUX_PARAMS,
+    },
+    SymbolDef {
+        kind: SymbolKind::Demux,
+        name: SharedStr::new_static("DEMUX"),
+        designator_prefix: SharedStr::new_static("U"),
+        bounding_box: BoundingBox::from_top_left_size(
+            Vec2 {
+                x: fixed!(0),
+                y: fixed!(-10),
+            },
+            fixed!(40),
+            fixed!(60),
+        ),
+        shape: Shape::Demux,
+        ports: PortLayout::Mux { demux: true },
+        params: MUX_PARAMS,
+    },
+    SymbolDef {
+        kind: SymbolKind::Adder,
+        name: SharedStr::new_static("ADD"),
+        designator_prefix: SharedStr::new_static("U"),
+        bounding_box: ARITHMETIC_BOUNDING_BOX,
+        shape: Shape::Adder,
+        ports: PortLayout::Arithmetic(ADDER_PORTS),
+        params: ARITHMETIC_PARAMS,
+    },
+    SymbolDef {
+        kind: SymbolKind::Subtractor,
+        name: SharedStr::new_static("SUB"),
+        designator_prefix: SharedStr::new_static("U"),
+        bounding_box: ARITHMETIC_BOUNDING_BOX,
+        shape: Shape::Subtractor,
+        ports: PortLayout::Arithmetic(SUBTRACTOR_PORTS),
+        params: ARITHMETIC_PARAMS,
+    },
+    SymbolDef {
+        kind: SymbolKind::Comparator,
+        name: SharedStr::new_static("CMP"),
+        designator_prefix: SharedStr::new_static("U"),
+        bounding_box: ARITHMETIC_BOUNDING_BOX,
+        shape: Shape::Comparator,
+        ports: PortLayout::Arithmetic(COMPARATOR_PORTS),
+        params: ARITHMETIC_PARAMS,
+    },
+    SymbolDef {
+        kind: SymbolKind::Register,
+        name: SharedStr::new_static("REG"),
+        designator_prefix: SharedStr::new_static("U"),
+        bounding_box: FLIP_FLOP_BOUNDING_BOX,
+        shape: Shape::FlipFlop,
+        ports: PortLayout::Arithmetic(REGISTER_PORTS),
+        params: ARITHMETIC_PARAMS,
+    },
+    SymbolDef {
+        kind: SymbolKind::Counter,
+        name: SharedStr::new_static("CNT"),
+        designator_prefix: SharedStr::new_static("U"),
+        bounding_box: COUNTER_BOUNDING_BOX,
+        shape: Shape::Counter,
+        ports: PortLayout::Arithmetic(COUNTER_PORTS),
+        params: ARITHMETIC_PARAMS,
+    },
+    SymbolDef {
+        kind: SymbolKind::ShiftRegister,
+        name: SharedStr::new_static("SHIFT"),
+        designator_prefix: SharedStr::new_static("U"),
+        bounding_box: SHIFT_REGISTER_BOUNDING_BOX,
+        shape: Shape::ShiftRegister,
+        ports: PortLayout::Arithmetic(SHIFT_REGISTER_PORTS),
+        params: ARITHMETIC_PARAMS,
+    },
+    SymbolDef {
+        kind: SymbolKind::Constant,
+        name: SharedStr::new_static("CONST"),
+        designator_prefix: SharedStr::new_static("J"),
+        bounding_box: BoundingBox::from_top_left_size(
+            Vec2 {
+                x: fixed!(-40),
+                y: fixed!(-10),
+            },
+            fixed!(40),
+            fixed!(20),
+        ),
+        shape: Shape::Constant,
+        ports: PortLayout::Arithmetic(CONSTANT_PORTS),
+        params: CONSTANT_PARAMS,
+    },
+    SymbolDef {
+        kind: SymbolKind::Tunnel,
+        name: SharedStr::new_static("TUNNEL"),
+        designator_prefix: SharedStr::new_static("T"),
+        bounding_box: BoundingBox::from_top_left_size(
+            Vec2 {
+                x: fixed!(0),
+                y: fixed!(-10),
+            },
+            fixed!(60),
+            fixed!(20),
+        ),
+        shape: Shape::Tunnel,
+        ports: PortLayout::Fixed(&[PortDef {
+            name: SharedStr::new_static("A"),
+            position: Vec2 {
+                x: fixed!(0),
+                y: fixed!(0),
+            },
+            direction: PortDirection::Bidirectional,
+            side: PortSide::Left,
+            bit_width: None,
+        }]),
+        params: TUNNEL_PARAMS,
+    },
+    SymbolDef {
+        kind: SymbolKind::Splitter,
+        name: SharedStr::new_static("SPLIT"),
+        designator_prefix: SharedStr::new_static("U"),
+        bounding_box: BoundingBox::from_top_left_size(
+            Vec2 {
+                x: fixed!(0),
+                y: fixed!(-10),
+            },
+            SPLITTER_WIDTH,
+            fixed!(40),
+        ),
+        shape: Shape::Splitter,
+        ports: PortLayout::Splitter,
+        params: SPLITTER_PARAMS,
+    },
+    SymbolDef {
+        kind: SymbolKind::PullUp,
+        name: SharedStr::new_static("PULLUP"),
+        designator_prefix: SharedStr::new_static("R"),
+        bounding_box: BoundingBox::from_top_left_size(
+            Vec2 {
+                x: fixed!(-10),
+                y: fixed!(-40),
+            },
+            fixed!(20),
+            fixed!(40),
+        ),
+        shape: Shape::PullUp,
+        ports: PortLayout::Fixed(&[PortDef {
+            name: SharedStr::new_static("A"),
+            position: Vec2 {
+                x: fixed!(0),
+                y: fixed!(0),
+            },
+            direction: PortDirection::Bidirectional,
+            side: PortSide::Bottom,
+            bit_width: None,
+        }]),
+        params: &[],
+    },
+    SymbolDef {
+        kind: SymbolKind::PullDown,
+        name: SharedStr::new_static("PULLDOWN"),
+        designator_prefix: SharedStr::new_static("R"),
+        bounding_box: BoundingBox::from_top_left_size(
+            Vec2 {
+                x: fixed!(-10),
+                y: fixed!(0),
+            },
+            fixed!(20),
+            fixed!(50),
+        ),
+        shape: Shape::PullDown,
+        ports: PortLayout::Fixed(&[PortDef {
             name: SharedStr::new_static("A"),
             position: Vec2 {
                 x: fixed!(0),
                 y: fixed!(0),
             },
-            input: true,
-            output: false,
-            directions: Directions::NEG_X,
-        }],
+            direction: PortDirection::Bidirectional,
+            side: PortSide::Top,
+            bit_width: None,
+        }]),
+        params: &[],
+    },
+    SymbolDef {
+        kind: SymbolKind::Buffer,
+        name: SharedStr::new_static("BUF"),
+        designator_prefix: SharedStr::new_static("U"),
+        bounding_box: BoundingBox::from_top_left_size(
+            Vec2 {
+                x: fixed!(0),
+                y: fixed!(-20),
+            },
+            fixed!(40),
+            fixed!(30),
+        ),
+        shape: Shape::Buffer,
+        ports: PortLayout::Fixed(BUFFER_PORTS),
+        params: &[],
+    },
+    SymbolDef {
+        kind: SymbolKind::OpenDrain,
+        name: SharedStr::new_static("OD"),
+        designator_prefix: SharedStr::new_static("U"),
+        bounding_box: BoundingBox::from_top_left_size(
+            Vec2 {
+                x: fixed!(0),
+                y: fixed!(-10),
+            },
+            fixed!(40),
+            fixed!(20),
+        ),
+        shape: Shape::OpenDrain,
+        ports: PortLayout::Fixed(BUFFER_PORTS.split_at(2).0),
+        params: &[],
+    },
+];
+
+/// The ports of buffers, and without the enable those of open-drain drivers.
+/// Their outputs can be switched off, so they are bidirectional and may share a net.
+const BUFFER_PORTS: &[PortDef] = &[
+    PortDef {
+        name: SharedStr::new_static("A"),
+        position: Vec2 {
+            x: fixed!(0),
+            y: fixed!(0),
+        },
+        direction: PortDirection::Input,
+        side: PortSide::Left,
+        bit_width: None,
+    },
+    PortDef {
+        name: SharedStr::new_static("Y"),
+        position: Vec2 {
+            x: fixed!(40),
+            y: fixed!(0),
+        },
+        direction: PortDirection::Bidirectional,
+        side: PortSide::Right,
+        bit_width: None,
+    },
+    PortDef {
+        name: SharedStr::new_static(ENABLE_PORT),
+        position: Vec2 {
+            x: fixed!(20),
+            y: fixed!(-20),
+        },
+        direction: PortDirection::Input,
+        side: PortSide::Top,
+        bit_width: Some(BitWidth(NonZeroU8::MIN)),
+    },
+];
+
+/// The name of the text parameter holding the literal driven by a constant,
+/// in the syntax of [`LogicState::parse`].
+pub const VALUE_PARAM: &str = "value";
+
+const CONSTANT_PARAMS: &[ParamDef] = &[
+    WIDTH_PARAM_DEF,
+    ParamDef {
+        name: SharedStr::new_static(VALUE_PARAM),
+        label: "Value",
+        ty: ParamType::Text,
+        default: ParamValue::Text(SharedStr::new_static("0")),
+    },
+];
+
+const CONSTANT_PORTS: &[PortDef] = &[PortDef {
+    name: SharedStr::new_static("Y"),
+    position: Vec2 {
+        x: fixed!(0),
+        y: fixed!(0),
+    },
+    direction: PortDirection::Output,
+    side: PortSide::Right,
+    bit_width: None,
+}];
+
+/// The name of the text parameter holding the label that joins tunnels.
+/// Tunnels with an empty label are not joined with any other tunnel.
+pub const LABEL_PARAM: &str = "label";
+
+const TUNNEL_PARAMS: &[ParamDef] = &[ParamDef {
+    name: SharedStr::new_static(LABEL_PARAM),
+    label: "Label",
+    ty: ParamType::Text,
+    default: ParamValue::Text(SharedStr::new_static("")),
+}];
+
+/// The name of the text parameter listing the taps of a splitter, separated by semicolons.
+/// Each tap is a list of bits of the bus in the syntax of [`Bits::parse`], like `0:3;4:7`.
+pub const TAPS_PARAM: &str = "taps";
+
+/// The name of the port of a splitter connecting to the whole bus.
+/// Its taps are named by their bits.
+pub const BUS_PORT: &str = "BUS";
+
+const SPLITTER_PARAMS: &[ParamDef] = &[
+    ParamDef {
+        name: SharedStr::new_static(WIDTH_PARAM),
+        label: "Width",
+        ty: ParamType::Integer {
+            min: 1,
+            max: MAX_ARITHMETIC_WIDTH,
+        },
+        default: ParamValue::Integer(2),
+    },
+    ParamDef {
+        name: SharedStr::new_static(TAPS_PARAM),
+        label: "Taps",
+        ty: ParamType::Text,
+        default: ParamValue::Text(SharedStr::new_static("")),
     },
 ];
 
+/// The spacing of the taps of a splitter.
+const SPLITTER_TAP_SPACING: Fixed = fixed!(20);
+const SPLITTER_WIDTH: Fixed = fixed!(10);
+
+/// The taps set by `parameters`. Taps that are invalid for the width of the bus or repeat
+/// an earlier one are left out, without any valid taps every bit is a tap of its own.
+pub fn splitter_taps(parameters: &Parameters) -> Vec<Bits> {
+    let width = arithmetic_width(parameters);
+    let mut taps = Vec::<Bits>::new();
+    for tap in parameters
+        .text(TAPS_PARAM)
+        .into_iter()
+        .flat_map(|taps| taps.split(';'))
+    {
+        let Ok(tap) = Bits::parse(tap) else {
+            continue;
+        };
+        if tap.validate(width).is_ok() && !taps.contains(&tap) {
+            taps.push(tap);
+        }
+    }
+
+    if taps.is_empty() {
+        taps = (0..width.0.get())
+            .map(|bit| Bits(smallvec::smallvec![bit]))
+            .collect();
+    }
+    taps
+}
+
+/// The outline of a splitter is a bar growing downwards with its taps.
+fn splitter_bounding_box(taps: usize) -> BoundingBox {
+    BoundingBox::from_top_left_size(
+        Vec2 {
+            x: fixed!(0),
+            y: fixed!(-10),
+        },
+        SPLITTER_WIDTH,
+        SPLITTER_TAP_SPACING * Fixed::from_u16(taps as u16),
+    )
+}
+
+/// The bus enters a splitter at the top of its left side, the taps leave on the right side.
+/// All ports are bidirectional, as a splitter merges its taps if they drive the bus.
+fn splitter_ports(width: BitWidth, taps: &[Bits]) -> Vec<PortDef> {
+    let bus = PortDef {
+        bit_width: Some(width),
+        ..PortDef::new(
+            BUS_PORT,
+            Vec2::ZERO,
+            PortDirection::Bidirectional,
+            PortSide::Left,
+        )
+    };
+
+    let taps = taps.iter().enumerate().map(|(index, tap)| PortDef {
+        bit_width: Some(BitWidth(NonZeroU8::new(tap.0.len() as u8).unwrap())),
+        ..PortDef::new(
+            tap.to_string(),
+            Vec2 {
+                x: SPLITTER_WIDTH,
+                y: SPLITTER_TAP_SPACING * Fixed::from_u16(index as u16),
+            },
+            PortDirection::Bidirectional,
+            PortSide::Right,
+        )
+    });
+
+    std::iter::once(bus).chain(taps).collect()
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct PortInfo {
     pub symbol: Entity,
@@ -203,6 +1320,7 @@ pub struct PortInfo {
     pub id: Entity,
     pub position: Vec2,
     pub direction: Directions,
+    pub output: bool,
 }
 
 #[derive(Debug)]
@@ -212,37 +1330,155 @@ pub struct SymbolBuilder<'a> {
     name: Option<SharedStr>,
     designator_number: Option<u32>,
     position: Option<Vec2>,
+    rotation: Rotation,
+    mirrored: bool,
     bit_width: Option<BitWidth>,
+    parameters: Parameters,
     ports: SmallVec<[PortInfo; 7]>,
 }
 
+/// A kind of symbol added to the [`SymbolRegistry`] at runtime,
+/// for example by an importer or as a custom chip.
+#[derive(Debug, Clone)]
+pub struct SymbolKindDescriptor {
+    /// The name the kind is saved and looked up by, unique within the registry.
+    pub name: SharedStr,
+    pub designator_prefix: SharedStr,
+    /// Symbols with [`Shape::Chip`] are drawn as their bounding box.
+    pub shape: Shape,
+    pub bounding_box: BoundingBox,
+    pub ports: Vec<PortDef>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegisterKindError {
+    /// A kind with the same name is already registered.
+    NameTaken(SharedStr),
+    /// There is no index left for another kind.
+    RegistryFull,
+}
+
+const CHIP_WIDTH: Fixed = fixed!(80);
+const CHIP_PORT_SPACING: Fixed = fixed!(20);
+
+impl SymbolKindDescriptor {
+    /// Describes a rectangular chip, like one made from the input and output symbols of a circuit.
+    /// Inputs are placed on the left side and all other ports on the right, each side in the order
+    /// the ports are given in.
+    pub fn chip(
+        name: SharedStr,
+        ports: impl IntoIterator<Item = (SharedStr, PortDirection, BitWidth)>,
+    ) -> Self {
+        let (mut left, mut right) = (0u16, 0u16);
+        let ports: Vec<_> = ports
+            .into_iter()
+            .map(|(name, direction, bit_width)| {
+                let (x, side, index) = if direction == PortDirection::Input {
+                    (fixed!(0), PortSide::Left, &mut left)
+                } else {
+                    (CHIP_WIDTH, PortSide::Right, &mut right)
+                };
+                let position = Vec2 {
+                    x,
+                    y: CHIP_PORT_SPACING * Fixed::from_u16(*index),
+                };
+                *index += 1;
+                PortDef::new(name, position, direction, side).with_bit_width(bit_width)
+            })
+            .collect();
+
+        let height = CHIP_PORT_SPACING * Fixed::from_u16(left.max(right).max(1));
+        Self {
+            name,
+            designator_prefix: SharedStr::new_static("U"),
+            shape: Shape::Chip,
+            bounding_box: BoundingBox::from_top_left_size(
+                Vec2 {
+                    x: fixed!(0),
+                    y: -CHIP_PORT_SPACING,
+                },
+                CHIP_WIDTH,
+                height + CHIP_PORT_SPACING,
+            ),
+            ports,
+        }
+    }
+}
+
+impl fmt::Display for RegisterKindError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NameTaken(name) => write!(f, "a symbol kind named `{name}` already exists"),
+            Self::RegistryFull => f.write_str("too many symbol kinds"),
+        }
+    }
+}
+
+impl std::error::Error for RegisterKindError {}
+
+/// All kinds of symbols, the built-in ones followed by the ones registered at runtime.
 #[derive(Debug, Resource)]
 pub struct SymbolRegistry {
     kinds: Vec<SymbolDef>,
 }
 
 impl SymbolRegistry {
-    pub fn get(&self, kind: SymbolKind) -> SymbolBuilder {
+    pub fn get(&self, kind: SymbolKind) -> SymbolBuilder<'_> {
         SymbolBuilder {
             registry: self,
             kind,
             name: None,
             designator_number: None,
             position: None,
+            rotation: Rotation::Rot0,
+            mirrored: false,
             bit_width: None,
+            parameters: Parameters::default(),
             ports: SmallVec::new(),
         }
     }
 
-    pub fn get_by_name(&self, name: &SharedStr) -> Option<SymbolBuilder> {
+    pub fn get_by_name(&self, name: &SharedStr) -> Option<SymbolBuilder<'_>> {
         let def = self.kinds.iter().find(|kind| kind.name == *name);
 
         def.map(|kind| self.get(kind.kind))
     }
 
+    pub fn get_def(&self, kind: SymbolKind) -> Option<&SymbolDef> {
+        self.kinds.get(kind.index())
+    }
+
     pub fn get_by_index(&self, index: usize) -> Option<&SymbolDef> {
         self.kinds.get(index)
     }
+
+    pub fn iter(&self) -> impl Iterator<Item = &SymbolDef> {
+        self.kinds.iter()
+    }
+
+    /// Adds a kind of symbol, returning the kind symbols of it are built with.
+    pub fn register(
+        &mut self,
+        descriptor: SymbolKindDescriptor,
+    ) -> Result<SymbolKind, RegisterKindError> {
+        if self.kinds.iter().any(|kind| kind.name == descriptor.name) {
+            return Err(RegisterKindError::NameTaken(descriptor.name));
+        }
+
+        let index = u16::try_from(self.kinds.len()).map_err(|_| RegisterKindError::RegistryFull)?;
+        let kind = SymbolKind::Custom(index);
+        self.kinds.push(SymbolDef {
+            kind,
+            name: descriptor.name,
+            designator_prefix: descriptor.designator_prefix,
+            ports: PortLayout::Registered(descriptor.ports.into()),
+            params: &[],
+            bounding_box: descriptor.bounding_box,
+            shape: descriptor.shape,
+        });
+
+        Ok(kind)
+    }
 }
 
 impl Default for SymbolRegistry {
@@ -269,25 +1505,50 @@ impl SymbolBuilder<'_> {
         self
     }
 
+    pub fn rotation(&mut self, rotation: Rotation) -> &mut Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Mirrors the symbol along the Y axis, before it is rotated.
+    pub fn mirrored(&mut self, mirrored: bool) -> &mut Self {
+        self.mirrored = mirrored;
+        self
+    }
+
     pub fn bit_width(&mut self, bit_width: BitWidth) -> &mut Self {
         self.bit_width = Some(bit_width);
         self
     }
 
+    /// Sets the parameter `name`. Parameters the kind doesn't declare are ignored,
+    /// invalid values are replaced by the default of the parameter.
+    pub fn parameter(&mut self, name: impl Into<SharedStr>, value: ParamValue) -> &mut Self {
+        self.parameters.insert(name.into(), value);
+        self
+    }
+
+    /// The parameters the symbol is built with.
+    pub fn parameters(&self) -> Parameters {
+        self.registry
+            .get_def(self.kind)
+            .map(|kind| kind.resolve_parameters(&self.parameters))
+            .unwrap_or_default()
+    }
+
     pub fn ports(&self) -> &[PortInfo] {
         &self.ports
     }
 
     pub fn bounding_box(&self) -> BoundingBox {
         self.registry
-            .kinds
-            .get(self.kind as usize)
-            .map(|kind| kind.bounding_box)
+            .get_def(self.kind)
+            .map(|kind| kind.bounding_box_for(&self.parameters()))
             .unwrap_or_default()
     }
 
     pub fn build(&mut self, commands: &mut Commands, circuit_id: Entity) -> Entity {
-        let kind = self.registry.kinds.get(self.kind as usize).unwrap();
+        let kind = self.registry.get_def(self.kind).unwrap();
 
         let symbol_id = commands
             .spawn(SymbolBundle {
@@ -299,6 +1560,8 @@ impl SymbolBuilder<'_> {
                 transform: TransformBundle {
                     transform: Transform {
                         translation: self.position.unwrap_or_default(),
+                        rotation: self.rotation,
+                        mirrored: self.mirrored,
                         ..Default::default()
                     },
                     ..Default::default()
@@ -306,7 +1569,7 @@ impl SymbolBuilder<'_> {
                 symbol: Symbol,
                 visibility: VisibilityBundle::default(),
                 bounds: BoundingBoxBundle {
-                    bounding_box: kind.bounding_box,
+                    bounding_box: kind.bounding_box_for(&self.parameters()),
                     ..Default::default()
                 },
             })
@@ -318,30 +1581,59 @@ impl SymbolBuilder<'_> {
                 .entity(symbol_id)
                 .insert(LogicState::from_bool(false));
         }
+        if !kind.params.is_empty() {
+            commands.entity(symbol_id).insert(self.parameters());
+        }
+
+        self.build_ports(commands, symbol_id);
+
+        symbol_id
+    }
+
+    /// Spawns the ports of the kind on `symbol_id`, which must not have ports yet.
+    /// Used by [`build`](Self::build), and when an existing symbol changes its kind.
+    pub fn build_ports(&mut self, commands: &mut Commands, symbol_id: Entity) -> &[PortInfo] {
+        let kind = self.registry.get_def(self.kind).unwrap();
 
         self.ports = kind
-            .ports
+            .port_defs(&self.parameters())
             .iter()
             .map(|port| {
                 let id = port.build(
                     commands,
                     symbol_id,
-                    self.bit_width.unwrap_or(BitWidth(NonZeroU8::MIN)),
+                    port.bit_width
+                        .or(self.bit_width)
+                        .unwrap_or(BitWidth(NonZeroU8::MIN)),
                 );
                 PortInfo {
                     symbol: symbol_id,
                     name: port.name.clone(),
                     id,
                     position: port.position,
-                    direction: port.directions,
+                    direction: port.side.directions(),
+                    output: port.direction.is_output(),
                 }
             })
             .collect();
 
-        symbol_id
+        &self.ports
     }
 }
 
+/// Spawns a port on a symbol that is not built from a [`SymbolDef`], like a sub-circuit.
+pub fn build_port(
+    commands: &mut Commands,
+    symbol_id: Entity,
+    name: SharedStr,
+    position: Vec2,
+    direction: PortDirection,
+    side: PortSide,
+    bit_width: BitWidth,
+) -> Entity {
+    PortDef::new(name, position, direction, side).build(commands, symbol_id, bit_width)
+}
+
 impl PortDef {
     fn build(&self, commands: &mut Commands, symbol_id: Entity, bit_width: BitWidth) -> Entity {
         let mut port_commands = commands.spawn(PortBundle {
@@ -360,8 +1652,10 @@ impl PortDef {
                 bounding_box: BoundingBox::from_half_size(PORT_HALF_WIDTH, PORT_HALF_WIDTH),
                 ..Default::default()
             },
+            direction: self.direction,
+            side: self.side,
             directions: DirectionsBundle {
-                directions: self.directions,
+                directions: self.side.directions(),
                 ..Default::default()
             },
         });
@@ -371,13 +1665,285 @@ impl PortDef {
             .set::<InheritTransform>(symbol_id)
             .set::<InheritVisibility>(symbol_id);
 
-        if self.input {
-            port_commands.insert(Input);
-        }
-        if self.output {
-            port_commands.insert(Output);
+        port_commands.id()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gate_inputs() {
+        let registry = SymbolRegistry::default();
+        let def = registry.get_def(SymbolKind::And).unwrap();
+
+        let defaults = def.resolve_parameters(&Parameters::default());
+        assert_eq!(defaults.integer(INPUTS_PARAM), Some(2));
+        assert_eq!(def.port_defs(&defaults).len(), 3);
+
+        let mut builder = registry.get(SymbolKind::And);
+        builder.parameter(INPUTS_PARAM, ParamValue::Integer(4));
+        let four = builder.parameters();
+        assert_eq!(def.port_defs(&four).len(), 5);
+        assert!(def.ports_differ(&defaults, &four));
+
+        // out of range counts are clamped
+        builder.parameter(INPUTS_PARAM, ParamValue::Integer(100));
+        assert_eq!(builder.parameters().integer(INPUTS_PARAM), Some(64));
+
+        // gates with many inputs stretch, their inputs keep being named like columns
+        builder.parameter(INPUTS_PARAM, ParamValue::Integer(30));
+        let ports = def.port_defs(&builder.parameters());
+        assert_eq!(ports[25].name.as_str(), "Z");
+        assert_eq!(ports[29].name.as_str(), "AD");
+        assert_eq!(ports[29].position.y, fixed!(580));
+        assert_eq!(ports[30].position.y, fixed!(290));
+        let bounding_box = def.bounding_box_for(&builder.parameters());
+        assert_eq!(
+            (bounding_box.min().y, bounding_box.max().y),
+            (fixed!(-10), fixed!(590))
+        );
+        assert_eq!(def.bounding_box_for(&four), def.bounding_box());
+
+        let not = registry.get_def(SymbolKind::Not).unwrap();
+        assert!(not.params().is_empty());
+        assert!(!not.ports_differ(&defaults, &four));
+    }
+
+    #[test]
+    fn mux_ports() {
+        let registry = SymbolRegistry::default();
+        let position = |ports: &[PortDef], name: &str| {
+            let port = ports
+                .iter()
+                .find(|port| port.name.as_str() == name)
+                .unwrap();
+            (port.position.x, port.position.y)
+        };
+
+        let mux = registry.get_def(SymbolKind::Mux).unwrap();
+        let defaults = mux.resolve_parameters(&Parameters::default());
+        assert_eq!(mux.bounding_box_for(&defaults), mux.bounding_box());
+        let ports = mux.port_defs(&defaults);
+        assert_eq!(ports.len(), 4);
+        assert_eq!(position(&ports, "D1"), (fixed!(0), fixed!(40)));
+        assert_eq!(position(&ports, "S0"), (fixed!(20), fixed!(40)));
+        assert_eq!(position(&ports, "Y"), (fixed!(40), fixed!(20)));
+
+        let demux = registry.get_def(SymbolKind::Demux).unwrap();
+        let ports = demux.port_defs(&defaults);
+        assert_eq!(position(&ports, "D"), (fixed!(0), fixed!(20)));
+        assert_eq!(position(&ports, "S0"), (fixed!(20), fixed!(40)));
+        assert_eq!(position(&ports, "Y1"), (fixed!(40), fixed!(40)));
+
+        // more select inputs widen the outline, and keep them on its sloped bottom edge
+        let mut builder = registry.get(SymbolKind::Mux);
+        builder.parameter(SELECT_BITS_PARAM, ParamValue::Integer(2));
+        let two = builder.parameters();
+        assert!(mux.ports_differ(&defaults, &two));
+        let ports = mux.port_defs(&two);
+        assert_eq!(ports.len(), 7);
+        assert_eq!(position(&ports, "D3"), (fixed!(0), fixed!(60)));
+        assert_eq!(position(&ports, "S0"), (fixed!(20), fixed!(60)));
+        assert_eq!(position(&ports, "S1"), (fixed!(40), fixed!(50)));
+        assert_eq!(position(&ports, "Y"), (fixed!(60), fixed!(30)));
+        let bounding_box = mux.bounding_box_for(&two);
+        assert_eq!(
+            bounding_box.max(),
+            Vec2 {
+                x: fixed!(60),
+                y: fixed!(70)
+            }
+        );
+
+        let ports = demux.port_defs(&two);
+        assert_eq!(position(&ports, "S0"), (fixed!(20), fixed!(50)));
+        assert_eq!(position(&ports, "S1"), (fixed!(40), fixed!(60)));
+    }
+
+    #[test]
+    fn splitter_ports() {
+        let registry = SymbolRegistry::default();
+        let splitter = registry.get_def(SymbolKind::Splitter).unwrap();
+        let defaults = splitter.resolve_parameters(&Parameters::default());
+        assert_eq!(
+            splitter.bounding_box_for(&defaults),
+            splitter.bounding_box()
+        );
+        let names = |parameters: &Parameters| {
+            splitter
+                .port_defs(parameters)
+                .iter()
+                .map(|port| (port.name.to_string(), port.bit_width.unwrap().0.get()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            names(&defaults),
+            [("BUS".into(), 2), ("0".into(), 1), ("1".into(), 1)]
+        );
+
+        // invalid and repeated taps are left out
+        let mut builder = registry.get(SymbolKind::Splitter);
+        builder.parameter(WIDTH_PARAM, ParamValue::Integer(8));
+        builder.parameter(TAPS_PARAM, ParamValue::Text("0:3; 4:7;9;x;0:3;1,5".into()));
+        let taps = builder.parameters();
+        assert_eq!(
+            names(&taps),
+            [
+                ("BUS".into(), 8),
+                ("0:3".into(), 4),
+                ("4:7".into(), 4),
+                ("1,5".into(), 2)
+            ]
+        );
+        let ports = splitter.port_defs(&taps);
+        assert_eq!(ports[3].position.y, fixed!(40));
+        assert_eq!(splitter.bounding_box_for(&taps).height(), fixed!(60));
+    }
+
+    #[test]
+    fn arithmetic_widths() {
+        let registry = SymbolRegistry::default();
+        let adder = registry.get_def(SymbolKind::Adder).unwrap();
+        let width = |ports: &[PortDef], name: &str| {
+            let port = ports
+                .iter()
+                .find(|port| port.name.as_str() == name)
+                .unwrap();
+            port.bit_width.unwrap().0.get()
+        };
+
+        let defaults = adder.resolve_parameters(&Parameters::default());
+        let ports = adder.port_defs(&defaults);
+        assert_eq!((width(&ports, "A"), width(&ports, CARRY_IN_PORT)), (1, 1));
+
+        // only the operands follow the width, carries stay a single bit
+        let mut builder = registry.get(SymbolKind::Adder);
+        builder.parameter(WIDTH_PARAM, ParamValue::Integer(8));
+        let wide = builder.parameters();
+        assert!(adder.ports_differ(&defaults, &wide));
+        let ports = adder.port_defs(&wide);
+        assert_eq!((width(&ports, "B"), width(&ports, "S")), (8, 8));
+        assert_eq!(
+            (width(&ports, CARRY_IN_PORT), width(&ports, CARRY_OUT_PORT)),
+            (1, 1)
+        );
+        assert_eq!(adder.bounding_box_for(&wide), adder.bounding_box());
+
+        // the clock and control inputs of counters are single bits as well
+        let counter = registry.get_def(SymbolKind::Counter).unwrap();
+        let ports = counter.port_defs(&wide);
+        assert_eq!((width(&ports, "D"), width(&ports, Q_PORT)), (8, 8));
+        assert_eq!(
+            (width(&ports, CLOCK_PORT), width(&ports, CARRY_OUT_PORT)),
+            (1, 1)
+        );
+    }
+
+    #[test]
+    fn register_kind() {
+        let mut registry = SymbolRegistry::default();
+        for (index, def) in registry.iter().enumerate() {
+            assert_eq!(def.kind().index(), index);
         }
 
-        port_commands.id()
+        let descriptor = SymbolKindDescriptor {
+            name: "LATCH".into(),
+            designator_prefix: "U".into(),
+            shape: Shape::Chip,
+            bounding_box: BoundingBox::from_top_left_size(Vec2::ZERO, fixed!(40), fixed!(40)),
+            ports: vec![
+                PortDef::input("D", Vec2::ZERO, PortSide::Left),
+                PortDef::input(
+                    "E",
+                    Vec2 {
+                        x: fixed!(0),
+                        y: fixed!(40),
+                    },
+                    PortSide::Left,
+                ),
+                PortDef::output(
+                    "Q",
+                    Vec2 {
+                        x: fixed!(40),
+                        y: fixed!(0),
+                    },
+                    PortSide::Right,
+                ),
+            ],
+        };
+        let kind = registry.register(descriptor.clone()).unwrap();
+        assert_eq!(kind, SymbolKind::Custom(24));
+
+        let def = registry.get_def(kind).unwrap();
+        assert_eq!(def.designator_prefix().as_str(), "U");
+        assert_eq!(def.port_defs(&Parameters::default()).len(), 3);
+        assert_eq!(
+            registry
+                .get_by_name(&"LATCH".into())
+                .unwrap()
+                .bounding_box(),
+            descriptor.bounding_box
+        );
+
+        assert_eq!(
+            registry.register(descriptor),
+            Err(RegisterKindError::NameTaken("LATCH".into()))
+        );
+    }
+    #[test]
+    fn chip_ports() {
+        let byte = BitWidth(NonZeroU8::new(8).unwrap());
+        let descriptor = SymbolKindDescriptor::chip(
+            "ALU".into(),
+            [
+                ("A".into(), PortDirection::Input, byte),
+                ("Y".into(), PortDirection::Output, byte),
+                ("B".into(), PortDirection::Input, byte),
+                ("OP".into(), PortDirection::Input, BitWidth(NonZeroU8::MIN)),
+            ],
+        );
+
+        let positions: Vec<_> = descriptor
+            .ports
+            .iter()
+            .map(|port| (port.name.as_str(), port.side, port.position))
+            .collect();
+        assert_eq!(
+            positions,
+            [
+                ("A", PortSide::Left, Vec2::ZERO),
+                (
+                    "Y",
+                    PortSide::Right,
+                    Vec2 {
+                        x: fixed!(80),
+                        y: fixed!(0)
+                    }
+                ),
+                (
+                    "B",
+                    PortSide::Left,
+                    Vec2 {
+                        x: fixed!(0),
+                        y: fixed!(20)
+                    }
+                ),
+                (
+                    "OP",
+                    PortSide::Left,
+                    Vec2 {
+                        x: fixed!(0),
+                        y: fixed!(40)
+                    }
+                ),
+            ]
+        );
+        assert_eq!(
+            descriptor.ports[3].bit_width,
+            Some(BitWidth(NonZeroU8::MIN))
+        );
+        assert_eq!(descriptor.bounding_box.height(), fixed!(80));
     }
 }
@@ -1,9 +1,10 @@
 use crate::{fixed, Fixed};
+use aery::edges::EdgeChanged;
 use aery::prelude::*;
 use bevy_derive::Deref;
 use bevy_ecs::prelude::*;
 use bevy_ecs::system::lifetimeless::{Read, Write};
-use bevy_reflect::Reflect;
+use bevy_reflect::{Reflect, ReflectDeserialize, ReflectSerialize};
 use bitflags::bitflags;
 use serde::{Deserialize, Serialize};
 use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
@@ -50,12 +51,84 @@ impl Vec2 {
         }
     }
 
+    /// Rounds both coordinates to the nearest multiple of `step`, which must be positive.
+    #[inline]
+    pub const fn round_to_multiple(self, step: Fixed) -> Self {
+        Self {
+            x: self.x.round_to_multiple(step),
+            y: self.y.round_to_multiple(step),
+        }
+    }
+
     #[inline]
     pub const fn manhatten_distance_to(self, other: Self) -> Fixed {
         let diff_x = self.x.const_sub(other.x).abs();
         let diff_y = self.y.const_sub(other.y).abs();
         diff_x.const_add(diff_y)
     }
+
+    #[inline]
+    pub const fn dot(self, rhs: Self) -> Fixed {
+        let x = self.x.const_mul(rhs.x);
+        let y = self.y.const_mul(rhs.y);
+        x.const_add(y)
+    }
+
+    #[inline]
+    pub const fn length(self) -> Fixed {
+        Fixed::hypot(self.x, self.y)
+    }
+
+    #[inline]
+    pub const fn distance_to(self, other: Self) -> Fixed {
+        Fixed::hypot(self.x.const_sub(other.x), self.y.const_sub(other.y))
+    }
+
+    /// Interpolates linearly from `self` at `t = 0` to `rhs` at `t = 1`.
+    #[inline]
+    pub const fn lerp(self, rhs: Self, t: Fixed) -> Self {
+        Self {
+            x: self.x.lerp(rhs.x, t),
+            y: self.y.lerp(rhs.y, t),
+        }
+    }
+
+    /// The point on the segment from `start` to `end` closest to `self`.
+    pub fn closest_point_on_segment(self, start: Self, end: Self) -> Self {
+        let (dx, dy) = (end - start).bits_wide();
+        let (vx, vy) = (self - start).bits_wide();
+
+        let projected = vx * dx + vy * dy;
+        let length_sqr = dx * dx + dy * dy;
+        if (length_sqr == 0) || (projected <= 0) {
+            return start;
+        }
+        if projected >= length_sqr {
+            return end;
+        }
+
+        // `projected / length_sqr` is the fraction of the segment up to the closest point
+        let offset = |d: i128| {
+            let bits = (2 * d * projected + length_sqr).div_euclid(2 * length_sqr);
+            Fixed::from_bits(bits as i32)
+        };
+        start
+            + Self {
+                x: offset(dx),
+                y: offset(dy),
+            }
+    }
+
+    /// The distance from `self` to the closest point on the segment from `start` to `end`.
+    #[inline]
+    pub fn distance_to_segment(self, start: Self, end: Self) -> Fixed {
+        self.distance_to(self.closest_point_on_segment(start, end))
+    }
+
+    #[inline]
+    fn bits_wide(self) -> (i128, i128) {
+        (self.x.to_bits() as i128, self.y.to_bits() as i128)
+    }
 }
 
 impl Default for Vec2 {
@@ -168,6 +241,17 @@ impl Rotation {
     pub fn radians(self) -> f64 {
         ((self as u8) as f64) * std::f64::consts::FRAC_PI_2
     }
+
+    /// The rotation that undoes this one.
+    #[inline]
+    pub fn inverse(self) -> Self {
+        match self {
+            Self::Rot0 => Self::Rot0,
+            Self::Rot90 => Self::Rot270,
+            Self::Rot180 => Self::Rot180,
+            Self::Rot270 => Self::Rot90,
+        }
+    }
 }
 
 impl Mul for Rotation {
@@ -193,6 +277,15 @@ impl MulAssign for Rotation {
 }
 
 impl Vec2 {
+    /// Mirrors the vector along the Y axis.
+    #[inline]
+    pub fn mirror(self) -> Self {
+        Self {
+            x: -self.x,
+            y: self.y,
+        }
+    }
+
     pub fn rotate(self, rotation: Rotation) -> Self {
         match rotation {
             Rotation::Rot0 => self,
@@ -213,10 +306,15 @@ impl Vec2 {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Component, Reflect)]
+#[reflect(Component)]
 pub struct Transform {
     pub translation: Vec2,
     pub rotation: Rotation,
     pub scale: Fixed,
+    /// Whether the entity is mirrored along the Y axis. Mirroring is applied before the rotation,
+    /// so a vertical flip is a mirror combined with a rotation by 180°.
+    #[serde(default)]
+    pub mirrored: bool,
 }
 
 impl Transform {
@@ -224,6 +322,7 @@ impl Transform {
         translation: Vec2::ZERO,
         rotation: Rotation::Rot0,
         scale: fixed!(1),
+        mirrored: false,
     };
 }
 
@@ -238,10 +337,18 @@ impl Mul for Transform {
     type Output = Self;
 
     fn mul(self, rhs: Self) -> Self::Output {
+        // mirroring reverses the direction of the rotations applied after it
+        let (translation, rotation) = if self.mirrored {
+            (rhs.translation.mirror(), rhs.rotation.inverse())
+        } else {
+            (rhs.translation, rhs.rotation)
+        };
+
         Self {
-            translation: self.translation + (rhs.translation * self.scale).rotate(self.rotation),
-            rotation: self.rotation * rhs.rotation,
+            translation: self.translation + (translation * self.scale).rotate(self.rotation),
+            rotation: self.rotation * rotation,
             scale: self.scale * rhs.scale,
+            mirrored: self.mirrored != rhs.mirrored,
         }
     }
 }
@@ -254,9 +361,17 @@ impl MulAssign for Transform {
 }
 
 impl Vec2 {
+    /// Applies the scale, mirroring, rotation and translation of `transform`, in that order,
+    /// the same way drawing does.
     #[inline]
     pub fn transform(self, transform: Transform) -> Self {
-        self.rotate(transform.rotation) + transform.translation
+        let scaled = self * transform.scale;
+        let mirrored = if transform.mirrored {
+            scaled.mirror()
+        } else {
+            scaled
+        };
+        mirrored.rotate(transform.rotation) + transform.translation
     }
 }
 
@@ -274,6 +389,7 @@ pub struct TransformBundle {
 #[derive(
     Default, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Component, Reflect,
 )]
+#[reflect(Component)]
 #[repr(C)]
 pub struct BoundingBox {
     min: Vec2,
@@ -382,6 +498,15 @@ impl BoundingBox {
         ]
     }
 
+    /// The smallest box containing both boxes.
+    #[inline]
+    pub const fn union(self, other: Self) -> Self {
+        Self {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
     #[inline]
     pub fn extrude(self, offset: Vec2) -> Self {
         Self::from_points(self.min - offset, self.max + offset)
@@ -419,6 +544,8 @@ impl BoundingBox {
         Self::from_points(a, b)
     }
 
+    /// Rotations are by multiples of 90°, so transforming two opposite corners
+    /// gives two opposite corners of the transformed box.
     pub fn transform(self, transform: Transform) -> Self {
         let a = self.min.transform(transform);
         let b = self.max.transform(transform);
@@ -456,6 +583,7 @@ pub struct BoundingBoxBundle {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Component, Reflect)]
+#[reflect(Component)]
 #[repr(u8)]
 pub enum Direction {
     PosX = 0,
@@ -478,6 +606,16 @@ impl Direction {
         }
     }
 
+    /// Mirrors the direction along the Y axis.
+    #[inline]
+    pub const fn mirror(self) -> Self {
+        match self {
+            Self::PosX => Self::NegX,
+            Self::NegX => Self::PosX,
+            Self::PosY | Self::NegY => self,
+        }
+    }
+
     #[inline]
     pub fn rotate(self, rotation: Rotation) -> Self {
         match ((self as u8) + (rotation as u8)) % 4 {
@@ -510,7 +648,7 @@ pub struct DirectionBundle {
 bitflags! {
     #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Component, Reflect)]
     #[repr(transparent)]
-    #[reflect_value]
+    #[reflect_value(Component, PartialEq, Hash, Serialize, Deserialize)]
     pub struct Directions: u8 {
         const POS_X = 0x1;
         const NEG_Y = 0x2;
@@ -540,6 +678,19 @@ impl Default for Directions {
 }
 
 impl Directions {
+    /// Mirrors the directions along the Y axis.
+    #[inline]
+    pub fn mirror(self) -> Self {
+        let mut mirrored = self.difference(Self::X);
+        if self.contains(Self::POS_X) {
+            mirrored |= Self::NEG_X;
+        }
+        if self.contains(Self::NEG_X) {
+            mirrored |= Self::POS_X;
+        }
+        mirrored
+    }
+
     #[inline]
     pub fn rotate(self, rotation: Rotation) -> Self {
         let shifted = self.bits() << (rotation as u8);
@@ -561,14 +712,24 @@ pub struct DirectionsBundle {
 #[derive(Debug, Relation)]
 pub struct InheritTransform;
 
+/// Roots whose transform changed, that were just detached from their parent or just spawned.
 type RootQuery<'w, 's> = Query<
     'w,
     's,
     (Read<Transform>, Write<GlobalTransform>),
-    Or<(Root<InheritTransform>, Abstains<InheritTransform>)>,
+    (
+        Or<(Root<InheritTransform>, Abstains<InheritTransform>)>,
+        Or<(
+            Changed<Transform>,
+            EdgeChanged<InheritTransform>,
+            Added<GlobalTransform>,
+        )>,
+    ),
 >;
 
 fn update_root_transform(mut roots: RootQuery) {
+    crate::profile_scope!("transform propagation");
+
     for (&transform, mut global_transform) in roots.iter_mut() {
         if global_transform.0 != transform {
             global_transform.0 = transform;
@@ -576,14 +737,60 @@ fn update_root_transform(mut roots: RootQuery) {
     }
 }
 
-fn update_transform(
-    mut tree: Query<(
-        (&Transform, &mut GlobalTransform),
+/// Detaching the last parent of an entity removes its edges instead of changing them,
+/// so the entity is marked changed to be placed as a root again.
+fn on_unset_inherit_transform(
+    trigger: Trigger<UnsetEvent<InheritTransform>>,
+    mut transforms: Query<Write<Transform>>,
+) {
+    if let Ok(mut transform) = transforms.get_mut(trigger.entity()) {
+        transform.set_changed();
+    }
+}
+
+/// Entities whose own transform or parent changed since the last update.
+type DirtyQuery<'w, 's> =
+    Query<'w, 's, Entity, Or<(Changed<Transform>, EdgeChanged<InheritTransform>)>>;
+
+type TreeQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        (Read<Transform>, Write<GlobalTransform>),
         Relations<InheritTransform>,
-    )>,
-    roots: Query<Entity, Root<InheritTransform>>,
+    ),
+>;
+
+/// Only the trees containing a changed entity are propagated,
+/// so unchanged parts of large circuits cost nothing.
+fn update_transform(
+    mut tree: TreeQuery,
+    dirty: DirtyQuery,
+    parents: Query<(Entity, Relations<InheritTransform>)>,
+    roots: Query<(), Root<InheritTransform>>,
+    mut dirty_roots: Local<Vec<Entity>>,
 ) {
-    tree.traverse_mut::<InheritTransform>(roots.iter())
+    crate::profile_scope!("transform propagation");
+
+    dirty_roots.clear();
+    for entity in dirty.iter() {
+        let mut root = entity;
+        parents
+            .traverse::<Up<InheritTransform>>([entity])
+            .for_each(|&mut parent, _| root = parent);
+
+        if roots.contains(root) {
+            dirty_roots.push(root);
+        }
+    }
+    if dirty_roots.is_empty() {
+        return;
+    }
+
+    dirty_roots.sort_unstable();
+    dirty_roots.dedup();
+
+    tree.traverse_mut::<InheritTransform>(dirty_roots.iter().copied())
         .track_self()
         .for_each(
             |(_, parent_global_transform), _, (child_transform, child_global_transform), _| {
@@ -595,12 +802,19 @@ fn update_transform(
         );
 }
 
-fn update_bounding_box(
-    mut query: Query<
-        (&BoundingBox, &mut AbsoluteBoundingBox, &GlobalTransform),
-        Changed<GlobalTransform>,
-    >,
-) {
+/// Bounds change with the transform, but also on their own, like when a symbol changes its kind.
+type ChangedBoundsQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Read<BoundingBox>,
+        Write<AbsoluteBoundingBox>,
+        Read<GlobalTransform>,
+    ),
+    Or<(Changed<GlobalTransform>, Changed<BoundingBox>)>,
+>;
+
+fn update_bounding_box(mut query: ChangedBoundsQuery) {
     for (bb, mut abs_bb, transform) in query.iter_mut() {
         abs_bb.0 = bb.transform(**transform);
     }
@@ -612,7 +826,12 @@ fn update_direction(
         Changed<GlobalTransform>,
     >,
 ) {
-    for (dir, mut abs_dir, transform) in query.iter_mut() {
+    for (&dir, mut abs_dir, transform) in query.iter_mut() {
+        let dir = if transform.mirrored {
+            dir.mirror()
+        } else {
+            dir
+        };
         abs_dir.0 = dir.rotate(transform.rotation);
     }
 }
@@ -623,7 +842,12 @@ fn update_directions(
         Changed<GlobalTransform>,
     >,
 ) {
-    for (dirs, mut abs_dirs, transform) in query.iter_mut() {
+    for (&dirs, mut abs_dirs, transform) in query.iter_mut() {
+        let dirs = if transform.mirrored {
+            dirs.mirror()
+        } else {
+            dirs
+        };
         abs_dirs.0 = dirs.rotate(transform.rotation);
     }
 }
@@ -647,6 +871,7 @@ impl bevy_app::Plugin for TransformPlugin {
             .register_type::<AbsoluteDirections>();
 
         app.register_relation::<InheritTransform>();
+        app.observe(on_unset_inherit_transform);
         app.add_systems(
             bevy_app::PostUpdate,
             (update_root_transform, update_transform)
@@ -659,3 +884,189 @@ impl bevy_app::Plugin for TransformPlugin {
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vec2(x: i16, y: i16) -> Vec2 {
+        Vec2 {
+            x: x.into(),
+            y: y.into(),
+        }
+    }
+
+    /// Random vectors with coordinates between -1000 and 1000, so no product overflows.
+    struct RandomVec2(u64);
+
+    impl RandomVec2 {
+        fn fixed(&mut self) -> Fixed {
+            // xorshift64
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            Fixed::from_bits(((self.0 % (2000 << 8)) as i32) - (1000 << 8))
+        }
+
+        fn next(&mut self) -> Vec2 {
+            Vec2 {
+                x: self.fixed(),
+                y: self.fixed(),
+            }
+        }
+    }
+
+    fn to_f64(v: Vec2) -> (f64, f64) {
+        (v.x.to_f64(), v.y.to_f64())
+    }
+
+    fn assert_close(actual: Fixed, expected: f64, ulps: f64) {
+        let tolerance = ulps / ((1 << crate::FIXED_FRACT_BITS) as f64);
+        assert!(
+            (actual.to_f64() - expected).abs() <= tolerance,
+            "{actual} is not within {tolerance} of {expected}",
+        );
+    }
+
+    #[test]
+    fn geometry_matches_f64() {
+        let mut random = RandomVec2(0x2545_f491_4f6c_dd1d);
+        for _ in 0..10_000 {
+            let (p, a, b) = (random.next(), random.next(), random.next());
+            let ((px, py), (ax, ay), (bx, by)) = (to_f64(p), to_f64(a), to_f64(b));
+
+            assert_close(p.dot(a), px * ax + py * ay, 2.0);
+            assert_close(p.length(), px.hypot(py), 0.5);
+            assert_close(p.distance_to(a), (px - ax).hypot(py - ay), 0.5);
+
+            let t = Fixed::from_bits(random.fixed().to_bits().rem_euclid(1 << 8));
+            let lerp = a.lerp(b, t);
+            assert_close(lerp.x, ax + (bx - ax) * t.to_f64(), 1.0);
+            assert_close(lerp.y, ay + (by - ay) * t.to_f64(), 1.0);
+
+            let (dx, dy) = (bx - ax, by - ay);
+            let fraction =
+                (((px - ax) * dx + (py - ay) * dy) / (dx * dx + dy * dy)).clamp(0.0, 1.0);
+            let (cx, cy) = (ax + dx * fraction, ay + dy * fraction);
+            assert_close(p.distance_to_segment(a, b), (px - cx).hypot(py - cy), 1.5);
+        }
+    }
+
+    #[test]
+    fn segment_ends() {
+        let (start, end) = (vec2(0, 0), vec2(10, 0));
+        assert_eq!(vec2(-3, 4).closest_point_on_segment(start, end), start);
+        assert_eq!(vec2(13, -4).distance_to_segment(start, end), fixed!(5));
+        assert_eq!(vec2(4, 2).closest_point_on_segment(start, end), vec2(4, 0));
+        assert_eq!(vec2(3, 4).distance_to_segment(start, start), fixed!(5));
+    }
+
+    #[test]
+    fn mirrored_parent() {
+        let parent = Transform {
+            translation: vec2(10, 0),
+            mirrored: true,
+            ..Transform::IDENTITY
+        };
+        let child = Transform {
+            translation: vec2(5, 0),
+            rotation: Rotation::Rot90,
+            ..Transform::IDENTITY
+        };
+
+        let global = parent * child;
+        assert_eq!(global.translation, vec2(5, 0));
+        assert_eq!(global.rotation, Rotation::Rot270);
+        assert!(global.mirrored);
+
+        let point = vec2(1, 2);
+        assert_eq!(
+            point.transform(global),
+            point.transform(child).transform(parent)
+        );
+    }
+
+    #[test]
+    fn propagate_changed_trees_only() {
+        fn spawn_at(world: &mut World, x: i16) -> Entity {
+            world
+                .spawn(TransformBundle {
+                    transform: Transform {
+                        translation: vec2(x, 0),
+                        ..Transform::IDENTITY
+                    },
+                    ..Default::default()
+                })
+                .id()
+        }
+
+        let mut app = bevy_app::App::new();
+        app.add_plugins(TransformPlugin);
+
+        let world = app.world_mut();
+        let parent = spawn_at(world, 10);
+        let child = spawn_at(world, 1);
+        let other = spawn_at(world, 100);
+        let other_child = spawn_at(world, 2);
+        world.entity_mut(child).set::<InheritTransform>(parent);
+        world.entity_mut(other_child).set::<InheritTransform>(other);
+        app.update();
+
+        let global = |app: &bevy_app::App, entity| {
+            app.world()
+                .entity(entity)
+                .get_ref::<GlobalTransform>()
+                .map(|global| (global.translation, global.last_changed()))
+                .unwrap()
+        };
+        assert_eq!(global(&app, child).0, vec2(11, 0));
+        let (other_translation, other_tick) = global(&app, other_child);
+        assert_eq!(other_translation, vec2(102, 0));
+
+        let world = app.world_mut();
+        world.get_mut::<Transform>(parent).unwrap().translation = vec2(20, 0);
+        app.update();
+
+        assert_eq!(global(&app, child).0, vec2(21, 0));
+        assert_eq!(global(&app, other_child), (other_translation, other_tick));
+
+        // a detached child is placed by its own transform alone
+        let world = app.world_mut();
+        world.entity_mut(child).unset::<InheritTransform>(parent);
+        app.update();
+
+        assert_eq!(global(&app, child).0, vec2(1, 0));
+        assert_eq!(global(&app, parent).0, vec2(20, 0));
+    }
+
+    #[test]
+    fn transformed_bounds() {
+        let bounds = BoundingBox::from_points(vec2(-10, -5), vec2(30, 5));
+
+        let rotated = bounds.transform(Transform {
+            translation: vec2(100, 100),
+            rotation: Rotation::Rot90,
+            ..Transform::IDENTITY
+        });
+        assert_eq!(rotated.min(), vec2(95, 90));
+        assert_eq!(rotated.max(), vec2(105, 130));
+
+        let mirrored = bounds.transform(Transform {
+            mirrored: true,
+            scale: fixed!(2),
+            ..Transform::IDENTITY
+        });
+        assert_eq!(mirrored.min(), vec2(-60, -10));
+        assert_eq!(mirrored.max(), vec2(20, 10));
+    }
+
+    #[test]
+    fn mirror_directions() {
+        assert_eq!(Directions::POS_X.mirror(), Directions::NEG_X);
+        assert_eq!(
+            (Directions::NEG_X | Directions::POS_Y).mirror(),
+            Directions::POS_X | Directions::POS_Y
+        );
+        assert_eq!(Direction::NegY.mirror(), Direction::NegY);
+    }
+}
@@ -1,10 +1,15 @@
+use crate::HashMap;
 use aery::prelude::*;
-use bevy_derive::Deref;
+use bevy_derive::{Deref, DerefMut};
 use bevy_ecs::prelude::*;
 use bevy_ecs::system::lifetimeless::{Read, Write};
-use bevy_reflect::Reflect;
+use bevy_reflect::{Reflect, ReflectDeserialize, ReflectSerialize};
+use bitflags::bitflags;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 #[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash, Component, Reflect)]
+#[reflect(Component)]
 pub enum Visibility {
     #[default]
     Inherit,
@@ -29,6 +34,46 @@ pub struct VisibilityBundle {
     pub computed_visibility: ComputedVisibility,
 }
 
+bitflags! {
+    /// The kinds of content a viewport shows. Hiding a layer hides all of its content,
+    /// regardless of the [`Visibility`] of the individual entities.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Component, Reflect)]
+    #[repr(transparent)]
+    #[reflect_value(Component, PartialEq, Hash, Serialize, Deserialize)]
+    pub struct VisibleLayers: u8 {
+        const SYMBOLS = 0x1;
+        const WIRES = 0x2;
+        const LABELS = 0x4;
+        const ANNOTATIONS = 0x8;
+        const ERC_MARKERS = 0x10;
+
+        const NONE = 0;
+        const ALL = Self::SYMBOLS.bits()
+            | Self::WIRES.bits()
+            | Self::LABELS.bits()
+            | Self::ANNOTATIONS.bits()
+            | Self::ERC_MARKERS.bits();
+    }
+}
+
+impl VisibleLayers {
+    /// Every layer with the name it is presented to users with.
+    pub const NAMED: [(Self, &'static str); 5] = [
+        (Self::SYMBOLS, "Symbols"),
+        (Self::WIRES, "Wires"),
+        (Self::LABELS, "Labels"),
+        (Self::ANNOTATIONS, "Annotations"),
+        (Self::ERC_MARKERS, "ERC markers"),
+    ];
+}
+
+impl Default for VisibleLayers {
+    #[inline]
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
 #[derive(Debug, Relation)]
 pub struct InheritVisibility;
 
@@ -79,6 +124,154 @@ fn update_visibility(
         );
 }
 
+/// Overrides the [`Visibility`] of entities in a single viewport.
+/// Children inheriting their visibility follow the override, just like they follow [`Visibility`].
+#[derive(Default, Debug, Clone, PartialEq, Eq, Deref, DerefMut, Component, Reflect)]
+pub struct VisibilityOverrides(pub BTreeMap<Entity, Visibility>);
+
+/// The visibility of entities in a single viewport, resolved from its [`VisibilityOverrides`].
+/// Only entities whose visibility differs from their [`ComputedVisibility`] are stored.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Component)]
+pub struct ViewVisibility {
+    differs: HashMap<Entity, bool>,
+}
+
+impl ViewVisibility {
+    /// Whether `entity` is visible in the viewport, given its visibility in all other viewports.
+    #[inline]
+    pub fn is_visible(&self, entity: Entity, computed: ComputedVisibility) -> bool {
+        self.differs.get(&entity).copied().unwrap_or(computed.0)
+    }
+}
+
+#[derive(Default, Debug, Bundle)]
+pub struct ViewVisibilityBundle {
+    pub overrides: VisibilityOverrides,
+    pub view_visibility: ViewVisibility,
+}
+
+type VisibilityTreeQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        (Entity, Read<Visibility>, Read<ComputedVisibility>),
+        Relations<InheritVisibility>,
+    ),
+>;
+
+type VisibilityNodeQuery<'w, 's> =
+    Query<'w, 's, (Entity, Read<Visibility>, Read<ComputedVisibility>)>;
+
+fn resolve_override(
+    entity: Entity,
+    visibility: Visibility,
+    tree: &VisibilityTreeQuery,
+    view_visibility: &ViewVisibility,
+) -> bool {
+    match visibility {
+        Visibility::Visible => true,
+        Visibility::Hidden => false,
+        Visibility::Inherit => {
+            let mut parent_visible = true;
+            if let Ok((_, edges)) = tree.get(entity) {
+                edges.join::<Up<InheritVisibility>>(tree).for_each(
+                    |((parent, _, &computed), _)| {
+                        parent_visible = view_visibility.is_visible(parent, computed);
+                    },
+                );
+            }
+            parent_visible
+        }
+    }
+}
+
+fn resolve_subtree(
+    entity: Entity,
+    visible: bool,
+    tree: &VisibilityTreeQuery,
+    nodes: &VisibilityNodeQuery,
+    overrides: &VisibilityOverrides,
+    view_visibility: &mut ViewVisibility,
+) {
+    let Ok(((_, _, computed), edges)) = tree.get(entity) else {
+        return;
+    };
+    if visible != computed.0 {
+        view_visibility.differs.insert(entity, visible);
+    }
+
+    let mut children = Vec::new();
+    edges
+        .join::<InheritVisibility>(nodes)
+        .for_each(|(child, &child_visibility, _)| {
+            // overridden children are resolved on their own
+            if !overrides.contains_key(&child) {
+                children.push((child, child_visibility));
+            }
+        });
+
+    for (child, child_visibility) in children {
+        let child_visible = match child_visibility {
+            Visibility::Inherit => visible,
+            Visibility::Visible => true,
+            Visibility::Hidden => false,
+        };
+        resolve_subtree(
+            child,
+            child_visible,
+            tree,
+            nodes,
+            overrides,
+            view_visibility,
+        );
+    }
+}
+
+fn depth(entity: Entity, tree: &VisibilityTreeQuery) -> usize {
+    let mut depth = 0;
+    if let Ok((_, edges)) = tree.get(entity) {
+        edges
+            .join::<Up<InheritVisibility>>(tree)
+            .for_each(|((parent, _, _), _)| depth = 1 + self::depth(parent, tree));
+    }
+    depth
+}
+
+type ChangedVisibilityQuery<'w, 's> =
+    Query<'w, 's, (), Or<(Changed<Visibility>, Changed<ComputedVisibility>)>>;
+
+/// Resolves the visibility of every viewport after the visibility shared by all viewports
+/// has been computed. Viewports without overrides see the shared visibility unchanged.
+fn update_view_visibility(
+    mut viewports: Query<(Ref<VisibilityOverrides>, Write<ViewVisibility>)>,
+    changed: ChangedVisibilityQuery,
+    tree: VisibilityTreeQuery,
+    nodes: VisibilityNodeQuery,
+) {
+    let visibility_changed = !changed.is_empty();
+
+    for (overrides, mut view_visibility) in viewports.iter_mut() {
+        if !overrides.is_changed() && !visibility_changed {
+            continue;
+        }
+
+        let view_visibility = &mut *view_visibility;
+        view_visibility.differs.clear();
+
+        // parents are resolved before their children, which may inherit from them
+        let mut sorted: Vec<_> = overrides
+            .iter()
+            .map(|(&entity, &visibility)| (depth(entity, &tree), entity, visibility))
+            .collect();
+        sorted.sort_unstable_by_key(|&(depth, entity, _)| (depth, entity));
+
+        for (_, entity, visibility) in sorted {
+            let visible = resolve_override(entity, visibility, &tree, view_visibility);
+            resolve_subtree(entity, visible, &tree, &nodes, &overrides, view_visibility);
+        }
+    }
+}
+
 #[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct VisibilitySet;
 
@@ -87,14 +280,97 @@ pub(crate) struct VisibilityPlugin;
 impl bevy_app::Plugin for VisibilityPlugin {
     fn build(&self, app: &mut bevy_app::App) {
         app.register_type::<Visibility>()
-            .register_type::<ComputedVisibility>();
+            .register_type::<ComputedVisibility>()
+            .register_type::<VisibleLayers>()
+            .register_type::<VisibilityOverrides>();
 
         app.register_relation::<InheritVisibility>();
         app.add_systems(
             bevy_app::PostUpdate,
-            (update_root_visibility, update_visibility)
+            (
+                update_root_visibility,
+                update_visibility,
+                update_view_visibility,
+            )
                 .chain()
                 .in_set(VisibilitySet),
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_visible(world: &World, view: Entity, entity: Entity) -> bool {
+        let computed = *world.get::<ComputedVisibility>(entity).unwrap();
+        world
+            .get::<ViewVisibility>(view)
+            .unwrap()
+            .is_visible(entity, computed)
+    }
+
+    #[test]
+    fn view_overrides() {
+        let mut app = bevy_app::App::new();
+        app.add_plugins(VisibilityPlugin);
+
+        let world = app.world_mut();
+        let symbol = world.spawn(VisibilityBundle::default()).id();
+        let [port_a, port_b] = [(); 2].map(|_| {
+            world
+                .spawn(VisibilityBundle::default())
+                .set::<InheritVisibility>(symbol)
+                .id()
+        });
+        let [hiding, plain] = [(); 2].map(|_| world.spawn(ViewVisibilityBundle::default()).id());
+
+        let mut overrides = world.get_mut::<VisibilityOverrides>(hiding).unwrap();
+        overrides.insert(symbol, Visibility::Hidden);
+        overrides.insert(port_b, Visibility::Visible);
+        app.update();
+
+        let visible = |view, entity| is_visible(app.world(), view, entity);
+        assert!(!visible(hiding, symbol));
+        assert!(!visible(hiding, port_a));
+        assert!(visible(hiding, port_b));
+        for entity in [symbol, port_a, port_b] {
+            assert!(visible(plain, entity));
+        }
+
+        // hiding the symbol everywhere is still overridden for the port
+        let world = app.world_mut();
+        *world.get_mut::<Visibility>(symbol).unwrap() = Visibility::Hidden;
+        world
+            .get_mut::<VisibilityOverrides>(hiding)
+            .unwrap()
+            .remove(&symbol);
+        app.update();
+
+        let visible = |view, entity| is_visible(app.world(), view, entity);
+        assert!(!visible(plain, port_a));
+        assert!(!visible(hiding, port_a));
+        assert!(visible(hiding, port_b));
+    }
+
+    #[test]
+    fn visible_layers_reflect_as_component() {
+        let mut registry = bevy_reflect::TypeRegistry::default();
+        registry.register::<VisibleLayers>();
+        let reflect_component = registry
+            .get_type_data::<ReflectComponent>(std::any::TypeId::of::<VisibleLayers>())
+            .unwrap();
+
+        let mut world = World::new();
+        let viewport = world.spawn_empty().id();
+        reflect_component.insert(
+            &mut world.entity_mut(viewport),
+            &VisibleLayers::WIRES,
+            &registry,
+        );
+        assert_eq!(
+            world.get::<VisibleLayers>(viewport),
+            Some(&VisibleLayers::WIRES)
+        );
+    }
+}
@@ -0,0 +1,260 @@
+//! Extension points for Bevy plugins that add to digilogic without changing its UI.
+//!
+//! Extensions are plugins added to the app next to digilogic's own, which use
+//! [`ExtensionAppExt`] to add
+//! - commands, listed in the Extensions menu,
+//! - panels, opened as dock tabs from the View menu,
+//! - kinds of symbols, placed like the built-in ones,
+//! - importers and exporters of circuit files,
+//!
+//! and draw on top of circuits by adding systems to [`DrawOverlaySet`].
+
+use bevy_ecs::prelude::*;
+use bevy_ecs::system::SystemId;
+use bevy_reflect::Reflect;
+use digilogic_core::components::SymbolKind;
+use digilogic_core::symbol::{RegisterKindError, SymbolKindDescriptor, SymbolRegistry};
+use digilogic_core::SharedStr;
+use std::fmt;
+
+pub use digilogic_serde::{ExportFn, Exporter, FileFormats, ImportFn, Importer};
+
+/// A command run by a one-shot system when picked from the Extensions menu.
+#[derive(Debug, Clone)]
+pub struct ExtensionCommand {
+    pub name: SharedStr,
+    pub system: SystemId,
+}
+
+#[derive(Debug, Default, Resource)]
+pub struct CommandRegistry {
+    commands: Vec<ExtensionCommand>,
+}
+
+impl CommandRegistry {
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = &ExtensionCommand> {
+        self.commands.iter()
+    }
+}
+
+/// Shows the contents of a panel, with access to the whole world.
+pub type PanelFn = Box<dyn FnMut(&mut egui::Ui, &mut World) + Send + Sync>;
+
+/// Identifies a panel within the [`PanelRegistry`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
+pub struct PanelId(usize);
+
+pub struct Panel {
+    pub title: SharedStr,
+    show: PanelFn,
+}
+
+impl fmt::Debug for Panel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Panel")
+            .field("title", &self.title)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Default, Resource)]
+pub struct PanelRegistry {
+    panels: Vec<Panel>,
+}
+
+impl PanelRegistry {
+    pub fn iter(&self) -> impl Iterator<Item = (PanelId, &Panel)> {
+        self.panels
+            .iter()
+            .enumerate()
+            .map(|(index, panel)| (PanelId(index), panel))
+    }
+
+    #[inline]
+    pub fn get(&self, id: PanelId) -> Option<&Panel> {
+        self.panels.get(id.0)
+    }
+
+    /// Shows the contents of the panel in `ui`.
+    pub fn show(&mut self, id: PanelId, ui: &mut egui::Ui, world: &mut World) {
+        if let Some(panel) = self.panels.get_mut(id.0) {
+            (panel.show)(ui, world);
+        }
+    }
+}
+
+/// Shapes drawn on top of the circuit shown in a viewport, in circuit coordinates.
+/// Every viewport has one, which is reset each frame before [`DrawOverlaySet`] runs.
+#[allow(missing_debug_implementations)]
+#[derive(Default, Component)]
+pub struct Overlay(pub vello::Scene);
+
+/// The systems drawing into [`Overlay`]s, found on the viewport entities next to the
+/// [`CircuitID`](digilogic_core::components::CircuitID) of the circuit they show.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, SystemSet)]
+pub struct DrawOverlaySet;
+
+/// Adds to digilogic from within [`Plugin::build`](bevy_app::Plugin::build).
+/// The order plugins are added in doesn't matter.
+pub trait ExtensionAppExt {
+    /// Adds a command to the Extensions menu, which runs `system` when picked.
+    fn add_command<M>(
+        &mut self,
+        name: impl Into<SharedStr>,
+        system: impl IntoSystem<(), (), M> + 'static,
+    ) -> &mut Self;
+
+    /// Adds a panel that can be opened from the View menu.
+    fn add_panel(
+        &mut self,
+        title: impl Into<SharedStr>,
+        show: impl FnMut(&mut egui::Ui, &mut World) + Send + Sync + 'static,
+    ) -> &mut Self;
+
+    /// Adds a kind of symbol, returning the kind symbols of it are built with.
+    fn add_symbol_kind(
+        &mut self,
+        descriptor: SymbolKindDescriptor,
+    ) -> Result<SymbolKind, RegisterKindError>;
+
+    fn add_importer(&mut self, importer: Importer) -> &mut Self;
+
+    fn add_exporter(&mut self, exporter: Exporter) -> &mut Self;
+}
+
+impl ExtensionAppExt for bevy_app::App {
+    fn add_command<M>(
+        &mut self,
+        name: impl Into<SharedStr>,
+        system: impl IntoSystem<(), (), M> + 'static,
+    ) -> &mut Self {
+        let system = self.register_system(system);
+        let world = self.world_mut();
+        world
+            .get_resource_or_insert_with(CommandRegistry::default)
+            .commands
+            .push(ExtensionCommand {
+                name: name.into(),
+                system,
+            });
+        self
+    }
+
+    fn add_panel(
+        &mut self,
+        title: impl Into<SharedStr>,
+        show: impl FnMut(&mut egui::Ui, &mut World) + Send + Sync + 'static,
+    ) -> &mut Self {
+        let world = self.world_mut();
+        world
+            .get_resource_or_insert_with(PanelRegistry::default)
+            .panels
+            .push(Panel {
+                title: title.into(),
+                show: Box::new(show),
+            });
+        self
+    }
+
+    fn add_symbol_kind(
+        &mut self,
+        descriptor: SymbolKindDescriptor,
+    ) -> Result<SymbolKind, RegisterKindError> {
+        let world = self.world_mut();
+        world
+            .get_resource_or_insert_with(SymbolRegistry::default)
+            .register(descriptor)
+    }
+
+    fn add_importer(&mut self, importer: Importer) -> &mut Self {
+        let world = self.world_mut();
+        world
+            .get_resource_or_insert_with(FileFormats::default)
+            .add_importer(importer);
+        self
+    }
+
+    fn add_exporter(&mut self, exporter: Exporter) -> &mut Self {
+        let world = self.world_mut();
+        world
+            .get_resource_or_insert_with(FileFormats::default)
+            .add_exporter(exporter);
+        self
+    }
+}
+
+/// Makes sure the registries exist even if no extension added to them.
+#[derive(Debug, Default)]
+pub struct ExtensionPlugin;
+
+impl bevy_app::Plugin for ExtensionPlugin {
+    fn build(&self, app: &mut bevy_app::App) {
+        app.init_resource::<CommandRegistry>();
+        app.init_resource::<PanelRegistry>();
+        app.init_resource::<FileFormats>();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use digilogic_core::components::{CircuitID, Shape};
+    use digilogic_core::fixed;
+    use digilogic_core::transform::BoundingBox;
+    use std::path::Path;
+
+    #[derive(Default, Resource)]
+    struct RunCount(u32);
+
+    fn count_runs(mut count: ResMut<RunCount>) {
+        count.0 += 1;
+    }
+
+    fn export_nothing(_world: &mut World, _circuit: CircuitID, _path: &Path) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    #[test]
+    fn extensions_can_be_added_before_digilogic() {
+        let mut app = bevy_app::App::new();
+        app.init_resource::<RunCount>();
+        app.add_command("Count", count_runs);
+        app.add_exporter(Exporter {
+            name: "Nothing".into(),
+            extensions: &["txt"],
+            export: export_nothing,
+        });
+        let kind = app
+            .add_symbol_kind(SymbolKindDescriptor {
+                name: "WIDGET".into(),
+                designator_prefix: "W".into(),
+                shape: Shape::Chip,
+                bounding_box: BoundingBox::from_half_size(fixed!(20), fixed!(20)),
+                ports: Vec::new(),
+            })
+            .unwrap();
+        app.add_plugins((
+            bevy_state::app::StatesPlugin,
+            digilogic_core::CorePlugin,
+            digilogic_serde::LoadSavePlugin,
+            ExtensionPlugin,
+        ));
+
+        let world = app.world_mut();
+        let system = world
+            .resource::<CommandRegistry>()
+            .iter()
+            .next()
+            .unwrap()
+            .system;
+        world.run_system(system).unwrap();
+        assert_eq!(world.resource::<RunCount>().0, 1);
+
+        assert!(matches!(kind, SymbolKind::Custom(_)));
+        let def = world.resource::<SymbolRegistry>().get_def(kind).unwrap();
+        assert_eq!(def.name().as_str(), "WIDGET");
+
+        assert_eq!(world.resource::<FileFormats>().exporters().len(), 1);
+    }
+}
@@ -13,10 +13,103 @@ impl Default for ClientState {
     }
 }
 
+/// How often the pulls are adjusted during one eval before giving up on them settling.
+const MAX_PULL_ROUNDS: usize = 16;
+
+/// A weak drive of a net, driving only the bits no other driver does.
+///
+/// gsim has no drive strengths, any two drivers of a bit conflict. So the pull is applied by
+/// driving the net between simulation runs: bits that settle high impedance get pulled, and a
+/// conflict on the net releases the pull until the next run shows which bits are free again.
+struct Pull {
+    net: WireId,
+    width: NonZeroU8,
+    /// The bit of each bit plane the pulled bits are driven with.
+    level: (bool, bool),
+    /// The bit planes of the drive set by the client, the pull never drives over it.
+    drive: ([u32; 8], [u32; 8]),
+    /// The bits currently pulled.
+    active: [u32; 8],
+}
+
+impl Pull {
+    fn new(net: WireId, width: NonZeroU8, up: bool) -> Self {
+        Self {
+            net,
+            width,
+            level: (up, true),
+            drive: ([0; 8], [0; 8]),
+            active: [0; 8],
+        }
+    }
+
+    fn word_count(&self) -> usize {
+        self.width.get().div_ceil(32) as usize
+    }
+
+    /// The bits of word `index` the net has.
+    fn word_mask(&self, index: usize) -> u32 {
+        let bits = self.width.get() as usize - index * 32;
+        if bits >= 32 {
+            u32::MAX
+        } else {
+            (1 << bits) - 1
+        }
+    }
+
+    /// The drive of the net, the client's drive plus the pulled bits.
+    fn net_drive(&self) -> LogicState {
+        let mut plane_0 = self.drive.0;
+        let mut plane_1 = self.drive.1;
+        for (index, &active) in self.active.iter().enumerate() {
+            if self.level.0 {
+                plane_0[index] |= active;
+            }
+            if self.level.1 {
+                plane_1[index] |= active;
+            }
+        }
+
+        let word_count = self.word_count();
+        LogicState::from_bit_planes(&plane_0[..word_count], &plane_1[..word_count])
+    }
+
+    fn set_drive(&mut self, drive: &LogicState) {
+        let (plane_0, plane_1) = drive.to_bit_planes(self.width);
+        for index in 0..self.word_count() {
+            let mask = self.word_mask(index);
+            self.drive.0[index] = plane_0[index] & mask;
+            self.drive.1[index] = plane_1[index] & mask;
+            self.active[index] &= !(self.drive.0[index] | self.drive.1[index]);
+        }
+    }
+
+    /// Pulls the bits that are high impedance in `state`, returns whether any were.
+    fn pull(&mut self, state: &LogicState) -> bool {
+        let (plane_0, plane_1) = state.to_bit_planes(self.width);
+        let mut changed = false;
+        for index in 0..self.word_count() {
+            let high_z = !(plane_0[index] | plane_1[index]) & self.word_mask(index);
+            let free = high_z & !(self.drive.0[index] | self.drive.1[index]);
+            changed |= free & !self.active[index] != 0;
+            self.active[index] |= free;
+        }
+        changed
+    }
+
+    /// Stops pulling all bits, returns whether any were pulled.
+    fn release(&mut self) -> bool {
+        let changed = self.active.iter().any(|&active| active != 0);
+        self.active = [0; 8];
+        changed
+    }
+}
+
 #[derive(Default)]
 #[allow(missing_debug_implementations)]
 pub struct GsimServer {
     clients: ahash::AHashMap<ClientId, ClientState>,
+    pulls: ahash::AHashMap<ClientId, Vec<Pull>>,
     bit_plane_0: [u8; 32],
     bit_plane_1: [u8; 32],
 }
@@ -73,6 +166,288 @@ fn simulation_result_to_server_result(result: SimulationRunResult) -> ServerResu
     }
 }
 
+/// Adds a wire only the components of a cell are connected to.
+fn add_internal_wire(builder: &mut SimulatorBuilder, width: NonZeroU8) -> ServerResult<WireId> {
+    builder.add_wire(width).ok_or(ServerError::OutOfResources)
+}
+
+/// Adds a wire that is always driven to `state`.
+fn add_constant(
+    builder: &mut SimulatorBuilder,
+    width: NonZeroU8,
+    state: LogicState,
+) -> ServerResult<WireId> {
+    let wire = add_internal_wire(builder, width)?;
+    builder
+        .set_wire_drive(wire, &state)
+        .map_err(|_| ServerError::InvalidNetId)?;
+    Ok(wire)
+}
+
+/// The state encoded by the bit planes of the netcode protocol, at most 256 bits wide.
+fn state_from_bit_planes(bit_plane_0: &[u8], bit_plane_1: &[u8]) -> LogicState {
+    let mut words0 = [0u32; 8];
+    let mut words1 = [0u32; 8];
+    bytemuck::cast_slice_mut(&mut words0)[..bit_plane_0.len()].copy_from_slice(bit_plane_0);
+    bytemuck::cast_slice_mut(&mut words1)[..bit_plane_1.len()].copy_from_slice(bit_plane_1);
+
+    let word_count = bit_plane_0.len().min(bit_plane_1.len()).div_ceil(4);
+    LogicState::from_bit_planes(&words0[..word_count], &words1[..word_count])
+}
+
+/// The state of a `width` bits wide net with all bits set.
+///
+/// Unlike [`LogicState::LOGIC_1`] the bits above `width` are clear, so arithmetic on it does not
+/// carry out of the net.
+fn all_ones(width: NonZeroU8) -> LogicState {
+    let mut words = vec![u32::MAX; (width.get() as usize).div_ceil(32)];
+    let remainder = width.get() % 32;
+    if remainder != 0 {
+        *words.last_mut().unwrap() = u32::MAX >> (32 - remainder);
+    }
+    LogicState::from_big_int(words).unwrap()
+}
+
+/// Adds an adder of `a`, `b` and the one bit wide `carry_in`.
+///
+/// gsim keeps the carry in the bits of the sum above `width`, where any adder it is fed into
+/// would carry it out again, so the sum is masked to its width.
+#[allow(clippy::too_many_arguments)]
+fn add_masked_adder(
+    builder: &mut SimulatorBuilder,
+    width: NonZeroU8,
+    a: WireId,
+    b: WireId,
+    carry_in: WireId,
+    sum: WireId,
+    carry_out: WireId,
+) -> ServerResult<ComponentId> {
+    let unmasked_sum = add_internal_wire(builder, width)?;
+    let adder = builder
+        .add_adder(a, b, carry_in, unmasked_sum, carry_out)
+        .map_err(component_error_to_server_error)?;
+    let mask = add_constant(builder, width, all_ones(width))?;
+    builder
+        .add_and_gate(&[unmasked_sum, mask], sum)
+        .map_err(component_error_to_server_error)?;
+    Ok(adder)
+}
+
+/// Checks that `net` has the `width` the client expects for it.
+fn check_width(builder: &SimulatorBuilder, net: WireId, width: NonZeroU8) -> ServerResult<()> {
+    let net_width = builder
+        .get_wire_width(net)
+        .map_err(|_| ServerError::InvalidNetId)?;
+    if width != net_width {
+        return Err(ServerError::WidthMismatch);
+    }
+    Ok(())
+}
+
+/// The wire an optional output of a cell drives, an internal one if it is not connected.
+fn output_or_internal(
+    builder: &mut SimulatorBuilder,
+    output: Option<WireId>,
+    width: NonZeroU8,
+) -> ServerResult<WireId> {
+    match output {
+        Some(output) => Ok(output),
+        None => add_internal_wire(builder, width),
+    }
+}
+
+/// Adds a register that takes on `next` on the rising edge of `clock` while `enable` is high.
+/// The reset is synchronous: while `reset` is high, the register is cleared on the rising edge
+/// of `clock` instead, regardless of `enable`.
+fn add_clocked(
+    builder: &mut SimulatorBuilder,
+    width: NonZeroU8,
+    next: WireId,
+    enable: Option<WireId>,
+    clock: WireId,
+    reset: Option<WireId>,
+    output: WireId,
+) -> ServerResult<ComponentId> {
+    let (data_in, enable) = match reset {
+        Some(reset) => {
+            let zero = add_constant(builder, width, LogicState::LOGIC_0)?;
+            let data_in = add_internal_wire(builder, width)?;
+            builder
+                .add_multiplexer(&[next, zero], reset, data_in)
+                .map_err(component_error_to_server_error)?;
+
+            // the reset does not depend on the register being enabled
+            let enable = match enable {
+                Some(enable) => {
+                    let enable_or_reset = add_internal_wire(builder, NonZeroU8::MIN)?;
+                    builder
+                        .add_or_gate(&[enable, reset], enable_or_reset)
+                        .map_err(component_error_to_server_error)?;
+                    Some(enable_or_reset)
+                }
+                None => None,
+            };
+            (data_in, enable)
+        }
+        None => (next, enable),
+    };
+
+    let enable = match enable {
+        Some(enable) => enable,
+        None => add_constant(builder, NonZeroU8::MIN, LogicState::LOGIC_1)?,
+    };
+    let register = builder
+        .add_register(data_in, output, enable, clock, ClockPolarity::Rising)
+        .map_err(component_error_to_server_error)?;
+
+    // registers start out cleared, otherwise the feedback of toggling flip-flops
+    // and counters would stay undefined until they are reset
+    if let Ok(ComponentData::RegisterValue(mut value)) = builder.get_component_data_mut(register) {
+        value.set_reset_value(LogicState::LOGIC_0);
+        value.reset();
+    }
+
+    Ok(register)
+}
+
+/// Replaces `next` with `data` while `load` is high, regardless of `enable`.
+/// Returns the new next state and enable.
+fn add_load(
+    builder: &mut SimulatorBuilder,
+    width: NonZeroU8,
+    next: WireId,
+    data: Option<WireId>,
+    load: Option<WireId>,
+    enable: Option<WireId>,
+) -> ServerResult<(WireId, Option<WireId>)> {
+    let (data, load) = match (data, load) {
+        (Some(data), Some(load)) => (data, load),
+        (None, Some(_)) => return Err(ServerError::InvalidInputCount),
+        (_, None) => return Ok((next, enable)),
+    };
+
+    let loaded = add_internal_wire(builder, width)?;
+    builder
+        .add_multiplexer(&[next, data], load, loaded)
+        .map_err(component_error_to_server_error)?;
+    let enable = match enable {
+        Some(enable) => {
+            let enable_or_load = add_internal_wire(builder, NonZeroU8::MIN)?;
+            builder
+                .add_or_gate(&[enable, load], enable_or_load)
+                .map_err(component_error_to_server_error)?;
+            Some(enable_or_load)
+        }
+        None => None,
+    };
+    Ok((loaded, enable))
+}
+
+/// The bit of `value` at `index`.
+fn add_bit(builder: &mut SimulatorBuilder, value: WireId, index: u8) -> ServerResult<WireId> {
+    let bit = add_internal_wire(builder, NonZeroU8::MIN)?;
+    builder
+        .add_slice(value, index, bit)
+        .map_err(component_error_to_server_error)?;
+    Ok(bit)
+}
+
+/// A part of a tap whose bits follow each other in the bus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct TapRun {
+    bus_offset: u8,
+    width: NonZeroU8,
+}
+
+/// Divides `tap` into the runs of bits that can be sliced at once, from its lowest bit.
+fn tap_runs(tap: &[u8]) -> Vec<TapRun> {
+    let mut runs = Vec::<TapRun>::new();
+    for &bit in tap {
+        match runs.last_mut() {
+            Some(run) if run.bus_offset.checked_add(run.width.get()) == Some(bit) => {
+                run.width = run.width.saturating_add(1);
+            }
+            _ => runs.push(TapRun {
+                bus_offset: bit,
+                width: NonZeroU8::MIN,
+            }),
+        }
+    }
+    runs
+}
+
+/// Drives `output` with the bits of `bus` listed by `tap`, the first one becoming the lowest bit.
+fn add_tap(
+    builder: &mut SimulatorBuilder,
+    bus: WireId,
+    tap: &[u8],
+    output: WireId,
+) -> ServerResult<ComponentId> {
+    let runs = tap_runs(tap);
+    if let [run] = runs.as_slice() {
+        return builder
+            .add_slice(bus, run.bus_offset, output)
+            .map_err(component_error_to_server_error);
+    }
+
+    let mut parts = Vec::with_capacity(runs.len());
+    for run in runs {
+        let part = add_internal_wire(builder, run.width)?;
+        builder
+            .add_slice(bus, run.bus_offset, part)
+            .map_err(component_error_to_server_error)?;
+        parts.push(part);
+    }
+    builder
+        .add_merge(&parts, output)
+        .map_err(component_error_to_server_error)
+}
+
+/// `value` shifted by one bit towards the lower bits with `serial_in` entering at the highest bit,
+/// or towards the higher bits with `serial_in` entering at the lowest bit if `left` is set.
+fn add_shift(
+    builder: &mut SimulatorBuilder,
+    width: NonZeroU8,
+    value: WireId,
+    serial_in: WireId,
+    left: bool,
+) -> ServerResult<WireId> {
+    let Some(kept_width) = NonZeroU8::new(width.get() - 1) else {
+        return Ok(serial_in);
+    };
+
+    let kept = add_internal_wire(builder, kept_width)?;
+    let shifted = add_internal_wire(builder, width)?;
+    let (offset, parts) = if left {
+        (0, [serial_in, kept])
+    } else {
+        (1, [kept, serial_in])
+    };
+    builder
+        .add_slice(value, offset, kept)
+        .and_then(|_| builder.add_merge(&parts, shifted))
+        .map_err(component_error_to_server_error)?;
+    Ok(shifted)
+}
+
+/// Merges the one bit wide `select` inputs into a single wire, the lowest bit first.
+fn add_select(builder: &mut SimulatorBuilder, select: &[WireId]) -> ServerResult<WireId> {
+    match *select {
+        [bit] => Ok(bit),
+        _ => {
+            let width = u8::try_from(select.len())
+                .ok()
+                .and_then(NonZeroU8::new)
+                .ok_or(ServerError::InvalidInputCount)?;
+            let merged = add_internal_wire(builder, width)?;
+            builder
+                .add_merge(select, merged)
+                .map_err(component_error_to_server_error)?;
+            Ok(merged)
+        }
+    }
+}
+
 macro_rules! gate_impl {
     ($name:ident) => {
         fn $name(
@@ -84,12 +459,7 @@ macro_rules! gate_impl {
         ) -> ServerResult<Self::CellId> {
             let builder = self.get_builder_mut(client_id)?;
 
-            let output_width = builder
-                .get_wire_width(output)
-                .map_err(|_| ServerError::InvalidNetId)?;
-            if width != output_width {
-                return Err(ServerError::WidthMismatch);
-            }
+            check_width(builder, output, width)?;
 
             builder
                 .$name(inputs, output)
@@ -112,11 +482,13 @@ impl SimServer for GsimServer {
 
     fn client_disconnected(&mut self, client_id: ClientId) {
         self.clients.remove(&client_id);
+        self.pulls.remove(&client_id);
     }
 
     fn begin_build(&mut self, client_id: ClientId) -> ServerResult<()> {
         let client_state = self.get_client_state_mut(client_id);
         *client_state = ClientState::default();
+        self.pulls.remove(&client_id);
         Ok(())
     }
 
@@ -154,43 +526,589 @@ impl SimServer for GsimServer {
     ) -> ServerResult<Self::CellId> {
         let builder = self.get_builder_mut(client_id)?;
 
-        let output_width = builder
-            .get_wire_width(output)
+        check_width(builder, output, width)?;
+
+        builder
+            .add_not_gate(input, output)
+            .map_err(component_error_to_server_error)
+    }
+
+    fn add_flip_flop(
+        &mut self,
+        client_id: ClientId,
+        kind: FlipFlopKind,
+        width: NonZeroU8,
+        inputs: &[Self::NetId],
+        clock: Self::NetId,
+        reset: Option<Self::NetId>,
+        output: Self::NetId,
+        inverted_output: Option<Self::NetId>,
+    ) -> ServerResult<Self::CellId> {
+        let builder = self.get_builder_mut(client_id)?;
+
+        check_width(builder, output, width)?;
+
+        // gsim only has plain registers, the next state of the other kinds
+        // is computed from their inputs and the current output with gates
+        let inverted = match (kind, inverted_output) {
+            (_, Some(inverted_output)) => Some(inverted_output),
+            (FlipFlopKind::Jk, None) => Some(add_internal_wire(builder, width)?),
+            (FlipFlopKind::D | FlipFlopKind::T, None) => None,
+        };
+        if let Some(inverted) = inverted {
+            builder
+                .add_not_gate(output, inverted)
+                .map_err(component_error_to_server_error)?;
+        }
+
+        let next = match (kind, inputs) {
+            (FlipFlopKind::D, &[data]) => data,
+            (FlipFlopKind::Jk, &[j, k]) => {
+                let inverted = inverted.unwrap();
+                let inverted_k = add_internal_wire(builder, width)?;
+                let set = add_internal_wire(builder, width)?;
+                let keep = add_internal_wire(builder, width)?;
+                let next = add_internal_wire(builder, width)?;
+                builder
+                    .add_not_gate(k, inverted_k)
+                    .and_then(|_| builder.add_and_gate(&[j, inverted], set))
+                    .and_then(|_| builder.add_and_gate(&[inverted_k, output], keep))
+                    .and_then(|_| builder.add_or_gate(&[set, keep], next))
+                    .map_err(component_error_to_server_error)?;
+                next
+            }
+            (FlipFlopKind::T, &[toggle]) => {
+                let next = add_internal_wire(builder, width)?;
+                builder
+                    .add_xor_gate(&[toggle, output], next)
+                    .map_err(component_error_to_server_error)?;
+                next
+            }
+            _ => return Err(ServerError::InvalidInputCount),
+        };
+
+        add_clocked(builder, width, next, None, clock, reset, output)
+    }
+
+    fn add_register(
+        &mut self,
+        client_id: ClientId,
+        width: NonZeroU8,
+        data: Self::NetId,
+        enable: Option<Self::NetId>,
+        clock: Self::NetId,
+        reset: Option<Self::NetId>,
+        output: Self::NetId,
+    ) -> ServerResult<Self::CellId> {
+        let builder = self.get_builder_mut(client_id)?;
+        check_width(builder, output, width)?;
+
+        add_clocked(builder, width, data, enable, clock, reset, output)
+    }
+
+    fn add_counter(
+        &mut self,
+        client_id: ClientId,
+        width: NonZeroU8,
+        data: Option<Self::NetId>,
+        load: Option<Self::NetId>,
+        enable: Option<Self::NetId>,
+        up: Option<Self::NetId>,
+        clock: Self::NetId,
+        reset: Option<Self::NetId>,
+        output: Self::NetId,
+        carry_out: Option<Self::NetId>,
+    ) -> ServerResult<Self::CellId> {
+        let builder = self.get_builder_mut(client_id)?;
+        check_width(builder, output, width)?;
+
+        // counting down adds all ones, whose carry is low only when wrapping around from zero
+        let one = add_constant(builder, width, LogicState::from_int(1))?;
+        let step = match up {
+            Some(up) => {
+                let minus_one = add_constant(builder, width, all_ones(width))?;
+                let step = add_internal_wire(builder, width)?;
+                builder
+                    .add_multiplexer(&[minus_one, one], up, step)
+                    .map_err(component_error_to_server_error)?;
+                step
+            }
+            None => one,
+        };
+        let zero = add_constant(builder, NonZeroU8::MIN, LogicState::LOGIC_0)?;
+        let count = add_internal_wire(builder, width)?;
+        let carry = match (up, carry_out) {
+            (None, Some(carry_out)) => carry_out,
+            _ => add_internal_wire(builder, NonZeroU8::MIN)?,
+        };
+        add_masked_adder(builder, width, output, step, zero, count, carry)?;
+        if let (Some(up), Some(carry_out)) = (up, carry_out) {
+            builder
+                .add_xnor_gate(&[carry, up], carry_out)
+                .map_err(component_error_to_server_error)?;
+        }
+
+        let (next, enable) = add_load(builder, width, count, data, load, enable)?;
+        add_clocked(builder, width, next, enable, clock, reset, output)
+    }
+
+    fn add_shift_register(
+        &mut self,
+        client_id: ClientId,
+        width: NonZeroU8,
+        serial_in: Option<Self::NetId>,
+        data: Option<Self::NetId>,
+        load: Option<Self::NetId>,
+        enable: Option<Self::NetId>,
+        left: Option<Self::NetId>,
+        clock: Self::NetId,
+        reset: Option<Self::NetId>,
+        output: Option<Self::NetId>,
+        serial_out: Option<Self::NetId>,
+    ) -> ServerResult<Self::CellId> {
+        let builder = self.get_builder_mut(client_id)?;
+        if let Some(output) = output {
+            check_width(builder, output, width)?;
+        }
+
+        let output = output_or_internal(builder, output, width)?;
+        let serial_in = match serial_in {
+            Some(serial_in) => serial_in,
+            None => add_constant(builder, NonZeroU8::MIN, LogicState::LOGIC_0)?,
+        };
+        let right_shifted = add_shift(builder, width, output, serial_in, false)?;
+        let serial_out = output_or_internal(builder, serial_out, NonZeroU8::MIN)?;
+
+        let next = match left {
+            Some(left) => {
+                let left_shifted = add_shift(builder, width, output, serial_in, true)?;
+                let lowest_bit = add_bit(builder, output, 0)?;
+                let highest_bit = add_bit(builder, output, width.get() - 1)?;
+                let next = add_internal_wire(builder, width)?;
+                builder
+                    .add_multiplexer(&[right_shifted, left_shifted], left, next)
+                    .and_then(|_| {
+                        builder.add_multiplexer(&[lowest_bit, highest_bit], left, serial_out)
+                    })
+                    .map_err(component_error_to_server_error)?;
+                next
+            }
+            None => {
+                builder
+                    .add_slice(output, 0, serial_out)
+                    .map_err(component_error_to_server_error)?;
+                right_shifted
+            }
+        };
+
+        let (next, enable) = add_load(builder, width, next, data, load, enable)?;
+        add_clocked(builder, width, next, enable, clock, reset, output)
+    }
+
+    fn add_mux(
+        &mut self,
+        client_id: ClientId,
+        width: NonZeroU8,
+        inputs: &[Self::NetId],
+        select: &[Self::NetId],
+        output: Self::NetId,
+    ) -> ServerResult<Self::CellId> {
+        let builder = self.get_builder_mut(client_id)?;
+
+        check_width(builder, output, width)?;
+
+        let select = add_select(builder, select)?;
+        builder
+            .add_multiplexer(inputs, select, output)
+            .map_err(component_error_to_server_error)
+    }
+
+    fn add_demux(
+        &mut self,
+        client_id: ClientId,
+        width: NonZeroU8,
+        input: Self::NetId,
+        select: &[Self::NetId],
+        outputs: &[Option<Self::NetId>],
+    ) -> ServerResult<Self::CellId> {
+        let builder = self.get_builder_mut(client_id)?;
+
+        check_width(builder, input, width)?;
+
+        // gsim has no demultiplexer, each output is the input multiplexed
+        // with zero by whether the select inputs equal its index
+        let zero = add_constant(builder, width, LogicState::LOGIC_0)?;
+        let select = add_select(builder, select)?;
+        let select_width = builder
+            .get_wire_width(select)
             .map_err(|_| ServerError::InvalidNetId)?;
-        if width != output_width {
-            return Err(ServerError::WidthMismatch);
+
+        let mut cell = None;
+        for (index, &output) in outputs.iter().enumerate() {
+            // unconnected outputs are still driven, so there is a cell even if none are connected
+            let output = match output {
+                Some(output) => output,
+                None => add_internal_wire(builder, width)?,
+            };
+
+            let index = add_constant(builder, select_width, LogicState::from_int(index as u32))?;
+            let selected = add_internal_wire(builder, NonZeroU8::MIN)?;
+            builder
+                .add_compare_equal(select, index, selected)
+                .map_err(component_error_to_server_error)?;
+            let id = builder
+                .add_multiplexer(&[zero, input], selected, output)
+                .map_err(component_error_to_server_error)?;
+            cell.get_or_insert(id);
         }
 
+        cell.ok_or(ServerError::InvalidInputCount)
+    }
+
+    fn add_adder(
+        &mut self,
+        client_id: ClientId,
+        width: NonZeroU8,
+        a: Self::NetId,
+        b: Self::NetId,
+        carry_in: Option<Self::NetId>,
+        sum: Option<Self::NetId>,
+        carry_out: Option<Self::NetId>,
+    ) -> ServerResult<Self::CellId> {
+        let builder = self.get_builder_mut(client_id)?;
+        check_width(builder, a, width)?;
+
+        let carry_in = match carry_in {
+            Some(carry_in) => carry_in,
+            None => add_constant(builder, NonZeroU8::MIN, LogicState::LOGIC_0)?,
+        };
+        let sum = output_or_internal(builder, sum, width)?;
+        let carry_out = output_or_internal(builder, carry_out, NonZeroU8::MIN)?;
+        add_masked_adder(builder, width, a, b, carry_in, sum, carry_out)
+    }
+
+    fn add_subtractor(
+        &mut self,
+        client_id: ClientId,
+        width: NonZeroU8,
+        a: Self::NetId,
+        b: Self::NetId,
+        borrow_in: Option<Self::NetId>,
+        difference: Option<Self::NetId>,
+        borrow_out: Option<Self::NetId>,
+    ) -> ServerResult<Self::CellId> {
+        let builder = self.get_builder_mut(client_id)?;
+        check_width(builder, a, width)?;
+
+        // a - b - borrow is computed as a + !b + !borrow, whose carry is the inverted borrow.
+        // b is inverted against a mask since a NOT gate sets the bits above the width as well.
+        let mask = add_constant(builder, width, all_ones(width))?;
+        let inverted_b = add_internal_wire(builder, width)?;
         builder
-            .add_not_gate(input, output)
+            .add_xor_gate(&[b, mask], inverted_b)
+            .map_err(component_error_to_server_error)?;
+        let carry_in = match borrow_in {
+            Some(borrow_in) => {
+                let carry_in = add_internal_wire(builder, NonZeroU8::MIN)?;
+                builder
+                    .add_not_gate(borrow_in, carry_in)
+                    .map_err(component_error_to_server_error)?;
+                carry_in
+            }
+            None => add_constant(builder, NonZeroU8::MIN, LogicState::LOGIC_1)?,
+        };
+        let difference = output_or_internal(builder, difference, width)?;
+        let carry_out = add_internal_wire(builder, NonZeroU8::MIN)?;
+        let adder = add_masked_adder(
+            builder, width, a, inverted_b, carry_in, difference, carry_out,
+        )?;
+        if let Some(borrow_out) = borrow_out {
+            builder
+                .add_not_gate(carry_out, borrow_out)
+                .map_err(component_error_to_server_error)?;
+        }
+
+        Ok(adder)
+    }
+
+    fn add_comparator(
+        &mut self,
+        client_id: ClientId,
+        width: NonZeroU8,
+        a: Self::NetId,
+        b: Self::NetId,
+        less: Option<Self::NetId>,
+        equal: Option<Self::NetId>,
+        greater: Option<Self::NetId>,
+    ) -> ServerResult<Self::CellId> {
+        let builder = self.get_builder_mut(client_id)?;
+        check_width(builder, a, width)?;
+
+        // the equality output is always built, so there is a cell even if nothing is connected
+        let equal = output_or_internal(builder, equal, NonZeroU8::MIN)?;
+        let cell = builder
+            .add_compare_equal(a, b, equal)
+            .map_err(component_error_to_server_error)?;
+        if let Some(less) = less {
+            builder
+                .add_compare_less_than(a, b, less)
+                .map_err(component_error_to_server_error)?;
+        }
+        if let Some(greater) = greater {
+            builder
+                .add_compare_greater_than(a, b, greater)
+                .map_err(component_error_to_server_error)?;
+        }
+
+        Ok(cell)
+    }
+
+    fn add_split(
+        &mut self,
+        client_id: ClientId,
+        width: NonZeroU8,
+        input: Self::NetId,
+        taps: &[Vec<u8>],
+        outputs: &[Self::NetId],
+    ) -> ServerResult<Self::CellId> {
+        let builder = self.get_builder_mut(client_id)?;
+        check_width(builder, input, width)?;
+        if taps.iter().flatten().any(|&bit| bit >= width.get()) {
+            return Err(ServerError::OutOfRange);
+        }
+
+        let mut cell = None;
+        for (tap, &output) in taps.iter().zip(outputs) {
+            let tap_cell = add_tap(builder, input, tap, output)?;
+            cell.get_or_insert(tap_cell);
+        }
+        cell.ok_or(ServerError::InvalidInputCount)
+    }
+
+    fn add_merge(
+        &mut self,
+        client_id: ClientId,
+        width: NonZeroU8,
+        inputs: &[Self::NetId],
+        taps: &[Vec<u8>],
+        output: Self::NetId,
+    ) -> ServerResult<Self::CellId> {
+        let builder = self.get_builder_mut(client_id)?;
+        check_width(builder, output, width)?;
+
+        // the tap and bit driving each bit of the output, in the same runs a split would slice
+        let mut sources = vec![None; width.get() as usize];
+        for (index, tap) in taps.iter().enumerate() {
+            for (tap_bit, &bit) in tap.iter().enumerate() {
+                let source = sources
+                    .get_mut(bit as usize)
+                    .ok_or(ServerError::OutOfRange)?;
+                *source = Some((index, tap_bit as u8));
+            }
+        }
+
+        let mut parts = Vec::new();
+        let mut bit = 0;
+        while bit < sources.len() {
+            let source = sources[bit];
+            let mut run_width = 1;
+            while sources.get(bit + run_width).is_some_and(|&next| {
+                next == source.map(|(index, tap_bit)| (index, tap_bit + run_width as u8))
+            }) {
+                run_width += 1;
+            }
+
+            let run_width = NonZeroU8::new(run_width as u8).unwrap();
+            let part = match source {
+                Some((index, tap_bit)) => {
+                    let part = add_internal_wire(builder, run_width)?;
+                    builder
+                        .add_slice(inputs[index], tap_bit, part)
+                        .map_err(component_error_to_server_error)?;
+                    part
+                }
+                None => add_constant(builder, run_width, LogicState::HIGH_Z)?,
+            };
+            parts.push(part);
+            bit += run_width.get() as usize;
+        }
+
+        builder
+            .add_merge(&parts, output)
             .map_err(component_error_to_server_error)
     }
 
-    fn set_net_drive(
+    fn add_constant(
         &mut self,
         client_id: ClientId,
-        net: Self::NetId,
+        width: NonZeroU8,
         bit_plane_0: &[u8],
         bit_plane_1: &[u8],
+        output: Self::NetId,
+    ) -> ServerResult<Self::CellId> {
+        let builder = self.get_builder_mut(client_id)?;
+        check_width(builder, output, width)?;
+
+        // drives are not components, so the value is passed through an always enabled buffer
+        let value = add_constant(
+            builder,
+            width,
+            state_from_bit_planes(bit_plane_0, bit_plane_1),
+        )?;
+        let enable = add_constant(builder, NonZeroU8::MIN, LogicState::LOGIC_1)?;
+        builder
+            .add_buffer(value, enable, output)
+            .map_err(component_error_to_server_error)
+    }
+
+    fn add_buffer(
+        &mut self,
+        client_id: ClientId,
+        width: NonZeroU8,
+        input: Self::NetId,
+        enable: Self::NetId,
+        output: Self::NetId,
+    ) -> ServerResult<Self::CellId> {
+        let builder = self.get_builder_mut(client_id)?;
+        check_width(builder, input, width)?;
+        builder
+            .add_buffer(input, enable, output)
+            .map_err(component_error_to_server_error)
+    }
+
+    fn add_open_drain(
+        &mut self,
+        client_id: ClientId,
+        width: NonZeroU8,
+        input: Self::NetId,
+        output: Self::NetId,
+    ) -> ServerResult<Self::CellId> {
+        let builder = self.get_builder_mut(client_id)?;
+        check_width(builder, input, width)?;
+
+        // buffers have a single enable, so each bit gets a buffer of a low bit enabled by its inverse
+        let zero = add_constant(builder, NonZeroU8::MIN, LogicState::LOGIC_0)?;
+        let mut cell = None;
+        let mut parts = Vec::with_capacity(width.get() as usize);
+        for bit in 0..width.get() {
+            let input_bit = add_internal_wire(builder, NonZeroU8::MIN)?;
+            builder
+                .add_slice(input, bit, input_bit)
+                .map_err(component_error_to_server_error)?;
+            let enable = add_internal_wire(builder, NonZeroU8::MIN)?;
+            builder
+                .add_not_gate(input_bit, enable)
+                .map_err(component_error_to_server_error)?;
+
+            let part = if width == NonZeroU8::MIN {
+                output
+            } else {
+                add_internal_wire(builder, NonZeroU8::MIN)?
+            };
+            let buffer = builder
+                .add_buffer(zero, enable, part)
+                .map_err(component_error_to_server_error)?;
+            cell.get_or_insert(buffer);
+            parts.push(part);
+        }
+
+        if width > NonZeroU8::MIN {
+            builder
+                .add_merge(&parts, output)
+                .map_err(component_error_to_server_error)?;
+        }
+        Ok(cell.unwrap())
+    }
+
+    fn add_pull(
+        &mut self,
+        client_id: ClientId,
+        width: NonZeroU8,
+        net: Self::NetId,
+        up: bool,
     ) -> ServerResult<()> {
-        let simulator = self.get_simulator_mut(client_id)?;
+        let builder = self.get_builder_mut(client_id)?;
+        check_width(builder, net, width)?;
 
-        let mut words0 = [0u32; 8];
-        let mut words1 = [0u32; 8];
-        bytemuck::cast_slice_mut(&mut words0)[..bit_plane_0.len()].copy_from_slice(bit_plane_0);
-        bytemuck::cast_slice_mut(&mut words1)[..bit_plane_1.len()].copy_from_slice(bit_plane_1);
+        let pulls = self.pulls.entry(client_id).or_default();
+        match pulls.iter_mut().find(|pull| pull.net == net) {
+            // a net pulled both ways is pulled to an undefined state
+            Some(pull) if pull.level.0 != up => pull.level = (true, false),
+            Some(_) => {}
+            None => pulls.push(Pull::new(net, width, up)),
+        }
+        Ok(())
+    }
 
-        let word_count = bit_plane_0.len().min(bit_plane_1.len()).div_ceil(4);
-        let new_drive = LogicState::from_bit_planes(&words0[..word_count], &words1[..word_count]);
+    fn set_net_drive(
+        &mut self,
+        client_id: ClientId,
+        net: Self::NetId,
+        bit_plane_0: &[u8],
+        bit_plane_1: &[u8],
+    ) -> ServerResult<()> {
+        let mut new_drive = state_from_bit_planes(bit_plane_0, bit_plane_1);
+        let pull = self
+            .pulls
+            .get_mut(&client_id)
+            .and_then(|pulls| pulls.iter_mut().find(|pull| pull.net == net));
+        if let Some(pull) = pull {
+            pull.set_drive(&new_drive);
+            new_drive = pull.net_drive();
+        }
 
+        let simulator = self.get_simulator_mut(client_id)?;
         simulator
             .set_wire_drive(net, &new_drive)
             .map_err(|_| ServerError::InvalidNetId)
     }
 
     fn eval(&mut self, client_id: ClientId, max_steps: u64) -> ServerResult<()> {
-        let simulator = self.get_simulator_mut(client_id)?;
-        simulation_result_to_server_result(simulator.run_sim(max_steps))
+        let simulator = match self.clients.get_mut(&client_id).expect("invalid client ID") {
+            ClientState::Building(_) => return Err(ServerError::InvalidState),
+            ClientState::Simulating(simulator) => simulator,
+        };
+        let Some(pulls) = self.pulls.get_mut(&client_id) else {
+            return simulation_result_to_server_result(simulator.run_sim(max_steps));
+        };
+
+        for _ in 0..MAX_PULL_ROUNDS {
+            let mut changed = Vec::new();
+            match simulator.run_sim(max_steps) {
+                SimulationRunResult::Ok => {
+                    for (index, pull) in pulls.iter_mut().enumerate() {
+                        let state = simulator.get_wire_state(pull.net).unwrap();
+                        if pull.pull(&state) {
+                            changed.push(index);
+                        }
+                    }
+                    if changed.is_empty() {
+                        return Ok(());
+                    }
+                }
+                SimulationRunResult::MaxStepsReached => return Err(ServerError::MaxStepsReached),
+                SimulationRunResult::Err(errors) => {
+                    for (index, pull) in pulls.iter_mut().enumerate() {
+                        if errors.conflicts.contains(&pull.net) && pull.release() {
+                            changed.push(index);
+                        }
+                    }
+                    // the conflict is between strong drivers
+                    if changed.is_empty() {
+                        return Err(ServerError::DriverConflict);
+                    }
+                }
+            }
+
+            for index in changed {
+                let pull = &pulls[index];
+                simulator
+                    .set_wire_drive(pull.net, &pull.net_drive())
+                    .map_err(|_| ServerError::InvalidNetId)?;
+            }
+        }
+
+        Err(ServerError::MaxStepsReached)
     }
 
     fn get_net_state(
@@ -212,3 +1130,512 @@ impl SimServer for GsimServer {
         Ok((bit_width, &self.bit_plane_0, &self.bit_plane_1))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLIENT: ClientId = ClientId::from_raw(1);
+
+    struct Harness {
+        server: GsimServer,
+    }
+
+    impl Harness {
+        fn new() -> Self {
+            let mut server = GsimServer::default();
+            server.client_connected(CLIENT);
+            server.begin_build(CLIENT).unwrap();
+            Self { server }
+        }
+
+        fn net(&mut self) -> WireId {
+            self.server.add_net(CLIENT, NonZeroU8::MIN).unwrap()
+        }
+
+        fn bus(&mut self, width: u8) -> WireId {
+            let width = NonZeroU8::new(width).unwrap();
+            self.server.add_net(CLIENT, width).unwrap()
+        }
+
+        /// Ends building, with `nets` driven low.
+        fn finish(&mut self, nets: &[WireId]) {
+            self.server.end_build(CLIENT).unwrap();
+            for &net in nets {
+                self.drive(net, false);
+            }
+        }
+
+        fn add_flip_flop(&mut self, kind: FlipFlopKind, inputs: &[WireId]) -> [WireId; 4] {
+            let [clock, reset, q, qn] = [self.net(), self.net(), self.net(), self.net()];
+            self.server
+                .add_flip_flop(
+                    CLIENT,
+                    kind,
+                    NonZeroU8::MIN,
+                    inputs,
+                    clock,
+                    Some(reset),
+                    q,
+                    Some(qn),
+                )
+                .unwrap();
+            self.finish(&[clock, reset]);
+            [clock, reset, q, qn]
+        }
+
+        fn drive(&mut self, net: WireId, value: bool) {
+            let simulator = self.server.get_simulator_mut(CLIENT).unwrap();
+            simulator
+                .set_wire_drive(net, &LogicState::from_bool(value))
+                .unwrap();
+            self.server.eval(CLIENT, 1000).unwrap();
+        }
+
+        fn tick(&mut self, clock: WireId) {
+            self.drive(clock, true);
+            self.drive(clock, false);
+        }
+
+        fn drive_int(&mut self, net: WireId, value: u32) {
+            let simulator = self.server.get_simulator_mut(CLIENT).unwrap();
+            simulator
+                .set_wire_drive(net, &LogicState::from_int(value))
+                .unwrap();
+            self.server.eval(CLIENT, 1000).unwrap();
+        }
+
+        fn state(&self, net: WireId) -> Option<bool> {
+            let simulator = self.server.get_simulator(CLIENT).unwrap();
+            simulator.get_wire_state(net).unwrap().to_bool().ok()
+        }
+
+        fn int_state(&self, net: WireId) -> Option<u32> {
+            let simulator = self.server.get_simulator(CLIENT).unwrap();
+            let width = simulator.get_wire_width(net).unwrap();
+            simulator.get_wire_state(net).unwrap().to_int(width).ok()
+        }
+    }
+
+    #[test]
+    fn d_flip_flop() {
+        let mut harness = Harness::new();
+        let d = harness.net();
+        let [clock, reset, q, qn] = harness.add_flip_flop(FlipFlopKind::D, &[d]);
+        assert_eq!(harness.state(q), Some(false));
+        assert_eq!(harness.state(qn), Some(true));
+
+        // the output only follows the input on the rising edge of the clock
+        harness.drive(d, true);
+        assert_eq!(harness.state(q), Some(false));
+        harness.drive(clock, true);
+        assert_eq!(harness.state(q), Some(true));
+        assert_eq!(harness.state(qn), Some(false));
+        harness.drive(d, false);
+        harness.drive(clock, false);
+        assert_eq!(harness.state(q), Some(true));
+        harness.tick(clock);
+        assert_eq!(harness.state(q), Some(false));
+
+        // the reset is synchronous
+        harness.drive(d, true);
+        harness.drive(reset, true);
+        assert_eq!(harness.state(q), Some(false));
+        harness.tick(clock);
+        assert_eq!(harness.state(q), Some(false));
+        harness.drive(reset, false);
+        harness.tick(clock);
+        assert_eq!(harness.state(q), Some(true));
+    }
+
+    #[test]
+    fn jk_flip_flop() {
+        let mut harness = Harness::new();
+        let [j, k] = [harness.net(), harness.net()];
+        let [clock, _, q, qn] = harness.add_flip_flop(FlipFlopKind::Jk, &[j, k]);
+
+        let mut expected = false;
+        for (j_value, k_value) in [
+            (true, false),
+            (false, false),
+            (false, true),
+            (true, true),
+            (true, true),
+            (false, false),
+        ] {
+            harness.drive(j, j_value);
+            harness.drive(k, k_value);
+            harness.tick(clock);
+            expected = match (j_value, k_value) {
+                (false, false) => expected,
+                (true, false) => true,
+                (false, true) => false,
+                (true, true) => !expected,
+            };
+            assert_eq!(harness.state(q), Some(expected));
+            assert_eq!(harness.state(qn), Some(!expected));
+        }
+    }
+
+    #[test]
+    fn t_flip_flop() {
+        let mut harness = Harness::new();
+        let t = harness.net();
+        let [clock, reset, q, _] = harness.add_flip_flop(FlipFlopKind::T, &[t]);
+
+        harness.drive(t, true);
+        for expected in [true, false, true] {
+            harness.tick(clock);
+            assert_eq!(harness.state(q), Some(expected));
+        }
+        harness.drive(t, false);
+        harness.tick(clock);
+        assert_eq!(harness.state(q), Some(true));
+
+        harness.drive(reset, true);
+        harness.tick(clock);
+        assert_eq!(harness.state(q), Some(false));
+    }
+
+    #[test]
+    fn mux_and_demux() {
+        let mut harness = Harness::new();
+        let inputs = [harness.net(), harness.net(), harness.net(), harness.net()];
+        let select = [harness.net(), harness.net()];
+        let output = harness.net();
+        let outputs = [harness.net(), harness.net(), harness.net()];
+        harness
+            .server
+            .add_mux(CLIENT, NonZeroU8::MIN, &inputs, &select, output)
+            .unwrap();
+        harness
+            .server
+            .add_demux(
+                CLIENT,
+                NonZeroU8::MIN,
+                output,
+                &select,
+                &[Some(outputs[0]), Some(outputs[1]), None, Some(outputs[2])],
+            )
+            .unwrap();
+        harness.finish(&[&inputs[..], &select[..]].concat());
+
+        for (index, &input) in inputs.iter().enumerate() {
+            harness.drive(select[0], index & 1 != 0);
+            harness.drive(select[1], index & 2 != 0);
+            for value in [true, false] {
+                harness.drive(input, value);
+                assert_eq!(harness.state(output), Some(value));
+
+                // the demultiplexer routes the value back to the output with the same index
+                for (output_index, &output) in [0, 1, 3].iter().zip(&outputs) {
+                    assert_eq!(harness.state(output), Some(value && *output_index == index));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn arithmetic() {
+        const WIDTH: NonZeroU8 = NonZeroU8::new(4).unwrap();
+
+        let mut harness = Harness::new();
+        let [a, b, sum, difference] = [(); 4].map(|_| harness.bus(4));
+        let [carry_in, carry_out, borrow_out, less, equal, greater] =
+            [(); 6].map(|_| harness.net());
+        let server = &mut harness.server;
+        server
+            .add_adder(
+                CLIENT,
+                WIDTH,
+                a,
+                b,
+                Some(carry_in),
+                Some(sum),
+                Some(carry_out),
+            )
+            .unwrap();
+        server
+            .add_subtractor(
+                CLIENT,
+                WIDTH,
+                a,
+                b,
+                None,
+                Some(difference),
+                Some(borrow_out),
+            )
+            .unwrap();
+        server
+            .add_comparator(CLIENT, WIDTH, a, b, Some(less), Some(equal), Some(greater))
+            .unwrap();
+        harness.finish(&[carry_in]);
+
+        for (a_value, b_value) in [(0, 0), (3, 5), (9, 9), (15, 1), (7, 12)] {
+            harness.drive_int(a, a_value);
+            harness.drive_int(b, b_value);
+            for carry in [false, true] {
+                harness.drive(carry_in, carry);
+                let total = a_value + b_value + u32::from(carry);
+                assert_eq!(harness.int_state(sum), Some(total & 0xF));
+                assert_eq!(harness.state(carry_out), Some(total > 0xF));
+            }
+
+            assert_eq!(
+                harness.int_state(difference),
+                Some(a_value.wrapping_sub(b_value) & 0xF)
+            );
+            assert_eq!(harness.state(borrow_out), Some(a_value < b_value));
+            assert_eq!(harness.state(less), Some(a_value < b_value));
+            assert_eq!(harness.state(equal), Some(a_value == b_value));
+            assert_eq!(harness.state(greater), Some(a_value > b_value));
+        }
+    }
+
+    #[test]
+    fn register_and_counter() {
+        const WIDTH: NonZeroU8 = NonZeroU8::new(4).unwrap();
+
+        let mut harness = Harness::new();
+        let [data, register_output, counter_output] = [(); 3].map(|_| harness.bus(4));
+        let [clock, reset, enable, load, up, carry_out] = [(); 6].map(|_| harness.net());
+        let server = &mut harness.server;
+        server
+            .add_register(
+                CLIENT,
+                WIDTH,
+                data,
+                Some(enable),
+                clock,
+                Some(reset),
+                register_output,
+            )
+            .unwrap();
+        server
+            .add_counter(
+                CLIENT,
+                WIDTH,
+                Some(data),
+                Some(load),
+                Some(enable),
+                Some(up),
+                clock,
+                Some(reset),
+                counter_output,
+                Some(carry_out),
+            )
+            .unwrap();
+        harness.finish(&[clock, reset, enable, load, up]);
+        assert_eq!(harness.int_state(register_output), Some(0));
+        assert_eq!(harness.int_state(counter_output), Some(0));
+
+        // neither changes while disabled
+        harness.drive_int(data, 9);
+        harness.tick(clock);
+        assert_eq!(harness.int_state(register_output), Some(0));
+        assert_eq!(harness.int_state(counter_output), Some(0));
+
+        // the counter wraps around in both directions, with the carry at the last value before
+        harness.drive(enable, true);
+        assert_eq!(harness.state(carry_out), Some(true));
+        harness.tick(clock);
+        assert_eq!(harness.int_state(register_output), Some(9));
+        assert_eq!(harness.int_state(counter_output), Some(15));
+        harness.drive(up, true);
+        assert_eq!(harness.state(carry_out), Some(true));
+        harness.tick(clock);
+        assert_eq!(harness.int_state(counter_output), Some(0));
+        harness.tick(clock);
+        assert_eq!(harness.int_state(counter_output), Some(1));
+        assert_eq!(harness.state(carry_out), Some(false));
+
+        // loading and resetting work without being enabled
+        harness.drive(enable, false);
+        harness.drive(load, true);
+        harness.tick(clock);
+        assert_eq!(harness.int_state(counter_output), Some(9));
+        harness.drive(reset, true);
+        harness.tick(clock);
+        assert_eq!(harness.int_state(register_output), Some(0));
+        assert_eq!(harness.int_state(counter_output), Some(0));
+    }
+
+    #[test]
+    fn shift_register() {
+        const WIDTH: NonZeroU8 = NonZeroU8::new(4).unwrap();
+
+        let mut harness = Harness::new();
+        let [data, output] = [(); 2].map(|_| harness.bus(4));
+        let [serial_in, load, left, clock, serial_out] = [(); 5].map(|_| harness.net());
+        harness
+            .server
+            .add_shift_register(
+                CLIENT,
+                WIDTH,
+                Some(serial_in),
+                Some(data),
+                Some(load),
+                None,
+                Some(left),
+                clock,
+                None,
+                Some(output),
+                Some(serial_out),
+            )
+            .unwrap();
+        harness.finish(&[serial_in, load, left, clock]);
+
+        // bits enter at the top and leave at the bottom while shifting right
+        harness.drive(serial_in, true);
+        harness.tick(clock);
+        assert_eq!(harness.int_state(output), Some(0b1000));
+        harness.drive(serial_in, false);
+        for expected in [0b0100, 0b0010, 0b0001] {
+            harness.tick(clock);
+            assert_eq!(harness.int_state(output), Some(expected));
+        }
+        assert_eq!(harness.state(serial_out), Some(true));
+
+        // and the other way around while shifting left
+        harness.drive(left, true);
+        assert_eq!(harness.state(serial_out), Some(false));
+        harness.drive(serial_in, true);
+        harness.tick(clock);
+        assert_eq!(harness.int_state(output), Some(0b0011));
+        harness.drive(serial_in, false);
+        harness.tick(clock);
+        harness.tick(clock);
+        assert_eq!(harness.int_state(output), Some(0b1100));
+        assert_eq!(harness.state(serial_out), Some(true));
+
+        harness.drive_int(data, 0b0110);
+        harness.drive(load, true);
+        harness.tick(clock);
+        assert_eq!(harness.int_state(output), Some(0b0110));
+    }
+    #[test]
+    fn split_and_merge() {
+        const WIDTH: NonZeroU8 = NonZeroU8::new(8).unwrap();
+
+        let mut harness = Harness::new();
+        let [bus, merged] = [(); 2].map(|_| harness.bus(8));
+        let [low, high, odd] = [(); 3].map(|_| harness.bus(4));
+        let taps = [vec![0, 1, 2, 3], vec![7, 6, 5, 4], vec![1, 3, 5, 7]];
+        harness
+            .server
+            .add_split(CLIENT, WIDTH, bus, &taps, &[low, high, odd])
+            .unwrap();
+        harness
+            .server
+            .add_merge(CLIENT, WIDTH, &[low, high], &taps[..2], merged)
+            .unwrap();
+        harness.finish(&[]);
+
+        harness.drive_int(bus, 0b1100_1010);
+        assert_eq!(harness.int_state(low), Some(0b1010));
+        assert_eq!(harness.int_state(high), Some(0b0011));
+        assert_eq!(harness.int_state(odd), Some(0b1011));
+        assert_eq!(harness.int_state(merged), Some(0b1100_1010));
+
+        assert_eq!(
+            tap_runs(&[1, 2, 3, 0]),
+            [
+                TapRun {
+                    bus_offset: 1,
+                    width: NonZeroU8::new(3).unwrap(),
+                },
+                TapRun {
+                    bus_offset: 0,
+                    width: NonZeroU8::MIN,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn constant() {
+        const WIDTH: NonZeroU8 = NonZeroU8::new(12).unwrap();
+
+        let mut harness = Harness::new();
+        let [output, undefined] = [(); 2].map(|_| harness.bus(12));
+        harness
+            .server
+            .add_constant(CLIENT, WIDTH, &[0xD2, 0x04], &[0xFF, 0x0F], output)
+            .unwrap();
+        harness
+            .server
+            .add_constant(CLIENT, WIDTH, &[0xFF, 0x0F], &[0x00, 0x00], undefined)
+            .unwrap();
+        harness.finish(&[]);
+        harness.server.eval(CLIENT, 1000).unwrap();
+
+        assert_eq!(harness.int_state(output), Some(1234));
+        assert_eq!(harness.int_state(undefined), None);
+    }
+    #[test]
+    fn pull() {
+        let mut harness = Harness::new();
+        let [enable, bus] = [harness.net(), harness.net()];
+        let builder = harness.server.get_builder_mut(CLIENT).unwrap();
+        let low = add_constant(builder, NonZeroU8::MIN, LogicState::LOGIC_0).unwrap();
+        builder.add_buffer(low, enable, bus).unwrap();
+        harness
+            .server
+            .add_pull(CLIENT, NonZeroU8::MIN, bus, true)
+            .unwrap();
+        harness.finish(&[enable]);
+        assert_eq!(harness.state(bus), Some(true));
+
+        harness.drive(enable, true);
+        assert_eq!(harness.state(bus), Some(false));
+        harness.drive(enable, false);
+        assert_eq!(harness.state(bus), Some(true));
+
+        harness
+            .server
+            .set_net_drive(CLIENT, bus, &[0], &[1])
+            .unwrap();
+        harness.server.eval(CLIENT, 1000).unwrap();
+        assert_eq!(harness.state(bus), Some(false));
+        harness
+            .server
+            .set_net_drive(CLIENT, bus, &[0], &[0])
+            .unwrap();
+        harness.server.eval(CLIENT, 1000).unwrap();
+        assert_eq!(harness.state(bus), Some(true));
+    }
+
+    #[test]
+    fn buffer_and_open_drain() {
+        const WIDTH: NonZeroU8 = NonZeroU8::new(2).unwrap();
+
+        let mut harness = Harness::new();
+        let [input, enable, output] = [(); 3].map(|_| harness.net());
+        let [drain_input, drain_output] = [(); 2].map(|_| harness.bus(2));
+        harness
+            .server
+            .add_buffer(CLIENT, NonZeroU8::MIN, input, enable, output)
+            .unwrap();
+        harness
+            .server
+            .add_open_drain(CLIENT, WIDTH, drain_input, drain_output)
+            .unwrap();
+        harness
+            .server
+            .add_pull(CLIENT, WIDTH, drain_output, true)
+            .unwrap();
+        harness.finish(&[input, enable]);
+
+        assert_eq!(harness.state(output), None);
+        harness.drive(input, true);
+        harness.drive(enable, true);
+        assert_eq!(harness.state(output), Some(true));
+
+        harness.drive_int(drain_input, 0b01);
+        assert_eq!(harness.int_state(drain_output), Some(0b01));
+        harness.drive_int(drain_input, 0b10);
+        assert_eq!(harness.int_state(drain_output), Some(0b10));
+        harness.drive_int(drain_input, 0b00);
+        assert_eq!(harness.int_state(drain_output), Some(0b00));
+    }
+}
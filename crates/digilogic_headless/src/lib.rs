@@ -0,0 +1,868 @@
+//! Runs the editor without a window, so scripts and tests can load, route and check circuits.
+//!
+//! ```no_run
+//! let mut app = digilogic_headless::HeadlessBuilder::default().build();
+//! let circuits = app.load("adder.dlc").unwrap();
+//! app.settle();
+//! for finding in app.findings() {
+//!     println!("{}", finding.message);
+//! }
+//! # let _ = circuits;
+//! ```
+
+use anyhow::{bail, Result};
+use bevy_app::App;
+use bevy_ecs::event::{Event, Events, ManualEventReader};
+use digilogic_core::components::{CircuitID, Name};
+use digilogic_core::events::*;
+use digilogic_routing::RoutingComplete;
+use digilogic_ux::{CircuitStats, ErcReport, Finding};
+use std::path::Path;
+
+/// The most frames [`HeadlessApp::settle`] runs before giving up on the circuits settling.
+const MAX_SETTLE_FRAMES: usize = 64;
+
+/// Configures which parts of the editor a [`HeadlessApp`] runs.
+#[derive(Debug, Clone)]
+pub struct HeadlessBuilder {
+    routing: bool,
+    log_level: Option<bevy_log::Level>,
+}
+
+impl Default for HeadlessBuilder {
+    fn default() -> Self {
+        Self {
+            routing: true,
+            log_level: None,
+        }
+    }
+}
+
+impl HeadlessBuilder {
+    /// Whether the wires of loaded circuits are routed.
+    pub fn routing(mut self, routing: bool) -> Self {
+        self.routing = routing;
+        self
+    }
+
+    /// Logs to the terminal at `level`. Logging can only be set up once per process.
+    pub fn log_level(mut self, level: bevy_log::Level) -> Self {
+        self.log_level = Some(level);
+        self
+    }
+
+    pub fn build(self) -> HeadlessApp {
+        let mut app = App::new();
+        app.add_plugins((
+            bevy_core::TaskPoolPlugin::default(),
+            bevy_core::TypeRegistrationPlugin,
+            bevy_core::FrameCountPlugin,
+            bevy_state::app::StatesPlugin,
+        ));
+        if let Some(level) = self.log_level {
+            app.add_plugins(bevy_log::LogPlugin {
+                level,
+                ..Default::default()
+            });
+        }
+
+        app.add_plugins((
+            digilogic_core::CorePlugin,
+            digilogic_serde::LoadSavePlugin,
+            digilogic_ux::UxPlugin,
+        ));
+        if self.routing {
+            app.add_plugins(digilogic_routing::RoutingPlugin);
+        } else {
+            // the editor listens for routing to complete, even if it never does
+            app.add_event::<RoutingComplete>();
+        }
+
+        app.finish();
+        app.cleanup();
+
+        let loaded = reader::<CircuitLoadedEvent>(&app);
+        let errors = reader::<ErrorEvent>(&app);
+        let routed = if self.routing {
+            reader::<RoutingComplete>(&app)
+        } else {
+            Default::default()
+        };
+        HeadlessApp {
+            app,
+            loaded,
+            errors,
+            routed,
+        }
+    }
+}
+
+fn reader<E: Event>(app: &App) -> ManualEventReader<E> {
+    app.world().resource::<Events<E>>().get_reader()
+}
+
+/// A summary of a loaded circuit.
+#[derive(Debug, Clone)]
+pub struct CircuitSummary {
+    pub circuit: CircuitID,
+    pub name: String,
+    pub stats: CircuitStats,
+}
+
+/// The editor without a window, updated on demand.
+#[allow(missing_debug_implementations)]
+pub struct HeadlessApp {
+    app: App,
+    loaded: ManualEventReader<CircuitLoadedEvent>,
+    errors: ManualEventReader<ErrorEvent>,
+    routed: ManualEventReader<RoutingComplete>,
+}
+
+impl HeadlessApp {
+    #[inline]
+    pub fn app(&self) -> &App {
+        &self.app
+    }
+
+    #[inline]
+    pub fn app_mut(&mut self) -> &mut App {
+        &mut self.app
+    }
+
+    /// Runs a single frame.
+    pub fn update(&mut self) {
+        self.app.update();
+    }
+
+    /// Loads a project or circuit file, returning the circuits it contains.
+    pub fn load(&mut self, filename: impl AsRef<Path>) -> Result<Vec<CircuitID>> {
+        let filename = filename.as_ref().to_owned();
+        let is_project = filename.extension().is_some_and(|ext| ext == "dlp");
+        if is_project {
+            self.app
+                .world_mut()
+                .send_event(ProjectLoadEvent { filename });
+        } else {
+            self.app
+                .world_mut()
+                .send_event(CircuitLoadEvent { filename });
+        }
+        self.update();
+        self.collect_loaded()
+    }
+
+    /// Loads a circuit from the contents of a Digilogic circuit file.
+    pub fn load_str(&mut self, name: &str, contents: &str) -> Result<CircuitID> {
+        self.app.world_mut().send_event(CircuitTemplateLoadEvent {
+            name: name.into(),
+            contents: contents.into(),
+        });
+        self.update();
+
+        let mut circuits = self.collect_loaded()?;
+        match circuits.pop() {
+            Some(circuit) => Ok(circuit),
+            None => bail!("circuit {name} was not loaded"),
+        }
+    }
+
+    fn collect_loaded(&mut self) -> Result<Vec<CircuitID>> {
+        let world = self.app.world();
+        let errors = self
+            .errors
+            .read(world.resource::<Events<ErrorEvent>>())
+            .filter(|error| error.severity == Severity::Error)
+            .map(|error| error.message.clone())
+            .collect::<Vec<_>>();
+        if !errors.is_empty() {
+            bail!("{}", errors.join("\n"));
+        }
+
+        let circuits = self
+            .loaded
+            .read(world.resource::<Events<CircuitLoadedEvent>>())
+            .map(|event| event.circuit)
+            .collect();
+        Ok(circuits)
+    }
+
+    /// Writes a loaded circuit to a file in the format named by its extension.
+    pub fn save(&mut self, circuit: CircuitID, filename: impl AsRef<Path>) -> Result<()> {
+        digilogic_serde::save_circuit_file(self.app.world_mut(), circuit, filename.as_ref())
+    }
+
+    /// Runs frames until routing and the checks of the circuits are up to date.
+    /// Returns whether they settled.
+    pub fn settle(&mut self) -> bool {
+        for frame in 0..MAX_SETTLE_FRAMES {
+            self.update();
+
+            let world = self.app.world();
+            let routed = match world.get_resource::<Events<RoutingComplete>>() {
+                Some(events) => self.routed.read(events).count(),
+                None => 0,
+            };
+            // entities spawned by the last frame are only routed in the next one
+            if (frame > 0) && (routed == 0) {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// The findings of the electrical rule check of all circuits.
+    pub fn findings(&self) -> &[Finding] {
+        self.app.world().resource::<ErcReport>().findings()
+    }
+
+    /// Summarizes a loaded circuit, `None` if it doesn't exist.
+    pub fn summary(&self, circuit: CircuitID) -> Option<CircuitSummary> {
+        let entity = self.app.world().get_entity(circuit.0)?;
+        Some(CircuitSummary {
+            circuit,
+            name: entity
+                .get::<Name>()
+                .map(|name| name.0.to_string())
+                .unwrap_or_default(),
+            stats: entity.get::<CircuitStats>().copied().unwrap_or_default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aery::prelude::*;
+    use bevy_ecs::prelude::*;
+    use bevy_ecs::system::lifetimeless::Read;
+    use bevy_ecs::system::RunSystemOnce;
+    use digilogic_core::components::*;
+    use digilogic_core::connectivity::Connectivity;
+    use digilogic_core::transform::{GlobalTransform, Rotation, Transform, Vec2};
+    use digilogic_core::{fixed, Fixed, HashMap};
+    use digilogic_ux::{DragEvent, DragType, HoverEvent, Modifiers, PointerButton};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const HALF_ADDER: &str = include_str!("../../digilogic/assets/templates/half_adder.dlc");
+
+    fn settled(mut app: HeadlessApp, circuit: CircuitID) -> (HeadlessApp, CircuitID) {
+        assert!(app.settle());
+        (app, circuit)
+    }
+
+    /// Loads a circuit file into a new app, once it settled.
+    fn load_settled(filename: impl AsRef<Path>) -> (HeadlessApp, CircuitID) {
+        let mut app = HeadlessBuilder::default().build();
+        let circuit = app.load(filename).unwrap()[0];
+        settled(app, circuit)
+    }
+
+    /// Loads the half adder template into a new app, once it settled.
+    fn load_half_adder() -> (HeadlessApp, CircuitID) {
+        let mut app = HeadlessBuilder::default().build();
+        let circuit = app.load_str("half adder", HALF_ADDER).unwrap();
+        settled(app, circuit)
+    }
+
+    /// Saves a circuit to a file in the format of `extension` and loads it into a new app.
+    fn save_and_reload(
+        app: &mut HeadlessApp,
+        circuit: CircuitID,
+        extension: &str,
+    ) -> (HeadlessApp, CircuitID) {
+        let filename =
+            std::env::temp_dir().join(format!("digilogic_save_{}.{extension}", std::process::id()));
+        app.save(circuit, &filename).unwrap();
+        let loaded = load_settled(&filename);
+        std::fs::remove_file(&filename).unwrap();
+        loaded
+    }
+
+    /// Symbols with their kind and name, along with `D`.
+    type SymbolQuery<'w, 's, D> =
+        Query<'w, 's, (Option<Read<SymbolKind>>, Read<Name>, D), With<Symbol>>;
+
+    /// The kind of a symbol, or the name of the circuit a sub-circuit instance refers to.
+    fn symbol_kind(kind: Option<&SymbolKind>, name: &Name) -> String {
+        match kind {
+            Some(kind) => format!("{kind:?}"),
+            None => name.0.to_string(),
+        }
+    }
+
+    /// Every port by the kind of its symbol and its name, like `Xor.A`.
+    /// Not every format keeps designators, so they are left out.
+    fn port_names(
+        symbols: SymbolQuery<Relations<Child>>,
+        ports: Query<(Entity, Read<Name>), With<Port>>,
+    ) -> HashMap<Entity, String> {
+        let mut names = HashMap::default();
+        for (kind, symbol_name, edges) in symbols.iter() {
+            let kind = symbol_kind(kind, symbol_name);
+            edges.join::<Child>(&ports).for_each(|(port, name)| {
+                names.insert(port, format!("{kind}.{}", name.0));
+            });
+        }
+        names
+    }
+
+    fn port_label(name: &str, position: Vec2) -> String {
+        format!("{name} ({}, {})", position.x, position.y)
+    }
+
+    /// The ports each net of a circuit connects, followed by where they are, in a stable order.
+    fn connections(app: &mut HeadlessApp, circuit: CircuitID) -> Vec<Vec<String>> {
+        let world = app.app_mut().world_mut();
+        let names = world.run_system_once(port_names);
+        let connectivity = world.resource::<Connectivity>();
+
+        let mut nets = connectivity
+            .nets_in(circuit.0)
+            .map(|net| {
+                let mut ports = connectivity
+                    .ports_of(net)
+                    .map(|port| {
+                        let position = world.get::<GlobalTransform>(port).unwrap().translation;
+                        port_label(&names[&port], position)
+                    })
+                    .collect::<Vec<_>>();
+                ports.sort();
+                ports
+            })
+            .collect::<Vec<_>>();
+        nets.sort();
+        nets
+    }
+
+    fn describe_symbols(symbols: SymbolQuery<Read<Transform>>) -> Vec<String> {
+        let mut descriptions = symbols
+            .iter()
+            .map(|(kind, name, transform)| {
+                let kind = symbol_kind(kind, name);
+                let position = transform.translation;
+                let mirrored = if transform.mirrored { " mirrored" } else { "" };
+                format!(
+                    "{kind} at ({}, {}) {:?}{mirrored}",
+                    position.x, position.y, transform.rotation
+                )
+            })
+            .collect::<Vec<_>>();
+        descriptions.sort();
+        descriptions
+    }
+
+    /// The kind and placement of every symbol, in a stable order.
+    fn symbols(app: &mut HeadlessApp) -> Vec<String> {
+        app.app_mut().world_mut().run_system_once(describe_symbols)
+    }
+
+    type EndpointQuery<'w, 's> =
+        Query<'w, 's, (Read<PortID>, Option<Read<Bits>>, Relations<Child>), With<Endpoint>>;
+
+    fn describe_endpoints(
+        In(names): In<HashMap<Entity, String>>,
+        endpoints: EndpointQuery,
+        waypoints: Query<(Read<Number>, Read<Transform>), With<Waypoint>>,
+    ) -> Vec<String> {
+        let mut descriptions = endpoints
+            .iter()
+            .map(|(port_id, bits, edges)| {
+                let mut route = Vec::new();
+                edges
+                    .join::<Child>(&waypoints)
+                    .for_each(|(number, transform)| {
+                        let position = transform.translation;
+                        route.push((number.0, format!("({}, {})", position.x, position.y)));
+                    });
+                route.sort();
+
+                let route = route.into_iter().map(|(_, position)| position);
+                let bits = bits.map(|bits| bits.0.to_vec()).unwrap_or_default();
+                format!(
+                    "{} bits {bits:?} via {:?}",
+                    names[&port_id.0],
+                    route.collect::<Vec<_>>()
+                )
+            })
+            .collect::<Vec<_>>();
+        descriptions.sort();
+        descriptions
+    }
+
+    /// The bits and waypoints of every endpoint connected to a port, in a stable order.
+    fn endpoints(app: &mut HeadlessApp) -> Vec<String> {
+        let world = app.app_mut().world_mut();
+        let names = world.run_system_once(port_names);
+        world.run_system_once_with(names, describe_endpoints)
+    }
+
+    #[test]
+    fn load_and_check_template() {
+        let mut app = HeadlessBuilder::default().build();
+        let circuit = app
+            .load_str(
+                "half adder",
+                include_str!("../../digilogic/assets/templates/half_adder.dlc"),
+            )
+            .unwrap();
+        assert!(app.settle());
+
+        let summary = app.summary(circuit).unwrap();
+        assert!(summary.stats.symbols > 0);
+        assert!(summary.stats.nets > 0);
+
+        assert!(app.load_str("broken", "{").is_err());
+    }
+
+    static EXPORTED: AtomicUsize = AtomicUsize::new(0);
+
+    fn count_exports(_world: &mut World, _circuit: CircuitID, _filename: &Path) -> Result<()> {
+        EXPORTED.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    #[test]
+    fn save_by_extension() {
+        let mut app = HeadlessBuilder::default().routing(false).build();
+        app.app_mut()
+            .world_mut()
+            .resource_mut::<digilogic_serde::FileFormats>()
+            .add_exporter(digilogic_serde::Exporter {
+                name: "Counter".into(),
+                extensions: &["count"],
+                export: count_exports,
+            });
+        let circuit = app
+            .load_str(
+                "half adder",
+                include_str!("../../digilogic/assets/templates/half_adder.dlc"),
+            )
+            .unwrap();
+
+        app.save(circuit, "half_adder.count").unwrap();
+        assert_eq!(EXPORTED.load(Ordering::Relaxed), 1);
+        assert!(app.save(circuit, "half_adder.unknown").is_err());
+        assert!(app.save(circuit, "half_adder").is_err());
+    }
+
+    #[test]
+    fn reload_replaces_circuit() {
+        use digilogic_core::components::FilePath;
+        use digilogic_core::events::{CircuitReloadEvent, CircuitReloadedEvent};
+
+        let filename =
+            std::env::temp_dir().join(format!("digilogic_reload_{}.dlc", std::process::id()));
+        std::fs::write(
+            &filename,
+            include_str!("../../digilogic/assets/templates/half_adder.dlc"),
+        )
+        .unwrap();
+
+        let mut app = HeadlessBuilder::default().routing(false).build();
+        let previous = app.load(&filename).unwrap()[0];
+        let mut reloaded = reader::<CircuitReloadedEvent>(app.app());
+        app.app_mut()
+            .world_mut()
+            .send_event(CircuitReloadEvent { circuit: previous });
+        app.update();
+        std::fs::remove_file(&filename).unwrap();
+
+        let circuit = app.collect_loaded().unwrap()[0];
+        let world = app.app().world();
+        let event = reloaded
+            .read(world.resource::<Events<CircuitReloadedEvent>>())
+            .next()
+            .unwrap();
+        assert_eq!((event.previous, event.circuit), (previous, circuit));
+        assert!(world.get_entity(previous.0).is_none());
+        assert!(world.get::<FilePath>(circuit.0).unwrap().0.is_absolute());
+        assert!(app.summary(circuit).unwrap().stats.symbols > 0);
+    }
+
+    #[test]
+    fn recovered_circuit_replaces_its_file() {
+        let dir = std::env::temp_dir();
+        let filename = dir.join(format!("digilogic_recover_{}.dlc", std::process::id()));
+        let copy = dir.join(format!("digilogic_recover_copy_{}.dlc", std::process::id()));
+        std::fs::write(
+            &filename,
+            include_str!("../../digilogic/assets/templates/logic_gates.dlc"),
+        )
+        .unwrap();
+        std::fs::write(&copy, HALF_ADDER).unwrap();
+
+        let (mut half_adder, _) = load_half_adder();
+        let expected = symbols(&mut half_adder);
+
+        // loading the file in the same frame returns the recovered circuit
+        let mut app = HeadlessBuilder::default().build();
+        app.app_mut().world_mut().send_event(CircuitRecoverEvent {
+            filename: filename.clone(),
+            copy: copy.clone(),
+        });
+        let loaded = app.load(&filename).unwrap();
+        std::fs::remove_file(&filename).unwrap();
+        std::fs::remove_file(&copy).unwrap();
+
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded[0], loaded[1]);
+        let circuit = loaded[0];
+        let world = app.app().world();
+        let stem = filename.file_stem().unwrap().to_string_lossy();
+        assert_eq!(&*world.get::<Name>(circuit.0).unwrap().0, &*stem);
+        assert_eq!(world.get::<FilePath>(circuit.0).unwrap().0, filename);
+        assert_eq!(
+            app.app_mut()
+                .world_mut()
+                .query::<&Circuit>()
+                .iter(app.app().world())
+                .count(),
+            1
+        );
+        assert!(app.settle());
+        assert_eq!(symbols(&mut app), expected);
+    }
+
+    #[test]
+    fn nudge_burst_is_one_edit() {
+        use digilogic_core::transform::Direction;
+        use digilogic_ux::{Changelog, EditKind, EndNudge, GridSize, NudgeSelection, SelectionSet};
+
+        let (mut app, circuit) = load_half_adder();
+        let world = app.app_mut().world_mut();
+        let symbol = world
+            .query_filtered::<Entity, With<Symbol>>()
+            .iter(world)
+            .next()
+            .unwrap();
+        let start = world.get::<Transform>(symbol).unwrap().translation;
+        world.resource_mut::<SelectionSet>().select_only(symbol);
+        app.update();
+
+        // a held down key nudges once per frame
+        for _ in 0..3 {
+            app.app_mut().world_mut().send_event(NudgeSelection {
+                direction: Direction::PosX,
+                large: false,
+            });
+            app.update();
+        }
+        app.app_mut().world_mut().send_event(EndNudge);
+        assert!(app.settle());
+
+        let world = app.app().world();
+        let step = world.resource::<GridSize>().0 * fixed!(3);
+        assert_eq!(
+            world.get::<Transform>(symbol).unwrap().translation,
+            Vec2 {
+                x: start.x + step,
+                y: start.y,
+            }
+        );
+
+        let edits: Vec<_> = world
+            .resource::<Changelog>()
+            .circuit(circuit.0)
+            .unwrap()
+            .edits()
+            .iter()
+            .filter(|edit| edit.entity == symbol)
+            .map(|edit| edit.kind)
+            .collect();
+        assert_eq!(edits, [EditKind::Changed("Transform")]);
+    }
+
+    #[test]
+    fn save_and_load_native_format() {
+        use digilogic_core::bundles::WaypointBundle;
+        use digilogic_core::transform::TransformBundle;
+        use digilogic_core::{fixed, transform::Vec2};
+
+        let (mut app, circuit) = load_half_adder();
+
+        // an endpoint using part of its net, routed through waypoints
+        let world = app.app_mut().world_mut();
+        let endpoint = world
+            .query_filtered::<Entity, (With<Endpoint>, With<PortID>)>()
+            .iter(world)
+            .next()
+            .unwrap();
+        world.entity_mut(endpoint).insert(Bits::parse("0").unwrap());
+        for (number, y) in [(0, fixed!(-40)), (1, fixed!(-60))] {
+            world
+                .spawn(WaypointBundle {
+                    waypoint: Waypoint,
+                    number: Number(number),
+                    transform: TransformBundle {
+                        transform: Transform {
+                            translation: Vec2 { x: fixed!(0), y },
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                    visibility: Default::default(),
+                    bounds: Default::default(),
+                })
+                .set::<Child>(endpoint);
+        }
+        assert!(app.settle());
+
+        let (mut loaded_app, loaded) = save_and_reload(&mut app, circuit, "dlc");
+        assert_eq!(
+            connections(&mut app, circuit),
+            connections(&mut loaded_app, loaded)
+        );
+        let saved_endpoints = endpoints(&mut app);
+        assert!(saved_endpoints
+            .iter()
+            .any(|endpoint| endpoint.ends_with(r#"bits [0] via ["(0, -40)", "(0, -60)"]"#)));
+        assert_eq!(saved_endpoints, endpoints(&mut loaded_app));
+
+        let saved = app.summary(circuit).unwrap().stats;
+        let loaded = loaded_app.summary(loaded).unwrap().stats;
+        assert_eq!(saved, loaded);
+        assert_eq!(app.findings().len(), loaded_app.findings().len());
+    }
+
+    #[test]
+    fn save_and_load_digital_format() {
+        let (mut app, circuit) = load_half_adder();
+
+        // a turned and a mirrored gate, with the wires routed to where their ports moved
+        let world = app.app_mut().world_mut();
+        let gates = world
+            .query_filtered::<Entity, With<Symbol>>()
+            .iter(world)
+            .filter(|&symbol| {
+                let kind = world.get::<SymbolKind>(symbol).unwrap();
+                !matches!(kind, SymbolKind::In | SymbolKind::Out)
+            })
+            .collect::<Vec<_>>();
+        world.get_mut::<Transform>(gates[0]).unwrap().rotation = Rotation::Rot90;
+        world.get_mut::<Transform>(gates[1]).unwrap().mirrored = true;
+        assert!(app.settle());
+
+        let (mut loaded_app, loaded) = save_and_reload(&mut app, circuit, "dig");
+        let saved_symbols = symbols(&mut app);
+        assert!(saved_symbols.iter().any(|symbol| symbol.contains("Rot90")));
+        assert!(saved_symbols
+            .iter()
+            .any(|symbol| symbol.ends_with("mirrored")));
+        assert_eq!(saved_symbols, symbols(&mut loaded_app));
+
+        assert_eq!(
+            connections(&mut app, circuit),
+            connections(&mut loaded_app, loaded)
+        );
+    }
+
+    #[test]
+    fn load_logisim_circuit() {
+        let (mut app, circuit) = load_settled("../digilogic_serde/testdata/half_adder.circ");
+
+        // Logisim's grid is half as fine, and gates are placed by their output
+        assert_eq!(
+            symbols(&mut app),
+            [
+                "And at (420, 400) Rot0",
+                "In at (200, 180) Rot0",
+                "In at (200, 460) Rot0",
+                "Out at (600, 220) Rot0",
+                "Out at (600, 420) Rot0",
+                "Xor at (420, 200) Rot0",
+            ]
+        );
+        assert_eq!(
+            connections(&mut app, circuit),
+            [
+                vec!["And.A (420, 400)", "In.Y (200, 180)", "Xor.A (420, 200)"],
+                vec!["And.B (420, 440)", "In.Y (200, 460)", "Xor.B (420, 240)"],
+                vec!["And.Y (500, 420)", "Out.A (600, 420)"],
+                vec!["Out.A (600, 220)", "Xor.Y (500, 220)"],
+            ]
+        );
+    }
+
+    #[test]
+    fn load_logisim_evolution_circuit() {
+        let (mut app, circuit) = load_settled("../digilogic_serde/testdata/full_adder.circ");
+
+        // the carry of the first half adder reaches the or gate through a tunnel
+        assert_eq!(
+            connections(&mut app, circuit),
+            [
+                vec!["In.Y (400, 200)", "half_adder.A (520, 200)"],
+                vec!["In.Y (400, 240)", "half_adder.B (520, 240)"],
+                vec!["In.Y (400, 320)", "half_adder.B (720, 320)"],
+                vec!["Or.A (880, 380)", "half_adder.C (800, 320)"],
+                vec![
+                    "Or.B (880, 420)",
+                    "Tunnel.A (640, 440)",
+                    "half_adder.C (600, 240)",
+                ],
+                vec!["Or.Y (960, 400)", "Out.A (1080, 400)"],
+                vec!["Out.A (960, 280)", "half_adder.S (800, 280)"],
+                vec!["half_adder.A (720, 280)", "half_adder.S (600, 200)"],
+            ]
+        );
+
+        // both half adders instantiate the same circuit
+        let world = app.app_mut().world_mut();
+        let instances = world
+            .query::<&SubCircuit>()
+            .iter(world)
+            .map(|sub_circuit| sub_circuit.0)
+            .collect::<Vec<_>>();
+        assert_eq!(instances.len(), 2);
+        assert_eq!(instances[0], instances[1]);
+
+        // pins and tunnels name the nets they are on
+        let net_names = world
+            .query_filtered::<&Name, With<Net>>()
+            .iter(world)
+            .map(|name| name.0.to_string())
+            .collect::<Vec<_>>();
+        for name in ["A", "B", "Cin", "S", "Cout", "C1", "C"] {
+            assert!(net_names.iter().any(|net_name| net_name == name), "{name}");
+        }
+    }
+
+    /// Pointer input for a viewport, sent during the next frame like the editor does.
+    #[derive(Default, Resource)]
+    struct PendingInput(Vec<(Entity, CircuitID, Option<DragType>, Vec2)>);
+
+    fn send_pending_input(mut commands: Commands, mut pending: ResMut<PendingInput>) {
+        let modifiers = Modifiers {
+            alt: false,
+            ctrl: false,
+            shift: false,
+            mac_cmd: false,
+            command: false,
+        };
+        for (viewport, circuit, drag_type, pos) in pending.0.drain(..) {
+            commands.trigger_targets(
+                HoverEvent {
+                    viewport,
+                    circuit,
+                    pos,
+                    modifiers,
+                },
+                viewport,
+            );
+            if let Some(drag_type) = drag_type {
+                commands.trigger_targets(
+                    DragEvent {
+                        drag_type,
+                        viewport,
+                        circuit,
+                        pos,
+                        delta: Vec2::ZERO,
+                        button: PointerButton::Primary,
+                        modifiers,
+                    },
+                    viewport,
+                );
+            }
+        }
+    }
+
+    /// Spawns a viewport into `circuit` that takes [`PendingInput`].
+    fn spawn_viewport(app: &mut HeadlessApp, circuit: CircuitID) -> Entity {
+        let app = app.app_mut();
+        app.init_resource::<PendingInput>()
+            .add_systems(bevy_app::Update, send_pending_input);
+        app.world_mut().spawn((Viewport, circuit)).id()
+    }
+
+    /// Drags with the primary button over the viewport from `from` to `to`.
+    /// The cursor hovers every position a frame before it is dragged there.
+    fn drag(app: &mut HeadlessApp, viewport: Entity, circuit: CircuitID, from: Vec2, to: Vec2) {
+        let steps = [
+            (None, from),
+            (Some(DragType::Start), from),
+            (Some(DragType::Dragging), to),
+            (Some(DragType::End), to),
+        ];
+        for (drag_type, pos) in steps {
+            let world = app.app_mut().world_mut();
+            let mut pending = world.resource_mut::<PendingInput>();
+            pending.0.push((viewport, circuit, drag_type, pos));
+            app.update();
+        }
+        assert!(app.settle());
+    }
+
+    /// Every port by the label [`connections`] gives it, and where it is.
+    fn ports(app: &mut HeadlessApp) -> Vec<(String, Vec2)> {
+        let world = app.app_mut().world_mut();
+        let names = world.run_system_once(port_names);
+        names
+            .into_iter()
+            .map(|(port, name)| {
+                let position = world.get::<GlobalTransform>(port).unwrap().translation;
+                (port_label(&name, position), position)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn draw_wires_between_ports() {
+        use digilogic_ux::{ActiveTool, PlaceSymbol};
+
+        let (mut app, circuit) = load_half_adder();
+        let world = app.app_mut().world_mut();
+        *world.resource_mut::<ActiveTool>() = ActiveTool::DrawWire;
+        for (kind, x) in [(SymbolKind::In, 600), (SymbolKind::Out, 800)] {
+            world.trigger(PlaceSymbol {
+                circuit,
+                kind,
+                pos: Vec2 {
+                    x: Fixed::from_i16(x),
+                    y: fixed!(500),
+                },
+            });
+        }
+        let viewport = spawn_viewport(&mut app, circuit);
+        assert!(app.settle());
+        let before = connections(&mut app, circuit);
+
+        // a new net between two unconnected ports
+        let ports = ports(&mut app);
+        let placed_port = |name: &str| {
+            ports
+                .iter()
+                .find(|(label, position)| label.starts_with(name) && (position.x > fixed!(500)))
+                .unwrap()
+                .clone()
+        };
+        let (input, output) = (placed_port("In.Y"), placed_port("Out.A"));
+        drag(&mut app, viewport, circuit, input.1, output.1);
+        let after = connections(&mut app, circuit);
+        assert_eq!(after.len(), before.len() + 1);
+        let wire = vec![input.0, output.0];
+        assert!(after.contains(&wire), "{after:?}");
+
+        // clicking a port without dragging doesn't leave a wire behind
+        drag(&mut app, viewport, circuit, output.1, output.1);
+        assert_eq!(connections(&mut app, circuit), after);
+
+        // a wire between two nets merges them
+        let sum = before
+            .iter()
+            .find(|net| net.iter().any(|port| port.starts_with("Xor.Y")))
+            .unwrap();
+        let sum_output = before
+            .iter()
+            .flatten()
+            .find(|port| port.starts_with("Out.A") && sum.contains(port))
+            .unwrap();
+        let (_, sum_position) = ports.iter().find(|(label, _)| label == sum_output).unwrap();
+        drag(&mut app, viewport, circuit, output.1, *sum_position);
+        let merged = connections(&mut app, circuit);
+        assert_eq!(merged.len(), before.len());
+        let mut expected = sum.clone();
+        expected.extend(wire);
+        expected.sort();
+        assert!(merged.contains(&expected), "{merged:?}");
+    }
+}
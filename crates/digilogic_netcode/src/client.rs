@@ -8,9 +8,15 @@ use bevy_reflect::prelude::*;
 use bevy_state::prelude::*;
 use bevy_time::prelude::*;
 use digilogic_core::components::*;
+use digilogic_core::connectivity::Connectivity;
 use digilogic_core::resources::Project;
 use digilogic_core::states::*;
-use digilogic_core::{HashMap, SharedStr, StateMut};
+use digilogic_core::symbol::{
+    BORROW_IN_PORT, BORROW_OUT_PORT, BUS_PORT, CARRY_IN_PORT, CARRY_OUT_PORT, CLOCK_PORT,
+    ENABLE_PORT, EQUAL_PORT, GREATER_PORT, LEFT_PORT, LESS_PORT, LOAD_PORT, Q_INVERTED_PORT,
+    Q_PORT, RESET_PORT, SELECT_PORT_PREFIX, SERIAL_IN_PORT, SERIAL_OUT_PORT, UP_PORT, VALUE_PARAM,
+};
+use digilogic_core::{HashMap, HashSet, SharedStr, StateMut};
 use std::net::ToSocketAddrs;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect, Component)]
@@ -272,10 +278,27 @@ fn process_eval_events(
 pub struct SimNet(NetId);
 
 type CircuitQuery<'w, 's> = Query<'w, 's, ((), Relations<Child>), With<Circuit>>;
-type SymbolQuery<'w, 's> =
-    Query<'w, 's, ((Entity, Read<SymbolKind>), Relations<Child>), With<Symbol>>;
-type PortQuery<'w, 's> = Query<'w, 's, (Option<Read<NetID>>, Has<Input>, Has<Output>), With<Port>>;
-type NetQuery<'w, 's> = Query<'w, 's, Entity, With<Net>>;
+type SymbolQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        (Entity, Read<SymbolKind>, Option<Read<Parameters>>),
+        Relations<Child>,
+    ),
+    With<Symbol>,
+>;
+type PortQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Option<Read<NetID>>,
+        Read<PortDirection>,
+        Read<Name>,
+        Option<Read<BitWidth>>,
+    ),
+    With<Port>,
+>;
+type NetQuery<'w, 's> = Query<'w, 's, (Entity, Option<Read<BitWidth>>), With<Net>>;
 
 #[derive(SystemParam)]
 struct BuildQueries<'w, 's> {
@@ -285,11 +308,106 @@ struct BuildQueries<'w, 's> {
     nets: NetQuery<'w, 's>,
 }
 
+/// Nets added for ports left unconnected, so symbols with open pins can still be simulated.
+/// Open inputs float on a net shared by all open inputs of the same width,
+/// every open output drives a net of its own that nothing reads.
+struct OpenPins {
+    next_net_id: NetId,
+    floating: HashMap<NonZeroU8, NetId>,
+}
+
+impl OpenPins {
+    fn add_net(
+        &mut self,
+        client: &mut RenetClient,
+        next_message_id: &mut NextMessageId,
+        width: NonZeroU8,
+    ) -> NetId {
+        client.send_command_message(ClientMessage {
+            id: next_message_id.get(),
+            kind: ClientMessageKind::AddNet { width },
+        });
+
+        let net_id = self.next_net_id;
+        self.next_net_id.0 += 1;
+        net_id
+    }
+
+    fn input(
+        &mut self,
+        client: &mut RenetClient,
+        next_message_id: &mut NextMessageId,
+        width: NonZeroU8,
+    ) -> NetId {
+        if let Some(&net_id) = self.floating.get(&width) {
+            return net_id;
+        }
+
+        let net_id = self.add_net(client, next_message_id, width);
+        self.floating.insert(width, net_id);
+        net_id
+    }
+
+    fn output(
+        &mut self,
+        client: &mut RenetClient,
+        next_message_id: &mut NextMessageId,
+        width: NonZeroU8,
+    ) -> NetId {
+        self.add_net(client, next_message_id, width)
+    }
+}
+
+/// A splitter whose direction is only known once it is clear which of its sides is driven.
+struct PendingSplitter {
+    width: NonZeroU8,
+    bus: NetId,
+    /// The bits and net of every connected tap.
+    taps: Vec<(Vec<u8>, NetId)>,
+}
+
+impl PendingSplitter {
+    /// The message adding the splitter, if one of its sides is in `driven`.
+    /// The nets on the other side are added to `driven`.
+    fn resolve(&self, driven: &mut HashSet<NetId>) -> Option<ClientMessageKind> {
+        let (taps, nets) = self.taps.iter().cloned().unzip();
+        if driven.contains(&self.bus) {
+            driven.extend(self.taps.iter().map(|&(_, net)| net));
+            Some(ClientMessageKind::AddSplit {
+                width: self.width,
+                input: self.bus,
+                taps,
+                outputs: nets,
+            })
+        } else if self.taps.iter().any(|(_, net)| driven.contains(net)) {
+            driven.insert(self.bus);
+            Some(ClientMessageKind::AddMerge {
+                width: self.width,
+                inputs: nets,
+                taps,
+                output: self.bus,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+fn flip_flop_kind(kind: SymbolKind) -> Option<FlipFlopKind> {
+    match kind {
+        SymbolKind::DFlipFlop => Some(FlipFlopKind::D),
+        SymbolKind::JkFlipFlop => Some(FlipFlopKind::Jk),
+        SymbolKind::TFlipFlop => Some(FlipFlopKind::T),
+        _ => None,
+    }
+}
+
 fn build(
     mut commands: Commands,
     mut client: ResMut<RenetClient>,
     project: Res<Project>,
     mut next_message_id: ResMut<NextMessageId>,
+    connectivity: Res<Connectivity>,
     queries: BuildQueries,
 ) {
     let root_circuit = project
@@ -309,33 +427,72 @@ fn build(
 
     let mut net_id = NetId(0);
     let mut offset = 0u64;
-    root_children.join::<Child>(&queries.nets).for_each(|net| {
-        client.send_command_message(ClientMessage {
-            id: next_message_id.get(),
-            kind: ClientMessageKind::AddNet {
-                width: NonZeroU8::MIN, // TODO: use actual net width
-            },
+    root_children
+        .join::<Child>(&queries.nets)
+        .for_each(|(net, bit_width)| {
+            // nets joined by tunnels are simulated as the net first seen of them
+            let root = connectivity.joined_root(net);
+            if let Some(&joined) = net_map.get(&root) {
+                let (_, joined_offset, _) = joined;
+                commands.entity(net).insert(StateOffset(joined_offset));
+                net_map.insert(net, joined);
+                return;
+            }
+
+            let width = bit_width.map_or(NonZeroU8::MIN, |bit_width| bit_width.0);
+            client.send_command_message(ClientMessage {
+                id: next_message_id.get(),
+                kind: ClientMessageKind::AddNet { width },
+            });
+
+            commands.entity(net).insert(StateOffset(offset));
+            net_map.insert(net, (net_id, offset, width));
+            net_map.insert(root, (net_id, offset, width));
+
+            net_id.0 += 1;
+            offset += width.get() as u64;
         });
 
-        commands.entity(net).insert(StateOffset(offset));
-        net_map.insert(net, (net_id, offset));
+    let mut open_pins = OpenPins {
+        next_net_id: net_id,
+        floating: HashMap::default(),
+    };
 
-        net_id.0 += 1;
-        offset += 1; // TODO: use actual net width
-    });
+    // splitters pass values on from the side that is driven, starting from the nets driven by outputs,
+    // including the outputs of buffers that can be switched off
+    let mut driven = HashSet::default();
+    let mut splitters = Vec::new();
+    root_children.join::<Child>(&queries.symbols).for_each(
+        |((_, symbol_kind, _), symbol_children)| {
+            let switched = matches!(symbol_kind, SymbolKind::Buffer | SymbolKind::OpenDrain);
+            symbol_children.join::<Child>(&queries.ports).for_each(
+                |(connected_net, direction, _, _)| {
+                    let drives = match direction {
+                        PortDirection::Output => true,
+                        PortDirection::Bidirectional => switched,
+                        PortDirection::Input => false,
+                    };
+                    if let (Some(connected_net), true) = (connected_net, drives) {
+                        if let Some(&(net_id, _, _)) = net_map.get(&connected_net.0) {
+                            driven.insert(net_id);
+                        }
+                    }
+                },
+            );
+        },
+    );
 
     root_children.join::<Child>(&queries.symbols).for_each(
-        |((symbol, symbol_kind), symbol_children)| {
+        |((symbol, symbol_kind, parameters), symbol_children)| {
             if matches!(symbol_kind, SymbolKind::In | SymbolKind::Out) {
                 let mut first = true;
-                symbol_children
-                    .join::<Child>(&queries.ports)
-                    .for_each(|(connected_net, _, _)| {
+                symbol_children.join::<Child>(&queries.ports).for_each(
+                    |(connected_net, _, _, _)| {
                         assert!(first, "input/output symbol has more than one port");
                         first = false;
 
                         if let Some(connected_net) = connected_net {
-                            let &(net_id, net_offset) = net_map
+                            let &(net_id, net_offset, _) = net_map
                                 .get(&connected_net.0)
                                 .expect("port connected to invalid net");
                             commands.entity(symbol).insert(StateOffset(net_offset));
@@ -345,40 +502,524 @@ fn build(
                                 commands.entity(symbol).insert(SimNet(net_id));
                             }
                         }
-                    });
+                    },
+                );
                 assert!(!first, "input/output symbol has no ports");
-            } else {
+            } else if let Some(kind) = flip_flop_kind(*symbol_kind) {
                 let mut inputs = Vec::new();
+                let mut clock = None;
+                let mut reset = None;
                 let mut output = None;
+                let mut inverted_output = None;
+                let mut width = NonZeroU8::MIN;
 
-                // TODO: this only works for basic gates
+                // the reset and inverted output are optional, other open pins are tied to open nets
+                symbol_children.join::<Child>(&queries.ports).for_each(
+                    |(connected_net, _, name, bit_width)| {
+                        let net = connected_net.map(|connected_net| {
+                            net_map
+                                .get(&connected_net.0)
+                                .expect("port connected to invalid net")
+                        });
+                        let net_id = net.map(|&(net_id, _, _)| net_id);
+
+                        match name.as_str() {
+                            CLOCK_PORT => clock = net_id,
+                            RESET_PORT => reset = net_id,
+                            Q_PORT => {
+                                output = net_id;
+                                if let Some(&(_, _, net_width)) = net {
+                                    width = net_width;
+                                } else if let Some(bit_width) = bit_width {
+                                    width = bit_width.0;
+                                }
+                            }
+                            Q_INVERTED_PORT => inverted_output = net_id,
+                            _ => inputs.push(net_id),
+                        }
+                    },
+                );
+
+                let inputs = inputs
+                    .into_iter()
+                    .map(|input| {
+                        input.unwrap_or_else(|| {
+                            open_pins.input(&mut client, &mut next_message_id, width)
+                        })
+                    })
+                    .collect();
+                let clock = clock.unwrap_or_else(|| {
+                    open_pins.input(&mut client, &mut next_message_id, NonZeroU8::MIN)
+                });
+                let output = output
+                    .unwrap_or_else(|| open_pins.output(&mut client, &mut next_message_id, width));
+                client.send_command_message(ClientMessage {
+                    id: next_message_id.get(),
+                    kind: ClientMessageKind::AddFlipFlop {
+                        kind,
+                        width,
+                        inputs,
+                        clock,
+                        reset,
+                        output,
+                        inverted_output,
+                    },
+                });
+            } else if matches!(symbol_kind, SymbolKind::Mux | SymbolKind::Demux) {
+                let mut data_inputs = Vec::new();
+                let mut select = Vec::new();
+                let mut outputs = Vec::new();
+
+                // the ports are built with the select inputs ordered from the lowest bit,
+                // open outputs of demultiplexers drive nothing, other open pins are tied to open nets
+                symbol_children.join::<Child>(&queries.ports).for_each(
+                    |(connected_net, direction, name, bit_width)| {
+                        let net = connected_net.map(|connected_net| {
+                            *net_map
+                                .get(&connected_net.0)
+                                .expect("port connected to invalid net")
+                        });
+                        let net_id = net.map(|(net_id, _, _)| net_id);
+                        let width = match net {
+                            Some((_, _, net_width)) => net_width,
+                            None => bit_width.map_or(NonZeroU8::MIN, |bit_width| bit_width.0),
+                        };
+
+                        if direction.is_output() {
+                            outputs.push((net_id, width));
+                        } else if name.starts_with(SELECT_PORT_PREFIX) {
+                            select.push(net_id);
+                        } else {
+                            data_inputs.push((net_id, width));
+                        }
+                    },
+                );
+
+                let select = select
+                    .into_iter()
+                    .map(|select| {
+                        select.unwrap_or_else(|| {
+                            open_pins.input(&mut client, &mut next_message_id, NonZeroU8::MIN)
+                        })
+                    })
+                    .collect();
+                let kind = if *symbol_kind == SymbolKind::Mux {
+                    assert_eq!(outputs.len(), 1, "multiple output ports");
+                    let (output, width) = outputs[0];
+                    let output = output.unwrap_or_else(|| {
+                        open_pins.output(&mut client, &mut next_message_id, width)
+                    });
+                    ClientMessageKind::AddMux {
+                        width,
+                        inputs: data_inputs
+                            .into_iter()
+                            .map(|(input, _)| {
+                                input.unwrap_or_else(|| {
+                                    open_pins.input(&mut client, &mut next_message_id, width)
+                                })
+                            })
+                            .collect(),
+                        select,
+                        output,
+                    }
+                } else {
+                    assert_eq!(data_inputs.len(), 1, "multiple input ports");
+                    let (input, width) = data_inputs[0];
+                    let input = input.unwrap_or_else(|| {
+                        open_pins.input(&mut client, &mut next_message_id, width)
+                    });
+                    ClientMessageKind::AddDemux {
+                        width,
+                        input,
+                        select,
+                        outputs: outputs.into_iter().map(|(output, _)| output).collect(),
+                    }
+                };
+                client.send_command_message(ClientMessage {
+                    id: next_message_id.get(),
+                    kind,
+                });
+            } else if matches!(
+                symbol_kind,
+                SymbolKind::Adder | SymbolKind::Subtractor | SymbolKind::Comparator
+            ) {
+                let mut a = None;
+                let mut b = None;
+                let mut flag_in = None;
+                let mut result = None;
+                let mut flag_out = None;
+                let mut less = None;
+                let mut equal = None;
+                let mut greater = None;
+                let mut width = NonZeroU8::MIN;
+
+                // open operands float, the data width is the one of operand A
+                symbol_children.join::<Child>(&queries.ports).for_each(
+                    |(connected_net, direction, name, bit_width)| {
+                        let net = connected_net.map(|connected_net| {
+                            *net_map
+                                .get(&connected_net.0)
+                                .expect("port connected to invalid net")
+                        });
+                        let net_id = net.map(|(net_id, _, _)| net_id);
+
+                        match name.as_str() {
+                            "A" => {
+                                a = net_id;
+                                width = match net {
+                                    Some((_, _, net_width)) => net_width,
+                                    None => {
+                                        bit_width.map_or(NonZeroU8::MIN, |bit_width| bit_width.0)
+                                    }
+                                };
+                            }
+                            "B" => b = net_id,
+                            CARRY_IN_PORT | BORROW_IN_PORT => flag_in = net_id,
+                            CARRY_OUT_PORT | BORROW_OUT_PORT => flag_out = net_id,
+                            LESS_PORT => less = net_id,
+                            EQUAL_PORT => equal = net_id,
+                            GREATER_PORT => greater = net_id,
+                            _ if direction.is_output() => result = net_id,
+                            _ => panic!("unknown arithmetic port"),
+                        }
+                    },
+                );
+
+                let a =
+                    a.unwrap_or_else(|| open_pins.input(&mut client, &mut next_message_id, width));
+                let b =
+                    b.unwrap_or_else(|| open_pins.input(&mut client, &mut next_message_id, width));
+                let kind = match symbol_kind {
+                    SymbolKind::Adder => ClientMessageKind::AddAdder {
+                        width,
+                        a,
+                        b,
+                        carry_in: flag_in,
+                        sum: result,
+                        carry_out: flag_out,
+                    },
+                    SymbolKind::Subtractor => ClientMessageKind::AddSubtractor {
+                        width,
+                        a,
+                        b,
+                        borrow_in: flag_in,
+                        difference: result,
+                        borrow_out: flag_out,
+                    },
+                    _ => ClientMessageKind::AddComparator {
+                        width,
+                        a,
+                        b,
+                        less,
+                        equal,
+                        greater,
+                    },
+                };
+                client.send_command_message(ClientMessage {
+                    id: next_message_id.get(),
+                    kind,
+                });
+            } else if matches!(
+                symbol_kind,
+                SymbolKind::Register | SymbolKind::Counter | SymbolKind::ShiftRegister
+            ) {
+                let mut serial_in = None;
+                let mut data = None;
+                let mut load = None;
+                let mut enable = None;
+                let mut up = None;
+                let mut left = None;
+                let mut clock = None;
+                let mut reset = None;
+                let mut output = None;
+                let mut serial_out = None;
+                let mut carry_out = None;
+                let mut width = NonZeroU8::MIN;
+
+                // all control inputs are optional, an open data input of a register floats
+                // and the output of shift registers may be left unconnected when used serially
+                symbol_children.join::<Child>(&queries.ports).for_each(
+                    |(connected_net, _, name, bit_width)| {
+                        let net_id = connected_net.map(|connected_net| {
+                            net_map
+                                .get(&connected_net.0)
+                                .expect("port connected to invalid net")
+                                .0
+                        });
+
+                        match name.as_str() {
+                            SERIAL_IN_PORT => serial_in = net_id,
+                            LOAD_PORT => load = net_id,
+                            ENABLE_PORT => enable = net_id,
+                            UP_PORT => up = net_id,
+                            LEFT_PORT => left = net_id,
+                            CLOCK_PORT => clock = net_id,
+                            RESET_PORT => reset = net_id,
+                            Q_PORT => {
+                                output = net_id;
+                                if let Some(bit_width) = bit_width {
+                                    width = bit_width.0;
+                                }
+                            }
+                            SERIAL_OUT_PORT => serial_out = net_id,
+                            CARRY_OUT_PORT => carry_out = net_id,
+                            _ => data = net_id,
+                        }
+                    },
+                );
+
+                let clock = clock.unwrap_or_else(|| {
+                    open_pins.input(&mut client, &mut next_message_id, NonZeroU8::MIN)
+                });
+                let kind = match symbol_kind {
+                    SymbolKind::Register => {
+                        let data = data.unwrap_or_else(|| {
+                            open_pins.input(&mut client, &mut next_message_id, width)
+                        });
+                        let output = output.unwrap_or_else(|| {
+                            open_pins.output(&mut client, &mut next_message_id, width)
+                        });
+                        ClientMessageKind::AddRegister {
+                            width,
+                            data,
+                            enable,
+                            clock,
+                            reset,
+                            output,
+                        }
+                    }
+                    SymbolKind::Counter => {
+                        let output = output.unwrap_or_else(|| {
+                            open_pins.output(&mut client, &mut next_message_id, width)
+                        });
+                        ClientMessageKind::AddCounter {
+                            width,
+                            data,
+                            load,
+                            enable,
+                            up,
+                            clock,
+                            reset,
+                            output,
+                            carry_out,
+                        }
+                    }
+                    _ => ClientMessageKind::AddShiftRegister {
+                        width,
+                        serial_in,
+                        data,
+                        load,
+                        enable,
+                        left,
+                        clock,
+                        reset,
+                        output,
+                        serial_out,
+                    },
+                };
+                client.send_command_message(ClientMessage {
+                    id: next_message_id.get(),
+                    kind,
+                });
+            } else if *symbol_kind == SymbolKind::Splitter {
+                let mut bus = None;
+                let mut taps = Vec::new();
+
+                // taps are named by their bits, unconnected ones are left out
+                symbol_children.join::<Child>(&queries.ports).for_each(
+                    |(connected_net, _, name, _)| {
+                        let Some(connected_net) = connected_net else {
+                            return;
+                        };
+                        let &(net_id, _, width) = net_map
+                            .get(&connected_net.0)
+                            .expect("port connected to invalid net");
+
+                        if name.as_str() == BUS_PORT {
+                            bus = Some((net_id, width));
+                        } else {
+                            let bits = Bits::parse(name).expect("invalid splitter tap");
+                            taps.push((bits.0.to_vec(), net_id));
+                        }
+                    },
+                );
+
+                if let Some((bus, width)) = bus.filter(|_| !taps.is_empty()) {
+                    splitters.push(PendingSplitter { width, bus, taps });
+                }
+            } else if *symbol_kind == SymbolKind::Constant {
+                let mut output = None;
+                symbol_children.join::<Child>(&queries.ports).for_each(
+                    |(connected_net, _, _, _)| {
+                        output = connected_net.map(|connected_net| {
+                            *net_map
+                                .get(&connected_net.0)
+                                .expect("port connected to invalid net")
+                        });
+                    },
+                );
+
+                // an unconnected constant drives nothing, an invalid value drives undefined bits
+                if let Some((output, _, width)) = output {
+                    let value = parameters.and_then(|parameters| parameters.text(VALUE_PARAM));
+                    let state = value
+                        .and_then(|value| LogicState::parse(value, BitWidth(width)).ok())
+                        .unwrap_or_else(|| LogicState::undefined(BitWidth(width)));
+                    client.send_command_message(ClientMessage {
+                        id: next_message_id.get(),
+                        kind: ClientMessageKind::AddConstant {
+                            width,
+                            bit_plane_0: state.bit_plane_0.to_vec(),
+                            bit_plane_1: state.bit_plane_1.to_vec(),
+                            output,
+                        },
+                    });
+                }
+            } else if matches!(symbol_kind, SymbolKind::Buffer | SymbolKind::OpenDrain) {
+                let mut input = None;
+                let mut enable = None;
+                let mut output = None;
+                symbol_children.join::<Child>(&queries.ports).for_each(
+                    |(connected_net, direction, name, _)| {
+                        let net = connected_net.map(|connected_net| {
+                            *net_map
+                                .get(&connected_net.0)
+                                .expect("port connected to invalid net")
+                        });
+                        match direction {
+                            PortDirection::Bidirectional => output = net,
+                            PortDirection::Input if name.as_str() == ENABLE_PORT => enable = net,
+                            PortDirection::Input => input = net,
+                            PortDirection::Output => unreachable!(),
+                        }
+                    },
+                );
+
+                // an unconnected output drives nothing
+                if let Some((output, _, width)) = output {
+                    // open inputs float
+                    let input = match input {
+                        Some((input, _, _)) => input,
+                        None => open_pins.input(&mut client, &mut next_message_id, width),
+                    };
+                    let kind = if *symbol_kind == SymbolKind::Buffer {
+                        let enable = match enable {
+                            Some((enable, _, _)) => enable,
+                            None => {
+                                open_pins.input(&mut client, &mut next_message_id, NonZeroU8::MIN)
+                            }
+                        };
+                        ClientMessageKind::AddBuffer {
+                            width,
+                            input,
+                            enable,
+                            output,
+                        }
+                    } else {
+                        ClientMessageKind::AddOpenDrain {
+                            width,
+                            input,
+                            output,
+                        }
+                    };
+                    client.send_command_message(ClientMessage {
+                        id: next_message_id.get(),
+                        kind,
+                    });
+                }
+            } else if matches!(symbol_kind, SymbolKind::PullUp | SymbolKind::PullDown) {
                 symbol_children.join::<Child>(&queries.ports).for_each(
-                    |(connected_net, is_input, is_output)| {
-                        let &(net_id, _) = net_map
-                            .get(&connected_net.expect("unconnected port").0)
+                    |(connected_net, _, _, _)| {
+                        let Some(connected_net) = connected_net else {
+                            return;
+                        };
+                        let &(net, _, width) = net_map
+                            .get(&connected_net.0)
                             .expect("port connected to invalid net");
+                        client.send_command_message(ClientMessage {
+                            id: next_message_id.get(),
+                            kind: ClientMessageKind::AddPull {
+                                width,
+                                net,
+                                up: *symbol_kind == SymbolKind::PullUp,
+                            },
+                        });
+                    },
+                );
+            } else if !matches!(symbol_kind, SymbolKind::Custom(_) | SymbolKind::Tunnel) {
+                // kinds registered at runtime have no simulation model,
+                // and tunnels only join nets
+                let mut inputs = Vec::new();
+                let mut output = None;
+                let mut width = NonZeroU8::MIN;
+
+                // TODO: this only works for basic gates
+                symbol_children.join::<Child>(&queries.ports).for_each(
+                    |(connected_net, direction, _, bit_width)| {
+                        let net = connected_net.map(|connected_net| {
+                            *net_map
+                                .get(&connected_net.0)
+                                .expect("port connected to invalid net")
+                        });
 
-                        match (is_input, is_output) {
-                            (true, true) => panic!("unsupported bidirectional port"),
-                            (true, false) => inputs.push(net_id),
-                            (false, true) => {
+                        match direction {
+                            PortDirection::Bidirectional => {
+                                panic!("unsupported bidirectional port")
+                            }
+                            PortDirection::Input => inputs.push(net.map(|(net_id, _, _)| net_id)),
+                            PortDirection::Output => {
                                 assert!(output.is_none(), "multiple output ports");
-                                output = Some(net_id);
+                                output = Some(net.map(|(net_id, _, _)| net_id));
+                                width = match net {
+                                    Some((_, _, net_width)) => net_width,
+                                    None => {
+                                        bit_width.map_or(NonZeroU8::MIN, |bit_width| bit_width.0)
+                                    }
+                                };
                             }
-                            (false, false) => panic!("port with missing direction"),
                         }
                     },
                 );
 
-                let output = output.expect("missing output port");
+                let output = output
+                    .expect("missing output port")
+                    .unwrap_or_else(|| open_pins.output(&mut client, &mut next_message_id, width));
+                let inputs: Vec<_> = inputs
+                    .into_iter()
+                    .map(|input| {
+                        input.unwrap_or_else(|| {
+                            open_pins.input(&mut client, &mut next_message_id, width)
+                        })
+                    })
+                    .collect();
 
                 match symbol_kind {
-                    SymbolKind::In | SymbolKind::Out => unreachable!(),
+                    SymbolKind::In
+                    | SymbolKind::Out
+                    | SymbolKind::DFlipFlop
+                    | SymbolKind::JkFlipFlop
+                    | SymbolKind::TFlipFlop
+                    | SymbolKind::Mux
+                    | SymbolKind::Demux
+                    | SymbolKind::Adder
+                    | SymbolKind::Subtractor
+                    | SymbolKind::Comparator
+                    | SymbolKind::Register
+                    | SymbolKind::Counter
+                    | SymbolKind::ShiftRegister
+                    | SymbolKind::Constant
+                    | SymbolKind::Tunnel
+                    | SymbolKind::PullUp
+                    | SymbolKind::PullDown
+                    | SymbolKind::Buffer
+                    | SymbolKind::OpenDrain
+                    | SymbolKind::Splitter
+                    | SymbolKind::Custom(_) => unreachable!(),
 
                     SymbolKind::And => client.send_command_message(ClientMessage {
                         id: next_message_id.get(),
                         kind: ClientMessageKind::AddAndGate {
-                            width: NonZeroU8::MIN, // TODO: use actual net width
+                            width,
                             inputs,
                             output,
                         },
@@ -386,7 +1027,7 @@ fn build(
                     SymbolKind::Or => client.send_command_message(ClientMessage {
                         id: next_message_id.get(),
                         kind: ClientMessageKind::AddOrGate {
-                            width: NonZeroU8::MIN, // TODO: use actual net width
+                            width,
                             inputs,
                             output,
                         },
@@ -394,7 +1035,7 @@ fn build(
                     SymbolKind::Xor => client.send_command_message(ClientMessage {
                         id: next_message_id.get(),
                         kind: ClientMessageKind::AddXorGate {
-                            width: NonZeroU8::MIN, // TODO: use actual net width
+                            width,
                             inputs,
                             output,
                         },
@@ -402,7 +1043,7 @@ fn build(
                     SymbolKind::Not => client.send_command_message(ClientMessage {
                         id: next_message_id.get(),
                         kind: ClientMessageKind::AddNotGate {
-                            width: NonZeroU8::MIN, // TODO: use actual net width
+                            width,
                             input: inputs[0],
                             output,
                         },
@@ -412,6 +1053,28 @@ fn build(
         },
     );
 
+    // every resolved splitter can drive the nets another one waits for,
+    // splitters driven from neither side are left out as they have nothing to pass on
+    while !splitters.is_empty() {
+        let pending = splitters.len();
+        splitters.retain(
+            |splitter: &PendingSplitter| match splitter.resolve(&mut driven) {
+                Some(kind) => {
+                    client.send_command_message(ClientMessage {
+                        id: next_message_id.get(),
+                        kind,
+                    });
+                    false
+                }
+                None => true,
+            },
+        );
+
+        if splitters.len() == pending {
+            break;
+        }
+    }
+
     client.send_command_message(ClientMessage {
         id: next_message_id.get(),
         kind: ClientMessageKind::EndBuild,
@@ -457,7 +1120,14 @@ impl Plugin for ClientPlugin {
                 .run_if(resource_exists::<NetcodeClientTransport>),
         );
 
-        app.add_systems(OnEnter(SimulationState::Building), build);
+        app.add_systems(OnEnter(SimulationState::Building), (build, clear_traces));
+
+        app.init_resource::<SignalTraces>().add_systems(
+            PostUpdate,
+            record_traces
+                .run_if(in_state(SimulationActive))
+                .run_if(resource_exists_and_changed::<SimState>),
+        );
 
         app.add_systems(
             Update,
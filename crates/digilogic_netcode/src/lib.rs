@@ -1,14 +1,16 @@
 use renet::transport::*;
 use renet::*;
 use serde::{Deserialize, Serialize};
-use std::net::{SocketAddr, UdpSocket};
+#[cfg(feature = "server")]
+use std::net::SocketAddr;
+use std::net::UdpSocket;
 use std::num::NonZeroU8;
 use std::time::{Duration, SystemTime};
 
 pub type HashMap<K, V> = ahash::AHashMap<K, V>;
 
 pub const PROTOCOL_MAJOR_VERSION: u32 = 1;
-pub const PROTOCOL_MINOR_VERSION: u32 = 0;
+pub const PROTOCOL_MINOR_VERSION: u32 = 1;
 const PROTOCOL_VERSION: u64 =
     ((PROTOCOL_MAJOR_VERSION as u64) << 32) | (PROTOCOL_MINOR_VERSION as u64);
 
@@ -64,6 +66,28 @@ impl TryFrom<usize> for CellId {
     }
 }
 
+/// The kinds of flip-flops, which update their output on the rising edge of their clock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum FlipFlopKind {
+    /// Stores its single input.
+    D,
+    /// Sets its output if only the first input is high, resets it if only the second one is,
+    /// and toggles it if both are.
+    Jk,
+    /// Toggles its output if its single input is high.
+    T,
+}
+
+impl FlipFlopKind {
+    /// The number of data inputs of the kind, besides the clock and reset.
+    pub fn input_count(self) -> usize {
+        match self {
+            Self::D | Self::T => 1,
+            Self::Jk => 2,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ServerError {
     /// The engine does not implement the requested feature
@@ -136,6 +160,116 @@ enum ClientMessageKind {
         input: NetId,
         output: NetId,
     },
+    AddFlipFlop {
+        kind: FlipFlopKind,
+        width: NonZeroU8,
+        inputs: Vec<NetId>,
+        clock: NetId,
+        reset: Option<NetId>,
+        output: NetId,
+        inverted_output: Option<NetId>,
+    },
+    AddRegister {
+        width: NonZeroU8,
+        data: NetId,
+        enable: Option<NetId>,
+        clock: NetId,
+        reset: Option<NetId>,
+        output: NetId,
+    },
+    AddCounter {
+        width: NonZeroU8,
+        data: Option<NetId>,
+        load: Option<NetId>,
+        enable: Option<NetId>,
+        up: Option<NetId>,
+        clock: NetId,
+        reset: Option<NetId>,
+        output: NetId,
+        carry_out: Option<NetId>,
+    },
+    AddShiftRegister {
+        width: NonZeroU8,
+        serial_in: Option<NetId>,
+        data: Option<NetId>,
+        load: Option<NetId>,
+        enable: Option<NetId>,
+        left: Option<NetId>,
+        clock: NetId,
+        reset: Option<NetId>,
+        output: Option<NetId>,
+        serial_out: Option<NetId>,
+    },
+    AddMux {
+        width: NonZeroU8,
+        inputs: Vec<NetId>,
+        select: Vec<NetId>,
+        output: NetId,
+    },
+    AddDemux {
+        width: NonZeroU8,
+        input: NetId,
+        select: Vec<NetId>,
+        outputs: Vec<Option<NetId>>,
+    },
+    AddAdder {
+        width: NonZeroU8,
+        a: NetId,
+        b: NetId,
+        carry_in: Option<NetId>,
+        sum: Option<NetId>,
+        carry_out: Option<NetId>,
+    },
+    AddSubtractor {
+        width: NonZeroU8,
+        a: NetId,
+        b: NetId,
+        borrow_in: Option<NetId>,
+        difference: Option<NetId>,
+        borrow_out: Option<NetId>,
+    },
+    AddComparator {
+        width: NonZeroU8,
+        a: NetId,
+        b: NetId,
+        less: Option<NetId>,
+        equal: Option<NetId>,
+        greater: Option<NetId>,
+    },
+    AddSplit {
+        width: NonZeroU8,
+        input: NetId,
+        taps: Vec<Vec<u8>>,
+        outputs: Vec<NetId>,
+    },
+    AddMerge {
+        width: NonZeroU8,
+        inputs: Vec<NetId>,
+        taps: Vec<Vec<u8>>,
+        output: NetId,
+    },
+    AddConstant {
+        width: NonZeroU8,
+        bit_plane_0: Vec<u8>,
+        bit_plane_1: Vec<u8>,
+        output: NetId,
+    },
+    AddPull {
+        width: NonZeroU8,
+        net: NetId,
+        up: bool,
+    },
+    AddBuffer {
+        width: NonZeroU8,
+        input: NetId,
+        enable: NetId,
+        output: NetId,
+    },
+    AddOpenDrain {
+        width: NonZeroU8,
+        input: NetId,
+        output: NetId,
+    },
 
     SetNetDrive {
         net: NetId,
@@ -192,6 +326,7 @@ fn mask(bit_width: u8) -> u8 {
     }
 }
 
+#[cfg(any(feature = "server", test))]
 #[inline]
 fn unalign_byte(value: u8, bit_offset: u8) -> (u8, u8) {
     assert!(bit_offset < 8);
@@ -222,7 +357,9 @@ fn align_byte(low: u8, high: u8, bit_offset: u8) -> u8 {
     low | high
 }
 
+#[cfg(any(feature = "server", test))]
 impl SimState {
+    #[cfg(feature = "server")]
     fn reset(&mut self, order: u64) {
         self.order = order;
         self.bit_len = 0;
@@ -262,7 +399,9 @@ impl SimState {
         self.bit_len += bit_width.get() as u64;
         net_offset
     }
+}
 
+impl SimState {
     pub fn get_net(
         &self,
         offset: u64,
@@ -303,6 +442,29 @@ impl SimState {
             bit_plane_1[i] = align_byte(low_1, high_1, bit_offset) & mask;
         }
     }
+
+    /// Formats the state of a net as a binary literal, most significant bit first,
+    /// with `x` for undefined and `z` for high impedance bits.
+    pub fn format_net(&self, offset: u64, bit_width: NonZeroU8) -> String {
+        let byte_width = bit_width.get().div_ceil(8) as usize;
+        let mut bit_plane_0 = vec![0; byte_width];
+        let mut bit_plane_1 = vec![0; byte_width];
+        self.get_net(offset, bit_width, &mut bit_plane_0, &mut bit_plane_1);
+
+        let mut literal = String::from("0b");
+        for bit in (0..bit_width.get() as usize).rev() {
+            let mask = 1 << (bit % 8);
+            let plane_0 = (bit_plane_0[bit / 8] & mask) != 0;
+            let plane_1 = (bit_plane_1[bit / 8] & mask) != 0;
+            literal.push(match (plane_0, plane_1) {
+                (false, true) => '0',
+                (true, true) => '1',
+                (false, false) => 'z',
+                (true, false) => 'x',
+            });
+        }
+        literal
+    }
 }
 
 #[cfg(feature = "client")]
@@ -310,6 +472,11 @@ mod client;
 #[cfg(feature = "client")]
 pub use client::*;
 
+#[cfg(feature = "client")]
+mod trace;
+#[cfg(feature = "client")]
+pub use trace::*;
+
 #[cfg(feature = "server")]
 mod server;
 #[cfg(feature = "server")]
@@ -328,6 +495,15 @@ mod tests {
         };
     }
 
+    #[test]
+    fn format_net() {
+        let mut sim_state = SimState::default();
+        sim_state.push_net(nz!(1), &[0b1], &[0b1]);
+        let offset = sim_state.push_net(nz!(10), &[0b0110, 0b10], &[0b0011_1101, 0b11]);
+        assert_eq!(sim_state.format_net(0, nz!(1)), "0b1");
+        assert_eq!(sim_state.format_net(offset, nz!(10)), "0b10zz0001x0");
+    }
+
     #[test]
     fn insert_1_bit_net() {
         let mut sim_state = SimState::default();
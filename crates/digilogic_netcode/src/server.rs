@@ -72,6 +72,264 @@ pub trait SimServer {
         Err(ServerError::Unsupported)
     }
 
+    /// Adds a flip-flop with `kind.input_count()` data inputs.
+    /// The reset is synchronous and clears the output on the next rising clock edge.
+    #[allow(clippy::too_many_arguments)]
+    fn add_flip_flop(
+        &mut self,
+        client_id: ClientId,
+        kind: FlipFlopKind,
+        width: NonZeroU8,
+        inputs: &[Self::NetId],
+        clock: Self::NetId,
+        reset: Option<Self::NetId>,
+        output: Self::NetId,
+        inverted_output: Option<Self::NetId>,
+    ) -> ServerResult<Self::CellId> {
+        let _ = (
+            client_id,
+            kind,
+            width,
+            inputs,
+            clock,
+            reset,
+            output,
+            inverted_output,
+        );
+        Err(ServerError::Unsupported)
+    }
+
+    /// Adds a register that loads `data` on the rising clock edge while `enable` is high,
+    /// which is always the case if it is `None`. The reset is synchronous like for flip-flops
+    /// and takes effect even if the register is not enabled.
+    #[allow(clippy::too_many_arguments)]
+    fn add_register(
+        &mut self,
+        client_id: ClientId,
+        width: NonZeroU8,
+        data: Self::NetId,
+        enable: Option<Self::NetId>,
+        clock: Self::NetId,
+        reset: Option<Self::NetId>,
+        output: Self::NetId,
+    ) -> ServerResult<Self::CellId> {
+        let _ = (client_id, width, data, enable, clock, reset, output);
+        Err(ServerError::Unsupported)
+    }
+
+    /// Adds a counter that steps on the rising clock edge while `enable` is high,
+    /// upwards while `up` is high, and loads `data` instead while `load` is high.
+    /// Missing control inputs count up on every clock edge without loading,
+    /// `data` has to be connected if `load` is.
+    /// The one bit wide carry output is high at the last value before the counter wraps around
+    /// in the current direction.
+    #[allow(clippy::too_many_arguments)]
+    fn add_counter(
+        &mut self,
+        client_id: ClientId,
+        width: NonZeroU8,
+        data: Option<Self::NetId>,
+        load: Option<Self::NetId>,
+        enable: Option<Self::NetId>,
+        up: Option<Self::NetId>,
+        clock: Self::NetId,
+        reset: Option<Self::NetId>,
+        output: Self::NetId,
+        carry_out: Option<Self::NetId>,
+    ) -> ServerResult<Self::CellId> {
+        let _ = (
+            client_id, width, data, load, enable, up, clock, reset, output, carry_out,
+        );
+        Err(ServerError::Unsupported)
+    }
+
+    /// Adds a shift register that shifts by one bit towards the lower bits on the rising clock edge
+    /// while `enable` is high, with `serial_in` entering at the highest bit and the lowest bit
+    /// driving `serial_out`. While `left` is high it shifts towards the higher bits instead,
+    /// with `serial_in` entering at the lowest bit and the highest bit driving `serial_out`.
+    /// Loading works like for counters, a missing serial input shifts in zeros.
+    #[allow(clippy::too_many_arguments)]
+    fn add_shift_register(
+        &mut self,
+        client_id: ClientId,
+        width: NonZeroU8,
+        serial_in: Option<Self::NetId>,
+        data: Option<Self::NetId>,
+        load: Option<Self::NetId>,
+        enable: Option<Self::NetId>,
+        left: Option<Self::NetId>,
+        clock: Self::NetId,
+        reset: Option<Self::NetId>,
+        output: Option<Self::NetId>,
+        serial_out: Option<Self::NetId>,
+    ) -> ServerResult<Self::CellId> {
+        let _ = (
+            client_id, width, serial_in, data, load, enable, left, clock, reset, output, serial_out,
+        );
+        Err(ServerError::Unsupported)
+    }
+
+    /// Adds a multiplexer with `2^select.len()` inputs.
+    /// The select inputs are one bit wide and ordered from the lowest bit.
+    fn add_mux(
+        &mut self,
+        client_id: ClientId,
+        width: NonZeroU8,
+        inputs: &[Self::NetId],
+        select: &[Self::NetId],
+        output: Self::NetId,
+    ) -> ServerResult<Self::CellId> {
+        let _ = (client_id, width, inputs, select, output);
+        Err(ServerError::Unsupported)
+    }
+
+    /// Adds a demultiplexer with `2^select.len()` outputs, of which only the connected ones are driven.
+    /// The select inputs are one bit wide and ordered from the lowest bit.
+    fn add_demux(
+        &mut self,
+        client_id: ClientId,
+        width: NonZeroU8,
+        input: Self::NetId,
+        select: &[Self::NetId],
+        outputs: &[Option<Self::NetId>],
+    ) -> ServerResult<Self::CellId> {
+        let _ = (client_id, width, input, select, outputs);
+        Err(ServerError::Unsupported)
+    }
+
+    /// Adds an adder of `a`, `b` and the one bit wide carry input, which is low if it is `None`.
+    /// Like for all arithmetic cells, only the outputs that are connected are driven.
+    #[allow(clippy::too_many_arguments)]
+    fn add_adder(
+        &mut self,
+        client_id: ClientId,
+        width: NonZeroU8,
+        a: Self::NetId,
+        b: Self::NetId,
+        carry_in: Option<Self::NetId>,
+        sum: Option<Self::NetId>,
+        carry_out: Option<Self::NetId>,
+    ) -> ServerResult<Self::CellId> {
+        let _ = (client_id, width, a, b, carry_in, sum, carry_out);
+        Err(ServerError::Unsupported)
+    }
+
+    /// Adds a subtractor of `b` and the one bit wide borrow input from `a`.
+    /// The borrow input is low if it is `None`.
+    #[allow(clippy::too_many_arguments)]
+    fn add_subtractor(
+        &mut self,
+        client_id: ClientId,
+        width: NonZeroU8,
+        a: Self::NetId,
+        b: Self::NetId,
+        borrow_in: Option<Self::NetId>,
+        difference: Option<Self::NetId>,
+        borrow_out: Option<Self::NetId>,
+    ) -> ServerResult<Self::CellId> {
+        let _ = (client_id, width, a, b, borrow_in, difference, borrow_out);
+        Err(ServerError::Unsupported)
+    }
+
+    /// Adds an unsigned comparator of `a` and `b`, whose outputs are one bit wide.
+    #[allow(clippy::too_many_arguments)]
+    fn add_comparator(
+        &mut self,
+        client_id: ClientId,
+        width: NonZeroU8,
+        a: Self::NetId,
+        b: Self::NetId,
+        less: Option<Self::NetId>,
+        equal: Option<Self::NetId>,
+        greater: Option<Self::NetId>,
+    ) -> ServerResult<Self::CellId> {
+        let _ = (client_id, width, a, b, less, equal, greater);
+        Err(ServerError::Unsupported)
+    }
+
+    /// Adds a splitter driving each of `outputs` with the bits of the `width` bits wide `input`
+    /// listed by the tap of the same index, the first listed bit becoming the lowest one.
+    fn add_split(
+        &mut self,
+        client_id: ClientId,
+        width: NonZeroU8,
+        input: Self::NetId,
+        taps: &[Vec<u8>],
+        outputs: &[Self::NetId],
+    ) -> ServerResult<Self::CellId> {
+        let _ = (client_id, width, input, taps, outputs);
+        Err(ServerError::Unsupported)
+    }
+
+    /// Adds a merger driving the bits of the `width` bits wide `output` listed by each tap
+    /// with the input of the same index, the reverse of [`add_split`](Self::add_split).
+    /// Bits that are not part of any tap are left high impedance.
+    fn add_merge(
+        &mut self,
+        client_id: ClientId,
+        width: NonZeroU8,
+        inputs: &[Self::NetId],
+        taps: &[Vec<u8>],
+        output: Self::NetId,
+    ) -> ServerResult<Self::CellId> {
+        let _ = (client_id, width, inputs, taps, output);
+        Err(ServerError::Unsupported)
+    }
+
+    /// Adds a cell permanently driving `output` with the state given by the bit planes,
+    /// in the same encoding as [`set_net_drive`](Self::set_net_drive).
+    fn add_constant(
+        &mut self,
+        client_id: ClientId,
+        width: NonZeroU8,
+        bit_plane_0: &[u8],
+        bit_plane_1: &[u8],
+        output: Self::NetId,
+    ) -> ServerResult<Self::CellId> {
+        let _ = (client_id, width, bit_plane_0, bit_plane_1, output);
+        Err(ServerError::Unsupported)
+    }
+
+    /// Adds a weak drive pulling the bits of `net` high or low that nothing else drives.
+    /// Pulls are not cells, a strong driver of the net overrides them instead of conflicting.
+    fn add_pull(
+        &mut self,
+        client_id: ClientId,
+        width: NonZeroU8,
+        net: Self::NetId,
+        up: bool,
+    ) -> ServerResult<()> {
+        let _ = (client_id, width, net, up);
+        Err(ServerError::Unsupported)
+    }
+
+    /// Adds a buffer driving `output` with `input` while `enable` is high,
+    /// and leaving it high impedance while `enable` is low.
+    fn add_buffer(
+        &mut self,
+        client_id: ClientId,
+        width: NonZeroU8,
+        input: Self::NetId,
+        enable: Self::NetId,
+        output: Self::NetId,
+    ) -> ServerResult<Self::CellId> {
+        let _ = (client_id, width, input, enable, output);
+        Err(ServerError::Unsupported)
+    }
+
+    /// Adds an open-drain driver pulling the bits of `output` low where `input` is low,
+    /// and leaving them high impedance where it is high.
+    fn add_open_drain(
+        &mut self,
+        client_id: ClientId,
+        width: NonZeroU8,
+        input: Self::NetId,
+        output: Self::NetId,
+    ) -> ServerResult<Self::CellId> {
+        let _ = (client_id, width, input, output);
+        Err(ServerError::Unsupported)
+    }
+
     fn set_net_drive(
         &mut self,
         client_id: ClientId,
@@ -166,11 +424,6 @@ impl<T> CellMap<T> {
         self.map.push(value);
         Ok(CellId(index))
     }
-
-    #[inline]
-    fn values(&self) -> impl Iterator<Item = &T> {
-        self.map.iter()
-    }
 }
 
 impl<T> Index<CellId> for CellMap<T> {
@@ -223,6 +476,8 @@ struct Adapter<S: SimServer> {
     inner: S,
     client_state: HashMap<ClientId, AdapterClientState<S>>,
     net_id_buffer: Vec<S::NetId>,
+    select_buffer: Vec<S::NetId>,
+    output_buffer: Vec<Option<S::NetId>>,
     sim_state: SimState,
 }
 
@@ -232,6 +487,8 @@ impl<S: SimServer> Adapter<S> {
             inner,
             client_state: HashMap::default(),
             net_id_buffer: Vec::new(),
+            select_buffer: Vec::new(),
+            output_buffer: Vec::new(),
             sim_state: SimState::default(),
         }
     }
@@ -252,6 +509,30 @@ macro_rules! client_state {
     };
 }
 
+/// Forwards an arithmetic cell with two operands and three optional ports to the inner server.
+macro_rules! arithmetic_impl {
+    ($name:ident) => {
+        #[allow(clippy::too_many_arguments)]
+        fn $name(
+            &mut self,
+            client_id: ClientId,
+            width: NonZeroU8,
+            a: NetId,
+            b: NetId,
+            c: Option<NetId>,
+            d: Option<NetId>,
+            e: Option<NetId>,
+        ) -> ServerResult<()> {
+            let client_state = client_state!(mut self, client_id);
+            let [a, b] = [a, b].map(|id| client_state.net_map[id]);
+            let [c, d, e] = [c, d, e].map(|id| id.map(|id| client_state.net_map[id]));
+            let cell_id = self.inner.$name(client_id, width, a, b, c, d, e)?;
+            client_state.cell_map.insert(cell_id)?;
+            Ok(())
+        }
+    };
+}
+
 macro_rules! gate_impl {
     ($name:ident) => {
         fn $name(
@@ -324,6 +605,298 @@ impl<S: SimServer> Adapter<S> {
         Ok(())
     }
 
+    #[allow(clippy::too_many_arguments)]
+    fn add_flip_flop(
+        &mut self,
+        client_id: ClientId,
+        kind: FlipFlopKind,
+        width: NonZeroU8,
+        inputs: &[NetId],
+        clock: NetId,
+        reset: Option<NetId>,
+        output: NetId,
+        inverted_output: Option<NetId>,
+    ) -> ServerResult<()> {
+        if inputs.len() != kind.input_count() {
+            return Err(ServerError::InvalidInputCount);
+        }
+
+        let client_state = client_state!(mut self, client_id);
+        self.net_id_buffer.clear();
+        self.net_id_buffer
+            .extend(inputs.iter().map(|&id| client_state.net_map[id]));
+        let clock = client_state.net_map[clock];
+        let reset = reset.map(|reset| client_state.net_map[reset]);
+        let output = client_state.net_map[output];
+        let inverted_output = inverted_output.map(|output| client_state.net_map[output]);
+        let cell_id = self.inner.add_flip_flop(
+            client_id,
+            kind,
+            width,
+            &self.net_id_buffer,
+            clock,
+            reset,
+            output,
+            inverted_output,
+        )?;
+        client_state.cell_map.insert(cell_id)?;
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn add_register(
+        &mut self,
+        client_id: ClientId,
+        width: NonZeroU8,
+        data: NetId,
+        enable: Option<NetId>,
+        clock: NetId,
+        reset: Option<NetId>,
+        output: NetId,
+    ) -> ServerResult<()> {
+        let client_state = client_state!(mut self, client_id);
+        let [data, clock, output] = [data, clock, output].map(|id| client_state.net_map[id]);
+        let [enable, reset] = [enable, reset].map(|id| id.map(|id| client_state.net_map[id]));
+        let cell_id = self
+            .inner
+            .add_register(client_id, width, data, enable, clock, reset, output)?;
+        client_state.cell_map.insert(cell_id)?;
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn add_counter(
+        &mut self,
+        client_id: ClientId,
+        width: NonZeroU8,
+        data: Option<NetId>,
+        load: Option<NetId>,
+        enable: Option<NetId>,
+        up: Option<NetId>,
+        clock: NetId,
+        reset: Option<NetId>,
+        output: NetId,
+        carry_out: Option<NetId>,
+    ) -> ServerResult<()> {
+        let client_state = client_state!(mut self, client_id);
+        let [clock, output] = [clock, output].map(|id| client_state.net_map[id]);
+        let [data, load, enable, up, reset, carry_out] = [data, load, enable, up, reset, carry_out]
+            .map(|id| id.map(|id| client_state.net_map[id]));
+        let cell_id = self.inner.add_counter(
+            client_id, width, data, load, enable, up, clock, reset, output, carry_out,
+        )?;
+        client_state.cell_map.insert(cell_id)?;
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn add_shift_register(
+        &mut self,
+        client_id: ClientId,
+        width: NonZeroU8,
+        serial_in: Option<NetId>,
+        data: Option<NetId>,
+        load: Option<NetId>,
+        enable: Option<NetId>,
+        left: Option<NetId>,
+        clock: NetId,
+        reset: Option<NetId>,
+        output: Option<NetId>,
+        serial_out: Option<NetId>,
+    ) -> ServerResult<()> {
+        let client_state = client_state!(mut self, client_id);
+        let clock = client_state.net_map[clock];
+        let [serial_in, data, load, enable, left, reset, output, serial_out] = [
+            serial_in, data, load, enable, left, reset, output, serial_out,
+        ]
+        .map(|id| id.map(|id| client_state.net_map[id]));
+        let cell_id = self.inner.add_shift_register(
+            client_id, width, serial_in, data, load, enable, left, clock, reset, output, serial_out,
+        )?;
+        client_state.cell_map.insert(cell_id)?;
+        Ok(())
+    }
+
+    fn add_mux(
+        &mut self,
+        client_id: ClientId,
+        width: NonZeroU8,
+        inputs: &[NetId],
+        select: &[NetId],
+        output: NetId,
+    ) -> ServerResult<()> {
+        if Some(inputs.len()) != 1usize.checked_shl(select.len() as u32) {
+            return Err(ServerError::InvalidInputCount);
+        }
+
+        let client_state = client_state!(mut self, client_id);
+        self.net_id_buffer.clear();
+        self.net_id_buffer
+            .extend(inputs.iter().map(|&id| client_state.net_map[id]));
+        self.select_buffer.clear();
+        self.select_buffer
+            .extend(select.iter().map(|&id| client_state.net_map[id]));
+        let output = client_state.net_map[output];
+        let cell_id = self.inner.add_mux(
+            client_id,
+            width,
+            &self.net_id_buffer,
+            &self.select_buffer,
+            output,
+        )?;
+        client_state.cell_map.insert(cell_id)?;
+        Ok(())
+    }
+
+    fn add_demux(
+        &mut self,
+        client_id: ClientId,
+        width: NonZeroU8,
+        input: NetId,
+        select: &[NetId],
+        outputs: &[Option<NetId>],
+    ) -> ServerResult<()> {
+        if Some(outputs.len()) != 1usize.checked_shl(select.len() as u32) {
+            return Err(ServerError::InvalidInputCount);
+        }
+
+        let client_state = client_state!(mut self, client_id);
+        let input = client_state.net_map[input];
+        self.select_buffer.clear();
+        self.select_buffer
+            .extend(select.iter().map(|&id| client_state.net_map[id]));
+        self.output_buffer.clear();
+        self.output_buffer.extend(
+            outputs
+                .iter()
+                .map(|output| output.map(|id| client_state.net_map[id])),
+        );
+        let cell_id = self.inner.add_demux(
+            client_id,
+            width,
+            input,
+            &self.select_buffer,
+            &self.output_buffer,
+        )?;
+        client_state.cell_map.insert(cell_id)?;
+        Ok(())
+    }
+
+    arithmetic_impl!(add_adder);
+    arithmetic_impl!(add_subtractor);
+    arithmetic_impl!(add_comparator);
+
+    fn add_split(
+        &mut self,
+        client_id: ClientId,
+        width: NonZeroU8,
+        input: NetId,
+        taps: &[Vec<u8>],
+        outputs: &[NetId],
+    ) -> ServerResult<()> {
+        if taps.len() != outputs.len() {
+            return Err(ServerError::InvalidInputCount);
+        }
+
+        let client_state = client_state!(mut self, client_id);
+        let input = client_state.net_map[input];
+        self.net_id_buffer.clear();
+        self.net_id_buffer
+            .extend(outputs.iter().map(|&id| client_state.net_map[id]));
+        let cell_id = self
+            .inner
+            .add_split(client_id, width, input, taps, &self.net_id_buffer)?;
+        client_state.cell_map.insert(cell_id)?;
+        Ok(())
+    }
+
+    fn add_merge(
+        &mut self,
+        client_id: ClientId,
+        width: NonZeroU8,
+        inputs: &[NetId],
+        taps: &[Vec<u8>],
+        output: NetId,
+    ) -> ServerResult<()> {
+        if taps.len() != inputs.len() {
+            return Err(ServerError::InvalidInputCount);
+        }
+
+        let client_state = client_state!(mut self, client_id);
+        self.net_id_buffer.clear();
+        self.net_id_buffer
+            .extend(inputs.iter().map(|&id| client_state.net_map[id]));
+        let output = client_state.net_map[output];
+        let cell_id = self
+            .inner
+            .add_merge(client_id, width, &self.net_id_buffer, taps, output)?;
+        client_state.cell_map.insert(cell_id)?;
+        Ok(())
+    }
+
+    fn add_constant(
+        &mut self,
+        client_id: ClientId,
+        width: NonZeroU8,
+        bit_plane_0: &[u8],
+        bit_plane_1: &[u8],
+        output: NetId,
+    ) -> ServerResult<()> {
+        let client_state = client_state!(mut self, client_id);
+        let output = client_state.net_map[output];
+        let cell_id =
+            self.inner
+                .add_constant(client_id, width, bit_plane_0, bit_plane_1, output)?;
+        client_state.cell_map.insert(cell_id)?;
+        Ok(())
+    }
+
+    fn add_pull(
+        &mut self,
+        client_id: ClientId,
+        width: NonZeroU8,
+        net: NetId,
+        up: bool,
+    ) -> ServerResult<()> {
+        let client_state = client_state!(self, client_id);
+        let net = client_state.net_map[net];
+        self.inner.add_pull(client_id, width, net, up)
+    }
+
+    fn add_buffer(
+        &mut self,
+        client_id: ClientId,
+        width: NonZeroU8,
+        input: NetId,
+        enable: NetId,
+        output: NetId,
+    ) -> ServerResult<()> {
+        let client_state = client_state!(mut self, client_id);
+        let input = client_state.net_map[input];
+        let enable = client_state.net_map[enable];
+        let output = client_state.net_map[output];
+        let cell_id = self
+            .inner
+            .add_buffer(client_id, width, input, enable, output)?;
+        client_state.cell_map.insert(cell_id)?;
+        Ok(())
+    }
+
+    fn add_open_drain(
+        &mut self,
+        client_id: ClientId,
+        width: NonZeroU8,
+        input: NetId,
+        output: NetId,
+    ) -> ServerResult<()> {
+        let client_state = client_state!(mut self, client_id);
+        let input = client_state.net_map[input];
+        let output = client_state.net_map[output];
+        let cell_id = self.inner.add_open_drain(client_id, width, input, output)?;
+        client_state.cell_map.insert(cell_id)?;
+        Ok(())
+    }
+
     fn set_net_drive(
         &mut self,
         client_id: ClientId,
@@ -406,6 +979,127 @@ fn process_message<S: SimServer>(
             input,
             output,
         } => adapter.add_not_gate(client_id, width, input, output)?,
+        ClientMessageKind::AddFlipFlop {
+            kind,
+            width,
+            inputs,
+            clock,
+            reset,
+            output,
+            inverted_output,
+        } => adapter.add_flip_flop(
+            client_id,
+            kind,
+            width,
+            &inputs,
+            clock,
+            reset,
+            output,
+            inverted_output,
+        )?,
+        ClientMessageKind::AddRegister {
+            width,
+            data,
+            enable,
+            clock,
+            reset,
+            output,
+        } => adapter.add_register(client_id, width, data, enable, clock, reset, output)?,
+        ClientMessageKind::AddCounter {
+            width,
+            data,
+            load,
+            enable,
+            up,
+            clock,
+            reset,
+            output,
+            carry_out,
+        } => adapter.add_counter(
+            client_id, width, data, load, enable, up, clock, reset, output, carry_out,
+        )?,
+        ClientMessageKind::AddShiftRegister {
+            width,
+            serial_in,
+            data,
+            load,
+            enable,
+            left,
+            clock,
+            reset,
+            output,
+            serial_out,
+        } => adapter.add_shift_register(
+            client_id, width, serial_in, data, load, enable, left, clock, reset, output, serial_out,
+        )?,
+        ClientMessageKind::AddMux {
+            width,
+            inputs,
+            select,
+            output,
+        } => adapter.add_mux(client_id, width, &inputs, &select, output)?,
+        ClientMessageKind::AddDemux {
+            width,
+            input,
+            select,
+            outputs,
+        } => adapter.add_demux(client_id, width, input, &select, &outputs)?,
+        ClientMessageKind::AddAdder {
+            width,
+            a,
+            b,
+            carry_in,
+            sum,
+            carry_out,
+        } => adapter.add_adder(client_id, width, a, b, carry_in, sum, carry_out)?,
+        ClientMessageKind::AddSubtractor {
+            width,
+            a,
+            b,
+            borrow_in,
+            difference,
+            borrow_out,
+        } => adapter.add_subtractor(client_id, width, a, b, borrow_in, difference, borrow_out)?,
+        ClientMessageKind::AddComparator {
+            width,
+            a,
+            b,
+            less,
+            equal,
+            greater,
+        } => adapter.add_comparator(client_id, width, a, b, less, equal, greater)?,
+        ClientMessageKind::AddSplit {
+            width,
+            input,
+            taps,
+            outputs,
+        } => adapter.add_split(client_id, width, input, &taps, &outputs)?,
+        ClientMessageKind::AddMerge {
+            width,
+            inputs,
+            taps,
+            output,
+        } => adapter.add_merge(client_id, width, &inputs, &taps, output)?,
+        ClientMessageKind::AddConstant {
+            width,
+            bit_plane_0,
+            bit_plane_1,
+            output,
+        } => adapter.add_constant(client_id, width, &bit_plane_0, &bit_plane_1, output)?,
+        ClientMessageKind::AddPull { width, net, up } => {
+            adapter.add_pull(client_id, width, net, up)?;
+        }
+        ClientMessageKind::AddBuffer {
+            width,
+            input,
+            enable,
+            output,
+        } => adapter.add_buffer(client_id, width, input, enable, output)?,
+        ClientMessageKind::AddOpenDrain {
+            width,
+            input,
+            output,
+        } => adapter.add_open_drain(client_id, width, input, output)?,
 
         ClientMessageKind::SetNetDrive {
             net,
@@ -476,7 +1170,10 @@ pub fn run_server<S: SimServer>(
         client_ids.clear();
         client_ids.extend(server.clients_id_iter());
         for &client_id in &client_ids {
-            while let Some(message) = server.receive_message(client_id, COMMAND_CHANNEL_ID) {
+            loop {
+                let Some(message) = server.receive_message(client_id, COMMAND_CHANNEL_ID) else {
+                    break;
+                };
                 let message: ClientMessage =
                     rmp_serde::from_slice(&message).expect("invalid client message");
 
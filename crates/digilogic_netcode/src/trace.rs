@@ -0,0 +1,188 @@
+//! Records the values of traced nets while simulating, for timing diagrams.
+
+use crate::{SimState, StateOffset};
+use bevy_ecs::prelude::*;
+use bevy_ecs::system::lifetimeless::Read;
+use digilogic_core::components::{BitWidth, Net, Traced};
+use digilogic_core::HashMap;
+use std::ops::Range;
+
+/// The values of the traced nets, recorded each time the simulation state updates.
+/// Every update is one step of the recording.
+#[derive(Debug, Default, Resource)]
+pub struct SignalTraces {
+    steps: u64,
+    /// The values of each net as binary literals, with the steps they changed at.
+    changes: HashMap<Entity, Vec<(u64, String)>>,
+}
+
+impl SignalTraces {
+    /// How many steps have been recorded.
+    #[inline]
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    pub fn clear(&mut self) {
+        self.steps = 0;
+        self.changes.clear();
+    }
+
+    fn record(&mut self, net: Entity, value: String) {
+        let step = self.steps;
+        let changes = self.changes.entry(net).or_default();
+        if changes.last().map(|(_, last)| last) != Some(&value) {
+            changes.push((step, value));
+        }
+    }
+
+    /// The value of a net at a step, `None` if it wasn't traced yet.
+    pub fn value_at(&self, net: Entity, step: u64) -> Option<&str> {
+        let changes = self.changes.get(&net)?;
+        let index = changes.partition_point(|(changed, _)| *changed <= step);
+        index.checked_sub(1).map(|index| changes[index].1.as_str())
+    }
+
+    /// Writes the values of `signals` during `window` as a [WaveDrom](https://wavedrom.com)
+    /// timing diagram, one cycle per step.
+    pub fn to_wavedrom(&self, signals: &[(Entity, &str)], window: Range<u64>) -> String {
+        let signals = signals
+            .iter()
+            .map(|&(net, name)| {
+                let mut wave = String::new();
+                let mut data = Vec::new();
+                let mut previous = None;
+                for step in window.clone() {
+                    let value = self.value_at(net, step);
+                    if (step > window.start) && (value == previous) {
+                        wave.push('.');
+                        continue;
+                    }
+                    previous = value;
+
+                    let (state, label) = wave_state(value);
+                    wave.push(state);
+                    data.extend(label);
+                }
+
+                let mut signal = serde_json::json!({ "name": name, "wave": wave });
+                if !data.is_empty() {
+                    signal["data"] = data.into();
+                }
+                signal
+            })
+            .collect::<Vec<_>>();
+
+        let diagram = serde_json::json!({
+            "signal": signals,
+            "head": { "tock": window.start },
+        });
+        serde_json::to_string_pretty(&diagram).unwrap()
+    }
+}
+
+/// The WaveDrom state of a value, with the label of a bus value.
+/// Buses with some undefined or high impedance bits are drawn as undefined.
+fn wave_state(value: Option<&str>) -> (char, Option<String>) {
+    let Some(bits) = value.map(|value| value.trim_start_matches("0b")) else {
+        return ('x', None);
+    };
+
+    if let [bit] = bits.as_bytes() {
+        return (*bit as char, None);
+    }
+    if bits.chars().all(|bit| bit == 'z') {
+        ('z', None)
+    } else if bits.contains(['x', 'z']) {
+        ('x', None)
+    } else {
+        ('=', Some(binary_to_hex(bits)))
+    }
+}
+
+fn binary_to_hex(bits: &str) -> String {
+    let bits = bits.as_bytes();
+    let mut digits = bits
+        .rchunks(4)
+        .map(|chunk| {
+            let digit = chunk
+                .iter()
+                .fold(0, |digit, &bit| (digit << 1) | u32::from(bit == b'1'));
+            char::from_digit(digit, 16).unwrap().to_ascii_uppercase()
+        })
+        .collect::<Vec<_>>();
+    while (digits.len() > 1) && (digits.last() == Some(&'0')) {
+        digits.pop();
+    }
+    let mut hex = String::from("0x");
+    hex.extend(digits.iter().rev());
+    hex
+}
+
+type TracedNetQuery<'w, 's> =
+    Query<'w, 's, (Entity, Read<BitWidth>, Read<StateOffset>), (With<Net>, With<Traced>)>;
+
+pub(crate) fn record_traces(
+    mut traces: ResMut<SignalTraces>,
+    sim_state: Res<SimState>,
+    nets: TracedNetQuery,
+) {
+    for (net, bit_width, offset) in nets.iter() {
+        // the state can still be from before the circuit was built
+        if (offset.0 + bit_width.0.get() as u64) > sim_state.bit_len {
+            continue;
+        }
+        traces.record(net, sim_state.format_net(offset.0, bit_width.0));
+    }
+    traces.steps += 1;
+}
+
+pub(crate) fn clear_traces(mut traces: ResMut<SignalTraces>) {
+    traces.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wavedrom() {
+        let clock = Entity::from_raw(1);
+        let bus = Entity::from_raw(2);
+
+        let mut traces = SignalTraces::default();
+        for (clock_value, bus_value) in [
+            ("0b0", "0bzzzz"),
+            ("0b1", "0b0101"),
+            ("0b0", "0b0101"),
+            ("0b1", "0b1x00"),
+            ("0b1", "0b1100"),
+        ] {
+            traces.record(clock, clock_value.into());
+            traces.record(bus, bus_value.into());
+            traces.steps += 1;
+        }
+        assert_eq!(traces.value_at(bus, 2), Some("0b0101"));
+        assert_eq!(traces.value_at(Entity::from_raw(3), 0), None);
+
+        let diagram = traces.to_wavedrom(&[(clock, "clk"), (bus, "data")], 1..5);
+        let diagram: serde_json::Value = serde_json::from_str(&diagram).unwrap();
+        assert_eq!(
+            diagram,
+            serde_json::json!({
+                "signal": [
+                    { "name": "clk", "wave": "101." },
+                    { "name": "data", "wave": "=.x=", "data": ["0x5", "0xC"] },
+                ],
+                "head": { "tock": 1 },
+            })
+        );
+    }
+
+    #[test]
+    fn hex_labels() {
+        assert_eq!(binary_to_hex("0000"), "0x0");
+        assert_eq!(binary_to_hex("110"), "0x6");
+        assert_eq!(binary_to_hex("111100001"), "0x1E1");
+    }
+}
@@ -0,0 +1,478 @@
+//! A WebSocket server that lets external tools, like grading scripts or notebooks,
+//! drive a running instance: open files, set inputs, step the simulation,
+//! query net values and export images of circuits.
+//!
+//! Clients send JSON objects naming a `command`, with an optional `id` that is echoed back:
+//! ```json
+//! {"id": 1, "command": "set_input", "designator": "IN1", "value": "0b1"}
+//! ```
+//! Every request gets a reply carrying either a `result` or an `error`:
+//! ```json
+//! {"id": 1, "result": null}
+//! ```
+//!
+//! The commands are
+//! - `open` with a `path`, which loads a project or circuit file,
+//! - `circuits`, the names of the open circuits,
+//! - `set_input` with a `designator` and a `value` in the syntax of
+//!   [`LogicState::parse`], which drives an input and evaluates the circuit,
+//! - `step`, which evaluates the circuit,
+//! - `query_nets`, the name, width and value of every net, with the value `null`
+//!   while the simulation isn't running,
+//! - `export` with a `path` ending in `.svg`, which writes an image of the schematic of a
+//!   circuit.
+//!
+//! Commands that work on circuits take an optional `circuit` name, without one they
+//! work on all open circuits. Loading files and evaluating happens in the background,
+//! so their effects show up in the results of later commands.
+//!
+//! The server only accepts connections from the same machine.
+
+pub mod websocket;
+
+use aery::prelude::*;
+use anyhow::{anyhow, bail, Result};
+use bevy_ecs::prelude::*;
+use bevy_ecs::system::lifetimeless::Read;
+use bevy_ecs::system::RunSystemOnce;
+use bevy_state::prelude::*;
+use digilogic_core::components::*;
+use digilogic_core::events::{CircuitLoadEvent, ErrorEvent, ProjectLoadEvent};
+use digilogic_core::states::SimulationState;
+use digilogic_netcode::{Eval, SimState, StateOffset};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::io::{self, BufReader};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::num::NonZeroU8;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use websocket::{Message, Role};
+
+/// How long the listener waits before checking for new connections again.
+const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The port the server listens on, 0 stops it.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Resource)]
+pub struct RemoteControl {
+    pub port: u16,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+enum Command {
+    Open {
+        path: PathBuf,
+    },
+    Circuits,
+    SetInput {
+        #[serde(default)]
+        circuit: Option<String>,
+        designator: String,
+        value: String,
+    },
+    Step,
+    QueryNets {
+        #[serde(default)]
+        circuit: Option<String>,
+    },
+    Export {
+        #[serde(default)]
+        circuit: Option<String>,
+        path: PathBuf,
+    },
+}
+
+type CommandResult = Result<Value, String>;
+
+/// A command received by a connection, waiting to be run on the world.
+#[derive(Debug)]
+struct PendingCommand {
+    command: Command,
+    reply: Sender<CommandResult>,
+}
+
+#[derive(Debug, Resource)]
+struct RemoteServer {
+    stop: Arc<AtomicBool>,
+    commands: Mutex<Receiver<PendingCommand>>,
+}
+
+impl RemoteServer {
+    fn start(port: u16) -> io::Result<Self> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+        listener.set_nonblocking(true)?;
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let (sender, receiver) = mpsc::channel();
+        let listener_stop = Arc::clone(&stop);
+        std::thread::Builder::new()
+            .name("remote control".into())
+            .spawn(move || listen(listener, listener_stop, sender))?;
+
+        bevy_log::info!("remote control listening on port {port}");
+        Ok(Self {
+            stop,
+            commands: Mutex::new(receiver),
+        })
+    }
+}
+
+impl Drop for RemoteServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+fn listen(listener: TcpListener, stop: Arc<AtomicBool>, commands: Sender<PendingCommand>) {
+    while !stop.load(Ordering::Relaxed) {
+        let result = listener.accept();
+        match result {
+            Ok((stream, addr)) => {
+                bevy_log::info!("remote control client connected from {addr}");
+                let stop = Arc::clone(&stop);
+                let commands = commands.clone();
+                std::thread::spawn(move || {
+                    if let Err(err) = serve(stream, &stop, &commands) {
+                        bevy_log::debug!("remote control client disconnected: {err}");
+                    }
+                });
+            }
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                std::thread::sleep(ACCEPT_POLL_INTERVAL);
+            }
+            Err(err) => {
+                bevy_log::warn!("remote control: {err}");
+                std::thread::sleep(ACCEPT_POLL_INTERVAL);
+            }
+        }
+    }
+}
+
+fn serve(
+    stream: TcpStream,
+    stop: &AtomicBool,
+    commands: &Sender<PendingCommand>,
+) -> io::Result<()> {
+    // some platforms pass on the non-blocking mode of the listener
+    stream.set_nonblocking(false)?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+    websocket::handshake(&mut reader, &mut writer)?;
+
+    loop {
+        let message = websocket::read_message(&mut reader)?;
+        if stop.load(Ordering::Relaxed) {
+            return websocket::write_close(&mut writer, Role::Server);
+        }
+
+        match message {
+            Message::Text(text) => {
+                let Some(reply) = dispatch(&text, commands) else {
+                    return websocket::write_close(&mut writer, Role::Server);
+                };
+                websocket::write_text(&mut writer, Role::Server, &reply)?;
+            }
+            Message::Binary(_) => {
+                let reply = reply(Value::Null, Err("expected a text message".into()));
+                websocket::write_text(&mut writer, Role::Server, &reply)?;
+            }
+            Message::Ping(payload) => websocket::write_pong(&mut writer, Role::Server, &payload)?,
+            Message::Close => return websocket::write_close(&mut writer, Role::Server),
+        }
+    }
+}
+
+fn reply(id: Value, result: CommandResult) -> String {
+    let reply = match result {
+        Ok(result) => json!({ "id": id, "result": result }),
+        Err(error) => json!({ "id": id, "error": error }),
+    };
+    reply.to_string()
+}
+
+/// Runs a request on the world and returns the reply to it,
+/// or `None` if the server was stopped in the meantime.
+fn dispatch(text: &str, commands: &Sender<PendingCommand>) -> Option<String> {
+    let request = match serde_json::from_str::<Value>(text) {
+        Ok(request) => request,
+        Err(err) => return Some(reply(Value::Null, Err(format!("invalid JSON: {err}")))),
+    };
+    let id = request.get("id").cloned().unwrap_or_default();
+    let command = match Command::deserialize(request) {
+        Ok(command) => command,
+        Err(err) => return Some(reply(id, Err(format!("invalid command: {err}")))),
+    };
+
+    let (sender, receiver) = mpsc::channel();
+    commands
+        .send(PendingCommand {
+            command,
+            reply: sender,
+        })
+        .ok()?;
+    let result = receiver.recv().ok()?;
+    Some(reply(id, result))
+}
+
+fn sync_server(
+    mut commands: Commands,
+    control: Res<RemoteControl>,
+    mut error_events: EventWriter<ErrorEvent>,
+) {
+    commands.remove_resource::<RemoteServer>();
+    if control.port == 0 {
+        return;
+    }
+
+    match RemoteServer::start(control.port) {
+        Ok(server) => commands.insert_resource(server),
+        Err(err) => {
+            error_events.send(ErrorEvent::error(
+                "remote control",
+                format!("error listening on port {}: {err}", control.port),
+            ));
+        }
+    }
+}
+
+fn run_pending_commands(world: &mut World) {
+    let Some(server) = world.get_resource::<RemoteServer>() else {
+        return;
+    };
+    let pending = server
+        .commands
+        .lock()
+        .unwrap()
+        .try_iter()
+        .collect::<Vec<_>>();
+
+    for pending in pending {
+        let result = run_command(world, pending.command).map_err(|err| format!("{err:#}"));
+        // the client may have disconnected already
+        let _ = pending.reply.send(result);
+    }
+}
+
+fn run_command(world: &mut World, command: Command) -> Result<Value> {
+    match command {
+        Command::Open { path } => {
+            if !path.is_file() {
+                bail!("{} is not a file", path.display());
+            }
+            if path.extension().is_some_and(|ext| ext == "dlp") {
+                world.send_event(ProjectLoadEvent { filename: path });
+            } else {
+                world.send_event(CircuitLoadEvent { filename: path });
+            }
+            Ok(Value::Null)
+        }
+        Command::Circuits => Ok(world.run_system_once(circuit_names)),
+        Command::SetInput {
+            circuit,
+            designator,
+            value,
+        } => world.run_system_once_with((circuit, designator, value), set_input),
+        Command::Step => world.run_system_once(step),
+        Command::QueryNets { circuit } => world.run_system_once_with(circuit, query_nets),
+        Command::Export { circuit, path } => {
+            if !path.extension().is_some_and(|ext| ext == "svg") {
+                bail!("images can only be exported as SVG");
+            }
+            let circuit = world.run_system_once_with(circuit, single_circuit)?;
+            digilogic_report::export_schematic(world, CircuitID(circuit), &path)?;
+            Ok(Value::Null)
+        }
+    }
+}
+
+type CircuitQuery<'w, 's> = Query<'w, 's, (Entity, Read<Name>), With<Circuit>>;
+
+/// Empty circuits have no relations yet, so they are queried separately.
+type ChildQuery<'w, 's> = Query<'w, 's, ((), Relations<Child>), With<Circuit>>;
+
+/// The circuits called `name`, or all circuits without a name.
+fn select_circuits(circuits: &CircuitQuery, name: Option<&str>) -> Result<Vec<Entity>> {
+    let selected = circuits
+        .iter()
+        .filter(|(_, circuit_name)| name.map_or(true, |name| circuit_name.0.as_str() == name))
+        .map(|(circuit, _)| circuit)
+        .collect::<Vec<_>>();
+    match name {
+        Some(name) if selected.is_empty() => bail!("there is no circuit called {name:?}"),
+        _ => Ok(selected),
+    }
+}
+
+fn single_circuit(In(name): In<Option<String>>, circuits: CircuitQuery) -> Result<Entity> {
+    match select_circuits(&circuits, name.as_deref())?.as_slice() {
+        &[circuit] => Ok(circuit),
+        [] => bail!("no circuit is open"),
+        _ => bail!("several circuits are open, pick one with \"circuit\""),
+    }
+}
+
+fn circuit_names(circuits: CircuitQuery) -> Value {
+    circuits
+        .iter()
+        .map(|(_, name)| Value::from(name.0.as_str()))
+        .collect()
+}
+
+type InputQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        Read<SymbolKind>,
+        Read<DesignatorPrefix>,
+        Read<DesignatorNumber>,
+        Option<Read<BitWidth>>,
+    ),
+    With<Symbol>,
+>;
+
+fn set_input(
+    In((circuit, designator, value)): In<(Option<String>, String, String)>,
+    mut commands: Commands,
+    circuits: CircuitQuery,
+    children: ChildQuery,
+    symbols: InputQuery,
+    simulation: Res<State<SimulationState>>,
+    mut eval_events: EventWriter<Eval>,
+) -> Result<Value> {
+    let mut found = None;
+    for circuit in select_circuits(&circuits, circuit.as_deref())? {
+        let Ok((_, edges)) = children.get(circuit) else {
+            continue;
+        };
+        edges
+            .join::<Child>(&symbols)
+            .for_each(|(symbol, &kind, prefix, number, bit_width)| {
+                if found.is_none() && format!("{}{}", prefix.0, number.0) == designator {
+                    found = Some((symbol, kind, bit_width.copied()));
+                }
+            });
+    }
+
+    let Some((symbol, kind, bit_width)) = found else {
+        bail!("there is no symbol {designator}");
+    };
+    if kind != SymbolKind::In {
+        bail!("{designator} is not an input");
+    }
+    let bit_width = bit_width.unwrap_or(BitWidth(NonZeroU8::MIN));
+    let state = LogicState::parse(&value, bit_width)
+        .map_err(|err| anyhow!("invalid value {value:?}: {err}"))?;
+
+    commands.entity(symbol).insert(state);
+    if simulation.is_active() {
+        eval_events.send(Eval);
+    }
+    Ok(Value::Null)
+}
+
+fn step(
+    simulation: Res<State<SimulationState>>,
+    mut eval_events: EventWriter<Eval>,
+) -> Result<Value> {
+    if !simulation.is_active() {
+        bail!("the simulation is not running");
+    }
+    eval_events.send(Eval);
+    Ok(Value::Null)
+}
+
+type NetQuery<'w, 's> =
+    Query<'w, 's, (Read<Name>, Read<BitWidth>, Option<Read<StateOffset>>), With<Net>>;
+
+fn query_nets(
+    In(circuit): In<Option<String>>,
+    circuits: CircuitQuery,
+    children: ChildQuery,
+    nets: NetQuery,
+    sim_state: Option<Res<SimState>>,
+) -> Result<Value> {
+    let mut result = Vec::new();
+    for circuit in select_circuits(&circuits, circuit.as_deref())? {
+        let Ok((_, edges)) = children.get(circuit) else {
+            continue;
+        };
+        let (_, circuit_name) = circuits.get(circuit)?;
+        edges
+            .join::<Child>(&nets)
+            .for_each(|(name, &bit_width, offset)| {
+                let value = sim_state
+                    .as_deref()
+                    .zip(offset)
+                    .map(|(sim_state, offset)| sim_state.format_net(offset.0, bit_width.0));
+                result.push(json!({
+                    "circuit": circuit_name.0.as_str(),
+                    "name": name.0.as_str(),
+                    "width": bit_width.0.get(),
+                    "value": value,
+                }));
+            });
+    }
+    Ok(Value::Array(result))
+}
+
+#[derive(Debug, Default)]
+pub struct RemotePlugin;
+
+impl bevy_app::Plugin for RemotePlugin {
+    fn build(&self, app: &mut bevy_app::App) {
+        app.init_resource::<RemoteControl>();
+        app.add_systems(
+            bevy_app::PreUpdate,
+            (
+                sync_server.run_if(resource_changed::<RemoteControl>),
+                run_pending_commands,
+            )
+                .chain(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use digilogic_headless::HeadlessBuilder;
+
+    #[test]
+    fn export_writes_svg_image() {
+        let mut app = HeadlessBuilder::default().build();
+        app.load_str(
+            "half adder",
+            include_str!("../../digilogic/assets/templates/half_adder.dlc"),
+        )
+        .unwrap();
+        assert!(app.settle());
+
+        let world = app.app_mut().world_mut();
+        let path =
+            std::env::temp_dir().join(format!("digilogic_export_{}.svg", std::process::id()));
+        let command = format!(
+            r#"{{"command": "export", "path": {}}}"#,
+            serde_json::to_string(&path).unwrap()
+        );
+        let command = serde_json::from_str(&command).unwrap();
+        assert_eq!(run_command(world, command).unwrap(), Value::Null);
+        let svg = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.trim_end().ends_with("</svg>"));
+        assert!(svg.contains("<polyline class=\"wire\""));
+        assert!(svg.contains("<rect class=\"symbol\""));
+
+        let command = Command::Export {
+            circuit: None,
+            path: path.with_extension("dlc"),
+        };
+        assert!(run_command(world, command).is_err());
+    }
+}
@@ -0,0 +1,371 @@
+//! Just enough of the WebSocket protocol ([RFC 6455](https://www.rfc-editor.org/rfc/rfc6455))
+//! to exchange messages between two digilogic instances, or with a script.
+
+use base64::Engine;
+use sha1::{Digest, Sha1};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, BufRead, Read, Write};
+
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Messages bigger than this are rejected instead of buffered.
+const MAX_MESSAGE_LEN: u64 = 16 * 1024 * 1024;
+
+const OPCODE_CONTINUATION: u8 = 0x0;
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_BINARY: u8 = 0x2;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xA;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    Text(String),
+    Binary(Vec<u8>),
+    Ping(Vec<u8>),
+    Close,
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Which end of a connection frames are written by. Clients mask their frames, servers don't.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Server,
+    Client,
+}
+
+/// Random bytes for masking keys, which only have to be unpredictable to proxies.
+fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0; N];
+    for chunk in bytes.chunks_mut(8) {
+        let random = RandomState::new().build_hasher().finish().to_le_bytes();
+        chunk.copy_from_slice(&random[..chunk.len()]);
+    }
+    bytes
+}
+
+/// The value of the `Sec-WebSocket-Accept` header answering `key`.
+fn accept_key(key: &str) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(key.trim().as_bytes());
+    hasher.update(ACCEPT_GUID.as_bytes());
+    base64::engine::general_purpose::STANDARD.encode(hasher.finalize())
+}
+
+/// Reads the HTTP upgrade request of a client and accepts it.
+pub fn handshake(reader: &mut impl BufRead, writer: &mut impl Write) -> io::Result<()> {
+    let mut key = None;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("sec-websocket-key") {
+                key = Some(value.trim().to_owned());
+            }
+        }
+    }
+
+    let Some(key) = key else {
+        writer.write_all(b"HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\n\r\n")?;
+        return Err(invalid_data("not a WebSocket upgrade request"));
+    };
+    write!(
+        writer,
+        "HTTP/1.1 101 Switching Protocols\r\n\
+         Upgrade: websocket\r\n\
+         Connection: Upgrade\r\n\
+         Sec-WebSocket-Accept: {}\r\n\r\n",
+        accept_key(&key),
+    )?;
+    writer.flush()
+}
+
+/// Asks the server at `host` to upgrade the connection, and checks its answer.
+pub fn client_handshake(
+    reader: &mut impl BufRead,
+    writer: &mut impl Write,
+    host: &str,
+) -> io::Result<()> {
+    let key = base64::engine::general_purpose::STANDARD.encode(random_bytes::<16>());
+    write!(
+        writer,
+        "GET / HTTP/1.1\r\n\
+         Host: {host}\r\n\
+         Upgrade: websocket\r\n\
+         Connection: Upgrade\r\n\
+         Sec-WebSocket-Key: {key}\r\n\
+         Sec-WebSocket-Version: 13\r\n\r\n",
+    )?;
+    writer.flush()?;
+
+    let mut status = String::new();
+    reader.read_line(&mut status)?;
+    if !status.starts_with("HTTP/1.1 101") {
+        return Err(invalid_data("the server refused to upgrade the connection"));
+    }
+
+    let mut accepted = false;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("sec-websocket-accept") {
+                accepted = value.trim() == accept_key(&key);
+            }
+        }
+    }
+
+    if accepted {
+        Ok(())
+    } else {
+        Err(invalid_data("the server answered with the wrong key"))
+    }
+}
+
+struct Frame {
+    fin: bool,
+    opcode: u8,
+    payload: Vec<u8>,
+}
+
+fn read_frame(reader: &mut impl Read) -> io::Result<Frame> {
+    let mut header = [0; 2];
+    reader.read_exact(&mut header)?;
+    let fin = (header[0] & 0x80) != 0;
+    let opcode = header[0] & 0x0F;
+    let masked = (header[1] & 0x80) != 0;
+
+    let len = match header[1] & 0x7F {
+        126 => {
+            let mut len = [0; 2];
+            reader.read_exact(&mut len)?;
+            u16::from_be_bytes(len) as u64
+        }
+        127 => {
+            let mut len = [0; 8];
+            reader.read_exact(&mut len)?;
+            u64::from_be_bytes(len)
+        }
+        len => len as u64,
+    };
+    if len > MAX_MESSAGE_LEN {
+        return Err(invalid_data("message too long"));
+    }
+
+    let mut mask = [0; 4];
+    if masked {
+        reader.read_exact(&mut mask)?;
+    }
+    let mut payload = vec![0; len as usize];
+    reader.read_exact(&mut payload)?;
+    for (i, byte) in payload.iter_mut().enumerate() {
+        *byte ^= mask[i % 4];
+    }
+
+    Ok(Frame {
+        fin,
+        opcode,
+        payload,
+    })
+}
+
+/// Reads the next message, putting fragmented messages back together
+/// and skipping pongs.
+pub fn read_message(reader: &mut impl Read) -> io::Result<Message> {
+    let mut message: Option<(u8, Vec<u8>)> = None;
+    loop {
+        let frame = read_frame(reader)?;
+        match frame.opcode {
+            OPCODE_CLOSE => return Ok(Message::Close),
+            OPCODE_PING => return Ok(Message::Ping(frame.payload)),
+            OPCODE_PONG => continue,
+            OPCODE_TEXT | OPCODE_BINARY if message.is_none() => {
+                message = Some((frame.opcode, frame.payload));
+            }
+            OPCODE_CONTINUATION if message.is_some() => {
+                let (_, payload) = message.as_mut().unwrap();
+                if (payload.len() + frame.payload.len()) as u64 > MAX_MESSAGE_LEN {
+                    return Err(invalid_data("message too long"));
+                }
+                payload.extend_from_slice(&frame.payload);
+            }
+            _ => return Err(invalid_data("unexpected frame")),
+        }
+
+        if frame.fin {
+            let (opcode, payload) = message.take().unwrap();
+            return if opcode == OPCODE_TEXT {
+                String::from_utf8(payload)
+                    .map(Message::Text)
+                    .map_err(|_| invalid_data("text message is not UTF-8"))
+            } else {
+                Ok(Message::Binary(payload))
+            };
+        }
+    }
+}
+
+fn write_frame(writer: &mut impl Write, role: Role, opcode: u8, payload: &[u8]) -> io::Result<()> {
+    let mask_bit = match role {
+        Role::Server => 0,
+        Role::Client => 0x80,
+    };
+    let mut header = vec![0x80 | opcode];
+    match payload.len() {
+        len @ 0..=125 => header.push(mask_bit | len as u8),
+        len @ 126..=0xFFFF => {
+            header.push(mask_bit | 126);
+            header.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            header.push(mask_bit | 127);
+            header.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+
+    match role {
+        Role::Server => {
+            writer.write_all(&header)?;
+            writer.write_all(payload)?;
+        }
+        Role::Client => {
+            let mask = random_bytes::<4>();
+            header.extend_from_slice(&mask);
+            let masked = payload
+                .iter()
+                .enumerate()
+                .map(|(i, byte)| byte ^ mask[i % 4])
+                .collect::<Vec<_>>();
+            writer.write_all(&header)?;
+            writer.write_all(&masked)?;
+        }
+    }
+    writer.flush()
+}
+
+pub fn write_text(writer: &mut impl Write, role: Role, text: &str) -> io::Result<()> {
+    write_frame(writer, role, OPCODE_TEXT, text.as_bytes())
+}
+
+pub fn write_binary(writer: &mut impl Write, role: Role, payload: &[u8]) -> io::Result<()> {
+    write_frame(writer, role, OPCODE_BINARY, payload)
+}
+
+pub fn write_pong(writer: &mut impl Write, role: Role, payload: &[u8]) -> io::Result<()> {
+    write_frame(writer, role, OPCODE_PONG, payload)
+}
+
+pub fn write_close(writer: &mut impl Write, role: Role) -> io::Result<()> {
+    write_frame(writer, role, OPCODE_CLOSE, &[])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn handshake_accepts_key() {
+        // the example from RFC 6455, section 1.3
+        let request = "GET /chat HTTP/1.1\r\n\
+                       Host: server.example.com\r\n\
+                       Upgrade: websocket\r\n\
+                       Connection: Upgrade\r\n\
+                       Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+                       Sec-WebSocket-Version: 13\r\n\r\n";
+        let mut response = Vec::new();
+        handshake(&mut Cursor::new(request), &mut response).unwrap();
+
+        let response = String::from_utf8(response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 101 "));
+        assert!(response.contains("Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n"));
+
+        let mut response = Vec::new();
+        let plain_request = "GET / HTTP/1.1\r\nHost: localhost\r\n\r\n";
+        assert!(handshake(&mut Cursor::new(plain_request), &mut response).is_err());
+        assert!(response.starts_with(b"HTTP/1.1 400 "));
+    }
+
+    #[test]
+    fn masked_and_fragmented_messages() {
+        // the examples from RFC 6455, section 5.7
+        let masked = [
+            0x81, 0x85, 0x37, 0xfa, 0x21, 0x3d, 0x7f, 0x9f, 0x4d, 0x51, 0x58,
+        ];
+        let message = read_message(&mut Cursor::new(masked)).unwrap();
+        assert_eq!(message, Message::Text("Hello".into()));
+
+        let fragmented = [0x01, 0x03, 0x48, 0x65, 0x6c, 0x80, 0x02, 0x6c, 0x6f];
+        let message = read_message(&mut Cursor::new(fragmented)).unwrap();
+        assert_eq!(message, Message::Text("Hello".into()));
+
+        let ping = [0x89, 0x05, 0x48, 0x65, 0x6c, 0x6c, 0x6f];
+        let message = read_message(&mut Cursor::new(ping)).unwrap();
+        assert_eq!(message, Message::Ping(b"Hello".to_vec()));
+    }
+
+    #[test]
+    fn write_round_trip() {
+        for len in [0, 125, 126, 300, 70_000] {
+            let text = "a".repeat(len);
+            let mut frame = Vec::new();
+            write_text(&mut frame, Role::Server, &text).unwrap();
+            let message = read_message(&mut Cursor::new(frame)).unwrap();
+            assert_eq!(message, Message::Text(text));
+        }
+
+        let mut frame = Vec::new();
+        write_binary(&mut frame, Role::Client, b"Hello").unwrap();
+        assert_eq!(frame[1], 0x85);
+        let message = read_message(&mut Cursor::new(frame)).unwrap();
+        assert_eq!(message, Message::Binary(b"Hello".to_vec()));
+    }
+
+    #[test]
+    fn client_and_server() {
+        let response = "HTTP/1.1 101 Switching Protocols\r\n\r\n";
+        let result = client_handshake(&mut Cursor::new(response), &mut Vec::new(), "localhost");
+        assert!(result.is_err());
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = io::BufReader::new(stream.try_clone().unwrap());
+            let mut writer = stream;
+            handshake(&mut reader, &mut writer).unwrap();
+            let message = read_message(&mut reader).unwrap();
+            assert_eq!(message, Message::Binary(vec![1, 2, 3]));
+            write_text(&mut writer, Role::Server, "done").unwrap();
+        });
+
+        let stream = std::net::TcpStream::connect(addr).unwrap();
+        let mut reader = io::BufReader::new(stream.try_clone().unwrap());
+        let mut writer = stream;
+        client_handshake(&mut reader, &mut writer, &addr.to_string()).unwrap();
+        write_binary(&mut writer, Role::Client, &[1, 2, 3]).unwrap();
+        assert_eq!(
+            read_message(&mut reader).unwrap(),
+            Message::Text("done".into())
+        );
+        server.join().unwrap();
+    }
+}
@@ -0,0 +1,403 @@
+use crate::{CircuitReport, Report};
+use digilogic_core::states::SimulationState;
+use digilogic_ux::{Rule, Severity};
+use std::fmt::{self, Write};
+
+/// Space around the contents of a schematic, in circuit units.
+const SCHEMATIC_MARGIN: f32 = 20.0;
+
+const STYLE: &str = "\
+body { font-family: sans-serif; margin: 2em auto; max-width: 60em; color: #222; }
+h1, h2, h3 { font-weight: 600; }
+table { border-collapse: collapse; margin: 0.5em 0 1.5em; }
+th, td { border: 1px solid #ccc; padding: 0.2em 0.6em; text-align: left; vertical-align: top; }
+th { background: #f0f0f0; }
+td.value { font-family: monospace; }
+.error { color: #c00; }
+.warning { color: #a60; }
+.schematic { width: 100%; max-height: 40em; border: 1px solid #ccc; background: #fff; }
+";
+
+/// The style of schematics, both in reports and as images of their own.
+const SCHEMATIC_STYLE: &str = "\
+.schematic .wire { fill: none; stroke: #08be2a; stroke-width: 2.5; }
+.schematic .junction { fill: #08be2a; }
+.schematic .symbol { fill: #f8f8f8; stroke: #333; stroke-width: 1.5; }
+.schematic text { font-size: 10px; fill: #333; }
+";
+
+/// Escapes text for use in HTML content and attribute values.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// The id of the section of a circuit, for links to it.
+fn anchor(report: &CircuitReport) -> String {
+    format!("circuit-{}", report.circuit.index())
+}
+
+fn rule_label(rule: Rule) -> &'static str {
+    match rule {
+        Rule::FloatingInput => "Floating input",
+        Rule::ConflictingDrivers => "Conflicting drivers",
+        Rule::BitWidthMismatch => "Bit width mismatch",
+    }
+}
+
+fn simulation_label(state: Option<SimulationState>) -> &'static str {
+    match state {
+        None | Some(SimulationState::Disconnected) => "Not running",
+        Some(SimulationState::WaitingOnServer | SimulationState::Building) => "Starting",
+        Some(SimulationState::ActiveIdle) => "Idle",
+        Some(SimulationState::ActiveRunning) => "Running",
+    }
+}
+
+pub(crate) fn write_report(report: &Report) -> String {
+    let mut html = String::new();
+    write_document(&mut html, report).expect("writing to a string doesn't fail");
+    html
+}
+
+fn write_document(out: &mut impl Write, report: &Report) -> fmt::Result {
+    let title = if report.project.is_empty() {
+        "Design Report".to_owned()
+    } else {
+        format!("{} Design Report", report.project)
+    };
+
+    writeln!(out, "<!DOCTYPE html>")?;
+    writeln!(out, "<html>\n<head>\n<meta charset=\"utf-8\">")?;
+    writeln!(out, "<title>{}</title>", escape(&title))?;
+    writeln!(
+        out,
+        "<style>\n{STYLE}{SCHEMATIC_STYLE}</style>\n</head>\n<body>"
+    )?;
+    writeln!(out, "<h1>{}</h1>", escape(&title))?;
+
+    write_overview(out, report)?;
+    write_hierarchy(out, report)?;
+    write_findings(out, report)?;
+    for circuit in &report.circuits {
+        write_circuit(out, circuit)?;
+    }
+
+    writeln!(out, "</body>\n</html>")
+}
+
+fn write_overview(out: &mut impl Write, report: &Report) -> fmt::Result {
+    let errors = report
+        .findings
+        .iter()
+        .filter(|finding| finding.severity == Severity::Error)
+        .count();
+    let warnings = report.findings.len() - errors;
+    let simulation = &report.simulation;
+
+    writeln!(out, "<h2>Overview</h2>\n<table>")?;
+    writeln!(
+        out,
+        "<tr><th>Circuits</th><td>{}</td></tr>",
+        report.circuits.len()
+    )?;
+    writeln!(
+        out,
+        "<tr><th>Symbols</th><td>{}</td></tr>",
+        report
+            .circuits
+            .iter()
+            .map(|c| c.stats.symbols)
+            .sum::<usize>(),
+    )?;
+    writeln!(
+        out,
+        "<tr><th>Nets</th><td>{}</td></tr>",
+        report.circuits.iter().map(|c| c.stats.nets).sum::<usize>(),
+    )?;
+    writeln!(
+        out,
+        "<tr><th>Rule check</th><td>{errors} errors, {warnings} warnings</td></tr>"
+    )?;
+    writeln!(
+        out,
+        "<tr><th>Simulation</th><td>{}</td></tr>",
+        simulation_label(simulation.state),
+    )?;
+    if simulation.nets > 0 {
+        writeln!(
+            out,
+            "<tr><th>Simulated nets</th><td>{} ({} bits)</td></tr>",
+            simulation.nets, simulation.bits,
+        )?;
+        writeln!(
+            out,
+            "<tr><th>Undefined nets</th><td>{}</td></tr>",
+            simulation.undefined_nets,
+        )?;
+        writeln!(
+            out,
+            "<tr><th>High impedance nets</th><td>{}</td></tr>",
+            simulation.high_z_nets,
+        )?;
+    }
+    writeln!(out, "</table>")
+}
+
+fn write_hierarchy(out: &mut impl Write, report: &Report) -> fmt::Result {
+    writeln!(out, "<h2>Hierarchy</h2>\n<ul>")?;
+    for &root in &report.roots {
+        write_hierarchy_node(out, report, root, None, &mut Vec::new())?;
+    }
+    writeln!(out, "</ul>")
+}
+
+fn write_hierarchy_node(
+    out: &mut impl Write,
+    report: &Report,
+    circuit: bevy_ecs::entity::Entity,
+    instance: Option<&str>,
+    path: &mut Vec<bevy_ecs::entity::Entity>,
+) -> fmt::Result {
+    let Some(circuit_report) = report.circuit(circuit) else {
+        return Ok(());
+    };
+
+    write!(out, "<li>")?;
+    if let Some(instance) = instance {
+        write!(out, "{}: ", escape(instance))?;
+    }
+    write!(
+        out,
+        "<a href=\"#{}\">{}</a>",
+        anchor(circuit_report),
+        escape(&circuit_report.name),
+    )?;
+
+    // a circuit containing itself is only listed once
+    if path.contains(&circuit) {
+        return writeln!(out, " (recursive)</li>");
+    }
+
+    let instances = circuit_report
+        .symbols
+        .iter()
+        .filter_map(|symbol| Some((symbol.designator.as_str(), symbol.sub_circuit?)))
+        .collect::<Vec<_>>();
+    if !instances.is_empty() {
+        path.push(circuit);
+        writeln!(out, "\n<ul>")?;
+        for (designator, sub_circuit) in instances {
+            write_hierarchy_node(out, report, sub_circuit, Some(designator), path)?;
+        }
+        writeln!(out, "</ul>")?;
+        path.pop();
+    }
+    writeln!(out, "</li>")
+}
+
+fn write_findings(out: &mut impl Write, report: &Report) -> fmt::Result {
+    writeln!(out, "<h2>Electrical Rule Check</h2>")?;
+    if report.findings.is_empty() {
+        return writeln!(out, "<p>No problems were found.</p>");
+    }
+
+    writeln!(
+        out,
+        "<table>\n<tr><th>Severity</th><th>Circuit</th><th>Rule</th><th>Message</th></tr>"
+    )?;
+    for finding in &report.findings {
+        let (class, severity) = match finding.severity {
+            Severity::Error => ("error", "Error"),
+            Severity::Warning => ("warning", "Warning"),
+        };
+        writeln!(
+            out,
+            "<tr><td class=\"{class}\">{severity}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            escape(&finding.circuit),
+            rule_label(finding.rule),
+            escape(&finding.message),
+        )?;
+    }
+    writeln!(out, "</table>")
+}
+
+fn write_circuit(out: &mut impl Write, circuit: &CircuitReport) -> fmt::Result {
+    writeln!(
+        out,
+        "<h2 id=\"{}\">{}</h2>",
+        anchor(circuit),
+        escape(&circuit.name),
+    )?;
+    if let Some(file) = &circuit.file {
+        writeln!(
+            out,
+            "<p>File: <code>{}</code></p>",
+            escape(&file.display().to_string()),
+        )?;
+    }
+
+    write_schematic(out, circuit)?;
+
+    writeln!(out, "<h3>Symbols</h3>")?;
+    if circuit.symbols.is_empty() {
+        writeln!(out, "<p>None</p>")?;
+    } else {
+        writeln!(
+            out,
+            "<table>\n<tr><th>Designator</th><th>Kind</th><th>Parameters</th></tr>"
+        )?;
+        for symbol in &circuit.symbols {
+            writeln!(
+                out,
+                "<tr><td>{}</td><td>{}</td><td>{}</td></tr>",
+                escape(&symbol.designator),
+                escape(&symbol.kind),
+                escape(&symbol.parameters),
+            )?;
+        }
+        writeln!(out, "</table>")?;
+    }
+
+    writeln!(out, "<h3>Nets</h3>")?;
+    if circuit.nets.is_empty() {
+        return writeln!(out, "<p>None</p>");
+    }
+    let simulated = circuit.nets.iter().any(|net| net.value.is_some());
+    write!(
+        out,
+        "<table>\n<tr><th>Name</th><th>Width</th><th>Connections</th>"
+    )?;
+    if simulated {
+        write!(out, "<th>Value</th>")?;
+    }
+    writeln!(out, "</tr>")?;
+    for net in &circuit.nets {
+        write!(
+            out,
+            "<tr><td>{}</td><td>{}</td><td>{}</td>",
+            escape(&net.name),
+            net.width,
+            escape(&net.connections.join(", ")),
+        )?;
+        if simulated {
+            write!(
+                out,
+                "<td class=\"value\">{}</td>",
+                net.value.as_deref().unwrap_or_default(),
+            )?;
+        }
+        writeln!(out, "</tr>")?;
+    }
+    writeln!(out, "</table>")
+}
+
+fn write_schematic(out: &mut impl Write, circuit: &CircuitReport) -> fmt::Result {
+    if circuit.stats.extent.is_none() {
+        return writeln!(out, "<p>The circuit is empty.</p>");
+    }
+    write_svg(out, circuit, false)
+}
+
+/// Writes the schematic of a circuit as an SVG image of its own.
+pub(crate) fn write_schematic_svg(circuit: &CircuitReport) -> String {
+    let mut svg = String::new();
+    write_svg(&mut svg, circuit, true).expect("writing to a string doesn't fail");
+    svg
+}
+
+/// Writes the schematic of a circuit as SVG, which carries its own style if it is `standalone`.
+fn write_svg(out: &mut impl Write, circuit: &CircuitReport, standalone: bool) -> fmt::Result {
+    let (x, y, width, height) = match circuit.stats.extent {
+        Some(extent) => {
+            let min = extent.min();
+            (
+                min.x.to_f32() - SCHEMATIC_MARGIN,
+                min.y.to_f32() - SCHEMATIC_MARGIN,
+                extent.width().to_f32() + 2.0 * SCHEMATIC_MARGIN,
+                extent.height().to_f32() + 2.0 * SCHEMATIC_MARGIN,
+            )
+        }
+        None => (0.0, 0.0, 2.0 * SCHEMATIC_MARGIN, 2.0 * SCHEMATIC_MARGIN),
+    };
+    writeln!(
+        out,
+        "<svg class=\"schematic\" xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{x} {y} {width} {height}\">"
+    )?;
+    if standalone {
+        writeln!(out, "<style>\n{SCHEMATIC_STYLE}</style>")?;
+        // a background of its own, as there is no page behind it
+        writeln!(
+            out,
+            "<rect x=\"{x}\" y=\"{y}\" width=\"{width}\" height=\"{height}\" fill=\"#fff\"/>"
+        )?;
+    }
+
+    for net in &circuit.nets {
+        for wire in &net.wires {
+            let points = wire
+                .iter()
+                .map(|(x, y)| format!("{x},{y}"))
+                .collect::<Vec<_>>()
+                .join(" ");
+            writeln!(out, "<polyline class=\"wire\" points=\"{points}\"/>")?;
+        }
+        for (x, y) in &net.junctions {
+            writeln!(
+                out,
+                "<circle class=\"junction\" cx=\"{x}\" cy=\"{y}\" r=\"4\"/>"
+            )?;
+        }
+    }
+
+    for symbol in &circuit.symbols {
+        let min = symbol.bounds.min();
+        let (x, y) = (min.x.to_f32(), min.y.to_f32());
+        let (width, height) = (
+            symbol.bounds.width().to_f32(),
+            symbol.bounds.height().to_f32(),
+        );
+        writeln!(
+            out,
+            "<rect class=\"symbol\" x=\"{x}\" y=\"{y}\" width=\"{width}\" height=\"{height}\"/>"
+        )?;
+        writeln!(
+            out,
+            "<text x=\"{x}\" y=\"{}\">{}</text>",
+            y - 3.0,
+            escape(&symbol.designator),
+        )?;
+        writeln!(
+            out,
+            "<text x=\"{}\" y=\"{}\" text-anchor=\"middle\" dominant-baseline=\"middle\">{}</text>",
+            x + width / 2.0,
+            y + height / 2.0,
+            escape(&symbol.kind),
+        )?;
+    }
+
+    writeln!(out, "</svg>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_markup() {
+        assert_eq!(
+            escape("<a href=\"x\">R&D's</a>"),
+            "&lt;a href=&quot;x&quot;&gt;R&amp;D&#39;s&lt;/a&gt;"
+        );
+    }
+}
@@ -0,0 +1,406 @@
+//! An exporter writing an HTML report of a project, for design documentation and coursework.
+//!
+//! The report shows every open circuit with a schematic, its symbols and nets,
+//! the hierarchy of sub-circuits, the findings of the electrical rule check and,
+//! while a simulation is running, the values of the nets.
+
+mod html;
+
+use aery::prelude::*;
+use anyhow::{bail, Result};
+use bevy_ecs::prelude::*;
+use bevy_ecs::system::lifetimeless::Read;
+use bevy_ecs::system::RunSystemOnce;
+use bevy_state::prelude::*;
+use digilogic_core::components::*;
+use digilogic_core::resources::Project;
+use digilogic_core::states::SimulationState;
+use digilogic_core::symbol::SymbolRegistry;
+use digilogic_core::transform::{AbsoluteBoundingBox, BoundingBox};
+use digilogic_netcode::{SimState, StateOffset};
+use digilogic_routing::{VertexKind, Vertices};
+use digilogic_serde::Exporter;
+use digilogic_ux::{CircuitStats, ErcReport, Rule, Severity};
+use std::path::{Path, PathBuf};
+
+struct Report {
+    project: String,
+    simulation: SimulationSummary,
+    circuits: Vec<CircuitReport>,
+    /// The circuits at the top of the hierarchy.
+    roots: Vec<Entity>,
+    findings: Vec<FindingRow>,
+}
+
+impl Report {
+    fn circuit(&self, circuit: Entity) -> Option<&CircuitReport> {
+        self.circuits
+            .iter()
+            .find(|report| report.circuit == circuit)
+    }
+}
+
+struct SimulationSummary {
+    state: Option<SimulationState>,
+    /// The nets with a value, and how many bits they have in total.
+    nets: usize,
+    bits: u64,
+    /// The nets with at least one undefined or high impedance bit.
+    undefined_nets: usize,
+    high_z_nets: usize,
+}
+
+struct CircuitReport {
+    circuit: Entity,
+    name: String,
+    file: Option<PathBuf>,
+    stats: CircuitStats,
+    symbols: Vec<SymbolRow>,
+    nets: Vec<NetRow>,
+}
+
+struct SymbolRow {
+    designator: String,
+    kind: String,
+    parameters: String,
+    bounds: BoundingBox,
+    /// The circuit instantiated by a sub-circuit symbol.
+    sub_circuit: Option<Entity>,
+}
+
+/// A point of a schematic.
+type Point = (f32, f32);
+
+struct NetRow {
+    name: String,
+    width: u8,
+    /// The ports connected to the net, like `U3.Q`.
+    connections: Vec<String>,
+    /// The value of the net while simulating.
+    value: Option<String>,
+    /// The wires of the net, one polyline each.
+    wires: Vec<Vec<Point>>,
+    junctions: Vec<Point>,
+}
+
+struct FindingRow {
+    severity: Severity,
+    circuit: String,
+    rule: Rule,
+    message: String,
+}
+
+type ReportCircuitQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        Read<Name>,
+        Option<Read<FilePath>>,
+        Option<Read<CircuitStats>>,
+    ),
+    With<Circuit>,
+>;
+
+/// Empty circuits have no relations yet, so they are queried separately.
+type ChildQuery<'w, 's> = Query<'w, 's, ((), Relations<Child>), With<Circuit>>;
+
+type ReportSymbolQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Read<SymbolKind>,
+        Read<DesignatorPrefix>,
+        Read<DesignatorNumber>,
+        Option<Read<Parameters>>,
+        Read<AbsoluteBoundingBox>,
+        Option<Read<SubCircuit>>,
+    ),
+    With<Symbol>,
+>;
+
+type ReportNetQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        (
+            Read<Name>,
+            Read<BitWidth>,
+            Option<Read<Vertices>>,
+            Option<Read<StateOffset>>,
+        ),
+        Relations<Child>,
+    ),
+    With<Net>,
+>;
+
+type ReportPortQuery<'w, 's> = Query<'w, 's, (Read<Name>, Relations<Child>), With<Port>>;
+
+type DesignatorQuery<'w, 's> =
+    Query<'w, 's, (Read<DesignatorPrefix>, Read<DesignatorNumber>), With<Symbol>>;
+
+fn designator(prefix: &DesignatorPrefix, number: &DesignatorNumber) -> String {
+    format!("{}{}", prefix.0, number.0)
+}
+
+fn wire_geometry(vertices: &Vertices) -> (Vec<Vec<Point>>, Vec<Point>) {
+    let mut wires = Vec::new();
+    let mut junctions = Vec::new();
+    let mut wire = Vec::new();
+    for vertex in vertices.iter() {
+        let point = (vertex.position.x.to_f32(), vertex.position.y.to_f32());
+        match vertex.kind {
+            VertexKind::WireStart { .. } => {
+                wire.clear();
+                wire.push(point);
+            }
+            VertexKind::Normal | VertexKind::Dummy => wire.push(point),
+            VertexKind::WireEnd { junction_kind } => {
+                wire.push(point);
+                wires.push(std::mem::take(&mut wire));
+                if junction_kind.is_some() {
+                    junctions.push(point);
+                }
+            }
+        }
+    }
+    (wires, junctions)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn collect_report(
+    project: Option<Res<Project>>,
+    symbol_registry: Res<SymbolRegistry>,
+    erc_report: Option<Res<ErcReport>>,
+    simulation: Option<Res<State<SimulationState>>>,
+    sim_state: Option<Res<SimState>>,
+    circuits: ReportCircuitQuery,
+    children: ChildQuery,
+    symbols: ReportSymbolQuery,
+    nets: ReportNetQuery,
+    endpoints: Query<Read<PortID>, With<Endpoint>>,
+    ports: ReportPortQuery,
+    designators: DesignatorQuery,
+) -> Report {
+    let mut simulation = SimulationSummary {
+        state: simulation.map(|state| *state.get()),
+        nets: 0,
+        bits: 0,
+        undefined_nets: 0,
+        high_z_nets: 0,
+    };
+
+    let mut reports = Vec::new();
+    for (circuit, name, file, stats) in circuits.iter() {
+        let mut report = CircuitReport {
+            circuit,
+            name: name.0.to_string(),
+            file: file.map(|file| file.0.clone()),
+            stats: stats.copied().unwrap_or_default(),
+            symbols: Vec::new(),
+            nets: Vec::new(),
+        };
+
+        if let Ok((_, edges)) = children.get(circuit) {
+            edges.join::<Child>(&symbols).for_each(
+                |(&kind, prefix, number, parameters, bounds, sub_circuit)| {
+                    let kind = symbol_registry
+                        .get_def(kind)
+                        .map(|def| def.name().to_string())
+                        .unwrap_or_default();
+                    let parameters = parameters
+                        .map(|parameters| {
+                            parameters
+                                .0
+                                .iter()
+                                .map(|(name, value)| format!("{name}={value}"))
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        })
+                        .unwrap_or_default();
+                    report.symbols.push(SymbolRow {
+                        designator: designator(prefix, number),
+                        kind,
+                        parameters,
+                        bounds: **bounds,
+                        sub_circuit: sub_circuit.map(|sub_circuit| sub_circuit.0),
+                    });
+                },
+            );
+
+            edges.join::<Child>(&nets).for_each(
+                |((name, &bit_width, vertices, offset), net_edges)| {
+                    let mut connections = Vec::new();
+                    net_edges.join::<Child>(&endpoints).for_each(|port_id| {
+                        let Ok((port_name, port_edges)) = ports.get(port_id.0) else {
+                            return;
+                        };
+                        port_edges
+                            .join::<Up<Child>>(&designators)
+                            .for_each(|(prefix, number)| {
+                                connections.push(format!(
+                                    "{}.{}",
+                                    designator(prefix, number),
+                                    port_name.0
+                                ));
+                            });
+                    });
+                    connections.sort();
+
+                    let value = sim_state
+                        .as_deref()
+                        .zip(offset)
+                        .map(|(sim_state, offset)| sim_state.format_net(offset.0, bit_width.0));
+                    if let Some(value) = &value {
+                        simulation.nets += 1;
+                        simulation.bits += bit_width.0.get() as u64;
+                        simulation.undefined_nets += value.contains('x') as usize;
+                        simulation.high_z_nets += value.contains('z') as usize;
+                    }
+
+                    let (wires, junctions) = vertices.map(wire_geometry).unwrap_or_default();
+                    report.nets.push(NetRow {
+                        name: name.0.to_string(),
+                        width: bit_width.0.get(),
+                        connections,
+                        value,
+                        wires,
+                        junctions,
+                    });
+                },
+            );
+        }
+
+        report
+            .symbols
+            .sort_by(|a, b| natural_key(&a.designator).cmp(&natural_key(&b.designator)));
+        report
+            .nets
+            .sort_by(|a, b| natural_key(&a.name).cmp(&natural_key(&b.name)));
+        reports.push(report);
+    }
+    reports.sort_by(|a, b| a.name.cmp(&b.name));
+
+    // circuits that are not instantiated anywhere are at the top of the hierarchy
+    let roots = match project.as_ref().and_then(|project| project.root_circuit) {
+        Some(root) => vec![root.0],
+        None => reports
+            .iter()
+            .filter(|report| {
+                !reports.iter().any(|parent| {
+                    parent
+                        .symbols
+                        .iter()
+                        .any(|symbol| symbol.sub_circuit == Some(report.circuit))
+                })
+            })
+            .map(|report| report.circuit)
+            .collect(),
+    };
+
+    let findings = erc_report
+        .iter()
+        .flat_map(|erc_report| erc_report.findings())
+        .map(|finding| FindingRow {
+            severity: finding.severity(),
+            circuit: reports
+                .iter()
+                .find(|report| report.circuit == finding.circuit.0)
+                .map(|report| report.name.clone())
+                .unwrap_or_default(),
+            rule: finding.rule,
+            message: finding.message.clone(),
+        })
+        .collect();
+
+    Report {
+        project: project
+            .map(|project| project.name.to_string())
+            .unwrap_or_default(),
+        simulation,
+        circuits: reports,
+        roots,
+        findings,
+    }
+}
+
+/// Sorts names like `U2` before `U10`.
+fn natural_key(name: &str) -> (&str, u64, &str) {
+    let digits_start = name
+        .find(|c: char| c.is_ascii_digit())
+        .unwrap_or(name.len());
+    let (prefix, rest) = name.split_at(digits_start);
+    let digits_end = rest
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(rest.len());
+    let (number, suffix) = rest.split_at(digits_end);
+    (prefix, number.parse().unwrap_or(0), suffix)
+}
+
+/// Writes a report of the project containing `circuit`, which covers all open circuits.
+pub fn export_report(world: &mut World, _circuit: CircuitID, filename: &Path) -> Result<()> {
+    let report = world.run_system_once(collect_report);
+    std::fs::write(filename, html::write_report(&report))?;
+    Ok(())
+}
+
+/// Writes the schematic of `circuit` to an SVG image.
+pub fn export_schematic(world: &mut World, circuit: CircuitID, filename: &Path) -> Result<()> {
+    let report = world.run_system_once(collect_report);
+    let Some(circuit) = report.circuit(circuit.0) else {
+        bail!("the circuit is not open");
+    };
+    std::fs::write(filename, html::write_schematic_svg(circuit))?;
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+pub struct ReportPlugin;
+
+impl bevy_app::Plugin for ReportPlugin {
+    fn build(&self, app: &mut bevy_app::App) {
+        app.world_mut()
+            .get_resource_or_insert_with(digilogic_serde::FileFormats::default)
+            .add_exporter(Exporter {
+                name: "HTML Design Report".into(),
+                extensions: &["html", "htm"],
+                export: export_report,
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use digilogic_headless::HeadlessBuilder;
+
+    #[test]
+    fn natural_order() {
+        let mut names = vec!["U10", "A", "U2", "U1b", "U1a"];
+        names.sort_by_key(|name| natural_key(name));
+        assert_eq!(names, ["A", "U1a", "U1b", "U2", "U10"]);
+    }
+
+    #[test]
+    fn report_template() {
+        let mut app = HeadlessBuilder::default().build();
+        let circuit = app
+            .load_str(
+                "half adder",
+                include_str!("../../digilogic/assets/templates/half_adder.dlc"),
+            )
+            .unwrap();
+        assert!(app.settle());
+
+        let filename =
+            std::env::temp_dir().join(format!("digilogic_report_{}.html", std::process::id()));
+        export_report(app.app_mut().world_mut(), circuit, &filename).unwrap();
+        let html = std::fs::read_to_string(&filename).unwrap();
+        std::fs::remove_file(&filename).unwrap();
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("half adder"));
+        assert!(html.contains("<svg"));
+        assert!(html.contains("<polyline"));
+    }
+}
@@ -0,0 +1,159 @@
+use crate::{JunctionKind, Vertex, VertexKind};
+use digilogic_core::transform::Vec2;
+
+fn find_root(parents: &mut [u32], mut index: u32) -> u32 {
+    while parents[index as usize] != index {
+        let parent = parents[index as usize];
+        parents[index as usize] = parents[parent as usize];
+        index = parent;
+    }
+    index
+}
+
+fn union(parents: &mut [u32], a: u32, b: u32) {
+    let a = find_root(parents, a);
+    let b = find_root(parents, b);
+    if a != b {
+        parents[a.max(b) as usize] = a.min(b);
+    }
+}
+
+/// Groups the vertices of a routed net into the parts that are connected by wires.
+///
+/// The segment from the vertex at index `removed_segment` to the next vertex is treated as
+/// missing, together with any junctions on it. Returns the component of every vertex,
+/// components are numbered from 0 in the order they first appear.
+pub fn connected_components(vertices: &[Vertex], removed_segment: Option<u32>) -> Vec<u32> {
+    let mut parents: Vec<u32> = (0..(vertices.len() as u32)).collect();
+
+    for (index, vertex) in vertices.iter().enumerate() {
+        let index = index as u32;
+
+        let is_wire_end = matches!(vertex.kind, VertexKind::WireEnd { .. });
+        if !is_wire_end
+            && ((index + 1) < (vertices.len() as u32))
+            && (removed_segment != Some(index))
+        {
+            union(&mut parents, index, index + 1);
+        }
+
+        for junction in vertex.connected_junctions.iter() {
+            // line segment junctions lie on the segment ending in this vertex
+            let on_removed_segment = (junction.kind == JunctionKind::LineSegment)
+                && (index > 0)
+                && (removed_segment == Some(index - 1));
+
+            if !on_removed_segment {
+                union(&mut parents, index, junction.vertex_index);
+            }
+        }
+    }
+
+    let mut labels = vec![u32::MAX; vertices.len()];
+    let mut components = Vec::with_capacity(vertices.len());
+    let mut component_count = 0;
+    for index in 0..(vertices.len() as u32) {
+        let root = find_root(&mut parents, index) as usize;
+        if labels[root] == u32::MAX {
+            labels[root] = component_count;
+            component_count += 1;
+        }
+        components.push(labels[root]);
+    }
+
+    components
+}
+
+/// Finds the vertex a wire of a routed net starts or ends at, for an endpoint at `position`.
+pub fn endpoint_vertex(vertices: &[Vertex], position: Vec2) -> Option<u32> {
+    vertices
+        .iter()
+        .position(|vertex| {
+            let is_endpoint = matches!(
+                vertex.kind,
+                VertexKind::WireStart { .. }
+                    | VertexKind::WireEnd {
+                        junction_kind: None
+                    }
+            );
+            is_endpoint && (vertex.position == position)
+        })
+        .map(|index| index as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Junction;
+    use smallvec::SmallVec;
+
+    fn vertex(x: i16, y: i16, kind: VertexKind) -> Vertex {
+        Vertex {
+            position: Vec2 {
+                x: x.into(),
+                y: y.into(),
+            },
+            kind,
+            connected_junctions: SmallVec::new(),
+        }
+    }
+
+    /// A root wire from (0, 0) to (20, 10) and a branch from (10, 20) joining it at (10, 0).
+    fn tee() -> Vec<Vertex> {
+        let mut vertices = vec![
+            vertex(0, 0, VertexKind::WireStart { is_root: true }),
+            vertex(10, 0, VertexKind::Normal),
+            vertex(20, 0, VertexKind::Normal),
+            vertex(
+                20,
+                10,
+                VertexKind::WireEnd {
+                    junction_kind: None,
+                },
+            ),
+            vertex(10, 20, VertexKind::WireStart { is_root: false }),
+            vertex(
+                10,
+                0,
+                VertexKind::WireEnd {
+                    junction_kind: Some(JunctionKind::Corner),
+                },
+            ),
+        ];
+        vertices[1].connected_junctions.push(Junction {
+            vertex_index: 5,
+            kind: JunctionKind::Corner,
+        });
+        vertices
+    }
+
+    #[test]
+    fn connected() {
+        assert_eq!(connected_components(&tee(), None), [0; 6]);
+    }
+
+    #[test]
+    fn remove_root_segment() {
+        assert_eq!(connected_components(&tee(), Some(0)), [0, 1, 1, 1, 1, 1]);
+        assert_eq!(connected_components(&tee(), Some(1)), [0, 0, 1, 1, 0, 0]);
+    }
+
+    #[test]
+    fn remove_branch_segment() {
+        assert_eq!(connected_components(&tee(), Some(4)), [0, 0, 0, 0, 1, 0]);
+    }
+
+    #[test]
+    fn find_endpoint_vertex() {
+        let vertices = tee();
+        let position = |x: i16, y: i16| Vec2 {
+            x: x.into(),
+            y: y.into(),
+        };
+
+        assert_eq!(endpoint_vertex(&vertices, position(0, 0)), Some(0));
+        assert_eq!(endpoint_vertex(&vertices, position(20, 10)), Some(3));
+        assert_eq!(endpoint_vertex(&vertices, position(10, 20)), Some(4));
+        assert_eq!(endpoint_vertex(&vertices, position(10, 0)), None);
+    }
+}
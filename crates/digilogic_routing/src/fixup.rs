@@ -123,7 +123,7 @@ struct Tail<'a, T> {
 }
 
 impl<T> Tail<'_, T> {
-    fn split_pair(&mut self, pair_index: usize) -> (&mut T, &mut T, Tail<T>) {
+    fn split_pair(&mut self, pair_index: usize) -> (&mut T, &mut T, Tail<'_, T>) {
         let (a, tail) = self.tail[(pair_index - self.offset)..]
             .split_first_mut()
             .unwrap();
@@ -226,8 +226,8 @@ impl BoundingBoxList {
     fn build(
         &mut self,
     ) -> (
-        SegmentTreeBuilder<HorizontalBoundingBox>,
-        SegmentTreeBuilder<VerticalBoundingBox>,
+        SegmentTreeBuilder<'_, HorizontalBoundingBox>,
+        SegmentTreeBuilder<'_, VerticalBoundingBox>,
     ) {
         (
             self.horizontal_bounding_boxes.build(),
@@ -236,12 +236,15 @@ impl BoundingBoxList {
     }
 
     #[inline]
-    fn iter_containing_horizontal(&self, y: Fixed) -> ContainingSegmentIter<HorizontalBoundingBox> {
+    fn iter_containing_horizontal(
+        &self,
+        y: Fixed,
+    ) -> ContainingSegmentIter<'_, HorizontalBoundingBox> {
         self.horizontal_bounding_boxes.iter_containing(y)
     }
 
     #[inline]
-    fn iter_containing_vertical(&self, x: Fixed) -> ContainingSegmentIter<VerticalBoundingBox> {
+    fn iter_containing_vertical(&self, x: Fixed) -> ContainingSegmentIter<'_, VerticalBoundingBox> {
         self.vertical_bounding_boxes.iter_containing(x)
     }
 }
@@ -673,11 +676,15 @@ fn generate_explicit_anchors(
         .join::<Child>(&tree.nets)
         .for_each(|(_, net_children)| {
             net_children.join::<Child>(&tree.endpoints).for_each(
-                |(_, endpoint_transform, has_port)| {
+                |(endpoint, endpoint_transform, has_port)| {
                     if !has_port {
                         let anchor = Anchor::new(endpoint_transform.translation, Directions::ALL);
                         explicit_anchors.push(anchor);
                     }
+
+                    for waypoint in tree.waypoints_of(endpoint) {
+                        explicit_anchors.push(Anchor::new(waypoint, Directions::ALL));
+                    }
                 },
             );
         });
@@ -1,4 +1,5 @@
 mod bit_grid;
+pub mod connectivity;
 mod fixup;
 pub mod graph;
 mod path_finding;
@@ -23,10 +24,20 @@ use tracing::Instrument;
 
 const MIN_WIRE_SPACING: Fixed = fixed!(10);
 
+/// Reroutes all nets of a circuit.
 #[derive(Default, Debug, Component, Reflect)]
 #[component(storage = "SparseSet")]
 struct GraphDirty;
 
+/// Reroutes only the nets of a circuit marked with [`NetDirty`].
+#[derive(Default, Debug, Component, Reflect)]
+#[component(storage = "SparseSet")]
+struct NetsDirty;
+
+#[derive(Default, Debug, Component, Reflect)]
+#[component(storage = "SparseSet")]
+struct NetDirty;
+
 #[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
 pub enum VertexKind {
     #[default]
@@ -64,7 +75,7 @@ pub struct Vertex {
 #[repr(transparent)]
 pub struct Vertices(Vec<Vertex>);
 
-#[derive(Debug, Resource, Reflect, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Resource, Reflect, Serialize, Deserialize)]
 #[reflect(Resource)]
 pub struct RoutingConfig {
     pub prune_graph: bool,
@@ -79,6 +90,16 @@ impl Default for RoutingConfig {
 #[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct RoutingSet;
 
+/// Summary of the most recent routing pass
+#[derive(Default, Debug, Clone, Copy, Resource, Reflect)]
+#[reflect(Resource)]
+pub struct RoutingStatus {
+    /// How many circuits were routed in the most recent pass
+    pub routed_circuits: usize,
+    /// How many nets were routed in the most recent pass
+    pub routed_nets: usize,
+}
+
 #[derive(Debug, Event, Reflect)]
 pub struct RoutingComplete {
     pub circuit: CircuitID,
@@ -88,10 +109,10 @@ type CircuitQuery<'w, 's> = Query<
     'w,
     's,
     (
-        (Entity, Write<graph::Graph>, Edges<Child>),
+        (Entity, Write<graph::Graph>, Edges<Child>, Has<GraphDirty>),
         Relations<Child>,
     ),
-    (With<Circuit>, With<GraphDirty>),
+    (With<Circuit>, Or<(With<GraphDirty>, With<NetsDirty>)>),
 >;
 
 type SymbolQuery<'w, 's> =
@@ -101,6 +122,8 @@ type PortQuery<'w, 's> =
 type NetQuery<'w, 's> = Query<'w, 's, ((Entity, Write<Vertices>), Relations<Child>), With<Net>>;
 type EndpointQuery<'w, 's> =
     Query<'w, 's, (Entity, Read<GlobalTransform>, Has<PortID>), With<Endpoint>>;
+type EndpointChildrenQuery<'w, 's> = Query<'w, 's, ((), Relations<Child>), With<Endpoint>>;
+type WaypointQuery<'w, 's> = Query<'w, 's, (Read<GlobalTransform>, Read<Number>), With<Waypoint>>;
 
 #[derive(SystemParam)]
 struct CircuitTree<'w, 's> {
@@ -108,6 +131,26 @@ struct CircuitTree<'w, 's> {
     ports: PortQuery<'w, 's>,
     nets: NetQuery<'w, 's>,
     endpoints: EndpointQuery<'w, 's>,
+    endpoint_children: EndpointChildrenQuery<'w, 's>,
+    waypoints: WaypointQuery<'w, 's>,
+}
+
+impl CircuitTree<'_, '_> {
+    /// The positions of the waypoints of an endpoint, in order starting from the endpoint.
+    fn waypoints_of(&self, endpoint: Entity) -> SmallVec<[Vec2; 4]> {
+        let mut waypoints = SmallVec::<[(Number, Vec2); 4]>::new();
+        if let Ok((_, endpoint_children)) = self.endpoint_children.get(endpoint) {
+            endpoint_children
+                .join::<Child>(&self.waypoints)
+                .for_each(|(transform, &number)| waypoints.push((number, transform.translation)));
+        }
+
+        waypoints.sort_unstable_by_key(|&(number, _)| number);
+        waypoints
+            .into_iter()
+            .map(|(_, position)| position)
+            .collect()
+    }
 }
 
 fn route(
@@ -115,20 +158,41 @@ fn route(
     config: Res<RoutingConfig>,
     mut circuits: CircuitQuery,
     mut tree: CircuitTree,
+    dirty_nets: Query<(), With<NetDirty>>,
+    mut status: ResMut<RoutingStatus>,
     mut routing_complete_events: EventWriter<RoutingComplete>,
 ) {
-    for ((circuit, mut graph, circuit_edges), circuit_children) in circuits.iter_mut() {
-        commands.entity(circuit).remove::<GraphDirty>();
+    if circuits.is_empty() {
+        return;
+    }
+    digilogic_core::profile_scope!("routing");
+
+    let mut routed_circuits = 0;
+    let mut routed_nets = 0;
+    for ((circuit, mut graph, circuit_edges, graph_dirty), circuit_children) in circuits.iter_mut()
+    {
+        commands
+            .entity(circuit)
+            .remove::<GraphDirty>()
+            .remove::<NetsDirty>();
+        routed_circuits += 1;
         graph.build(&circuit_children, &tree, config.prune_graph);
 
         ComputeTaskPool::get().scope(|scope| {
             for &child in circuit_edges.hosts() {
+                if dirty_nets.contains(child) {
+                    commands.entity(child).remove::<NetDirty>();
+                } else if !graph_dirty {
+                    continue;
+                }
+
                 let child = unsafe {
                     // SAFETY: `hosts()` never returns the same entity more than once.
                     tree.nets.get_unchecked(child)
                 };
 
                 if let Ok(((_, vertices), net_children)) = child {
+                    routed_nets += 1;
                     scope.spawn({
                         let span = info_span!("route_net");
 
@@ -136,13 +200,8 @@ fn route(
                             let mut vertices = vertices;
                             let net_children = net_children;
 
-                            routing::connect_net(
-                                &graph,
-                                &mut vertices.0,
-                                &net_children,
-                                &tree.endpoints,
-                            )
-                            .unwrap();
+                            routing::connect_net(&graph, &mut vertices.0, &net_children, &tree)
+                                .unwrap();
                         }
                         .instrument(span)
                     });
@@ -156,6 +215,11 @@ fn route(
             circuit: CircuitID(circuit),
         });
     }
+
+    *status = RoutingStatus {
+        routed_circuits,
+        routed_nets,
+    };
 }
 
 fn inject_graph(trigger: Trigger<OnAdd, Circuit>, mut commands: Commands) {
@@ -172,6 +236,18 @@ fn inject_vertices(trigger: Trigger<OnAdd, Net>, mut commands: Commands) {
         .insert(Vertices::default());
 }
 
+/// Reroutes a net in the next routing pass, for example after its endpoints changed.
+pub fn reroute_net(commands: &mut Commands, circuit: CircuitID, net: Entity) {
+    commands.entity(net).insert(NetDirty);
+    commands.entity(circuit.0).insert(NetsDirty);
+}
+
+/// Rebuilds the routing graph of a circuit and reroutes all of its nets,
+/// for example after symbols were moved out of it.
+pub fn reroute_circuit(commands: &mut Commands, circuit: CircuitID) {
+    commands.entity(circuit.0).insert(GraphDirty);
+}
+
 fn route_on_config_change(
     mut commands: Commands,
     config: Res<RoutingConfig>,
@@ -184,16 +260,38 @@ fn route_on_config_change(
     }
 }
 
+/// Only the nets attached to a moved symbol are rerouted, the rest of the circuit keeps its wires.
 #[allow(clippy::type_complexity)]
 fn route_on_symbol_change(
     mut commands: Commands,
     circuits: Query<Entity, With<Circuit>>,
-    symbols: Query<((), Relations<Child>), (With<Symbol>, Changed<GlobalTransform>)>,
+    symbols: Query<Entity, (With<Symbol>, Changed<GlobalTransform>)>,
+    ports: Query<((), Relations<Child>), With<Port>>,
+    nets: Query<(Entity, Relations<Child>), With<Net>>,
+    endpoints: Query<(&PortID, Relations<Child>), With<Endpoint>>,
 ) {
-    for (_, edges) in symbols.iter() {
-        edges.join::<Up<Child>>(&circuits).for_each(|circuit| {
-            commands.entity(circuit).insert(GraphDirty);
-        });
+    if symbols.is_empty() {
+        return;
+    }
+
+    for (port_id, edges) in endpoints.iter() {
+        let Ok((_, port_edges)) = ports.get(port_id.0) else {
+            continue;
+        };
+
+        let mut attached = false;
+        port_edges
+            .join::<Up<Child>>(&symbols)
+            .for_each(|_| attached = true);
+
+        if attached {
+            edges.join::<Up<Child>>(&nets).for_each(|(net, net_edges)| {
+                commands.entity(net).insert(NetDirty);
+                net_edges.join::<Up<Child>>(&circuits).for_each(|circuit| {
+                    commands.entity(circuit).insert(NetsDirty);
+                });
+            });
+        }
     }
 }
 
@@ -216,6 +314,26 @@ fn route_on_endpoint_change(
     }
 }
 
+/// Waypoints are part of the graph, so moving one reroutes the whole circuit.
+#[allow(clippy::type_complexity)]
+fn route_on_waypoint_change(
+    mut commands: Commands,
+    circuits: Query<Entity, With<Circuit>>,
+    nets: Query<((), Relations<Child>), With<Net>>,
+    endpoints: Query<((), Relations<Child>), With<Endpoint>>,
+    waypoints: Query<((), Relations<Child>), (With<Waypoint>, Changed<GlobalTransform>)>,
+) {
+    for (_, edges) in waypoints.iter() {
+        edges.join::<Up<Child>>(&endpoints).for_each(|(_, edges)| {
+            edges.join::<Up<Child>>(&nets).for_each(|(_, edges)| {
+                edges.join::<Up<Child>>(&circuits).for_each(|circuit| {
+                    commands.entity(circuit).insert(GraphDirty);
+                });
+            });
+        });
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct RoutingPlugin;
 
@@ -223,9 +341,13 @@ impl bevy_app::Plugin for RoutingPlugin {
     fn build(&self, app: &mut bevy_app::App) {
         app.register_type::<Vertices>()
             .register_type::<RoutingConfig>()
-            .register_type::<GraphDirty>();
+            .register_type::<RoutingStatus>()
+            .register_type::<GraphDirty>()
+            .register_type::<NetsDirty>()
+            .register_type::<NetDirty>();
 
         app.init_resource::<RoutingConfig>();
+        app.init_resource::<RoutingStatus>();
         app.add_event::<RoutingComplete>();
         app.observe(inject_graph);
         app.observe(inject_vertices);
@@ -233,7 +355,12 @@ impl bevy_app::Plugin for RoutingPlugin {
         app.add_systems(bevy_app::PostUpdate, route_on_config_change);
         app.add_systems(
             bevy_app::PostUpdate,
-            (route_on_symbol_change, route_on_endpoint_change).after(TransformSet),
+            (
+                route_on_symbol_change,
+                route_on_endpoint_change,
+                route_on_waypoint_change,
+            )
+                .after(TransformSet),
         );
     }
 }
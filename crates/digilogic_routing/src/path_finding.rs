@@ -193,9 +193,15 @@ impl PathFinder {
         len
     }
 
+    /// Appends the path from `start_index` to the closest end to `path`.
     #[tracing::instrument(skip_all, name = "find_path")]
-    fn find_path_impl(&mut self, graph: &Graph, start_index: NodeIndex) -> PathFindResult {
-        let mut path = Path::default();
+    fn find_path_segment(
+        &mut self,
+        graph: &Graph,
+        start_index: NodeIndex,
+        path: &mut Path,
+    ) -> bool {
+        let prev_len = path.nodes.len();
 
         self.g_score.clear();
         self.predecessor.clear();
@@ -213,7 +219,7 @@ impl PathFinder {
                 // Shortest path to one end found, construct it.
                 if self.end_indices.contains(&current_index) {
                     self.assert_data_is_valid(graph);
-                    self.build_path(&mut path, graph, start_index, current_index);
+                    self.build_path(path, graph, start_index, current_index);
                     break 'outer;
                 }
 
@@ -317,47 +323,18 @@ impl PathFinder {
             }
         }
 
-        if !path.nodes.is_empty() {
-            PathFindResult::Found(path)
-        } else {
-            PathFindResult::NotFound
-        }
+        path.nodes.len() > prev_len
     }
 
-    pub(crate) fn find_path(&mut self, graph: &Graph, start: Vec2, end: Vec2) -> PathFindResult {
-        let Some(start_index) = graph.find_node(start) else {
-            error!(
-                "Start point ({}, {}) does not exist in the graph",
-                start.x, start.y,
-            );
-            return PathFindResult::InvalidStartPoint;
-        };
-
-        let Some(end_index) = graph.find_node(end) else {
-            error!(
-                "End point ({}, {}) does not exist in the graph",
-                end.x, end.y,
-            );
-            return PathFindResult::InvalidEndPoint;
-        };
-
-        if graph.nodes[end_index].neighbor_count() == 0 {
-            return PathFindResult::NotFound;
-        }
-
-        self.end_indices.clear();
-        self.end_indices.insert(end_index);
-
-        self.find_path_impl(graph, start_index)
-    }
-
-    pub(crate) fn find_path_multi(
+    /// Finds a path from `start` to the closest of `ends` that passes through all `waypoints` in order.
+    pub(crate) fn find_path_via(
         &mut self,
         graph: &Graph,
         start: Vec2,
+        waypoints: &[Vec2],
         ends: impl Iterator<Item = Vec2>,
     ) -> PathFindResult {
-        let Some(start_index) = graph.find_node(start) else {
+        let Some(mut start_index) = graph.find_node(start) else {
             error!(
                 "Start point ({}, {}) does not exist in the graph",
                 start.x, start.y,
@@ -365,6 +342,27 @@ impl PathFinder {
             return PathFindResult::InvalidStartPoint;
         };
 
+        let mut path = Path::default();
+        for &waypoint in waypoints {
+            let Some(waypoint_index) = graph.find_node(waypoint) else {
+                error!(
+                    "Waypoint ({}, {}) does not exist in the graph",
+                    waypoint.x, waypoint.y,
+                );
+                return PathFindResult::InvalidEndPoint;
+            };
+
+            self.end_indices.clear();
+            self.end_indices.insert(waypoint_index);
+            if (waypoint_index != start_index)
+                && !self.find_path_segment(graph, start_index, &mut path)
+            {
+                return PathFindResult::NotFound;
+            }
+
+            start_index = waypoint_index;
+        }
+
         self.end_indices.clear();
         let mut total_neighbor_count = 0;
         for end in ends {
@@ -393,6 +391,10 @@ impl PathFinder {
             return PathFindResult::NotFound;
         }
 
-        self.find_path_impl(graph, start_index)
+        if self.find_path_segment(graph, start_index, &mut path) {
+            PathFindResult::Found(path)
+        } else {
+            PathFindResult::NotFound
+        }
     }
 }
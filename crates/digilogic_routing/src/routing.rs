@@ -1,6 +1,8 @@
 use crate::graph::Graph;
 use crate::path_finding::*;
-use crate::{EndpointQuery, Junction, JunctionKind, Vertex, VertexKind, MIN_WIRE_SPACING};
+use crate::{
+    CircuitTree, EndpointQuery, Junction, JunctionKind, Vertex, VertexKind, MIN_WIRE_SPACING,
+};
 use aery::operations::utils::RelationsItem;
 use aery::prelude::*;
 use bevy_ecs::prelude::*;
@@ -210,19 +212,28 @@ fn route_root_wire(
     vertices: &mut Vec<Vertex>,
     root_start: Entity,
     root_end: Entity,
-    endpoints: &EndpointQuery,
+    tree: &CircuitTree,
     thread_local_data: &mut ThreadLocalData,
 ) -> Result<(), RoutingError> {
-    let (_, root_start_transform, _) = endpoints.get(root_start).unwrap();
-    let (_, root_end_transform, _) = endpoints.get(root_end).unwrap();
+    let (_, root_start_transform, _) = tree.endpoints.get(root_start).unwrap();
+    let (_, root_end_transform, _) = tree.endpoints.get(root_end).unwrap();
     let root_start_pos = root_start_transform.translation;
     let root_end_pos = root_end_transform.translation;
 
+    // the waypoints of the end are passed in reverse, since they start at the endpoint
+    let mut waypoints = tree.waypoints_of(root_start);
+    waypoints.extend(tree.waypoints_of(root_end).into_iter().rev());
+
     let ThreadLocalData {
         path_finder, ends, ..
     } = thread_local_data;
 
-    match path_finder.find_path(graph, root_start_pos, root_end_pos) {
+    match path_finder.find_path_via(
+        graph,
+        root_start_pos,
+        &waypoints,
+        std::iter::once(root_end_pos),
+    ) {
         PathFindResult::Found(path) => {
             push_vertices(&path, vertices, ends, true, None);
         }
@@ -256,7 +267,7 @@ fn route_branch_wires(
     vertices: &mut Vec<Vertex>,
     roots: [Entity; 2],
     net_children: &RelationsItem<Child>,
-    endpoints: &EndpointQuery,
+    tree: &CircuitTree,
     thread_local_data: &mut ThreadLocalData,
 ) -> Result<(), RoutingError> {
     let ThreadLocalData { path_finder, ends } = thread_local_data;
@@ -264,16 +275,18 @@ fn route_branch_wires(
     let mut result = Ok(());
 
     net_children
-        .join::<Child>(endpoints)
+        .join::<Child>(&tree.endpoints)
         .for_each(|(endpoint, endpoint_transform, _)| {
             if roots.contains(&endpoint) {
                 return JCF::Continue;
             }
 
             let endpoint_pos = endpoint_transform.translation;
-            let (junction_kind, junction_vertex_index) = match path_finder.find_path_multi(
+            let waypoints = tree.waypoints_of(endpoint);
+            let (junction_kind, junction_vertex_index) = match path_finder.find_path_via(
                 graph,
                 endpoint_pos,
+                &waypoints,
                 ends.iter().map(|end| end.position),
             ) {
                 PathFindResult::Found(path) => {
@@ -340,15 +353,15 @@ pub(crate) fn connect_net(
     graph: &Graph,
     vertices: &mut Vec<Vertex>,
     net_children: &RelationsItem<Child>,
-    endpoints: &EndpointQuery,
+    tree: &CircuitTree,
 ) -> Result<(), RoutingError> {
     thread_local! {
         static THREAD_LOCAL_DATA: RefCell<ThreadLocalData> = RefCell::default();
     }
 
     THREAD_LOCAL_DATA.with_borrow_mut(|thread_local_data| {
-        let (root_start, root_end) =
-            pick_root_path(net_children, endpoints).ok_or(RoutingError::NotEnoughEndpoints)?;
+        let (root_start, root_end) = pick_root_path(net_children, &tree.endpoints)
+            .ok_or(RoutingError::NotEnoughEndpoints)?;
 
         vertices.clear();
         thread_local_data.ends.clear();
@@ -358,7 +371,7 @@ pub(crate) fn connect_net(
             vertices,
             root_start,
             root_end,
-            endpoints,
+            tree,
             thread_local_data,
         )?;
 
@@ -367,7 +380,7 @@ pub(crate) fn connect_net(
             vertices,
             [root_start, root_end],
             net_children,
-            endpoints,
+            tree,
             thread_local_data,
         )?;
 
@@ -38,7 +38,7 @@ pub struct SegmentTreeBuilder<'a, T> {
 
 impl<T> SegmentTree<T> {
     #[inline]
-    pub fn build(&mut self) -> SegmentTreeBuilder<T> {
+    pub fn build(&mut self) -> SegmentTreeBuilder<'_, T> {
         self.segments.clear();
         SegmentTreeBuilder { tree: self }
     }
@@ -0,0 +1,302 @@
+//! The functions scripts can call. Scripts work on a copy of the circuit that is applied
+//! to the world after they finished, so they never see it half updated.
+//!
+//! - `symbols()` lists the symbols of the circuit.
+//! - `find(designator)` is the symbol with the designator, or `()` if there is none.
+//! - `find_kind(name)` lists the symbols of the kind, like `"AND"`.
+//! - `place(kind, x, y)` places a new symbol of the kind and returns it.
+//! - `route()` reroutes all nets of the circuit.
+//! - `simulate()` starts the simulation.
+//!
+//! Symbols have the properties `kind`, `designator`, `x` and `y`, and the methods
+//! `param(name)` and `set_param(name, value)`.
+
+use bevy_ecs::entity::Entity;
+use digilogic_core::components::{ParamValue, Parameters, SymbolKind};
+use digilogic_core::symbol::{SymbolDef, SymbolRegistry};
+use digilogic_core::transform::Vec2;
+use digilogic_core::{Fixed, SharedStr};
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Scope, FLOAT, INT};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Keeps runaway scripts from freezing the editor.
+const MAX_OPERATIONS: u64 = 10_000_000;
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+#[derive(Debug)]
+pub(crate) struct SymbolModel {
+    /// `None` for symbols placed by the script.
+    pub(crate) entity: Option<Entity>,
+    pub(crate) kind: SymbolKind,
+    pub(crate) prefix: SharedStr,
+    pub(crate) number: u32,
+    pub(crate) position: Vec2,
+    pub(crate) parameters: Parameters,
+    pub(crate) parameters_changed: bool,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct CircuitModel {
+    pub(crate) symbols: Vec<SymbolModel>,
+    pub(crate) route: bool,
+    pub(crate) simulate: bool,
+}
+
+impl CircuitModel {
+    /// The lowest designator number not used by a symbol with `prefix` yet.
+    fn next_number(&self, prefix: &SharedStr) -> u32 {
+        (0..)
+            .find(|&number| {
+                !self
+                    .symbols
+                    .iter()
+                    .any(|symbol| (&symbol.prefix == prefix) && (symbol.number == number))
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// A symbol as seen by scripts.
+#[derive(Clone)]
+struct SymbolRef {
+    circuit: Rc<RefCell<CircuitModel>>,
+    defs: Rc<[SymbolDef]>,
+    index: usize,
+}
+
+impl SymbolRef {
+    fn def(&self) -> Option<SymbolDef> {
+        let kind = self.circuit.borrow().symbols[self.index].kind;
+        self.defs.iter().find(|def| def.kind() == kind).cloned()
+    }
+
+    fn kind(&mut self) -> String {
+        self.def()
+            .map(|def| def.name().to_string())
+            .unwrap_or_default()
+    }
+
+    fn designator(&mut self) -> String {
+        let circuit = self.circuit.borrow();
+        let symbol = &circuit.symbols[self.index];
+        format!("{}{}", symbol.prefix, symbol.number)
+    }
+
+    fn x(&mut self) -> FLOAT {
+        self.circuit.borrow().symbols[self.index]
+            .position
+            .x
+            .to_f64()
+    }
+
+    fn y(&mut self) -> FLOAT {
+        self.circuit.borrow().symbols[self.index]
+            .position
+            .y
+            .to_f64()
+    }
+
+    fn param(&mut self, name: &str) -> ScriptResult<Dynamic> {
+        let def = self.def().ok_or("the symbol has an unknown kind")?;
+        let circuit = self.circuit.borrow();
+        let parameters = def.resolve_parameters(&circuit.symbols[self.index].parameters);
+        match parameters.get(name) {
+            Some(ParamValue::Integer(value)) => Ok(Dynamic::from(*value as INT)),
+            Some(ParamValue::Text(value)) => Ok(Dynamic::from(value.to_string())),
+            None => Err(format!("{} has no parameter `{name}`", def.name()).into()),
+        }
+    }
+
+    fn set_param(&mut self, name: &str, value: Dynamic) -> ScriptResult<()> {
+        let def = self.def().ok_or("the symbol has an unknown kind")?;
+        let param = def
+            .params()
+            .iter()
+            .find(|param| param.name().as_str() == name)
+            .ok_or_else(|| format!("{} has no parameter `{name}`", def.name()))?;
+
+        let value = if let Ok(value) = value.as_int() {
+            ParamValue::Integer(u32::try_from(value).map_err(|_| "the value is negative")?)
+        } else {
+            let text = value
+                .into_immutable_string()
+                .map_err(|_| "parameters are integers or strings")?;
+            ParamValue::Text(text.as_str().into())
+        };
+        let value = param
+            .validate(&value)
+            .ok_or_else(|| format!("`{name}` can't be set to {value}"))?;
+
+        let mut circuit = self.circuit.borrow_mut();
+        let symbol = &mut circuit.symbols[self.index];
+        symbol.parameters.insert(param.name().clone(), value);
+        symbol.parameters_changed = true;
+        Ok(())
+    }
+
+    fn display(&mut self) -> String {
+        format!("{} {}", self.kind(), self.designator())
+    }
+}
+
+fn to_fixed(value: FLOAT) -> ScriptResult<Fixed> {
+    Fixed::try_from_f64(value).ok_or_else(|| format!("{value} is out of range").into())
+}
+
+fn register_functions(
+    engine: &mut Engine,
+    circuit: &Rc<RefCell<CircuitModel>>,
+    defs: &Rc<[SymbolDef]>,
+) {
+    let symbol_ref = {
+        let circuit = circuit.clone();
+        let defs = defs.clone();
+        move |index: usize| SymbolRef {
+            circuit: circuit.clone(),
+            defs: defs.clone(),
+            index,
+        }
+    };
+
+    engine
+        .register_type_with_name::<SymbolRef>("Symbol")
+        .register_get("kind", SymbolRef::kind)
+        .register_get("designator", SymbolRef::designator)
+        .register_get("x", SymbolRef::x)
+        .register_get("y", SymbolRef::y)
+        .register_fn("param", SymbolRef::param)
+        .register_fn("set_param", SymbolRef::set_param)
+        .register_fn("to_string", SymbolRef::display)
+        .register_fn("to_debug", SymbolRef::display);
+
+    {
+        let circuit = circuit.clone();
+        let symbol_ref = symbol_ref.clone();
+        engine.register_fn("symbols", move || -> Array {
+            let count = circuit.borrow().symbols.len();
+            (0..count)
+                .map(|index| Dynamic::from(symbol_ref(index)))
+                .collect()
+        });
+    }
+
+    {
+        let circuit = circuit.clone();
+        let symbol_ref = symbol_ref.clone();
+        engine.register_fn("find", move |designator: &str| -> Dynamic {
+            let count = circuit.borrow().symbols.len();
+            (0..count)
+                .map(&symbol_ref)
+                .find(|symbol| symbol.clone().designator() == designator)
+                .map(Dynamic::from)
+                .unwrap_or(Dynamic::UNIT)
+        });
+    }
+
+    {
+        let circuit = circuit.clone();
+        let symbol_ref = symbol_ref.clone();
+        engine.register_fn("find_kind", move |name: &str| -> Array {
+            let count = circuit.borrow().symbols.len();
+            (0..count)
+                .map(&symbol_ref)
+                .filter(|symbol| symbol.clone().kind() == name)
+                .map(Dynamic::from)
+                .collect()
+        });
+    }
+
+    let place = {
+        let circuit = circuit.clone();
+        let defs = defs.clone();
+        move |name: &str, x: FLOAT, y: FLOAT| -> ScriptResult<SymbolRef> {
+            let def = defs
+                .iter()
+                .find(|def| def.name().as_str() == name)
+                .ok_or_else(|| format!("there is no kind of symbol named `{name}`"))?;
+            let position = Vec2 {
+                x: to_fixed(x)?,
+                y: to_fixed(y)?,
+            };
+
+            let mut model = circuit.borrow_mut();
+            let number = model.next_number(def.designator_prefix());
+            model.symbols.push(SymbolModel {
+                entity: None,
+                kind: def.kind(),
+                prefix: def.designator_prefix().clone(),
+                number,
+                position,
+                parameters: Parameters::default(),
+                parameters_changed: false,
+            });
+            Ok(symbol_ref(model.symbols.len() - 1))
+        }
+    };
+    {
+        let place = place.clone();
+        engine.register_fn("place", move |name: &str, x: INT, y: INT| {
+            place(name, x as FLOAT, y as FLOAT)
+        });
+    }
+    engine.register_fn("place", place);
+
+    {
+        let circuit = circuit.clone();
+        engine.register_fn("route", move || circuit.borrow_mut().route = true);
+    }
+    {
+        let circuit = circuit.clone();
+        engine.register_fn("simulate", move || circuit.borrow_mut().simulate = true);
+    }
+}
+
+/// Runs `source` on `model`, returning the edited model along with what the script
+/// evaluated to, if it isn't `()`, and the lines it printed.
+pub(crate) fn run(
+    symbol_registry: &SymbolRegistry,
+    model: CircuitModel,
+    source: &str,
+) -> (CircuitModel, ScriptResult<Option<String>>, Vec<String>) {
+    let circuit = Rc::new(RefCell::new(model));
+    let defs: Rc<[SymbolDef]> = symbol_registry.iter().cloned().collect();
+    let output = Rc::new(RefCell::new(Vec::new()));
+
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    {
+        let output = output.clone();
+        engine.on_print(move |text| output.borrow_mut().push(text.to_owned()));
+    }
+    {
+        let output = output.clone();
+        engine.on_debug(move |text, _, _| output.borrow_mut().push(text.to_owned()));
+    }
+    register_functions(&mut engine, &circuit, &defs);
+
+    // the value is formatted by the script engine, which knows how to show symbols
+    let mut scope = Scope::new();
+    let result = engine
+        .eval_with_scope::<Dynamic>(&mut scope, source)
+        .and_then(|value| {
+            if value.is_unit() {
+                return Ok(None);
+            }
+            scope.push("value", value);
+            engine
+                .eval_with_scope::<String>(&mut scope, "value.to_debug()")
+                .map(Some)
+        });
+    drop(scope);
+    drop(engine);
+
+    let model = Rc::into_inner(circuit)
+        .expect("scripts don't outlive the engine")
+        .into_inner();
+    let output = Rc::into_inner(output)
+        .expect("scripts don't outlive the engine")
+        .into_inner();
+    (model, result, output)
+}
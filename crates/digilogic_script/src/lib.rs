@@ -0,0 +1,244 @@
+//! Scripts written in [Rhai](https://rhai.rs) that query and edit circuits, for batch edits
+//! and generators. See [`bindings`] for what scripts can call.
+
+mod bindings;
+use bindings::*;
+
+use aery::prelude::*;
+use bevy_ecs::prelude::*;
+use digilogic_core::components::*;
+use digilogic_core::symbol::SymbolRegistry;
+use digilogic_core::transform::Transform;
+
+/// Runs `source` as a script on `circuit`.
+/// Its output and errors are appended to the [`ScriptConsole`].
+#[derive(Event, Debug)]
+pub struct RunScript {
+    pub circuit: CircuitID,
+    pub source: String,
+}
+
+/// Triggered when a script asks for the simulation to be started,
+/// which is up to the frontend since it knows the backend to connect to.
+#[derive(Event, Debug)]
+pub struct StartSimulation;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConsoleLineKind {
+    /// A script that was run, or the name of the file it was read from.
+    Input,
+    /// Printed by a script, or the value it evaluated to.
+    Output,
+    Error,
+}
+
+#[derive(Debug, Clone)]
+pub struct ConsoleLine {
+    pub kind: ConsoleLineKind,
+    pub text: String,
+}
+
+/// Everything scripts printed so far, along with what was run.
+#[derive(Default, Debug, Resource)]
+pub struct ScriptConsole {
+    pub lines: Vec<ConsoleLine>,
+}
+
+impl ScriptConsole {
+    pub fn push(&mut self, kind: ConsoleLineKind, text: impl Into<String>) {
+        self.lines.push(ConsoleLine {
+            kind,
+            text: text.into(),
+        });
+    }
+
+    pub fn clear(&mut self) {
+        self.lines.clear();
+    }
+}
+
+type ScriptSymbolQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static SymbolKind,
+        &'static DesignatorPrefix,
+        &'static DesignatorNumber,
+        &'static Transform,
+        Option<&'static Parameters>,
+    ),
+    With<Symbol>,
+>;
+
+fn run_script(
+    trigger: Trigger<RunScript>,
+    mut commands: Commands,
+    symbol_registry: Res<SymbolRegistry>,
+    circuits: Query<(), With<Circuit>>,
+    children: Query<((), Relations<Child>), With<Circuit>>,
+    symbols: ScriptSymbolQuery,
+    mut console: ResMut<ScriptConsole>,
+) {
+    let event = trigger.event();
+    if !circuits.contains(event.circuit.0) {
+        console.push(ConsoleLineKind::Error, "the circuit doesn't exist anymore");
+        return;
+    }
+
+    // empty circuits have no relations yet
+    let mut model = CircuitModel::default();
+    if let Ok((_, edges)) = children.get(event.circuit.0) {
+        edges.join::<Child>(&symbols).for_each(
+            |(entity, &kind, prefix, &number, transform, parameters)| {
+                model.symbols.push(SymbolModel {
+                    entity: Some(entity),
+                    kind,
+                    prefix: prefix.0.clone(),
+                    number: number.0,
+                    position: transform.translation,
+                    parameters: parameters.cloned().unwrap_or_default(),
+                    parameters_changed: false,
+                });
+            },
+        );
+    }
+
+    let (model, result, output) = run(&symbol_registry, model, &event.source);
+    for line in output {
+        console.push(ConsoleLineKind::Output, line);
+    }
+    match result {
+        Ok(Some(value)) => console.push(ConsoleLineKind::Output, value),
+        Ok(None) => (),
+        Err(error) => console.push(ConsoleLineKind::Error, error.to_string()),
+    }
+
+    // edits are kept even if the script failed halfway, like edits made by hand
+    for symbol in model.symbols {
+        match symbol.entity {
+            Some(entity) if symbol.parameters_changed => {
+                commands.trigger(digilogic_ux::SetSymbolParameters {
+                    symbol: entity,
+                    parameters: symbol.parameters,
+                });
+            }
+            Some(_) => (),
+            None => {
+                let mut builder = symbol_registry.get(symbol.kind);
+                for (name, value) in symbol.parameters.0 {
+                    builder.parameter(name, value);
+                }
+                builder
+                    .position(symbol.position)
+                    .designator_number(symbol.number)
+                    .build(&mut commands, event.circuit.0);
+            }
+        }
+    }
+    if model.route {
+        digilogic_routing::reroute_circuit(&mut commands, event.circuit);
+    }
+    if model.simulate {
+        commands.trigger(StartSimulation);
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ScriptPlugin;
+
+impl bevy_app::Plugin for ScriptPlugin {
+    fn build(&self, app: &mut bevy_app::App) {
+        app.init_resource::<ScriptConsole>();
+        app.observe(run_script);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use digilogic_core::fixed;
+    use digilogic_core::transform::Vec2;
+
+    fn app() -> bevy_app::App {
+        let mut app = bevy_app::App::new();
+        app.add_plugins((
+            bevy_state::app::StatesPlugin,
+            digilogic_core::CorePlugin,
+            ScriptPlugin,
+        ));
+        app
+    }
+
+    fn run_on(app: &mut bevy_app::App, circuit: Entity, source: &str) -> Vec<ConsoleLine> {
+        app.world_mut().trigger(RunScript {
+            circuit: CircuitID(circuit),
+            source: source.into(),
+        });
+        app.world_mut().flush();
+        std::mem::take(&mut app.world_mut().resource_mut::<ScriptConsole>().lines)
+    }
+
+    #[test]
+    fn places_and_finds_symbols() {
+        let mut app = app();
+        let world = app.world_mut();
+        let circuit = world.spawn(Circuit).id();
+        world.resource_scope(|world, registry: Mut<SymbolRegistry>| {
+            let mut commands = world.commands();
+            registry
+                .get(SymbolKind::And)
+                .position(Vec2::ZERO)
+                .designator_number(0)
+                .build(&mut commands, circuit);
+        });
+        world.flush();
+
+        let lines = run_on(
+            &mut app,
+            circuit,
+            r#"
+                let gate = place("OR", 40, 20);
+                gate.set_param("inputs", 3);
+                print(gate.designator);
+                find_kind("AND").len()
+            "#,
+        );
+        let text = lines
+            .iter()
+            .map(|line| line.text.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(text, ["U1", "1"]);
+
+        let mut placed = app
+            .world_mut()
+            .query::<(&SymbolKind, &DesignatorNumber, &Transform, &Parameters)>();
+        let (_, number, transform, parameters) = placed
+            .iter(app.world())
+            .find(|(kind, ..)| **kind == SymbolKind::Or)
+            .unwrap();
+        assert_eq!(number.0, 1);
+        assert_eq!(
+            transform.translation,
+            Vec2 {
+                x: fixed!(40),
+                y: fixed!(20)
+            }
+        );
+        assert_eq!(parameters.integer("inputs"), Some(3));
+    }
+
+    #[test]
+    fn reports_errors() {
+        let mut app = app();
+        let circuit = app.world_mut().spawn(Circuit).id();
+
+        let lines = run_on(&mut app, circuit, r#"place("NAND", 0, 0)"#);
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].kind, ConsoleLineKind::Error);
+        assert!(lines[0].text.contains("NAND"), "{}", lines[0].text);
+
+        let lines = run_on(&mut app, circuit, r#"place("AND", 0, 0).set_param("x", 1)"#);
+        assert_eq!(lines[0].kind, ConsoleLineKind::Error);
+    }
+}
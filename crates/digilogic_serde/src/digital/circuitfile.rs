@@ -49,7 +49,7 @@ pub struct VisualElements {
     pub visual_element: Vec<VisualElement>,
 }
 
-/// NOTE: Must be kept in sync with SymbolKind!
+/// NOTE: Must be kept in sync with SymbolKind and `as_str`!
 #[derive(Serialize, Deserialize, Copy, Clone)]
 #[serde(deny_unknown_fields)]
 pub enum ElementName {
@@ -60,6 +60,24 @@ pub enum ElementName {
     Not,
     In,
     Out,
+    Multiplexer,
+    Demultiplexer,
+}
+
+impl ElementName {
+    /// The name of the element in Digital files.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::And => "And",
+            Self::Or => "Or",
+            Self::Xor => "XOr",
+            Self::Not => "Not",
+            Self::In => "In",
+            Self::Out => "Out",
+            Self::Multiplexer => "Multiplexer",
+            Self::Demultiplexer => "Demultiplexer",
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -0,0 +1,376 @@
+use super::circuitfile::{self, AttributeValue, ElementName, Point};
+use aery::operations::utils::RelationsItem;
+use aery::prelude::*;
+use anyhow::{anyhow, bail, Result};
+use bevy_ecs::prelude::*;
+use bevy_ecs::system::lifetimeless::Read;
+use bevy_ecs::system::{RunSystemOnce, SystemParam};
+use bevy_log::info;
+use digilogic_core::components::*;
+use digilogic_core::symbol::{INPUTS_PARAM, SELECT_BITS_PARAM};
+use digilogic_core::transform::*;
+use digilogic_routing::{VertexKind, Vertices};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use xml::writer::{EmitterConfig, EventWriter, XmlEvent};
+
+/// The version of the file format Digital writes.
+const FILE_VERSION: &str = "2";
+
+type SymbolQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Read<SymbolKind>,
+        Read<Transform>,
+        Read<DesignatorPrefix>,
+        Read<DesignatorNumber>,
+        Option<Read<Parameters>>,
+    ),
+    With<Symbol>,
+>;
+
+type NetQuery<'w, 's> = Query<'w, 's, (Option<Read<Vertices>>, Relations<Child>), With<Net>>;
+
+#[derive(SystemParam)]
+struct DigitalWriter<'w, 's> {
+    circuits: Query<'w, 's, ((), Relations<Child>), With<Circuit>>,
+    symbols: SymbolQuery<'w, 's>,
+    nets: NetQuery<'w, 's>,
+    endpoints: Query<'w, 's, Read<GlobalTransform>, With<Endpoint>>,
+}
+
+// NOTE: Must be kept in sync with ElementName!
+fn element_name(kind: SymbolKind) -> Option<ElementName> {
+    match kind {
+        SymbolKind::And => Some(ElementName::And),
+        SymbolKind::Or => Some(ElementName::Or),
+        SymbolKind::Xor => Some(ElementName::Xor),
+        SymbolKind::Not => Some(ElementName::Not),
+        SymbolKind::In => Some(ElementName::In),
+        SymbolKind::Out => Some(ElementName::Out),
+        SymbolKind::Mux => Some(ElementName::Multiplexer),
+        SymbolKind::Demux => Some(ElementName::Demultiplexer),
+        _ => None,
+    }
+}
+
+fn point(position: Vec2) -> Point {
+    Point {
+        x: position.x.into(),
+        y: position.y.into(),
+    }
+}
+
+/// Digital only connects wires at their ends, so segments are split
+/// wherever another segment of the same net ends on them.
+fn split_at_junctions(segments: Vec<[Vec2; 2]>) -> Vec<[Vec2; 2]> {
+    let ends: Vec<Vec2> = segments.iter().flatten().copied().collect();
+
+    let mut split = Vec::new();
+    for [a, b] in segments {
+        let mut points: Vec<Vec2> = ends
+            .iter()
+            .copied()
+            .filter(|&point| is_inside_segment(point, a, b))
+            .collect();
+        points.sort_by_key(|&point| a.manhatten_distance_to(point));
+        points.dedup();
+
+        let mut start = a;
+        for point in points.into_iter().chain(std::iter::once(b)) {
+            if point != start {
+                split.push([start, point]);
+            }
+            start = point;
+        }
+    }
+    split
+}
+
+/// Whether a point lies strictly between the ends of an axis aligned segment.
+fn is_inside_segment(point: Vec2, a: Vec2, b: Vec2) -> bool {
+    let between = |v, a, b| ((a < v) && (v < b)) || ((b < v) && (v < a));
+    ((a.x == b.x) && (point.x == a.x) && between(point.y, a.y, b.y))
+        || ((a.y == b.y) && (point.y == a.y) && between(point.x, a.x, b.x))
+}
+
+fn entry(key: &str, value: AttributeValue) -> circuitfile::AttributesEntry {
+    circuitfile::AttributesEntry {
+        value: [AttributeValue::String(key.to_owned()), value],
+    }
+}
+
+impl DigitalWriter<'_, '_> {
+    fn visual_elements(
+        &self,
+        circuit_edges: &RelationsItem<'_, Child>,
+    ) -> Result<Vec<circuitfile::VisualElement>> {
+        let mut elements = Vec::new();
+        let mut unsupported = None;
+        circuit_edges.join::<Child>(&self.symbols).for_each(
+            |(&kind, transform, prefix, number, parameters)| {
+                let Some(element_name) = element_name(kind) else {
+                    unsupported = Some(format!("{}{}", prefix.0, number.0));
+                    return;
+                };
+
+                let mut entries = Vec::new();
+                for (name, value) in parameters.iter().flat_map(|parameters| &parameters.0) {
+                    let key = match name.as_str() {
+                        INPUTS_PARAM => "Inputs",
+                        SELECT_BITS_PARAM => "Selector Bits",
+                        _ => continue,
+                    };
+                    if let ParamValue::Integer(value) = *value {
+                        if let Ok(value) = i32::try_from(value) {
+                            entries.push(entry(key, AttributeValue::Int(value)));
+                        }
+                    }
+                }
+                if matches!(kind, SymbolKind::In | SymbolKind::Out) {
+                    // inputs and outputs share a designator prefix, but need distinct labels
+                    let label = format!("{}{}", element_name.as_str(), number.0);
+                    entries.push(entry("Label", AttributeValue::String(label)));
+                }
+                // Digital turns counterclockwise
+                let quarter_turns = (4 - (transform.rotation as u8)) % 4;
+                if quarter_turns != 0 {
+                    let rotation = circuitfile::Rotation {
+                        rotation: quarter_turns.to_string(),
+                    };
+                    entries.push(entry("rotation", AttributeValue::Rotation(rotation)));
+                }
+                if transform.mirrored {
+                    entries.push(entry("mirror", AttributeValue::Boolean("true".to_owned())));
+                }
+
+                elements.push(circuitfile::VisualElement {
+                    element_name,
+                    element_attributes: circuitfile::Attributes {
+                        entry: (!entries.is_empty()).then_some(entries),
+                    },
+                    pos: point(transform.translation),
+                });
+            },
+        );
+
+        if let Some(designator) = unsupported {
+            bail!("symbol {designator} has no equivalent in Digital");
+        }
+        Ok(elements)
+    }
+
+    /// The segments of the routed wires of all nets.
+    /// Nets that haven't been routed are drawn as straight lines between their endpoints.
+    fn wires(&self, circuit_edges: &RelationsItem<'_, Child>) -> Vec<circuitfile::Wire> {
+        let mut wires = Vec::new();
+        circuit_edges
+            .join::<Child>(&self.nets)
+            .for_each(|(vertices, net_edges)| {
+                let mut segments = Vec::new();
+                match vertices {
+                    Some(vertices) if !vertices.is_empty() => {
+                        let mut previous = None;
+                        for vertex in vertices.iter() {
+                            if let VertexKind::WireStart { .. } = vertex.kind {
+                                previous = None;
+                            }
+                            if let Some(previous) = previous {
+                                segments.push([previous, vertex.position]);
+                            }
+                            previous = Some(vertex.position);
+                        }
+                    }
+                    _ => {
+                        let mut previous = None;
+                        net_edges
+                            .join::<Child>(&self.endpoints)
+                            .for_each(|transform| {
+                                if let Some(previous) = previous {
+                                    segments.push([previous, transform.translation]);
+                                }
+                                previous = Some(transform.translation);
+                            });
+                    }
+                }
+
+                wires.extend(split_at_junctions(segments).into_iter().map(|[a, b]| {
+                    circuitfile::Wire {
+                        p1: point(a),
+                        p2: point(b),
+                    }
+                }));
+            });
+        wires
+    }
+
+    fn circuit_file(&self, circuit: CircuitID) -> Result<circuitfile::Circuit> {
+        let (_, circuit_edges) = self
+            .circuits
+            .get(circuit.0)
+            .map_err(|_| anyhow!("invalid circuit ID"))?;
+
+        Ok(circuitfile::Circuit {
+            version: FILE_VERSION.to_owned(),
+            attributes: circuitfile::Attributes { entry: None },
+            visual_elements: circuitfile::VisualElements {
+                visual_element: self.visual_elements(&circuit_edges)?,
+            },
+            wires: circuitfile::Wires {
+                wire: self.wires(&circuit_edges),
+            },
+            measurement_ordering: circuitfile::MeasurementOrdering { string: None },
+        })
+    }
+}
+
+fn write_text<W: Write>(writer: &mut EventWriter<W>, name: &str, text: &str) -> Result<()> {
+    writer.write(XmlEvent::start_element(name))?;
+    writer.write(XmlEvent::characters(text))?;
+    writer.write(XmlEvent::end_element())?;
+    Ok(())
+}
+
+fn write_point<W: Write>(writer: &mut EventWriter<W>, name: &str, point: &Point) -> Result<()> {
+    let (x, y) = (point.x.to_string(), point.y.to_string());
+    writer.write(XmlEvent::start_element(name).attr("x", &x).attr("y", &y))?;
+    writer.write(XmlEvent::end_element())?;
+    Ok(())
+}
+
+fn write_strings<W: Write>(
+    writer: &mut EventWriter<W>,
+    name: &str,
+    strings: Option<&Vec<String>>,
+) -> Result<()> {
+    writer.write(XmlEvent::start_element(name))?;
+    for string in strings.into_iter().flatten() {
+        write_text(writer, "string", string)?;
+    }
+    writer.write(XmlEvent::end_element())?;
+    Ok(())
+}
+
+fn write_attribute_value<W: Write>(
+    writer: &mut EventWriter<W>,
+    value: &AttributeValue,
+) -> Result<()> {
+    match value {
+        AttributeValue::AwtColor(color) => {
+            writer.write(XmlEvent::start_element("awt-color"))?;
+            write_text(writer, "red", &color.red.to_string())?;
+            write_text(writer, "green", &color.green.to_string())?;
+            write_text(writer, "blue", &color.blue.to_string())?;
+            write_text(writer, "alpha", &color.alpha.to_string())?;
+            writer.write(XmlEvent::end_element())?;
+        }
+        AttributeValue::Data(data) => write_text(writer, "data", data)?,
+        AttributeValue::File(file) => write_text(writer, "file", file)?,
+        AttributeValue::TestData(test_data) => {
+            writer.write(XmlEvent::start_element("testData"))?;
+            write_text(writer, "dataString", &test_data.data_string)?;
+            writer.write(XmlEvent::end_element())?;
+        }
+        AttributeValue::Value(value) => {
+            writer.write(
+                XmlEvent::start_element("value")
+                    .attr("v", &value.v)
+                    .attr("z", &value.z),
+            )?;
+            writer.write(XmlEvent::end_element())?;
+        }
+        AttributeValue::InverterConfig(config) => {
+            write_strings(writer, "inverterConfig", config.string.as_ref())?
+        }
+        AttributeValue::IntFormat(format) => write_text(writer, "intFormat", format)?,
+        AttributeValue::Long(value) => write_text(writer, "long", &value.to_string())?,
+        AttributeValue::Int(value) => write_text(writer, "int", &value.to_string())?,
+        AttributeValue::Boolean(value) => write_text(writer, "boolean", value)?,
+        AttributeValue::Rotation(rotation) => {
+            writer
+                .write(XmlEvent::start_element("rotation").attr("rotation", &rotation.rotation))?;
+            writer.write(XmlEvent::end_element())?;
+        }
+        AttributeValue::String(value) => write_text(writer, "string", value)?,
+        AttributeValue::ShapeType(shape) => write_text(writer, "shapeType", shape)?,
+    }
+    Ok(())
+}
+
+fn write_attributes<W: Write>(
+    writer: &mut EventWriter<W>,
+    name: &str,
+    attributes: &circuitfile::Attributes,
+) -> Result<()> {
+    writer.write(XmlEvent::start_element(name))?;
+    for entry in attributes.entry.iter().flatten() {
+        writer.write(XmlEvent::start_element("entry"))?;
+        for value in &entry.value {
+            write_attribute_value(writer, value)?;
+        }
+        writer.write(XmlEvent::end_element())?;
+    }
+    writer.write(XmlEvent::end_element())?;
+    Ok(())
+}
+
+fn write_circuit<W: Write>(
+    writer: &mut EventWriter<W>,
+    circuit: &circuitfile::Circuit,
+) -> Result<()> {
+    writer.write(XmlEvent::start_element("circuit"))?;
+    write_text(writer, "version", &circuit.version)?;
+    write_attributes(writer, "attributes", &circuit.attributes)?;
+
+    writer.write(XmlEvent::start_element("visualElements"))?;
+    for element in &circuit.visual_elements.visual_element {
+        writer.write(XmlEvent::start_element("visualElement"))?;
+        write_text(writer, "elementName", element.element_name.as_str())?;
+        write_attributes(writer, "elementAttributes", &element.element_attributes)?;
+        write_point(writer, "pos", &element.pos)?;
+        writer.write(XmlEvent::end_element())?;
+    }
+    writer.write(XmlEvent::end_element())?;
+
+    writer.write(XmlEvent::start_element("wires"))?;
+    for wire in &circuit.wires.wire {
+        writer.write(XmlEvent::start_element("wire"))?;
+        write_point(writer, "p1", &wire.p1)?;
+        write_point(writer, "p2", &wire.p2)?;
+        writer.write(XmlEvent::end_element())?;
+    }
+    writer.write(XmlEvent::end_element())?;
+
+    write_strings(
+        writer,
+        "measurementOrdering",
+        circuit.measurement_ordering.string.as_ref(),
+    )?;
+    writer.write(XmlEvent::end_element())?;
+    Ok(())
+}
+
+fn digital_circuit(
+    In(circuit): In<CircuitID>,
+    writer: DigitalWriter,
+) -> Result<circuitfile::Circuit> {
+    writer.circuit_file(circuit)
+}
+
+/// Writes a circuit to a file in the format of the Digital logic simulator.
+/// Wires end where the ports of the symbols are in Digilogic, which may not match
+/// the pins of the elements in Digital.
+pub fn save_digital(world: &mut World, circuit: CircuitID, filename: &Path) -> Result<()> {
+    info!("saving Digital circuit {}", filename.display());
+
+    let circuit = world.run_system_once_with(circuit, digital_circuit)?;
+    let file = BufWriter::new(File::create(filename)?);
+    let mut writer = EmitterConfig::new()
+        .perform_indent(true)
+        .create_writer(file);
+    write_circuit(&mut writer, &circuit)?;
+    writer.into_inner().flush()?;
+    Ok(())
+}
@@ -0,0 +1,82 @@
+//! Circuit file formats added by other plugins, next to the ones built into this crate.
+
+use anyhow::Result;
+use bevy_ecs::prelude::*;
+use digilogic_core::components::CircuitID;
+use digilogic_core::symbol::SymbolRegistry;
+use digilogic_core::SharedStr;
+use std::ffi::OsStr;
+use std::path::Path;
+
+/// Spawns the circuit stored in a file, returning its entity.
+pub type ImportFn = fn(&mut Commands, &Path, &SymbolRegistry) -> Result<Entity>;
+
+/// Writes a circuit to a file.
+pub type ExportFn = fn(&mut World, CircuitID, &Path) -> Result<()>;
+
+#[derive(Debug, Clone)]
+pub struct Importer {
+    /// The name of the format, shown in file dialogs.
+    pub name: SharedStr,
+    /// The file extensions of the format, without the leading dot.
+    pub extensions: &'static [&'static str],
+    pub import: ImportFn,
+}
+
+#[derive(Debug, Clone)]
+pub struct Exporter {
+    /// The name of the format, shown in menus and file dialogs.
+    pub name: SharedStr,
+    /// The file extensions of the format, without the leading dot.
+    /// The first one is used for new files.
+    pub extensions: &'static [&'static str],
+    pub export: ExportFn,
+}
+
+/// The importers and exporters added by other plugins.
+/// The built-in formats take precedence over importers for the same extension.
+#[derive(Debug, Default, Resource)]
+pub struct FileFormats {
+    importers: Vec<Importer>,
+    exporters: Vec<Exporter>,
+}
+
+impl FileFormats {
+    pub fn add_importer(&mut self, importer: Importer) {
+        self.importers.push(importer);
+    }
+
+    pub fn add_exporter(&mut self, exporter: Exporter) {
+        self.exporters.push(exporter);
+    }
+
+    #[inline]
+    pub fn importers(&self) -> &[Importer] {
+        &self.importers
+    }
+
+    #[inline]
+    pub fn exporters(&self) -> &[Exporter] {
+        &self.exporters
+    }
+
+    /// The first exporter added for files with the extension.
+    pub fn exporter_for(&self, ext: &OsStr) -> Option<&Exporter> {
+        self.exporters.iter().find(|exporter| {
+            exporter
+                .extensions
+                .iter()
+                .any(|&candidate| ext == candidate)
+        })
+    }
+
+    /// The first importer added for files with the extension.
+    pub fn importer_for(&self, ext: &OsStr) -> Option<&Importer> {
+        self.importers.iter().find(|importer| {
+            importer
+                .extensions
+                .iter()
+                .any(|&candidate| ext == candidate)
+        })
+    }
+}
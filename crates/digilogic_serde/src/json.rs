@@ -1,16 +1,26 @@
 mod circuitfile;
 use circuitfile::*;
 
+mod clipboard;
+pub use clipboard::*;
+
+mod save;
+pub use save::*;
+
 use aery::prelude::*;
 use anyhow::{bail, Result};
 use bevy_ecs::prelude::*;
 use bevy_log::info;
 use digilogic_core::bundles::*;
 use digilogic_core::components::*;
+use digilogic_core::connections::connect_endpoint;
+use digilogic_core::stable_id::StableId;
+// the components, not their serialized forms
+use digilogic_core::components::{Annotation, Attributes, Graphic};
 use digilogic_core::symbol::SymbolRegistry;
 use digilogic_core::transform::*;
 use digilogic_core::visibility::VisibilityBundle;
-use digilogic_core::HashMap;
+use digilogic_core::{Fixed, HashMap, SharedStr};
 use std::num::NonZeroU8;
 use std::path::Path;
 
@@ -25,8 +35,64 @@ pub fn load_json(
         bail!("error getting file name of {}", filename.display(),);
     };
 
+    load_json_as(commands, filename, &name.to_string_lossy(), symbols)
+}
+
+/// Loads a circuit file under a name other than its file name, like a copy of another file.
+pub fn load_json_as(
+    commands: &mut Commands,
+    filename: &Path,
+    name: &str,
+    symbols: &SymbolRegistry,
+) -> Result<Entity> {
     let circuit = CircuitFile::load(filename)?;
-    translate_circuit(commands, &circuit, symbols, &name.to_string_lossy())
+    translate_circuit(commands, &circuit, symbols, name, true)
+}
+
+pub fn load_json_str(
+    commands: &mut Commands,
+    name: &str,
+    contents: &str,
+    symbols: &SymbolRegistry,
+) -> Result<Entity> {
+    info!("loading Digilogic circuit template {name}");
+
+    // every circuit created from a template is a new one
+    let circuit = CircuitFile::try_from(contents)?;
+    translate_circuit(commands, &circuit, symbols, name, false)
+}
+
+/// State shared while spawning the entities of a circuit file
+struct TranslateContext<'a, 'w, 's> {
+    commands: &'a mut Commands<'w, 's>,
+    symbols: &'a SymbolRegistry,
+    id_map: HashMap<Id, Entity>,
+    /// Added to every position in the file
+    offset: Vec2,
+    /// The next free designator number for each prefix, renumbers the symbols if present
+    next_designators: Option<HashMap<SharedStr, u32>>,
+    /// Whether entities keep the stable IDs saved in the file or get new ones
+    keep_stable_ids: bool,
+}
+
+impl<'a, 'w, 's> TranslateContext<'a, 'w, 's> {
+    fn new(commands: &'a mut Commands<'w, 's>, symbols: &'a SymbolRegistry) -> Self {
+        Self {
+            commands,
+            symbols,
+            id_map: HashMap::new(),
+            offset: Vec2::ZERO,
+            next_designators: None,
+            keep_stable_ids: true,
+        }
+    }
+
+    fn position(&self, position: [Fixed; 2]) -> Vec2 {
+        Vec2 {
+            x: position[0],
+            y: position[1],
+        } + self.offset
+    }
 }
 
 fn translate_circuit(
@@ -34,44 +100,149 @@ fn translate_circuit(
     circuit: &CircuitFile,
     symbols: &SymbolRegistry,
     name: &str,
+    keep_stable_ids: bool,
 ) -> Result<Entity> {
-    let mut id_map = HashMap::new();
+    let mut ctx = TranslateContext {
+        keep_stable_ids,
+        ..TranslateContext::new(commands, symbols)
+    };
     let modules = &circuit.modules;
     let mut top_id: Option<Entity> = None;
 
     for module in modules.iter() {
-        let circuit_id = commands
+        let circuit_id = ctx
+            .commands
             .spawn(CircuitBundle {
                 circuit: Circuit,
                 name: Name(name.into()),
             })
             .id();
+        insert_attributes(ctx.commands, circuit_id, &module.attributes);
+        insert_stable_id(&mut ctx, circuit_id, module.stable_id);
 
         for symbol in module.symbols.iter() {
-            translate_symbol(symbol, &mut id_map, commands, circuit_id, symbols)?;
+            translate_symbol(symbol, &mut ctx, circuit_id)?;
         }
 
         for net in module.nets.iter() {
-            translate_net(net, &mut id_map, commands, circuit_id)?;
+            translate_net(net, &mut ctx, circuit_id)?;
+        }
+
+        for annotation in module.annotations.iter() {
+            translate_annotation(annotation, &mut ctx, circuit_id);
+        }
+
+        for graphic in module.graphics.iter() {
+            translate_graphic(graphic, &mut ctx, circuit_id);
         }
+
         if top_id.is_none() {
             top_id = Some(circuit_id);
         }
     }
 
-    Ok(top_id.unwrap())
+    top_id.ok_or_else(|| anyhow::anyhow!("circuit contains no modules"))
+}
+
+fn insert_attributes(
+    commands: &mut Commands,
+    entity: Entity,
+    attributes: &circuitfile::Attributes,
+) {
+    if !attributes.is_empty() {
+        commands
+            .entity(entity)
+            .insert(Attributes(attributes.clone()));
+    }
+}
+
+fn insert_stable_id(ctx: &mut TranslateContext, entity: Entity, stable_id: Option<StableId>) {
+    if let Some(stable_id) = stable_id.filter(|_| ctx.keep_stable_ids) {
+        ctx.commands.entity(entity).insert(stable_id);
+    }
+}
+
+fn translate_annotation(
+    annotation: &circuitfile::Annotation,
+    ctx: &mut TranslateContext,
+    circuit_id: Entity,
+) -> Entity {
+    let annotation_id = ctx
+        .commands
+        .spawn(AnnotationBundle {
+            annotation: Annotation {
+                text: annotation.text.clone(),
+                font_size: annotation.font_size,
+                color: annotation.color,
+            },
+            transform: TransformBundle {
+                transform: Transform {
+                    translation: ctx.position(annotation.position),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .set::<Child>(circuit_id)
+        .id();
+    insert_stable_id(ctx, annotation_id, annotation.stable_id);
+    annotation_id
+}
+
+fn translate_graphic(
+    graphic: &circuitfile::Graphic,
+    ctx: &mut TranslateContext,
+    circuit_id: Entity,
+) -> Entity {
+    let graphic_id = ctx
+        .commands
+        .spawn(GraphicBundle {
+            graphic: Graphic {
+                kind: graphic.kind.to_component(),
+                extent: Vec2 {
+                    x: graphic.extent[0],
+                    y: graphic.extent[1],
+                },
+                color: graphic.color,
+            },
+            transform: TransformBundle {
+                transform: Transform {
+                    translation: ctx.position(graphic.position),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .set::<Child>(circuit_id)
+        .id();
+    insert_stable_id(ctx, graphic_id, graphic.stable_id);
+    graphic_id
+}
+
+fn rotation_from_degrees(degrees: u16) -> Result<Rotation> {
+    match degrees % 360 {
+        0 => Ok(Rotation::Rot0),
+        90 => Ok(Rotation::Rot90),
+        180 => Ok(Rotation::Rot180),
+        270 => Ok(Rotation::Rot270),
+        _ => bail!("rotation by {degrees} degrees is not a multiple of 90"),
+    }
+}
+
+fn rotation_to_degrees(rotation: Rotation) -> u16 {
+    (rotation as u16) * 90
 }
 
-// TODO: a context struct would reduce the number of arguments
 fn translate_symbol(
     symbol: &circuitfile::Symbol,
-    id_map: &mut HashMap<Id, Entity>,
-    commands: &mut Commands,
+    ctx: &mut TranslateContext,
     circuit_id: Entity,
-    symbols: &SymbolRegistry,
-) -> Result<()> {
-    let symbol_builder = if let Some(kind_name) = symbol.symbol_kind_name.as_ref() {
-        symbols.get_by_name(kind_name)
+) -> Result<Entity> {
+    let symbols = ctx.symbols;
+    let kind_name = if let Some(kind_name) = symbol.symbol_kind_name.as_ref() {
+        kind_name
     } else if symbol.symbol_kind_id.is_some() {
         return Err(anyhow::anyhow!(
             "Symbol {} has SymbolKindID but it's not supported",
@@ -80,39 +251,56 @@ fn translate_symbol(
     } else {
         return Err(anyhow::anyhow!("Symbol {} has no SymbolKind", symbol.id.0));
     };
-    if symbol_builder.is_none() {
+    let Some(mut symbol_builder) = symbols.get_by_name(kind_name) else {
         return Err(anyhow::anyhow!(
             "Symbol {} has unknown SymbolKind {}",
             symbol.id.0,
-            symbol
-                .symbol_kind_name
-                .as_ref()
-                .unwrap_or(&symbol.symbol_kind_id.as_ref().unwrap().0)
+            kind_name
         ));
+    };
+
+    let mut number = symbol.number;
+    if let Some(next_designators) = ctx.next_designators.as_mut() {
+        let prefix = symbols
+            .iter()
+            .find(|def| def.name() == kind_name)
+            .map(|def| def.designator_prefix().clone())
+            .unwrap_or_default();
+        let next = next_designators.entry(prefix).or_default();
+        number = *next;
+        *next += 1;
     }
-    let mut symbol_builder = symbol_builder.unwrap();
-    symbol_builder
-        .designator_number(symbol.number)
-        .position(Vec2 {
-            x: symbol.position[0],
-            y: symbol.position[1],
-        })
-        .build(commands, circuit_id);
+
+    for (name, value) in symbol.parameters.iter() {
+        symbol_builder.parameter(name.clone(), value.clone());
+    }
+
+    let symbol_id = symbol_builder
+        .designator_number(number)
+        .position(ctx.position(symbol.position))
+        .rotation(rotation_from_degrees(symbol.rotation)?)
+        .mirrored(symbol.mirrored)
+        .build(ctx.commands, circuit_id);
+    if symbol.de_morgan {
+        ctx.commands.entity(symbol_id).insert(DeMorgan);
+    }
+    insert_attributes(ctx.commands, symbol_id, &symbol.attributes);
+    insert_stable_id(ctx, symbol_id, symbol.stable_id);
     for port in symbol_builder.ports().iter() {
         let symbol_name_pair = format!("{}:{}", symbol.id.0, port.name);
-        id_map.insert(Id(symbol_name_pair.into()), port.id);
+        ctx.id_map.insert(Id(symbol_name_pair.into()), port.id);
     }
 
-    Ok(())
+    Ok(symbol_id)
 }
 
 fn translate_net(
     net: &circuitfile::Net,
-    id_map: &mut HashMap<Id, Entity>,
-    commands: &mut Commands,
+    ctx: &mut TranslateContext,
     circuit_id: Entity,
-) -> Result<()> {
-    let net_id = commands
+) -> Result<Entity> {
+    let net_id = ctx
+        .commands
         .spawn(NetBundle {
             net: Net,
             name: Name(net.name.clone()),
@@ -121,37 +309,40 @@ fn translate_net(
         })
         .set::<Child>(circuit_id)
         .id();
+    insert_attributes(ctx.commands, net_id, &net.attributes);
+    insert_stable_id(ctx, net_id, net.stable_id);
 
     for subnet in net.subnets.iter() {
-        translate_subnet(subnet, id_map, commands, net_id)?;
+        translate_subnet(subnet, ctx, net_id)?;
     }
 
-    Ok(())
+    Ok(net_id)
 }
 
-fn translate_subnet(
-    subnet: &Subnet,
-    id_map: &mut HashMap<Id, Entity>,
-    commands: &mut Commands,
-    net_id: Entity,
-) -> Result<()> {
+fn translate_subnet(subnet: &Subnet, ctx: &mut TranslateContext, net_id: Entity) -> Result<()> {
     for endpoint in subnet.endpoints.iter() {
-        translate_endpoint(endpoint, id_map, commands, net_id)?;
+        let endpoint_id = translate_endpoint(endpoint, ctx, net_id)?;
+
+        // subnets without bits use the whole net
+        if !subnet.subnet_bits.is_empty() {
+            ctx.commands
+                .entity(endpoint_id)
+                .insert(Bits(subnet.subnet_bits.iter().copied().collect()));
+        }
     }
     Ok(())
 }
 
 fn translate_endpoint(
     endpoint: &circuitfile::Endpoint,
-    id_map: &mut HashMap<Id, Entity>,
-    commands: &mut Commands,
+    ctx: &mut TranslateContext,
     net_id: Entity,
-) -> Result<()> {
+) -> Result<Entity> {
     let portref = &endpoint.portref;
 
     let port_id = if let Some(port_name) = portref.port_name.as_ref() {
         let port_name_pair = format!("{}:{}", portref.symbol.0, port_name);
-        if let Some(id) = id_map.get(&Id(port_name_pair.into())) {
+        if let Some(id) = ctx.id_map.get(&Id(port_name_pair.into())) {
             Some(*id)
         } else {
             return Err(anyhow::anyhow!(
@@ -165,14 +356,12 @@ fn translate_endpoint(
         None
     };
 
-    let endpoint_id = commands
+    let endpoint_id = ctx
+        .commands
         .spawn(EndpointBundle {
             transform: TransformBundle {
                 transform: Transform {
-                    translation: Vec2 {
-                        x: endpoint.position[0],
-                        y: endpoint.position[1],
-                    },
+                    translation: ctx.position(endpoint.position),
                     ..Default::default()
                 },
                 ..Default::default()
@@ -183,17 +372,32 @@ fn translate_endpoint(
         .id();
 
     if let Some(port_id) = port_id {
-        commands
-            .entity(endpoint_id)
-            .insert(PortID(port_id))
-            .insert(Transform::default());
+        connect_endpoint(ctx.commands, endpoint_id, port_id, net_id);
+    }
 
-        // Remember to disconnect this when disconnecting from the port.
-        commands
-            .entity(endpoint_id)
-            .set::<InheritTransform>(port_id);
-        commands.entity(port_id).insert(NetID(net_id));
+    for (number, waypoint) in endpoint.waypoints.iter().enumerate() {
+        ctx.commands
+            .spawn(WaypointBundle {
+                waypoint: Waypoint,
+                number: Number(number as i32),
+                transform: TransformBundle {
+                    transform: Transform {
+                        translation: ctx.position(waypoint.position),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                visibility: VisibilityBundle::default(),
+                bounds: BoundingBoxBundle {
+                    bounding_box: BoundingBox::from_half_size(
+                        WAYPOINT_HALF_SIZE,
+                        WAYPOINT_HALF_SIZE,
+                    ),
+                    ..Default::default()
+                },
+            })
+            .set::<Child>(endpoint_id);
     }
 
-    Ok(())
+    Ok(endpoint_id)
 }
@@ -1,10 +1,18 @@
+use digilogic_core::components::{AttributeValue, ParamValue};
+use digilogic_core::stable_id::StableId;
 use digilogic_core::{Fixed, SharedStr};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::Path;
 
+/// The version written to new circuit files and copied fragments.
+pub const VERSION: u32 = 5;
+
 #[derive(PartialEq, Eq, Hash, Debug, Serialize, Deserialize, Clone)]
 pub struct Id(pub SharedStr);
 
+pub type Attributes = BTreeMap<SharedStr, AttributeValue>;
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct CircuitFile {
@@ -22,6 +30,14 @@ pub struct Module {
     pub symbol_kind: Id,
     pub symbols: Vec<Symbol>,
     pub nets: Vec<Net>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub annotations: Vec<Annotation>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub graphics: Vec<Graphic>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub attributes: Attributes,
+    #[serde(rename = "stableId", default, skip_serializing_if = "Option::is_none")]
+    pub stable_id: Option<StableId>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -34,6 +50,81 @@ pub struct Symbol {
     pub symbol_kind_id: Option<Id>,
     pub position: [Fixed; 2],
     pub number: u32,
+    /// Clockwise rotation in degrees, a multiple of 90.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub rotation: u16,
+    /// Mirrored along the Y axis, before rotating.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub mirrored: bool,
+    /// Drawn as the De Morgan equivalent of the gate.
+    #[serde(rename = "deMorgan", default, skip_serializing_if = "is_default")]
+    pub de_morgan: bool,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub attributes: Attributes,
+    /// The parameters of the symbol kind, missing ones take their default value.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub parameters: BTreeMap<SharedStr, ParamValue>,
+    #[serde(rename = "stableId", default, skip_serializing_if = "Option::is_none")]
+    pub stable_id: Option<StableId>,
+}
+
+fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Annotation {
+    pub position: [Fixed; 2],
+    pub text: SharedStr,
+    #[serde(rename = "fontSize")]
+    pub font_size: Fixed,
+    /// RGBA
+    pub color: [u8; 4],
+    #[serde(rename = "stableId", default, skip_serializing_if = "Option::is_none")]
+    pub stable_id: Option<StableId>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum GraphicKind {
+    Rectangle,
+    Line,
+    Ellipse,
+}
+
+impl GraphicKind {
+    pub fn from_component(kind: digilogic_core::components::GraphicKind) -> Self {
+        use digilogic_core::components::GraphicKind as Kind;
+
+        match kind {
+            Kind::Rectangle => Self::Rectangle,
+            Kind::Line => Self::Line,
+            Kind::Ellipse => Self::Ellipse,
+        }
+    }
+
+    pub fn to_component(self) -> digilogic_core::components::GraphicKind {
+        use digilogic_core::components::GraphicKind as Kind;
+
+        match self {
+            Self::Rectangle => Kind::Rectangle,
+            Self::Line => Kind::Line,
+            Self::Ellipse => Kind::Ellipse,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Graphic {
+    pub kind: GraphicKind,
+    pub position: [Fixed; 2],
+    pub extent: [Fixed; 2],
+    /// RGBA
+    pub color: [u8; 4],
+    #[serde(rename = "stableId", default, skip_serializing_if = "Option::is_none")]
+    pub stable_id: Option<StableId>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -42,6 +133,10 @@ pub struct Net {
     pub id: Id,
     pub name: SharedStr,
     pub subnets: Vec<Subnet>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub attributes: Attributes,
+    #[serde(rename = "stableId", default, skip_serializing_if = "Option::is_none")]
+    pub stable_id: Option<StableId>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -68,6 +163,16 @@ pub struct Endpoint {
     pub id: Id,
     pub position: [Fixed; 2],
     pub portref: PortRef,
+    /// The points the wire of the endpoint passes through, starting from the endpoint.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub waypoints: Vec<Waypoint>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Waypoint {
+    pub id: Id,
+    pub position: [Fixed; 2],
 }
 
 impl TryFrom<&str> for CircuitFile {
@@ -96,7 +201,7 @@ impl CircuitFile {
 
 #[cfg(test)]
 mod tests {
-    use super::CircuitFile;
+    use super::{AttributeValue, CircuitFile, StableId, Symbol};
 
     #[test]
     fn reads_small_sample() {
@@ -112,4 +217,71 @@ mod tests {
     fn reads_large_sample() {
         CircuitFile::load("testdata/large.dlc").unwrap();
     }
+
+    #[test]
+    fn symbol_orientation_is_optional() {
+        let json = r#"{"id":"0","symbolKindName":"AND","position":[10.0,20.0],"number":1}"#;
+        let mut symbol: Symbol = serde_json::from_str(json).unwrap();
+        assert_eq!((symbol.rotation, symbol.mirrored), (0, false));
+        assert!(!serde_json::to_string(&symbol).unwrap().contains("rotation"));
+
+        symbol.rotation = 270;
+        symbol.mirrored = true;
+        let json = serde_json::to_string(&symbol).unwrap();
+        let symbol: Symbol = serde_json::from_str(&json).unwrap();
+        assert_eq!((symbol.rotation, symbol.mirrored), (270, true));
+    }
+
+    #[test]
+    fn symbol_de_morgan_is_optional() {
+        let json = r#"{"id":"0","symbolKindName":"AND","position":[10.0,20.0],"number":1}"#;
+        let mut symbol: Symbol = serde_json::from_str(json).unwrap();
+        assert!(!symbol.de_morgan);
+        assert!(!serde_json::to_string(&symbol).unwrap().contains("deMorgan"));
+
+        symbol.de_morgan = true;
+        let json = serde_json::to_string(&symbol).unwrap();
+        assert!(json.contains(r#""deMorgan":true"#));
+        let symbol: Symbol = serde_json::from_str(&json).unwrap();
+        assert!(symbol.de_morgan);
+    }
+
+    #[test]
+    fn symbol_attributes_round_trip() {
+        let json = r#"{"id":"0","symbolKindName":"AND","position":[0.0,0.0],"number":1}"#;
+        let mut symbol: Symbol = serde_json::from_str(json).unwrap();
+        assert!(symbol.attributes.is_empty());
+        assert!(!serde_json::to_string(&symbol)
+            .unwrap()
+            .contains("attributes"));
+
+        symbol.attributes.insert("part".into(), "SN74HC08N".into());
+        symbol.attributes.insert("cost".into(), 0.25.into());
+        symbol.attributes.insert("socketed".into(), true.into());
+        let json = serde_json::to_string(&symbol).unwrap();
+        assert!(json.contains(r#""attributes":{"cost":0.25,"part":"SN74HC08N","socketed":true}"#));
+
+        let loaded: Symbol = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.attributes, symbol.attributes);
+        assert_eq!(
+            loaded.attributes.get("socketed"),
+            Some(&AttributeValue::Bool(true))
+        );
+    }
+
+    #[test]
+    fn symbol_stable_id_round_trip() {
+        let json = r#"{"id":"0","symbolKindName":"AND","position":[0.0,0.0],"number":1}"#;
+        let mut symbol: Symbol = serde_json::from_str(json).unwrap();
+        assert_eq!(symbol.stable_id, None);
+        assert!(!serde_json::to_string(&symbol).unwrap().contains("stableId"));
+
+        let stable_id = StableId::new();
+        symbol.stable_id = Some(stable_id);
+        let json = serde_json::to_string(&symbol).unwrap();
+        assert!(json.contains(&format!(r#""stableId":"{stable_id}""#)));
+
+        let loaded: Symbol = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.stable_id, Some(stable_id));
+    }
 }
@@ -0,0 +1,308 @@
+use super::circuitfile::{self, CircuitFile, Id, Module, PortRef, Subnet};
+use super::{
+    rotation_to_degrees, translate_annotation, translate_graphic, translate_net, translate_symbol,
+    TranslateContext,
+};
+use aery::prelude::*;
+use anyhow::{bail, Result};
+use bevy_ecs::prelude::*;
+use bevy_ecs::system::lifetimeless::Read;
+use bevy_ecs::system::SystemParam;
+use digilogic_core::components::*;
+use digilogic_core::symbol::SymbolRegistry;
+use digilogic_core::transform::*;
+use digilogic_core::{HashMap, HashSet, SharedStr};
+
+pub(super) type SymbolQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        Read<SymbolKind>,
+        Read<Transform>,
+        Read<DesignatorPrefix>,
+        Read<DesignatorNumber>,
+        Option<Read<Attributes>>,
+        Option<Read<Parameters>>,
+        Has<DeMorgan>,
+    ),
+    With<Symbol>,
+>;
+
+pub(super) type AnnotationQuery<'w, 's> =
+    Query<'w, 's, (Entity, Read<Annotation>, Read<Transform>)>;
+
+pub(super) type GraphicQuery<'w, 's> = Query<'w, 's, (Entity, Read<Graphic>, Read<Transform>)>;
+
+type NetQuery<'w, 's> =
+    Query<'w, 's, (Read<Name>, Option<Read<Attributes>>, Relations<Child>), With<Net>>;
+
+type EndpointQuery<'w, 's> =
+    Query<'w, 's, (Read<GlobalTransform>, Option<Read<PortID>>), With<Endpoint>>;
+
+/// Copies parts of a circuit to text in the Digilogic circuit file format and pastes them back.
+#[allow(missing_debug_implementations)]
+#[derive(SystemParam)]
+pub struct CircuitFragments<'w, 's> {
+    commands: Commands<'w, 's>,
+    symbol_registry: Res<'w, SymbolRegistry>,
+    circuits: Query<'w, 's, ((), Relations<Child>), With<Circuit>>,
+    symbols: SymbolQuery<'w, 's>,
+    symbol_ids: Query<'w, 's, Entity, With<Symbol>>,
+    ports: Query<'w, 's, (Read<Name>, Relations<Child>), With<Port>>,
+    nets: NetQuery<'w, 's>,
+    endpoints: EndpointQuery<'w, 's>,
+    annotations: AnnotationQuery<'w, 's>,
+    graphics: GraphicQuery<'w, 's>,
+}
+
+pub(super) fn entity_id(entity: Entity) -> Id {
+    Id(entity.to_bits().to_string().into())
+}
+
+impl CircuitFragments<'_, '_> {
+    /// Serializes the selected symbols, annotations and graphics of a circuit,
+    /// and the nets connecting them.
+    /// Nets are only copied if at least two of their endpoints connect to copied symbols.
+    pub fn copy(
+        &self,
+        circuit: CircuitID,
+        selection: impl IntoIterator<Item = Entity>,
+    ) -> Option<String> {
+        let selection: HashSet<Entity> = selection.into_iter().collect();
+        let (_, circuit_edges) = self.circuits.get(circuit.0).ok()?;
+
+        let mut selected_symbols = Vec::new();
+        circuit_edges.join::<Child>(&self.symbols).for_each(
+            |(entity, &kind, transform, _, &number, attributes, parameters, de_morgan)| {
+                if selection.contains(&entity) {
+                    selected_symbols.push((
+                        entity,
+                        kind,
+                        *transform,
+                        number,
+                        attributes.cloned(),
+                        parameters.cloned(),
+                        de_morgan,
+                    ));
+                }
+            },
+        );
+
+        let mut selected_annotations = Vec::new();
+        circuit_edges.join::<Child>(&self.annotations).for_each(
+            |(entity, annotation, transform)| {
+                if selection.contains(&entity) {
+                    selected_annotations.push((annotation.clone(), transform.translation));
+                }
+            },
+        );
+
+        let mut selected_graphics = Vec::new();
+        circuit_edges
+            .join::<Child>(&self.graphics)
+            .for_each(|(entity, graphic, transform)| {
+                if selection.contains(&entity) {
+                    selected_graphics.push((graphic.clone(), transform.translation));
+                }
+            });
+
+        // positions are stored relative to the top left symbol, annotation or graphic
+        let origin = selected_symbols
+            .iter()
+            .map(|&(_, _, transform, ..)| transform.translation)
+            .chain(selected_annotations.iter().map(|&(_, position)| position))
+            .chain(selected_graphics.iter().map(|&(_, position)| position))
+            .reduce(Vec2::min)?;
+
+        let mut symbols = Vec::new();
+        for (entity, kind, transform, number, attributes, parameters, de_morgan) in selected_symbols
+        {
+            let Some(def) = self.symbol_registry.iter().find(|def| def.kind() == kind) else {
+                continue;
+            };
+
+            let position = transform.translation - origin;
+            symbols.push(circuitfile::Symbol {
+                id: entity_id(entity),
+                symbol_kind_name: Some(def.name().clone()),
+                symbol_kind_id: None,
+                position: [position.x, position.y],
+                number: number.0,
+                rotation: rotation_to_degrees(transform.rotation),
+                mirrored: transform.mirrored,
+                de_morgan,
+                attributes: attributes
+                    .map(|attributes| attributes.0)
+                    .unwrap_or_default(),
+                parameters: parameters
+                    .map(|parameters| parameters.0)
+                    .unwrap_or_default(),
+                stable_id: None,
+            });
+        }
+
+        let mut nets = Vec::new();
+        circuit_edges
+            .join::<Child>(&self.nets)
+            .for_each(|(name, attributes, net_edges)| {
+                let mut endpoints = Vec::new();
+                let mut connected_endpoints = 0;
+
+                net_edges
+                    .join::<Child>(&self.endpoints)
+                    .for_each(|(transform, port_id)| {
+                        let position = transform.translation - origin;
+                        let portref = match port_id {
+                            Some(port_id) => {
+                                let Ok((port_name, port_edges)) = self.ports.get(port_id.0) else {
+                                    return;
+                                };
+
+                                let mut symbol = None;
+                                port_edges
+                                    .join::<Up<Child>>(&self.symbol_ids)
+                                    .for_each(|entity| symbol = Some(entity));
+
+                                match symbol {
+                                    Some(symbol) if selection.contains(&symbol) => {
+                                        connected_endpoints += 1;
+                                        PortRef {
+                                            symbol: entity_id(symbol),
+                                            port_name: Some(port_name.0.clone()),
+                                            port: None,
+                                        }
+                                    }
+                                    _ => return,
+                                }
+                            }
+                            None => PortRef {
+                                symbol: Id(SharedStr::default()),
+                                port_name: None,
+                                port: None,
+                            },
+                        };
+
+                        endpoints.push(circuitfile::Endpoint {
+                            id: Id(endpoints.len().to_string().into()),
+                            position: [position.x, position.y],
+                            portref,
+                            waypoints: Vec::new(),
+                        });
+                    });
+
+                if connected_endpoints >= 2 {
+                    nets.push(circuitfile::Net {
+                        id: Id(nets.len().to_string().into()),
+                        name: name.0.clone(),
+                        subnets: vec![Subnet {
+                            id: Id("0".into()),
+                            name: name.0.clone(),
+                            subnet_bits: Vec::new(),
+                            endpoints,
+                        }],
+                        attributes: attributes
+                            .map(|attributes| attributes.0.clone())
+                            .unwrap_or_default(),
+                        stable_id: None,
+                    });
+                }
+            });
+
+        let annotations = selected_annotations
+            .into_iter()
+            .map(|(annotation, position)| {
+                let position = position - origin;
+                circuitfile::Annotation {
+                    position: [position.x, position.y],
+                    text: annotation.text,
+                    font_size: annotation.font_size,
+                    color: annotation.color,
+                    stable_id: None,
+                }
+            })
+            .collect();
+
+        let graphics = selected_graphics
+            .into_iter()
+            .map(|(graphic, position)| {
+                let position = position - origin;
+                circuitfile::Graphic {
+                    kind: circuitfile::GraphicKind::from_component(graphic.kind),
+                    position: [position.x, position.y],
+                    extent: [graphic.extent.x, graphic.extent.y],
+                    color: graphic.color,
+                    stable_id: None,
+                }
+            })
+            .collect();
+
+        let file = CircuitFile {
+            version: circuitfile::VERSION,
+            modules: vec![Module {
+                id: Id("0".into()),
+                name: SharedStr::default(),
+                prefix: SharedStr::default(),
+                symbol_kind: Id(SharedStr::default()),
+                symbols,
+                nets,
+                annotations,
+                graphics,
+                attributes: Default::default(),
+                stable_id: None,
+            }],
+        };
+
+        serde_json::to_string(&file).ok()
+    }
+
+    /// Spawns a copied fragment into a circuit, with its top left symbol at `position`.
+    /// The pasted symbols are numbered after the existing ones.
+    /// Returns the spawned symbols, nets, annotations and graphics.
+    pub fn paste(
+        &mut self,
+        circuit: CircuitID,
+        contents: &str,
+        position: Vec2,
+    ) -> Result<Vec<Entity>> {
+        let file = CircuitFile::try_from(contents)?;
+        let Some(module) = file.modules.first() else {
+            bail!("circuit fragment without modules");
+        };
+
+        let Ok((_, circuit_edges)) = self.circuits.get(circuit.0) else {
+            bail!("invalid circuit ID");
+        };
+
+        let mut next_designators: HashMap<SharedStr, u32> = HashMap::new();
+        circuit_edges
+            .join::<Child>(&self.symbols)
+            .for_each(|(_, _, _, prefix, number, ..)| {
+                let next = next_designators.entry(prefix.0.clone()).or_default();
+                *next = (*next).max(number.0 + 1);
+            });
+
+        let mut ctx = TranslateContext {
+            offset: position,
+            next_designators: Some(next_designators),
+            keep_stable_ids: false,
+            ..TranslateContext::new(&mut self.commands, &self.symbol_registry)
+        };
+
+        let mut entities = Vec::new();
+        for symbol in module.symbols.iter() {
+            entities.push(translate_symbol(symbol, &mut ctx, circuit.0)?);
+        }
+        for net in module.nets.iter() {
+            entities.push(translate_net(net, &mut ctx, circuit.0)?);
+        }
+        for annotation in module.annotations.iter() {
+            entities.push(translate_annotation(annotation, &mut ctx, circuit.0));
+        }
+        for graphic in module.graphics.iter() {
+            entities.push(translate_graphic(graphic, &mut ctx, circuit.0));
+        }
+
+        Ok(entities)
+    }
+}
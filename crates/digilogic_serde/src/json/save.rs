@@ -0,0 +1,271 @@
+use super::circuitfile::{self, CircuitFile, Id, Module, PortRef, Subnet};
+use super::clipboard::{entity_id, AnnotationQuery, GraphicQuery, SymbolQuery};
+use super::rotation_to_degrees;
+use aery::operations::utils::RelationsItem;
+use aery::prelude::*;
+use anyhow::{anyhow, bail, Result};
+use bevy_ecs::prelude::*;
+use bevy_ecs::system::lifetimeless::Read;
+use bevy_ecs::system::{RunSystemOnce, SystemParam};
+use bevy_log::info;
+use digilogic_core::components::*;
+use digilogic_core::stable_id::StableId;
+use digilogic_core::symbol::SymbolRegistry;
+use digilogic_core::transform::*;
+use digilogic_core::SharedStr;
+use std::path::Path;
+
+type CircuitQuery<'w, 's> =
+    Query<'w, 's, (Read<Name>, Option<Read<Attributes>>, Relations<Child>), With<Circuit>>;
+
+type NetQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        Read<Name>,
+        Option<Read<Attributes>>,
+        Relations<Child>,
+    ),
+    With<Net>,
+>;
+
+type EndpointQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Read<Transform>,
+        Read<GlobalTransform>,
+        Option<Read<PortID>>,
+        Option<Read<Bits>>,
+        Relations<Child>,
+    ),
+    With<Endpoint>,
+>;
+
+/// The parts of a circuit the Digilogic circuit file format stores.
+/// Wires are routed again through their waypoints when the circuit is loaded.
+#[derive(SystemParam)]
+struct CircuitWriter<'w, 's> {
+    symbol_registry: Res<'w, SymbolRegistry>,
+    circuits: CircuitQuery<'w, 's>,
+    symbols: SymbolQuery<'w, 's>,
+    symbol_ids: Query<'w, 's, Entity, With<Symbol>>,
+    ports: Query<'w, 's, (Read<Name>, Relations<Child>), With<Port>>,
+    nets: NetQuery<'w, 's>,
+    endpoints: EndpointQuery<'w, 's>,
+    waypoints: Query<'w, 's, (Read<Number>, Read<Transform>), With<Waypoint>>,
+    annotations: AnnotationQuery<'w, 's>,
+    graphics: GraphicQuery<'w, 's>,
+    stable_ids: Query<'w, 's, Read<StableId>>,
+}
+
+impl CircuitWriter<'_, '_> {
+    fn stable_id(&self, entity: Entity) -> Option<StableId> {
+        self.stable_ids.get(entity).ok().copied()
+    }
+
+    /// The port an endpoint is connected to, by its symbol and name.
+    fn port_ref(&self, port: Entity) -> Option<PortRef> {
+        let (port_name, port_edges) = self.ports.get(port).ok()?;
+
+        let mut symbol = None;
+        port_edges
+            .join::<Up<Child>>(&self.symbol_ids)
+            .for_each(|entity| symbol = Some(entity));
+
+        Some(PortRef {
+            symbol: entity_id(symbol?),
+            port_name: Some(port_name.0.clone()),
+            port: None,
+        })
+    }
+
+    fn symbols(
+        &self,
+        circuit_edges: &RelationsItem<'_, Child>,
+    ) -> Result<Vec<circuitfile::Symbol>> {
+        let mut symbols = Vec::new();
+        let mut unknown = None;
+        circuit_edges.join::<Child>(&self.symbols).for_each(
+            |(entity, &kind, transform, prefix, number, attributes, parameters, de_morgan)| {
+                let Some(def) = self.symbol_registry.iter().find(|def| def.kind() == kind) else {
+                    unknown = Some(format!("{}{}", prefix.0, number.0));
+                    return;
+                };
+
+                let position = transform.translation;
+                symbols.push(circuitfile::Symbol {
+                    id: entity_id(entity),
+                    symbol_kind_name: Some(def.name().clone()),
+                    symbol_kind_id: None,
+                    position: [position.x, position.y],
+                    number: number.0,
+                    rotation: rotation_to_degrees(transform.rotation),
+                    mirrored: transform.mirrored,
+                    de_morgan,
+                    attributes: attributes
+                        .map(|attributes| attributes.0.clone())
+                        .unwrap_or_default(),
+                    parameters: parameters
+                        .map(|parameters| parameters.0.clone())
+                        .unwrap_or_default(),
+                    stable_id: self.stable_id(entity),
+                });
+            },
+        );
+
+        if let Some(designator) = unknown {
+            bail!("symbol {designator} has a kind that is not in the symbol library");
+        }
+        Ok(symbols)
+    }
+
+    fn nets(&self, circuit_edges: &RelationsItem<'_, Child>) -> Vec<circuitfile::Net> {
+        let mut nets = Vec::new();
+        circuit_edges
+            .join::<Child>(&self.nets)
+            .for_each(|(net, name, attributes, net_edges)| {
+                // endpoints using the same bits of the net share a subnet
+                let mut subnets: Vec<Subnet> = Vec::new();
+                let mut endpoint_count = 0usize;
+                net_edges.join::<Child>(&self.endpoints).for_each(
+                    |(transform, global_transform, port_id, bits, endpoint_edges)| {
+                        let port_ref = port_id.and_then(|port_id| self.port_ref(port_id.0));
+                        // connected endpoints are moved onto their port when loading
+                        let (position, portref) = match port_ref {
+                            Some(port_ref) => (global_transform.translation, port_ref),
+                            None => (
+                                transform.translation,
+                                PortRef {
+                                    symbol: Id(SharedStr::default()),
+                                    port_name: None,
+                                    port: None,
+                                },
+                            ),
+                        };
+
+                        let mut waypoints = Vec::new();
+                        endpoint_edges.join::<Child>(&self.waypoints).for_each(
+                            |(number, transform)| waypoints.push((number.0, transform.translation)),
+                        );
+                        waypoints.sort_unstable_by_key(|&(number, _)| number);
+
+                        let subnet_bits = bits.map(|bits| bits.0.to_vec()).unwrap_or_default();
+                        let index = subnets
+                            .iter()
+                            .position(|subnet| subnet.subnet_bits == subnet_bits)
+                            .unwrap_or_else(|| {
+                                subnets.push(Subnet {
+                                    id: Id(subnets.len().to_string().into()),
+                                    name: name.0.clone(),
+                                    subnet_bits,
+                                    endpoints: Vec::new(),
+                                });
+                                subnets.len() - 1
+                            });
+
+                        subnets[index].endpoints.push(circuitfile::Endpoint {
+                            id: Id(endpoint_count.to_string().into()),
+                            position: [position.x, position.y],
+                            portref,
+                            waypoints: waypoints
+                                .into_iter()
+                                .enumerate()
+                                .map(|(index, (_, position))| circuitfile::Waypoint {
+                                    id: Id(format!("{endpoint_count}:{index}").into()),
+                                    position: [position.x, position.y],
+                                })
+                                .collect(),
+                        });
+                        endpoint_count += 1;
+                    },
+                );
+
+                nets.push(circuitfile::Net {
+                    id: entity_id(net),
+                    name: name.0.clone(),
+                    subnets,
+                    attributes: attributes
+                        .map(|attributes| attributes.0.clone())
+                        .unwrap_or_default(),
+                    stable_id: self.stable_id(net),
+                });
+            });
+        nets
+    }
+
+    fn annotations(
+        &self,
+        circuit_edges: &RelationsItem<'_, Child>,
+    ) -> Vec<circuitfile::Annotation> {
+        let mut annotations = Vec::new();
+        circuit_edges.join::<Child>(&self.annotations).for_each(
+            |(entity, annotation, transform)| {
+                let position = transform.translation;
+                annotations.push(circuitfile::Annotation {
+                    position: [position.x, position.y],
+                    text: annotation.text.clone(),
+                    font_size: annotation.font_size,
+                    color: annotation.color,
+                    stable_id: self.stable_id(entity),
+                });
+            },
+        );
+        annotations
+    }
+
+    fn graphics(&self, circuit_edges: &RelationsItem<'_, Child>) -> Vec<circuitfile::Graphic> {
+        let mut graphics = Vec::new();
+        circuit_edges
+            .join::<Child>(&self.graphics)
+            .for_each(|(entity, graphic, transform)| {
+                let position = transform.translation;
+                graphics.push(circuitfile::Graphic {
+                    kind: circuitfile::GraphicKind::from_component(graphic.kind),
+                    position: [position.x, position.y],
+                    extent: [graphic.extent.x, graphic.extent.y],
+                    color: graphic.color,
+                    stable_id: self.stable_id(entity),
+                });
+            });
+        graphics
+    }
+
+    fn circuit_file(&self, circuit: CircuitID) -> Result<CircuitFile> {
+        let (name, attributes, circuit_edges) = self
+            .circuits
+            .get(circuit.0)
+            .map_err(|_| anyhow!("invalid circuit ID"))?;
+
+        Ok(CircuitFile {
+            version: circuitfile::VERSION,
+            modules: vec![Module {
+                id: entity_id(circuit.0),
+                name: name.0.clone(),
+                prefix: SharedStr::default(),
+                symbol_kind: Id(SharedStr::default()),
+                symbols: self.symbols(&circuit_edges)?,
+                nets: self.nets(&circuit_edges),
+                annotations: self.annotations(&circuit_edges),
+                graphics: self.graphics(&circuit_edges),
+                attributes: attributes
+                    .map(|attributes| attributes.0.clone())
+                    .unwrap_or_default(),
+                stable_id: self.stable_id(circuit.0),
+            }],
+        })
+    }
+}
+
+fn write_circuit_file(In(circuit): In<CircuitID>, writer: CircuitWriter) -> Result<CircuitFile> {
+    writer.circuit_file(circuit)
+}
+
+/// Writes a circuit to a file in the Digilogic circuit file format.
+pub fn save_json(world: &mut World, circuit: CircuitID, filename: &Path) -> Result<()> {
+    info!("saving Digilogic circuit {}", filename.display());
+
+    let file = world.run_system_once_with(circuit, write_circuit_file)?;
+    file.save(filename)
+}
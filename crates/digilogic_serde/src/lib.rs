@@ -1,18 +1,26 @@
 mod digital;
+mod formats;
 mod json;
+mod library;
+mod logisim;
+mod wires;
 mod yosys;
 
 use anyhow::{bail, Result};
 use bevy_derive::{Deref, DerefMut};
+use bevy_ecs::event::ManualEventReader;
 use bevy_ecs::prelude::*;
-use bevy_log::error;
-use digilogic_core::components::{CircuitID, FilePath};
+use digilogic_core::components::{Circuit, CircuitID, FilePath, SubCircuit};
 use digilogic_core::events::*;
 use digilogic_core::symbol::SymbolRegistry;
 use digilogic_core::HashMap;
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 
+pub use formats::*;
+pub use json::CircuitFragments;
+pub use library::{load_symbol_library, parse_symbol_kind};
+
 #[cfg(target_family = "unix")]
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 #[repr(transparent)]
@@ -47,6 +55,7 @@ fn load_circuit_file(
     filename: &Path,
     registry: &mut FileRegistry,
     symbols: &SymbolRegistry,
+    formats: &FileFormats,
 ) -> Result<CircuitID> {
     let file_id = FileId::for_path(filename)?;
 
@@ -62,18 +71,23 @@ fn load_circuit_file(
             json::load_json(commands, filename, symbols)?
         } else if ext == "dig" {
             digital::load_digital(commands, filename, symbols)?
+        } else if ext == "circ" {
+            logisim::load_logisim(commands, filename, symbols)?
         } else if ext == "yosys" {
             yosys::load_yosys(commands, filename, symbols)?
         } else if ext == "json" {
             yosys::load_yosys(commands, filename, symbols)
                 .or_else(|_| json::load_json(commands, filename, symbols))?
+        } else if let Some(importer) = formats.importer_for(ext) {
+            (importer.import)(commands, filename, symbols)?
         } else {
             bail!("unsupported file extension '{}'", ext.to_string_lossy());
         };
 
-        commands
-            .entity(circuit)
-            .insert(FilePath(filename.to_owned()));
+        // project files name their circuits relative to the project, which is only
+        // the working directory while loading
+        let path = std::path::absolute(filename).unwrap_or_else(|_| filename.to_owned());
+        commands.entity(circuit).insert(FilePath(path));
 
         let circuit = CircuitID(circuit);
         registry.0.insert(file_id, circuit);
@@ -83,21 +97,225 @@ fn load_circuit_file(
     }
 }
 
+/// Loads a circuit from a copy of its file in the Digilogic format, as if it was loaded from the file.
+fn recover_circuit_file(
+    commands: &mut Commands,
+    filename: &Path,
+    copy: &Path,
+    registry: &mut FileRegistry,
+    symbols: &SymbolRegistry,
+) -> Result<CircuitID> {
+    let Some(name) = filename.file_stem() else {
+        bail!("error getting file name of {}", filename.display());
+    };
+
+    let circuit = json::load_json_as(commands, copy, &name.to_string_lossy(), symbols)?;
+    let path = std::path::absolute(filename).unwrap_or_else(|_| filename.to_owned());
+    commands.entity(circuit).insert(FilePath(path));
+
+    let circuit = CircuitID(circuit);
+    // the file may be gone by now, saving the circuit creates it again
+    if let Ok(file_id) = FileId::for_path(filename) {
+        registry.0.insert(file_id, circuit);
+    }
+    Ok(circuit)
+}
+
+/// Writes a circuit to a file in the format named by its extension.
+pub fn save_circuit_file(world: &mut World, circuit: CircuitID, filename: &Path) -> Result<()> {
+    let Some(ext) = filename.extension() else {
+        bail!("file without extension is not supported");
+    };
+
+    if ext == "dlc" || ext == "json" {
+        return json::save_json(world, circuit, filename);
+    } else if ext == "dig" {
+        return digital::save_digital(world, circuit, filename);
+    }
+
+    let exporter = world
+        .get_resource::<FileFormats>()
+        .and_then(|formats| formats.exporter_for(ext))
+        .cloned();
+    match exporter {
+        Some(exporter) => (exporter.export)(world, circuit, filename),
+        None => bail!("unsupported file extension '{}'", ext.to_string_lossy()),
+    }
+}
+
+/// Saves circuits, after which loading their files again returns the saved circuit.
+fn handle_save_events(world: &mut World, mut save_events: Local<ManualEventReader<SaveEvent>>) {
+    let events: Vec<_> = save_events
+        .read(world.resource::<Events<SaveEvent>>())
+        .map(|ev| (ev.circuit, ev.filename.clone()))
+        .collect();
+
+    for (circuit, filename) in events {
+        let result = save_circuit_file(world, circuit, &filename);
+        if let Err(e) = result {
+            world.send_event(ErrorEvent::error(
+                "saver",
+                format!("error saving circuit {}: {e:#}", filename.display()),
+            ));
+            continue;
+        }
+
+        let path = std::path::absolute(&filename).unwrap_or_else(|_| filename.clone());
+        if let Some(mut entity) = world.get_entity_mut(circuit.0) {
+            entity.insert(FilePath(path));
+        }
+        if let Ok(file_id) = FileId::for_path(&filename) {
+            world
+                .resource_mut::<FileRegistry>()
+                .insert(file_id, circuit);
+        }
+
+        world.send_event(SavedEvent { circuit, filename });
+    }
+}
+
 fn handle_circuit_load_events(
     mut commands: Commands,
     mut circuit_load_events: EventReader<CircuitLoadEvent>,
     mut circuit_loaded_events: EventWriter<CircuitLoadedEvent>,
+    mut error_events: EventWriter<ErrorEvent>,
     mut registry: ResMut<FileRegistry>,
     symbols: Res<SymbolRegistry>,
+    formats: Res<FileFormats>,
 ) {
     for ev in circuit_load_events.read() {
-        match load_circuit_file(&mut commands, &ev.filename, &mut registry, &symbols) {
+        let result = load_circuit_file(
+            &mut commands,
+            &ev.filename,
+            &mut registry,
+            &symbols,
+            &formats,
+        );
+        match result {
+            Ok(circuit) => {
+                circuit_loaded_events.send(CircuitLoadedEvent { circuit });
+            }
+            Err(e) => {
+                error_events.send(ErrorEvent::error(
+                    "loader",
+                    format!("error loading circuit {}: {e:#}", ev.filename.display()),
+                ));
+            }
+        }
+    }
+}
+
+fn handle_circuit_recover_events(
+    mut commands: Commands,
+    mut circuit_recover_events: EventReader<CircuitRecoverEvent>,
+    mut circuit_loaded_events: EventWriter<CircuitLoadedEvent>,
+    mut error_events: EventWriter<ErrorEvent>,
+    mut registry: ResMut<FileRegistry>,
+    symbols: Res<SymbolRegistry>,
+) {
+    for ev in circuit_recover_events.read() {
+        let result = recover_circuit_file(
+            &mut commands,
+            &ev.filename,
+            &ev.copy,
+            &mut registry,
+            &symbols,
+        );
+        match result {
             Ok(circuit) => {
                 circuit_loaded_events.send(CircuitLoadedEvent { circuit });
             }
             Err(e) => {
-                // TODO: instead of this, send an ErrorEvent
-                error!("error loading circuit {}: {:?}", ev.filename.display(), e);
+                error_events.send(ErrorEvent::error(
+                    "loader",
+                    format!("error recovering circuit {}: {e:#}", ev.filename.display()),
+                ));
+            }
+        }
+    }
+}
+
+/// Loads circuits again from their files, replacing the previous versions wherever
+/// other circuits and the project refer to them.
+#[allow(clippy::too_many_arguments)]
+fn handle_circuit_reload_events(
+    mut commands: Commands,
+    mut circuit_reload_events: EventReader<CircuitReloadEvent>,
+    mut circuit_reloaded_events: EventWriter<CircuitReloadedEvent>,
+    mut circuit_loaded_events: EventWriter<CircuitLoadedEvent>,
+    mut error_events: EventWriter<ErrorEvent>,
+    mut registry: ResMut<FileRegistry>,
+    symbols: Res<SymbolRegistry>,
+    formats: Res<FileFormats>,
+    mut project: Option<ResMut<digilogic_core::resources::Project>>,
+    circuits: Query<&FilePath, With<Circuit>>,
+    mut instances: Query<&mut SubCircuit>,
+) {
+    for ev in circuit_reload_events.read() {
+        let previous = ev.circuit;
+        let Ok(FilePath(filename)) = circuits.get(previous.0) else {
+            continue;
+        };
+
+        // forget the previous version, otherwise loading the file just returns it
+        let file_ids = registry
+            .iter()
+            .filter(|(_, circuit)| **circuit == previous)
+            .map(|(file_id, _)| file_id.clone())
+            .collect::<Vec<_>>();
+        for file_id in &file_ids {
+            registry.remove(file_id);
+        }
+
+        let result = load_circuit_file(&mut commands, filename, &mut registry, &symbols, &formats);
+        match result {
+            Ok(circuit) => {
+                for mut instance in instances.iter_mut() {
+                    if instance.0 == previous.0 {
+                        instance.0 = circuit.0;
+                    }
+                }
+                if let Some(project) = project.as_deref_mut() {
+                    if project.root_circuit == Some(previous) {
+                        project.root_circuit = Some(circuit);
+                    }
+                }
+                commands.entity(previous.0).despawn();
+
+                circuit_loaded_events.send(CircuitLoadedEvent { circuit });
+                circuit_reloaded_events.send(CircuitReloadedEvent { previous, circuit });
+            }
+            Err(e) => {
+                registry.extend(file_ids.into_iter().map(|file_id| (file_id, previous)));
+                error_events.send(ErrorEvent::error(
+                    "loader",
+                    format!("error reloading circuit {}: {e:#}", filename.display()),
+                ));
+            }
+        }
+    }
+}
+
+fn handle_circuit_template_load_events(
+    mut commands: Commands,
+    mut template_load_events: EventReader<CircuitTemplateLoadEvent>,
+    mut circuit_loaded_events: EventWriter<CircuitLoadedEvent>,
+    mut error_events: EventWriter<ErrorEvent>,
+    symbols: Res<SymbolRegistry>,
+) {
+    for ev in template_load_events.read() {
+        let result = json::load_json_str(&mut commands, &ev.name, &ev.contents, &symbols);
+        match result {
+            Ok(circuit) => {
+                circuit_loaded_events.send(CircuitLoadedEvent {
+                    circuit: CircuitID(circuit),
+                });
+            }
+            Err(e) => {
+                error_events.send(ErrorEvent::error(
+                    "loader",
+                    format!("error loading circuit template {}: {e:#}", ev.name),
+                ));
             }
         }
     }
@@ -117,6 +335,7 @@ fn load_project_file(
     filename: &Path,
     registry: &mut FileRegistry,
     symbols: &SymbolRegistry,
+    formats: &FileFormats,
 ) -> Result<Vec<CircuitID>> {
     let ron = std::fs::read_to_string(filename)?;
     let project: Project = ron::Options::default()
@@ -129,7 +348,9 @@ fn load_project_file(
     let circuits = project
         .circuits
         .iter()
-        .map(|circuit_filename| load_circuit_file(commands, circuit_filename, registry, symbols))
+        .map(|circuit_filename| {
+            load_circuit_file(commands, circuit_filename, registry, symbols, formats)
+        })
         .collect::<Result<Vec<_>>>()?;
 
     if let Some(prev_dir) = prev_dir {
@@ -145,16 +366,26 @@ fn load_project_file(
     Ok(circuits)
 }
 
+#[allow(clippy::too_many_arguments)]
 fn handle_project_load_events(
     mut commands: Commands,
     mut project_load_events: EventReader<ProjectLoadEvent>,
     mut project_loaded_events: EventWriter<ProjectLoadedEvent>,
     mut circuit_loaded_events: EventWriter<CircuitLoadedEvent>,
+    mut error_events: EventWriter<ErrorEvent>,
     mut registry: ResMut<FileRegistry>,
     symbols: Res<SymbolRegistry>,
+    formats: Res<FileFormats>,
 ) {
     for ev in project_load_events.read() {
-        match load_project_file(&mut commands, &ev.filename, &mut registry, &symbols) {
+        let result = load_project_file(
+            &mut commands,
+            &ev.filename,
+            &mut registry,
+            &symbols,
+            &formats,
+        );
+        match result {
             Ok(circuits) => {
                 for circuit in circuits {
                     circuit_loaded_events.send(CircuitLoadedEvent { circuit });
@@ -162,8 +393,10 @@ fn handle_project_load_events(
                 project_loaded_events.send(ProjectLoadedEvent);
             }
             Err(e) => {
-                // TODO: instead of this, send an ErrorEvent
-                error!("error loading project {}: {:?}", ev.filename.display(), e);
+                error_events.send(ErrorEvent::error(
+                    "loader",
+                    format!("error loading project {}: {e:#}", ev.filename.display()),
+                ));
             }
         }
     }
@@ -175,9 +408,20 @@ pub struct LoadSavePlugin;
 impl bevy_app::Plugin for LoadSavePlugin {
     fn build(&self, app: &mut bevy_app::App) {
         app.init_resource::<FileRegistry>();
+        app.init_resource::<FileFormats>();
         app.add_systems(
             bevy_app::Update,
-            (handle_circuit_load_events, handle_project_load_events),
+            (
+                // recovered circuits are picked up by loading their files in the same frame
+                handle_circuit_recover_events
+                    .before(handle_circuit_load_events)
+                    .before(handle_project_load_events),
+                handle_circuit_load_events,
+                handle_circuit_reload_events,
+                handle_circuit_template_load_events,
+                handle_project_load_events,
+                handle_save_events,
+            ),
         );
     }
 }
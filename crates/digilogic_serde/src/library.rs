@@ -0,0 +1,320 @@
+//! Symbol kinds described in data files, loaded into the [`SymbolRegistry`] at startup.
+//!
+//! Every `.json` or `.toml` file in the library directory describes one kind:
+//!
+//! ```toml
+//! name = "LATCH"
+//! designatorPrefix = "U"
+//! size = [40, 40]
+//! shape = "chip"
+//!
+//! [[ports]]
+//! name = "D"
+//! side = "left"
+//! offset = 0
+//! direction = "input"
+//! ```
+//!
+//! Ports sit on a side of the outline, `offset` from its top or left end,
+//! and are an `input`, an `output` or `bidirectional`.
+
+use anyhow::{bail, Context, Result};
+use bevy_log::{info, warn};
+use digilogic_core::components::{PortDirection, PortSide, Shape};
+use digilogic_core::symbol::{PortDef, SymbolKindDescriptor, SymbolRegistry};
+use digilogic_core::transform::{BoundingBox, Vec2};
+use digilogic_core::{fixed, Fixed, HashSet, SharedStr};
+use serde::Deserialize;
+use std::path::Path;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+struct KindFile {
+    name: SharedStr,
+    #[serde(default = "default_designator_prefix")]
+    designator_prefix: SharedStr,
+    /// The width and height of the outline, with its top left corner at the symbol position.
+    size: [Fixed; 2],
+    #[serde(default)]
+    shape: ShapeRef,
+    #[serde(default)]
+    ports: Vec<PortFile>,
+}
+
+fn default_designator_prefix() -> SharedStr {
+    SharedStr::new_static("U")
+}
+
+/// The built-in shape a kind is drawn with.
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ShapeRef {
+    #[default]
+    Chip,
+    And,
+    Or,
+    Xor,
+    Not,
+    Input,
+    Output,
+    FlipFlop,
+    Mux,
+    Demux,
+    Adder,
+    Subtractor,
+    Comparator,
+    Counter,
+    ShiftRegister,
+    Constant,
+    Tunnel,
+    Splitter,
+    PullUp,
+    PullDown,
+    Buffer,
+    OpenDrain,
+}
+
+impl ShapeRef {
+    fn to_component(self) -> Shape {
+        match self {
+            Self::Chip => Shape::Chip,
+            Self::And => Shape::And,
+            Self::Or => Shape::Or,
+            Self::Xor => Shape::Xor,
+            Self::Not => Shape::Not,
+            Self::Input => Shape::Input,
+            Self::Output => Shape::Output,
+            Self::FlipFlop => Shape::FlipFlop,
+            Self::Mux => Shape::Mux,
+            Self::Demux => Shape::Demux,
+            Self::Adder => Shape::Adder,
+            Self::Subtractor => Shape::Subtractor,
+            Self::Comparator => Shape::Comparator,
+            Self::Counter => Shape::Counter,
+            Self::ShiftRegister => Shape::ShiftRegister,
+            Self::Constant => Shape::Constant,
+            Self::Tunnel => Shape::Tunnel,
+            Self::Splitter => Shape::Splitter,
+            Self::PullUp => Shape::PullUp,
+            Self::PullDown => Shape::PullDown,
+            Self::Buffer => Shape::Buffer,
+            Self::OpenDrain => Shape::OpenDrain,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PortFile {
+    name: SharedStr,
+    side: Side,
+    #[serde(default)]
+    offset: Fixed,
+    direction: Direction,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Side {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+impl Side {
+    fn to_component(self) -> PortSide {
+        match self {
+            Self::Left => PortSide::Left,
+            Self::Right => PortSide::Right,
+            Self::Top => PortSide::Top,
+            Self::Bottom => PortSide::Bottom,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Direction {
+    Input,
+    Output,
+    Bidirectional,
+}
+
+impl Direction {
+    fn to_component(self) -> PortDirection {
+        match self {
+            Self::Input => PortDirection::Input,
+            Self::Output => PortDirection::Output,
+            Self::Bidirectional => PortDirection::Bidirectional,
+        }
+    }
+}
+
+impl KindFile {
+    fn into_descriptor(self) -> Result<SymbolKindDescriptor> {
+        let [width, height] = self.size;
+        if (width <= fixed!(0)) || (height <= fixed!(0)) {
+            bail!("symbol kind `{}` has an empty size", self.name);
+        }
+
+        let mut names = HashSet::default();
+        let mut ports = Vec::with_capacity(self.ports.len());
+        for port in self.ports {
+            if !names.insert(port.name.clone()) {
+                bail!("port `{}` is defined more than once", port.name);
+            }
+
+            let (position, length) = match port.side {
+                Side::Left => (
+                    Vec2 {
+                        x: fixed!(0),
+                        y: port.offset,
+                    },
+                    height,
+                ),
+                Side::Right => (
+                    Vec2 {
+                        x: width,
+                        y: port.offset,
+                    },
+                    height,
+                ),
+                Side::Top => (
+                    Vec2 {
+                        x: port.offset,
+                        y: fixed!(0),
+                    },
+                    width,
+                ),
+                Side::Bottom => (
+                    Vec2 {
+                        x: port.offset,
+                        y: height,
+                    },
+                    width,
+                ),
+            };
+            if (port.offset < fixed!(0)) || (port.offset > length) {
+                bail!("port `{}` lies outside of its side", port.name);
+            }
+
+            ports.push(PortDef::new(
+                port.name,
+                position,
+                port.direction.to_component(),
+                port.side.to_component(),
+            ));
+        }
+
+        Ok(SymbolKindDescriptor {
+            name: self.name,
+            designator_prefix: self.designator_prefix,
+            shape: self.shape.to_component(),
+            bounding_box: BoundingBox::from_top_left_size(Vec2::ZERO, width, height),
+            ports,
+        })
+    }
+}
+
+/// Parses a symbol kind description, in the format given by the file extension `ext`.
+pub fn parse_symbol_kind(contents: &str, ext: &str) -> Result<SymbolKindDescriptor> {
+    let file: KindFile = match ext {
+        "json" => serde_json::from_str(contents)?,
+        "toml" => toml::from_str(contents)?,
+        _ => bail!("unsupported symbol kind file extension '{ext}'"),
+    };
+    file.into_descriptor()
+}
+
+fn load_symbol_kind(path: &Path, ext: &str, registry: &mut SymbolRegistry) -> Result<()> {
+    let contents = std::fs::read_to_string(path)?;
+    let descriptor = parse_symbol_kind(&contents, ext)?;
+    registry.register(descriptor)?;
+    Ok(())
+}
+
+/// Registers the symbol kinds described by the files in `dir`, in the order of their names.
+/// Invalid files are skipped with a warning. Returns the number of kinds registered.
+pub fn load_symbol_library(dir: &Path, registry: &mut SymbolRegistry) -> usize {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return 0,
+        Err(err) => {
+            warn!("error reading symbol library {}: {err}", dir.display());
+            return 0;
+        }
+    };
+
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .collect();
+    paths.sort();
+
+    let mut count = 0;
+    for path in paths {
+        let Some(ext) = path.extension().and_then(|ext| ext.to_str()) else {
+            continue;
+        };
+        if !matches!(ext, "json" | "toml") {
+            continue;
+        }
+
+        let result = load_symbol_kind(&path, ext, registry)
+            .with_context(|| format!("loading symbol kind {}", path.display()));
+        match result {
+            Ok(()) => count += 1,
+            Err(err) => warn!("{err:#}"),
+        }
+    }
+
+    if count > 0 {
+        info!("loaded {count} symbol kind(s) from {}", dir.display());
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use digilogic_core::components::SymbolKind;
+
+    #[test]
+    fn parse_and_register() {
+        let toml = r#"
+            name = "LATCH"
+            size = [40, 40]
+
+            [[ports]]
+            name = "D"
+            side = "left"
+            direction = "input"
+
+            [[ports]]
+            name = "Q"
+            side = "right"
+            offset = 20
+            direction = "output"
+        "#;
+        let json = r#"{
+            "name": "BUF",
+            "designatorPrefix": "B",
+            "size": [40, 20],
+            "shape": "not",
+            "ports": [{ "name": "A", "side": "top", "offset": 50, "direction": "input" }]
+        }"#;
+
+        let mut registry = SymbolRegistry::default();
+        let latch = parse_symbol_kind(toml, "toml").unwrap();
+        assert_eq!(latch.designator_prefix.as_str(), "U");
+        assert_eq!(latch.ports.len(), 2);
+        assert_eq!(registry.register(latch).unwrap(), SymbolKind::Custom(24));
+        assert!(registry.get_by_name(&"LATCH".into()).is_some());
+
+        // the port is further along than the top side is wide
+        assert!(parse_symbol_kind(json, "json").is_err());
+        let json = json.replace("50", "20");
+        let buf = parse_symbol_kind(&json, "json").unwrap();
+        assert!(matches!(buf.shape, Shape::Not));
+    }
+}
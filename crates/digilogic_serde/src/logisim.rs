@@ -0,0 +1,896 @@
+mod circuitfile;
+
+use crate::wires::WireMap;
+use aery::prelude::*;
+use anyhow::{bail, Result};
+use bevy_ecs::prelude::*;
+use bevy_log::{info, warn};
+use circuitfile::{Appearance, Comp, Location, Project};
+use digilogic_core::bundles::*;
+use digilogic_core::components::*;
+use digilogic_core::symbol::{
+    build_port, SymbolRegistry, INPUTS_PARAM, LABEL_PARAM, SELECT_BITS_PARAM, VALUE_PARAM,
+    WIDTH_PARAM,
+};
+use digilogic_core::transform::*;
+use digilogic_core::visibility::VisibilityBundle;
+use digilogic_core::{HashMap, SharedStr};
+use std::num::NonZeroU8;
+use std::path::Path;
+
+/// Logisim's grid is half as fine as the one the symbols are drawn on.
+const SCALE: i32 = 2;
+
+/// The number of inputs of gates that don't set it.
+const DEFAULT_GATE_INPUTS: u32 = 5;
+
+/// The number of inputs of gates that don't set it in Logisim Evolution.
+const DEFAULT_EVOLUTION_GATE_INPUTS: u32 = 2;
+
+pub fn load_logisim(
+    commands: &mut Commands,
+    filename: &Path,
+    symbols: &SymbolRegistry,
+) -> Result<Entity> {
+    info!("loading Logisim circuit {}", filename.display());
+
+    let Some(name) = filename.file_stem() else {
+        bail!("error getting file name of {}", filename.display(),);
+    };
+
+    let project = Project::load(filename)?;
+    let Some(circuit) = project.main_circuit() else {
+        bail!("project has no circuits");
+    };
+
+    let mut loader = Loader {
+        project: &project,
+        symbols,
+        circuits: HashMap::default(),
+    };
+    loader.translate_circuit(commands, circuit, &name.to_string_lossy())
+}
+
+struct Loader<'a> {
+    project: &'a Project,
+    symbols: &'a SymbolRegistry,
+    /// The circuits that were translated for their instances, by their Logisim names.
+    /// `None` while a circuit is being translated.
+    circuits: HashMap<String, Option<Entity>>,
+}
+
+impl Loader<'_> {
+    fn translate_circuit(
+        &mut self,
+        commands: &mut Commands,
+        circuit: &circuitfile::Circuit,
+        name: &str,
+    ) -> Result<Entity> {
+        let mut wire_map = WireMap::default();
+        // the labels of pins and tunnels, which name the nets they connect to
+        let mut labels = HashMap::<Entity, SharedStr>::default();
+        let mut instance_count = 0;
+
+        let circuit_id = commands
+            .spawn(CircuitBundle {
+                circuit: Circuit,
+                name: Name(name.into()),
+            })
+            .id();
+
+        for comp in circuit.comps.iter() {
+            if comp.lib.is_some() {
+                translate_symbol(
+                    self.project,
+                    comp,
+                    commands,
+                    circuit_id,
+                    &mut wire_map,
+                    &mut labels,
+                    self.symbols,
+                )?;
+            } else if self.translate_instance(
+                comp,
+                commands,
+                circuit_id,
+                instance_count,
+                &mut wire_map,
+            )? {
+                instance_count += 1;
+            }
+        }
+
+        for wire in circuit.wires.iter() {
+            wire_map.add_wire([position(wire.from)?, position(wire.to)?]);
+        }
+        for (net_id, ports) in wire_map.translate(commands, circuit_id) {
+            if let Some(label) = ports.iter().find_map(|port| labels.get(port)) {
+                commands.entity(net_id).insert(Name(label.clone()));
+            }
+        }
+
+        Ok(circuit_id)
+    }
+
+    /// Translates an instance of another circuit of the project to a symbol with its ports,
+    /// returns whether the circuit exists.
+    fn translate_instance(
+        &mut self,
+        comp: &Comp,
+        commands: &mut Commands,
+        circuit_id: Entity,
+        designator_number: u32,
+        wire_map: &mut WireMap,
+    ) -> Result<bool> {
+        let project = self.project;
+        let Some(circuit) = project.circuit(&comp.name) else {
+            warn!("skipping instance of unknown Logisim circuit {}", comp.name);
+            return Ok(false);
+        };
+
+        let sub_circuit = match self.circuits.get(&circuit.name) {
+            Some(Some(sub_circuit)) => *sub_circuit,
+            Some(None) => bail!("Logisim circuit {} contains itself", circuit.name),
+            None => {
+                self.circuits.insert(circuit.name.clone(), None);
+                let sub_circuit = self.translate_circuit(commands, circuit, &circuit.name)?;
+                self.circuits
+                    .insert(circuit.name.clone(), Some(sub_circuit));
+                sub_circuit
+            }
+        };
+
+        let outline = Outline::of(project, circuit);
+        let rotation = facing(comp) * outline.facing.inverse();
+        let translation = position(comp.loc)?;
+
+        let instance = commands
+            .spawn((
+                Symbol,
+                Name(circuit.name.as_str().into()),
+                DesignatorPrefix(SharedStr::new_static("X")),
+                DesignatorNumber(designator_number),
+                Shape::Chip,
+                SubCircuit(sub_circuit),
+                TransformBundle {
+                    transform: Transform {
+                        translation,
+                        rotation,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                VisibilityBundle::default(),
+                BoundingBoxBundle {
+                    bounding_box: BoundingBox::from_points(
+                        position(outline.min)?,
+                        position(outline.max)?,
+                    ),
+                    ..Default::default()
+                },
+            ))
+            .set::<Child>(circuit_id)
+            .id();
+
+        for (index, (pin, offset)) in outline.ports.iter().copied().enumerate() {
+            let output = is_output(pin);
+            // ports are referred to by their names, so pins without a label are numbered
+            let name = label(pin).map_or_else(|| format!("P{index}").into(), SharedStr::from);
+            let bit_width = bit_width(pin)?;
+            let port_position = position(offset)?;
+            let port = build_port(
+                commands,
+                instance,
+                name,
+                port_position,
+                if output {
+                    PortDirection::Output
+                } else {
+                    PortDirection::Input
+                },
+                outline.side(offset),
+                bit_width,
+            );
+            wire_map.add_port(
+                translation + port_position.rotate(rotation),
+                port,
+                bit_width,
+            );
+        }
+
+        Ok(true)
+    }
+}
+
+fn position(location: Location) -> Result<Vec2> {
+    Ok(Vec2 {
+        x: (location.x * SCALE).try_into()?,
+        y: (location.y * SCALE).try_into()?,
+    })
+}
+
+fn integer_attribute(comp: &Comp, name: &str, default: u32) -> Result<u32> {
+    match comp.attributes.get(name) {
+        Some(value) => Ok(value.parse()?),
+        None => Ok(default),
+    }
+}
+
+/// A pin of a Logisim component, relative to its location when facing east.
+struct Pin {
+    offset: Location,
+    /// Whether the pin is as wide as the data of the component, otherwise it is a single bit.
+    data: bool,
+}
+
+const fn pin(x: i32, y: i32) -> Option<Pin> {
+    Some(Pin {
+        offset: Location { x, y },
+        data: true,
+    })
+}
+
+const fn control_pin(x: i32, y: i32) -> Option<Pin> {
+    Some(Pin {
+        offset: Location { x, y },
+        data: false,
+    })
+}
+
+/// The vertical offsets of the inputs of a gate, as Logisim spaces them.
+fn gate_input_offsets(inputs: u32, size: i32) -> Vec<i32> {
+    let inputs = inputs as i32;
+    let (skip_start, skip_dist, skip_lower_even) = if inputs <= 3 {
+        if size < 40 {
+            (-5, 10, 10)
+        } else if (size < 60) || (inputs <= 2) {
+            (-10, 20, 20)
+        } else {
+            (-15, 30, 30)
+        }
+    } else if (inputs == 4) && (size >= 60) {
+        (-5, 20, 0)
+    } else {
+        (-5, 10, 10)
+    };
+
+    (0..inputs)
+        .map(|index| {
+            if inputs % 2 == 1 {
+                skip_start * (inputs - 1) + skip_dist * index
+            } else if index >= inputs / 2 {
+                skip_start * inputs + skip_dist * index + skip_lower_even
+            } else {
+                skip_start * inputs + skip_dist * index
+            }
+        })
+        .collect()
+}
+
+/// The data pins of a multiplexer or demultiplexer, then its select pin.
+fn plexer_pins(select_bits: u32, side: i32) -> Vec<Option<Pin>> {
+    let count = 1 << select_bits;
+    let data = if count == 2 {
+        vec![pin(side * 30, -10), pin(side * 30, 10)]
+    } else {
+        (0..count)
+            .map(|index| pin(side * 40, -(count / 2) * 10 + 10 * index))
+            .collect()
+    };
+    let select_y = if count == 2 { 20 } else { (count / 2) * 10 };
+    // a select input wider than a bit is split into single bit ports
+    let select = (0..select_bits).map(|_| match select_bits {
+        1 => control_pin(side * 20, select_y),
+        _ => None,
+    });
+    data.into_iter().chain(select).collect()
+}
+
+/// What a Logisim component translates to.
+struct Element {
+    kind: SymbolKind,
+    /// The pins in the order of the ports of the kind, `None` for ports without one.
+    pins: Vec<Option<Pin>>,
+    parameters: Vec<(&'static str, ParamValue)>,
+}
+
+/// The element a component of a library translates to, `None` if there is no equivalent.
+fn element(lib: &str, comp: &Comp, evolution: bool) -> Result<Option<Element>> {
+    let mut parameters = Vec::new();
+    let (kind, pins) = match (lib, comp.name.as_str()) {
+        ("#Wiring", "Pin") => {
+            let kind = if is_output(comp) {
+                SymbolKind::Out
+            } else {
+                SymbolKind::In
+            };
+            (kind, vec![pin(0, 0)])
+        }
+        ("#Wiring", "Clock") => (SymbolKind::In, vec![pin(0, 0)]),
+        ("#Wiring", "Tunnel") => {
+            let label = label(comp).unwrap_or_default();
+            parameters.push((LABEL_PARAM, ParamValue::Text(label.into())));
+            (SymbolKind::Tunnel, vec![pin(0, 0)])
+        }
+        ("#Wiring", "Constant" | "Power" | "Ground") => {
+            let value = match comp.name.as_str() {
+                "Power" => "1",
+                "Ground" => "0",
+                _ => comp.attributes.get("value").map_or("0x1", String::as_str),
+            };
+            let width = integer_attribute(comp, "width", 1)?;
+            parameters.push((WIDTH_PARAM, ParamValue::Integer(width)));
+            parameters.push((VALUE_PARAM, ParamValue::Text(value.into())));
+            (SymbolKind::Constant, vec![pin(0, 0)])
+        }
+        ("#Wiring", "Pull Resistor") => match comp.attributes.get("pull").map(String::as_str) {
+            None | Some("0") => (SymbolKind::PullDown, vec![pin(0, 0)]),
+            Some("1") => (SymbolKind::PullUp, vec![pin(0, 0)]),
+            Some(_) => return Ok(None),
+        },
+        ("#Gates", "AND Gate" | "OR Gate" | "XOR Gate") => {
+            // XOR gates are longer by the width of their extra curve
+            let (kind, extra_length) = match comp.name.as_str() {
+                "AND Gate" => (SymbolKind::And, 0),
+                "OR Gate" => (SymbolKind::Or, 0),
+                _ => (SymbolKind::Xor, 10),
+            };
+            let default_inputs = if evolution {
+                DEFAULT_EVOLUTION_GATE_INPUTS
+            } else {
+                DEFAULT_GATE_INPUTS
+            };
+            let inputs = integer_attribute(comp, "inputs", default_inputs)?;
+            let size = integer_attribute(comp, "size", 50)? as i32;
+            parameters.push((INPUTS_PARAM, ParamValue::Integer(inputs)));
+
+            let pins = gate_input_offsets(inputs, size)
+                .into_iter()
+                .map(|y| pin(-(size + extra_length), y))
+                .chain(std::iter::once(pin(0, 0)))
+                .collect();
+            (kind, pins)
+        }
+        ("#Gates", "NOT Gate") => {
+            let size = integer_attribute(comp, "size", 30)? as i32;
+            (SymbolKind::Not, vec![pin(-size, 0), pin(0, 0)])
+        }
+        ("#Gates", "Controlled Buffer") => {
+            let control_y = match comp.attributes.get("control").map(String::as_str) {
+                Some("left") => -10,
+                _ => 10,
+            };
+            let pins = vec![pin(-20, 0), pin(0, 0), control_pin(-10, control_y)];
+            (SymbolKind::Buffer, pins)
+        }
+        ("#Plexers", "Multiplexer") => {
+            let select_bits = integer_attribute(comp, "select", 1)?;
+            parameters.push((SELECT_BITS_PARAM, ParamValue::Integer(select_bits)));
+            let mut pins = plexer_pins(select_bits, -1);
+            pins.push(pin(0, 0));
+            (SymbolKind::Mux, pins)
+        }
+        ("#Plexers", "Demultiplexer") => {
+            let select_bits = integer_attribute(comp, "select", 1)?;
+            parameters.push((SELECT_BITS_PARAM, ParamValue::Integer(select_bits)));
+            let mut pins = plexer_pins(select_bits, 1);
+            pins.push(pin(0, 0));
+            (SymbolKind::Demux, pins)
+        }
+        _ => return Ok(None),
+    };
+
+    Ok(Some(Element {
+        kind,
+        pins,
+        parameters,
+    }))
+}
+
+/// Turns the offset of a pin of a component facing east to the way it faces.
+fn face(offset: Location, facing: Rotation) -> Location {
+    let Location { x, y } = offset;
+    match facing {
+        Rotation::Rot0 => Location { x, y },
+        Rotation::Rot90 => Location { x: y, y: x },
+        Rotation::Rot180 => Location { x: -x, y },
+        Rotation::Rot270 => Location { x: y, y: -x },
+    }
+}
+
+/// The rotation of a component that faces `facing`, relative to facing east.
+fn rotation(facing: Option<&str>) -> Rotation {
+    match facing {
+        Some("south") => Rotation::Rot90,
+        Some("west") => Rotation::Rot180,
+        Some("north") => Rotation::Rot270,
+        _ => Rotation::Rot0,
+    }
+}
+
+fn facing(comp: &Comp) -> Rotation {
+    rotation(comp.attributes.get("facing").map(String::as_str))
+}
+
+fn bit_width(comp: &Comp) -> Result<BitWidth> {
+    let width = integer_attribute(comp, "width", 1)?;
+    Ok(u8::try_from(width)
+        .ok()
+        .and_then(NonZeroU8::new)
+        .map(BitWidth)
+        .unwrap_or(BitWidth(NonZeroU8::MIN)))
+}
+
+fn label(comp: &Comp) -> Option<&str> {
+    comp.attributes
+        .get("label")
+        .map(String::as_str)
+        .filter(|label| !label.is_empty())
+}
+
+fn is_pin(project: &Project, comp: &Comp) -> bool {
+    project.lib_desc(comp) == Some("#Wiring") && comp.name == "Pin"
+}
+
+fn is_output(pin: &Comp) -> bool {
+    pin.attributes
+        .get("output")
+        .is_some_and(|value| value == "true")
+}
+
+/// Where the ports of instances of a circuit are, relative to the location of an instance
+/// that faces the same way as the appearance of the circuit.
+struct Outline<'a> {
+    /// The way the appearance faces, which instances are rotated relative to.
+    facing: Rotation,
+    /// The pins of the circuit with the locations of their ports.
+    ports: Vec<(&'a Comp, Location)>,
+    min: Location,
+    max: Location,
+}
+
+impl<'a> Outline<'a> {
+    fn of(project: &Project, circuit: &'a circuitfile::Circuit) -> Self {
+        let pins = circuit
+            .comps
+            .iter()
+            .filter(|comp| is_pin(project, comp))
+            .collect::<Vec<_>>();
+        let custom = circuit
+            .appearance
+            .as_ref()
+            .filter(|appearance| appearance.anchor.is_some() && !appearance.ports.is_empty());
+
+        match (
+            circuit.attributes.get("appearance").map(String::as_str),
+            custom,
+        ) {
+            (Some("custom") | None, Some(appearance)) => Self::custom(appearance, &pins),
+            (Some("logisim_evolution"), _) => Self::evolution(circuit, pins),
+            (None, _) if project.is_evolution() => Self::evolution(circuit, pins),
+            _ => Self::classic(pins),
+        }
+    }
+
+    /// The ports as they are drawn on the custom appearance of the circuit.
+    fn custom(appearance: &Appearance, pins: &[&'a Comp]) -> Self {
+        let (anchor, facing) = appearance
+            .anchor
+            .as_ref()
+            .map(|anchor| (anchor.loc, rotation(anchor.facing.as_deref())))
+            .unwrap_or_default();
+
+        let ports = appearance
+            .ports
+            .iter()
+            .filter_map(|port| {
+                let pin = pins.iter().find(|pin| pin.loc == port.pin)?;
+                Some((*pin, port.loc - anchor))
+            })
+            .collect::<Vec<_>>();
+
+        let corners = if appearance.rects.is_empty() {
+            ports.iter().flat_map(|&(_, loc)| [loc, loc]).collect()
+        } else {
+            appearance
+                .rects
+                .iter()
+                .flat_map(|rect| {
+                    let size = Location {
+                        x: rect.width,
+                        y: rect.height,
+                    };
+                    [rect.loc - anchor, rect.loc + size - anchor]
+                })
+                .collect::<Vec<_>>()
+        };
+        let min = corners
+            .iter()
+            .fold(Location::default(), |min, corner| Location {
+                x: min.x.min(corner.x),
+                y: min.y.min(corner.y),
+            });
+        let max = corners
+            .iter()
+            .fold(Location::default(), |max, corner| Location {
+                x: max.x.max(corner.x),
+                y: max.y.max(corner.y),
+            });
+
+        Self {
+            facing,
+            ports,
+            min,
+            max,
+        }
+    }
+
+    /// The box the original Logisim draws, with every pin on the side opposite to where it faces.
+    fn classic(pins: Vec<&'a Comp>) -> Self {
+        let (mut west, mut east, mut north, mut south) =
+            (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+        for pin in pins {
+            match facing(pin) {
+                Rotation::Rot0 => west.push(pin),
+                Rotation::Rot90 => north.push(pin),
+                Rotation::Rot180 => east.push(pin),
+                Rotation::Rot270 => south.push(pin),
+            }
+        }
+        west.sort_by_key(|pin| (pin.loc.y, pin.loc.x));
+        east.sort_by_key(|pin| (pin.loc.y, pin.loc.x));
+        north.sort_by_key(|pin| (pin.loc.x, pin.loc.y));
+        south.sort_by_key(|pin| (pin.loc.x, pin.loc.y));
+
+        let [num_west, num_east, num_north, num_south] =
+            [&west, &east, &north, &south].map(|pins| pins.len() as i32);
+        let max_vertical = num_north.max(num_south);
+        let max_horizontal = num_east.max(num_west);
+
+        let offset = |num_facing: i32, num_opposite: i32, max_others: i32| {
+            let max_this = num_facing.max(num_opposite);
+            let max_offset = match (max_this, max_others) {
+                (0 | 1, 0) => 15,
+                (3.., 0) => 5,
+                _ => 10,
+            };
+            max_offset + 10 * ((max_this - num_facing) / 2)
+        };
+        let dimension = |max_this: i32, max_others: i32| {
+            if max_this < 3 {
+                30
+            } else if max_others == 0 {
+                10 * max_this
+            } else {
+                10 * max_this + 10
+            }
+        };
+        let offset_west = offset(num_west, num_east, max_vertical);
+        let offset_east = offset(num_east, num_west, max_vertical);
+        let offset_north = offset(num_north, num_south, max_horizontal);
+        let offset_south = offset(num_south, num_north, max_horizontal);
+        let width = dimension(max_vertical, max_horizontal);
+        let height = dimension(max_horizontal, max_vertical);
+
+        let anchor = if num_east > 0 {
+            Location {
+                x: width,
+                y: offset_east,
+            }
+        } else if num_north > 0 {
+            Location {
+                x: offset_north,
+                y: 0,
+            }
+        } else if num_west > 0 {
+            Location {
+                x: 0,
+                y: offset_west,
+            }
+        } else if num_south > 0 {
+            Location {
+                x: offset_south,
+                y: height,
+            }
+        } else {
+            Location::default()
+        };
+
+        let mut ports = Vec::new();
+        let mut place = |pins: Vec<&'a Comp>, start: Location, step: Location| {
+            for (index, pin) in pins.into_iter().enumerate() {
+                let index = index as i32;
+                let loc = Location {
+                    x: start.x + step.x * index,
+                    y: start.y + step.y * index,
+                };
+                ports.push((pin, loc - anchor));
+            }
+        };
+        let (down, right) = (Location { x: 0, y: 10 }, Location { x: 10, y: 0 });
+        place(
+            west,
+            Location {
+                x: 0,
+                y: offset_west,
+            },
+            down,
+        );
+        place(
+            east,
+            Location {
+                x: width,
+                y: offset_east,
+            },
+            down,
+        );
+        place(
+            north,
+            Location {
+                x: offset_north,
+                y: 0,
+            },
+            right,
+        );
+        place(
+            south,
+            Location {
+                x: offset_south,
+                y: height,
+            },
+            right,
+        );
+
+        Self {
+            facing: Rotation::Rot0,
+            ports,
+            min: Location::default() - anchor,
+            max: Location {
+                x: width,
+                y: height,
+            } - anchor,
+        }
+    }
+
+    /// The box Logisim Evolution draws by default, with the inputs on the left and
+    /// the outputs on the right, wide enough for the labels of the pins and the circuit name.
+    fn evolution(circuit: &circuitfile::Circuit, pins: Vec<&'a Comp>) -> Self {
+        const CHAR_WIDTH: i32 = 8;
+        const PIN_SPACING: i32 = 20;
+        const TITLE_HEIGHT: i32 = 20;
+
+        let (mut outputs, mut inputs): (Vec<_>, Vec<_>) =
+            pins.into_iter().partition(|pin| is_output(pin));
+        inputs.sort_by_key(|pin| (pin.loc.y, pin.loc.x));
+        outputs.sort_by_key(|pin| (pin.loc.y, pin.loc.x));
+
+        let label_width = |pins: &[&Comp]| {
+            pins.iter()
+                .map(|pin| label(pin).map_or(0, |label| label.chars().count() as i32))
+                .max()
+                .unwrap_or_default()
+                * CHAR_WIDTH
+        };
+        let fixed_size = circuit
+            .attributes
+            .get("circuitnamedboxfixedsize")
+            .is_some_and(|value| value == "true");
+        let text_width = if fixed_size {
+            25 * CHAR_WIDTH
+        } else {
+            let title_width = circuit.name.chars().count() as i32 * CHAR_WIDTH;
+            (label_width(&inputs) + label_width(&outputs) + 35).max(title_width + 15)
+        };
+        let width = (text_width / 10) * 10 + 20;
+        let max_vertical = inputs.len().max(outputs.len()) as i32;
+        let height = if max_vertical > 0 {
+            max_vertical * PIN_SPACING + TITLE_HEIGHT
+        } else {
+            10 + TITLE_HEIGHT
+        };
+
+        let anchor = if !outputs.is_empty() {
+            Location { x: width, y: 10 }
+        } else if !inputs.is_empty() {
+            Location { x: 0, y: 10 }
+        } else {
+            Location::default()
+        };
+
+        let place = |pins: Vec<&'a Comp>, x: i32| {
+            pins.into_iter().enumerate().map(move |(index, pin)| {
+                let loc = Location {
+                    x,
+                    y: 10 + PIN_SPACING * index as i32,
+                };
+                (pin, loc - anchor)
+            })
+        };
+        let ports = place(inputs, 0).chain(place(outputs, width)).collect();
+
+        Self {
+            facing: Rotation::Rot0,
+            ports,
+            min: Location::default() - anchor,
+            max: Location {
+                x: width,
+                y: height,
+            } - anchor,
+        }
+    }
+
+    /// The side of the outline a port at `offset` is on.
+    fn side(&self, offset: Location) -> PortSide {
+        [
+            (offset.x - self.min.x, PortSide::Left),
+            (self.max.x - offset.x, PortSide::Right),
+            (offset.y - self.min.y, PortSide::Top),
+            (self.max.y - offset.y, PortSide::Bottom),
+        ]
+        .into_iter()
+        .min_by_key(|&(distance, _)| distance)
+        .map_or(PortSide::Left, |(_, side)| side)
+    }
+}
+
+fn translate_symbol(
+    project: &Project,
+    comp: &Comp,
+    commands: &mut Commands,
+    circuit_id: Entity,
+    wire_map: &mut WireMap,
+    labels: &mut HashMap<Entity, SharedStr>,
+    symbols: &SymbolRegistry,
+) -> Result<()> {
+    let element = match project.lib_desc(comp) {
+        Some(lib) => element(lib, comp, project.is_evolution())?,
+        None => None,
+    };
+    let Some(element) = element else {
+        warn!(
+            "skipping Logisim component {}, it has no equivalent",
+            comp.name
+        );
+        return Ok(());
+    };
+
+    let facing = facing(comp);
+    // output symbols face west when they aren't rotated, like output pins usually do
+    let rotation = match element.kind {
+        SymbolKind::Out => facing * Rotation::Rot180,
+        _ => facing,
+    };
+    let bit_width = bit_width(comp)?;
+    let label = label(comp).map(SharedStr::from);
+
+    let mut symbol_builder = symbols.get(element.kind);
+    for (name, value) in element.parameters {
+        symbol_builder.parameter(name, value);
+    }
+    if let Some(label) = &label {
+        symbol_builder.name(label.clone());
+    }
+    let symbol_id = symbol_builder
+        .rotation(rotation)
+        .bit_width(bit_width)
+        .build(commands, circuit_id);
+
+    let ports = symbol_builder.ports();
+    if ports.len() != element.pins.len() {
+        bail!(
+            "Logisim component {} has {} pins instead of {}",
+            comp.name,
+            element.pins.len(),
+            ports.len()
+        );
+    }
+
+    // the port at the location of the component goes where the component is
+    let anchor = element
+        .pins
+        .iter()
+        .zip(ports)
+        .find(|(pin, _)| {
+            pin.as_ref()
+                .is_some_and(|pin| pin.offset == Location::default())
+        })
+        .map(|(_, port)| port.position.rotate(rotation))
+        .unwrap_or_default();
+    commands.entity(symbol_id).insert(Transform {
+        translation: position(comp.loc)? - anchor,
+        rotation,
+        ..Default::default()
+    });
+
+    for (pin, port) in element.pins.iter().zip(ports) {
+        let Some(pin) = pin else {
+            continue;
+        };
+        let offset = face(pin.offset, facing);
+        let location = Location {
+            x: comp.loc.x + offset.x,
+            y: comp.loc.y + offset.y,
+        };
+        let bit_width = if pin.data {
+            bit_width
+        } else {
+            BitWidth(NonZeroU8::MIN)
+        };
+        wire_map.add_port(position(location)?, port.id, bit_width);
+
+        if let (Some(label), SymbolKind::In | SymbolKind::Out | SymbolKind::Tunnel) =
+            (&label, element.kind)
+        {
+            labels.insert(port.id, label.clone());
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_pin, Outline};
+    use crate::logisim::circuitfile::{Location, Project};
+
+    fn port_offsets(outline: &Outline) -> Vec<(String, Location)> {
+        outline
+            .ports
+            .iter()
+            .map(|(pin, offset)| (pin.attributes["label"].clone(), *offset))
+            .collect()
+    }
+
+    #[test]
+    fn classic_outline() {
+        let project = Project::load("testdata/half_adder.circ").unwrap();
+        let outline = Outline::of(&project, project.main_circuit().unwrap());
+        assert_eq!(
+            port_offsets(&outline),
+            [
+                ("A".into(), Location { x: -30, y: 0 }),
+                ("B".into(), Location { x: -30, y: 10 }),
+                ("S".into(), Location { x: 0, y: 0 }),
+                ("C".into(), Location { x: 0, y: 10 }),
+            ]
+        );
+        assert_eq!(outline.min, Location { x: -30, y: -10 });
+        assert_eq!(outline.max, Location { x: 0, y: 20 });
+    }
+
+    #[test]
+    fn custom_and_evolution_outlines() {
+        let project = Project::load("testdata/full_adder.circ").unwrap();
+        let circuit = project.circuit("half_adder").unwrap();
+
+        let outline = Outline::of(&project, circuit);
+        assert_eq!(
+            port_offsets(&outline),
+            [
+                ("A".into(), Location { x: -40, y: 0 }),
+                ("B".into(), Location { x: -40, y: 20 }),
+                ("S".into(), Location { x: 0, y: 0 }),
+                ("C".into(), Location { x: 0, y: 20 }),
+            ]
+        );
+        assert_eq!(outline.min, Location { x: -40, y: -10 });
+        assert_eq!(outline.max, Location { x: 0, y: 30 });
+
+        // the circuit has a fixed size box when it is drawn the default way
+        let pins = circuit
+            .comps
+            .iter()
+            .filter(|comp| is_pin(&project, comp))
+            .collect();
+        let outline = Outline::evolution(circuit, pins);
+        assert_eq!(
+            port_offsets(&outline),
+            [
+                ("A".into(), Location { x: -220, y: 0 }),
+                ("B".into(), Location { x: -220, y: 20 }),
+                ("S".into(), Location { x: 0, y: 0 }),
+                ("C".into(), Location { x: 0, y: 20 }),
+            ]
+        );
+    }
+}
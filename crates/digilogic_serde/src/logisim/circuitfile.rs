@@ -0,0 +1,442 @@
+use anyhow::{anyhow, Result};
+use digilogic_core::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::ops::{Add, Sub};
+use std::path::Path;
+use xml::attribute::OwnedAttribute;
+use xml::reader::{EventReader, XmlEvent};
+
+/// The `<a name=".." val=".."/>` attributes of a circuit or component.
+/// Only attributes that differ from their defaults are stored.
+pub type Attributes = HashMap<String, String>;
+
+/// A point on Logisim's grid, written as `(x,y)`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Location {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Location {
+    fn parse(text: &str) -> Result<Self> {
+        text.trim()
+            .strip_prefix('(')
+            .and_then(|text| text.strip_suffix(')'))
+            .ok_or_else(|| anyhow!("invalid location `{text}`"))
+            .and_then(Self::parse_coordinates)
+    }
+
+    /// Parses a location without parentheses, like `x,y`.
+    fn parse_coordinates(text: &str) -> Result<Self> {
+        let invalid = || anyhow!("invalid location `{text}`");
+        let (x, y) = text.split_once(',').ok_or_else(invalid)?;
+        Ok(Self {
+            x: x.trim().parse().map_err(|_| invalid())?,
+            y: y.trim().parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+impl Add for Location {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self {
+            x: self.x + rhs.x,
+            y: self.y + rhs.y,
+        }
+    }
+}
+
+impl Sub for Location {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self {
+            x: self.x - rhs.x,
+            y: self.y - rhs.y,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Lib {
+    /// What components refer to the library by, usually its index.
+    pub name: String,
+    /// Which library it is, like `#Gates` for a built-in one.
+    pub desc: String,
+}
+
+#[derive(Debug)]
+pub struct Wire {
+    pub from: Location,
+    pub to: Location,
+}
+
+#[derive(Debug)]
+pub struct Comp {
+    /// The name of the library the component is from,
+    /// `None` for an instance of another circuit of the project.
+    pub lib: Option<String>,
+    pub name: String,
+    pub loc: Location,
+    pub attributes: Attributes,
+}
+
+/// The point of a custom appearance that goes where an instance of the circuit is placed.
+#[derive(Debug)]
+pub struct Anchor {
+    pub loc: Location,
+    /// The direction instances face when they aren't rotated.
+    pub facing: Option<String>,
+}
+
+/// A port drawn on a custom appearance.
+#[derive(Debug)]
+pub struct AppearancePort {
+    /// The location of the pin inside the circuit the port belongs to.
+    pub pin: Location,
+    pub loc: Location,
+}
+
+#[derive(Debug)]
+pub struct Rect {
+    pub loc: Location,
+    pub width: i32,
+    pub height: i32,
+}
+
+/// The custom appearance of a circuit when it is placed in another one.
+/// Only the parts that place its ports are stored, text and other shapes are skipped.
+#[derive(Debug, Default)]
+pub struct Appearance {
+    pub anchor: Option<Anchor>,
+    pub ports: Vec<AppearancePort>,
+    pub rects: Vec<Rect>,
+}
+
+#[derive(Debug)]
+pub struct Circuit {
+    pub name: String,
+    pub attributes: Attributes,
+    pub wires: Vec<Wire>,
+    pub comps: Vec<Comp>,
+    pub appearance: Option<Appearance>,
+}
+
+#[derive(Debug)]
+pub struct Project {
+    /// The version of Logisim that wrote the file.
+    pub source: String,
+    pub libs: Vec<Lib>,
+    /// The name of the circuit that is simulated when the project is opened.
+    pub main: Option<String>,
+    pub circuits: Vec<Circuit>,
+}
+
+fn attribute<'a>(attributes: &'a [OwnedAttribute], name: &str) -> Option<&'a str> {
+    attributes
+        .iter()
+        .find(|attribute| attribute.name.local_name == name)
+        .map(|attribute| attribute.value.as_str())
+}
+
+fn location(attributes: &[OwnedAttribute], name: &str) -> Result<Location> {
+    let text = attribute(attributes, name).ok_or_else(|| anyhow!("missing `{name}`"))?;
+    Location::parse(text)
+}
+
+fn integer(attributes: &[OwnedAttribute], name: &str) -> Result<i32> {
+    let text = attribute(attributes, name).ok_or_else(|| anyhow!("missing `{name}`"))?;
+    text.trim()
+        .parse()
+        .map_err(|_| anyhow!("invalid `{name}` `{text}`"))
+}
+
+/// The top left corner of a shape of an appearance.
+fn corner(attributes: &[OwnedAttribute]) -> Result<Location> {
+    Ok(Location {
+        x: integer(attributes, "x")?,
+        y: integer(attributes, "y")?,
+    })
+}
+
+/// The center of a marker of an appearance, which is stored as a shape around it.
+fn center(attributes: &[OwnedAttribute]) -> Result<Location> {
+    let corner = corner(attributes)?;
+    Ok(Location {
+        x: corner.x + integer(attributes, "width").unwrap_or_default() / 2,
+        y: corner.y + integer(attributes, "height").unwrap_or_default() / 2,
+    })
+}
+
+impl Project {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = File::open(path)?;
+        Self::read(BufReader::new(file))
+    }
+
+    pub fn read<R: Read>(reader: R) -> Result<Self> {
+        let mut project = Self {
+            source: String::new(),
+            libs: Vec::new(),
+            main: None,
+            circuits: Vec::new(),
+        };
+
+        let mut circuit: Option<Circuit> = None;
+        let mut comp: Option<Comp> = None;
+        // the name of the attribute whose value is the text content of its element
+        let mut text_attribute: Option<String> = None;
+        // elements like `<options>` and `<toolbar>` have attributes that are not read
+        let mut ignored_depth = 0;
+
+        for event in EventReader::new(reader) {
+            match event? {
+                XmlEvent::StartElement {
+                    name, attributes, ..
+                } => {
+                    if ignored_depth > 0 {
+                        ignored_depth += 1;
+                        continue;
+                    }
+
+                    match (name.local_name.as_str(), circuit.as_mut()) {
+                        ("project", None) => {
+                            project.source = attribute(&attributes, "source")
+                                .unwrap_or_default()
+                                .to_owned();
+                        }
+                        ("lib", None) => {
+                            project.libs.push(Lib {
+                                name: attribute(&attributes, "name")
+                                    .unwrap_or_default()
+                                    .to_owned(),
+                                desc: attribute(&attributes, "desc")
+                                    .unwrap_or_default()
+                                    .to_owned(),
+                            });
+                            ignored_depth = 1;
+                        }
+                        ("main", None) => {
+                            project.main = attribute(&attributes, "name").map(str::to_owned);
+                        }
+                        ("circuit", None) => {
+                            circuit = Some(Circuit {
+                                name: attribute(&attributes, "name")
+                                    .unwrap_or_default()
+                                    .to_owned(),
+                                attributes: Attributes::default(),
+                                wires: Vec::new(),
+                                comps: Vec::new(),
+                                appearance: None,
+                            });
+                        }
+                        ("appear", Some(circuit)) => {
+                            circuit.appearance = Some(Appearance::default());
+                        }
+                        (
+                            "circ-anchor",
+                            Some(Circuit {
+                                appearance: Some(appearance),
+                                ..
+                            }),
+                        ) => {
+                            appearance.anchor = Some(Anchor {
+                                loc: center(&attributes)?,
+                                facing: attribute(&attributes, "facing").map(str::to_owned),
+                            });
+                            ignored_depth = 1;
+                        }
+                        (
+                            "circ-port",
+                            Some(Circuit {
+                                appearance: Some(appearance),
+                                ..
+                            }),
+                        ) => {
+                            appearance.ports.push(AppearancePort {
+                                pin: Location::parse_coordinates(
+                                    attribute(&attributes, "pin").unwrap_or_default(),
+                                )?,
+                                loc: center(&attributes)?,
+                            });
+                            ignored_depth = 1;
+                        }
+                        (
+                            "rect",
+                            Some(Circuit {
+                                appearance: Some(appearance),
+                                ..
+                            }),
+                        ) => {
+                            appearance.rects.push(Rect {
+                                loc: corner(&attributes)?,
+                                width: integer(&attributes, "width")?,
+                                height: integer(&attributes, "height")?,
+                            });
+                            ignored_depth = 1;
+                        }
+                        ("wire", Some(circuit)) => {
+                            circuit.wires.push(Wire {
+                                from: location(&attributes, "from")?,
+                                to: location(&attributes, "to")?,
+                            });
+                        }
+                        ("comp", Some(_)) => {
+                            comp = Some(Comp {
+                                lib: attribute(&attributes, "lib").map(str::to_owned),
+                                name: attribute(&attributes, "name")
+                                    .unwrap_or_default()
+                                    .to_owned(),
+                                loc: location(&attributes, "loc")?,
+                                attributes: Attributes::default(),
+                            });
+                        }
+                        ("a", Some(circuit)) => {
+                            let Some(name) = attribute(&attributes, "name") else {
+                                continue;
+                            };
+                            let attributes_of = match comp.as_mut() {
+                                Some(comp) => &mut comp.attributes,
+                                None => &mut circuit.attributes,
+                            };
+                            match attribute(&attributes, "val") {
+                                Some(value) => {
+                                    attributes_of.insert(name.to_owned(), value.to_owned());
+                                }
+                                None => {
+                                    attributes_of.insert(name.to_owned(), String::new());
+                                    text_attribute = Some(name.to_owned());
+                                }
+                            }
+                        }
+                        _ => ignored_depth = 1,
+                    }
+                }
+                XmlEvent::EndElement { name } => {
+                    if ignored_depth > 0 {
+                        ignored_depth -= 1;
+                        continue;
+                    }
+
+                    match name.local_name.as_str() {
+                        "a" => text_attribute = None,
+                        "comp" => {
+                            if let (Some(circuit), Some(comp)) = (circuit.as_mut(), comp.take()) {
+                                circuit.comps.push(comp);
+                            }
+                        }
+                        "circuit" => project.circuits.extend(circuit.take()),
+                        _ => (),
+                    }
+                }
+                XmlEvent::Characters(text) => {
+                    let Some(name) = text_attribute.as_ref() else {
+                        continue;
+                    };
+                    let attributes_of = match (comp.as_mut(), circuit.as_mut()) {
+                        (Some(comp), _) => &mut comp.attributes,
+                        (None, Some(circuit)) => &mut circuit.attributes,
+                        (None, None) => continue,
+                    };
+                    if let Some(value) = attributes_of.get_mut(name) {
+                        value.push_str(&text);
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        Ok(project)
+    }
+
+    /// The circuit that is simulated when the project is opened,
+    /// or the first one if none is set.
+    pub fn main_circuit(&self) -> Option<&Circuit> {
+        self.main
+            .as_ref()
+            .and_then(|main| self.circuits.iter().find(|circuit| &circuit.name == main))
+            .or_else(|| self.circuits.first())
+    }
+
+    pub fn circuit(&self, name: &str) -> Option<&Circuit> {
+        self.circuits.iter().find(|circuit| circuit.name == name)
+    }
+
+    /// Whether the file was written by Logisim Evolution, whose versions continue
+    /// after the last version of the original Logisim, 2.7.1.
+    pub fn is_evolution(&self) -> bool {
+        let mut numbers = self
+            .source
+            .split('.')
+            .map(|number| number.trim().parse::<u32>().unwrap_or_default());
+        let major = numbers.next().unwrap_or_default();
+        let minor = numbers.next().unwrap_or_default();
+        (major, minor) > (2, 7)
+    }
+
+    /// The description of the library a component is from, like `#Gates`.
+    pub fn lib_desc(&self, comp: &Comp) -> Option<&str> {
+        let lib = comp.lib.as_ref()?;
+        self.libs
+            .iter()
+            .find(|candidate| &candidate.name == lib)
+            .map(|lib| lib.desc.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Location, Project};
+
+    #[test]
+    fn reads_half_adder() {
+        let project = Project::load("testdata/half_adder.circ").unwrap();
+        assert_eq!(project.source, "2.7.1");
+        assert!(!project.is_evolution());
+        assert_eq!(project.libs.len(), 7);
+
+        let circuit = project.main_circuit().unwrap();
+        assert_eq!(circuit.name, "main");
+        assert_eq!(circuit.wires.len(), 10);
+        assert_eq!(circuit.comps.len(), 6);
+
+        let xor = &circuit.comps[2];
+        assert_eq!(project.lib_desc(xor), Some("#Gates"));
+        assert_eq!(xor.name, "XOR Gate");
+        assert_eq!(xor.loc, Location { x: 250, y: 110 });
+        assert_eq!(xor.attributes.get("inputs").unwrap(), "2");
+    }
+
+    #[test]
+    fn reads_custom_appearance() {
+        let project = Project::load("testdata/full_adder.circ").unwrap();
+        assert!(project.is_evolution());
+        assert_eq!(project.main_circuit().unwrap().name, "full_adder");
+
+        let circuit = project.circuit("half_adder").unwrap();
+        let appearance = circuit.appearance.as_ref().unwrap();
+        let anchor = appearance.anchor.as_ref().unwrap();
+        assert_eq!(anchor.loc, Location { x: 90, y: 60 });
+        assert_eq!(anchor.facing.as_deref(), Some("east"));
+        assert_eq!(appearance.rects.len(), 1);
+        assert_eq!(appearance.ports.len(), 4);
+        assert_eq!(appearance.ports[1].pin, Location { x: 100, y: 230 });
+        assert_eq!(appearance.ports[1].loc, Location { x: 50, y: 80 });
+    }
+
+    #[test]
+    fn parses_locations() {
+        assert_eq!(
+            Location::parse("(120, -30)").unwrap(),
+            Location { x: 120, y: -30 }
+        );
+        assert!(Location::parse("120,30").is_err());
+        assert_eq!(
+            Location::parse_coordinates("120,30").unwrap(),
+            Location { x: 120, y: 30 }
+        );
+    }
+}
@@ -0,0 +1,122 @@
+//! Connectivity of formats that store wires as line segments,
+//! which connect to each other and to ports where their ends meet.
+
+use aery::prelude::*;
+use bevy_ecs::prelude::*;
+use digilogic_core::bundles::*;
+use digilogic_core::components::*;
+use digilogic_core::connections::connect_endpoint;
+use digilogic_core::transform::*;
+use digilogic_core::visibility::VisibilityBundle;
+use digilogic_core::{fixed, HashMap, HashSet};
+use std::num::NonZeroU8;
+
+/// The ports and wire ends at a position.
+#[derive(Default)]
+struct PosEntry {
+    ports: Vec<(Entity, BitWidth)>,
+    wires: Vec<[Vec2; 2]>,
+}
+
+/// The ports of a circuit by their positions, connected by wires into nets.
+#[derive(Default)]
+pub(crate) struct WireMap {
+    positions: HashMap<Vec2, PosEntry>,
+}
+
+impl WireMap {
+    /// Adds a port, which connects to wires ending at `position`
+    /// and to other ports at the same position.
+    pub(crate) fn add_port(&mut self, position: Vec2, port: Entity, bit_width: BitWidth) {
+        self.positions
+            .entry(position)
+            .or_default()
+            .ports
+            .push((port, bit_width));
+    }
+
+    pub(crate) fn add_wire(&mut self, ends: [Vec2; 2]) {
+        for end in ends {
+            self.positions.entry(end).or_default().wires.push(ends);
+        }
+    }
+
+    /// Spawns a net for every group of connected ports, as wide as its widest port.
+    /// Ports that connect to nothing else don't get a net.
+    /// Returns the nets with their ports.
+    pub(crate) fn translate(
+        &self,
+        commands: &mut Commands,
+        circuit_id: Entity,
+    ) -> Vec<(Entity, Vec<Entity>)> {
+        let mut visited = HashSet::<Vec2>::default();
+        let mut todo = Vec::<Vec2>::default();
+        let mut nets = Vec::new();
+
+        // do a "flood fill" to find all connected ports and assign them to nets
+        for pos in self.positions.keys() {
+            if visited.contains(pos) {
+                continue;
+            }
+
+            let mut ports = Vec::new();
+            todo.clear();
+            todo.push(*pos);
+            while let Some(pos) = todo.pop() {
+                if !visited.insert(pos) {
+                    continue;
+                }
+
+                if let Some(pos_entry) = self.positions.get(&pos) {
+                    ports.extend(pos_entry.ports.iter().copied());
+
+                    for wire in pos_entry.wires.iter() {
+                        for end in wire.iter() {
+                            if !visited.contains(end) {
+                                todo.push(*end);
+                            }
+                        }
+                    }
+                }
+            }
+
+            if ports.len() < 2 {
+                continue;
+            }
+
+            let bit_width = ports
+                .iter()
+                .map(|&(_, bit_width)| bit_width)
+                .max()
+                .unwrap_or(BitWidth(NonZeroU8::MIN));
+            let net_id = commands
+                .spawn(NetBundle {
+                    net: Net,
+                    name: Default::default(),
+                    bit_width,
+                    visibility: VisibilityBundle::default(),
+                })
+                .set::<Child>(circuit_id)
+                .id();
+
+            for &(port, _) in &ports {
+                // Connect port to net
+                let endpoint_id = commands
+                    .spawn(EndpointBundle {
+                        bounds: BoundingBoxBundle {
+                            bounding_box: BoundingBox::from_half_size(fixed!(2.5), fixed!(2.5)),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    })
+                    .set::<Child>(net_id)
+                    .id();
+                connect_endpoint(commands, endpoint_id, port, net_id);
+            }
+
+            nets.push((net_id, ports.into_iter().map(|(port, _)| port).collect()));
+        }
+
+        nets
+    }
+}
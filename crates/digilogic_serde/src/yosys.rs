@@ -6,11 +6,11 @@ use bevy_ecs::prelude::*;
 use bevy_log::info;
 use digilogic_core::bundles::*;
 use digilogic_core::components::*;
+use digilogic_core::connections::spawn_port_endpoint;
 use digilogic_core::symbol::PortInfo;
 use digilogic_core::symbol::SymbolRegistry;
 use digilogic_core::transform::BoundingBox;
 use digilogic_core::transform::Directions;
-use digilogic_core::transform::InheritTransform;
 use digilogic_core::transform::Transform;
 use digilogic_core::transform::TransformBundle;
 use digilogic_core::transform::Vec2;
@@ -109,7 +109,7 @@ fn translate_netlist(
         }
     }
 
-    Ok(top_id.unwrap())
+    top_id.ok_or_else(|| anyhow::anyhow!("circuit contains no modules"))
 }
 
 fn translate_module_port(
@@ -214,6 +214,16 @@ fn translate_cell(
         .name(name.clone())
         .build(commands, circuit_id);
 
+    // keep the cell parameters, like the width of its ports, for the user to see
+    if !cell.parameters.is_empty() {
+        let parameters = cell
+            .parameters
+            .iter()
+            .map(|(key, value)| (key.as_str().into(), value.as_str().into()))
+            .collect();
+        commands.entity(symbol_id).insert(Attributes(parameters));
+    }
+
     graph
         .bounding_boxes
         .insert(symbol_id, symbol_builder.bounding_box());
@@ -306,16 +316,7 @@ fn translate_net(
     let mut others: Vec<PortInfo> = Vec::new();
 
     for port in port_infos.iter() {
-        commands
-            .spawn(EndpointBundle {
-                endpoint: Endpoint,
-                ..Default::default()
-            })
-            .insert(PortID(port.id))
-            .set::<Child>(net_id)
-            .set::<InheritTransform>(port.id);
-
-        commands.entity(port.id).insert(NetID(net_id));
+        spawn_port_endpoint(commands, net_id, port.id);
 
         if !graph
             .entity_ids
@@ -426,27 +427,19 @@ fn translate_net(
 fn layout_circuit(
     commands: &mut Commands,
     graph: &mut MetaGraph,
-    bit_map: &HashMap<usize, NetBit>,
+    _bit_map: &HashMap<usize, NetBit>,
 ) -> Result<()> {
     // add adjacency constraints
     let node_indices = graph.graph.node_indices().collect::<Vec<_>>();
     for index in node_indices.iter() {
         let node = graph.graph.node_weight_mut(*index).unwrap();
-        node.input_ports.sort_by(|a, b| a.index.cmp(&b.index));
-        node.output_ports.sort_by(|a, b| a.index.cmp(&b.index));
-        node.other_ports.sort_by(|a, b| a.index.cmp(&b.index));
+        node.input_ports.sort_by_key(|a| a.index);
+        node.output_ports.sort_by_key(|a| a.index);
+        node.other_ports.sort_by_key(|a| a.index);
     }
 
     digilogic_layout::layout_graph(&mut graph.graph).map_err(anyhow::Error::msg)?;
 
-    std::fs::write(
-        "graph.dot",
-        format!(
-            "{:?}",
-            petgraph::dot::Dot::with_config(&graph.graph, &[petgraph::dot::Config::EdgeNoLabel])
-        ),
-    )?;
-
     let mut max_x = 0.0;
     let mut max_y = 0.0;
 
@@ -10,7 +10,7 @@ use serde::Deserialize;
 use std::{collections::BTreeMap, path::Path, sync::Arc};
 
 /// The known Yosys cell types
-#[allow(missing_docs)]
+#[allow(missing_docs, dead_code)]
 #[derive(Debug, Clone)]
 pub enum CellType {
     Not,
@@ -158,6 +158,7 @@ pub struct Port {
     pub bits: Bits,
 }
 
+#[allow(dead_code)]
 #[derive(Deserialize)]
 pub struct Cell {
     #[serde(default)]
@@ -170,6 +171,7 @@ pub struct Cell {
     pub connections: BTreeMap<String, Bits>,
 }
 
+#[allow(dead_code)]
 #[derive(Deserialize)]
 pub struct NetNameOpts {
     #[serde(default)]
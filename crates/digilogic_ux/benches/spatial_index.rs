@@ -0,0 +1,120 @@
+//! Compares the spatial index backends on a generated circuit the size of a large imported netlist.
+//! Run with `cargo bench -p digilogic_ux --bench spatial_index`.
+
+// benchmarks are built against all dependencies of the crate
+#![allow(unused_crate_dependencies)]
+
+use bvh_arena::Bvh;
+use digilogic_core::transform::{BoundingBox, Vec2};
+use digilogic_core::Fixed;
+use digilogic_ux::{GridIndex, SpatialIndexBackend};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const SYMBOLS_PER_ROW: i32 = 400;
+const ROWS: i32 = 250;
+const QUERIES: usize = 100_000;
+
+fn fixed(value: i32) -> Fixed {
+    Fixed::try_from_i32(value).unwrap()
+}
+
+/// Symbols laid out in rows, each with a few ports and a wire to the next symbol.
+fn circuit() -> Vec<BoundingBox> {
+    let mut boxes = Vec::new();
+    for row in 0..ROWS {
+        for column in 0..SYMBOLS_PER_ROW {
+            let center = Vec2 {
+                x: fixed(column * 120),
+                y: fixed(row * 100),
+            };
+            boxes.push(BoundingBox::from_center_half_size(
+                center,
+                fixed(30),
+                fixed(20),
+            ));
+
+            for port in -1..=1 {
+                let port = Vec2 {
+                    x: fixed(column * 120 + 30),
+                    y: fixed(row * 100 + port * 10),
+                };
+                boxes.push(BoundingBox::from_center_half_size(port, fixed(2), fixed(2)));
+            }
+
+            let wire = Vec2 {
+                x: fixed(column * 120 + 60),
+                y: fixed(row * 100),
+            };
+            boxes.push(BoundingBox::from_center_half_size(
+                wire,
+                fixed(30),
+                fixed(4),
+            ));
+        }
+    }
+    boxes
+}
+
+/// Cursor sized boxes spread over the circuit, like hovering and snapping query it.
+fn queries() -> Vec<BoundingBox> {
+    let mut state = 1u32;
+    let mut next = |range: i32| {
+        state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+        ((state >> 8) % (range as u32)) as i32
+    };
+
+    (0..QUERIES)
+        .map(|_| {
+            let center = Vec2 {
+                x: fixed(next(SYMBOLS_PER_ROW * 120)),
+                y: fixed(next(ROWS * 100)),
+            };
+            BoundingBox::from_center_half_size(center, fixed(10), fixed(10))
+        })
+        .collect()
+}
+
+fn bench<B: SpatialIndexBackend<u32>>(name: &str, boxes: &[BoundingBox], queries: &[BoundingBox]) {
+    let start = Instant::now();
+    let mut index = B::default();
+    let handles: Vec<_> = boxes
+        .iter()
+        .enumerate()
+        .map(|(item, &bounds)| index.insert(item as u32, bounds))
+        .collect();
+    let insert = start.elapsed();
+
+    let start = Instant::now();
+    let mut hits = 0usize;
+    for query in queries {
+        index.for_each_overlaps(query, |_| hits += 1);
+    }
+    black_box(hits);
+    let query = start.elapsed();
+
+    let start = Instant::now();
+    for &handle in handles.iter() {
+        index.remove(handle);
+    }
+    let remove = start.elapsed();
+
+    let per_item = |duration: Duration, count: usize| duration / (count as u32);
+    println!(
+        "{name:>4}: insert {:>9.2?} ({:>7.2?}/item), query {:>9.2?} ({:>7.2?}/query), remove {:>9.2?}",
+        insert,
+        per_item(insert, boxes.len()),
+        query,
+        per_item(query, queries.len()),
+        remove,
+    );
+}
+
+fn main() {
+    let boxes = circuit();
+    let queries = queries();
+    println!("{} boxes, {} queries", boxes.len(), queries.len());
+
+    bench::<Bvh<u32, BoundingBox>>("bvh", &boxes, &queries);
+    bench::<GridIndex<u32>>("grid", &boxes, &queries);
+}
@@ -0,0 +1,165 @@
+use crate::{ActiveTool, ClickEvent, DoubleClickEvent, GridSize, HoveredEntity, PointerButton};
+use aery::prelude::*;
+use bevy_ecs::prelude::*;
+use digilogic_core::bundles::AnnotationBundle;
+use digilogic_core::components::*;
+use digilogic_core::transform::*;
+use digilogic_core::{fixed, Fixed};
+
+/// Requests the editor of an annotation to be opened in `viewport`.
+/// Sent for annotations placed with [`ActiveTool::AddText`] and for double-clicked ones.
+#[derive(Event, Debug)]
+pub struct EditAnnotation {
+    pub viewport: Entity,
+    pub annotation: Entity,
+}
+
+/// Replaces the text and appearance of an annotation.
+/// Annotations whose text is empty afterwards are removed.
+#[derive(Event, Debug)]
+pub struct SetAnnotation {
+    pub annotation: Entity,
+    pub value: Annotation,
+}
+
+/// The average width of a character relative to the font size, used to estimate
+/// the bounds of an annotation without laying out its text.
+const CHAR_WIDTH: f32 = 0.6;
+const LINE_HEIGHT: f32 = 1.2;
+
+/// The approximate area covered by the text of an annotation, relative to its position.
+fn annotation_bounds(annotation: &Annotation) -> BoundingBox {
+    let font_size = annotation.font_size.to_f32();
+    let lines = annotation.text.lines().count().max(1);
+    let columns = annotation
+        .text
+        .lines()
+        .map(|line| line.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max(1);
+
+    let width =
+        Fixed::try_from_f32((columns as f32) * font_size * CHAR_WIDTH).unwrap_or(Fixed::MAX);
+    let height =
+        Fixed::try_from_f32((lines as f32) * font_size * LINE_HEIGHT).unwrap_or(Fixed::MAX);
+    BoundingBox::from_top_left_size(Vec2::ZERO, width.max(fixed!(0)), height.max(fixed!(0)))
+}
+
+/// Clicking with the text tool places an empty annotation and opens its editor.
+pub(crate) fn place_annotation_on_click(
+    trigger: Trigger<ClickEvent>,
+    mut commands: Commands,
+    mut active_tool: ResMut<ActiveTool>,
+    grid_size: Res<GridSize>,
+    mut edit_events: EventWriter<EditAnnotation>,
+) {
+    let event = trigger.event();
+
+    if *active_tool != ActiveTool::AddText {
+        return;
+    }
+
+    if event.button == PointerButton::Secondary {
+        *active_tool = ActiveTool::Select;
+        return;
+    }
+
+    let mut position = event.pos;
+    if grid_size.0 > fixed!(0) {
+        position = position.round_to_multiple(grid_size.0);
+    }
+
+    let annotation = commands
+        .spawn(AnnotationBundle {
+            transform: TransformBundle {
+                transform: Transform {
+                    translation: position,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .set::<Child>(event.circuit.0)
+        .id();
+
+    edit_events.send(EditAnnotation {
+        viewport: trigger.entity(),
+        annotation,
+    });
+}
+
+/// Double-clicking an annotation opens its editor.
+pub(crate) fn edit_annotation_on_double_click(
+    trigger: Trigger<DoubleClickEvent>,
+    hover_query: Query<&HoveredEntity>,
+    active_tool: Res<ActiveTool>,
+    annotations: Query<(), With<Annotation>>,
+    mut edit_events: EventWriter<EditAnnotation>,
+) {
+    let event = trigger.event();
+    let viewport = trigger.entity();
+
+    if (*active_tool != ActiveTool::Select) || (event.button != PointerButton::Primary) {
+        return;
+    }
+
+    let Some(annotation) = hover_query.get(viewport).ok().and_then(|hovered| hovered.0) else {
+        return;
+    };
+    if annotations.contains(annotation) {
+        edit_events.send(EditAnnotation {
+            viewport,
+            annotation,
+        });
+    }
+}
+
+pub(crate) fn set_annotations(
+    mut commands: Commands,
+    mut events: EventReader<SetAnnotation>,
+    mut annotations: Query<&mut Annotation>,
+) {
+    for event in events.read() {
+        let Ok(mut annotation) = annotations.get_mut(event.annotation) else {
+            continue;
+        };
+
+        if event.value.text.trim().is_empty() {
+            commands.entity(event.annotation).despawn();
+        } else if *annotation != event.value {
+            *annotation = event.value.clone();
+        }
+    }
+}
+
+/// Keeps the bounds of annotations, used for hovering and selecting them, in sync with their text.
+pub(crate) fn update_annotation_bounds(
+    mut annotations: Query<(&Annotation, &mut BoundingBox), Changed<Annotation>>,
+) {
+    for (annotation, mut bounds) in annotations.iter_mut() {
+        *bounds = annotation_bounds(annotation);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounds_grow_with_text() {
+        let single = annotation_bounds(&Annotation {
+            text: "abcd".into(),
+            ..Default::default()
+        });
+        let multi = annotation_bounds(&Annotation {
+            text: "abcdefgh\nab".into(),
+            ..Default::default()
+        });
+
+        assert_eq!(single.min(), Vec2::ZERO);
+        assert!(multi.max().x > single.max().x);
+        assert!(multi.max().y > single.max().y);
+    }
+}
@@ -0,0 +1,379 @@
+use crate::systems::NudgeBurst;
+use crate::SelectionMoved;
+use aery::prelude::*;
+use bevy_ecs::prelude::*;
+use digilogic_core::components::*;
+use digilogic_core::events::CircuitLoadedEvent;
+use digilogic_core::stable_id::StableId;
+use digilogic_core::transform::Transform;
+use digilogic_core::HashMap;
+
+/// What happened to an entity in an [`Edit`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditKind {
+    Spawned,
+    Despawned,
+    /// The component with this name was changed.
+    Changed(&'static str),
+}
+
+/// A single structural edit of a circuit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Edit {
+    /// Increases with every edit, across all circuits.
+    pub sequence: u64,
+    pub entity: Entity,
+    /// The ID the entity is known by across sessions, if it has one.
+    pub stable_id: Option<StableId>,
+    pub kind: EditKind,
+}
+
+/// Every edit made to a circuit since it was loaded or created, oldest first.
+#[derive(Debug, Default)]
+pub struct CircuitChangelog {
+    edits: Vec<Edit>,
+}
+
+impl CircuitChangelog {
+    #[inline]
+    pub fn edits(&self) -> &[Edit] {
+        &self.edits
+    }
+
+    /// The edits with a sequence number of at least `sequence`.
+    pub fn since(&self, sequence: u64) -> &[Edit] {
+        let start = self.edits.partition_point(|edit| edit.sequence < sequence);
+        &self.edits[start..]
+    }
+}
+
+/// The edits made to each open circuit, recorded once per frame.
+/// Entries are only ever appended; loading a circuit starts its changelog afresh.
+#[derive(Debug, Default, Resource)]
+pub struct Changelog {
+    circuits: HashMap<Entity, CircuitChangelog>,
+    next_sequence: u64,
+}
+
+impl Changelog {
+    pub fn circuit(&self, circuit: Entity) -> Option<&CircuitChangelog> {
+        self.circuits.get(&circuit)
+    }
+
+    /// The sequence number the next edit will get.
+    #[inline]
+    pub fn next_sequence(&self) -> u64 {
+        self.next_sequence
+    }
+
+    fn record(
+        &mut self,
+        circuit: Entity,
+        entity: Entity,
+        stable_id: Option<StableId>,
+        kind: EditKind,
+    ) {
+        // entities despawned along with their circuit are not recorded
+        let Some(changelog) = self.circuits.get_mut(&circuit) else {
+            return;
+        };
+
+        changelog.edits.push(Edit {
+            sequence: self.next_sequence,
+            entity,
+            stable_id,
+            kind,
+        });
+        self.next_sequence += 1;
+    }
+}
+
+/// The circuit an entity was last recorded in.
+/// The relations of an entity are already gone once its despawn is observed,
+/// so it has to remember which circuit the despawn is recorded in.
+#[derive(Debug, Clone, Copy, Component)]
+pub(crate) struct LoggedIn(Entity);
+
+type Edited = Or<(
+    With<Circuit>,
+    With<Symbol>,
+    With<Net>,
+    With<Endpoint>,
+    With<Waypoint>,
+    With<Annotation>,
+    With<Graphic>,
+)>;
+
+type Spawned = Or<(
+    Added<Symbol>,
+    Added<Net>,
+    Added<Endpoint>,
+    Added<Waypoint>,
+    Added<Annotation>,
+    Added<Graphic>,
+)>;
+
+/// The circuit `entity` belongs to, or itself if it is a circuit.
+fn owning_circuit(
+    entity: Entity,
+    circuits: &Query<(), With<Circuit>>,
+    children: &Query<(Entity, Relations<Child>)>,
+) -> Option<Entity> {
+    if circuits.contains(entity) {
+        return Some(entity);
+    }
+
+    let mut owner = None;
+    children
+        .traverse::<Up<Child>>([entity])
+        .for_each(|&mut parent, _| {
+            if owner.is_none() && circuits.contains(parent) {
+                owner = Some(parent);
+            }
+        });
+    owner
+}
+
+/// Records `entity` in the changelog of its circuit, remembering the circuit for its despawn.
+#[allow(clippy::too_many_arguments)]
+fn record_edit(
+    commands: &mut Commands,
+    changelog: &mut Changelog,
+    circuits: &Query<(), With<Circuit>>,
+    children: &Query<(Entity, Relations<Child>)>,
+    entity: Entity,
+    logged_in: Option<&LoggedIn>,
+    stable_id: Option<&StableId>,
+    kind: EditKind,
+) {
+    let Some(circuit) = owning_circuit(entity, circuits, children) else {
+        return;
+    };
+
+    changelog.record(circuit, entity, stable_id.copied(), kind);
+    if logged_in.map(|logged_in| logged_in.0) != Some(circuit) {
+        commands.entity(entity).insert(LoggedIn(circuit));
+    }
+}
+
+pub(crate) fn on_add_circuit_changelog(
+    trigger: Trigger<OnAdd, Circuit>,
+    mut changelog: ResMut<Changelog>,
+) {
+    changelog
+        .circuits
+        .insert(trigger.entity(), CircuitChangelog::default());
+}
+
+pub(crate) fn on_remove_circuit_changelog(
+    trigger: Trigger<OnRemove, Circuit>,
+    mut changelog: ResMut<Changelog>,
+) {
+    changelog.circuits.remove(&trigger.entity());
+}
+
+pub(crate) fn on_remove_logged_record_despawn(
+    trigger: Trigger<OnRemove, LoggedIn>,
+    logged: Query<(&LoggedIn, Option<&StableId>)>,
+    mut changelog: ResMut<Changelog>,
+) {
+    let entity = trigger.entity();
+    if let Ok((logged_in, stable_id)) = logged.get(entity) {
+        changelog.record(logged_in.0, entity, stable_id.copied(), EditKind::Despawned);
+    }
+}
+
+pub(crate) fn record_spawns(
+    mut commands: Commands,
+    mut changelog: ResMut<Changelog>,
+    spawned: Query<(Entity, Option<&LoggedIn>, Option<&StableId>), Spawned>,
+    circuits: Query<(), With<Circuit>>,
+    children: Query<(Entity, Relations<Child>)>,
+) {
+    for (entity, logged_in, stable_id) in spawned.iter() {
+        record_edit(
+            &mut commands,
+            &mut changelog,
+            &circuits,
+            &children,
+            entity,
+            logged_in,
+            stable_id,
+            EditKind::Spawned,
+        );
+    }
+}
+
+type ChangedQuery<'w, 's, C> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        Ref<'static, C>,
+        Option<&'static LoggedIn>,
+        Option<&'static StableId>,
+    ),
+    (Changed<C>, Edited),
+>;
+
+pub(crate) fn record_changes<C: Component>(
+    mut commands: Commands,
+    mut changelog: ResMut<Changelog>,
+    changed: ChangedQuery<C>,
+    circuits: Query<(), With<Circuit>>,
+    children: Query<(Entity, Relations<Child>)>,
+) {
+    let name = std::any::type_name::<C>();
+    let name = name.rsplit("::").next().unwrap_or(name);
+
+    for (entity, component, logged_in, stable_id) in changed.iter() {
+        // components of a spawned entity are part of its spawn
+        if component.is_added() {
+            continue;
+        }
+
+        record_edit(
+            &mut commands,
+            &mut changelog,
+            &circuits,
+            &children,
+            entity,
+            logged_in,
+            stable_id,
+            EditKind::Changed(name),
+        );
+    }
+}
+
+/// Records changed transforms like [`record_changes`], except for symbols being nudged.
+/// A burst of nudges is recorded once it ends, as a single edit of each moved symbol.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn record_transform_changes(
+    mut commands: Commands,
+    mut changelog: ResMut<Changelog>,
+    changed: ChangedQuery<Transform>,
+    logged: Query<(Option<&LoggedIn>, Option<&StableId>)>,
+    circuits: Query<(), With<Circuit>>,
+    children: Query<(Entity, Relations<Child>)>,
+    burst: Res<NudgeBurst>,
+    mut moved_events: EventReader<SelectionMoved>,
+) {
+    let moved: Vec<Entity> = moved_events
+        .read()
+        .flat_map(|event| event.entities.iter().copied())
+        .collect();
+
+    for (entity, transform, logged_in, stable_id) in changed.iter() {
+        if transform.is_added() || burst.entities.contains(&entity) || moved.contains(&entity) {
+            continue;
+        }
+
+        record_edit(
+            &mut commands,
+            &mut changelog,
+            &circuits,
+            &children,
+            entity,
+            logged_in,
+            stable_id,
+            EditKind::Changed("Transform"),
+        );
+    }
+
+    for entity in moved {
+        let Ok((logged_in, stable_id)) = logged.get(entity) else {
+            continue;
+        };
+
+        record_edit(
+            &mut commands,
+            &mut changelog,
+            &circuits,
+            &children,
+            entity,
+            logged_in,
+            stable_id,
+            EditKind::Changed("Transform"),
+        );
+    }
+}
+
+/// Loading a circuit is not an edit, its changelog starts out empty.
+pub(crate) fn clear_changelog_on_load(
+    mut changelog: ResMut<Changelog>,
+    mut circuit_loaded_events: EventReader<CircuitLoadedEvent>,
+) {
+    for event in circuit_loaded_events.read() {
+        if let Some(circuit) = changelog.circuits.get_mut(&event.circuit.0) {
+            circuit.edits.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use digilogic_core::transform::Transform;
+
+    #[test]
+    fn records_edits() {
+        let mut app = bevy_app::App::new();
+        app.add_plugins((bevy_state::app::StatesPlugin, digilogic_core::CorePlugin));
+        app.init_resource::<Changelog>()
+            .observe(on_add_circuit_changelog)
+            .observe(on_remove_circuit_changelog)
+            .observe(on_remove_logged_record_despawn);
+        app.add_systems(
+            bevy_app::Last,
+            (
+                record_spawns,
+                record_changes::<Transform>,
+                clear_changelog_on_load,
+            )
+                .chain(),
+        );
+
+        let circuit = app.world_mut().spawn(Circuit).id();
+        let symbol = app
+            .world_mut()
+            .spawn((Symbol, Transform::default()))
+            .set::<Child>(circuit)
+            .id();
+        app.update();
+
+        app.world_mut()
+            .get_mut::<Transform>(symbol)
+            .unwrap()
+            .translation
+            .x = digilogic_core::fixed!(10);
+        app.update();
+        app.world_mut().despawn(symbol);
+
+        let changelog = app.world().resource::<Changelog>();
+        let kinds: Vec<_> = changelog
+            .circuit(circuit)
+            .unwrap()
+            .edits()
+            .iter()
+            .map(|edit| (edit.entity, edit.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                (symbol, EditKind::Spawned),
+                (symbol, EditKind::Changed("Transform")),
+                (symbol, EditKind::Despawned),
+            ]
+        );
+
+        let sequence = changelog.circuit(circuit).unwrap().edits()[1].sequence;
+        assert_eq!(changelog.circuit(circuit).unwrap().since(sequence).len(), 2);
+
+        app.world_mut().send_event(CircuitLoadedEvent {
+            circuit: CircuitID(circuit),
+        });
+        app.update();
+        let changelog = app.world().resource::<Changelog>();
+        assert!(changelog.circuit(circuit).unwrap().edits().is_empty());
+    }
+}
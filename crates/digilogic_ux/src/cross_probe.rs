@@ -0,0 +1,178 @@
+use crate::SelectionChanged;
+use aery::prelude::*;
+use bevy_ecs::prelude::*;
+use bevy_ecs::system::SystemParam;
+use digilogic_core::components::*;
+
+type HierarchySymbolQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        Option<&'static SymbolKind>,
+        Option<&'static SubCircuit>,
+    ),
+    With<Symbol>,
+>;
+
+/// Finds the entities in other circuits of the hierarchy that correspond to an entity.
+#[derive(SystemParam)]
+pub(crate) struct Hierarchy<'w, 's> {
+    edges: Query<'w, 's, Relations<Child>>,
+    circuits: Query<'w, 's, Entity, With<Circuit>>,
+    symbols: HierarchySymbolQuery<'w, 's>,
+    names: Query<'w, 's, &'static Name>,
+    ports: Query<'w, 's, (Entity, Option<&'static NetID>), With<Port>>,
+    nets: Query<'w, 's, (), With<Net>>,
+    endpoints: Query<'w, 's, &'static PortID, With<Endpoint>>,
+}
+
+impl Hierarchy<'_, '_> {
+    fn circuit_of(&self, entity: Entity) -> Option<Entity> {
+        let mut circuit = None;
+        if let Ok(edges) = self.edges.get(entity) {
+            edges
+                .join::<Up<Child>>(&self.circuits)
+                .for_each(|entity| circuit = Some(entity));
+        }
+        circuit
+    }
+
+    fn symbol_of(&self, port: Entity) -> Option<Entity> {
+        let mut symbol = None;
+        if let Ok(edges) = self.edges.get(port) {
+            edges
+                .join::<Up<Child>>(&self.symbols)
+                .for_each(|(entity, _, _)| symbol = Some(entity));
+        }
+        symbol
+    }
+
+    fn ports_of(&self, symbol: Entity) -> Vec<(Entity, Option<Entity>)> {
+        let mut ports = Vec::new();
+        if let Ok(edges) = self.edges.get(symbol) {
+            edges
+                .join::<Child>(&self.ports)
+                .for_each(|(port, net)| ports.push((port, net.map(|net| net.0))));
+        }
+        ports
+    }
+
+    fn has_name(&self, entity: Entity, name: &Name) -> bool {
+        self.names.get(entity).is_ok_and(|other| other.0 == name.0)
+    }
+
+    /// The symbols instantiating `circuit`.
+    fn instances_of(&self, circuit: Entity) -> Vec<Entity> {
+        self.symbols
+            .iter()
+            .filter(|(_, _, sub_circuit)| sub_circuit.is_some_and(|sub| sub.0 == circuit))
+            .map(|(instance, _, _)| instance)
+            .collect()
+    }
+
+    /// The input or output symbol of `circuit` called `name`.
+    fn boundary_symbol_named(&self, circuit: Entity, name: &Name) -> Option<Entity> {
+        let mut boundary = None;
+        if let Ok(edges) = self.edges.get(circuit) {
+            edges
+                .join::<Child>(&self.symbols)
+                .for_each(|(symbol, kind, _)| {
+                    if matches!(kind, Some(SymbolKind::In | SymbolKind::Out))
+                        && self.has_name(symbol, name)
+                    {
+                        boundary = Some(symbol);
+                    }
+                });
+        }
+        boundary
+    }
+
+    /// Collects the entities related to `entity` one level up and down the hierarchy:
+    /// the symbols instantiating its circuit, and for nets the nets they connect to
+    /// through the ports of sub-circuit symbols.
+    pub(crate) fn related(&self, entity: Entity, related: &mut Vec<Entity>) {
+        let Some(circuit) = self.circuit_of(entity) else {
+            return;
+        };
+
+        let instances = self.instances_of(circuit);
+        related.extend(&instances);
+
+        if !self.nets.contains(entity) {
+            return;
+        }
+
+        let mut ports = Vec::new();
+        if let Ok(edges) = self.edges.get(entity) {
+            edges
+                .join::<Child>(&self.endpoints)
+                .for_each(|port| ports.push(port.0));
+        }
+
+        for port in ports {
+            let Some(symbol) = self.symbol_of(port) else {
+                continue;
+            };
+            let Ok((_, kind, sub_circuit)) = self.symbols.get(symbol) else {
+                continue;
+            };
+
+            if matches!(kind, Some(SymbolKind::In | SymbolKind::Out)) {
+                // up: the net connected to the matching port of each instance
+                let Ok(name) = self.names.get(symbol) else {
+                    continue;
+                };
+                for &instance in &instances {
+                    related.extend(
+                        self.ports_of(instance)
+                            .into_iter()
+                            .filter(|&(port, _)| self.has_name(port, name))
+                            .filter_map(|(_, net)| net),
+                    );
+                }
+            } else if let Some(sub_circuit) = sub_circuit {
+                // down: the net connected to the matching input or output of the sub-circuit
+                let Ok(name) = self.names.get(port) else {
+                    continue;
+                };
+                if let Some(boundary) = self.boundary_symbol_named(sub_circuit.0, name) {
+                    related.extend(
+                        self.ports_of(boundary)
+                            .into_iter()
+                            .filter_map(|(_, net)| net),
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Highlights the entities related to the selection in the other circuits of the hierarchy.
+pub(crate) fn cross_probe(
+    mut commands: Commands,
+    mut events: EventReader<SelectionChanged>,
+    hierarchy: Hierarchy,
+    probed: Query<Entity, With<Probed>>,
+) {
+    let Some(event) = events.read().last() else {
+        return;
+    };
+
+    let mut related = Vec::new();
+    for &entity in &event.selected {
+        hierarchy.related(entity, &mut related);
+    }
+    related.retain(|entity| !event.selected.contains(entity));
+
+    for entity in probed.iter() {
+        if !related.contains(&entity) {
+            commands.entity(entity).remove::<Probed>();
+        }
+    }
+    for entity in related {
+        if !probed.contains(entity) {
+            commands.entity(entity).insert(Probed);
+        }
+    }
+}
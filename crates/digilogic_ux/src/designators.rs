@@ -0,0 +1,93 @@
+use aery::prelude::*;
+use bevy_ecs::prelude::*;
+use digilogic_core::components::*;
+use digilogic_core::transform::{GlobalTransform, Vec2};
+use digilogic_core::{HashMap, SharedStr};
+
+/// Renumbers the designators of all symbols in a circuit, per prefix from zero,
+/// top-to-bottom and then left-to-right.
+/// All symbols are updated by this one event, within the same frame.
+#[derive(Event, Debug)]
+pub struct RenumberDesignators {
+    pub circuit: CircuitID,
+}
+
+/// The new designator number of every symbol, in reading order per prefix.
+fn renumbered(mut symbols: Vec<(Entity, SharedStr, Vec2)>) -> Vec<(Entity, u32)> {
+    symbols.sort_by(|(_, _, a), (_, _, b)| a.y.cmp(&b.y).then(a.x.cmp(&b.x)));
+
+    let mut next_numbers = HashMap::<SharedStr, u32>::default();
+    symbols
+        .into_iter()
+        .map(|(symbol, prefix, _)| {
+            let next = next_numbers.entry(prefix).or_default();
+            let number = *next;
+            *next += 1;
+            (symbol, number)
+        })
+        .collect()
+}
+
+type RenumberSymbolQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static DesignatorPrefix,
+        &'static mut DesignatorNumber,
+        &'static GlobalTransform,
+    ),
+    With<Symbol>,
+>;
+
+pub(crate) fn renumber_designators(
+    trigger: Trigger<RenumberDesignators>,
+    circuits: Query<Relations<Child>, With<Circuit>>,
+    mut symbols: RenumberSymbolQuery,
+) {
+    let Ok(edges) = circuits.get(trigger.event().circuit.0) else {
+        return;
+    };
+
+    let mut placed = Vec::new();
+    edges
+        .join::<Child>(&symbols)
+        .for_each(|(symbol, prefix, _, transform)| {
+            placed.push((symbol, prefix.0.clone(), transform.translation));
+        });
+
+    for (symbol, number) in renumbered(placed) {
+        if let Ok((_, _, mut designator_number, _)) = symbols.get_mut(symbol) {
+            // only touch what changed, so unchanged symbols are not reported as edited
+            if designator_number.0 != number {
+                designator_number.0 = number;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use digilogic_core::Fixed;
+
+    fn at(x: i16, y: i16) -> Vec2 {
+        Vec2 {
+            x: Fixed::from_i16(x),
+            y: Fixed::from_i16(y),
+        }
+    }
+
+    #[test]
+    fn reading_order_per_prefix() {
+        let [a, b, c, d] = [1, 2, 3, 4].map(Entity::from_raw);
+        let numbers = renumbered(vec![
+            (a, SharedStr::new_static("U"), at(50, 100)),
+            (b, SharedStr::new_static("U"), at(0, 100)),
+            (c, SharedStr::new_static("U"), at(200, 0)),
+            (d, SharedStr::new_static("IN"), at(0, 200)),
+        ]);
+
+        assert_eq!(numbers, vec![(c, 0), (b, 1), (a, 2), (d, 0)]);
+    }
+}
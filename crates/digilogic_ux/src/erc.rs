@@ -0,0 +1,533 @@
+use crate::SetEndpointBits;
+use aery::prelude::*;
+use bevy_ecs::prelude::*;
+use bevy_ecs::system::lifetimeless::Read;
+use bevy_ecs::system::SystemParam;
+use bevy_reflect::Reflect;
+use digilogic_core::components::*;
+use digilogic_core::connectivity::Connectivity;
+use std::collections::BTreeMap;
+
+/// How serious a violation of an electrical rule is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Reflect)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// The electrical rules that are checked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
+pub enum Rule {
+    /// An input port of a symbol is not connected to any net.
+    FloatingInput,
+    /// A net is driven by more than one output that can't be switched off.
+    ConflictingDrivers,
+    /// The width of a port differs from the bits of the net it is connected to.
+    BitWidthMismatch,
+}
+
+impl Rule {
+    pub fn severity(self) -> Severity {
+        match self {
+            Self::FloatingInput => Severity::Warning,
+            Self::ConflictingDrivers | Self::BitWidthMismatch => Severity::Error,
+        }
+    }
+}
+
+/// A violation of an electrical rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub rule: Rule,
+    /// The circuit the offending entity is placed in.
+    pub circuit: CircuitID,
+    /// The entity the finding is attached to.
+    pub entity: Entity,
+    /// Other entities involved, like the symbols driving a net.
+    pub related: Vec<Entity>,
+    pub message: String,
+    /// Changes that would resolve the finding, most likely one first.
+    pub fixes: Vec<QuickFix>,
+}
+
+impl Finding {
+    #[inline]
+    pub fn severity(&self) -> Severity {
+        self.rule.severity()
+    }
+}
+
+/// A change that resolves a finding.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QuickFix {
+    /// Connects an endpoint to the given bits of its net only.
+    SelectBits { endpoint: Entity, bits: Bits },
+    /// Connects an endpoint to all bits of its net.
+    ClearBits { endpoint: Entity },
+    /// Changes the width of a net.
+    SetNetWidth { net: Entity, width: BitWidth },
+}
+
+impl QuickFix {
+    pub fn label(&self) -> String {
+        match self {
+            Self::SelectBits { bits, .. } => format!("Connect bits {bits}"),
+            Self::ClearBits { .. } => "Connect all bits".to_owned(),
+            Self::SetNetWidth { width, .. } => format!("Set net width to {}", width.0),
+        }
+    }
+}
+
+/// Requests a quick fix to be applied.
+#[derive(Event, Debug)]
+pub struct ApplyQuickFix(pub QuickFix);
+
+/// The findings of the electrical rule check, updated whenever the circuits change.
+#[derive(Debug, Default, Resource)]
+pub struct ErcReport {
+    findings: Vec<Finding>,
+}
+
+impl ErcReport {
+    #[inline]
+    pub fn findings(&self) -> &[Finding] {
+        &self.findings
+    }
+}
+
+/// Replaces the findings of `rule`, leaving the report untouched if they are the same.
+fn replace_findings(report: &mut ResMut<ErcReport>, rule: Rule, findings: Vec<Finding>) {
+    let previous = report.bypass_change_detection().findings.iter();
+    if previous
+        .filter(|finding| finding.rule == rule)
+        .eq(findings.iter())
+    {
+        return;
+    }
+
+    report.findings.retain(|finding| finding.rule != rule);
+    report.findings.extend(findings);
+}
+
+/// Marks an entity that violates an electrical rule with a warning.
+#[derive(Debug, Default, Component, Reflect)]
+pub struct ErcWarning;
+
+/// Marks an entity that violates an electrical rule with an error.
+#[derive(Debug, Default, Component, Reflect)]
+pub struct ErcError;
+
+fn designator(prefix: &DesignatorPrefix, number: &DesignatorNumber) -> String {
+    format!("{}{}", prefix.0, number.0)
+}
+
+type ErcSymbolQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        (Entity, Read<DesignatorPrefix>, Read<DesignatorNumber>),
+        Relations<Child>,
+    ),
+    With<Symbol>,
+>;
+
+type FloatingInputQuery<'w, 's> =
+    Query<'w, 's, (Entity, Read<Name>, Read<PortDirection>), (With<Port>, Without<NetID>)>;
+
+/// Detects changes to the connections and designators the checks are based on,
+/// so the checks only run again when their findings could have changed.
+#[derive(SystemParam)]
+pub(crate) struct ConnectionChanges<'w, 's> {
+    changed: ChangeQuery<'w, 's>,
+    removed_net_ids: RemovedComponents<'w, 's, NetID>,
+    removed_ports: RemovedComponents<'w, 's, Port>,
+    removed_bits: RemovedComponents<'w, 's, Bits>,
+}
+
+impl ConnectionChanges<'_, '_> {
+    fn any(&mut self) -> bool {
+        let removed = (self.removed_net_ids.read().count()
+            + self.removed_ports.read().count()
+            + self.removed_bits.read().count())
+            > 0;
+        removed || !self.changed.is_empty()
+    }
+}
+
+type ChangeQuery<'w, 's> = Query<
+    'w,
+    's,
+    (),
+    Or<(
+        Added<Port>,
+        Changed<NetID>,
+        Changed<PortID>,
+        Changed<BitWidth>,
+        Changed<Bits>,
+        Changed<DesignatorPrefix>,
+        Changed<DesignatorNumber>,
+        Changed<Name>,
+        Changed<Parameters>,
+    )>,
+>;
+
+/// Flags input ports of symbols that have no endpoint connected to them.
+pub(crate) fn check_floating_inputs(
+    mut report: ResMut<ErcReport>,
+    mut changes: ConnectionChanges,
+    circuits: Query<(Entity, Relations<Child>), With<Circuit>>,
+    symbols: ErcSymbolQuery,
+    ports: FloatingInputQuery,
+) {
+    if !changes.any() {
+        return;
+    }
+
+    let mut findings = Vec::new();
+    for (circuit, edges) in circuits.iter() {
+        edges
+            .join::<Child>(&symbols)
+            .for_each(|((_, prefix, number), symbol_edges)| {
+                symbol_edges
+                    .join::<Child>(&ports)
+                    .for_each(|(port, name, &direction)| {
+                        if direction != PortDirection::Input {
+                            return;
+                        }
+
+                        findings.push(Finding {
+                            rule: Rule::FloatingInput,
+                            circuit: CircuitID(circuit),
+                            entity: port,
+                            related: Vec::new(),
+                            fixes: Vec::new(),
+                            message: format!(
+                                "Input {} of {} is not connected",
+                                name.0,
+                                designator(prefix, number),
+                            ),
+                        });
+                    });
+            });
+    }
+
+    replace_findings(&mut report, Rule::FloatingInput, findings);
+}
+
+type DriverQuery<'w, 's> = Query<'w, 's, (Read<PortDirection>, Relations<Child>), With<Port>>;
+
+/// Flags nets driven by more than one output port. Bidirectional ports
+/// can be switched off and are not counted as drivers.
+/// Nets joined by tunnels are checked together and reported by their first net.
+pub(crate) fn check_conflicting_drivers(
+    mut report: ResMut<ErcReport>,
+    mut changes: ConnectionChanges,
+    connectivity: Res<Connectivity>,
+    circuits: Query<Entity, With<Circuit>>,
+    symbols: ErcSymbolQuery,
+    drivers: DriverQuery,
+    nets: Query<&Name, With<Net>>,
+) {
+    if !changes.any() {
+        return;
+    }
+
+    let mut findings = Vec::new();
+    for circuit in circuits.iter() {
+        // ordered by net so the findings stay stable between runs
+        let mut net_drivers = BTreeMap::<Entity, Vec<(Entity, String)>>::new();
+        for net in connectivity.nets_in(circuit) {
+            let root = connectivity.joined_root(net);
+            for port in connectivity.ports_of(net) {
+                let Ok((&direction, port_edges)) = drivers.get(port) else {
+                    continue;
+                };
+                if direction != PortDirection::Output {
+                    continue;
+                }
+
+                port_edges
+                    .join::<Up<Child>>(&symbols)
+                    .for_each(|((symbol, prefix, number), _)| {
+                        net_drivers
+                            .entry(root)
+                            .or_default()
+                            .push((symbol, designator(prefix, number)));
+                    });
+            }
+        }
+
+        for (net, mut symbols) in net_drivers {
+            if symbols.len() < 2 {
+                continue;
+            }
+            symbols.sort_by(|(_, a), (_, b)| a.cmp(b));
+
+            let net_name = match nets.get(net) {
+                Ok(name) if !name.0.is_empty() => format!("Net {}", name.0),
+                _ => "An unnamed net".to_owned(),
+            };
+            let designators = symbols
+                .iter()
+                .map(|(_, designator)| designator.as_str())
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            findings.push(Finding {
+                rule: Rule::ConflictingDrivers,
+                circuit: CircuitID(circuit),
+                entity: net,
+                related: symbols.iter().map(|&(symbol, _)| symbol).collect(),
+                message: format!("{net_name} is driven by multiple outputs: {designators}"),
+                fixes: Vec::new(),
+            });
+        }
+    }
+
+    replace_findings(&mut report, Rule::ConflictingDrivers, findings);
+}
+
+/// The fixes for an endpoint connecting a port of `port_width` to a net of `net_width`
+/// through `bits`, or `None` if the widths match.
+fn bit_width_fixes(
+    endpoint: Entity,
+    net: Entity,
+    port_width: BitWidth,
+    net_width: BitWidth,
+    bits: Option<&Bits>,
+) -> Option<Vec<QuickFix>> {
+    let port_bits = port_width.0.get();
+    let connected = match bits {
+        Some(bits) if bits.validate(net_width).is_ok() => bits.0.len(),
+        Some(_) => 0,
+        None => net_width.0.get() as usize,
+    };
+    if connected == (port_bits as usize) {
+        return None;
+    }
+
+    let mut fixes = Vec::new();
+    if (bits.is_some()) && (port_width == net_width) {
+        fixes.push(QuickFix::ClearBits { endpoint });
+    }
+    if port_width < net_width {
+        fixes.push(QuickFix::SelectBits {
+            endpoint,
+            bits: Bits((0..port_bits).rev().collect()),
+        });
+    }
+    if bits.is_none() {
+        fixes.push(QuickFix::SetNetWidth {
+            net,
+            width: port_width,
+        });
+    }
+    Some(fixes)
+}
+
+type WidthNetQuery<'w, 's> =
+    Query<'w, 's, ((Entity, Read<Name>, Read<BitWidth>), Relations<Child>), With<Net>>;
+
+type WidthEndpointQuery<'w, 's> =
+    Query<'w, 's, (Entity, Read<PortID>, Option<Read<Bits>>), With<Endpoint>>;
+
+type WidthPortQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        (Read<Name>, Option<Read<BitWidth>>, Read<PortDirection>),
+        Relations<Child>,
+    ),
+    With<Port>,
+>;
+
+/// Flags endpoints whose port is wider or narrower than the bits of the net they connect to.
+pub(crate) fn check_bit_widths(
+    mut report: ResMut<ErcReport>,
+    mut changes: ConnectionChanges,
+    circuits: Query<(Entity, Relations<Child>), With<Circuit>>,
+    nets: WidthNetQuery,
+    endpoints: WidthEndpointQuery,
+    ports: WidthPortQuery,
+    symbols: ErcSymbolQuery,
+) {
+    if !changes.any() {
+        return;
+    }
+
+    let mut findings = Vec::new();
+    for (circuit, edges) in circuits.iter() {
+        edges
+            .join::<Child>(&nets)
+            .for_each(|((net, net_name, &net_width), net_edges)| {
+                net_edges
+                    .join::<Child>(&endpoints)
+                    .for_each(|(endpoint, port_id, bits)| {
+                        let Ok(((port_name, port_width, direction), port_edges)) =
+                            ports.get(port_id.0)
+                        else {
+                            return;
+                        };
+                        let port_width = port_width
+                            .copied()
+                            .unwrap_or(BitWidth(std::num::NonZeroU8::MIN));
+                        let Some(fixes) =
+                            bit_width_fixes(endpoint, net, port_width, net_width, bits)
+                        else {
+                            return;
+                        };
+
+                        let mut owner = String::new();
+                        port_edges.join::<Up<Child>>(&symbols).for_each(
+                            |((_, prefix, number), _)| owner = designator(prefix, number),
+                        );
+                        let connected = match bits {
+                            Some(bits) => format!("bits {bits} of"),
+                            None => format!("{} bits of", net_width.0),
+                        };
+                        let net_name = if net_name.0.is_empty() {
+                            "an unnamed net".to_owned()
+                        } else {
+                            format!("net {}", net_name.0)
+                        };
+
+                        findings.push(Finding {
+                            rule: Rule::BitWidthMismatch,
+                            circuit: CircuitID(circuit),
+                            entity: port_id.0,
+                            related: vec![endpoint, net],
+                            message: format!(
+                                "{} {} of {owner} is {} bits wide but connects to {connected} {net_name}",
+                                match direction {
+                                    PortDirection::Input => "Input",
+                                    PortDirection::Output => "Output",
+                                    PortDirection::Bidirectional => "Port",
+                                },
+                                port_name.0,
+                                port_width.0,
+                            ),
+                            fixes,
+                        });
+                    });
+            });
+    }
+
+    replace_findings(&mut report, Rule::BitWidthMismatch, findings);
+}
+
+pub(crate) fn apply_quick_fixes(
+    mut commands: Commands,
+    mut events: EventReader<ApplyQuickFix>,
+    mut bits_events: EventWriter<SetEndpointBits>,
+) {
+    for ApplyQuickFix(fix) in events.read() {
+        match fix {
+            QuickFix::SelectBits { endpoint, bits } => {
+                bits_events.send(SetEndpointBits {
+                    endpoint: *endpoint,
+                    bits: Some(bits.clone()),
+                });
+            }
+            QuickFix::ClearBits { endpoint } => {
+                bits_events.send(SetEndpointBits {
+                    endpoint: *endpoint,
+                    bits: None,
+                });
+            }
+            QuickFix::SetNetWidth { net, width } => {
+                if let Some(mut net) = commands.get_entity(*net) {
+                    net.insert(*width);
+                }
+            }
+        }
+    }
+}
+
+fn sync_markers<M: Component + Default>(
+    commands: &mut Commands,
+    report: &ErcReport,
+    severity: Severity,
+    marked: &Query<Entity, With<M>>,
+) {
+    let flagged = |entity: Entity| {
+        report
+            .findings
+            .iter()
+            .any(|finding| (finding.entity == entity) && (finding.severity() == severity))
+    };
+
+    for entity in marked.iter() {
+        if !flagged(entity) {
+            commands.entity(entity).remove::<M>();
+        }
+    }
+
+    for finding in report.findings.iter() {
+        if (finding.severity() == severity) && !marked.contains(finding.entity) {
+            if let Some(mut entity) = commands.get_entity(finding.entity) {
+                entity.insert(M::default());
+            }
+        }
+    }
+}
+
+/// Puts the markers rendered at offending entities in line with the report.
+pub(crate) fn update_erc_markers(
+    mut commands: Commands,
+    report: Res<ErcReport>,
+    warnings: Query<Entity, With<ErcWarning>>,
+    errors: Query<Entity, With<ErcError>>,
+) {
+    if !report.is_changed() {
+        return;
+    }
+
+    sync_markers(&mut commands, &report, Severity::Warning, &warnings);
+    sync_markers(&mut commands, &report, Severity::Error, &errors);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::num::NonZeroU8;
+
+    fn width(bits: u8) -> BitWidth {
+        BitWidth(NonZeroU8::new(bits).unwrap())
+    }
+
+    #[test]
+    fn bit_width_fix_suggestions() {
+        let endpoint = Entity::from_raw(1);
+        let net = Entity::from_raw(2);
+
+        assert_eq!(
+            bit_width_fixes(endpoint, net, width(4), width(4), None),
+            None
+        );
+        let bits = Bits::parse("5:4").unwrap();
+        assert_eq!(
+            bit_width_fixes(endpoint, net, width(2), width(8), Some(&bits)),
+            None
+        );
+
+        assert_eq!(
+            bit_width_fixes(endpoint, net, width(2), width(8), None),
+            Some(vec![
+                QuickFix::SelectBits {
+                    endpoint,
+                    bits: Bits::parse("1:0").unwrap(),
+                },
+                QuickFix::SetNetWidth {
+                    net,
+                    width: width(2),
+                },
+            ])
+        );
+
+        let out_of_range = Bits::parse("9").unwrap();
+        assert_eq!(
+            bit_width_fixes(endpoint, net, width(8), width(8), Some(&out_of_range)),
+            Some(vec![QuickFix::ClearBits { endpoint }])
+        );
+    }
+}
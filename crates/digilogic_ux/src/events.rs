@@ -1,5 +1,6 @@
 use bevy_ecs::prelude::*;
-use digilogic_core::{components::CircuitID, transform::Vec2};
+use digilogic_core::components::{CircuitID, SymbolKind};
+use digilogic_core::transform::{Direction, Rotation, Vec2};
 
 #[derive(Event, Debug, Copy, Clone, PartialEq, Eq)]
 pub enum PointerButton {
@@ -30,6 +31,27 @@ pub struct ClickEvent {
     pub modifiers: Modifiers,
 }
 
+/// Places a new symbol of `kind` at `pos`, numbered after the symbols already in the circuit.
+#[derive(Event, Debug)]
+pub struct PlaceSymbol {
+    pub circuit: CircuitID,
+    pub kind: SymbolKind,
+    pub pos: Vec2,
+}
+
+#[derive(Event, Debug)]
+pub struct DoubleClickEvent {
+    /// Which viewport does this event target?
+    pub viewport: Entity,
+
+    /// Which circuit does this event target?
+    pub circuit: CircuitID,
+
+    pub pos: Vec2,
+    pub button: PointerButton,
+    pub modifiers: Modifiers,
+}
+
 #[derive(Event, Debug)]
 pub struct HoverEvent {
     /// Which viewport does this event target?
@@ -73,3 +95,52 @@ pub struct MoveEntity {
     pub pos: Vec2,
     pub offset: Vec2,
 }
+
+/// Rotates the selected symbols around the center of their combined bounding box.
+#[derive(Event, Debug)]
+pub struct RotateSelection {
+    pub rotation: Rotation,
+}
+
+/// Mirrors the selected symbols around the center of their combined bounding box,
+/// left to right or, if `vertical` is set, top to bottom.
+#[derive(Event, Debug)]
+pub struct MirrorSelection {
+    pub vertical: bool,
+}
+
+/// Moves the selected symbols one grid unit in `direction`, or ten if `large` is set.
+#[derive(Event, Debug)]
+pub struct NudgeSelection {
+    pub direction: Direction,
+    pub large: bool,
+}
+
+/// Ends the current burst of [`NudgeSelection`] events, e.g. when the arrow key is released.
+#[derive(Event, Debug)]
+pub struct EndNudge;
+
+/// Sent once for every finished move of the selected symbols.
+/// A burst of nudges from a held down key is reported as a single move,
+/// which the [`Changelog`](crate::Changelog) records as one edit of each moved symbol.
+#[derive(Event, Debug)]
+pub struct SelectionMoved {
+    pub entities: Vec<Entity>,
+    pub delta: Vec2,
+}
+
+/// Locks or unlocks entities, see [`Locked`](digilogic_core::components::Locked).
+#[derive(Event, Debug)]
+pub struct SetLocked {
+    pub entities: Vec<Entity>,
+    pub locked: bool,
+}
+
+/// Draws gates as their De Morgan equivalent or as themselves again,
+/// see [`DeMorgan`](digilogic_core::components::DeMorgan).
+/// Entities that aren't such gates are left alone.
+#[derive(Event, Debug)]
+pub struct SetDeMorgan {
+    pub entities: Vec<Entity>,
+    pub de_morgan: bool,
+}
@@ -0,0 +1,191 @@
+use crate::{ActiveTool, DragEvent, DragType, GraphicPlacementKind, GridSize, PointerButton};
+use aery::prelude::*;
+use bevy_ecs::prelude::*;
+use digilogic_core::bundles::GraphicBundle;
+use digilogic_core::components::*;
+use digilogic_core::transform::*;
+use digilogic_core::{fixed, Fixed};
+
+/// How far from the outline of a graphic the cursor still hovers it.
+/// The inside of rectangles and ellipses is left free, so the entities they frame stay reachable.
+pub(crate) const GRAPHIC_HOVER_DISTANCE: Fixed = fixed!(4);
+
+/// The graphic a viewport is currently drawing, and where the drag started.
+#[derive(Debug, Component)]
+pub(crate) struct DrawingGraphic {
+    graphic: Entity,
+    start: Vec2,
+}
+
+pub(crate) fn snap(position: Vec2, grid_size: Fixed) -> Vec2 {
+    if grid_size > fixed!(0) {
+        position.round_to_multiple(grid_size)
+    } else {
+        position
+    }
+}
+
+/// Whether `point`, relative to the position of the graphic, lies within `tolerance` of its outline.
+pub(crate) fn is_on_outline(graphic: &Graphic, point: Vec2, tolerance: Fixed) -> bool {
+    let (x, y) = (point.x.to_f32(), point.y.to_f32());
+    let (width, height) = (graphic.extent.x.to_f32(), graphic.extent.y.to_f32());
+    let tolerance = tolerance.to_f32();
+
+    match graphic.kind {
+        GraphicKind::Rectangle => {
+            let (min_x, max_x) = (width.min(0.0), width.max(0.0));
+            let (min_y, max_y) = (height.min(0.0), height.max(0.0));
+
+            let in_outer = (x >= min_x - tolerance)
+                && (x <= max_x + tolerance)
+                && (y >= min_y - tolerance)
+                && (y <= max_y + tolerance);
+            let in_inner = (x > min_x + tolerance)
+                && (x < max_x - tolerance)
+                && (y > min_y + tolerance)
+                && (y < max_y - tolerance);
+            in_outer && !in_inner
+        }
+        GraphicKind::Line => {
+            let length_squared = (width * width) + (height * height);
+            let t = if length_squared > 0.0 {
+                (((x * width) + (y * height)) / length_squared).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            let (dx, dy) = (x - (t * width), y - (t * height));
+            ((dx * dx) + (dy * dy)).sqrt() <= tolerance
+        }
+        GraphicKind::Ellipse => {
+            let (radius_x, radius_y) = ((width / 2.0).abs(), (height / 2.0).abs());
+            if (radius_x == 0.0) || (radius_y == 0.0) {
+                return false;
+            }
+
+            // the distance to the outline is approximated in the direction of the point
+            let (cx, cy) = (x - (width / 2.0), y - (height / 2.0));
+            let normalized = ((cx / radius_x).powi(2) + (cy / radius_y).powi(2)).sqrt();
+            (normalized - 1.0).abs() * radius_x.min(radius_y) <= tolerance
+        }
+    }
+}
+
+/// Dragging with the shape tool draws a new graphic from the start of the drag to the cursor.
+pub(crate) fn draw_graphic_on_drag(
+    trigger: Trigger<DragEvent>,
+    mut commands: Commands,
+    active_tool: Res<ActiveTool>,
+    placement_kind: Res<GraphicPlacementKind>,
+    grid_size: Res<GridSize>,
+    drawing: Query<&DrawingGraphic>,
+    mut graphics: Query<&mut Graphic>,
+) {
+    let event = trigger.event();
+    let viewport = trigger.entity();
+
+    if event.button != PointerButton::Primary {
+        return;
+    }
+
+    let position = snap(event.pos, grid_size.0);
+
+    let Ok(drawing) = drawing.get(viewport) else {
+        if (event.drag_type == DragType::Start) && (*active_tool == ActiveTool::DrawGraphic) {
+            let graphic = commands
+                .spawn(GraphicBundle {
+                    graphic: Graphic {
+                        kind: placement_kind.0,
+                        ..Default::default()
+                    },
+                    transform: TransformBundle {
+                        transform: Transform {
+                            translation: position,
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .set::<Child>(event.circuit.0)
+                .id();
+
+            commands.entity(viewport).insert(DrawingGraphic {
+                graphic,
+                start: position,
+            });
+        }
+        return;
+    };
+
+    let extent = position - drawing.start;
+    if let Ok(mut graphic) = graphics.get_mut(drawing.graphic) {
+        if graphic.extent != extent {
+            graphic.extent = extent;
+        }
+    }
+
+    if event.drag_type == DragType::End {
+        // a click without dragging leaves nothing to see
+        if extent == Vec2::ZERO {
+            commands.entity(drawing.graphic).despawn();
+        }
+        commands.entity(viewport).remove::<DrawingGraphic>();
+    }
+}
+
+/// Keeps the bounds of graphics, used for hovering and selecting them, in sync with their extent.
+pub(crate) fn update_graphic_bounds(
+    mut graphics: Query<(&Graphic, &mut BoundingBox), Changed<Graphic>>,
+) {
+    for (graphic, mut bounds) in graphics.iter_mut() {
+        let padding = Vec2 {
+            x: GRAPHIC_HOVER_DISTANCE,
+            y: GRAPHIC_HOVER_DISTANCE,
+        };
+        let min = Vec2::ZERO.min(graphic.extent) - padding;
+        let max = Vec2::ZERO.max(graphic.extent) + padding;
+        *bounds = BoundingBox::from_points(min, max);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graphic(kind: GraphicKind) -> Graphic {
+        Graphic {
+            kind,
+            extent: Vec2 {
+                x: fixed!(100),
+                y: fixed!(50),
+            },
+            ..Default::default()
+        }
+    }
+
+    fn point(x: i16, y: i16) -> Vec2 {
+        Vec2 {
+            x: Fixed::from_i16(x),
+            y: Fixed::from_i16(y),
+        }
+    }
+
+    #[test]
+    fn outline_hit_testing() {
+        let tolerance = GRAPHIC_HOVER_DISTANCE;
+
+        let rectangle = graphic(GraphicKind::Rectangle);
+        assert!(is_on_outline(&rectangle, point(50, 1), tolerance));
+        assert!(is_on_outline(&rectangle, point(102, 25), tolerance));
+        assert!(!is_on_outline(&rectangle, point(50, 25), tolerance));
+
+        let line = graphic(GraphicKind::Line);
+        assert!(is_on_outline(&line, point(50, 26), tolerance));
+        assert!(!is_on_outline(&line, point(50, 0), tolerance));
+
+        let ellipse = graphic(GraphicKind::Ellipse);
+        assert!(is_on_outline(&ellipse, point(50, 0), tolerance));
+        assert!(is_on_outline(&ellipse, point(0, 25), tolerance));
+        assert!(!is_on_outline(&ellipse, point(50, 25), tolerance));
+    }
+}
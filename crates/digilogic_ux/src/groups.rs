@@ -0,0 +1,141 @@
+use crate::SelectionSet;
+use aery::prelude::*;
+use bevy_ecs::prelude::*;
+use bevy_ecs::system::SystemParam;
+use digilogic_core::components::*;
+
+/// Groups the selected symbols of `circuit`. Symbols that are already part of a group
+/// bring their outermost group along, which becomes nested in the new one.
+#[derive(Event, Debug)]
+pub struct GroupSelection {
+    pub circuit: CircuitID,
+}
+
+/// Dissolves the outermost groups of the selected symbols. Nested groups are kept.
+#[derive(Event, Debug)]
+pub struct UngroupSelection;
+
+/// Resolves symbols to the groups they are part of.
+#[derive(SystemParam)]
+pub(crate) struct Groups<'w, 's> {
+    members: Query<'w, 's, (Entity, Relations<Grouped>)>,
+    groups: Query<'w, 's, Entity, With<Group>>,
+}
+
+impl Groups<'_, '_> {
+    fn parent_group(&self, entity: Entity) -> Option<Entity> {
+        let (_, edges) = self.members.get(entity).ok()?;
+        let mut parent = None;
+        edges
+            .join::<Up<Grouped>>(&self.groups)
+            .for_each(|group| parent = Some(group));
+        parent
+    }
+
+    /// The outermost group `entity` is part of, or `entity` itself if it isn't grouped.
+    pub(crate) fn outermost(&self, entity: Entity) -> Entity {
+        let mut outermost = entity;
+        while let Some(group) = self.parent_group(outermost) {
+            outermost = group;
+        }
+        outermost
+    }
+
+    fn collect_members(&self, group: Entity, members: &mut Vec<Entity>) {
+        let Ok((_, edges)) = self.members.get(group) else {
+            return;
+        };
+
+        let mut direct = Vec::new();
+        edges
+            .join::<Grouped>(&self.members)
+            .for_each(|(member, _)| direct.push(member));
+
+        for member in direct {
+            if self.groups.contains(member) {
+                self.collect_members(member, members);
+            } else {
+                members.push(member);
+            }
+        }
+    }
+
+    /// The entities selected and moved together with `entity`:
+    /// all symbols of its outermost group, or only `entity` if it isn't grouped.
+    pub(crate) fn unit(&self, entity: Entity) -> Vec<Entity> {
+        let outermost = self.outermost(entity);
+        if outermost == entity {
+            return vec![entity];
+        }
+
+        let mut members = Vec::new();
+        self.collect_members(outermost, &mut members);
+        members
+    }
+
+    /// The direct members of a group.
+    fn direct_members(&self, group: Entity) -> Vec<Entity> {
+        let mut members = Vec::new();
+        if let Ok((_, edges)) = self.members.get(group) {
+            edges
+                .join::<Grouped>(&self.members)
+                .for_each(|(member, _)| members.push(member));
+        }
+        members
+    }
+}
+
+pub(crate) fn group_selection(
+    mut commands: Commands,
+    mut events: EventReader<GroupSelection>,
+    selection: Res<SelectionSet>,
+    symbols: Query<(), With<Symbol>>,
+    groups: Groups,
+) {
+    for event in events.read() {
+        let mut members = Vec::new();
+        for entity in selection.iter().filter(|&entity| symbols.contains(entity)) {
+            let outermost = groups.outermost(entity);
+            if !members.contains(&outermost) {
+                members.push(outermost);
+            }
+        }
+
+        // a single symbol or group doesn't need another group around it
+        if members.len() < 2 {
+            continue;
+        }
+
+        let group = commands
+            .spawn((Group, Name("Group".into())))
+            .set::<Child>(event.circuit.0)
+            .id();
+        for member in members {
+            commands.entity(member).set::<Grouped>(group);
+        }
+    }
+}
+
+pub(crate) fn ungroup_selection(
+    mut commands: Commands,
+    mut events: EventReader<UngroupSelection>,
+    selection: Res<SelectionSet>,
+    groups: Groups,
+) {
+    for _ in events.read() {
+        let mut dissolved = Vec::new();
+        for entity in selection.iter() {
+            let outermost = groups.outermost(entity);
+            if (outermost != entity) && !dissolved.contains(&outermost) {
+                dissolved.push(outermost);
+            }
+        }
+
+        for group in dissolved {
+            for member in groups.direct_members(group) {
+                commands.entity(member).unset::<Grouped>(group);
+            }
+            commands.entity(group).despawn();
+        }
+    }
+}
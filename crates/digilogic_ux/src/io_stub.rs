@@ -0,0 +1,215 @@
+use crate::nets::EndpointConnections;
+use crate::spatial_index::SpatialIndex;
+use crate::systems::{next_designator_number, DesignatorQuery};
+use crate::{
+    ActiveTool, DragEvent, DragType, HoveredEntity, MouseMoving, PointerButton, PortStub,
+    SelectionSet,
+};
+use aery::prelude::*;
+use bevy_ecs::prelude::*;
+use digilogic_core::bundles::NetBundle;
+use digilogic_core::components::*;
+use digilogic_core::connections::spawn_port_endpoint;
+use digilogic_core::symbol::SymbolRegistry;
+use digilogic_core::transform::{GlobalTransform, Vec2};
+use digilogic_core::visibility::VisibilityBundle;
+use digilogic_routing::reroute_net;
+
+/// Sent when a drag from an unconnected port ends on an empty spot of the canvas,
+/// to offer creating an input or output symbol there that is wired to the port.
+#[derive(Event, Debug, Clone)]
+pub struct OfferIoSymbol {
+    pub viewport: Entity,
+    pub circuit: CircuitID,
+    pub port: Entity,
+    pub position: Vec2,
+    /// The kind that fits the direction of the port: inputs are driven by an `In` symbol.
+    pub suggested: SymbolKind,
+}
+
+/// Creates an `In` or `Out` symbol at `position` and connects it to `port` with a new net.
+/// The symbol and the net are named after the port.
+#[derive(Event, Debug)]
+pub struct CreateIoSymbol {
+    pub circuit: CircuitID,
+    pub port: Entity,
+    pub kind: SymbolKind,
+    pub position: Vec2,
+}
+
+/// Whether the straight stub from `port` at `from` to `to` crosses a symbol
+/// other than the one the port belongs to.
+fn stub_crosses_symbol(
+    spatial_index: &SpatialIndex,
+    symbols: &Query<(), With<Symbol>>,
+    children: &Query<(Entity, Relations<Child>)>,
+    port: Entity,
+    from: Vec2,
+    to: Vec2,
+) -> bool {
+    let mut owners = Vec::new();
+    children
+        .traverse::<Up<Child>>([port])
+        .for_each(|&mut owner, _| owners.push(owner));
+
+    let mut crosses = false;
+    spatial_index.query_segment(from, to, |&entity| {
+        crosses |= symbols.contains(entity) && !owners.contains(&entity);
+    });
+    crosses
+}
+
+/// Dragging from an unconnected port draws a stub wire. Releasing it on an empty spot
+/// offers to create an input or output symbol there.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn stub_from_port(
+    trigger: Trigger<DragEvent>,
+    mut viewports: Query<(&HoveredEntity, &mut PortStub, Has<MouseMoving>)>,
+    active_tool: Res<ActiveTool>,
+    ports: Query<(&GlobalTransform, &PortDirection), With<Port>>,
+    endpoint_connections: EndpointConnections,
+    spatial_indices: Query<&SpatialIndex, With<Circuit>>,
+    symbols: Query<(), With<Symbol>>,
+    children: Query<(Entity, Relations<Child>)>,
+    mut offer_events: EventWriter<OfferIoSymbol>,
+) {
+    let event = trigger.event();
+    let viewport = trigger.entity();
+
+    if event.button != PointerButton::Primary {
+        return;
+    }
+
+    let Ok((hovered_entity, mut port_stub, moving)) = viewports.get_mut(viewport) else {
+        return;
+    };
+
+    let crosses_symbol = |port: Entity, from: Vec2, to: Vec2| {
+        spatial_indices
+            .get(event.circuit.0)
+            .is_ok_and(|spatial_index| {
+                stub_crosses_symbol(spatial_index, &symbols, &children, port, from, to)
+            })
+    };
+
+    match event.drag_type {
+        DragType::Start => {
+            if (*active_tool != ActiveTool::Select) || moving {
+                return;
+            }
+            let Some(port) = hovered_entity.0 else {
+                return;
+            };
+            let Ok((transform, _)) = ports.get(port) else {
+                return;
+            };
+            if endpoint_connections.endpoint_at_port(port).is_none() {
+                let from = transform.translation;
+                let crosses = crosses_symbol(port, from, event.pos);
+                port_stub.0 = Some((port, from, event.pos, crosses));
+            }
+        }
+        DragType::Dragging => {
+            if let Some((port, from, current, crosses)) = &mut port_stub.0 {
+                *current = event.pos;
+                *crosses = crosses_symbol(*port, *from, event.pos);
+            }
+        }
+        DragType::End => {
+            let Some((port, _, _, _)) = port_stub.0.take() else {
+                return;
+            };
+
+            // releasing on another entity is left to wire drawing
+            if hovered_entity.0.is_some() {
+                return;
+            }
+
+            let is_input = ports
+                .get(port)
+                .is_ok_and(|(_, &direction)| direction == PortDirection::Input);
+            offer_events.send(OfferIoSymbol {
+                viewport,
+                circuit: event.circuit,
+                port,
+                position: event.pos,
+                suggested: if is_input {
+                    SymbolKind::In
+                } else {
+                    SymbolKind::Out
+                },
+            });
+        }
+    }
+}
+
+type StubPortQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Name,
+        Option<&'static BitWidth>,
+        Option<&'static NetID>,
+    ),
+    With<Port>,
+>;
+
+pub(crate) fn create_io_symbol(
+    trigger: Trigger<CreateIoSymbol>,
+    mut commands: Commands,
+    symbol_registry: Res<SymbolRegistry>,
+    ports: StubPortQuery,
+    children: Query<(Entity, Relations<Child>)>,
+    designators: DesignatorQuery,
+    mut selection: ResMut<SelectionSet>,
+) {
+    let event = trigger.event();
+    if !matches!(event.kind, SymbolKind::In | SymbolKind::Out) {
+        return;
+    }
+
+    let Ok((name, bit_width, net_id)) = ports.get(event.port) else {
+        return;
+    };
+    if net_id.is_some() {
+        // the port was connected in the meantime
+        return;
+    }
+    let bit_width = bit_width
+        .copied()
+        .unwrap_or(BitWidth(std::num::NonZeroU8::MIN));
+
+    let designator_number = next_designator_number(
+        &symbol_registry,
+        &children,
+        &designators,
+        event.circuit,
+        event.kind,
+    );
+
+    let mut builder = symbol_registry.get(event.kind);
+    builder
+        .name(name.0.clone())
+        .position(event.position)
+        .designator_number(designator_number)
+        .bit_width(bit_width);
+    let symbol = builder.build(&mut commands, event.circuit.0);
+    let Some(symbol_port) = builder.ports().first().map(|port| port.id) else {
+        return;
+    };
+
+    let net = commands
+        .spawn(NetBundle {
+            net: Net,
+            name: Name(name.0.clone()),
+            bit_width,
+            visibility: VisibilityBundle::default(),
+        })
+        .set::<Child>(event.circuit.0)
+        .id();
+    spawn_port_endpoint(&mut commands, net, event.port);
+    spawn_port_endpoint(&mut commands, net, symbol_port);
+    reroute_net(&mut commands, event.circuit, net);
+
+    selection.select_only(symbol);
+}
@@ -1,5 +1,6 @@
 mod states;
 use states::*;
+pub use states::{AlignmentGuide, AlignmentGuides, CursorPosition, PortStub, SelectionBox};
 
 mod events;
 pub use events::*;
@@ -7,7 +8,62 @@ pub use events::*;
 mod systems;
 use systems::*;
 
+mod tools;
+pub use tools::*;
+
+mod selection;
+pub use selection::*;
+
+mod nets;
+pub use nets::*;
+
+mod waypoints;
+
+mod properties;
+pub use properties::*;
+
+mod subcircuit;
+pub use subcircuit::{CreateChip, CreateSubCircuit, EnterSubCircuit};
+
+mod replace_kind;
+pub use replace_kind::{ReplaceSymbolKind, SetSymbolParameters};
+
+mod groups;
+pub use groups::{GroupSelection, UngroupSelection};
+
+mod cross_probe;
+
+mod annotations;
+pub use annotations::{EditAnnotation, SetAnnotation};
+
+mod graphics;
+
+mod wires;
+
+mod io_stub;
+pub use io_stub::{CreateIoSymbol, OfferIoSymbol};
+
+mod erc;
+pub use erc::{ApplyQuickFix, ErcError, ErcReport, ErcWarning, Finding, QuickFix, Rule, Severity};
+
+mod net_naming;
+pub use net_naming::NetNamePattern;
+
+mod designators;
+pub use designators::RenumberDesignators;
+
+mod modified;
+
+mod changelog;
+pub use changelog::{Changelog, CircuitChangelog, Edit, EditKind};
+
+mod stats;
+pub use stats::CircuitStats;
+
 mod spatial_index;
+pub use spatial_index::{
+    GridHandle, GridIndex, SpatialIndex, SpatialIndexBackend, SpatialIndexBatch,
+};
 
 #[derive(Clone, Debug, Default)]
 pub struct UxPlugin;
@@ -17,16 +73,112 @@ impl bevy_app::Plugin for UxPlugin {
         use bevy_ecs::prelude::*;
 
         app.register_type::<HoveredEntity>()
+            .register_type::<HoverCandidates>()
+            .register_type::<CursorPosition>()
+            .register_type::<AlignmentGuides>()
+            .register_type::<SelectionBox>()
+            .register_type::<PortStub>()
             .register_type::<EntityOffset>()
             .register_type::<MouseState>()
             .register_type::<MouseIdle>()
-            .register_type::<MouseMoving>();
+            .register_type::<MouseMoving>()
+            .register_type::<ReconnectingEndpoint>()
+            .register_type::<ActiveTool>()
+            .register_type::<PlacementKind>()
+            .register_type::<GraphicPlacementKind>()
+            .register_type::<GridSize>()
+            .register_type::<NetNamePattern>()
+            .register_type::<SelectionSet>()
+            .register_type::<ErcWarning>()
+            .register_type::<ErcError>();
+
+        app.init_resource::<ActiveTool>()
+            .init_resource::<PlacementKind>()
+            .init_resource::<GraphicPlacementKind>()
+            .init_resource::<GridSize>()
+            .init_resource::<NetNamePattern>()
+            .init_resource::<SelectionSet>()
+            .init_resource::<ErcReport>();
 
         app.add_event::<DragEvent>();
         app.add_event::<ClickEvent>();
         app.add_event::<HoverEvent>();
         app.add_event::<MoveEntity>();
+        app.add_event::<RotateSelection>();
+        app.add_event::<MirrorSelection>();
+        app.add_event::<NudgeSelection>();
+        app.add_event::<EndNudge>();
+        app.add_event::<SelectionMoved>();
+        app.init_resource::<NudgeBurst>();
+        app.add_event::<EditSymbolProperties>();
+        app.add_event::<SetSymbolProperties>();
+        app.add_event::<MergeNets>();
+        app.add_event::<RemoveSegment>();
+        app.add_event::<RenameNet>();
+        app.add_event::<SetEndpointBits>();
+        app.add_event::<EnterSubCircuit>();
+        app.add_event::<SelectionChanged>();
+        app.add_event::<SetLocked>();
+        app.add_event::<SetDeMorgan>();
+        app.add_event::<GroupSelection>();
+        app.add_event::<UngroupSelection>();
+        app.add_event::<EditAnnotation>();
+        app.add_event::<SetAnnotation>();
+        app.add_event::<OfferIoSymbol>();
+        app.add_event::<ApplyQuickFix>();
         app.observe(on_add_viewport_augment_with_fsm);
+        app.observe(subcircuit::create_sub_circuit);
+        app.observe(subcircuit::create_chip);
+        app.observe(place_symbol);
+        app.observe(replace_kind::replace_symbol_kind);
+        app.observe(replace_kind::set_symbol_parameters);
+        app.observe(io_stub::create_io_symbol);
+        app.observe(designators::renumber_designators);
+
+        app.observe(modified::on_remove_mark_modified::<digilogic_core::components::Symbol>);
+        app.observe(modified::on_remove_mark_modified::<digilogic_core::components::Net>);
+        app.observe(modified::on_remove_mark_modified::<digilogic_core::components::Endpoint>);
+        app.observe(modified::on_remove_mark_modified::<digilogic_core::components::Waypoint>);
+        app.observe(modified::on_remove_mark_modified::<digilogic_core::components::Annotation>);
+        app.observe(modified::on_remove_mark_modified::<digilogic_core::components::Graphic>);
+        app.add_systems(
+            bevy_app::Last,
+            (
+                modified::mark_modified_circuits,
+                modified::clear_modified_on_load,
+                modified::clear_modified_on_save,
+            )
+                .chain(),
+        );
+
+        app.init_resource::<Changelog>();
+        app.observe(changelog::on_add_circuit_changelog);
+        app.observe(changelog::on_remove_circuit_changelog);
+        app.observe(changelog::on_remove_logged_record_despawn);
+        app.add_systems(
+            bevy_app::Last,
+            (
+                changelog::record_spawns,
+                changelog::record_transform_changes,
+                changelog::record_changes::<digilogic_core::components::Name>,
+                changelog::record_changes::<digilogic_core::components::DesignatorPrefix>,
+                changelog::record_changes::<digilogic_core::components::DesignatorNumber>,
+                changelog::record_changes::<digilogic_core::components::BitWidth>,
+                changelog::record_changes::<digilogic_core::components::Bits>,
+                changelog::record_changes::<digilogic_core::components::PortID>,
+                changelog::record_changes::<digilogic_core::components::Annotation>,
+                changelog::record_changes::<digilogic_core::components::Graphic>,
+                changelog::record_changes::<digilogic_core::components::Locked>,
+                changelog::record_changes::<digilogic_core::components::Attributes>,
+                changelog::clear_changelog_on_load,
+            )
+                .chain(),
+        );
+
+        app.observe(stats::inject_circuit_stats);
+        app.observe(stats::on_set_child_count);
+        app.observe(stats::on_remove_counted);
+        app.add_systems(bevy_app::Last, stats::update_circuit_extents);
 
         app.observe(spatial_index::inject_spatial_index);
         app.add_systems(bevy_app::PreUpdate, spatial_index::update_spatial_index);
@@ -34,8 +186,69 @@ impl bevy_app::Plugin for UxPlugin {
             bevy_app::PreUpdate,
             spatial_index::update_spatial_index_on_routing.after(digilogic_routing::RoutingSet),
         );
+        app.add_systems(
+            bevy_app::PreUpdate,
+            (update_hovered_entities, hover_nets)
+                .chain()
+                .after(spatial_index::update_spatial_index)
+                .after(spatial_index::update_spatial_index_on_routing),
+        );
         app.observe(spatial_index::on_remove_bounding_box_update_spatial_index);
         app.observe(spatial_index::on_remove_net_update_spatial_index);
-        app.add_systems(bevy_app::PostUpdate, move_entities_with_snap);
+        app.add_systems(
+            bevy_app::PostUpdate,
+            (
+                move_entities_with_snap,
+                rotate_selection,
+                mirror_selection,
+                nudge_selection,
+                set_symbol_properties,
+                (
+                    annotations::set_annotations,
+                    annotations::update_annotation_bounds,
+                )
+                    .chain(),
+                graphics::update_graphic_bounds,
+            )
+                .before(digilogic_core::transform::TransformSet),
+        );
+        app.add_systems(
+            bevy_app::PostUpdate,
+            clear_alignment_guides.after(move_entities_with_snap),
+        );
+        app.add_systems(
+            bevy_app::PostUpdate,
+            (sync_selected, cross_probe::cross_probe).chain(),
+        );
+        app.add_systems(bevy_app::PostUpdate, (merge_nets, remove_segments).chain());
+        app.add_systems(
+            bevy_app::PostUpdate,
+            (rename_nets, set_endpoint_bits, set_locked, set_de_morgan),
+        );
+        app.add_systems(
+            bevy_app::PostUpdate,
+            net_naming::name_unnamed_nets.after(rename_nets),
+        );
+        app.add_systems(
+            bevy_app::PostUpdate,
+            erc::apply_quick_fixes.before(set_endpoint_bits),
+        );
+        app.add_systems(
+            bevy_app::PostUpdate,
+            (
+                (
+                    erc::check_floating_inputs,
+                    erc::check_conflicting_drivers,
+                    erc::check_bit_widths,
+                ),
+                erc::update_erc_markers,
+            )
+                .chain()
+                .after(digilogic_core::connectivity::ConnectivitySet),
+        );
+        app.add_systems(
+            bevy_app::PostUpdate,
+            (groups::group_selection, groups::ungroup_selection).before(sync_selected),
+        );
     }
 }
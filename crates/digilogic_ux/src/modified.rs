@@ -0,0 +1,107 @@
+use aery::prelude::*;
+use bevy_ecs::prelude::*;
+use digilogic_core::components::*;
+use digilogic_core::events::{CircuitLoadedEvent, SavedEvent};
+use digilogic_core::transform::Transform;
+use digilogic_core::HashSet;
+
+type EditedQuery<'w, 's> = Query<
+    'w,
+    's,
+    Entity,
+    Or<(
+        Changed<Transform>,
+        Changed<Name>,
+        Changed<DesignatorPrefix>,
+        Changed<DesignatorNumber>,
+        Changed<BitWidth>,
+        Changed<Bits>,
+        Changed<PortID>,
+        Changed<Annotation>,
+        Changed<Graphic>,
+        Changed<Locked>,
+    )>,
+>;
+
+/// The circuits `entity` belongs to, including itself if it is a circuit.
+fn owning_circuits(
+    entity: Entity,
+    circuits: &Query<Has<Modified>, With<Circuit>>,
+    children: &Query<(Entity, Relations<Child>)>,
+    owners: &mut HashSet<Entity>,
+) {
+    if circuits.contains(entity) {
+        owners.insert(entity);
+    }
+    children
+        .traverse::<Up<Child>>([entity])
+        .for_each(|&mut parent, _| {
+            if circuits.contains(parent) {
+                owners.insert(parent);
+            }
+        });
+}
+
+/// Marks circuits whose contents have been edited as [`Modified`].
+pub(crate) fn mark_modified_circuits(
+    mut commands: Commands,
+    edited: EditedQuery,
+    circuits: Query<Has<Modified>, With<Circuit>>,
+    children: Query<(Entity, Relations<Child>)>,
+) {
+    let mut owners = HashSet::default();
+    for entity in edited.iter() {
+        owning_circuits(entity, &circuits, &children, &mut owners);
+    }
+
+    for circuit in owners {
+        if circuits.get(circuit) == Ok(false) {
+            commands.entity(circuit).insert(Modified);
+        }
+    }
+}
+
+/// Marks the circuit an entity is removed from as [`Modified`].
+pub(crate) fn on_remove_mark_modified<C: Component>(
+    trigger: Trigger<OnRemove, C>,
+    mut commands: Commands,
+    circuits: Query<Has<Modified>, With<Circuit>>,
+    children: Query<(Entity, Relations<Child>)>,
+) {
+    let mut owners = HashSet::default();
+    owning_circuits(trigger.entity(), &circuits, &children, &mut owners);
+
+    for circuit in owners {
+        if circuits.get(circuit) == Ok(false) {
+            // the circuit itself may be despawned along with its contents
+            commands.entity(circuit).try_insert(Modified);
+        }
+    }
+}
+
+/// Freshly loaded circuits start out unmodified,
+/// regardless of the changes spawning their contents caused.
+pub(crate) fn clear_modified_on_load(
+    mut commands: Commands,
+    mut circuit_loaded_events: EventReader<CircuitLoadedEvent>,
+    circuits: Query<(), With<Circuit>>,
+) {
+    for event in circuit_loaded_events.read() {
+        if circuits.contains(event.circuit.0) {
+            commands.entity(event.circuit.0).remove::<Modified>();
+        }
+    }
+}
+
+/// Saved circuits match their files again.
+pub(crate) fn clear_modified_on_save(
+    mut commands: Commands,
+    mut saved_events: EventReader<SavedEvent>,
+    circuits: Query<(), With<Circuit>>,
+) {
+    for event in saved_events.read() {
+        if circuits.contains(event.circuit.0) {
+            commands.entity(event.circuit.0).remove::<Modified>();
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use aery::prelude::*;
+use bevy_ecs::prelude::*;
+use bevy_ecs::system::lifetimeless::Read;
+use bevy_reflect::Reflect;
+use digilogic_core::components::*;
+use digilogic_core::{HashMap, HashSet, SharedStr};
+
+/// The pattern names of unnamed nets are generated from.
+/// `{n}` is replaced by the lowest number that gives a name not used in the circuit yet,
+/// `{driver}` by the output port driving the net, like `U3.Q`.
+/// Nets without a driver are named after [`NetNamePattern::DEFAULT`] instead.
+#[derive(Debug, Clone, PartialEq, Eq, Resource, Reflect)]
+#[reflect(Resource)]
+pub struct NetNamePattern(pub SharedStr);
+
+impl NetNamePattern {
+    pub const DEFAULT: &'static str = "N${n}";
+}
+
+impl Default for NetNamePattern {
+    fn default() -> Self {
+        Self(SharedStr::new_static(Self::DEFAULT))
+    }
+}
+
+/// Generates a name from `pattern` that is not in `taken`.
+/// Existing names are never changed, so numbers freed by deleted nets are reused
+/// instead of renumbering the nets after them.
+fn generate_net_name(pattern: &str, driver: Option<&str>, taken: &HashSet<String>) -> String {
+    let pattern = if pattern.trim().is_empty() || (pattern.contains("{driver}") && driver.is_none())
+    {
+        NetNamePattern::DEFAULT
+    } else {
+        pattern
+    };
+
+    let expand = |n: u32| {
+        pattern
+            .replace("{driver}", driver.unwrap_or_default())
+            .replace("{n}", &n.to_string())
+    };
+
+    if pattern.contains("{n}") {
+        return (1..)
+            .map(expand)
+            .find(|name| !taken.contains(name))
+            .expect("ran out of net numbers");
+    }
+
+    let base = expand(0);
+    if !taken.contains(&base) {
+        return base;
+    }
+    (2..)
+        .map(|suffix| format!("{base}_{suffix}"))
+        .find(|name| !taken.contains(name))
+        .expect("ran out of net numbers")
+}
+
+type NamingNetQuery<'w, 's> =
+    Query<'w, 's, (Entity, Ref<'static, Name>, Relations<Child>), With<Net>>;
+
+type DriverPortQuery<'w, 's> =
+    Query<'w, 's, (Read<Name>, Read<PortDirection>, Relations<Child>), With<Port>>;
+
+/// Names nets that are created without a name, or lose it, after [`NetNamePattern`].
+pub(crate) fn name_unnamed_nets(
+    mut commands: Commands,
+    pattern: Res<NetNamePattern>,
+    circuits: Query<(Entity, Relations<Child>), With<Circuit>>,
+    nets: NamingNetQuery,
+    endpoints: Query<Read<PortID>, With<Endpoint>>,
+    ports: DriverPortQuery,
+    symbols: Query<(Read<DesignatorPrefix>, Read<DesignatorNumber>), With<Symbol>>,
+) {
+    let unnamed = nets
+        .iter()
+        .filter(|(_, name, _)| name.is_changed() && name.0.is_empty())
+        .map(|(net, _, _)| net)
+        .collect::<Vec<_>>();
+    if unnamed.is_empty() {
+        return;
+    }
+
+    let mut taken_by_circuit = HashMap::<Entity, HashSet<String>>::default();
+    for net in unnamed {
+        let Ok((_, _, edges)) = nets.get(net) else {
+            continue;
+        };
+
+        let mut circuit = None;
+        edges
+            .join::<Up<Child>>(&circuits)
+            .for_each(|(entity, _)| circuit = Some(entity));
+        let circuit = circuit.unwrap_or(Entity::PLACEHOLDER);
+
+        let taken = taken_by_circuit.entry(circuit).or_insert_with(|| {
+            let mut taken = HashSet::default();
+            if let Ok((_, circuit_edges)) = circuits.get(circuit) {
+                circuit_edges.join::<Child>(&nets).for_each(|(_, name, _)| {
+                    taken.insert(name.0.to_string());
+                });
+            }
+            taken
+        });
+
+        let mut driver = None;
+        edges.join::<Child>(&endpoints).for_each(|port_id| {
+            let Ok((port_name, &direction, port_edges)) = ports.get(port_id.0) else {
+                return;
+            };
+            if direction != PortDirection::Output {
+                return;
+            }
+            port_edges
+                .join::<Up<Child>>(&symbols)
+                .for_each(|(prefix, number)| {
+                    driver = Some(format!("{}{}.{}", prefix.0, number.0, port_name.0));
+                });
+        });
+
+        let name = generate_net_name(&pattern.0, driver.as_deref(), taken);
+        taken.insert(name.clone());
+        commands.entity(net).insert(Name(name.into()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn taken(names: &[&str]) -> HashSet<String> {
+        names.iter().map(|&name| name.to_owned()).collect()
+    }
+
+    #[test]
+    fn generated_names() {
+        let none = taken(&[]);
+        assert_eq!(generate_net_name("N${n}", None, &none), "N$1");
+
+        // gaps left by deleted nets are filled, existing names stay as they are
+        let some = taken(&["N$1", "N$3"]);
+        assert_eq!(generate_net_name("N${n}", None, &some), "N$2");
+
+        assert_eq!(generate_net_name("{driver}", Some("U3.Q"), &none), "U3.Q");
+        assert_eq!(
+            generate_net_name("{driver}", Some("U3.Q"), &taken(&["U3.Q"])),
+            "U3.Q_2"
+        );
+        assert_eq!(generate_net_name("{driver}", None, &some), "N$2");
+        assert_eq!(generate_net_name("", None, &none), "N$1");
+    }
+}
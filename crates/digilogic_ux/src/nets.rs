@@ -0,0 +1,427 @@
+use crate::spatial_index::SpatialIndex;
+use crate::{ActiveTool, ClickEvent, HoveredEntity, PointerButton};
+use aery::prelude::*;
+use bevy_ecs::prelude::*;
+use bevy_ecs::system::SystemParam;
+use digilogic_core::bundles::NetBundle;
+use digilogic_core::components::*;
+use digilogic_core::connections::{connect_endpoint, disconnect_endpoint, move_endpoint};
+use digilogic_core::transform::{GlobalTransform, Vec2};
+use digilogic_core::visibility::VisibilityBundle;
+use digilogic_core::HashMap;
+use digilogic_core::{Fixed, SharedStr};
+use digilogic_routing::connectivity::{connected_components, endpoint_vertex};
+use digilogic_routing::{reroute_net, Vertex, VertexKind, Vertices};
+
+/// Merges the `source` net into the `target` net, for example when a wire connects the two.
+/// The endpoints of `source` are moved to `target` and `source` is despawned.
+#[derive(Event, Debug)]
+pub struct MergeNets {
+    pub circuit: CircuitID,
+    pub target: Entity,
+    pub source: Entity,
+}
+
+/// Deletes the segment of a net's wires that starts at the vertex `segment`.
+/// If this disconnects the net, it is split into one net per connected part.
+#[derive(Event, Debug)]
+pub struct RemoveSegment {
+    pub circuit: CircuitID,
+    pub net: Entity,
+    pub segment: u32,
+}
+
+/// Gives a net a new name. Names are not required to be unique.
+#[derive(Event, Debug)]
+pub struct RenameNet {
+    pub net: Entity,
+    pub name: SharedStr,
+}
+
+/// Assigns the bits of its net an endpoint carries, or makes it carry all of them if `bits` is `None`.
+#[derive(Event, Debug)]
+pub struct SetEndpointBits {
+    pub endpoint: Entity,
+    pub bits: Option<Bits>,
+}
+
+/// The point on the segment from `start` to `end` closest to `position`.
+/// Wire segments are always horizontal or vertical.
+fn closest_point_on_segment(start: Vec2, end: Vec2, position: Vec2) -> Vec2 {
+    position.clamp(start.min(end), start.max(end))
+}
+
+/// Finds the segment of a routed net closest to `position`.
+/// Returns the index of the vertex the segment starts at and the closest point on it.
+pub(crate) fn closest_segment(vertices: &[Vertex], position: Vec2) -> Option<(usize, Vec2)> {
+    let mut closest: Option<(usize, Vec2, Fixed)> = None;
+    for (index, pair) in vertices.windows(2).enumerate() {
+        if matches!(pair[0].kind, VertexKind::WireEnd { .. }) {
+            continue;
+        }
+
+        let point = closest_point_on_segment(pair[0].position, pair[1].position, position);
+        let dist = point.manhatten_distance_to(position);
+        if closest.map_or(true, |(_, _, closest_dist)| dist < closest_dist) {
+            closest = Some((index, point, dist));
+        }
+    }
+
+    closest.map(|(index, point, _)| (index, point))
+}
+
+type ConnectionEndpointQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        Option<&'static PortID>,
+        &'static GlobalTransform,
+        Relations<Child>,
+    ),
+    With<Endpoint>,
+>;
+
+/// Connects endpoints to ports and disconnects them again.
+#[derive(SystemParam)]
+pub(crate) struct EndpointConnections<'w, 's> {
+    endpoints: ConnectionEndpointQuery<'w, 's>,
+    nets: Query<'w, 's, Entity, With<Net>>,
+    ports: Query<'w, 's, (), With<Port>>,
+    spatial_indices: Query<'w, 's, &'static SpatialIndex, With<Circuit>>,
+    merge_events: EventWriter<'w, MergeNets>,
+}
+
+impl EndpointConnections<'_, '_> {
+    /// The endpoint connected to a port, if any.
+    pub(crate) fn endpoint_at_port(&self, port: Entity) -> Option<Entity> {
+        self.endpoints
+            .iter()
+            .find(|(_, port_id, _, _)| port_id.is_some_and(|port_id| port_id.0 == port))
+            .map(|(endpoint, _, _, _)| endpoint)
+    }
+
+    /// The net an endpoint is part of.
+    pub(crate) fn net_of(&self, endpoint: Entity) -> Option<Entity> {
+        let (_, _, _, edges) = self.endpoints.get(endpoint).ok()?;
+        let mut net = None;
+        edges
+            .join::<Up<Child>>(&self.nets)
+            .for_each(|entity| net = Some(entity));
+        net
+    }
+
+    /// Whether the entity is an endpoint.
+    pub(crate) fn is_endpoint(&self, entity: Entity) -> bool {
+        self.endpoints.contains(entity)
+    }
+
+    /// Merges the `source` net into the `target` net.
+    pub(crate) fn merge(&mut self, circuit: CircuitID, target: Entity, source: Entity) {
+        self.merge_events.send(MergeNets {
+            circuit,
+            target,
+            source,
+        });
+    }
+
+    /// Detaches an endpoint from its port, leaving it where it is.
+    /// Returns the position of the endpoint.
+    pub(crate) fn disconnect(&self, commands: &mut Commands, endpoint: Entity) -> Option<Vec2> {
+        let (_, port_id, transform, _) = self.endpoints.get(endpoint).ok()?;
+
+        if let Some(port_id) = port_id {
+            disconnect_endpoint(commands, endpoint, port_id.0, transform.translation);
+        }
+
+        Some(transform.translation)
+    }
+
+    /// Connects an endpoint to the port at `position`, if there is one.
+    /// If the port is already connected to another net, that net is merged into the endpoint's
+    /// net and the endpoint is dropped in favor of the one already connected to the port.
+    pub(crate) fn connect_at(
+        &mut self,
+        commands: &mut Commands,
+        circuit: CircuitID,
+        endpoint: Entity,
+        position: Vec2,
+    ) {
+        let Ok(spatial_index) = self.spatial_indices.get(circuit.0) else {
+            return;
+        };
+
+        let Some(port) = spatial_index.nearest(position, Fixed::EPSILON, |entity| {
+            self.ports.contains(entity)
+        }) else {
+            return;
+        };
+
+        let Some(net) = self.net_of(endpoint) else {
+            return;
+        };
+
+        if let Some(other_endpoint) = self.endpoint_at_port(port) {
+            // ports only connect to a single endpoint, so the endpoint already there takes over
+            match self.net_of(other_endpoint) {
+                Some(other_net) if other_net != net => {
+                    commands.entity(endpoint).despawn();
+                    self.merge_events.send(MergeNets {
+                        circuit,
+                        target: net,
+                        source: other_net,
+                    });
+                }
+                _ => {
+                    bevy_log::debug!(
+                        "port {port} is already connected to endpoint {other_endpoint} of the same net"
+                    );
+                }
+            }
+            return;
+        }
+
+        connect_endpoint(commands, endpoint, port, net);
+        commands.entity(endpoint).remove::<Disconnected>();
+
+        reroute_net(commands, circuit, net);
+    }
+}
+
+type NetQuery<'w, 's> =
+    Query<'w, 's, (&'static mut Name, &'static mut BitWidth, Relations<Child>), With<Net>>;
+
+pub(crate) fn merge_nets(
+    mut commands: Commands,
+    mut events: EventReader<MergeNets>,
+    mut nets: NetQuery,
+    endpoints: Query<(Entity, Option<&PortID>), With<Endpoint>>,
+    mut merged: Local<HashMap<Entity, Entity>>,
+) {
+    merged.clear();
+
+    for event in events.read() {
+        // nets merged earlier in this frame are only despawned once the commands are applied
+        let resolve = |mut net: Entity| {
+            while let Some(&into) = merged.get(&net) {
+                net = into;
+            }
+            net
+        };
+
+        let target = resolve(event.target);
+        let source = resolve(event.source);
+        if target == source {
+            continue;
+        }
+
+        let Ok((source_name, &source_bit_width, source_edges)) = nets.get(source) else {
+            continue;
+        };
+        let source_name = source_name.clone();
+
+        let mut moved_endpoints = Vec::new();
+        source_edges
+            .join::<Child>(&endpoints)
+            .for_each(|(endpoint, port_id)| moved_endpoints.push((endpoint, port_id.copied())));
+
+        let Ok((mut target_name, mut target_bit_width, _)) = nets.get_mut(target) else {
+            continue;
+        };
+
+        // keep the name of the target unless only the source has one
+        if target_name.is_empty() && !source_name.is_empty() {
+            *target_name = source_name;
+        }
+
+        if *target_bit_width != source_bit_width {
+            bevy_log::warn!(
+                "merging nets with bit widths {} and {}",
+                target_bit_width.0,
+                source_bit_width.0,
+            );
+            target_bit_width.0 = target_bit_width.0.max(source_bit_width.0);
+        }
+
+        for (endpoint, port_id) in moved_endpoints {
+            move_endpoint(
+                &mut commands,
+                endpoint,
+                port_id.map(|port_id| port_id.0),
+                target,
+            );
+        }
+
+        commands.entity(source).despawn();
+        merged.insert(source, target);
+        reroute_net(&mut commands, event.circuit, target);
+    }
+}
+
+type SegmentNetQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Name,
+        &'static BitWidth,
+        &'static Vertices,
+        Relations<Child>,
+        Has<Locked>,
+    ),
+    With<Net>,
+>;
+
+pub(crate) fn remove_segments(
+    mut commands: Commands,
+    mut events: EventReader<RemoveSegment>,
+    nets: SegmentNetQuery,
+    endpoints: Query<(Entity, &GlobalTransform, Option<&PortID>), With<Endpoint>>,
+) {
+    for event in events.read() {
+        let Ok((name, &bit_width, vertices, edges, locked)) = nets.get(event.net) else {
+            continue;
+        };
+        if locked {
+            continue;
+        }
+
+        let components = connected_components(vertices, Some(event.segment));
+
+        // group the endpoints by the part of the wires they are attached to
+        let mut groups: Vec<Vec<(Entity, Option<PortID>)>> = Vec::new();
+        edges
+            .join::<Child>(&endpoints)
+            .for_each(|(endpoint, transform, port_id)| {
+                let component = endpoint_vertex(vertices, transform.translation)
+                    .map(|vertex| components[vertex as usize])
+                    .unwrap_or(0) as usize;
+
+                if groups.len() <= component {
+                    groups.resize_with(component + 1, Vec::new);
+                }
+                groups[component].push((endpoint, port_id.copied()));
+            });
+
+        // the largest part keeps the original net
+        groups.sort_by_key(|group| std::cmp::Reverse(group.len()));
+        let keeps_net = groups.first().is_some_and(|group| group.len() >= 2);
+
+        let mut split_count = 0;
+        for (index, group) in groups.into_iter().enumerate() {
+            if group.len() < 2 {
+                // a single endpoint doesn't make a wire anymore
+                for (endpoint, port_id) in group {
+                    commands.entity(endpoint).despawn();
+                    if let Some(port_id) = port_id {
+                        commands.entity(port_id.0).remove::<NetID>();
+                    }
+                }
+                continue;
+            }
+
+            let net = if index == 0 {
+                event.net
+            } else {
+                split_count += 1;
+                commands
+                    .spawn(NetBundle {
+                        net: Net,
+                        name: Name(format!("{}_{}", name.0, split_count).into()),
+                        bit_width,
+                        visibility: VisibilityBundle::default(),
+                    })
+                    .set::<Child>(event.circuit.0)
+                    .id()
+            };
+
+            for (endpoint, port_id) in group {
+                if net != event.net {
+                    move_endpoint(
+                        &mut commands,
+                        endpoint,
+                        port_id.map(|port_id| port_id.0),
+                        net,
+                    );
+                }
+            }
+
+            reroute_net(&mut commands, event.circuit, net);
+        }
+
+        if !keeps_net {
+            commands.entity(event.net).despawn();
+        }
+    }
+}
+
+/// Whether a click rips the hovered wire segment out of its net.
+/// Alt alone already picks the next entity under the cursor, so ripping needs Ctrl as well.
+pub(crate) fn is_rip_click(event: &ClickEvent) -> bool {
+    (event.button == PointerButton::Primary) && event.modifiers.alt && event.modifiers.command
+}
+
+/// Ctrl+Alt+clicking a wire deletes the segment under the cursor.
+pub(crate) fn rip_segment_on_click(
+    trigger: Trigger<ClickEvent>,
+    hover_query: Query<&HoveredEntity>,
+    active_tool: Res<ActiveTool>,
+    nets: Query<&Vertices, With<Net>>,
+    mut remove_events: EventWriter<RemoveSegment>,
+) {
+    let event = trigger.event();
+    let viewport = trigger.entity();
+
+    if (*active_tool != ActiveTool::Select) || !is_rip_click(event) {
+        return;
+    }
+
+    let Some(net) = hover_query.get(viewport).ok().and_then(|hovered| hovered.0) else {
+        return;
+    };
+    let Ok(vertices) = nets.get(net) else {
+        return;
+    };
+
+    if let Some((segment, _)) = closest_segment(vertices, event.pos) {
+        remove_events.send(RemoveSegment {
+            circuit: event.circuit,
+            net,
+            segment: segment as u32,
+        });
+    }
+}
+
+pub(crate) fn rename_nets(
+    mut events: EventReader<RenameNet>,
+    mut nets: Query<&mut Name, With<Net>>,
+) {
+    for event in events.read() {
+        if let Ok(mut name) = nets.get_mut(event.net) {
+            if name.0 != event.name {
+                name.0 = event.name.clone();
+            }
+        }
+    }
+}
+
+pub(crate) fn set_endpoint_bits(
+    mut commands: Commands,
+    mut events: EventReader<SetEndpointBits>,
+    endpoints: Query<Option<&Bits>, With<Endpoint>>,
+) {
+    for event in events.read() {
+        let Ok(bits) = endpoints.get(event.endpoint) else {
+            continue;
+        };
+
+        match &event.bits {
+            Some(new_bits) if bits != Some(new_bits) => {
+                commands.entity(event.endpoint).insert(new_bits.clone());
+            }
+            None if bits.is_some() => {
+                commands.entity(event.endpoint).remove::<Bits>();
+            }
+            _ => (),
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use super::HoveredEntity;
+use crate::{ActiveTool, DoubleClickEvent, PointerButton, SetSymbolParameters};
+use bevy_ecs::prelude::*;
+use digilogic_core::components::*;
+use digilogic_core::transform::*;
+use digilogic_core::SharedStr;
+use std::num::NonZeroU8;
+
+/// The user editable properties of a symbol.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolProperties {
+    pub name: SharedStr,
+    pub designator_prefix: SharedStr,
+    pub designator_number: u32,
+    pub designator_suffix: SharedStr,
+    pub bit_width: NonZeroU8,
+    pub rotation: Rotation,
+    /// The values of the parameters the kind of the symbol declares.
+    pub parameters: Parameters,
+}
+
+type SymbolPropertiesQueryData = (
+    &'static Name,
+    &'static DesignatorPrefix,
+    &'static DesignatorNumber,
+    Option<&'static DesignatorSuffix>,
+    Option<&'static BitWidth>,
+    &'static Transform,
+    Option<&'static Parameters>,
+);
+
+impl SymbolProperties {
+    /// Reads the current properties of a symbol, or `None` if the entity is not a symbol.
+    pub fn read(symbols: &SymbolPropertiesQuery, symbol: Entity) -> Option<Self> {
+        let (name, prefix, number, suffix, bit_width, transform, parameters) =
+            symbols.get(symbol).ok()?;
+
+        Some(Self {
+            name: name.0.clone(),
+            designator_prefix: prefix.0.clone(),
+            designator_number: number.0,
+            designator_suffix: suffix.map(|suffix| suffix.0.clone()).unwrap_or_default(),
+            bit_width: bit_width
+                .map(|bit_width| bit_width.0)
+                .unwrap_or(NonZeroU8::MIN),
+            rotation: transform.rotation,
+            parameters: parameters.cloned().unwrap_or_default(),
+        })
+    }
+}
+
+/// The query needed by [`SymbolProperties::read`].
+pub type SymbolPropertiesQuery<'w, 's> = Query<'w, 's, SymbolPropertiesQueryData, With<Symbol>>;
+
+/// Double-clicking a symbol requests its properties dialog.
+/// Sub-circuit symbols are entered instead, see [`EnterSubCircuit`](crate::EnterSubCircuit).
+pub(crate) fn edit_symbol_on_double_click(
+    trigger: Trigger<DoubleClickEvent>,
+    hover_query: Query<&HoveredEntity>,
+    active_tool: Res<ActiveTool>,
+    symbols: Query<(), (With<Symbol>, Without<SubCircuit>)>,
+    mut edit_events: EventWriter<EditSymbolProperties>,
+) {
+    let event = trigger.event();
+    let viewport = trigger.entity();
+
+    if (*active_tool != ActiveTool::Select) || (event.button != PointerButton::Primary) {
+        return;
+    }
+
+    let Some(symbol) = hover_query.get(viewport).ok().and_then(|hovered| hovered.0) else {
+        return;
+    };
+    if symbols.contains(symbol) {
+        edit_events.send(EditSymbolProperties { symbol });
+    }
+}
+
+/// Requests the properties dialog of a symbol.
+#[derive(Event, Debug)]
+pub struct EditSymbolProperties {
+    pub symbol: Entity,
+}
+
+/// Replaces the properties of a symbol.
+/// Every edit made in the properties dialog is a single one of these events,
+/// applied within the same frame.
+#[derive(Event, Debug)]
+pub struct SetSymbolProperties {
+    pub symbol: Entity,
+    pub properties: SymbolProperties,
+}
+
+type EditableSymbolQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static mut Name,
+        &'static mut DesignatorPrefix,
+        &'static mut DesignatorNumber,
+        Option<&'static DesignatorSuffix>,
+        Option<&'static BitWidth>,
+        &'static mut Transform,
+        Option<&'static Parameters>,
+    ),
+    With<Symbol>,
+>;
+
+pub(crate) fn set_symbol_properties(
+    mut commands: Commands,
+    mut events: EventReader<SetSymbolProperties>,
+    mut symbols: EditableSymbolQuery,
+) {
+    for event in events.read() {
+        let Ok((mut name, mut prefix, mut number, suffix, bit_width, mut transform, parameters)) =
+            symbols.get_mut(event.symbol)
+        else {
+            continue;
+        };
+        let properties = &event.properties;
+
+        // only touch what changed, a changed transform reroutes the connected nets
+        if name.0 != properties.name {
+            name.0 = properties.name.clone();
+        }
+        if prefix.0 != properties.designator_prefix {
+            prefix.0 = properties.designator_prefix.clone();
+        }
+        if number.0 != properties.designator_number {
+            number.0 = properties.designator_number;
+        }
+        if transform.rotation != properties.rotation {
+            transform.rotation = properties.rotation;
+        }
+
+        let mut symbol = commands.entity(event.symbol);
+        let suffix = suffix.map(|suffix| suffix.0.as_ref()).unwrap_or_default();
+        if properties.designator_suffix.is_empty() {
+            symbol.remove::<DesignatorSuffix>();
+        } else if *properties.designator_suffix != *suffix {
+            symbol.insert(DesignatorSuffix(properties.designator_suffix.clone()));
+        }
+        if bit_width.map(|bit_width| bit_width.0) != Some(properties.bit_width) {
+            symbol.insert(BitWidth(properties.bit_width));
+        }
+
+        // parameters may change the ports of the symbol
+        if parameters.cloned().unwrap_or_default() != properties.parameters {
+            commands.trigger(SetSymbolParameters {
+                symbol: event.symbol,
+                parameters: properties.parameters.clone(),
+            });
+        }
+    }
+}
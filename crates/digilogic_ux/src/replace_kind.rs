@@ -0,0 +1,267 @@
+use aery::prelude::*;
+use bevy_ecs::prelude::*;
+use digilogic_core::components::*;
+use digilogic_core::connections::{connect_endpoint, disconnect_endpoint};
+use digilogic_core::symbol::{SymbolBuilder, SymbolRegistry};
+use digilogic_core::transform::GlobalTransform;
+use digilogic_core::SharedStr;
+use digilogic_routing::reroute_net;
+
+/// Changes the kind of a symbol in place, keeping its position, designator and connections.
+/// Connections are moved to the port of the new kind with the same name and direction,
+/// endpoints without such a port are left where they are and marked as [`Disconnected`].
+#[derive(Event, Debug)]
+pub struct ReplaceSymbolKind {
+    pub circuit: CircuitID,
+    pub symbol: Entity,
+    pub kind: SymbolKind,
+}
+
+/// Changes the parameters of a symbol, rebuilding its ports if they depend on them.
+/// Connections are kept the same way as by [`ReplaceSymbolKind`].
+#[derive(Event, Debug)]
+pub struct SetSymbolParameters {
+    pub symbol: Entity,
+    pub parameters: Parameters,
+}
+
+type ReplaceableSymbolQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        (
+            &'static SymbolKind,
+            &'static Name,
+            &'static DesignatorPrefix,
+            Option<&'static BitWidth>,
+            Option<&'static Parameters>,
+        ),
+        Relations<Child>,
+    ),
+    With<Symbol>,
+>;
+
+type ConnectedEndpointQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        (Entity, &'static PortID, &'static GlobalTransform),
+        Relations<Child>,
+    ),
+    With<Endpoint>,
+>;
+
+type PortQuery<'w, 's> = Query<'w, 's, (Entity, &'static Name, &'static PortDirection), With<Port>>;
+
+/// A port of a symbol, with its name and whether it is an output.
+type OldPort = (Entity, SharedStr, bool);
+
+/// Replaces `old_ports` of `symbol` with the ones `builder` spawns, moving each connection to the
+/// new port with the same name and direction. Returns the number of connections that could not
+/// be moved.
+fn replace_ports(
+    commands: &mut Commands,
+    circuit: CircuitID,
+    symbol: Entity,
+    old_ports: Vec<OldPort>,
+    builder: &mut SymbolBuilder,
+    endpoints: &ConnectedEndpointQuery,
+    nets: &Query<Entity, With<Net>>,
+) -> usize {
+    let new_ports = builder.build_ports(commands, symbol).to_vec();
+
+    let mut affected_nets = Vec::new();
+    let mut unmapped = 0;
+    for ((endpoint, port_id, transform), endpoint_edges) in endpoints.iter() {
+        let Some((old_port, port_name, output)) =
+            old_ports.iter().find(|(port, _, _)| *port == port_id.0)
+        else {
+            continue;
+        };
+
+        let mut net = None;
+        endpoint_edges
+            .join::<Up<Child>>(nets)
+            .for_each(|entity| net = Some(entity));
+        let Some(net) = net else {
+            continue;
+        };
+
+        let new_port = new_ports
+            .iter()
+            .find(|port| (port.name == *port_name) && (port.output == *output));
+        if let Some(new_port) = new_port {
+            connect_endpoint(commands, endpoint, new_port.id, net);
+        } else {
+            disconnect_endpoint(commands, endpoint, *old_port, transform.translation);
+            commands.entity(endpoint).insert(Disconnected);
+            unmapped += 1;
+        }
+
+        if !affected_nets.contains(&net) {
+            affected_nets.push(net);
+        }
+    }
+
+    for (port, _, _) in old_ports {
+        commands.entity(port).despawn();
+    }
+
+    for net in affected_nets {
+        reroute_net(commands, circuit, net);
+    }
+
+    unmapped
+}
+
+pub(crate) fn replace_symbol_kind(
+    trigger: Trigger<ReplaceSymbolKind>,
+    mut commands: Commands,
+    symbol_registry: Res<SymbolRegistry>,
+    symbols: ReplaceableSymbolQuery,
+    ports: PortQuery,
+    endpoints: ConnectedEndpointQuery,
+    nets: Query<Entity, With<Net>>,
+) {
+    let event = trigger.event();
+    let Ok(((&old_kind, name, prefix, bit_width, parameters), edges)) = symbols.get(event.symbol)
+    else {
+        return;
+    };
+    if old_kind == event.kind {
+        return;
+    }
+    let (Some(old_def), Some(new_def)) = (
+        symbol_registry.get_def(old_kind),
+        symbol_registry.get_def(event.kind),
+    ) else {
+        return;
+    };
+
+    let mut builder = symbol_registry.get(event.kind);
+    if let Some(&bit_width) = bit_width {
+        builder.bit_width(bit_width);
+    }
+    // parameters both kinds declare, like the number of inputs of a gate, are kept
+    for (name, value) in parameters.iter().flat_map(|parameters| parameters.iter()) {
+        builder.parameter(name.clone(), value.clone());
+    }
+    let mut old_ports = Vec::new();
+    edges
+        .join::<Child>(&ports)
+        .for_each(|(port, name, direction)| {
+            old_ports.push((port, name.0.clone(), direction.is_output()))
+        });
+    let unmapped = replace_ports(
+        &mut commands,
+        event.circuit,
+        event.symbol,
+        old_ports,
+        &mut builder,
+        &endpoints,
+        &nets,
+    );
+
+    let mut symbol_commands = commands.entity(event.symbol);
+    symbol_commands.insert((
+        event.kind,
+        new_def.shape(),
+        new_def.bounding_box_for(&builder.parameters()),
+    ));
+    if new_def.params().is_empty() {
+        symbol_commands.remove::<Parameters>();
+    } else {
+        symbol_commands.insert(builder.parameters());
+    }
+    // names and designators that were left at the defaults of the old kind follow the new one
+    if name.0 == *old_def.name() {
+        symbol_commands.insert(Name(new_def.name().clone()));
+    }
+    if prefix.0 == *old_def.designator_prefix() {
+        symbol_commands.insert(DesignatorPrefix(new_def.designator_prefix().clone()));
+    }
+    if event.kind == SymbolKind::In {
+        symbol_commands.insert(LogicState::from_bool(false));
+    } else if old_kind == SymbolKind::In {
+        symbol_commands.remove::<LogicState>();
+    }
+
+    if unmapped > 0 {
+        bevy_log::warn!(
+            "{unmapped} connection(s) of {} have no matching port on {} and were disconnected",
+            name.0,
+            new_def.name(),
+        );
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn set_symbol_parameters(
+    trigger: Trigger<SetSymbolParameters>,
+    mut commands: Commands,
+    symbol_registry: Res<SymbolRegistry>,
+    symbols: ReplaceableSymbolQuery,
+    circuits: Query<Entity, With<Circuit>>,
+    ports: PortQuery,
+    endpoints: ConnectedEndpointQuery,
+    nets: Query<Entity, With<Net>>,
+) {
+    let event = trigger.event();
+    let Ok(((&kind, name, _, bit_width, parameters), edges)) = symbols.get(event.symbol) else {
+        return;
+    };
+    let Some(def) = symbol_registry.get_def(kind) else {
+        return;
+    };
+
+    let old_parameters = def.resolve_parameters(parameters.unwrap_or(&Parameters::default()));
+    let new_parameters = def.resolve_parameters(&event.parameters);
+    if new_parameters == old_parameters {
+        return;
+    }
+
+    if def.ports_differ(&old_parameters, &new_parameters) {
+        let mut circuit = None;
+        edges
+            .join::<Up<Child>>(&circuits)
+            .for_each(|entity| circuit = Some(CircuitID(entity)));
+        let Some(circuit) = circuit else {
+            return;
+        };
+
+        let mut builder = symbol_registry.get(kind);
+        if let Some(&bit_width) = bit_width {
+            builder.bit_width(bit_width);
+        }
+        for (name, value) in new_parameters.iter() {
+            builder.parameter(name.clone(), value.clone());
+        }
+        let mut old_ports = Vec::new();
+        edges
+            .join::<Child>(&ports)
+            .for_each(|(port, name, direction)| {
+                old_ports.push((port, name.0.clone(), direction.is_output()))
+            });
+        let unmapped = replace_ports(
+            &mut commands,
+            circuit,
+            event.symbol,
+            old_ports,
+            &mut builder,
+            &endpoints,
+            &nets,
+        );
+
+        if unmapped > 0 {
+            bevy_log::warn!(
+                "{unmapped} connection(s) of {} have no matching port anymore and were disconnected",
+                name.0,
+            );
+        }
+    }
+
+    // gates stretch with their inputs
+    commands
+        .entity(event.symbol)
+        .insert((def.bounding_box_for(&new_parameters), new_parameters));
+}
@@ -0,0 +1,269 @@
+use crate::spatial_index::SpatialIndex;
+use aery::prelude::*;
+use bevy_ecs::prelude::*;
+use bevy_ecs::system::SystemParam;
+use bevy_reflect::prelude::*;
+use digilogic_core::components::*;
+use digilogic_core::transform::{AbsoluteBoundingBox, BoundingBox};
+use digilogic_routing::Vertices;
+
+/// The entities currently selected, in the order they were selected.
+/// Entities in the set are kept in sync with the [`Selected`] marker component.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Resource, Reflect)]
+#[reflect(Resource)]
+pub struct SelectionSet {
+    entities: Vec<Entity>,
+}
+
+impl SelectionSet {
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    #[inline]
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.entities.contains(&entity)
+    }
+
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.entities.iter().copied()
+    }
+
+    /// Adds an entity to the selection, returns `false` if it was already selected.
+    pub fn insert(&mut self, entity: Entity) -> bool {
+        if self.contains(entity) {
+            false
+        } else {
+            self.entities.push(entity);
+            true
+        }
+    }
+
+    /// Removes an entity from the selection, returns `false` if it wasn't selected.
+    pub fn remove(&mut self, entity: Entity) -> bool {
+        let len = self.entities.len();
+        self.entities.retain(|&selected| selected != entity);
+        self.entities.len() != len
+    }
+
+    /// Adds the entity to the selection if it isn't selected, removes it otherwise.
+    pub fn toggle(&mut self, entity: Entity) {
+        if !self.remove(entity) {
+            self.entities.push(entity);
+        }
+    }
+
+    /// Replaces the selection with a single entity.
+    pub fn select_only(&mut self, entity: Entity) {
+        self.entities.clear();
+        self.entities.push(entity);
+    }
+
+    pub fn clear(&mut self) {
+        self.entities.clear();
+    }
+
+    /// Selects all of `entities`, keeping the current selection.
+    pub fn select_all(&mut self, entities: impl IntoIterator<Item = Entity>) {
+        for entity in entities {
+            self.insert(entity);
+        }
+    }
+
+    /// Selects the entities of `entities` that aren't selected and deselects the rest.
+    pub fn invert(&mut self, entities: impl IntoIterator<Item = Entity>) {
+        let previous = std::mem::take(&mut self.entities);
+        self.entities = entities
+            .into_iter()
+            .filter(|entity| !previous.contains(entity))
+            .collect();
+    }
+}
+
+/// Sent whenever the selection changes, with the entities selected afterwards.
+/// Views that show the selection in their own way, like highlighting related entities
+/// in other circuits, react to this instead of polling the [`SelectionSet`].
+#[derive(Event, Debug, Clone)]
+pub struct SelectionChanged {
+    pub selected: Vec<Entity>,
+}
+
+type SelectableQuery<'w, 's> =
+    Query<'w, 's, Entity, Or<(With<Symbol>, With<Net>, With<Annotation>, With<Graphic>)>>;
+
+/// Operations on the selection that need to know which entities a circuit contains.
+#[allow(missing_debug_implementations)]
+#[derive(SystemParam)]
+pub struct Selection<'w, 's> {
+    pub set: ResMut<'w, SelectionSet>,
+    circuit_children: Query<'w, 's, ((), Relations<Child>), With<Circuit>>,
+    selectable: SelectableQuery<'w, 's>,
+}
+
+impl Selection<'_, '_> {
+    fn selectable_in(&self, circuit: CircuitID) -> Vec<Entity> {
+        let mut entities = Vec::new();
+        if let Ok((_, edges)) = self.circuit_children.get(circuit.0) {
+            edges
+                .join::<Child>(&self.selectable)
+                .for_each(|entity| entities.push(entity));
+        }
+        entities
+    }
+
+    /// Selects every symbol, net, annotation and graphic of the circuit.
+    pub fn select_all(&mut self, circuit: CircuitID) {
+        let entities = self.selectable_in(circuit);
+        self.set.select_all(entities);
+    }
+
+    /// Inverts the selection of the symbols, nets, annotations and graphics of the circuit.
+    pub fn invert(&mut self, circuit: CircuitID) {
+        let entities = self.selectable_in(circuit);
+        self.set.invert(entities);
+    }
+
+    pub fn clear(&mut self) {
+        // Don't trigger change detection if nothing changed.
+        if !self.set.is_empty() {
+            self.set.clear();
+        }
+    }
+}
+
+type BoxedEntityQuery<'w, 's> = Query<
+    'w,
+    's,
+    &'static AbsoluteBoundingBox,
+    Or<(With<Symbol>, With<Annotation>, With<Graphic>)>,
+>;
+
+/// Finds the entities a selection box covers.
+#[derive(SystemParam)]
+pub(crate) struct RegionQuery<'w, 's> {
+    selectable: SelectableQuery<'w, 's>,
+    spatial_indices: Query<'w, 's, &'static SpatialIndex, With<Circuit>>,
+    entity_bounds: BoxedEntityQuery<'w, 's>,
+    net_vertices: Query<'w, 's, &'static Vertices, With<Net>>,
+    locked: Query<'w, 's, (), With<Locked>>,
+}
+
+impl RegionQuery<'_, '_> {
+    /// The symbols, nets, annotations and graphics of the circuit that lie entirely inside `bounds`.
+    /// Locked entities are skipped unless `include_locked` is set.
+    pub(crate) fn entities_in(
+        &self,
+        circuit: CircuitID,
+        bounds: BoundingBox,
+        include_locked: bool,
+    ) -> Vec<Entity> {
+        let Ok(spatial_index) = self.spatial_indices.get(circuit.0) else {
+            return Vec::new();
+        };
+
+        let mut entities = Vec::new();
+        spatial_index.query(bounds, |&entity| {
+            if !self.selectable.contains(entity) || entities.contains(&entity) {
+                return;
+            }
+            if !include_locked && self.locked.contains(entity) {
+                return;
+            }
+
+            let inside = if let Ok(entity_bounds) = self.entity_bounds.get(entity) {
+                bounds.contains(entity_bounds.min()) && bounds.contains(entity_bounds.max())
+            } else if let Ok(vertices) = self.net_vertices.get(entity) {
+                vertices
+                    .iter()
+                    .all(|vertex| bounds.contains(vertex.position))
+            } else {
+                false
+            };
+
+            if inside {
+                entities.push(entity);
+            }
+        });
+
+        entities
+    }
+}
+
+/// Adds and removes [`Selected`] markers to match the [`SelectionSet`].
+pub(crate) fn sync_selected(
+    mut commands: Commands,
+    mut selection: ResMut<SelectionSet>,
+    entities: Query<Has<Selected>>,
+    selected: Query<Entity, With<Selected>>,
+    mut previous: Local<Vec<Entity>>,
+    mut changed_events: EventWriter<SelectionChanged>,
+) {
+    // forget entities that have been despawned
+    if selection.iter().any(|entity| !entities.contains(entity)) {
+        selection
+            .entities
+            .retain(|&entity| entities.contains(entity));
+    }
+
+    for entity in selected.iter() {
+        if !selection.contains(entity) {
+            commands.entity(entity).remove::<Selected>();
+        }
+    }
+
+    for entity in selection.iter() {
+        if let Ok(false) = entities.get(entity) {
+            commands.entity(entity).insert(Selected);
+        }
+    }
+
+    if selection.entities != *previous {
+        previous.clone_from(&selection.entities);
+        changed_events.send(SelectionChanged {
+            selected: selection.entities.clone(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entities(count: u32) -> Vec<Entity> {
+        (0..count).map(Entity::from_raw).collect()
+    }
+
+    #[test]
+    fn toggle() {
+        let [a, b] = entities(2)[..] else {
+            unreachable!()
+        };
+
+        let mut selection = SelectionSet::default();
+        selection.toggle(a);
+        selection.toggle(b);
+        selection.toggle(a);
+        assert_eq!(selection.iter().collect::<Vec<_>>(), [b]);
+    }
+
+    #[test]
+    fn invert() {
+        let all = entities(4);
+
+        let mut selection = SelectionSet::default();
+        selection.insert(all[1]);
+        selection.insert(all[3]);
+        selection.invert(all.iter().copied());
+        assert_eq!(selection.iter().collect::<Vec<_>>(), [all[0], all[2]]);
+
+        selection.invert(all.iter().copied());
+        assert_eq!(selection.iter().collect::<Vec<_>>(), [all[1], all[3]]);
+    }
+}
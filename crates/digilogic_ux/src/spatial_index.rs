@@ -3,23 +3,217 @@ use bevy_ecs::prelude::*;
 use bvh_arena::{Bvh, VolumeHandle};
 use digilogic_core::components::{Child, Circuit, Net};
 use digilogic_core::transform::{AbsoluteBoundingBox, BoundingBox, Vec2};
-use digilogic_core::{fixed, Fixed, HashMap};
+use digilogic_core::{fixed, Fixed, HashMap, HashSet};
 use digilogic_routing::{RoutingComplete, VertexKind, Vertices};
 
+mod grid;
+pub use grid::{GridHandle, GridIndex};
+
+/// Stores the bounding boxes of a [`SpatialIndex`] and finds the ones overlapping a box.
+/// The exact tests against the indexed shapes happen in the [`SpatialIndex`] itself.
+pub trait SpatialIndexBackend<T>: Default {
+    type Handle: Copy;
+
+    fn insert(&mut self, item: T, bounds: BoundingBox) -> Self::Handle;
+
+    fn remove(&mut self, handle: Self::Handle);
+
+    fn clear(&mut self);
+
+    /// Calls `cb` once for every item whose bounds intersect `bounds`.
+    fn for_each_overlaps(&self, bounds: &BoundingBox, cb: impl FnMut(&T));
+}
+
+impl<T> SpatialIndexBackend<T> for Bvh<T, BoundingBox> {
+    type Handle = VolumeHandle;
+
+    #[inline]
+    fn insert(&mut self, item: T, bounds: BoundingBox) -> Self::Handle {
+        Bvh::insert(self, item, bounds)
+    }
+
+    #[inline]
+    fn remove(&mut self, handle: Self::Handle) {
+        Bvh::remove(self, handle);
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        Bvh::clear(self);
+    }
+
+    #[inline]
+    fn for_each_overlaps(&self, bounds: &BoundingBox, cb: impl FnMut(&T)) {
+        Bvh::for_each_overlaps(self, bounds, cb);
+    }
+}
+
+/// The BVH adapts to any layout of the circuit, the grid is a lot faster on very large circuits
+/// as long as entities are about the size of its cells (see `benches/spatial_index.rs`).
+#[cfg(not(feature = "grid_spatial_index"))]
+type Backend = Bvh<(Entity, Shape), BoundingBox>;
+#[cfg(feature = "grid_spatial_index")]
+type Backend = GridIndex<(Entity, Shape)>;
+
+type Handle = <Backend as SpatialIndexBackend<(Entity, Shape)>>::Handle;
+
+/// The exact shape of an entry in the index, used after the bounding volumes overlap.
+#[derive(Debug, Clone, Copy)]
+enum Shape {
+    Box(BoundingBox),
+    /// An axis aligned wire segment.
+    Segment(Vec2, Vec2),
+}
+
+impl Shape {
+    fn closest_point(self, point: Vec2) -> Vec2 {
+        let bounds = match self {
+            Shape::Box(bounds) => bounds,
+            Shape::Segment(a, b) => BoundingBox::from_points(a, b),
+        };
+        point.clamp(bounds.min(), bounds.max())
+    }
+
+    fn intersects_segment(self, a: Vec2, b: Vec2) -> bool {
+        match self {
+            Shape::Box(bounds) => {
+                if bounds.contains(a) || bounds.contains(b) {
+                    return true;
+                }
+
+                let [c0, c1, c2, c3] = bounds.corners();
+                [(c0, c1), (c1, c2), (c2, c3), (c3, c0)]
+                    .into_iter()
+                    .any(|(c, d)| segments_intersect(a, b, c, d))
+            }
+            Shape::Segment(c, d) => segments_intersect(a, b, c, d),
+        }
+    }
+}
+
+/// The side of the line through `a` and `b` that `c` lies on, computed exactly.
+fn orientation(a: Vec2, b: Vec2, c: Vec2) -> std::cmp::Ordering {
+    let coords = |v: Vec2| (v.x.to_bits() as i128, v.y.to_bits() as i128);
+    let (ax, ay) = coords(a);
+    let (bx, by) = coords(b);
+    let (cx, cy) = coords(c);
+    ((bx - ax) * (cy - ay) - (by - ay) * (cx - ax)).cmp(&0)
+}
+
+/// Whether the segments `a`-`b` and `c`-`d` touch, including their endpoints.
+fn segments_intersect(a: Vec2, b: Vec2, c: Vec2, d: Vec2) -> bool {
+    use std::cmp::Ordering::Equal;
+
+    let o1 = orientation(a, b, c);
+    let o2 = orientation(a, b, d);
+    let o3 = orientation(c, d, a);
+    let o4 = orientation(c, d, b);
+
+    if (o1 != o2) && (o3 != o4) && (o1 != Equal || o2 != Equal) {
+        return true;
+    }
+
+    // collinear points only touch the other segment if they lie within its bounds
+    let on_segment = |p: Vec2, q: Vec2, r: Vec2| BoundingBox::from_points(p, q).contains(r);
+    ((o1 == Equal) && on_segment(a, b, c))
+        || ((o2 == Equal) && on_segment(a, b, d))
+        || ((o3 == Equal) && on_segment(c, d, a))
+        || ((o4 == Equal) && on_segment(c, d, b))
+}
+
+#[derive(Default)]
+struct Entry {
+    shapes: Vec<(Shape, BoundingBox)>,
+    handles: Vec<Handle>,
+}
+
 #[allow(missing_debug_implementations)]
 #[derive(Default, Component)]
 pub struct SpatialIndex {
-    index: Bvh<Entity, BoundingBox>,
-    handles: HashMap<Entity, Vec<VolumeHandle>>,
+    index: Backend,
+    entries: HashMap<Entity, Entry>,
 }
 
 impl SpatialIndex {
+    /// Start a batch of updates that is applied to the BVH at once when the batch is dropped.
+    pub fn batch(&mut self) -> SpatialIndexBatch<'_> {
+        SpatialIndexBatch {
+            spatial_index: self,
+            changed: HashSet::default(),
+            stale_handles: Vec::new(),
+        }
+    }
+
     pub fn remove(&mut self, entity: Entity) {
-        if let Some(handles) = self.handles.remove(&entity) {
-            for handle in handles {
-                self.index.remove(handle);
+        self.batch().remove(entity);
+    }
+
+    pub fn query(&self, bounds: BoundingBox, mut cb: impl FnMut(&Entity)) {
+        self.index
+            .for_each_overlaps(&bounds, |(entity, _)| cb(entity));
+    }
+
+    /// Calls `cb` for every box and wire segment touched by the segment from `a` to `b`,
+    /// so entities made up of several of them may be reported more than once.
+    pub fn query_segment(&self, a: Vec2, b: Vec2, mut cb: impl FnMut(&Entity)) {
+        // bounding boxes only intersect if they overlap by more than their edges
+        let bounds = BoundingBox::from_points(a, b).extrude(Vec2::splat(Fixed::EPSILON));
+        self.index.for_each_overlaps(&bounds, |(entity, shape)| {
+            if shape.intersects_segment(a, b) {
+                cb(entity);
+            }
+        });
+    }
+
+    /// Find the entity closest to `point` that passes `filter`, if any is within `max_distance`.
+    /// Distances are manhatten distances to the closest point of the entity's bounding boxes
+    /// or wire segments, ties are broken by the lowest entity so the result doesn't depend on
+    /// the index layout.
+    pub fn nearest(
+        &self,
+        point: Vec2,
+        max_distance: Fixed,
+        mut filter: impl FnMut(Entity) -> bool,
+    ) -> Option<Entity> {
+        // bounding boxes only intersect if they overlap by more than their edges
+        let half_size = max_distance + Fixed::EPSILON;
+        let bounds = BoundingBox::from_center_half_size(point, half_size, half_size);
+
+        let mut nearest: Option<(Fixed, Entity)> = None;
+        self.index.for_each_overlaps(&bounds, |&(entity, shape)| {
+            let distance = shape.closest_point(point).manhatten_distance_to(point);
+            if (distance > max_distance) || nearest.is_some_and(|n| n <= (distance, entity)) {
+                return;
+            }
+
+            if filter(entity) {
+                nearest = Some((distance, entity));
             }
+        });
+
+        nearest.map(|(_, entity)| entity)
+    }
+}
+
+/// Once more than one in this many entries of an index changed in a batch,
+/// the BVH is rebuilt instead of updating the entries one by one.
+const REBUILD_RATIO: usize = 4;
+
+/// Updates to a [`SpatialIndex`] that are collected and applied to the BVH at once when dropped,
+/// rebuilding it if a large part of the entries changed.
+#[allow(missing_debug_implementations)]
+pub struct SpatialIndexBatch<'a> {
+    spatial_index: &'a mut SpatialIndex,
+    changed: HashSet<Entity>,
+    stale_handles: Vec<Handle>,
+}
+
+impl SpatialIndexBatch<'_> {
+    pub fn remove(&mut self, entity: Entity) {
+        if let Some(entry) = self.spatial_index.entries.remove(&entity) {
+            self.stale_handles.extend(entry.handles);
         }
+        self.changed.remove(&entity);
     }
 
     /// Update the spatial index for the given entity with a single bounding box.
@@ -31,25 +225,60 @@ impl SpatialIndex {
     /// Update the spatial index for the given entity with multiple bounding boxes.
     /// Any existing bounding boxes for the entity will be removed.
     pub fn update_all(&mut self, entity: Entity, bounds: &[BoundingBox]) {
-        let handles = if let Some(handles) = self.handles.get_mut(&entity) {
-            for handle in handles.iter() {
-                self.index.remove(*handle);
+        self.update_shapes(
+            entity,
+            bounds.iter().map(|&bounds| (Shape::Box(bounds), bounds)),
+        );
+    }
+
+    /// Update the spatial index for the given entity with the axis aligned segments of a wire.
+    /// Any existing bounding boxes for the entity will be removed.
+    pub fn update_segments(&mut self, entity: Entity, segments: &[(Vec2, Vec2)]) {
+        self.update_shapes(
+            entity,
+            segments
+                .iter()
+                .map(|&(a, b)| (Shape::Segment(a, b), wire_bounding_box(a, b))),
+        );
+    }
+
+    fn update_shapes(
+        &mut self,
+        entity: Entity,
+        shapes: impl Iterator<Item = (Shape, BoundingBox)>,
+    ) {
+        let entry = self.spatial_index.entries.entry(entity).or_default();
+        self.stale_handles.append(&mut entry.handles);
+        entry.shapes.clear();
+        entry.shapes.extend(shapes);
+        self.changed.insert(entity);
+    }
+}
+
+impl Drop for SpatialIndexBatch<'_> {
+    fn drop(&mut self) {
+        let SpatialIndex { index, entries } = &mut *self.spatial_index;
+
+        if self.changed.len() * REBUILD_RATIO > entries.len() {
+            index.clear();
+            for (&entity, entry) in entries.iter_mut() {
+                entry.handles.clear();
+                for &(shape, bounds) in entry.shapes.iter() {
+                    entry.handles.push(index.insert((entity, shape), bounds));
+                }
             }
-            handles.clear();
-            handles
         } else {
-            self.handles.insert(entity, Vec::new());
-            self.handles.get_mut(&entity).unwrap()
-        };
-        for &bound in bounds {
-            let handle = self.index.insert(entity, bound);
-            handles.push(handle);
+            for &handle in self.stale_handles.iter() {
+                index.remove(handle);
+            }
+            for entity in self.changed.iter() {
+                let entry = entries.get_mut(entity).unwrap();
+                for &(shape, bounds) in entry.shapes.iter() {
+                    entry.handles.push(index.insert((*entity, shape), bounds));
+                }
+            }
         }
     }
-
-    pub fn query(&self, bounds: BoundingBox, cb: impl FnMut(&Entity)) {
-        self.index.for_each_overlaps(&bounds, cb);
-    }
 }
 
 pub(crate) fn inject_spatial_index(trigger: Trigger<OnAdd, Circuit>, mut commands: Commands) {
@@ -62,16 +291,33 @@ pub(crate) fn update_spatial_index(
     mut circuits: Query<&mut SpatialIndex, With<Circuit>>,
     children: Query<(Entity, Relations<Child>)>,
     bounding_boxes: Query<(Entity, &AbsoluteBoundingBox), Changed<AbsoluteBoundingBox>>,
+    mut updates: Local<Vec<(Entity, Entity, BoundingBox)>>,
 ) {
+    digilogic_core::profile_scope!("spatial index");
+
+    updates.clear();
     for (bounds_entity, bounds) in bounding_boxes.iter() {
         children
             .traverse::<Up<Child>>([bounds_entity])
             .for_each(|&mut entity, _| {
-                if let Ok(mut spatial_index) = circuits.get_mut(entity) {
-                    spatial_index.update(bounds_entity, **bounds);
+                if circuits.contains(entity) {
+                    updates.push((entity, bounds_entity, **bounds));
                 }
             });
     }
+
+    // update each circuit in a single batch, so large edits like pasting don't thrash the BVH
+    updates.sort_unstable_by_key(|&(circuit, _, _)| circuit);
+    for circuit_updates in updates.chunk_by(|a, b| a.0 == b.0) {
+        let Ok(mut spatial_index) = circuits.get_mut(circuit_updates[0].0) else {
+            continue;
+        };
+
+        let mut batch = spatial_index.batch();
+        for &(_, entity, bounds) in circuit_updates {
+            batch.update(entity, bounds);
+        }
+    }
 }
 
 pub(crate) fn on_remove_bounding_box_update_spatial_index(
@@ -94,9 +340,11 @@ pub(crate) fn update_spatial_index_on_routing(
     nets: Query<(Entity, &Vertices), With<Net>>,
 ) {
     for event in routing_events.read() {
+        digilogic_core::profile_scope!("spatial index");
         bevy_log::debug!("Updating spatial index on routing event");
         let (mut spatial_index, circuit_children) = circuits.get_mut(event.circuit.0).unwrap();
-        let mut boxes = Vec::new();
+        let mut segments = Vec::new();
+        let mut batch = spatial_index.batch();
         circuit_children
             .join::<Child>(&nets)
             .for_each(|(net_id, vertices)| {
@@ -105,7 +353,7 @@ pub(crate) fn update_spatial_index_on_routing(
                     match vertex.kind {
                         VertexKind::Normal | VertexKind::Dummy => {
                             if let Some(prev_vertex) = prev_vertex {
-                                add_bounding_box(prev_vertex, vertex.position, &mut boxes);
+                                segments.push((prev_vertex, vertex.position));
                             }
                             prev_vertex = Some(vertex.position);
                         }
@@ -114,38 +362,30 @@ pub(crate) fn update_spatial_index_on_routing(
                         }
                         VertexKind::WireEnd { .. } => {
                             if let Some(prev_vertex) = prev_vertex {
-                                add_bounding_box(prev_vertex, vertex.position, &mut boxes);
+                                segments.push((prev_vertex, vertex.position));
                             }
                             prev_vertex = None;
                         }
                     }
                 }
 
-                spatial_index.update_all(net_id, &boxes);
-                boxes.clear();
+                batch.update_segments(net_id, &segments);
+                segments.clear();
             });
     }
 }
 
 const WIRE_BBOX_THICKNESS: Fixed = fixed!(4);
 
-fn add_bounding_box(p1: Vec2, p2: Vec2, boxes: &mut Vec<BoundingBox>) {
+fn wire_bounding_box(p1: Vec2, p2: Vec2) -> BoundingBox {
     if p1.x == p2.x {
         let half_extent = (p2.y - p1.y).abs() / fixed!(2);
         let center = (p1 + p2) / fixed!(2);
-        boxes.push(BoundingBox::from_center_half_size(
-            center,
-            WIRE_BBOX_THICKNESS,
-            half_extent,
-        ));
+        BoundingBox::from_center_half_size(center, WIRE_BBOX_THICKNESS, half_extent)
     } else {
         let half_extent = (p2.x - p1.x).abs() / fixed!(2);
         let center = (p1 + p2) / fixed!(2);
-        boxes.push(BoundingBox::from_center_half_size(
-            center,
-            half_extent,
-            WIRE_BBOX_THICKNESS,
-        ));
+        BoundingBox::from_center_half_size(center, half_extent, WIRE_BBOX_THICKNESS)
     }
 }
 
@@ -162,3 +402,151 @@ pub(crate) fn on_remove_net_update_spatial_index(
             }
         });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point_bounds(x: Fixed, y: Fixed) -> BoundingBox {
+        BoundingBox::from_center_half_size(Vec2 { x, y }, fixed!(1), fixed!(1))
+    }
+
+    #[test]
+    fn nearest() {
+        let mut world = World::new();
+        let [a, b, c] = [(); 3].map(|_| world.spawn_empty().id());
+
+        let mut spatial_index = SpatialIndex::default();
+        let mut batch = spatial_index.batch();
+        batch.update(a, point_bounds(fixed!(10), fixed!(0)));
+        batch.update(b, point_bounds(fixed!(4), fixed!(3)));
+        batch.update_all(
+            c,
+            &[
+                point_bounds(fixed!(-20), fixed!(0)),
+                point_bounds(fixed!(0), fixed!(-5)),
+            ],
+        );
+        drop(batch);
+
+        let origin = Vec2::default();
+        assert_eq!(spatial_index.nearest(origin, fixed!(10), |_| true), Some(c));
+        assert_eq!(
+            spatial_index.nearest(origin, fixed!(10), |e| e != c),
+            Some(b)
+        );
+        assert_eq!(spatial_index.nearest(origin, fixed!(5), |e| e == a), None);
+        assert_eq!(
+            spatial_index.nearest(origin, fixed!(9), |e| e == a),
+            Some(a)
+        );
+        assert_eq!(spatial_index.nearest(origin, fixed!(3), |_| true), None);
+
+        spatial_index.remove(c);
+        assert_eq!(spatial_index.nearest(origin, fixed!(10), |_| true), Some(b));
+    }
+
+    #[test]
+    fn query_segment() {
+        let mut world = World::new();
+        let [symbol, wire] = [(); 2].map(|_| world.spawn_empty().id());
+
+        let mut spatial_index = SpatialIndex::default();
+        let mut batch = spatial_index.batch();
+        batch.update(symbol, point_bounds(fixed!(10), fixed!(10)));
+        batch.update_segments(
+            wire,
+            &[
+                (
+                    Vec2::default(),
+                    Vec2 {
+                        x: fixed!(0),
+                        y: fixed!(20),
+                    },
+                ),
+                (
+                    Vec2 {
+                        x: fixed!(0),
+                        y: fixed!(20),
+                    },
+                    Vec2 {
+                        x: fixed!(20),
+                        y: fixed!(20),
+                    },
+                ),
+            ],
+        );
+        drop(batch);
+
+        let hits = |a: Vec2, b: Vec2| {
+            let mut hits = Vec::new();
+            spatial_index.query_segment(a, b, |&entity| hits.push(entity));
+            hits.sort();
+            hits.dedup();
+            hits
+        };
+
+        let p = |x: Fixed, y: Fixed| Vec2 { x, y };
+        // crosses the symbol and the vertical segment of the wire
+        assert_eq!(
+            hits(p(fixed!(-5), fixed!(10)), p(fixed!(15), fixed!(10))),
+            vec![symbol, wire]
+        );
+        // diagonally through the symbol
+        assert_eq!(
+            hits(p(fixed!(5), fixed!(5)), p(fixed!(15), fixed!(15))),
+            vec![symbol]
+        );
+        // touching the symbol's corner
+        assert_eq!(
+            hits(p(fixed!(5), fixed!(13)), p(fixed!(11), fixed!(11))),
+            vec![symbol]
+        );
+        // within the padding of the wire, but not touching it
+        assert_eq!(
+            hits(p(fixed!(2), fixed!(2)), p(fixed!(2), fixed!(5))),
+            vec![]
+        );
+        // ending on the wire
+        assert_eq!(
+            hits(p(fixed!(5), fixed!(25)), p(fixed!(5), fixed!(20))),
+            vec![wire]
+        );
+    }
+
+    #[test]
+    fn batch_updates() {
+        let mut world = World::new();
+        let entities: Vec<_> = (0..16).map(|_| world.spawn_empty().id()).collect();
+        let position = |i: usize| Fixed::from_u16(i as u16 * 10);
+
+        // inserting everything at once rebuilds the BVH
+        let mut spatial_index = SpatialIndex::default();
+        let mut batch = spatial_index.batch();
+        for (i, &entity) in entities.iter().enumerate() {
+            batch.update(entity, point_bounds(position(i), fixed!(0)));
+        }
+        drop(batch);
+
+        // moving a single entity updates it in place
+        let mut batch = spatial_index.batch();
+        batch.update(entities[0], point_bounds(fixed!(-100), fixed!(0)));
+        batch.update(entities[0], point_bounds(fixed!(-50), fixed!(0)));
+        batch.remove(entities[1]);
+        drop(batch);
+
+        let at = |x: Fixed| {
+            let mut found = Vec::new();
+            let bounds =
+                BoundingBox::from_center_half_size(Vec2 { x, y: fixed!(0) }, fixed!(2), fixed!(2));
+            spatial_index.query(bounds, |&entity| found.push(entity));
+            found
+        };
+        assert_eq!(at(fixed!(-50)), vec![entities[0]]);
+        assert_eq!(at(fixed!(-100)), vec![]);
+        assert_eq!(at(fixed!(0)), vec![]);
+        assert_eq!(at(position(1)), vec![]);
+        assert_eq!(at(position(2)), vec![entities[2]]);
+        assert_eq!(at(position(15)), vec![entities[15]]);
+    }
+}
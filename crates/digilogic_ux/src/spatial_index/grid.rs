@@ -0,0 +1,205 @@
+use super::SpatialIndexBackend;
+use digilogic_core::transform::BoundingBox;
+use digilogic_core::{Fixed, HashMap};
+
+/// The size of a grid cell, roughly the size of a small symbol.
+const CELL_SIZE: i32 = 64;
+
+type Cell = (i32, i32);
+
+fn cell_range(bounds: BoundingBox) -> (Cell, Cell) {
+    let cell = |v: Fixed| v.to_i32().div_euclid(CELL_SIZE);
+    let min = bounds.min();
+    let max = bounds.max();
+    ((cell(min.x), cell(min.y)), (cell(max.x), cell(max.y)))
+}
+
+/// Handle of an item inserted into a [`GridIndex`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GridHandle(u32);
+
+/// A spatial hash grid. Unlike the BVH, inserting and removing items doesn't depend on
+/// the number of items already indexed, at the cost of slower queries for large boxes.
+#[derive(Debug)]
+pub struct GridIndex<T> {
+    items: Vec<Option<(T, BoundingBox)>>,
+    free: Vec<u32>,
+    cells: HashMap<Cell, Vec<u32>>,
+}
+
+impl<T> Default for GridIndex<T> {
+    fn default() -> Self {
+        Self {
+            items: Vec::new(),
+            free: Vec::new(),
+            cells: HashMap::default(),
+        }
+    }
+}
+
+impl<T> GridIndex<T> {
+    fn for_each_cell(&self, (min, max): (Cell, Cell), mut cb: impl FnMut(Cell, &[u32])) {
+        let cell_count =
+            ((max.0 as i64) - (min.0 as i64) + 1) * ((max.1 as i64) - (min.1 as i64) + 1);
+
+        // don't walk the empty cells of boxes larger than everything indexed
+        if cell_count > (self.cells.len() as i64) {
+            for (&cell, items) in self.cells.iter() {
+                if (min.0..=max.0).contains(&cell.0) && (min.1..=max.1).contains(&cell.1) {
+                    cb(cell, items);
+                }
+            }
+        } else {
+            for x in min.0..=max.0 {
+                for y in min.1..=max.1 {
+                    if let Some(items) = self.cells.get(&(x, y)) {
+                        cb((x, y), items);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<T> SpatialIndexBackend<T> for GridIndex<T> {
+    type Handle = GridHandle;
+
+    fn insert(&mut self, item: T, bounds: BoundingBox) -> Self::Handle {
+        let index = match self.free.pop() {
+            Some(index) => {
+                self.items[index as usize] = Some((item, bounds));
+                index
+            }
+            None => {
+                self.items.push(Some((item, bounds)));
+                (self.items.len() - 1) as u32
+            }
+        };
+
+        let (min, max) = cell_range(bounds);
+        for x in min.0..=max.0 {
+            for y in min.1..=max.1 {
+                self.cells.entry((x, y)).or_default().push(index);
+            }
+        }
+
+        GridHandle(index)
+    }
+
+    fn remove(&mut self, GridHandle(index): Self::Handle) {
+        let Some((_, bounds)) = self.items[index as usize].take() else {
+            return;
+        };
+        self.free.push(index);
+
+        let (min, max) = cell_range(bounds);
+        for x in min.0..=max.0 {
+            for y in min.1..=max.1 {
+                let Some(items) = self.cells.get_mut(&(x, y)) else {
+                    continue;
+                };
+
+                if let Some(position) = items.iter().position(|&item| item == index) {
+                    items.swap_remove(position);
+                }
+                if items.is_empty() {
+                    self.cells.remove(&(x, y));
+                }
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.items.clear();
+        self.free.clear();
+        self.cells.clear();
+    }
+
+    fn for_each_overlaps(&self, bounds: &BoundingBox, mut cb: impl FnMut(&T)) {
+        let query_range = cell_range(*bounds);
+        let (query_min, _) = query_range;
+
+        self.for_each_cell(query_range, |cell, items| {
+            for &index in items {
+                let Some((item, item_bounds)) = &self.items[index as usize] else {
+                    continue;
+                };
+
+                // items spanning several cells are only reported from the first cell they share
+                // with the query, so they aren't reported more than once
+                let (item_min, _) = cell_range(*item_bounds);
+                if (cell.0 != item_min.0.max(query_min.0))
+                    || (cell.1 != item_min.1.max(query_min.1))
+                {
+                    continue;
+                }
+
+                if bounds.intersects(*item_bounds) {
+                    cb(item);
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bvh_arena::Bvh;
+    use digilogic_core::fixed;
+    use digilogic_core::transform::Vec2;
+
+    /// A deterministic pseudo random box somewhere around the origin.
+    fn random_box(state: &mut u32) -> BoundingBox {
+        let mut next = |range: u32| {
+            *state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+            ((*state >> 8) % range) as i32
+        };
+
+        let center = Vec2 {
+            x: Fixed::try_from_i32(next(2000) - 1000).unwrap(),
+            y: Fixed::try_from_i32(next(2000) - 1000).unwrap(),
+        };
+        let half_width = Fixed::try_from_i32(next(150)).unwrap();
+        let half_height = Fixed::try_from_i32(next(150)).unwrap();
+        BoundingBox::from_center_half_size(center, half_width, half_height)
+    }
+
+    fn overlaps<B: SpatialIndexBackend<u32>>(index: &B, bounds: BoundingBox) -> Vec<u32> {
+        let mut found = Vec::new();
+        index.for_each_overlaps(&bounds, |&item| found.push(item));
+        found.sort_unstable();
+        found
+    }
+
+    #[test]
+    fn matches_bvh() {
+        let mut state = 1;
+        let mut grid = GridIndex::default();
+        let mut bvh = Bvh::default();
+        let mut handles = Vec::new();
+        for item in 0..500 {
+            let bounds = random_box(&mut state);
+            handles.push((
+                grid.insert(item, bounds),
+                SpatialIndexBackend::insert(&mut bvh, item, bounds),
+            ));
+        }
+
+        for &(grid_handle, bvh_handle) in handles.iter().step_by(3) {
+            grid.remove(grid_handle);
+            SpatialIndexBackend::remove(&mut bvh, bvh_handle);
+        }
+
+        for _ in 0..200 {
+            let bounds = random_box(&mut state);
+            let found = overlaps(&grid, bounds);
+            assert_eq!(found, overlaps(&bvh, bounds));
+            assert!(found.windows(2).all(|pair| pair[0] != pair[1]));
+        }
+
+        // larger than every cell, so the indexed cells are walked instead
+        let everything = BoundingBox::from_half_size(fixed!(100000), fixed!(100000));
+        assert_eq!(overlaps(&grid, everything).len(), 333);
+    }
+}
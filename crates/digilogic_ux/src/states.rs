@@ -1,11 +1,53 @@
 use bevy_derive::{Deref, DerefMut};
 use bevy_ecs::prelude::*;
 use bevy_reflect::Reflect;
-use digilogic_core::transform::Vec2;
+use digilogic_core::transform::{BoundingBox, Vec2};
 
 #[derive(Debug, Default, Component, Deref, DerefMut, Reflect)]
 pub struct HoveredEntity(pub Option<Entity>);
 
+/// All entities under the cursor, in the order they are picked in.
+/// Clicking repeatedly cycles through them.
+#[derive(Debug, Default, Component, Reflect)]
+pub struct HoverCandidates {
+    pub entities: Vec<Entity>,
+    pub index: usize,
+}
+
+/// The most recent position of the pointer over a viewport, in circuit coordinates.
+#[derive(Debug, Default, Component, Deref, DerefMut, Reflect)]
+pub struct CursorPosition(pub Vec2);
+
+/// A line between a dragged entity and what it has been aligned to, in circuit coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub struct AlignmentGuide {
+    pub from: Vec2,
+    pub to: Vec2,
+}
+
+/// The alignment guides of the drag in progress in a viewport.
+#[derive(Debug, Default, Component, Deref, DerefMut, Reflect)]
+pub struct AlignmentGuides(pub Vec<AlignmentGuide>);
+
+/// The region dragged out in a viewport to select the entities inside it, in circuit coordinates.
+/// Holds the point the drag started at and the current one.
+#[derive(Debug, Default, Component, Reflect)]
+pub struct SelectionBox(pub Option<(Vec2, Vec2)>);
+
+impl SelectionBox {
+    #[inline]
+    pub fn bounds(&self) -> Option<BoundingBox> {
+        self.0
+            .map(|(origin, current)| BoundingBox::from_points(origin, current))
+    }
+}
+
+/// The wire drawn while dragging from an unconnected port, in circuit coordinates.
+/// Holds the port, its position, the current position of the cursor
+/// and whether the stub crosses a symbol on its way.
+#[derive(Debug, Default, Component, Reflect)]
+pub struct PortStub(pub Option<(Entity, Vec2, Vec2, bool)>);
+
 #[derive(Debug, Component, Copy, Clone, Reflect)]
 pub struct EntityOffset {
     pub entity: Entity,
@@ -21,5 +63,9 @@ pub enum MouseState {
 #[derive(Debug, Component, Reflect)]
 pub struct MouseIdle;
 
+/// An endpoint that has been detached from its port and is being dragged to another one.
+#[derive(Debug, Component, Deref, Reflect)]
+pub struct ReconnectingEndpoint(pub Entity);
+
 #[derive(Debug, Component, Deref, DerefMut, Reflect)]
 pub struct MouseMoving(pub Vec<EntityOffset>);
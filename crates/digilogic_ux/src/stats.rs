@@ -0,0 +1,360 @@
+use aery::prelude::*;
+use bevy_ecs::prelude::*;
+use digilogic_core::components::*;
+use digilogic_core::transform::{AbsoluteBoundingBox, BoundingBox};
+use digilogic_core::HashMap;
+
+/// The number of symbols, nets and endpoints in a circuit, and the area its contents cover.
+/// Kept up to date as entities are added to and removed from the circuit,
+/// so reading it never walks the contents of the circuit.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Component)]
+pub struct CircuitStats {
+    pub symbols: usize,
+    pub nets: usize,
+    pub endpoints: usize,
+    pub annotations: usize,
+    pub graphics: usize,
+    /// The union of the bounding boxes of all symbols, endpoints, annotations and graphics,
+    /// `None` if there are none.
+    pub extent: Option<BoundingBox>,
+    /// An entity on the edge of the extent moved or was removed, so the extent may be too large.
+    extent_dirty: bool,
+}
+
+impl CircuitStats {
+    fn count_mut(&mut self, kind: CountedKind) -> &mut usize {
+        match kind {
+            CountedKind::Symbol => &mut self.symbols,
+            CountedKind::Net => &mut self.nets,
+            CountedKind::Endpoint => &mut self.endpoints,
+            CountedKind::Annotation => &mut self.annotations,
+            CountedKind::Graphic => &mut self.graphics,
+        }
+    }
+
+    fn add(&mut self, counted: &CountedIn) {
+        *self.count_mut(counted.kind) += 1;
+        if let Some(bounds) = counted.bounds {
+            self.grow_extent(bounds);
+        }
+    }
+
+    fn remove(&mut self, counted: &CountedIn) {
+        let count = self.count_mut(counted.kind);
+        *count = count.saturating_sub(1);
+        if let Some(bounds) = counted.bounds {
+            self.shrink_extent(bounds);
+        }
+    }
+
+    fn grow_extent(&mut self, bounds: BoundingBox) {
+        self.extent = Some(match self.extent {
+            Some(extent) => extent.union(bounds),
+            None => bounds,
+        });
+    }
+
+    fn shrink_extent(&mut self, bounds: BoundingBox) {
+        let Some(extent) = self.extent else {
+            return;
+        };
+
+        // boxes strictly inside the extent don't define it
+        if (bounds.min().x <= extent.min().x)
+            || (bounds.min().y <= extent.min().y)
+            || (bounds.max().x >= extent.max().x)
+            || (bounds.max().y >= extent.max().y)
+        {
+            self.extent_dirty = true;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CountedKind {
+    Symbol,
+    Net,
+    Endpoint,
+    Annotation,
+    Graphic,
+}
+
+/// The circuit an entity is counted in.
+/// The relations of an entity are already gone once its despawn is observed,
+/// so it has to remember which circuit to remove itself from.
+#[derive(Debug, Clone, Copy, Component)]
+pub(crate) struct CountedIn {
+    circuit: Entity,
+    kind: CountedKind,
+    /// The bounding box the entity contributed to the extent of the circuit.
+    bounds: Option<BoundingBox>,
+}
+
+pub(crate) fn inject_circuit_stats(trigger: Trigger<OnAdd, Circuit>, mut commands: Commands) {
+    commands
+        .entity(trigger.entity())
+        .insert(CircuitStats::default());
+}
+
+type KindQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Has<Symbol>,
+        Has<Net>,
+        Has<Endpoint>,
+        Has<Annotation>,
+        Has<Graphic>,
+    ),
+>;
+
+/// Counts symbols, nets, annotations and graphics as they become part of a circuit,
+/// and endpoints as they become part of a net.
+/// Moving a net to another circuit moves its endpoints along with it.
+pub(crate) fn on_set_child_count(
+    trigger: Trigger<SetEvent<Child>>,
+    mut commands: Commands,
+    kinds: KindQuery,
+    mut counted: Query<&mut CountedIn>,
+    mut stats: Query<&mut CircuitStats>,
+    children: Query<(Entity, Relations<Child>)>,
+    endpoints: Query<Entity, With<Endpoint>>,
+) {
+    let host = trigger.entity();
+    let parent = trigger.event().target;
+    let Ok((is_symbol, is_net, is_endpoint, is_annotation, is_graphic)) = kinds.get(host) else {
+        return;
+    };
+
+    let in_circuit = stats.contains(parent);
+    let (circuit, kind) = if is_symbol && in_circuit {
+        (parent, CountedKind::Symbol)
+    } else if is_net && in_circuit {
+        (parent, CountedKind::Net)
+    } else if is_annotation && in_circuit {
+        (parent, CountedKind::Annotation)
+    } else if is_graphic && in_circuit {
+        (parent, CountedKind::Graphic)
+    } else if is_endpoint && kinds.get(parent).is_ok_and(|(_, is_net, ..)| is_net) {
+        let Ok(net) = counted.get(parent) else {
+            return;
+        };
+        (net.circuit, CountedKind::Endpoint)
+    } else {
+        return;
+    };
+
+    let mut moved = vec![(host, kind)];
+    if kind == CountedKind::Net {
+        if let Ok((_, edges)) = children.get(host) {
+            edges
+                .join::<Child>(&endpoints)
+                .for_each(|endpoint| moved.push((endpoint, CountedKind::Endpoint)));
+        }
+    }
+
+    for (entity, kind) in moved {
+        match counted.get_mut(entity) {
+            Ok(entry) if entry.circuit == circuit => (),
+            Ok(mut entry) => {
+                if let Ok(mut old_stats) = stats.get_mut(entry.circuit) {
+                    old_stats.remove(&entry);
+                }
+                entry.circuit = circuit;
+                if let Ok(mut new_stats) = stats.get_mut(circuit) {
+                    new_stats.add(&entry);
+                }
+            }
+            Err(_) => {
+                let entry = CountedIn {
+                    circuit,
+                    kind,
+                    bounds: None,
+                };
+                if let Ok(mut new_stats) = stats.get_mut(circuit) {
+                    new_stats.add(&entry);
+                }
+                commands.entity(entity).insert(entry);
+            }
+        }
+    }
+}
+
+pub(crate) fn on_remove_counted(
+    trigger: Trigger<OnRemove, CountedIn>,
+    counted: Query<&CountedIn>,
+    mut stats: Query<&mut CircuitStats>,
+) {
+    let Ok(entry) = counted.get(trigger.entity()) else {
+        return;
+    };
+    // the circuit may be despawned along with its contents
+    if let Ok(mut stats) = stats.get_mut(entry.circuit) {
+        stats.remove(entry);
+    }
+}
+
+type ChangedBoundsQuery<'w, 's> = Query<
+    'w,
+    's,
+    (&'static AbsoluteBoundingBox, &'static mut CountedIn),
+    Changed<AbsoluteBoundingBox>,
+>;
+
+/// Updates the extent of circuits whose contents moved.
+/// The extent only has to be recomputed from all entities if one on its edge moved inwards.
+pub(crate) fn update_circuit_extents(
+    mut stats: Query<(Entity, &mut CircuitStats)>,
+    mut bounds: ParamSet<(ChangedBoundsQuery, Query<&CountedIn>)>,
+) {
+    for (bounds, mut entry) in bounds.p0().iter_mut() {
+        let Ok((_, mut stats)) = stats.get_mut(entry.circuit) else {
+            continue;
+        };
+        if let Some(old_bounds) = entry.bounds {
+            stats.shrink_extent(old_bounds);
+        }
+        stats.grow_extent(**bounds);
+        entry.bounds = Some(**bounds);
+    }
+
+    let mut extents: HashMap<Entity, Option<BoundingBox>> = stats
+        .iter()
+        .filter(|(_, stats)| stats.extent_dirty)
+        .map(|(circuit, _)| (circuit, None))
+        .collect();
+    if extents.is_empty() {
+        return;
+    }
+
+    for entry in bounds.p1().iter() {
+        if let (Some(extent), Some(bounds)) = (extents.get_mut(&entry.circuit), entry.bounds) {
+            *extent = Some(match *extent {
+                Some(extent) => extent.union(bounds),
+                None => bounds,
+            });
+        }
+    }
+
+    for (circuit, extent) in extents {
+        if let Ok((_, mut stats)) = stats.get_mut(circuit) {
+            stats.extent = extent;
+            stats.extent_dirty = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use digilogic_core::bundles::{EndpointBundle, GraphicBundle};
+    use digilogic_core::transform::{BoundingBoxBundle, Transform, TransformBundle, Vec2};
+    use digilogic_core::{fixed, Fixed};
+
+    fn stats(app: &mut bevy_app::App, circuit: Entity) -> CircuitStats {
+        *app.world().get::<CircuitStats>(circuit).unwrap()
+    }
+
+    fn spawn_symbol(app: &mut bevy_app::App, circuit: Entity, x: Fixed) -> Entity {
+        app.world_mut()
+            .spawn((
+                Symbol,
+                TransformBundle {
+                    transform: Transform {
+                        translation: Vec2 { x, y: fixed!(0) },
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                BoundingBoxBundle {
+                    bounding_box: BoundingBox::from_half_size(fixed!(10), fixed!(10)),
+                    ..Default::default()
+                },
+            ))
+            .set::<Child>(circuit)
+            .id()
+    }
+
+    #[test]
+    fn counts_and_extent() {
+        let mut app = bevy_app::App::new();
+        app.add_plugins((bevy_state::app::StatesPlugin, digilogic_core::CorePlugin));
+        app.observe(inject_circuit_stats)
+            .observe(on_set_child_count)
+            .observe(on_remove_counted);
+        app.add_systems(bevy_app::Last, update_circuit_extents);
+
+        let [circuit_a, circuit_b] = [(); 2].map(|_| app.world_mut().spawn(Circuit).id());
+        let left = spawn_symbol(&mut app, circuit_a, fixed!(0));
+        let right = spawn_symbol(&mut app, circuit_a, fixed!(100));
+        let net = app.world_mut().spawn(Net).set::<Child>(circuit_a).id();
+        for _ in 0..3 {
+            app.world_mut()
+                .spawn(EndpointBundle::default())
+                .set::<Child>(net);
+        }
+        app.update();
+
+        let a = stats(&mut app, circuit_a);
+        assert_eq!((a.symbols, a.nets, a.endpoints), (2, 1, 3));
+        let extent = a.extent.unwrap();
+        assert_eq!((extent.min().x, extent.max().x), (fixed!(-10), fixed!(110)));
+
+        // the net takes its endpoints along
+        app.world_mut().entity_mut(net).set::<Child>(circuit_b);
+        let b = stats(&mut app, circuit_b);
+        assert_eq!((b.nets, b.endpoints), (1, 3));
+        assert_eq!(stats(&mut app, circuit_a).endpoints, 0);
+
+        // graphics and endpoints count towards the extent as well
+        app.world_mut()
+            .spawn(GraphicBundle {
+                transform: TransformBundle {
+                    transform: Transform {
+                        translation: Vec2 {
+                            x: fixed!(200),
+                            y: fixed!(0),
+                        },
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                bounds: BoundingBoxBundle {
+                    bounding_box: BoundingBox::from_top_left_size(
+                        Vec2::ZERO,
+                        fixed!(40),
+                        fixed!(20),
+                    ),
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .set::<Child>(circuit_b);
+        app.update();
+        let b = stats(&mut app, circuit_b);
+        assert_eq!(b.graphics, 1);
+        let extent = b.extent.unwrap();
+        assert_eq!((extent.min().x, extent.max().x), (fixed!(0), fixed!(240)));
+
+        // moving the symbol on the edge inwards shrinks the extent
+        app.world_mut()
+            .get_mut::<Transform>(right)
+            .unwrap()
+            .translation
+            .x = fixed!(50);
+        app.update();
+        let extent = stats(&mut app, circuit_a).extent.unwrap();
+        assert_eq!(extent.max().x, fixed!(60));
+
+        app.world_mut().despawn(right);
+        app.update();
+        let a = stats(&mut app, circuit_a);
+        assert_eq!(a.symbols, 1);
+        assert_eq!(a.extent.map(|extent| extent.max().x), Some(fixed!(10)));
+
+        app.world_mut().despawn(left);
+        app.update();
+        let a = stats(&mut app, circuit_a);
+        assert_eq!((a.symbols, a.extent), (0, None));
+    }
+}
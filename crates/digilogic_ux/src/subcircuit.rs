@@ -0,0 +1,413 @@
+use crate::spatial_index::SpatialIndex;
+use crate::{ActiveTool, DoubleClickEvent, GridSize, HoveredEntity, PointerButton, SelectionSet};
+use aery::prelude::*;
+use bevy_ecs::prelude::*;
+use digilogic_core::bundles::{CircuitBundle, NetBundle};
+use digilogic_core::components::*;
+use digilogic_core::connections::{move_endpoint, spawn_port_endpoint};
+use digilogic_core::symbol::{build_port, SymbolKindDescriptor, SymbolRegistry};
+use digilogic_core::transform::*;
+use digilogic_core::visibility::VisibilityBundle;
+use digilogic_core::{fixed, Fixed, HashSet, SharedStr};
+use digilogic_routing::{reroute_circuit, reroute_net};
+
+const SUB_CIRCUIT_WIDTH: Fixed = fixed!(80);
+const SUB_CIRCUIT_PORT_SPACING: Fixed = fixed!(20);
+const BOUNDARY_SYMBOL_SPACING: Fixed = fixed!(40);
+
+/// Moves the selected symbols of `circuit` into a new circuit and replaces them with
+/// a single symbol instantiating it. Nets that connect the selection to the rest of
+/// the circuit become ports of the new symbol.
+#[derive(Event, Debug)]
+pub struct CreateSubCircuit {
+    pub circuit: CircuitID,
+}
+
+/// Registers `circuit` as a kind of chip that can be placed in other circuits, named like the
+/// circuit. Its input and output symbols become the ports of the chip, ordered top to bottom.
+#[derive(Event, Debug)]
+pub struct CreateChip {
+    pub circuit: CircuitID,
+}
+
+/// Requests the circuit instantiated by the sub-circuit symbol `instance` to be shown.
+/// `viewport` is the viewport the symbol was entered from.
+#[derive(Event, Debug)]
+pub struct EnterSubCircuit {
+    pub viewport: Entity,
+    pub instance: Entity,
+    pub circuit: CircuitID,
+}
+
+/// A net crossing the boundary of the selection.
+struct BoundaryNet {
+    net: Entity,
+    name: SharedStr,
+    bit_width: BitWidth,
+    inside: Vec<(Entity, Option<Entity>)>,
+    drives_outside: bool,
+}
+
+type CircuitQuery<'w, 's> = Query<'w, 's, (&'static Name, Relations<Child>), With<Circuit>>;
+type SymbolChildrenQuery<'w, 's> = Query<'w, 's, (Entity, Relations<Child>), With<Symbol>>;
+type NetQuery<'w, 's> =
+    Query<'w, 's, (Entity, &'static Name, &'static BitWidth, Relations<Child>), With<Net>>;
+type EndpointQuery<'w, 's> = Query<'w, 's, (Entity, Option<&'static PortID>), With<Endpoint>>;
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn create_sub_circuit(
+    trigger: Trigger<CreateSubCircuit>,
+    mut commands: Commands,
+    mut selection: ResMut<SelectionSet>,
+    grid_size: Res<GridSize>,
+    symbol_registry: Res<SymbolRegistry>,
+    circuits: CircuitQuery,
+    symbols: SymbolChildrenQuery,
+    ports: Query<(Entity, &PortDirection), With<Port>>,
+    nets: NetQuery,
+    endpoints: EndpointQuery,
+    designators: Query<(&DesignatorPrefix, &DesignatorNumber), With<Symbol>>,
+    mut bounding_boxes: Query<&mut AbsoluteBoundingBox>,
+    mut spatial_indices: Query<&mut SpatialIndex, With<Circuit>>,
+) {
+    let circuit = trigger.event().circuit;
+    let Ok((circuit_name, circuit_edges)) = circuits.get(circuit.0) else {
+        return;
+    };
+
+    let mut inner_symbols = Vec::new();
+    let mut inner_ports = HashSet::new();
+    let mut designator_number = 0;
+    circuit_edges
+        .join::<Child>(&symbols)
+        .for_each(|(symbol, symbol_edges)| {
+            if let Ok((prefix, number)) = designators.get(symbol) {
+                if &*prefix.0 == "X" {
+                    designator_number = designator_number.max(number.0 + 1);
+                }
+            }
+
+            if selection.contains(symbol) {
+                inner_symbols.push(symbol);
+                symbol_edges.join::<Child>(&ports).for_each(|(port, _)| {
+                    inner_ports.insert(port);
+                });
+            }
+        });
+    if inner_symbols.is_empty() {
+        return;
+    }
+
+    let mut selection_bounds: Option<BoundingBox> = None;
+    for &symbol in &inner_symbols {
+        if let Ok(bounds) = bounding_boxes.get(symbol) {
+            selection_bounds = Some(match selection_bounds {
+                Some(selection_bounds) => BoundingBox::from_points(
+                    selection_bounds.min().min(bounds.min()),
+                    selection_bounds.max().max(bounds.max()),
+                ),
+                None => **bounds,
+            });
+        }
+    }
+    let Some(selection_bounds) = selection_bounds else {
+        return;
+    };
+
+    // nets with all endpoints inside move along, nets with endpoints on both sides are split
+    let mut inner_nets = Vec::new();
+    let mut boundary_nets = Vec::new();
+    circuit_edges
+        .join::<Child>(&nets)
+        .for_each(|(net, name, &bit_width, net_edges)| {
+            let mut inside = Vec::new();
+            let mut outside = 0;
+            let mut drives_outside = false;
+
+            net_edges
+                .join::<Child>(&endpoints)
+                .for_each(|(endpoint, port_id)| {
+                    let port = port_id.map(|port_id| port_id.0);
+                    let is_inside = match port {
+                        Some(port) => inner_ports.contains(&port),
+                        None => selection.contains(net),
+                    };
+
+                    if is_inside {
+                        if let Some(port) = port {
+                            drives_outside |= ports
+                                .get(port)
+                                .is_ok_and(|(_, direction)| direction.is_output());
+                        }
+                        inside.push((endpoint, port));
+                    } else {
+                        outside += 1;
+                    }
+                });
+
+            if inside.is_empty() {
+                return;
+            }
+
+            if outside == 0 {
+                inner_nets.push(net);
+            } else {
+                boundary_nets.push(BoundaryNet {
+                    net,
+                    name: name.0.clone(),
+                    bit_width,
+                    inside,
+                    drives_outside,
+                });
+            }
+        });
+
+    let sub_circuit_name: SharedStr = format!("{}_X{}", circuit_name.0, designator_number).into();
+    let sub_circuit = commands
+        .spawn(CircuitBundle {
+            circuit: Circuit,
+            name: Name(sub_circuit_name.clone()),
+        })
+        .id();
+
+    // the moved entities are indexed again in the new circuit once their bounds are touched
+    let mut moved = Vec::new();
+    for &symbol in &inner_symbols {
+        commands.entity(symbol).set::<Child>(sub_circuit);
+        moved.push(symbol);
+        if let Ok((_, symbol_edges)) = symbols.get(symbol) {
+            symbol_edges
+                .join::<Child>(&ports)
+                .for_each(|(port, _)| moved.push(port));
+        }
+    }
+    for &net in &inner_nets {
+        commands.entity(net).set::<Child>(sub_circuit);
+        moved.push(net);
+        if let Ok((_, _, _, net_edges)) = nets.get(net) {
+            net_edges
+                .join::<Child>(&endpoints)
+                .for_each(|(endpoint, _)| moved.push(endpoint));
+        }
+    }
+
+    let input_count = boundary_nets
+        .iter()
+        .filter(|boundary| !boundary.drives_outside)
+        .count();
+    let output_count = boundary_nets.len() - input_count;
+    let height = SUB_CIRCUIT_PORT_SPACING * Fixed::from_u16(input_count.max(output_count) as u16);
+
+    let mut position = selection_bounds.center();
+    if grid_size.0 > fixed!(0) {
+        position = position.round_to_multiple(grid_size.0);
+    }
+
+    let instance = commands
+        .spawn((
+            Symbol,
+            Name(sub_circuit_name),
+            DesignatorPrefix(SharedStr::new_static("X")),
+            DesignatorNumber(designator_number),
+            Shape::Chip,
+            SubCircuit(sub_circuit),
+            TransformBundle {
+                transform: Transform {
+                    translation: position,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            VisibilityBundle::default(),
+            BoundingBoxBundle {
+                bounding_box: BoundingBox::from_top_left_size(
+                    Vec2 {
+                        x: fixed!(0),
+                        y: -SUB_CIRCUIT_PORT_SPACING,
+                    },
+                    SUB_CIRCUIT_WIDTH,
+                    height + SUB_CIRCUIT_PORT_SPACING,
+                ),
+                ..Default::default()
+            },
+        ))
+        .set::<Child>(circuit.0)
+        .id();
+
+    let (mut input_index, mut output_index) = (0u16, 0u16);
+    for boundary in boundary_nets {
+        let (instance_port_position, boundary_kind, boundary_position) = if boundary.drives_outside
+        {
+            let offset = SUB_CIRCUIT_PORT_SPACING * Fixed::from_u16(output_index);
+            output_index += 1;
+            (
+                Vec2 {
+                    x: SUB_CIRCUIT_WIDTH,
+                    y: offset,
+                },
+                SymbolKind::Out,
+                Vec2 {
+                    x: selection_bounds.max().x + BOUNDARY_SYMBOL_SPACING,
+                    y: selection_bounds.min().y
+                        + BOUNDARY_SYMBOL_SPACING * Fixed::from_u16(output_index),
+                },
+            )
+        } else {
+            let offset = SUB_CIRCUIT_PORT_SPACING * Fixed::from_u16(input_index);
+            input_index += 1;
+            (
+                Vec2 {
+                    x: fixed!(0),
+                    y: offset,
+                },
+                SymbolKind::In,
+                Vec2 {
+                    x: selection_bounds.min().x - BOUNDARY_SYMBOL_SPACING,
+                    y: selection_bounds.min().y
+                        + BOUNDARY_SYMBOL_SPACING * Fixed::from_u16(input_index),
+                },
+            )
+        };
+
+        // the outside keeps the original net, connected to a new port of the instance
+        let instance_port = build_port(
+            &mut commands,
+            instance,
+            boundary.name.clone(),
+            instance_port_position,
+            if boundary.drives_outside {
+                PortDirection::Output
+            } else {
+                PortDirection::Input
+            },
+            if boundary.drives_outside {
+                PortSide::Right
+            } else {
+                PortSide::Left
+            },
+            boundary.bit_width,
+        );
+        spawn_port_endpoint(&mut commands, boundary.net, instance_port);
+        reroute_net(&mut commands, circuit, boundary.net);
+
+        // the inside gets a new net, connected to an input or output symbol of the sub-circuit
+        let inner_net = commands
+            .spawn(NetBundle {
+                net: Net,
+                name: Name(boundary.name.clone()),
+                bit_width: boundary.bit_width,
+                visibility: VisibilityBundle::default(),
+            })
+            .set::<Child>(sub_circuit)
+            .id();
+
+        for &(endpoint, port) in &boundary.inside {
+            move_endpoint(&mut commands, endpoint, port, inner_net);
+            moved.push(endpoint);
+        }
+
+        let mut builder = symbol_registry.get(boundary_kind);
+        builder
+            .name(boundary.name)
+            .position(boundary_position)
+            .bit_width(boundary.bit_width);
+        builder.build(&mut commands, sub_circuit);
+        if let Some(port) = builder.ports().first() {
+            spawn_port_endpoint(&mut commands, inner_net, port.id);
+        }
+    }
+
+    if let Ok(mut spatial_index) = spatial_indices.get_mut(circuit.0) {
+        for &entity in &moved {
+            spatial_index.remove(entity);
+        }
+    }
+    for &entity in &moved {
+        if let Ok(mut bounds) = bounding_boxes.get_mut(entity) {
+            bounds.set_changed();
+        }
+    }
+
+    reroute_circuit(&mut commands, circuit);
+
+    selection.clear();
+    selection.select_all([instance]);
+}
+
+type BoundarySymbolQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        (&'static SymbolKind, &'static Name, &'static Transform),
+        Relations<Child>,
+    ),
+    With<Symbol>,
+>;
+
+pub(crate) fn create_chip(
+    trigger: Trigger<CreateChip>,
+    mut symbol_registry: ResMut<SymbolRegistry>,
+    circuits: CircuitQuery,
+    symbols: BoundarySymbolQuery,
+    ports: Query<&BitWidth, With<Port>>,
+) {
+    let circuit = trigger.event().circuit;
+    let Ok((circuit_name, circuit_edges)) = circuits.get(circuit.0) else {
+        return;
+    };
+
+    let mut boundary = Vec::new();
+    circuit_edges
+        .join::<Child>(&symbols)
+        .for_each(|((kind, name, transform), symbol_edges)| {
+            let direction = match kind {
+                SymbolKind::In => PortDirection::Input,
+                SymbolKind::Out => PortDirection::Output,
+                _ => return,
+            };
+
+            let mut bit_width = BitWidth(std::num::NonZeroU8::MIN);
+            symbol_edges
+                .join::<Child>(&ports)
+                .for_each(|&port_width| bit_width = port_width);
+            boundary.push((transform.translation, name.0.clone(), direction, bit_width));
+        });
+    boundary.sort_by_key(|&(position, ..)| (position.y, position.x));
+
+    let descriptor = SymbolKindDescriptor::chip(
+        circuit_name.0.clone(),
+        boundary
+            .into_iter()
+            .map(|(_, name, direction, bit_width)| (name, direction, bit_width)),
+    );
+    if let Err(error) = symbol_registry.register(descriptor) {
+        bevy_log::warn!("Cannot create a chip from `{}`: {error}", circuit_name.0);
+    }
+}
+
+/// Double-clicking a sub-circuit symbol enters the circuit it instantiates.
+pub(crate) fn enter_sub_circuit_on_double_click(
+    trigger: Trigger<DoubleClickEvent>,
+    hover_query: Query<&HoveredEntity>,
+    active_tool: Res<ActiveTool>,
+    instances: Query<&SubCircuit, With<Symbol>>,
+    mut enter_events: EventWriter<EnterSubCircuit>,
+) {
+    let event = trigger.event();
+    let viewport = trigger.entity();
+
+    if (*active_tool != ActiveTool::Select) || (event.button != PointerButton::Primary) {
+        return;
+    }
+
+    let Some(instance) = hover_query.get(viewport).ok().and_then(|hovered| hovered.0) else {
+        return;
+    };
+    if let Ok(sub_circuit) = instances.get(instance) {
+        enter_events.send(EnterSubCircuit {
+            viewport,
+            instance,
+            circuit: CircuitID(sub_circuit.0),
+        });
+    }
+}
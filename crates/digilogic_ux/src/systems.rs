@@ -1,13 +1,28 @@
-use super::{EntityOffset, HoveredEntity, MouseIdle, MouseMoving, MouseState};
+use super::{
+    AlignmentGuide, AlignmentGuides, CursorPosition, EntityOffset, HoverCandidates, HoveredEntity,
+    MouseIdle, MouseMoving, MouseState, PortStub, ReconnectingEndpoint, SelectionBox,
+};
+use crate::graphics::{is_on_outline, GRAPHIC_HOVER_DISTANCE};
+use crate::groups::Groups;
+use crate::nets::{closest_segment, is_rip_click, EndpointConnections};
+use crate::selection::RegionQuery;
 use crate::spatial_index::SpatialIndex;
-use crate::{ClickEvent, DragEvent, DragType, HoverEvent, MoveEntity, PointerButton};
+use crate::{
+    ActiveTool, ClickEvent, DragEvent, DragType, EndNudge, GridSize, HoverEvent, MirrorSelection,
+    MoveEntity, NudgeSelection, PlaceSymbol, PlacementKind, PointerButton, RotateSelection,
+    SelectionMoved, SelectionSet, SetDeMorgan, SetLocked,
+};
 use aery::prelude::*;
 use bevy_ecs::prelude::*;
 use bevy_state::prelude::*;
 use digilogic_core::states::SimulationState;
-use digilogic_core::transform::{BoundingBox, GlobalTransform, Transform, Vec2};
-use digilogic_core::Fixed;
+use digilogic_core::symbol::SymbolRegistry;
+use digilogic_core::transform::{
+    AbsoluteBoundingBox, BoundingBox, Direction, GlobalTransform, Rotation, Transform, Vec2,
+};
 use digilogic_core::{components::*, fixed};
+use digilogic_core::{Fixed, HashSet};
+use digilogic_routing::Vertices;
 
 /// Called when a new viewport is added to the world.
 pub(crate) fn on_add_viewport_augment_with_fsm(
@@ -17,66 +32,217 @@ pub(crate) fn on_add_viewport_augment_with_fsm(
     commands
         .entity(trigger.entity())
         .insert(HoveredEntity::default())
+        .insert(HoverCandidates::default())
+        .insert(CursorPosition::default())
+        .insert(AlignmentGuides::default())
+        .insert(SelectionBox::default())
+        .insert(PortStub::default())
         .insert(MouseState::Idle)
         .observe(hover_system)
         .observe(mouse_click_inputs)
-        .observe(mouse_drag_system);
+        .observe(select_on_click)
+        .observe(crate::nets::rip_segment_on_click)
+        .observe(place_symbol_on_click)
+        .observe(crate::annotations::place_annotation_on_click)
+        .observe(crate::graphics::draw_graphic_on_drag)
+        .observe(crate::wires::draw_wire_on_drag)
+        .observe(mouse_drag_system)
+        .observe(box_select)
+        .observe(crate::io_stub::stub_from_port)
+        .observe(crate::waypoints::insert_waypoint_on_double_click)
+        .observe(crate::properties::edit_symbol_on_double_click)
+        .observe(crate::subcircuit::enter_sub_circuit_on_double_click)
+        .observe(crate::annotations::edit_annotation_on_double_click);
 }
 
+/// What kind of entity is hovered, entities of a higher kind are picked over lower ones.
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[repr(u8)]
-enum HoveredEntityKind {
+pub(crate) enum HoveredEntityKind {
     #[default]
     Other,
     Net,
+    Symbol,
+    Waypoint,
     Endpoint,
     Port,
 }
 
-type EntityKindQuery<'w, 's> = Query<'w, 's, (Has<Port>, Has<Endpoint>, Has<Net>)>;
+type EntityKindQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Has<Port>,
+        Has<Endpoint>,
+        Has<Waypoint>,
+        Has<Symbol>,
+        Has<Net>,
+    ),
+>;
 
-fn hover_system(
-    trigger: Trigger<HoverEvent>,
+/// How far from a wire the cursor still hovers it
+const WIRE_HOVER_DISTANCE: Fixed = fixed!(4);
+
+fn hover_system(trigger: Trigger<HoverEvent>, mut cursor_positions: Query<&mut CursorPosition>) {
+    if let Ok(mut cursor_position) = cursor_positions.get_mut(trigger.entity()) {
+        // Don't trigger change detection if nothing changed.
+        if cursor_position.0 != trigger.event().pos {
+            cursor_position.0 = trigger.event().pos;
+        }
+    }
+}
+
+/// Moves the `Hovered` marker to a different entity.
+fn set_hovered_entity(
+    commands: &mut Commands,
+    hovered_entity: &mut HoveredEntity,
+    new_hovered_entity: Option<Entity>,
+) {
+    if new_hovered_entity == hovered_entity.0 {
+        return;
+    }
+
+    if let Some(current_hovered_entity) = hovered_entity.0 {
+        // the hovered entity may have been despawned in the meantime
+        if let Some(mut entity) = commands.get_entity(current_hovered_entity) {
+            entity.remove::<Hovered>();
+        }
+    }
+    if let Some(new_hovered_entity) = new_hovered_entity {
+        commands.entity(new_hovered_entity).insert(Hovered);
+    }
+
+    hovered_entity.0 = new_hovered_entity;
+}
+
+type HoverViewportQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static CircuitID,
+        &'static CursorPosition,
+        &'static mut HoveredEntity,
+        &'static mut HoverCandidates,
+    ),
+    With<Viewport>,
+>;
+
+/// Picks the entity under the cursor of each viewport every frame, so entities that move
+/// under a resting cursor are hovered as well.
+pub(crate) fn update_hovered_entities(
     mut commands: Commands,
+    mut viewports: HoverViewportQuery,
     circuits: Query<&SpatialIndex, With<Circuit>>,
     entity_kind_query: EntityKindQuery,
-    mut current_hovered_entity: Query<&mut HoveredEntity>,
+    nets: Query<&Vertices, With<Net>>,
+    graphics: Query<(&Graphic, &GlobalTransform)>,
+    mut found: Local<Vec<(HoveredEntityKind, Entity)>>,
 ) {
-    let spatial_index = circuits
-        .get(trigger.event().circuit.0)
-        .expect("invalid circuit ID");
+    for (circuit, cursor_position, mut hovered_entity, mut candidates) in viewports.iter_mut() {
+        let Ok(spatial_index) = circuits.get(circuit.0) else {
+            continue;
+        };
 
-    let position = trigger.event().pos;
-    let viewport = trigger.entity();
-    let bounds = BoundingBox::from_center_half_size(position, Fixed::EPSILON, Fixed::EPSILON);
-
-    let mut new_hovered_entity = None;
-    let mut new_hovered_entity_kind = HoveredEntityKind::default();
-    spatial_index.query(bounds, |&entity| {
-        let (is_port, is_endpoint, is_net) = entity_kind_query.get(entity).unwrap_or_default();
-        let kind = match (is_port, is_endpoint, is_net) {
-            (true, _, _) => HoveredEntityKind::Port,
-            (_, true, _) => HoveredEntityKind::Endpoint,
-            (_, _, true) => HoveredEntityKind::Net,
-            _ => HoveredEntityKind::Other,
+        let position = cursor_position.0;
+        let bounds = BoundingBox::from_center_half_size(position, Fixed::EPSILON, Fixed::EPSILON);
+
+        found.clear();
+        spatial_index.query(bounds, |&entity| {
+            let (is_port, is_endpoint, is_waypoint, is_symbol, is_net) =
+                entity_kind_query.get(entity).unwrap_or_default();
+            let kind = match (is_port, is_endpoint, is_waypoint, is_symbol, is_net) {
+                (true, _, _, _, _) => HoveredEntityKind::Port,
+                (_, true, _, _, _) => HoveredEntityKind::Endpoint,
+                (_, _, true, _, _) => HoveredEntityKind::Waypoint,
+                (_, _, _, true, _) => HoveredEntityKind::Symbol,
+                (_, _, _, _, true) => HoveredEntityKind::Net,
+                _ => HoveredEntityKind::Other,
+            };
+
+            // the bounding boxes of wires are padded, check the segments themselves
+            if kind == HoveredEntityKind::Net {
+                let Ok(vertices) = nets.get(entity) else {
+                    return;
+                };
+
+                let is_on_wire = closest_segment(vertices, position).is_some_and(|(_, point)| {
+                    point.manhatten_distance_to(position) <= WIRE_HOVER_DISTANCE
+                });
+                if !is_on_wire {
+                    return;
+                }
+            }
+
+            // graphics are only hovered near their outline
+            if let Ok((graphic, transform)) = graphics.get(entity) {
+                let point = position - transform.translation;
+                if !is_on_outline(graphic, point, GRAPHIC_HOVER_DISTANCE) {
+                    return;
+                }
+            }
+
+            found.push((kind, entity));
+        });
+
+        // highest priority first, the order within a kind only has to be stable
+        found.sort_unstable_by(|a, b| b.cmp(a));
+        found.dedup();
+
+        if !candidates
+            .entities
+            .iter()
+            .copied()
+            .eq(found.iter().map(|&(_, entity)| entity))
+        {
+            candidates.entities.clear();
+            candidates
+                .entities
+                .extend(found.iter().map(|&(_, entity)| entity));
+            candidates.index = 0;
+        }
+
+        let new_hovered_entity = candidates.entities.get(candidates.index).copied();
+        set_hovered_entity(&mut commands, &mut hovered_entity, new_hovered_entity);
+    }
+}
+
+/// Extends hovering a wire, endpoint or waypoint to its whole net, so every wire of the net
+/// and the ports it connects light up together.
+pub(crate) fn hover_nets(
+    mut commands: Commands,
+    viewports: Query<&HoveredEntity, With<Viewport>>,
+    children: Query<(Entity, Relations<Child>)>,
+    nets: Query<Has<Hovered>, With<Net>>,
+    hovered_nets: Query<Entity, (With<Net>, With<Hovered>)>,
+    mut desired: Local<Vec<Entity>>,
+) {
+    desired.clear();
+    for hovered_entity in viewports.iter() {
+        let Some(hovered_entity) = hovered_entity.0 else {
+            continue;
         };
 
-        if kind >= new_hovered_entity_kind {
-            new_hovered_entity = Some(entity);
-            new_hovered_entity_kind = kind;
+        if nets.contains(hovered_entity) && !desired.contains(&hovered_entity) {
+            desired.push(hovered_entity);
         }
-    });
+        children
+            .traverse::<Up<Child>>(std::iter::once(hovered_entity))
+            .for_each(|&mut entity, _| {
+                if nets.contains(entity) && !desired.contains(&entity) {
+                    desired.push(entity);
+                }
+            });
+    }
 
-    let mut current_hovered_entity = current_hovered_entity.get_mut(viewport).unwrap();
-    if new_hovered_entity != current_hovered_entity.0 {
-        if let Some(current_hovered_entity) = current_hovered_entity.0 {
-            commands.entity(current_hovered_entity).remove::<Hovered>();
+    for net in hovered_nets.iter() {
+        if !desired.contains(&net) {
+            commands.entity(net).remove::<Hovered>();
         }
-        if let Some(new_hovered_entity) = new_hovered_entity {
-            commands.entity(new_hovered_entity).insert(Hovered);
+    }
+    for &net in desired.iter() {
+        if nets.get(net).is_ok_and(|hovered| !hovered) {
+            commands.entity(net).insert(Hovered);
         }
-
-        current_hovered_entity.0 = new_hovered_entity;
     }
 }
 
@@ -85,6 +251,7 @@ fn mouse_click_inputs(
     hover_query: Query<&HoveredEntity>,
     mut input_query: Query<(&SymbolKind, &mut LogicState), With<Symbol>>,
     simulation: Res<State<SimulationState>>,
+    active_tool: Res<ActiveTool>,
     mut eval_event: EventWriter<digilogic_netcode::Eval>,
 ) {
     let event = trigger.event();
@@ -94,6 +261,10 @@ fn mouse_click_inputs(
         return;
     }
 
+    if *active_tool != ActiveTool::Select {
+        return;
+    }
+
     if event.button != PointerButton::Primary {
         return;
     }
@@ -124,12 +295,158 @@ fn mouse_click_inputs(
     }
 }
 
+fn select_on_click(
+    trigger: Trigger<ClickEvent>,
+    mut commands: Commands,
+    mut hover_query: Query<(&mut HoveredEntity, &mut HoverCandidates)>,
+    active_tool: Res<ActiveTool>,
+    mut selection: ResMut<SelectionSet>,
+    groups: Groups,
+) {
+    let event = trigger.event();
+    let viewport = trigger.entity();
+
+    if (*active_tool != ActiveTool::Select) || (event.button != PointerButton::Primary) {
+        return;
+    }
+    if is_rip_click(event) {
+        return;
+    }
+
+    let (mut hovered_entity, mut candidates) = hover_query.get_mut(viewport).unwrap();
+
+    // clicking the selected entity again, or Alt+click, picks the next entity under the cursor
+    let is_repeated_click = hovered_entity
+        .0
+        .is_some_and(|entity| (selection.len() == 1) && selection.contains(entity))
+        && !event.modifiers.shift;
+    if (is_repeated_click || event.modifiers.alt) && (candidates.entities.len() > 1) {
+        candidates.index = (candidates.index + 1) % candidates.entities.len();
+        let next_entity = candidates.entities[candidates.index];
+        set_hovered_entity(&mut commands, &mut hovered_entity, Some(next_entity));
+    }
+
+    // grouped symbols are selected together with the rest of their group
+    match (hovered_entity.0, event.modifiers.shift) {
+        (Some(hovered_entity), true) => {
+            let unit = groups.unit(hovered_entity);
+            if selection.contains(hovered_entity) {
+                for entity in unit {
+                    selection.remove(entity);
+                }
+            } else {
+                selection.select_all(unit);
+            }
+        }
+        (Some(hovered_entity), false) => {
+            selection.clear();
+            selection.select_all(groups.unit(hovered_entity));
+        }
+        (None, true) => (),
+        (None, false) => {
+            // Don't trigger change detection if nothing changed.
+            if !selection.is_empty() {
+                selection.clear();
+            }
+        }
+    }
+}
+
+fn place_symbol_on_click(
+    trigger: Trigger<ClickEvent>,
+    mut commands: Commands,
+    mut active_tool: ResMut<ActiveTool>,
+    placement_kind: Res<PlacementKind>,
+) {
+    let event = trigger.event();
+
+    if *active_tool != ActiveTool::PlaceSymbol {
+        return;
+    }
+
+    if event.button == PointerButton::Secondary {
+        *active_tool = ActiveTool::Select;
+        return;
+    }
+
+    if let Some(kind) = placement_kind.0 {
+        commands.trigger(PlaceSymbol {
+            circuit: event.circuit,
+            kind,
+            pos: event.pos,
+        });
+    }
+}
+
+pub(crate) fn place_symbol(
+    trigger: Trigger<PlaceSymbol>,
+    mut commands: Commands,
+    symbol_registry: Res<SymbolRegistry>,
+    children: Query<(Entity, Relations<Child>)>,
+    designators: DesignatorQuery,
+) {
+    let event = trigger.event();
+    let kind = event.kind;
+
+    let mut builder = symbol_registry.get(kind);
+    let designator_number = next_designator_number(
+        &symbol_registry,
+        &children,
+        &designators,
+        event.circuit,
+        kind,
+    );
+
+    builder
+        .position(event.pos)
+        .designator_number(designator_number)
+        .build(&mut commands, event.circuit.0);
+}
+
+pub(crate) type DesignatorQuery<'w, 's> =
+    Query<'w, 's, (&'static DesignatorPrefix, &'static DesignatorNumber), With<Symbol>>;
+
+/// The designator number for a new symbol of `kind`,
+/// the lowest one not used by a symbol with the same prefix yet.
+pub(crate) fn next_designator_number(
+    symbol_registry: &SymbolRegistry,
+    children: &Query<(Entity, Relations<Child>)>,
+    designators: &DesignatorQuery,
+    circuit: CircuitID,
+    kind: SymbolKind,
+) -> u32 {
+    let prefix = symbol_registry
+        .get_def(kind)
+        .map(|def| def.designator_prefix().clone());
+
+    let mut used = HashSet::default();
+    children
+        .traverse::<Child>(std::iter::once(circuit.0))
+        .for_each(|&mut entity, _| {
+            if let Ok((other_prefix, other_number)) = designators.get(entity) {
+                if prefix.as_ref() == Some(&other_prefix.0) {
+                    used.insert(other_number.0);
+                }
+            }
+        });
+    (0..)
+        .find(|number| !used.contains(number))
+        .unwrap_or_default()
+}
+
+#[allow(clippy::too_many_arguments)]
 fn mouse_drag_system(
     trigger: Trigger<DragEvent>,
     mut commands: Commands,
     moving_query: Query<&MouseMoving>,
-    hover_query: Query<&HoveredEntity>,
+    hover_query: Query<(&HoveredEntity, &SelectionBox)>,
+    reconnecting_query: Query<&ReconnectingEndpoint>,
     transform_query: Query<(&Transform, Has<Port>)>,
+    locked: Query<(), With<Locked>>,
+    groups: Groups,
+    mut endpoint_connections: EndpointConnections,
+    active_tool: Res<ActiveTool>,
+    selection: Res<SelectionSet>,
     mut move_events: EventWriter<MoveEntity>,
 ) {
     let event = trigger.event();
@@ -139,21 +456,77 @@ fn mouse_drag_system(
         return;
     }
 
+    let (hovered_entity, selection_box) = hover_query.get(viewport).unwrap();
+    if selection_box.0.is_some() {
+        return;
+    }
+
     let moving = if let Ok(moving) = moving_query.get(viewport) {
+        // a drag that is already in progress finishes even if the tool changed in between
         moving
+    } else if *active_tool != ActiveTool::Select {
+        // the other tools are handled by their own systems
+        return;
     } else {
-        // TODO: offset_list should be populated with all selected entities, for now just use the hovered entity
         let mut offset_list = Vec::new();
-        let hovered_entity = hover_query.get(viewport).unwrap();
         if let Some(hovered_entity) = hovered_entity.0 {
-            if let Ok((transform, is_port)) = transform_query.get(hovered_entity) {
+            // ports are hovered over the endpoints connected to them, so grab the endpoint instead
+            let endpoint = if endpoint_connections.is_endpoint(hovered_entity) {
+                Some(hovered_entity)
+            } else {
+                endpoint_connections.endpoint_at_port(hovered_entity)
+            };
+
+            let endpoint_locked = endpoint.is_some_and(|endpoint| {
+                endpoint_connections
+                    .net_of(endpoint)
+                    .is_some_and(|net| locked.contains(net))
+            });
+
+            if endpoint_locked || locked.contains(hovered_entity) {
+                // locked entities stay where they are
+            } else if let Some(endpoint) = endpoint {
+                // dragging an endpoint detaches it so it can be dropped onto another port
+                if let Some(position) = endpoint_connections.disconnect(&mut commands, endpoint) {
+                    commands
+                        .entity(viewport)
+                        .insert(ReconnectingEndpoint(endpoint));
+                    offset_list.push(EntityOffset {
+                        entity: endpoint,
+                        offset: position - event.pos,
+                    });
+                }
+            } else if let Ok((transform, is_port)) = transform_query.get(hovered_entity) {
                 if is_port {
-                    // TODO: enter wire drawing mode
+                    // wires are drawn from ports with the wire tool, see `draw_wire_on_drag`
+                } else if selection.contains(hovered_entity) {
+                    // dragging a selected entity moves the whole selection
+                    for entity in selection.iter().filter(|&entity| !locked.contains(entity)) {
+                        if let Ok((transform, false)) = transform_query.get(entity) {
+                            offset_list.push(EntityOffset {
+                                entity,
+                                offset: transform.translation - event.pos,
+                            });
+                        }
+                    }
                 } else {
                     offset_list.push(EntityOffset {
                         entity: hovered_entity,
                         offset: transform.translation - event.pos,
                     });
+
+                    // the rest of its group comes along
+                    for entity in groups.unit(hovered_entity) {
+                        if entity == hovered_entity || locked.contains(entity) {
+                            continue;
+                        }
+                        if let Ok((transform, false)) = transform_query.get(entity) {
+                            offset_list.push(EntityOffset {
+                                entity,
+                                offset: transform.translation - event.pos,
+                            });
+                        }
+                    }
                 }
             }
         }
@@ -179,29 +552,182 @@ fn mouse_drag_system(
     }
 
     if event.drag_type == DragType::End {
+        if let Ok(&ReconnectingEndpoint(endpoint)) = reconnecting_query.get(viewport) {
+            let offset = moving
+                .iter()
+                .find(|entity_offset| entity_offset.entity == endpoint)
+                .map(|entity_offset| entity_offset.offset)
+                .unwrap_or_default();
+            let position = event.pos + offset;
+            endpoint_connections.connect_at(&mut commands, event.circuit, endpoint, position);
+            commands.entity(viewport).remove::<ReconnectingEndpoint>();
+        }
+
         commands.entity(viewport).remove::<MouseMoving>();
         commands.entity(viewport).insert(MouseIdle);
     }
 }
 
+/// Dragging from an empty spot selects the entities inside the dragged region.
+/// Shift keeps the current selection, Alt includes locked entities.
+fn box_select(
+    trigger: Trigger<DragEvent>,
+    mut viewports: Query<(&HoveredEntity, &mut SelectionBox, Has<MouseMoving>)>,
+    active_tool: Res<ActiveTool>,
+    mut selection: ResMut<SelectionSet>,
+    region: RegionQuery,
+    groups: Groups,
+) {
+    let event = trigger.event();
+    let viewport = trigger.entity();
+
+    if event.button != PointerButton::Primary {
+        return;
+    }
+
+    let Ok((hovered_entity, mut selection_box, moving)) = viewports.get_mut(viewport) else {
+        return;
+    };
+
+    match event.drag_type {
+        DragType::Start => {
+            if (*active_tool == ActiveTool::Select) && hovered_entity.0.is_none() && !moving {
+                selection_box.0 = Some((event.pos, event.pos));
+            }
+        }
+        DragType::Dragging => {
+            if let Some((_, current)) = &mut selection_box.0 {
+                *current = event.pos;
+            }
+        }
+        DragType::End => {
+            let Some(bounds) = selection_box.bounds() else {
+                return;
+            };
+            selection_box.0 = None;
+
+            let entities = region.entities_in(event.circuit, bounds, event.modifiers.alt);
+            if !event.modifiers.shift {
+                selection.clear();
+            }
+            for entity in entities {
+                selection.select_all(groups.unit(entity));
+            }
+        }
+    }
+}
+
 const SNAP_CANDIDATE_DISTANCE: Fixed = fixed!(500);
 const SNAP_DISTANCE: Fixed = fixed!(7);
 
-/// Move entities while snapping the entity's ports to nearby ports
+/// The closest alignment found so far along one axis
+#[derive(Debug, Clone, Copy)]
+struct Alignment {
+    dist: Fixed,
+    delta: Fixed,
+    /// The point aligned to
+    anchor: Vec2,
+    /// The point of the moved entity that gets aligned
+    point: Vec2,
+}
+
+impl Default for Alignment {
+    fn default() -> Self {
+        Self {
+            dist: Fixed::MAX_INT,
+            delta: fixed!(0),
+            anchor: Vec2::ZERO,
+            point: Vec2::ZERO,
+        }
+    }
+}
+
+impl Alignment {
+    fn consider_x(&mut self, point: Vec2, anchor: Vec2) {
+        let dist = (point.x - anchor.x).abs();
+        if dist < self.dist {
+            *self = Self {
+                dist,
+                delta: anchor.x - point.x,
+                anchor,
+                point,
+            };
+        }
+    }
+
+    fn consider_y(&mut self, point: Vec2, anchor: Vec2) {
+        let dist = (point.y - anchor.y).abs();
+        if dist < self.dist {
+            *self = Self {
+                dist,
+                delta: anchor.y - point.y,
+                anchor,
+                point,
+            };
+        }
+    }
+
+    /// The snap offset, if the closest alignment is close enough to snap to
+    fn snap_delta(&self) -> Option<Fixed> {
+        (self.dist <= SNAP_DISTANCE).then_some(self.delta)
+    }
+}
+
+/// The points of a bounding box that get aligned with other bounding boxes,
+/// the middle of its left and right edges and of its top and bottom edges.
+fn edge_points(bounds: BoundingBox) -> ([Vec2; 2], [Vec2; 2]) {
+    let center = bounds.center();
+    (
+        [
+            Vec2 {
+                x: bounds.min().x,
+                y: center.y,
+            },
+            Vec2 {
+                x: bounds.max().x,
+                y: center.y,
+            },
+        ],
+        [
+            Vec2 {
+                x: center.x,
+                y: bounds.min().y,
+            },
+            Vec2 {
+                x: center.x,
+                y: bounds.max().y,
+            },
+        ],
+    )
+}
+
+/// Move entities while snapping them to the grid, the entity's ports to nearby ports
+/// and the entity's edges to the edges of nearby symbols
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn move_entities_with_snap(
     mut events: EventReader<MoveEntity>,
+    grid_size: Res<GridSize>,
     spatial_indices: Query<&SpatialIndex, With<Circuit>>,
     children: Query<(Entity, Relations<Child>)>,
     port_transform_query: Query<&GlobalTransform, With<Port>>,
+    symbol_bounds_query: Query<&AbsoluteBoundingBox, With<Symbol>>,
+    endpoint_query: Query<(), With<Endpoint>>,
     mut transform_query: Query<&mut Transform, Without<Port>>,
+    mut guides_query: Query<&mut AlignmentGuides>,
     mut port_positions: Local<Vec<Vec2>>,
     mut excluded_ports: Local<Vec<Entity>>,
+    mut cleared_viewports: Local<Vec<Entity>>,
 ) {
+    cleared_viewports.clear();
+
     // for each MoveEntity event
     for event in events.read() {
         // find the transform for the entity
         if let Ok(mut transform) = transform_query.get_mut(event.entity) {
-            let proposed_pos = event.pos + event.offset;
+            let mut proposed_pos = event.pos + event.offset;
+            if grid_size.0 > fixed!(0) {
+                proposed_pos = proposed_pos.round_to_multiple(grid_size.0);
+            }
             let delta = proposed_pos - transform.translation;
 
             // find all ports for the entity
@@ -216,22 +742,27 @@ pub(crate) fn move_entities_with_snap(
                     }
                 });
 
-            let mut x_delta = fixed!(0);
-            let mut x_dist = Fixed::MAX_INT;
+            // dragged endpoints snap onto ports themselves
+            if endpoint_query.contains(event.entity) {
+                port_positions.push(proposed_pos);
+            }
 
-            let mut y_delta = fixed!(0);
-            let mut y_dist = Fixed::MAX_INT;
+            let moved_bounds = symbol_bounds_query
+                .get(event.entity)
+                .ok()
+                .map(|bounds| edge_points(bounds.translate(delta)));
 
-            let mut comparisons = 0;
+            let mut x_alignment = Alignment::default();
+            let mut y_alignment = Alignment::default();
 
-            // check all entities within SNAP_CANDIDATE_DISTANCE for ports to snap to
+            // check all entities within SNAP_CANDIDATE_DISTANCE for ports and edges to snap to
             let snap_vec = Vec2 {
                 x: SNAP_CANDIDATE_DISTANCE,
                 y: SNAP_CANDIDATE_DISTANCE,
             };
             let bbox = BoundingBox::from_points(proposed_pos - snap_vec, proposed_pos + snap_vec);
 
-            // scan the spatial index for ports within bbox
+            // scan the spatial index for ports and symbols within bbox
             let spatial_index = spatial_indices
                 .get(event.circuit.0)
                 .expect("CircuitID is invalid on MoveEntity event");
@@ -243,40 +774,311 @@ pub(crate) fn move_entities_with_snap(
                     }
 
                     // for each of the moved entity's ports
-                    for port_pos in port_positions.iter() {
-                        // check if the x coordinate of the port is closer, if so, record it
-                        let dx = (port_pos.x - candidate_transform.translation.x).abs();
-                        if dx < x_dist {
-                            x_dist = dx;
-                            x_delta = candidate_transform.translation.x - port_pos.x;
-                        }
+                    for &port_pos in port_positions.iter() {
+                        x_alignment.consider_x(port_pos, candidate_transform.translation);
+                        y_alignment.consider_y(port_pos, candidate_transform.translation);
+                    }
+                } else if let Ok(candidate_bounds) = symbol_bounds_query.get(*entity) {
+                    if *entity == event.entity {
+                        // do not snap to our own edges
+                        return;
+                    }
 
-                        // check if the y coordinate of the port is closer, if so, record it
-                        let dy = (port_pos.y - candidate_transform.translation.y).abs();
-                        if dy < y_dist {
-                            y_dist = dy;
-                            y_delta = candidate_transform.translation.y - port_pos.y;
-                        }
+                    let Some((moved_x_points, moved_y_points)) = moved_bounds else {
+                        return;
+                    };
 
-                        comparisons += 1;
+                    let (x_points, y_points) = edge_points(**candidate_bounds);
+                    for &point in moved_x_points.iter() {
+                        for &anchor in x_points.iter() {
+                            x_alignment.consider_x(point, anchor);
+                        }
+                    }
+                    for &point in moved_y_points.iter() {
+                        for &anchor in y_points.iter() {
+                            y_alignment.consider_y(point, anchor);
+                        }
                     }
                 }
             });
 
             // if the closest coordinates are too far away, don't snap to them
-            if x_dist > SNAP_DISTANCE {
-                x_delta = fixed!(0);
+            let x_delta = x_alignment.snap_delta();
+            let y_delta = y_alignment.snap_delta();
+
+            // update the position with any snap delta added
+            let snap_delta = Vec2 {
+                x: x_delta.unwrap_or_default(),
+                y: y_delta.unwrap_or_default(),
+            };
+            let translation = proposed_pos + snap_delta;
+
+            // only trigger change detection, and with it rerouting, if the entity actually moved
+            if transform.translation != translation {
+                transform.translation = translation;
             }
-            if y_dist > SNAP_DISTANCE {
-                y_delta = fixed!(0);
+
+            // show what the entity has been aligned to
+            if let Ok(mut guides) = guides_query.get_mut(event.viewport) {
+                if !cleared_viewports.contains(&event.viewport) {
+                    cleared_viewports.push(event.viewport);
+                    guides.clear();
+                }
+
+                if x_delta.is_some() {
+                    let Alignment { anchor, point, .. } = x_alignment;
+                    guides.push(AlignmentGuide {
+                        from: anchor,
+                        to: Vec2 {
+                            x: anchor.x,
+                            y: point.y + snap_delta.y,
+                        },
+                    });
+                }
+                if y_delta.is_some() {
+                    let Alignment { anchor, point, .. } = y_alignment;
+                    guides.push(AlignmentGuide {
+                        from: anchor,
+                        to: Vec2 {
+                            x: point.x + snap_delta.x,
+                            y: anchor.y,
+                        },
+                    });
+                }
             }
+        }
+    }
+}
 
-            // update the position with any snap delta added
-            transform.translation = proposed_pos
-                + Vec2 {
-                    x: x_delta,
-                    y: y_delta,
-                };
+/// Hide the alignment guides once a drag ends
+pub(crate) fn clear_alignment_guides(
+    mut guides_query: Query<&mut AlignmentGuides, Without<MouseMoving>>,
+) {
+    for mut guides in guides_query.iter_mut() {
+        // Don't trigger change detection if nothing changed.
+        if !guides.is_empty() {
+            guides.clear();
+        }
+    }
+}
+
+/// Locked symbols are left out, so they stay in place when the selection is transformed.
+type SelectedSymbolQuery<'w, 's> = Query<
+    'w,
+    's,
+    (&'static mut Transform, &'static AbsoluteBoundingBox),
+    (With<Symbol>, Without<Locked>),
+>;
+
+/// The center of the combined bounding box of the selected symbols, rounded to the grid.
+/// Rotating or mirroring around a grid point keeps the symbols on the grid.
+fn selection_center(
+    selection: &SelectionSet,
+    grid_size: GridSize,
+    symbols: &SelectedSymbolQuery,
+) -> Option<Vec2> {
+    let mut bounds: Option<BoundingBox> = None;
+    for entity in selection.iter() {
+        if let Ok((_, &bounding_box)) = symbols.get(entity) {
+            bounds = Some(match bounds {
+                Some(bounds) => BoundingBox::from_points(
+                    bounds.min().min(bounding_box.min()),
+                    bounds.max().max(bounding_box.max()),
+                ),
+                None => *bounding_box,
+            });
+        }
+    }
+
+    let mut center = bounds?.center();
+    if grid_size.0 > fixed!(0) {
+        center = center.round_to_multiple(grid_size.0);
+    }
+    Some(center)
+}
+
+/// Rotate the selected symbols around the center of their combined bounding box
+pub(crate) fn rotate_selection(
+    mut events: EventReader<RotateSelection>,
+    selection: Res<SelectionSet>,
+    grid_size: Res<GridSize>,
+    mut symbols: SelectedSymbolQuery,
+) {
+    for event in events.read() {
+        let Some(center) = selection_center(&selection, *grid_size, &symbols) else {
+            continue;
+        };
+
+        // ports, endpoints and wires follow through transform propagation and rerouting
+        for entity in selection.iter() {
+            if let Ok((mut transform, _)) = symbols.get_mut(entity) {
+                transform.translation =
+                    (transform.translation - center).rotate(event.rotation) + center;
+                transform.rotation *= event.rotation;
+            }
+        }
+    }
+}
+
+/// Mirror the selected symbols around the center of their combined bounding box
+pub(crate) fn mirror_selection(
+    mut events: EventReader<MirrorSelection>,
+    selection: Res<SelectionSet>,
+    grid_size: Res<GridSize>,
+    mut symbols: SelectedSymbolQuery,
+) {
+    for event in events.read() {
+        let Some(center) = selection_center(&selection, *grid_size, &symbols) else {
+            continue;
+        };
+
+        // a vertical flip is a horizontal one followed by a rotation by 180°
+        let flip = if event.vertical {
+            Transform {
+                translation: Vec2 {
+                    x: fixed!(0),
+                    y: center.y * fixed!(2),
+                },
+                rotation: Rotation::Rot180,
+                mirrored: true,
+                ..Transform::IDENTITY
+            }
+        } else {
+            Transform {
+                translation: Vec2 {
+                    x: center.x * fixed!(2),
+                    y: fixed!(0),
+                },
+                mirrored: true,
+                ..Transform::IDENTITY
+            }
+        };
+
+        for entity in selection.iter() {
+            if let Ok((mut transform, _)) = symbols.get_mut(entity) {
+                *transform = flip * *transform;
+            }
+        }
+    }
+}
+
+/// The nudges applied since the last [`EndNudge`].
+#[derive(Debug, Default, Resource)]
+pub(crate) struct NudgeBurst {
+    pub(crate) entities: Vec<Entity>,
+    delta: Vec2,
+}
+
+/// Move the selected symbols by whole grid units
+pub(crate) fn nudge_selection(
+    mut nudge_events: EventReader<NudgeSelection>,
+    mut end_events: EventReader<EndNudge>,
+    mut moved_events: EventWriter<SelectionMoved>,
+    mut burst: ResMut<NudgeBurst>,
+    selection: Res<SelectionSet>,
+    grid_size: Res<GridSize>,
+    mut symbols: SelectedSymbolQuery,
+) {
+    let unit = if grid_size.0 > fixed!(0) {
+        grid_size.0
+    } else {
+        fixed!(1)
+    };
+
+    for event in nudge_events.read() {
+        let distance = if event.large { unit * fixed!(10) } else { unit };
+        let delta = match event.direction {
+            Direction::PosX => Vec2 {
+                x: distance,
+                y: fixed!(0),
+            },
+            Direction::NegX => Vec2 {
+                x: -distance,
+                y: fixed!(0),
+            },
+            Direction::PosY => Vec2 {
+                x: fixed!(0),
+                y: distance,
+            },
+            Direction::NegY => Vec2 {
+                x: fixed!(0),
+                y: -distance,
+            },
+        };
+
+        let entities: Vec<Entity> = selection
+            .iter()
+            .filter(|&entity| symbols.contains(entity))
+            .collect();
+        if entities.is_empty() {
+            continue;
+        }
+
+        // changing the selection in the middle of a burst starts a new move
+        if burst.entities != entities {
+            let previous = std::mem::take(&mut *burst);
+            if !previous.entities.is_empty() {
+                moved_events.send(SelectionMoved {
+                    entities: previous.entities,
+                    delta: previous.delta,
+                });
+            }
+            burst.entities = entities;
+        }
+
+        // ports, endpoints and wires follow through transform propagation and rerouting
+        for &entity in &burst.entities {
+            if let Ok((mut transform, _)) = symbols.get_mut(entity) {
+                transform.translation += delta;
+            }
+        }
+        burst.delta += delta;
+    }
+
+    if end_events.read().count() > 0 {
+        let burst = std::mem::take(&mut *burst);
+        if !burst.entities.is_empty() {
+            moved_events.send(SelectionMoved {
+                entities: burst.entities,
+                delta: burst.delta,
+            });
+        }
+    }
+}
+
+pub(crate) fn set_de_morgan(
+    mut commands: Commands,
+    mut events: EventReader<SetDeMorgan>,
+    kinds: Query<&SymbolKind, With<Symbol>>,
+) {
+    for event in events.read() {
+        for &entity in &event.entities {
+            if !kinds
+                .get(entity)
+                .is_ok_and(|kind| kind.has_de_morgan_equivalent())
+            {
+                continue;
+            }
+
+            if event.de_morgan {
+                commands.entity(entity).insert(DeMorgan);
+            } else {
+                commands.entity(entity).remove::<DeMorgan>();
+            }
+        }
+    }
+}
+
+pub(crate) fn set_locked(mut commands: Commands, mut events: EventReader<SetLocked>) {
+    for event in events.read() {
+        for &entity in &event.entities {
+            if let Some(mut entity_commands) = commands.get_entity(entity) {
+                if event.locked {
+                    entity_commands.insert(Locked);
+                } else {
+                    entity_commands.remove::<Locked>();
+                }
+            }
         }
     }
 }
@@ -0,0 +1,85 @@
+use bevy_ecs::prelude::*;
+use bevy_reflect::prelude::*;
+use digilogic_core::components::{GraphicKind, SymbolKind};
+use digilogic_core::{fixed, Fixed};
+
+/// The tool currently selected in the tool bar.
+/// Pointer input on a viewport is interpreted according to this tool.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Resource, Reflect)]
+#[reflect(Resource)]
+pub enum ActiveTool {
+    /// Hover, click and drag entities
+    #[default]
+    Select,
+    /// Place new symbols into the circuit
+    PlaceSymbol,
+    /// Draw wires between ports
+    DrawWire,
+    /// Add text annotations
+    AddText,
+    /// Draw rectangles, lines and ellipses by dragging
+    DrawGraphic,
+    /// Pan the viewport with the primary button
+    Pan,
+    /// Zoom into a region drawn with the primary button
+    Zoom,
+}
+
+impl ActiveTool {
+    pub const ALL: &[Self] = &[
+        Self::Select,
+        Self::PlaceSymbol,
+        Self::DrawWire,
+        Self::AddText,
+        Self::DrawGraphic,
+        Self::Pan,
+        Self::Zoom,
+    ];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Select => "Select",
+            Self::PlaceSymbol => "Place Symbol",
+            Self::DrawWire => "Draw Wire",
+            Self::AddText => "Add Text",
+            Self::DrawGraphic => "Draw Shape",
+            Self::Pan => "Pan",
+            Self::Zoom => "Zoom",
+        }
+    }
+
+    /// Whether the primary pointer button pans the viewport.
+    /// The middle button always pans, regardless of the active tool.
+    #[inline]
+    pub const fn pans(self) -> bool {
+        matches!(self, Self::Pan)
+    }
+
+    /// Whether dragging with the primary button zooms into the dragged region.
+    /// Dragging with the secondary button while holding Ctrl always does.
+    #[inline]
+    pub const fn zooms(self) -> bool {
+        matches!(self, Self::Zoom)
+    }
+}
+
+/// The grid dragged symbols snap to. Zero disables grid snapping.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Resource, Reflect)]
+#[reflect(Resource)]
+pub struct GridSize(pub Fixed);
+
+impl Default for GridSize {
+    fn default() -> Self {
+        Self(fixed!(10))
+    }
+}
+
+/// The kind of symbol placed by [`ActiveTool::PlaceSymbol`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Resource, Reflect)]
+#[reflect(Resource)]
+pub struct PlacementKind(pub Option<SymbolKind>);
+
+/// The kind of graphic drawn by [`ActiveTool::DrawGraphic`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Resource, Reflect)]
+#[reflect(Resource)]
+pub struct GraphicPlacementKind(pub GraphicKind);
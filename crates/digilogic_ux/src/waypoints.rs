@@ -0,0 +1,200 @@
+use super::HoveredEntity;
+use crate::nets::closest_segment;
+use crate::{ActiveTool, DoubleClickEvent, GridSize, PointerButton};
+use aery::prelude::*;
+use bevy_ecs::prelude::*;
+use digilogic_core::bundles::{WaypointBundle, WAYPOINT_HALF_SIZE};
+use digilogic_core::components::*;
+use digilogic_core::transform::*;
+use digilogic_core::visibility::VisibilityBundle;
+use digilogic_core::{fixed, Fixed};
+use digilogic_routing::{Vertex, VertexKind, Vertices};
+
+/// Where in a chain of points a new point is inserted with the smallest detour.
+/// If `open_end` is set, the point can also be appended after the last point of the chain.
+fn insertion_index(chain: &[Vec2], point: Vec2, open_end: bool) -> usize {
+    let mut best_index = 0;
+    let mut best_cost = Fixed::MAX_INT;
+    for (index, pair) in chain.windows(2).enumerate() {
+        let cost = pair[0].manhatten_distance_to(point) + point.manhatten_distance_to(pair[1])
+            - pair[0].manhatten_distance_to(pair[1]);
+        if cost < best_cost {
+            best_index = index;
+            best_cost = cost;
+        }
+    }
+
+    if open_end {
+        if let Some(&last) = chain.last() {
+            if last.manhatten_distance_to(point) < best_cost {
+                best_index = chain.len() - 1;
+            }
+        }
+    }
+
+    best_index
+}
+
+type EndpointQuery<'w, 's> =
+    Query<'w, 's, ((Entity, &'static GlobalTransform), Relations<Child>), With<Endpoint>>;
+
+/// Double-clicking a wire inserts a waypoint at the clicked position.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn insert_waypoint_on_double_click(
+    trigger: Trigger<DoubleClickEvent>,
+    mut commands: Commands,
+    hover_query: Query<&HoveredEntity>,
+    active_tool: Res<ActiveTool>,
+    grid_size: Res<GridSize>,
+    nets: Query<(&Vertices, Relations<Child>), With<Net>>,
+    endpoints: EndpointQuery,
+    waypoints: Query<(Entity, &Number, &GlobalTransform), With<Waypoint>>,
+) {
+    let event = trigger.event();
+    let viewport = trigger.entity();
+
+    if (*active_tool != ActiveTool::Select) || (event.button != PointerButton::Primary) {
+        return;
+    }
+
+    let Some(net) = hover_query.get(viewport).ok().and_then(|hovered| hovered.0) else {
+        return;
+    };
+    let Ok((vertices, net_edges)) = nets.get(net) else {
+        return;
+    };
+    let vertices: &[Vertex] = vertices;
+
+    let Some((segment, point)) = closest_segment(vertices, event.pos) else {
+        return;
+    };
+    let mut point = point;
+    if grid_size.0 > fixed!(0) {
+        point = point.round_to_multiple(grid_size.0);
+    }
+
+    // the wire the segment is part of
+    let Some(wire_start) = vertices[..=segment]
+        .iter()
+        .rposition(|vertex| matches!(vertex.kind, VertexKind::WireStart { .. }))
+    else {
+        return;
+    };
+    let Some(wire_end) = vertices[(segment + 1)..]
+        .iter()
+        .position(|vertex| matches!(vertex.kind, VertexKind::WireEnd { .. }))
+        .map(|index| index + segment + 1)
+    else {
+        return;
+    };
+    let is_root = matches!(
+        vertices[wire_start].kind,
+        VertexKind::WireStart { is_root: true }
+    );
+
+    // wires start at an endpoint and pass its waypoints in order,
+    // the root wire also passes the waypoints of the endpoint it ends at in reverse
+    let endpoint_with_waypoints = |position: Vec2| {
+        let mut found = None;
+        net_edges
+            .join::<Child>(&endpoints)
+            .for_each(|((endpoint, transform), endpoint_edges)| {
+                if transform.translation == position {
+                    let mut list = Vec::new();
+                    endpoint_edges.join::<Child>(&waypoints).for_each(
+                        |(waypoint, &number, transform)| {
+                            list.push((waypoint, number, transform.translation));
+                        },
+                    );
+                    list.sort_unstable_by_key(|&(_, number, _)| number);
+                    found = Some((endpoint, list));
+                }
+            });
+        found
+    };
+
+    let Some((start_endpoint, start_waypoints)) =
+        endpoint_with_waypoints(vertices[wire_start].position)
+    else {
+        return;
+    };
+
+    let mut chain = Vec::new();
+    chain.push(vertices[wire_start].position);
+    chain.extend(start_waypoints.iter().map(|&(_, _, position)| position));
+
+    let (endpoint, waypoint_list, index) = if is_root {
+        let Some((end_endpoint, end_waypoints)) =
+            endpoint_with_waypoints(vertices[wire_end].position)
+        else {
+            return;
+        };
+
+        chain.extend(end_waypoints.iter().rev().map(|&(_, _, position)| position));
+        chain.push(vertices[wire_end].position);
+
+        let index = insertion_index(&chain, point, false);
+        if index <= start_waypoints.len() {
+            (start_endpoint, start_waypoints, index)
+        } else {
+            let index = start_waypoints.len() + end_waypoints.len() - index;
+            (end_endpoint, end_waypoints, index)
+        }
+    } else {
+        let index = insertion_index(&chain, point, true);
+        (start_endpoint, start_waypoints, index)
+    };
+
+    // make room for the new waypoint
+    for (list_index, &(waypoint, number, _)) in waypoint_list.iter().enumerate() {
+        let new_number = if list_index < index {
+            list_index
+        } else {
+            list_index + 1
+        };
+
+        if number.0 != (new_number as i32) {
+            commands.entity(waypoint).insert(Number(new_number as i32));
+        }
+    }
+
+    // waypoints are part of the routing graph, adding one reroutes the circuit
+    commands
+        .spawn(WaypointBundle {
+            waypoint: Waypoint,
+            number: Number(index as i32),
+            transform: TransformBundle {
+                transform: Transform {
+                    translation: point,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            visibility: VisibilityBundle::default(),
+            bounds: BoundingBoxBundle {
+                bounding_box: BoundingBox::from_half_size(WAYPOINT_HALF_SIZE, WAYPOINT_HALF_SIZE),
+                ..Default::default()
+            },
+        })
+        .set::<Child>(endpoint);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(x: i16, y: i16) -> Vec2 {
+        Vec2 {
+            x: x.into(),
+            y: y.into(),
+        }
+    }
+
+    #[test]
+    fn insert_into_chain() {
+        let chain = [point(0, 0), point(20, 0), point(20, 20)];
+        assert_eq!(insertion_index(&chain, point(10, 0), false), 0);
+        assert_eq!(insertion_index(&chain, point(20, 10), false), 1);
+        assert_eq!(insertion_index(&chain, point(20, 30), true), 2);
+    }
+}
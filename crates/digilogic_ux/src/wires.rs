@@ -0,0 +1,186 @@
+use crate::graphics::snap;
+use crate::nets::EndpointConnections;
+use crate::{ActiveTool, DragEvent, DragType, GridSize, HoverCandidates, PointerButton};
+use aery::prelude::*;
+use bevy_ecs::prelude::*;
+use digilogic_core::bundles::{EndpointBundle, NetBundle};
+use digilogic_core::components::*;
+use digilogic_core::connections::spawn_port_endpoint;
+use digilogic_core::transform::*;
+use digilogic_core::visibility::VisibilityBundle;
+use digilogic_routing::reroute_net;
+
+/// The wire a viewport is currently drawing.
+#[derive(Debug, Component)]
+pub(crate) struct DrawingWire {
+    /// The port the wire starts at.
+    port: Entity,
+    start: Vec2,
+    bit_width: BitWidth,
+    /// The wire, once the cursor left the port.
+    wire: Option<Wire>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Wire {
+    /// The net the wire is part of.
+    net: Entity,
+    /// The endpoint following the cursor.
+    endpoint: Entity,
+}
+
+/// What the end of a drawn wire was dropped onto.
+enum WireTarget {
+    Port(Entity, Vec2),
+    Net(Entity),
+}
+
+/// Dragging from a port with the wire tool draws a wire from the port to the cursor.
+/// Releasing it on another port connects the two, releasing it on another wire joins its net.
+/// If the nets on both ends already existed, they are merged.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn draw_wire_on_drag(
+    trigger: Trigger<DragEvent>,
+    mut commands: Commands,
+    active_tool: Res<ActiveTool>,
+    grid_size: Res<GridSize>,
+    mut viewports: Query<(&HoverCandidates, Option<&mut DrawingWire>)>,
+    ports: Query<(&GlobalTransform, &BitWidth), With<Port>>,
+    nets: Query<(), With<Net>>,
+    mut transforms: Query<&mut Transform, With<Endpoint>>,
+    mut endpoint_connections: EndpointConnections,
+) {
+    let event = trigger.event();
+    let viewport = trigger.entity();
+
+    if event.button != PointerButton::Primary {
+        return;
+    }
+
+    let Ok((candidates, drawing)) = viewports.get_mut(viewport) else {
+        return;
+    };
+
+    let position = snap(event.pos, grid_size.0);
+
+    let Some(mut drawing) = drawing else {
+        if (event.drag_type != DragType::Start) || (*active_tool != ActiveTool::DrawWire) {
+            return;
+        }
+
+        // ports are picked over everything else under the cursor
+        let Some(&port) = candidates.entities.first() else {
+            return;
+        };
+        if let Ok((transform, &bit_width)) = ports.get(port) {
+            commands.entity(viewport).insert(DrawingWire {
+                port,
+                start: transform.translation,
+                bit_width,
+                wire: None,
+            });
+        }
+        return;
+    };
+
+    // the end of the wire never rests on the port it starts at, that would leave nothing to route
+    if position != drawing.start {
+        match drawing.wire {
+            Some(wire) => {
+                if let Ok(mut transform) = transforms.get_mut(wire.endpoint) {
+                    if transform.translation != position {
+                        transform.translation = position;
+                    }
+                }
+            }
+            None => {
+                // a port only connects to a single endpoint, so a wired port extends its net
+                let net = endpoint_connections
+                    .endpoint_at_port(drawing.port)
+                    .and_then(|endpoint| endpoint_connections.net_of(endpoint))
+                    .unwrap_or_else(|| {
+                        let net = commands
+                            .spawn(NetBundle {
+                                net: Net,
+                                name: Name::default(),
+                                bit_width: drawing.bit_width,
+                                visibility: VisibilityBundle::default(),
+                            })
+                            .set::<Child>(event.circuit.0)
+                            .id();
+                        spawn_port_endpoint(&mut commands, net, drawing.port);
+                        net
+                    });
+
+                let endpoint = commands
+                    .spawn(EndpointBundle {
+                        transform: TransformBundle {
+                            transform: Transform {
+                                translation: position,
+                                ..Default::default()
+                            },
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    })
+                    .set::<Child>(net)
+                    .id();
+                reroute_net(&mut commands, event.circuit, net);
+
+                drawing.wire = Some(Wire { net, endpoint });
+            }
+        }
+    }
+
+    if event.drag_type != DragType::End {
+        return;
+    }
+    commands.entity(viewport).remove::<DrawingWire>();
+
+    // a click without dragging leaves nothing to see
+    let Some(wire) = drawing.wire else {
+        return;
+    };
+
+    // the wire being drawn is under the cursor as well
+    let target = candidates
+        .entities
+        .iter()
+        .filter(|&&entity| (entity != wire.endpoint) && (entity != wire.net))
+        .find_map(|&entity| {
+            if let Ok((transform, _)) = ports.get(entity) {
+                Some(WireTarget::Port(entity, transform.translation))
+            } else if nets.contains(entity) {
+                Some(WireTarget::Net(entity))
+            } else {
+                None
+            }
+        });
+
+    match target {
+        Some(WireTarget::Port(port, port_position)) if port != drawing.port => {
+            let port_net = endpoint_connections
+                .endpoint_at_port(port)
+                .and_then(|endpoint| endpoint_connections.net_of(endpoint));
+            if port_net == Some(wire.net) {
+                // both ports are already connected by the net
+                commands.entity(wire.endpoint).despawn();
+                reroute_net(&mut commands, event.circuit, wire.net);
+            } else {
+                endpoint_connections.connect_at(
+                    &mut commands,
+                    event.circuit,
+                    wire.endpoint,
+                    port_position,
+                );
+            }
+        }
+        Some(WireTarget::Net(net)) if net != wire.net => {
+            // the end of the wire stays where it was dropped, on the wire of the other net
+            endpoint_connections.merge(event.circuit, net, wire.net);
+        }
+        _ => {
+            // the wire ends in the open, where it can be picked up again later
+        }
+    }
+}